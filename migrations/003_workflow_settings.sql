@@ -0,0 +1,5 @@
+-- NEXUS Database Schema
+-- Migration 003: Workflow run settings shared between instances
+
+ALTER TABLE workflows ADD COLUMN IF NOT EXISTS concurrency JSONB;
+ALTER TABLE workflows ADD COLUMN IF NOT EXISTS model_pins JSONB;
@@ -0,0 +1,89 @@
+//! Scheduling cost for large adaptive plans: level computation, the
+//! critical path, validating a replan and simulating conditions.
+//!
+//! Run with `cargo bench --bench scheduling`.
+
+use std::collections::HashMap;
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use nexus_lib::workflow::adaptive::{validate_modifications, PlanModification};
+use nexus_lib::workflow::orchestrator::{plan_to_graph, OrchestratorPlan, PlannedTask};
+use nexus_lib::workflow::simulation::{simulate, Scenario};
+use nexus_lib::workflow::ExecutionCondition;
+
+const WIDTH: usize = 10;
+
+fn task(id: String, depends_on: Vec<String>) -> PlannedTask {
+    PlannedTask {
+        name: id.clone(),
+        id,
+        agent_role: "implementer".to_string(),
+        description: "Implement part of the feature".to_string(),
+        depends_on,
+        system_prompt: None,
+    }
+}
+
+/// `size` tasks in levels of ten, each depending on up to three tasks of
+/// the level before
+fn plan(size: usize) -> OrchestratorPlan {
+    let tasks = (0..size)
+        .map(|i| {
+            let (level, slot) = (i / WIDTH, i % WIDTH);
+            let depends_on = match level {
+                0 => Vec::new(),
+                _ => (0..3)
+                    .map(|k| format!("task-{}", (level - 1) * WIDTH + (slot + k) % WIDTH))
+                    .collect(),
+            };
+            task(format!("task-{}", i), depends_on)
+        })
+        .collect();
+
+    OrchestratorPlan {
+        project_summary: "Benchmark plan".to_string(),
+        tasks,
+    }
+}
+
+fn bench_scheduling(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scheduling");
+
+    for size in [100, 500, 2000] {
+        let plan = plan(size);
+        let graph = plan_to_graph(&plan);
+        let levels = graph.compute_execution_levels().unwrap();
+
+        group.bench_with_input(BenchmarkId::new("execution_levels", size), &graph, |b, graph| {
+            b.iter(|| graph.compute_execution_levels().unwrap())
+        });
+
+        group.bench_with_input(BenchmarkId::new("critical_path", size), &graph, |b, graph| {
+            b.iter(|| graph.critical_path_in_levels(black_box(&levels), |_| 1))
+        });
+
+        let replan = vec![PlanModification::InsertTaskBetween {
+            task: task("review".to_string(), Vec::new()),
+            after_task_id: "task-0".to_string(),
+            before_task_ids: vec![format!("task-{}", WIDTH)],
+            reason: "Review before building on it".to_string(),
+        }];
+        group.bench_with_input(BenchmarkId::new("validate_modifications", size), &plan, |b, plan| {
+            b.iter(|| validate_modifications(&plan.tasks, black_box(&replan)).unwrap())
+        });
+
+        let conditions: HashMap<String, ExecutionCondition> = plan
+            .tasks
+            .iter()
+            .map(|t| (t.id.clone(), ExecutionCondition::AllPredecessorsSucceeded))
+            .collect();
+        group.bench_with_input(BenchmarkId::new("simulate_conditions", size), &graph, |b, graph| {
+            b.iter(|| simulate(graph, &conditions, &HashMap::new(), &Scenario::default()).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, bench_scheduling);
+criterion_main!(benches);
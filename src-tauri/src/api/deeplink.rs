@@ -0,0 +1,193 @@
+//! `nexus://` deep links.
+//!
+//! Links opened by the OS (from a shortcut, a browser or another app) reach
+//! the running instance, which parses and acts on them. Supported links:
+//! - `nexus://execute?workflow=<id>&project=<id>[&prompt=<text>]` starts a
+//!   saved workflow
+//! - `nexus://run-preset/<id>` runs a saved workflow with a run preset's
+//!   arguments
+//! - `nexus://open/<path>` asks the UI to show a view, e.g.
+//!   `nexus://open/executions/<id>`
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+use uuid::Uuid;
+
+use crate::commands::workflow::{start_preset, start_workflow};
+
+pub const SCHEME: &str = "nexus";
+
+/// Event emitted for every handled link, for the UI
+pub const DEEP_LINK_EVENT: &str = "deep-link";
+
+/// What a deep link asks for
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum DeepLink {
+    Execute {
+        workflow_id: Uuid,
+        project_id: Uuid,
+        prompt: String,
+    },
+    RunPreset {
+        preset_id: Uuid,
+    },
+    Open {
+        path: String,
+    },
+}
+
+/// A handled link and its outcome
+#[derive(Debug, Clone, Serialize)]
+pub struct DeepLinkResult {
+    pub url: String,
+    pub link: Option<DeepLink>,
+    pub execution_id: Option<String>,
+    pub error: Option<String>,
+}
+
+/// Parse a `nexus://` URL
+pub fn parse(url: &str) -> Result<DeepLink, String> {
+    let rest = url
+        .strip_prefix(SCHEME)
+        .and_then(|rest| rest.strip_prefix("://"))
+        .ok_or_else(|| format!("Not a {}:// link", SCHEME))?;
+    let (path, query) = rest.split_once('?').unwrap_or((rest, ""));
+    let path = path.trim_matches('/');
+
+    let param = |name: &str| {
+        query
+            .split('&')
+            .map(|pair| pair.split_once('=').unwrap_or((pair, "")))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| percent_decode(value))
+    };
+    let id_param = |name: &str| -> Result<Uuid, String> {
+        let value = param(name).ok_or_else(|| format!("Missing '{}' parameter", name))?;
+        Uuid::parse_str(&value).map_err(|_| format!("Invalid {} ID: {}", name, value))
+    };
+
+    let (action, target) = path.split_once('/').unwrap_or((path, ""));
+    match action {
+        "execute" => Ok(DeepLink::Execute {
+            workflow_id: id_param("workflow")?,
+            project_id: id_param("project")?,
+            prompt: param("prompt").unwrap_or_default(),
+        }),
+        "run-preset" => Ok(DeepLink::RunPreset {
+            preset_id: Uuid::parse_str(target).map_err(|_| format!("Invalid preset ID: {}", target))?,
+        }),
+        "open" => Ok(DeepLink::Open {
+            path: target.to_string(),
+        }),
+        other => Err(format!("Unknown deep link action: '{}'", other)),
+    }
+}
+
+/// Decode `%XX` escapes and `+` (as space) in a query value
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => decoded.push(b' '),
+            b'%' => {
+                let hex = value.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                match hex {
+                    Some(byte) => {
+                        decoded.push(byte);
+                        i += 2;
+                    }
+                    None => decoded.push(b'%'),
+                }
+            }
+            byte => decoded.push(byte),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Act on a link and tell the UI what happened
+pub fn handle(app: &AppHandle, url: &str) {
+    log::info!("Handling deep link: {}", url);
+    let mut result = DeepLinkResult {
+        url: url.to_string(),
+        link: None,
+        execution_id: None,
+        error: None,
+    };
+
+    match parse(url) {
+        Ok(link) => {
+            let started = match &link {
+                DeepLink::Execute { workflow_id, project_id, prompt } => {
+                    Some(start_workflow(app, &workflow_id.to_string(), &project_id.to_string(), prompt.clone()))
+                }
+                DeepLink::RunPreset { preset_id } => Some(start_preset(app, preset_id)),
+                DeepLink::Open { .. } => None,
+            };
+            match started {
+                Some(Ok(execution_id)) => result.execution_id = Some(execution_id.to_string()),
+                Some(Err(e)) => result.error = Some(e),
+                None => {}
+            }
+            result.link = Some(link);
+        }
+        Err(e) => result.error = Some(e),
+    }
+
+    if let Some(ref error) = result.error {
+        log::warn!("Deep link {} failed: {}", url, error);
+    }
+    let _ = app.emit(DEEP_LINK_EVENT, &result);
+}
+
+/// Bring the main window to the front
+pub fn focus_main_window(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window("main") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_deep_links() {
+        let workflow = Uuid::new_v4();
+        let project = Uuid::new_v4();
+
+        let url = format!("nexus://execute?workflow={}&project={}&prompt=Fix+the%20build%21", workflow, project);
+        assert_eq!(
+            parse(&url).unwrap(),
+            DeepLink::Execute {
+                workflow_id: workflow,
+                project_id: project,
+                prompt: "Fix the build!".to_string(),
+            }
+        );
+
+        assert_eq!(
+            parse("nexus://open/executions/123/").unwrap(),
+            DeepLink::Open { path: "executions/123".to_string() }
+        );
+
+        assert_eq!(
+            parse(&format!("nexus://run-preset/{}", workflow)).unwrap(),
+            DeepLink::RunPreset { preset_id: workflow }
+        );
+        assert!(parse("nexus://run-preset/weekly").is_err());
+
+        assert!(parse(&format!("nexus://execute?workflow={}", workflow)).unwrap_err().contains("project"));
+        assert!(parse("nexus://execute?workflow=nope&project=x").unwrap_err().contains("Invalid workflow ID"));
+        assert!(parse("nexus://delete").is_err());
+        assert!(parse("https://example.com").is_err());
+    }
+}
@@ -0,0 +1,614 @@
+//! GraphQL queries over executions, history, agents and projects.
+//!
+//! Dashboards usually want nested data (an execution, its nodes, each
+//! node's attempts and agent) which takes a REST call per level. Provides:
+//! - `POST /api/graphql` taking `{ "query", "variables", "operationName" }`
+//!   and answering `{ "data", "errors" }`, run by async-graphql, so
+//!   fragments, directives and introspection work as in any GraphQL server
+//!   and GraphiQL or Apollo can browse the schema
+//! - Root fields `executions`, `execution(id)`, `agents`, `agent(id)`,
+//!   `projects`, `project(id)`, `history` and `historyRecord(id)`
+//! - Links between them: `Execution.nodes/agents/project/history/events`,
+//!   `Node.agent/attempts/retries/outputPreview/activity`,
+//!   `Agent.execution/project`, `Project.executions/agents/history` and
+//!   `HistoryRecord.execution/project`
+//!
+//! Agents, projects, nodes and history records are the app's own types,
+//! which derive their GraphQL types with the `graphql` feature. Nested
+//! data without a GraphQL type, such as a node's attempts or a record's
+//! metrics, is a `JSON` scalar. Only queries are supported.
+
+use async_graphql::{
+    ComplexObject, Context, EmptyMutation, EmptySubscription, Json as JsonScalar, Object, Schema, SimpleObject,
+    Variables,
+};
+use axum::{extract::State, response::Json};
+use chrono::{DateTime, Utc};
+use once_cell::sync::OnceCell;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+use super::routes::ApiState;
+use crate::commands::project::{all_projects, Project};
+use crate::commands::workflow::{agent_executions, all_executions, find_execution, get_history_store};
+use crate::process::manager::AgentInfo;
+use crate::process::query::AgentFilter;
+use crate::state::AppState;
+use crate::workflow::state::NodeExecutionState;
+use crate::workflow::text;
+use crate::workflow::{
+    ExecutionRecord, ExecutionStatus, NodeExecutionStatus, NodeTranscript, WorkflowExecutionState, ACTIVITY_STORE,
+    EVENT_JOURNAL,
+};
+
+pub const PATH: &str = "/api/graphql";
+
+/// Characters of output in `Node.outputPreview` unless `length` is given
+const DEFAULT_PREVIEW_LENGTH: usize = 200;
+
+pub type NexusSchema = Schema<Query, EmptyMutation, EmptySubscription>;
+
+lazy_static::lazy_static! {
+    static ref SCHEMA: NexusSchema = Schema::new(Query, EmptyMutation, EmptySubscription);
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct GraphQLRequest {
+    pub query: String,
+    #[serde(default)]
+    pub variables: Option<Map<String, Value>>,
+    /// Operation to run when the query defines several
+    #[serde(default, rename = "operationName")]
+    pub operation_name: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct GraphQLResponse {
+    pub data: Option<Value>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<Value>,
+}
+
+/// What the resolvers of one request read
+struct RequestData {
+    app: Arc<AppState>,
+    /// Agent ID -> execution that spawned it, built on first use
+    agent_executions: OnceCell<HashMap<Uuid, Uuid>>,
+}
+
+impl RequestData {
+    fn execution_of_agent(&self, agent_id: &Uuid) -> Option<Uuid> {
+        self.agent_executions.get_or_init(agent_executions).get(agent_id).copied()
+    }
+
+    fn agent(&self, id: &Uuid) -> Option<AgentInfo> {
+        self.app.agents.get(id).map(|entry| entry.value().clone())
+    }
+
+    fn agents_newest_first(&self) -> Vec<AgentInfo> {
+        let mut agents: Vec<AgentInfo> = self.app.agents.iter().map(|entry| entry.value().clone()).collect();
+        agents.sort_by_key(|agent| std::cmp::Reverse(agent.started_at));
+        agents
+    }
+}
+
+fn request_data<'a>(ctx: &Context<'a>) -> &'a RequestData {
+    ctx.data_unchecked::<RequestData>()
+}
+
+pub struct Query;
+
+#[Object]
+impl Query {
+    /// Executions, newest first
+    async fn executions(
+        &self,
+        status: Option<ExecutionStatus>,
+        project_id: Option<Uuid>,
+        workflow_id: Option<Uuid>,
+        limit: Option<usize>,
+    ) -> Vec<Execution> {
+        executions_newest_first()
+            .into_iter()
+            .filter(|state| status.map_or(true, |status| state.get_status() == status))
+            .filter(|state| project_id.map_or(true, |id| state.project_id == id))
+            .filter(|state| workflow_id.map_or(true, |id| state.workflow_id == id))
+            .take(limit.unwrap_or(usize::MAX))
+            .map(Execution)
+            .collect()
+    }
+
+    async fn execution(&self, id: Uuid) -> Option<Execution> {
+        find_execution(&id).map(|(state, _)| Execution(state))
+    }
+
+    /// Agents, newest first
+    async fn agents(&self, ctx: &Context<'_>, filter: Option<AgentFilter>, limit: Option<usize>) -> Vec<AgentInfo> {
+        let data = request_data(ctx);
+        let filter = filter.unwrap_or_default();
+        let now = Utc::now();
+        data.agents_newest_first()
+            .into_iter()
+            .filter(|agent| filter.matches(agent, data.execution_of_agent(&agent.id), now))
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    async fn agent(&self, ctx: &Context<'_>, id: Uuid) -> Option<AgentInfo> {
+        request_data(ctx).agent(&id)
+    }
+
+    /// Projects by name
+    async fn projects(&self) -> Vec<Project> {
+        let mut projects = all_projects();
+        projects.sort_by(|a, b| a.name.cmp(&b.name));
+        projects
+    }
+
+    async fn project(&self, id: Uuid) -> Option<Project> {
+        find_project(&id)
+    }
+
+    /// Finished executions, newest first; `search` matches as the history
+    /// view's search does
+    async fn history(
+        &self,
+        project_id: Option<Uuid>,
+        search: Option<String>,
+        status: Option<ExecutionStatus>,
+        limit: Option<usize>,
+    ) -> Vec<ExecutionRecord> {
+        let store = get_history_store();
+        let records = match (search, project_id) {
+            (Some(query), project_id) => store
+                .search(&query)
+                .into_iter()
+                .filter(|r| project_id.map_or(true, |id| r.project_id == id))
+                .collect(),
+            (None, Some(project_id)) => store.list_for_project(&project_id),
+            (None, None) => store.list(),
+        };
+        records
+            .into_iter()
+            .filter(|r| status.map_or(true, |status| r.status == status))
+            .take(limit.unwrap_or(usize::MAX))
+            .collect()
+    }
+
+    async fn history_record(&self, id: Uuid) -> Option<ExecutionRecord> {
+        get_history_store().get(&id)
+    }
+}
+
+/// A running or finished workflow execution
+pub struct Execution(Arc<WorkflowExecutionState>);
+
+#[Object]
+impl Execution {
+    async fn id(&self) -> Uuid {
+        self.0.execution_id
+    }
+
+    async fn workflow_id(&self) -> Uuid {
+        self.0.workflow_id
+    }
+
+    async fn project_id(&self) -> Uuid {
+        self.0.project_id
+    }
+
+    async fn status(&self) -> ExecutionStatus {
+        self.0.get_status()
+    }
+
+    async fn input_prompt(&self) -> &str {
+        &self.0.input_prompt
+    }
+
+    async fn started_at(&self) -> DateTime<Utc> {
+        self.0.started_at
+    }
+
+    async fn completed_at(&self) -> Option<DateTime<Utc>> {
+        *self.0.completed_at.read()
+    }
+
+    async fn cancel_reason(&self) -> Option<String> {
+        self.0.get_cancel_reason()
+    }
+
+    /// Overall progress, 0-100
+    async fn progress(&self) -> u8 {
+        self.0.get_overall_progress()
+    }
+
+    async fn total_nodes(&self) -> usize {
+        self.0.total_nodes()
+    }
+
+    async fn completed_nodes(&self) -> usize {
+        self.0.completed_nodes()
+    }
+
+    /// Nodes in execution order, then any outside the levels
+    async fn nodes(&self, status: Option<NodeExecutionStatus>) -> Vec<Node> {
+        let state = &self.0;
+        let mut order: Vec<String> = state.execution_levels.iter().flatten().cloned().collect();
+        let mut rest: Vec<String> = state
+            .node_states
+            .iter()
+            .map(|node| node.key().clone())
+            .filter(|id| !order.contains(id))
+            .collect();
+        rest.sort();
+        order.extend(rest);
+
+        order
+            .iter()
+            .filter_map(|id| state.get_node_state(id))
+            .filter(|node| status.map_or(true, |status| node.status == status))
+            .map(|node| Node {
+                state: node,
+                execution: state.clone(),
+            })
+            .collect()
+    }
+
+    /// Agents the execution's nodes spawned
+    async fn agents(&self, ctx: &Context<'_>) -> Vec<AgentInfo> {
+        let data = request_data(ctx);
+        let ids: Vec<Uuid> = self.0.node_states.iter().filter_map(|node| node.agent_id).collect();
+        ids.iter().filter_map(|id| data.agent(id)).collect()
+    }
+
+    async fn project(&self) -> Option<Project> {
+        find_project(&self.0.project_id)
+    }
+
+    /// The execution's record, once it has finished
+    async fn history(&self) -> Option<ExecutionRecord> {
+        get_history_store().get(&self.0.execution_id)
+    }
+
+    /// Journaled events after sequence number `since`
+    async fn events(&self, #[graphql(default)] since: u64) -> JsonScalar<Value> {
+        let events = EVENT_JOURNAL
+            .since(&self.0.execution_id, since)
+            .map(|found| found.events)
+            .unwrap_or_default();
+        json(&events)
+    }
+}
+
+/// A node of an execution
+#[derive(SimpleObject)]
+#[graphql(complex)]
+pub struct Node {
+    #[graphql(flatten)]
+    state: NodeExecutionState,
+    #[graphql(skip)]
+    execution: Arc<WorkflowExecutionState>,
+}
+
+#[ComplexObject]
+impl Node {
+    async fn agent(&self, ctx: &Context<'_>) -> Option<AgentInfo> {
+        self.state.agent_id.and_then(|id| request_data(ctx).agent(&id))
+    }
+
+    /// Transcript of each attempt at the node
+    async fn attempts(&self) -> JsonScalar<Value> {
+        json(&node_attempts(&self.execution, &self.state.node_id))
+    }
+
+    /// How often the node was retried
+    async fn retries(&self) -> usize {
+        node_attempts(&self.execution, &self.state.node_id).len().saturating_sub(1)
+    }
+
+    /// Each failed attempt: the ones retried, then the final failure
+    async fn retry_attempts(&self) -> JsonScalar<Value> {
+        json(&self.state.retry_attempts)
+    }
+
+    /// The start of the node's output
+    async fn output_preview(&self, #[graphql(default_with = "DEFAULT_PREVIEW_LENGTH")] length: usize) -> Option<String> {
+        self.state.output.as_deref().map(|output| preview(output, length))
+    }
+
+    async fn activity(&self) -> JsonScalar<Value> {
+        json(&ACTIVITY_STORE.get(self.execution.execution_id, &self.state.node_id))
+    }
+}
+
+#[ComplexObject]
+impl AgentInfo {
+    /// The execution whose node spawned the agent, if any
+    async fn execution(&self, ctx: &Context<'_>) -> Option<Execution> {
+        request_data(ctx)
+            .execution_of_agent(&self.id)
+            .and_then(|id| find_execution(&id))
+            .map(|(state, _)| Execution(state))
+    }
+
+    async fn project(&self) -> Option<Project> {
+        self.project_id.and_then(|id| find_project(&id))
+    }
+}
+
+#[ComplexObject]
+impl Project {
+    /// The project's executions, newest first
+    async fn executions(&self) -> Vec<Execution> {
+        executions_newest_first()
+            .into_iter()
+            .filter(|state| state.project_id == self.id)
+            .map(Execution)
+            .collect()
+    }
+
+    /// The project's agents, newest first
+    async fn agents(&self, ctx: &Context<'_>) -> Vec<AgentInfo> {
+        request_data(ctx)
+            .agents_newest_first()
+            .into_iter()
+            .filter(|agent| agent.project_id == Some(self.id))
+            .collect()
+    }
+
+    async fn history(&self) -> Vec<ExecutionRecord> {
+        get_history_store().list_for_project(&self.id)
+    }
+}
+
+#[ComplexObject]
+impl ExecutionRecord {
+    /// The execution, while it is still in memory
+    async fn execution(&self) -> Option<Execution> {
+        find_execution(&self.id).map(|(state, _)| Execution(state))
+    }
+
+    async fn project(&self) -> Option<Project> {
+        find_project(&self.project_id)
+    }
+
+    async fn node_records(&self) -> JsonScalar<Value> {
+        json(&self.node_records)
+    }
+
+    /// Agent outputs by node
+    async fn outputs(&self) -> JsonScalar<Value> {
+        json(&self.outputs)
+    }
+
+    async fn timeline(&self) -> JsonScalar<Value> {
+        json(&self.timeline)
+    }
+
+    async fn metrics(&self) -> JsonScalar<Value> {
+        json(&self.metrics)
+    }
+
+    async fn anomalies(&self) -> JsonScalar<Value> {
+        json(&self.anomalies)
+    }
+
+    async fn environment(&self) -> JsonScalar<Value> {
+        json(&self.environment)
+    }
+
+    async fn model_substitutions(&self) -> JsonScalar<Value> {
+        json(&self.model_substitutions)
+    }
+
+    async fn annotations(&self) -> JsonScalar<Value> {
+        json(&self.annotations)
+    }
+
+    async fn ratings(&self) -> JsonScalar<Value> {
+        json(&self.ratings)
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> JsonScalar<Value> {
+    JsonScalar(serde_json::to_value(value).unwrap_or(Value::Null))
+}
+
+fn preview(output: &str, length: usize) -> String {
+    text::truncate(output, length, "…")
+}
+
+fn node_attempts(execution: &WorkflowExecutionState, node_id: &str) -> Vec<NodeTranscript> {
+    find_execution(&execution.execution_id)
+        .and_then(|(_, context)| context)
+        .map(|context| {
+            context
+                .get_transcripts()
+                .into_iter()
+                .filter(|t| t.node_id == node_id)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn executions_newest_first() -> Vec<Arc<WorkflowExecutionState>> {
+    let mut executions = all_executions();
+    executions.sort_by_key(|state| std::cmp::Reverse(state.started_at));
+    executions
+}
+
+fn find_project(id: &Uuid) -> Option<Project> {
+    all_projects().into_iter().find(|p| p.id == *id)
+}
+
+/// The schema in GraphQL SDL
+pub fn sdl() -> String {
+    SCHEMA.sdl()
+}
+
+/// Run a query against the app's current state
+pub async fn execute_query(app: Arc<AppState>, request: GraphQLRequest) -> GraphQLResponse {
+    let variables = Variables::from_json(Value::Object(request.variables.unwrap_or_default()));
+    let mut query = async_graphql::Request::new(request.query).variables(variables);
+    if let Some(operation_name) = request.operation_name {
+        query = query.operation_name(operation_name);
+    }
+    let response = SCHEMA
+        .execute(query.data(RequestData {
+            app,
+            agent_executions: OnceCell::new(),
+        }))
+        .await;
+
+    // Requests that fail before execution have no data
+    let data = response.data.into_json().ok().filter(|data| !data.is_null());
+    GraphQLResponse {
+        data,
+        errors: response
+            .errors
+            .iter()
+            .map(|error| serde_json::to_value(error).unwrap_or(Value::Null))
+            .collect(),
+    }
+}
+
+/// POST /api/graphql - Run a GraphQL query
+pub async fn execute(State(state): State<ApiState>, Json(request): Json<GraphQLRequest>) -> Json<GraphQLResponse> {
+    Json(execute_query(state.app_state.clone(), request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::manager::{AgentConfig, AgentStatus};
+    use serde_json::json;
+
+    async fn query(app: &Arc<AppState>, query: &str, variables: Value) -> GraphQLResponse {
+        execute_query(
+            app.clone(),
+            GraphQLRequest {
+                query: query.to_string(),
+                variables: variables.as_object().cloned(),
+                operation_name: None,
+            },
+        )
+        .await
+    }
+
+    fn message(response: &GraphQLResponse) -> &str {
+        response.errors[0]["message"].as_str().unwrap()
+    }
+
+    #[test]
+    fn test_schema() {
+        let sdl = sdl();
+        for expected in [
+            "type Query",
+            "agents(filter: AgentFilter, limit: Int): [AgentInfo!]!",
+            "enum AgentStatus",
+            "type Node",
+            "outputPreview(length: Int! = 200): String",
+            "type ExecutionRecord",
+        ] {
+            assert!(sdl.contains(expected), "{} is missing from\n{}", expected, sdl);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_operation_name_and_variables() {
+        let app = Arc::new(AppState::new());
+        let document = "query First($limit: Int = 0) { agents(limit: $limit) { id } } query Second { projects { id } }";
+        let run = |operation_name: Option<&str>, variables: Value| {
+            execute_query(
+                app.clone(),
+                GraphQLRequest {
+                    query: document.to_string(),
+                    variables: variables.as_object().cloned(),
+                    operation_name: operation_name.map(str::to_string),
+                },
+            )
+        };
+
+        let response = run(None, Value::Null).await;
+        assert!(response.data.is_none());
+        assert!(message(&response).contains("Operation name required"), "{:?}", response.errors);
+        assert!(message(&run(Some("Third"), Value::Null).await).contains("Unknown operation"));
+
+        let response = run(Some("First"), json!({ "limit": 1 })).await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(response.data.unwrap(), json!({ "agents": [] }));
+        let response = run(Some("Second"), Value::Null).await;
+        assert!(response.data.unwrap()["projects"].is_array());
+
+        let response = query(&app, "mutation { kill }", Value::Null).await;
+        assert!(response.data.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_query_agents() {
+        let app = Arc::new(AppState::new());
+        let config = AgentConfig {
+            name: "reviewer".to_string(),
+            role: "tester".to_string(),
+            working_directory: "/tmp".to_string(),
+            project_id: None,
+            system_prompt: None,
+            assigned_task: Some("review the diff".to_string()),
+            model: None,
+            env: Vec::new(),
+            owner: None,
+        };
+        let mut running = AgentInfo::new(Uuid::new_v4(), &config);
+        running.status = AgentStatus::Running;
+        let mut finished = AgentInfo::new(Uuid::new_v4(), &config);
+        finished.status = AgentStatus::Completed;
+        app.agents.insert(running.id, running.clone());
+        app.agents.insert(finished.id, finished);
+
+        let response = query(
+            &app,
+            r#"
+            query($filter: AgentFilter) {
+                live: agents(filter: $filter) { __typename ...Fields project { name } }
+            }
+            fragment Fields on AgentInfo { id status task: assignedTask pid @skip(if: true) }
+            "#,
+            json!({ "filter": { "statuses": ["running"] } }),
+        )
+        .await;
+        assert!(response.errors.is_empty(), "{:?}", response.errors);
+        assert_eq!(
+            response.data.unwrap(),
+            json!({ "live": [{
+                "__typename": "AgentInfo",
+                "id": running.id,
+                "status": "RUNNING",
+                "task": "review the diff",
+                "project": null,
+            }] })
+        );
+
+        // Unknown fields fail validation, pointing at the field
+        let response = query(&app, &format!("{{ agent(id: \"{}\") {{ name unknown }} }}", running.id), Value::Null).await;
+        assert!(response.data.is_none());
+        assert!(message(&response).contains("Unknown field \"unknown\""), "{:?}", response.errors);
+        assert!(response.errors[0]["locations"].is_array());
+
+        let response = query(&app, "{ agents }", Value::Null).await;
+        assert!(message(&response).contains("must have a selection of subfields"), "{:?}", response.errors);
+
+        let response = query(&app, "{ __type(name: \"Node\") { fields { name } } }", Value::Null).await;
+        let fields = response.data.unwrap()["__type"]["fields"].clone();
+        assert!(fields.as_array().unwrap().contains(&json!({ "name": "outputPreview" })));
+    }
+
+    #[test]
+    fn test_preview() {
+        assert_eq!(preview("short", 10), "short");
+        assert_eq!(preview("héllo wörld", 5), "héllo…");
+    }
+}
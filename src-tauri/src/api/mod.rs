@@ -1,3 +1,7 @@
+pub mod deeplink;
+#[cfg(feature = "graphql")]
+pub mod graphql;
+pub mod openapi;
 pub mod server;
 pub mod routes;
 pub mod templates;
@@ -0,0 +1,406 @@
+//! OpenAPI description of the HTTP API.
+//!
+//! Stream Deck plugins and scripts are written against the HTTP API, so
+//! they need docs that can't drift from the code. Provides:
+//! - The API's operations, each with the handler the router mounts, so a
+//!   route can't be served without being documented
+//! - An OpenAPI 3 spec whose request and response schemas are generated
+//!   from the Rust types the handlers use
+//! - `/openapi.json` serving the spec and `/docs` serving Swagger UI on
+//!   it. Swagger UI's assets are built into the binary, so the page needs
+//!   nothing from the network.
+
+use axum::extract::Path;
+use axum::handler::Handler;
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Json, Redirect, Response};
+use axum::routing::{self, MethodRouter};
+use schemars::gen::{SchemaGenerator, SchemaSettings};
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+use utoipa_swagger_ui::{Config, SwaggerFile};
+
+use super::routes::{
+    self, AgentResponse, ApiResponse, ApiState, PresetRunResponse, QuickActionRequest, RunPresetResponse,
+    SpawnAgentRequest, SpawnFromTemplateRequest, SystemStatus, API_VERSION,
+};
+use super::templates::{AgentTemplate, QuickAction};
+
+pub const SPEC_PATH: &str = "/openapi.json";
+pub const DOCS_PATH: &str = "/docs";
+pub const DOCS_INDEX_PATH: &str = "/docs/";
+/// Swagger UI's assets, under the docs
+pub const DOCS_FILES_PATH: &str = "/docs/*file";
+
+/// Schema of a body, generated when the spec is
+type SchemaFn = fn(&mut SchemaGenerator) -> Schema;
+
+/// Body of a request or a successful response
+enum Body {
+    Json(SchemaFn),
+    /// Server-Sent Events, described in prose
+    EventStream(&'static str),
+}
+
+/// A method and the handler serving it
+pub(super) struct Endpoint {
+    method: &'static str,
+    pub(super) handler: MethodRouter<ApiState>,
+}
+
+fn get<H: Handler<T, ApiState>, T: 'static>(handler: H) -> Endpoint {
+    Endpoint { method: "get", handler: routing::get(handler) }
+}
+
+fn post<H: Handler<T, ApiState>, T: 'static>(handler: H) -> Endpoint {
+    Endpoint { method: "post", handler: routing::post(handler) }
+}
+
+fn delete<H: Handler<T, ApiState>, T: 'static>(handler: H) -> Endpoint {
+    Endpoint { method: "delete", handler: routing::delete(handler) }
+}
+
+/// One method on one path, with the path in axum syntax (`/agents/:id`)
+pub(super) struct Operation {
+    pub(super) endpoint: Endpoint,
+    pub(super) path: &'static str,
+    tag: &'static str,
+    summary: &'static str,
+    request: Option<SchemaFn>,
+    response: Body,
+    /// Statuses besides 200 and what they mean
+    other_statuses: &'static [(u16, &'static str)],
+}
+
+fn schema<T: JsonSchema>(gen: &mut SchemaGenerator) -> Schema {
+    gen.subschema_for::<T>()
+}
+
+/// Every operation of the API; `create_router` mounts exactly these
+pub(super) fn operations() -> Vec<Operation> {
+    const INVALID_ID: (u16, &str) = (400, "The ID isn't a valid UUID");
+
+    #[allow(unused_mut)]
+    let mut operations = vec![
+        Operation {
+            endpoint: get(routes::health_check),
+            path: "/api/health",
+            tag: "status",
+            summary: "Health check",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<String>>),
+            other_statuses: &[],
+        },
+        Operation {
+            endpoint: get(routes::get_status),
+            path: "/api/status",
+            tag: "status",
+            summary: "System status",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<SystemStatus>>),
+            other_statuses: &[],
+        },
+        Operation {
+            endpoint: get(routes::list_agents),
+            path: "/api/agents",
+            tag: "agents",
+            summary: "List all agents",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<Vec<AgentResponse>>>),
+            other_statuses: &[],
+        },
+        Operation {
+            endpoint: delete(routes::kill_all_agents),
+            path: "/api/agents",
+            tag: "agents",
+            summary: "Kill all agents, returning how many were killed",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<usize>>),
+            other_statuses: &[],
+        },
+        Operation {
+            endpoint: post(routes::spawn_agent),
+            path: "/api/agents/spawn",
+            tag: "agents",
+            summary: "Spawn a new agent",
+            request: Some(schema::<SpawnAgentRequest>),
+            response: Body::Json(schema::<ApiResponse<AgentResponse>>),
+            other_statuses: &[],
+        },
+        Operation {
+            endpoint: post(routes::spawn_from_template),
+            path: "/api/agents/spawn/:template",
+            tag: "agents",
+            summary: "Spawn an agent from a template",
+            request: Some(schema::<SpawnFromTemplateRequest>),
+            response: Body::Json(schema::<ApiResponse<AgentResponse>>),
+            other_statuses: &[(404, "No template with this ID")],
+        },
+        Operation {
+            endpoint: get(routes::get_agent),
+            path: "/api/agents/:id",
+            tag: "agents",
+            summary: "Get an agent by ID",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<AgentResponse>>),
+            other_statuses: &[INVALID_ID, (404, "No agent with this ID")],
+        },
+        Operation {
+            endpoint: delete(routes::kill_agent),
+            path: "/api/agents/:id",
+            tag: "agents",
+            summary: "Kill an agent",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<String>>),
+            other_statuses: &[INVALID_ID, (404, "No agent with this ID")],
+        },
+        Operation {
+            endpoint: get(routes::list_templates),
+            path: "/api/templates",
+            tag: "templates",
+            summary: "List all agent templates",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<Vec<AgentTemplate>>>),
+            other_statuses: &[],
+        },
+        Operation {
+            endpoint: get(routes::get_template),
+            path: "/api/templates/:id",
+            tag: "templates",
+            summary: "Get a template by ID",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<AgentTemplate>>),
+            other_statuses: &[(404, "No template with this ID")],
+        },
+        Operation {
+            endpoint: get(routes::list_quick_actions),
+            path: "/api/quick-actions",
+            tag: "quick actions",
+            summary: "List all quick actions",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<Vec<QuickAction>>>),
+            other_statuses: &[],
+        },
+        Operation {
+            endpoint: get(routes::get_quick_action),
+            path: "/api/quick-actions/:id",
+            tag: "quick actions",
+            summary: "Get a quick action by ID",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<QuickAction>>),
+            other_statuses: &[(404, "No quick action with this ID")],
+        },
+        Operation {
+            endpoint: post(routes::execute_quick_action),
+            path: "/api/quick-actions/:id/execute",
+            tag: "quick actions",
+            summary: "Execute a quick action",
+            request: Some(schema::<QuickActionRequest>),
+            response: Body::Json(schema::<ApiResponse<AgentResponse>>),
+            other_statuses: &[
+                (404, "No quick action with this ID"),
+                (500, "The quick action's template is missing"),
+            ],
+        },
+        Operation {
+            endpoint: get(routes::list_run_presets),
+            path: "/api/presets",
+            tag: "presets",
+            summary: "List workflow run presets",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<Vec<RunPresetResponse>>>),
+            other_statuses: &[],
+        },
+        Operation {
+            endpoint: post(routes::run_preset),
+            path: "/api/presets/:id/run",
+            tag: "presets",
+            summary: "Run a saved workflow with a preset's project, prompt, variables and node configs",
+            request: None,
+            response: Body::Json(schema::<ApiResponse<PresetRunResponse>>),
+            other_statuses: &[INVALID_ID, (404, "No preset with this ID")],
+        },
+        Operation {
+            endpoint: get(routes::stream_execution_events),
+            path: "/api/executions/:id/events",
+            tag: "executions",
+            summary: "Stream a workflow execution's events",
+            request: None,
+            response: Body::EventStream(
+                "Replays the execution's events, then streams new ones until it finishes. \
+                 Each event's `id` is its sequence number and its data is the event as JSON. \
+                 Reconnect with `Last-Event-ID` to resume; a `truncated` event means older \
+                 events are no longer available.",
+            ),
+            other_statuses: &[
+                INVALID_ID,
+                (204, "The execution has finished and there are no newer events"),
+                (404, "No execution with this ID"),
+            ],
+        },
+    ];
+
+    #[cfg(feature = "graphql")]
+    operations.push(Operation {
+        endpoint: post(super::graphql::execute),
+        path: super::graphql::PATH,
+        tag: "graphql",
+        summary: "Run a GraphQL query over executions, history, agents and projects",
+        request: Some(schema::<super::graphql::GraphQLRequest>),
+        response: Body::Json(schema::<super::graphql::GraphQLResponse>),
+        other_statuses: &[],
+    });
+
+    operations
+}
+
+/// `/agents/:id` in OpenAPI syntax (`/agents/{id}`) with its parameter names
+fn openapi_path(path: &str) -> (String, Vec<&str>) {
+    let mut params = Vec::new();
+    let segments: Vec<String> = path
+        .split('/')
+        .map(|segment| match segment.strip_prefix(':') {
+            Some(name) => {
+                params.push(name);
+                format!("{{{}}}", name)
+            }
+            None => segment.to_string(),
+        })
+        .collect();
+    (segments.join("/"), params)
+}
+
+fn operation_json(operation: &Operation, params: &[&str], gen: &mut SchemaGenerator) -> Value {
+    let success = match operation.response {
+        Body::Json(schema) => json!({
+            "description": "Success",
+            "content": { "application/json": { "schema": schema(gen) } },
+        }),
+        Body::EventStream(description) => json!({
+            "description": description,
+            "content": { "text/event-stream": { "schema": { "type": "string" } } },
+        }),
+    };
+
+    let mut responses = Map::new();
+    responses.insert("200".to_string(), success);
+    for (status, description) in operation.other_statuses {
+        responses.insert(status.to_string(), json!({ "description": description }));
+    }
+
+    let mut value = json!({
+        "tags": [operation.tag],
+        "summary": operation.summary,
+        "responses": responses,
+    });
+    if !params.is_empty() {
+        value["parameters"] = params
+            .iter()
+            .map(|name| json!({ "name": name, "in": "path", "required": true, "schema": { "type": "string" } }))
+            .collect();
+    }
+    if let Some(schema) = operation.request {
+        value["requestBody"] = json!({
+            "required": true,
+            "content": { "application/json": { "schema": schema(gen) } },
+        });
+    }
+    value
+}
+
+/// The OpenAPI 3 spec of the HTTP API
+pub fn spec() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    let mut paths = Map::new();
+
+    for operation in operations() {
+        let (path, params) = openapi_path(operation.path);
+        let item = paths.entry(path).or_insert_with(|| json!({}));
+        item[operation.endpoint.method] = operation_json(&operation, &params, &mut gen);
+    }
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "NEXUS API",
+            "version": API_VERSION,
+            "description": "Local HTTP API for controlling NEXUS agents from Stream Deck plugins and scripts",
+        },
+        "paths": paths,
+        "components": { "schemas": gen.take_definitions() },
+    })
+}
+
+/// GET /openapi.json - OpenAPI spec
+pub async fn serve_spec() -> Json<Value> {
+    Json(spec())
+}
+
+/// GET /docs - Swagger UI, reading the spec from /openapi.json
+pub async fn redirect_docs() -> Redirect {
+    Redirect::permanent(DOCS_INDEX_PATH)
+}
+
+/// GET /docs/ and /docs/*file - Swagger UI's page and assets
+pub async fn serve_docs(file: Option<Path<String>>) -> Response {
+    match docs_file(file.as_deref().map_or("", String::as_str)) {
+        Ok(Some(file)) => ([(header::CONTENT_TYPE, file.content_type)], file.bytes.into_owned()).into_response(),
+        Ok(None) => StatusCode::NOT_FOUND.into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+/// A file of Swagger UI, configured to load the spec; the page for ""
+fn docs_file(path: &str) -> Result<Option<SwaggerFile<'static>>, String> {
+    utoipa_swagger_ui::serve(path, Arc::new(Config::from(SPEC_PATH))).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_operation_is_in_the_spec() {
+        let spec = spec();
+        let operations = operations();
+        assert!(operations.len() > 10);
+
+        for operation in operations {
+            let (path, _) = openapi_path(operation.path);
+            let method = operation.endpoint.method;
+            assert!(spec["paths"][&path][method].is_object(), "{} {} is missing", method, operation.path);
+        }
+    }
+
+    #[test]
+    fn test_docs_serve_swagger_ui_on_the_spec() {
+        let page = docs_file("").unwrap().unwrap();
+        assert_eq!(page.content_type, "text/html");
+        assert!(String::from_utf8_lossy(&page.bytes).contains("swagger-ui"));
+
+        let initializer = docs_file("swagger-initializer.js").unwrap().unwrap();
+        assert!(String::from_utf8_lossy(&initializer.bytes).contains(SPEC_PATH));
+        assert!(docs_file("missing.js").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_schemas_are_resolvable() {
+        let spec = spec();
+        let operation = &spec["paths"]["/api/agents/spawn/{template}"]["post"];
+        assert_eq!(operation["parameters"][0]["name"], "template");
+
+        let request = &operation["requestBody"]["content"]["application/json"]["schema"]["$ref"];
+        assert_eq!(request, "#/components/schemas/SpawnFromTemplateRequest");
+        let schema = &spec["components"]["schemas"]["SpawnFromTemplateRequest"];
+        assert_eq!(schema["required"], json!(["working_directory"]));
+
+        // Every reference points at a generated schema
+        let text = spec.to_string();
+        for reference in text.split("\"#/components/schemas/").skip(1) {
+            let name = reference.split('"').next().unwrap();
+            assert!(spec["components"]["schemas"][name].is_object(), "missing schema {}", name);
+        }
+    }
+}
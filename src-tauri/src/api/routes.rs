@@ -1,19 +1,34 @@
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
-    response::Json,
-    routing::{delete, get, post},
+    http::{HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Json, Response,
+    },
+    routing::get,
     Router,
 };
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::convert::Infallible;
 use std::sync::Arc;
 use tauri::{AppHandle, Emitter};
+use tokio::sync::{broadcast::error::RecvError, mpsc};
+use tokio_stream::wrappers::ReceiverStream;
 use uuid::Uuid;
 
+use crate::commands::workflow::{find_execution, start_preset};
+use crate::process::group::{self, Signal};
 use crate::process::manager::{AgentConfig, AgentInfo, AgentManager, AgentStatus};
 use crate::state::AppState;
+use crate::workflow::run_presets::{RunPreset, RUN_PRESETS};
+use crate::workflow::{JournaledEvent, EVENT_JOURNAL};
+use super::openapi;
 use super::templates::{self, AgentTemplate, QuickAction};
 
+/// Version of the HTTP API, reported by `/api/status` and the OpenAPI spec
+pub const API_VERSION: &str = "1.0.0";
+
 #[derive(Clone)]
 pub struct ApiState {
     pub app_handle: AppHandle,
@@ -21,17 +36,20 @@ pub struct ApiState {
 }
 
 // Request/Response types
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct SpawnAgentRequest {
+    /// Defaults to `Agent-<timestamp>`
     pub name: Option<String>,
+    /// Defaults to `implementer`
     pub role: Option<String>,
     pub working_directory: String,
     pub system_prompt: Option<String>,
     pub assigned_task: Option<String>,
+    /// Project UUID; ignored if it isn't one
     pub project_id: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct SpawnFromTemplateRequest {
     pub working_directory: String,
     pub assigned_task: Option<String>,
@@ -39,14 +57,45 @@ pub struct SpawnFromTemplateRequest {
     pub name_override: Option<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct QuickActionRequest {
+    /// Used unless the quick action has its own working directory
     pub working_directory: String,
     pub task_override: Option<String>,
     pub project_id: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RunPresetResponse {
+    pub id: String,
+    pub name: String,
+    pub workflow_id: String,
+    pub project_id: String,
+    /// With the preset's variables filled in
+    pub input_prompt: String,
+    pub last_run_at: Option<String>,
+}
+
+impl From<RunPreset> for RunPresetResponse {
+    fn from(preset: RunPreset) -> Self {
+        Self {
+            input_prompt: preset.prompt(),
+            id: preset.id.to_string(),
+            name: preset.name,
+            workflow_id: preset.workflow_id.to_string(),
+            project_id: preset.project_id.to_string(),
+            last_run_at: preset.last_run_at.map(|at| at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PresetRunResponse {
+    pub preset_id: String,
+    pub execution_id: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct AgentResponse {
     pub id: String,
     pub name: String,
@@ -71,7 +120,7 @@ impl From<&AgentInfo> for AgentResponse {
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct SystemStatus {
     pub agents_running: usize,
     pub database_connected: bool,
@@ -79,7 +128,7 @@ pub struct SystemStatus {
     pub uptime_seconds: u64,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ApiResponse<T> {
     pub success: bool,
     pub data: Option<T>,
@@ -107,23 +156,23 @@ impl<T: Serialize> ApiResponse<T> {
 // Route handlers
 
 /// GET /api/health - Health check
-async fn health_check() -> Json<ApiResponse<&'static str>> {
+pub(super) async fn health_check() -> Json<ApiResponse<&'static str>> {
     Json(ApiResponse::success("OK"))
 }
 
 /// GET /api/status - System status
-async fn get_status(State(state): State<ApiState>) -> Json<ApiResponse<SystemStatus>> {
+pub(super) async fn get_status(State(state): State<ApiState>) -> Json<ApiResponse<SystemStatus>> {
     let status = SystemStatus {
         agents_running: state.app_state.agents.len(),
         database_connected: state.app_state.has_db(),
-        api_version: "1.0.0".to_string(),
+        api_version: API_VERSION.to_string(),
         uptime_seconds: 0, // TODO: Track actual uptime
     };
     Json(ApiResponse::success(status))
 }
 
 /// GET /api/agents - List all agents
-async fn list_agents(State(state): State<ApiState>) -> Json<ApiResponse<Vec<AgentResponse>>> {
+pub(super) async fn list_agents(State(state): State<ApiState>) -> Json<ApiResponse<Vec<AgentResponse>>> {
     let agents: Vec<AgentResponse> = state
         .app_state
         .agents
@@ -134,7 +183,7 @@ async fn list_agents(State(state): State<ApiState>) -> Json<ApiResponse<Vec<Agen
 }
 
 /// GET /api/agents/:id - Get agent by ID
-async fn get_agent(
+pub(super) async fn get_agent(
     State(state): State<ApiState>,
     Path(id): Path<String>,
 ) -> Result<Json<ApiResponse<AgentResponse>>, StatusCode> {
@@ -147,7 +196,7 @@ async fn get_agent(
 }
 
 /// POST /api/agents/spawn - Spawn a new agent
-async fn spawn_agent(
+pub(super) async fn spawn_agent(
     State(state): State<ApiState>,
     Json(request): Json<SpawnAgentRequest>,
 ) -> Result<Json<ApiResponse<AgentResponse>>, StatusCode> {
@@ -158,6 +207,9 @@ async fn spawn_agent(
         project_id: request.project_id.and_then(|s| Uuid::parse_str(&s).ok()),
         system_prompt: request.system_prompt,
         assigned_task: request.assigned_task,
+        model: None,
+        env: Vec::new(),
+        owner: None,
     };
 
     let manager = AgentManager::new(state.app_handle.clone());
@@ -175,7 +227,7 @@ async fn spawn_agent(
 }
 
 /// POST /api/agents/spawn/:template - Spawn agent from template
-async fn spawn_from_template(
+pub(super) async fn spawn_from_template(
     State(state): State<ApiState>,
     Path(template_id): Path<String>,
     Json(request): Json<SpawnFromTemplateRequest>,
@@ -190,6 +242,9 @@ async fn spawn_from_template(
         project_id: request.project_id.and_then(|s| Uuid::parse_str(&s).ok()),
         system_prompt: Some(template.system_prompt.clone()),
         assigned_task: request.assigned_task,
+        model: None,
+        env: Vec::new(),
+        owner: None,
     };
 
     let manager = AgentManager::new(state.app_handle.clone());
@@ -207,7 +262,7 @@ async fn spawn_from_template(
 }
 
 /// POST /api/quick-actions/:action - Execute a quick action
-async fn execute_quick_action(
+pub(super) async fn execute_quick_action(
     State(state): State<ApiState>,
     Path(action_id): Path<String>,
     Json(request): Json<QuickActionRequest>,
@@ -232,6 +287,9 @@ async fn execute_quick_action(
         project_id: request.project_id.and_then(|s| Uuid::parse_str(&s).ok()),
         system_prompt: Some(template.system_prompt.clone()),
         assigned_task: Some(task),
+        model: None,
+        env: Vec::new(),
+        owner: None,
     };
 
     let manager = AgentManager::new(state.app_handle.clone());
@@ -249,7 +307,7 @@ async fn execute_quick_action(
 }
 
 /// DELETE /api/agents/:id - Kill an agent
-async fn kill_agent(
+pub(super) async fn kill_agent(
     State(state): State<ApiState>,
     Path(id): Path<String>,
 ) -> Result<Json<ApiResponse<&'static str>>, StatusCode> {
@@ -257,11 +315,7 @@ async fn kill_agent(
 
     if let Some((_, mut info)) = state.app_state.agents.remove(&uuid) {
         if let Some(pid) = info.pid {
-            #[cfg(unix)]
-            {
-                use std::process::Command;
-                let _ = Command::new("kill").arg("-9").arg(pid.to_string()).output();
-            }
+            group::signal(pid, Signal::Kill);
         }
         info.status = AgentStatus::Killed;
         let _ = state.app_handle.emit("agent-killed", id);
@@ -272,7 +326,7 @@ async fn kill_agent(
 }
 
 /// DELETE /api/agents - Kill all agents
-async fn kill_all_agents(State(state): State<ApiState>) -> Json<ApiResponse<usize>> {
+pub(super) async fn kill_all_agents(State(state): State<ApiState>) -> Json<ApiResponse<usize>> {
     let mut killed = 0;
 
     let ids: Vec<Uuid> = state.app_state.agents.iter().map(|e| *e.key()).collect();
@@ -280,11 +334,7 @@ async fn kill_all_agents(State(state): State<ApiState>) -> Json<ApiResponse<usiz
     for id in ids {
         if let Some((_, mut info)) = state.app_state.agents.remove(&id) {
             if let Some(pid) = info.pid {
-                #[cfg(unix)]
-                {
-                    use std::process::Command;
-                    let _ = Command::new("kill").arg("-9").arg(pid.to_string()).output();
-                }
+                group::signal(pid, Signal::Kill);
             }
             info.status = AgentStatus::Killed;
             let _ = state.app_handle.emit("agent-killed", id.to_string());
@@ -296,12 +346,12 @@ async fn kill_all_agents(State(state): State<ApiState>) -> Json<ApiResponse<usiz
 }
 
 /// GET /api/templates - List all agent templates
-async fn list_templates() -> Json<ApiResponse<Vec<&'static AgentTemplate>>> {
+pub(super) async fn list_templates() -> Json<ApiResponse<Vec<&'static AgentTemplate>>> {
     Json(ApiResponse::success(templates::list_templates()))
 }
 
 /// GET /api/templates/:id - Get template by ID
-async fn get_template(Path(id): Path<String>) -> Result<Json<ApiResponse<&'static AgentTemplate>>, StatusCode> {
+pub(super) async fn get_template(Path(id): Path<String>) -> Result<Json<ApiResponse<&'static AgentTemplate>>, StatusCode> {
     match templates::get_template(&id) {
         Some(t) => Ok(Json(ApiResponse::success(t))),
         None => Err(StatusCode::NOT_FOUND),
@@ -309,37 +359,130 @@ async fn get_template(Path(id): Path<String>) -> Result<Json<ApiResponse<&'stati
 }
 
 /// GET /api/quick-actions - List all quick actions
-async fn list_quick_actions() -> Json<ApiResponse<Vec<&'static QuickAction>>> {
+pub(super) async fn list_quick_actions() -> Json<ApiResponse<Vec<&'static QuickAction>>> {
     Json(ApiResponse::success(templates::list_quick_actions()))
 }
 
 /// GET /api/quick-actions/:id - Get quick action by ID
-async fn get_quick_action(Path(id): Path<String>) -> Result<Json<ApiResponse<&'static QuickAction>>, StatusCode> {
+pub(super) async fn get_quick_action(Path(id): Path<String>) -> Result<Json<ApiResponse<&'static QuickAction>>, StatusCode> {
     match templates::get_quick_action(&id) {
         Some(a) => Ok(Json(ApiResponse::success(a))),
         None => Err(StatusCode::NOT_FOUND),
     }
 }
 
-/// Build the API router
+/// GET /api/presets - List workflow run presets
+pub(super) async fn list_run_presets() -> Json<ApiResponse<Vec<RunPresetResponse>>> {
+    Json(ApiResponse::success(RUN_PRESETS.list(None).into_iter().map(RunPresetResponse::from).collect()))
+}
+
+/// POST /api/presets/:id/run - Run a workflow with a preset's arguments
+pub(super) async fn run_preset(
+    State(state): State<ApiState>,
+    Path(id): Path<String>,
+) -> Result<Json<ApiResponse<PresetRunResponse>>, StatusCode> {
+    let preset_id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if RUN_PRESETS.get(&preset_id).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    match start_preset(&state.app_handle, &preset_id) {
+        Ok(execution_id) => Ok(Json(ApiResponse::success(PresetRunResponse {
+            preset_id: id,
+            execution_id: execution_id.to_string(),
+        }))),
+        Err(e) => {
+            log::error!("Failed to run preset {}: {}", id, e);
+            Ok(Json(ApiResponse::error(&e)))
+        }
+    }
+}
+
+/// GET /api/executions/:id/events - Execution events as Server-Sent Events
+///
+/// Replays the execution's event journal, then streams live events until
+/// the execution finishes. Each event's id is its sequence number, so a
+/// client reconnecting with `Last-Event-ID` resumes where it left off. If
+/// older events were dropped from the journal a `truncated` event comes
+/// first.
+pub(super) async fn stream_execution_events(Path(id): Path<String>, headers: HeaderMap) -> Result<Response, StatusCode> {
+    let execution_id = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
+    if find_execution(&execution_id).is_none() && EVENT_JOURNAL.since(&execution_id, 0).is_none() {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    let last_seen: u64 = headers
+        .get("last-event-id")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0);
+
+    let (replay, mut live) = EVENT_JOURNAL.subscribe_since(execution_id, last_seen);
+    if replay.finished && replay.events.is_empty() {
+        // Nothing more will come; 204 also tells EventSource to stop reconnecting
+        return Ok(StatusCode::NO_CONTENT.into_response());
+    }
+
+    let key = execution_id.to_string();
+    let (tx, rx) = mpsc::channel::<Result<Event, Infallible>>(64);
+    tokio::spawn(async move {
+        if replay.truncated {
+            let notice = Event::default().event("truncated").data(replay.last_seq.to_string());
+            if tx.send(Ok(notice)).await.is_err() {
+                return;
+            }
+        }
+
+        let mut last_seq = last_seen;
+        let mut pending = replay.events;
+        loop {
+            for journaled in pending.drain(..) {
+                if journaled.seq <= last_seq {
+                    continue;
+                }
+                last_seq = journaled.seq;
+                let finished = journaled.event.is_terminal();
+                if tx.send(Ok(sse_event(&journaled))).await.is_err() || finished {
+                    return;
+                }
+            }
+
+            tokio::select! {
+                _ = tx.closed() => return,
+                received = live.recv() => match received {
+                    Ok(journaled) if journaled.event.execution_id() == key => pending.push(journaled),
+                    Ok(_) => {}
+                    // Fell behind the live channel; the journal still has them
+                    Err(RecvError::Lagged(_)) => match EVENT_JOURNAL.since(&execution_id, last_seq) {
+                        Some(missed) => pending = missed.events,
+                        None => return,
+                    },
+                    Err(RecvError::Closed) => return,
+                },
+            }
+        }
+    });
+
+    Ok(Sse::new(ReceiverStream::new(rx))
+        .keep_alive(KeepAlive::default())
+        .into_response())
+}
+
+fn sse_event(journaled: &JournaledEvent) -> Event {
+    let event = Event::default().id(journaled.seq.to_string());
+    match serde_json::to_string(journaled) {
+        Ok(data) => event.data(data),
+        Err(e) => event.event("error").data(e.to_string()),
+    }
+}
+
+/// Build the API router from the documented operations, plus the docs
 pub fn create_router(api_state: ApiState) -> Router {
-    Router::new()
-        // Health & Status
-        .route("/api/health", get(health_check))
-        .route("/api/status", get(get_status))
-        // Agents
-        .route("/api/agents", get(list_agents))
-        .route("/api/agents", delete(kill_all_agents))
-        .route("/api/agents/spawn", post(spawn_agent))
-        .route("/api/agents/spawn/:template", post(spawn_from_template))
-        .route("/api/agents/:id", get(get_agent))
-        .route("/api/agents/:id", delete(kill_agent))
-        // Templates
-        .route("/api/templates", get(list_templates))
-        .route("/api/templates/:id", get(get_template))
-        // Quick Actions
-        .route("/api/quick-actions", get(list_quick_actions))
-        .route("/api/quick-actions/:id", get(get_quick_action))
-        .route("/api/quick-actions/:id/execute", post(execute_quick_action))
+    openapi::operations()
+        .into_iter()
+        .fold(Router::new(), |router, operation| router.route(operation.path, operation.endpoint.handler))
+        .route(openapi::SPEC_PATH, get(openapi::serve_spec))
+        .route(openapi::DOCS_PATH, get(openapi::redirect_docs))
+        .route(openapi::DOCS_INDEX_PATH, get(openapi::serve_docs))
+        .route(openapi::DOCS_FILES_PATH, get(openapi::serve_docs))
         .with_state(api_state)
 }
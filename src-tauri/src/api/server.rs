@@ -1,25 +1,95 @@
-use std::sync::Arc;
+//! The local HTTP API server (OpenDeck/Stream Deck integration).
+//!
+//! Provides:
+//! - Binding the preferred port, or the next free one if it is taken
+//! - What the server is doing, for `get_api_server_info` and the
+//!   `api-server-status` event; `api-server-bind-failed` when no port
+//!   could be bound
+//! - Stopping and restarting the server, e.g. after its settings change
+//!
+//! The server runs on a thread with its own tokio runtime, so a restart
+//! drops every connection the old one had open.
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
-use tauri::AppHandle;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use tokio::net::TcpListener;
+use tokio::sync::oneshot;
 use tower_http::cors::{Any, CorsLayer};
 
+use crate::settings::SETTINGS;
 use crate::state::AppState;
 use super::routes::{create_router, ApiState};
 
-const DEFAULT_PORT: u16 = 9999;
 const MAX_PORT_ATTEMPTS: u16 = 10;
 
+/// How long open connections get when the server stops
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What the API server is doing
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ApiServerInfo {
+    pub running: bool,
+    /// Port from settings, or `NEXUS_API_PORT`
+    pub preferred_port: u16,
+    /// Port listened on; another one than preferred if that was taken
+    pub port: Option<u16>,
+    pub url: Option<String>,
+    /// Why the server is not running, if it failed
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+/// The server thread and how to stop it
+struct RunningServer {
+    shutdown: oneshot::Sender<()>,
+    thread: JoinHandle<()>,
+}
+
+lazy_static! {
+    static ref SERVER: Mutex<Option<RunningServer>> = Mutex::new(None);
+    static ref INFO: RwLock<ApiServerInfo> = RwLock::new(ApiServerInfo::default());
+}
+
+/// The port to try first: `NEXUS_API_PORT`, or the one in settings
+pub fn configured_port() -> u16 {
+    std::env::var("NEXUS_API_PORT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or(SETTINGS.get().api.port)
+}
+
+pub fn info() -> ApiServerInfo {
+    INFO.read().clone()
+}
+
+/// Port the API server is listening on, while it runs
+pub fn bound_port() -> Option<u16> {
+    INFO.read().port
+}
+
+/// Ports tried for `preferred`, in order
+fn candidate_ports(preferred: u16) -> impl Iterator<Item = u16> {
+    (0..MAX_PORT_ATTEMPTS).filter_map(move |offset| preferred.checked_add(offset))
+}
+
 /// Check if a port is available for binding
 async fn is_port_available(port: u16) -> bool {
-    tokio::net::TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port)))
+    TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port)))
         .await
         .is_ok()
 }
 
 /// Find an available port starting from the preferred port
-async fn find_available_port(preferred: u16) -> Option<u16> {
-    for offset in 0..MAX_PORT_ATTEMPTS {
-        let port = preferred + offset;
+pub(crate) async fn find_available_port(preferred: u16) -> Option<u16> {
+    for port in candidate_ports(preferred) {
         if is_port_available(port).await {
             return Some(port);
         }
@@ -28,38 +98,77 @@ async fn find_available_port(preferred: u16) -> Option<u16> {
     None
 }
 
-pub async fn start_api_server(
+/// Bind the preferred port or the next free one, keeping the listener so
+/// nothing takes the port in between
+async fn bind(preferred: u16) -> Result<(TcpListener, u16), String> {
+    let mut last_error = None;
+    for port in candidate_ports(preferred) {
+        match TcpListener::bind(SocketAddr::from(([127, 0, 0, 1], port))).await {
+            Ok(listener) => return Ok((listener, port)),
+            Err(e) => {
+                log::debug!("Port {} is in use, trying next...", port);
+                last_error = Some(e);
+            }
+        }
+    }
+    let last = candidate_ports(preferred).last().unwrap_or(preferred);
+    Err(match last_error {
+        Some(e) => format!("No free port in range {}-{}: {}", preferred, last, e),
+        None => format!("No free port in range {}-{}", preferred, last),
+    })
+}
+
+fn set_info(app_handle: &AppHandle, info: ApiServerInfo) {
+    *INFO.write() = info.clone();
+    let _ = app_handle.emit("api-server-status", &info);
+}
+
+/// Serve on `preferred` or the next free port until `shutdown` fires.
+/// `bound` gets the server's state once the port is bound, or binding
+/// failed.
+async fn serve(
     app_handle: AppHandle,
     app_state: Arc<AppState>,
-    port: Option<u16>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    let preferred_port = port.unwrap_or(DEFAULT_PORT);
-
-    // Find an available port
-    let actual_port = match find_available_port(preferred_port).await {
-        Some(p) => p,
-        None => {
-            log::warn!(
-                "Could not find available port in range {}-{}. API server disabled.",
-                preferred_port,
-                preferred_port + MAX_PORT_ATTEMPTS - 1
-            );
-            return Ok(());
+    preferred: u16,
+    shutdown: oneshot::Receiver<()>,
+    bound: oneshot::Sender<ApiServerInfo>,
+) {
+    let (listener, port) = match bind(preferred).await {
+        Ok(bound) => bound,
+        Err(e) => {
+            log::warn!("{}. API server disabled.", e);
+            let info = ApiServerInfo {
+                preferred_port: preferred,
+                error: Some(e),
+                ..Default::default()
+            };
+            set_info(&app_handle, info.clone());
+            let _ = app_handle.emit("api-server-bind-failed", &info);
+            let _ = bound.send(info);
+            return;
         }
     };
 
-    if actual_port != preferred_port {
-        log::info!(
-            "Port {} was in use, using port {} instead",
-            preferred_port,
-            actual_port
-        );
+    if port != preferred {
+        log::info!("Port {} was in use, using port {} instead", preferred, port);
     }
+    let url = format!("http://localhost:{}/api", port);
+    log::info!("Starting NEXUS API server on http://127.0.0.1:{}", port);
+    log::info!("OpenDeck can now connect to: {}", url);
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], actual_port));
+    let info = ApiServerInfo {
+        running: true,
+        preferred_port: preferred,
+        port: Some(port),
+        url: Some(url),
+        error: None,
+        started_at: Some(Utc::now()),
+    };
+    set_info(&app_handle, info.clone());
+    let _ = bound.send(info);
 
     let api_state = ApiState {
-        app_handle,
+        app_handle: app_handle.clone(),
         app_state,
     };
 
@@ -71,13 +180,24 @@ pub async fn start_api_server(
 
     let app = create_router(api_state).layer(cors);
 
-    log::info!("Starting NEXUS API server on http://{}", addr);
-    log::info!("OpenDeck can now connect to: http://localhost:{}/api", actual_port);
-
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
-
-    Ok(())
+    // Event streams stay open indefinitely, so the server is dropped
+    // rather than waiting for its connections to finish
+    let error = tokio::select! {
+        result = axum::serve(listener, app).into_future() => result.err().map(|e| e.to_string()),
+        _ = shutdown => None,
+    };
+    if let Some(e) = &error {
+        log::error!("API server error: {}", e);
+    }
+    set_info(
+        &app_handle,
+        ApiServerInfo {
+            running: false,
+            preferred_port: preferred,
+            error,
+            ..Default::default()
+        },
+    );
 }
 
 /// Start the API server in a background thread with its own tokio runtime
@@ -86,12 +206,81 @@ pub fn spawn_api_server(
     app_state: Arc<AppState>,
     port: Option<u16>,
 ) {
-    std::thread::spawn(move || {
+    start(app_handle, app_state, port.unwrap_or_else(configured_port));
+}
+
+/// Start the server thread. The receiver gets the server's state once it
+/// has bound a port or failed to.
+fn start(app_handle: AppHandle, app_state: Arc<AppState>, preferred: u16) -> oneshot::Receiver<ApiServerInfo> {
+    let (shutdown_tx, shutdown_rx) = oneshot::channel();
+    let (bound_tx, bound_rx) = oneshot::channel();
+
+    let mut server = SERVER.lock();
+    let thread = std::thread::spawn(move || {
         let rt = tokio::runtime::Runtime::new().expect("Failed to create API server runtime");
-        rt.block_on(async move {
-            if let Err(e) = start_api_server(app_handle, app_state, port).await {
-                log::error!("API server error: {}", e);
-            }
-        });
+        rt.block_on(serve(app_handle, app_state, preferred, shutdown_rx, bound_tx));
+        rt.shutdown_timeout(SHUTDOWN_TIMEOUT);
     });
+    let previous = server.replace(RunningServer {
+        shutdown: shutdown_tx,
+        thread,
+    });
+    drop(server);
+    if let Some(previous) = previous {
+        log::warn!("API server started while another was running; stopping the old one");
+        stop(previous);
+    }
+    bound_rx
+}
+
+fn stop(server: RunningServer) {
+    let _ = server.shutdown.send(());
+    if server.thread.join().is_err() {
+        log::error!("API server thread panicked");
+    }
+}
+
+/// Stop the API server, if it runs, and wait for its port to be released
+pub fn stop_api_server(app_handle: &AppHandle) {
+    let Some(server) = SERVER.lock().take() else {
+        return;
+    };
+    log::info!("Stopping NEXUS API server");
+    stop(server);
+    set_info(
+        app_handle,
+        ApiServerInfo {
+            preferred_port: configured_port(),
+            ..Default::default()
+        },
+    );
+}
+
+/// Stop the API server and start it again with the current settings, or
+/// leave it stopped if they disable it. Returns the new state once the
+/// port is bound.
+pub async fn restart_api_server(app_handle: AppHandle, app_state: Arc<AppState>) -> ApiServerInfo {
+    let stopping = app_handle.clone();
+    let _ = tokio::task::spawn_blocking(move || stop_api_server(&stopping)).await;
+
+    if !SETTINGS.get().api.enabled {
+        log::info!("HTTP API server disabled in settings");
+        return info();
+    }
+    start(app_handle, app_state, configured_port()).await.unwrap_or_else(|_| info())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bind_falls_back_to_next_free_port() {
+        let (taken, preferred) = bind(41870).await.unwrap();
+        let (_listener, port) = bind(preferred).await.unwrap();
+        assert!(port > preferred && port < preferred + MAX_PORT_ATTEMPTS);
+        drop(taken);
+
+        assert_eq!(candidate_ports(u16::MAX - 1).collect::<Vec<_>>(), [u16::MAX - 1, u16::MAX]);
+    }
 }
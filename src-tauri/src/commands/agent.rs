@@ -1,8 +1,14 @@
+use crate::commands::workflow::agent_executions;
+use crate::process::archive::AGENT_ARCHIVE;
 use crate::process::manager::{AgentConfig, AgentInfo, AgentManager, AgentStatus};
+use crate::process::query::{resource_usage, AgentFilter, AgentSortField, ResourceUsage};
 use crate::process::registry::AGENT_REGISTRY;
 use crate::state::AppState;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
 use std::sync::Arc;
+use std::time::Duration;
 use tauri::{AppHandle, Emitter, State};
 use uuid::Uuid;
 
@@ -16,6 +22,14 @@ pub struct AgentResponse {
     pub assigned_task: Option<String>,
     pub progress: u8,
     pub pid: Option<u32>,
+    pub started_at: DateTime<Utc>,
+    /// Seconds the agent has been running, or ran for if it finished
+    pub runtime_secs: Option<u64>,
+    pub last_output_at: Option<DateTime<Utc>>,
+    /// Workflow execution that spawned the agent (filled in by `list_agents`)
+    pub execution_id: Option<String>,
+    /// CPU and memory of a live agent (filled in by `list_agents`)
+    pub usage: Option<ResourceUsage>,
 }
 
 impl From<&AgentInfo> for AgentResponse {
@@ -29,10 +43,52 @@ impl From<&AgentInfo> for AgentResponse {
             assigned_task: info.assigned_task.clone(),
             progress: info.progress,
             pid: info.pid,
+            started_at: info.started_at,
+            runtime_secs: AGENT_REGISTRY.get_runtime(&info.id).map(|d| d.as_secs()).or_else(|| {
+                // Agents restored from the archive aren't in the registry
+                info.finished_at.map(|end| (end - info.started_at).num_seconds().max(0) as u64)
+            }),
+            last_output_at: AGENT_REGISTRY.last_output_at(&info.id),
+            execution_id: None,
+            usage: None,
         }
     }
 }
 
+/// Filter, order and page for `list_agents`
+#[derive(Debug, Default, Deserialize)]
+pub struct AgentListQuery {
+    #[serde(flatten)]
+    pub filter: AgentFilter,
+    #[serde(default)]
+    pub sort_by: AgentSortField,
+    #[serde(default)]
+    pub descending: bool,
+    #[serde(default)]
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// One page of agents
+#[derive(Debug, Serialize)]
+pub struct AgentList {
+    pub agents: Vec<AgentResponse>,
+    /// Agents matching the filter, across all pages
+    pub total: usize,
+}
+
+fn compare_agents(a: &AgentResponse, b: &AgentResponse, field: AgentSortField) -> Ordering {
+    let ordering = match field {
+        AgentSortField::Name => a.name.cmp(&b.name),
+        AgentSortField::Role => a.role.cmp(&b.role),
+        AgentSortField::Status => a.status.cmp(&b.status),
+        AgentSortField::StartedAt => a.started_at.cmp(&b.started_at),
+        AgentSortField::Runtime => a.runtime_secs.cmp(&b.runtime_secs),
+        AgentSortField::LastOutput => a.last_output_at.cmp(&b.last_output_at),
+    };
+    ordering.then_with(|| a.id.cmp(&b.id))
+}
+
 #[derive(Debug, Deserialize)]
 pub struct SpawnAgentRequest {
     pub name: String,
@@ -62,6 +118,9 @@ pub async fn spawn_agent(
         project_id,
         system_prompt: request.system_prompt,
         assigned_task: request.assigned_task,
+        model: None,
+        env: Vec::new(),
+        owner: None,
     };
 
     let manager = AgentManager::new(app.clone());
@@ -96,6 +155,7 @@ pub async fn kill_agent(
         // Agent might not be in registry but could be in state
         if state.agents.contains_key(&id) {
             state.agents.remove(&id);
+            AGENT_ARCHIVE.remove(&id);
             Ok(())
         } else {
             Err("Agent not found".to_string())
@@ -103,14 +163,55 @@ pub async fn kill_agent(
     }
 }
 
+/// List agents, optionally filtered, sorted and paged. Without a query all
+/// agents are returned, oldest first.
 #[tauri::command]
-pub async fn list_agents(state: State<'_, Arc<AppState>>) -> Result<Vec<AgentResponse>, String> {
-    let agents: Vec<AgentResponse> = state
+pub async fn list_agents(
+    state: State<'_, Arc<AppState>>,
+    query: Option<AgentListQuery>,
+) -> Result<AgentList, String> {
+    let query = query.unwrap_or_default();
+    let executions = agent_executions();
+    let now = Utc::now();
+
+    let mut agents: Vec<AgentResponse> = state
         .agents
         .iter()
-        .map(|entry| AgentResponse::from(entry.value()))
+        .filter_map(|entry| {
+            let execution_id = executions.get(entry.key()).copied();
+            if !query.filter.matches(entry.value(), execution_id, now) {
+                return None;
+            }
+            let mut agent = AgentResponse::from(entry.value());
+            agent.execution_id = execution_id.map(|id| id.to_string());
+            Some(agent)
+        })
         .collect();
-    Ok(agents)
+
+    agents.sort_by(|a, b| compare_agents(a, b, query.sort_by));
+    if query.descending {
+        agents.reverse();
+    }
+
+    let total = agents.len();
+    let mut agents: Vec<AgentResponse> = agents
+        .into_iter()
+        .skip(query.offset)
+        .take(query.limit.unwrap_or(usize::MAX))
+        .collect();
+
+    // Only sample the page, and only live processes: a finished agent's pid
+    // may belong to something else by now
+    let live = |agent: &AgentResponse| matches!(agent.status.as_str(), "Starting" | "Running" | "Paused");
+    let pids: Vec<u32> = agents.iter().filter(|a| live(a)).filter_map(|a| a.pid).collect();
+    let usage = tokio::task::spawn_blocking(move || resource_usage(&pids))
+        .await
+        .map_err(|e| format!("Failed to sample resource usage: {}", e))?;
+    for agent in agents.iter_mut().filter(|a| live(a)) {
+        agent.usage = agent.pid.and_then(|pid| usage.get(&pid).copied());
+    }
+
+    Ok(AgentList { agents, total })
 }
 
 #[tauri::command]
@@ -160,6 +261,7 @@ pub async fn restart_agent(
 
     // Clean up registry
     AGENT_REGISTRY.remove(&id);
+    AGENT_ARCHIVE.remove(&id);
 
     // Spawn a new agent with the same config
     let manager = AgentManager::new(app.clone());
@@ -173,6 +275,34 @@ pub async fn restart_agent(
     Ok(response)
 }
 
+/// Reattach an agent that was running when the app last stopped to its
+/// Claude Code session, keeping its ID
+#[tauri::command]
+pub async fn reattach_agent(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    agent_id: String,
+) -> Result<AgentResponse, String> {
+    let id = Uuid::parse_str(&agent_id).map_err(|e| format!("Invalid agent ID: {}", e))?;
+
+    if AGENT_REGISTRY.is_running(&id) {
+        return Err("Agent is already running".to_string());
+    }
+    let record = AGENT_ARCHIVE
+        .session(&id)
+        .ok_or_else(|| "No interrupted session recorded for agent".to_string())?;
+
+    let manager = AgentManager::new(app.clone());
+    let info = manager.reattach_agent(&record)?;
+
+    let response = AgentResponse::from(&info);
+    state.agents.insert(info.id, info);
+
+    log::info!("Reattached agent {} to session {}", agent_id, record.session_id);
+
+    Ok(response)
+}
+
 #[tauri::command]
 pub async fn get_agent_output(
     _state: State<'_, Arc<AppState>>,
@@ -182,6 +312,7 @@ pub async fn get_agent_output(
 
     AGENT_REGISTRY
         .get_output(&id)
+        .or_else(|| AGENT_ARCHIVE.read_output(&id))
         .ok_or_else(|| "Agent not found or no output available".to_string())
 }
 
@@ -251,3 +382,166 @@ pub async fn resume_agent(
 
     Ok(())
 }
+
+/// IDs of the standalone agents matching a filter for a bulk operation.
+/// An empty filter is refused unless `all` is set. Agents working for a
+/// workflow execution are left out: their execution waits on them, so
+/// they are stopped by cancelling it.
+fn matching_agents(state: &AppState, filter: &AgentFilter, all: bool) -> Result<Vec<Uuid>, String> {
+    if filter.is_empty() && !all {
+        return Err("The filter matches every agent; set `all` to act on all of them".to_string());
+    }
+
+    let executions = agent_executions();
+    let now = Utc::now();
+    Ok(state
+        .agents
+        .iter()
+        .filter(|entry| filter.matches(entry.value(), executions.get(entry.key()).copied(), now))
+        .map(|entry| *entry.key())
+        .filter(|id| AGENT_REGISTRY.get_config(id).map_or(true, |config| config.owner.is_none()))
+        .collect())
+}
+
+/// An agent a bulk operation couldn't act on
+#[derive(Debug, Serialize)]
+pub struct BulkAgentFailure {
+    pub agent_id: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestartedAgent {
+    pub previous_id: String,
+    pub agent: AgentResponse,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestartAgentsResult {
+    pub restarted: Vec<RestartedAgent>,
+    pub failed: Vec<BulkAgentFailure>,
+}
+
+/// Kill every unfinished standalone agent matching the filter. An empty
+/// filter needs `all`. Returns the IDs of the agents killed.
+#[tauri::command]
+pub async fn kill_agents(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    filter: AgentFilter,
+    all: bool,
+) -> Result<Vec<String>, String> {
+    let ids: Vec<Uuid> = matching_agents(&state, &filter, all)?
+        .into_iter()
+        .filter(|id| state.agents.get(id).is_some_and(|agent| !agent.status.is_terminal()))
+        .collect();
+
+    let targets = ids.clone();
+    tokio::task::spawn_blocking(move || AGENT_REGISTRY.terminate(&targets, Duration::from_secs(2)))
+        .await
+        .map_err(|e| format!("Failed to kill agents: {}", e))?;
+
+    let mut killed = Vec::with_capacity(ids.len());
+    for id in ids {
+        if let Some(mut agent) = state.agents.get_mut(&id) {
+            agent.status = AgentStatus::Killed;
+        }
+        let _ = app.emit("agent-killed", id.to_string());
+        killed.push(id.to_string());
+    }
+
+    log::info!("Killed {} agents", killed.len());
+    Ok(killed)
+}
+
+/// Restart every matching standalone agent with its original config. An
+/// empty filter needs `all`. Agents without a stored config, or that fail
+/// to spawn, are reported as failures.
+#[tauri::command]
+pub async fn restart_agents(
+    app: AppHandle,
+    state: State<'_, Arc<AppState>>,
+    filter: AgentFilter,
+    all: bool,
+) -> Result<RestartAgentsResult, String> {
+    let mut failed = Vec::new();
+    let mut targets = Vec::new();
+    for id in matching_agents(&state, &filter, all)? {
+        match AGENT_REGISTRY.get_config(&id) {
+            Some(config) => targets.push((id, config)),
+            None => failed.push(BulkAgentFailure {
+                agent_id: id.to_string(),
+                error: "No config found for agent (cannot restart)".to_string(),
+            }),
+        }
+    }
+
+    // Stop them all together rather than paying the grace period per agent
+    let ids: Vec<Uuid> = targets.iter().map(|(id, _)| *id).collect();
+    tokio::task::spawn_blocking(move || AGENT_REGISTRY.terminate(&ids, Duration::from_secs(2)))
+        .await
+        .map_err(|e| format!("Failed to stop agents: {}", e))?;
+
+    let manager = AgentManager::new(app.clone());
+    let mut restarted = Vec::with_capacity(targets.len());
+    for (id, config) in targets {
+        state.agents.remove(&id);
+        AGENT_REGISTRY.remove(&id);
+        AGENT_ARCHIVE.remove(&id);
+
+        match manager.spawn_agent(config) {
+            Ok(info) => {
+                let agent = AgentResponse::from(&info);
+                state.agents.insert(info.id, info);
+                log::info!("Restarted agent {} as new agent {}", id, agent.id);
+                restarted.push(RestartedAgent {
+                    previous_id: id.to_string(),
+                    agent,
+                });
+            }
+            Err(error) => failed.push(BulkAgentFailure {
+                agent_id: id.to_string(),
+                error,
+            }),
+        }
+    }
+
+    Ok(RestartAgentsResult { restarted, failed })
+}
+
+/// Forget completed, failed and killed agents that finished at least
+/// `older_than_secs` ago, along with their output and archived records.
+/// Returns the IDs removed.
+#[tauri::command]
+pub async fn cleanup_finished_agents(
+    state: State<'_, Arc<AppState>>,
+    older_than_secs: u64,
+) -> Result<Vec<String>, String> {
+    let older_than = Duration::from_secs(older_than_secs);
+    let now = Utc::now();
+
+    let finished: Vec<Uuid> = state
+        .agents
+        .iter()
+        .filter(|entry| entry.value().status.is_terminal() && !AGENT_REGISTRY.is_running(entry.key()))
+        .filter(|entry| {
+            // Agents restored from the archive only have a finish time;
+            // agents never seen finishing count from their start
+            let since_finish = AGENT_REGISTRY.time_since_finish(entry.key()).unwrap_or_else(|| {
+                let agent = entry.value();
+                (now - agent.finished_at.unwrap_or(agent.started_at)).to_std().unwrap_or_default()
+            });
+            since_finish >= older_than
+        })
+        .map(|entry| *entry.key())
+        .collect();
+
+    for id in &finished {
+        state.agents.remove(id);
+        AGENT_REGISTRY.remove(id);
+        AGENT_ARCHIVE.remove(id);
+    }
+
+    log::info!("Cleaned up {} finished agents", finished.len());
+    Ok(finished.iter().map(Uuid::to_string).collect())
+}
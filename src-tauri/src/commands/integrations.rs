@@ -0,0 +1,271 @@
+use once_cell::sync::OnceCell;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::AppHandle;
+use uuid::Uuid;
+
+use crate::commands::workflow::{find_execution, prepare_batch};
+use crate::integrations::{
+    Issue, IssueFilter, IssueTracker, RegistrySync, SyncConfig, SyncItem, SyncItemKind, SyncReport, SyncStatus,
+    TrackerConfig,
+};
+use crate::workflow::batch;
+use crate::workflow::text;
+use crate::workflow::{BatchHandle, BatchItem, BatchItemRecord, ExecutionStatus};
+
+/// Maximum characters of agent output posted back to an issue
+const MAX_COMMENT_CHARS: usize = 60_000;
+/// How often a running issue batch is checked for items to write back
+const WRITE_BACK_POLL_MS: u64 = 1000;
+
+// =============================================================================
+// Issue Tracker Commands
+// =============================================================================
+
+/// Fetch issues from GitHub or Jira
+#[tauri::command]
+pub async fn fetch_tracker_issues(
+    tracker: TrackerConfig,
+    filter: Option<IssueFilter>,
+) -> Result<Vec<Issue>, String> {
+    IssueTracker::new(tracker)
+        .fetch_issues(&filter.unwrap_or_default())
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Post a comment on an issue
+#[tauri::command]
+pub async fn comment_on_issue(
+    tracker: TrackerConfig,
+    issue_key: String,
+    body: String,
+) -> Result<(), String> {
+    IssueTracker::new(tracker)
+        .add_comment(&issue_key, &body)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// Move an issue to a new state
+#[tauri::command]
+pub async fn transition_issue(
+    tracker: TrackerConfig,
+    issue_key: String,
+    state: String,
+) -> Result<(), String> {
+    IssueTracker::new(tracker)
+        .transition(&issue_key, &state)
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// What to write back to each issue once its execution finishes
+#[derive(Debug, Clone, Deserialize)]
+pub struct IssueWriteBack {
+    /// Comment with the execution result
+    #[serde(default = "default_true")]
+    pub comment: bool,
+    /// State to move the issue to when the execution succeeds
+    pub transition_on_success: Option<String>,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Request to run a workflow once per issue
+#[derive(Debug, Deserialize)]
+pub struct IssueWorkflowRequest {
+    pub tracker: TrackerConfig,
+    /// Issues to run on; fetched with `filter` when omitted
+    pub issues: Option<Vec<Issue>>,
+    pub filter: Option<IssueFilter>,
+    pub workflow_id: String,
+    pub project_id: String,
+    /// Prompt template using `{{issue_title}}`, `{{issue_body}}`, etc.
+    /// Defaults to the issue's title, link and body.
+    pub input_prompt: Option<String>,
+    pub max_concurrent: Option<usize>,
+    pub write_back: Option<IssueWriteBack>,
+}
+
+/// Run a saved workflow for each issue as a batch, optionally writing
+/// results back to the tracker. Returns the batch ID.
+#[tauri::command]
+pub async fn execute_workflow_for_issues(
+    app: AppHandle,
+    request: IssueWorkflowRequest,
+) -> Result<String, String> {
+    let workflow_id = Uuid::parse_str(&request.workflow_id)
+        .map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    let project_id = Uuid::parse_str(&request.project_id)
+        .map_err(|e| format!("Invalid project ID: {}", e))?;
+
+    let tracker = Arc::new(IssueTracker::new(request.tracker));
+    let issues = match request.issues {
+        Some(issues) => issues,
+        None => tracker
+            .fetch_issues(&request.filter.unwrap_or_default())
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+
+    let has_template = request.input_prompt.is_some();
+    let items: Vec<BatchItem> = issues
+        .iter()
+        .map(|issue| {
+            let mut item = issue.to_batch_item();
+            if !has_template {
+                item.input_prompt = Some(issue.default_prompt());
+            }
+            item
+        })
+        .collect();
+
+    let (handle, backend) = prepare_batch(&app, workflow_id, project_id, &items, request.max_concurrent)?;
+    let batch_id = handle.id();
+    let prompt_template = request.input_prompt.unwrap_or_default();
+
+    log::info!("Starting issue batch {} with {} issues", batch_id, items.len());
+    tokio::spawn(async move {
+        let write_back = request
+            .write_back
+            .map(|write_back| tokio::spawn(write_back_results(tracker, handle.clone(), write_back)));
+        batch::run_batch(handle, items, prompt_template, backend).await;
+
+        if let Some(write_back) = write_back {
+            let _ = write_back.await;
+        }
+    });
+
+    Ok(batch_id.to_string())
+}
+
+/// Write back to each issue as soon as its item finishes, until the whole
+/// batch has
+async fn write_back_results(tracker: Arc<IssueTracker>, handle: Arc<BatchHandle>, write_back: IssueWriteBack) {
+    let mut written: HashSet<usize> = HashSet::new();
+    loop {
+        let record = handle.record();
+        for item in record.items {
+            if item.completed_at.is_some() && written.insert(item.index) {
+                write_back_item(&tracker, item, &write_back).await;
+            }
+        }
+        if record.completed_at.is_some() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(WRITE_BACK_POLL_MS)).await;
+    }
+}
+
+/// Comment on and transition an issue according to its item's outcome
+async fn write_back_item(tracker: &IssueTracker, item: BatchItemRecord, write_back: &IssueWriteBack) {
+    let Some(issue_key) = item.key else { return };
+
+    if write_back.comment {
+        let summary = item
+            .execution_id
+            .and_then(|id| execution_result_text(&id))
+            .unwrap_or_default();
+        let body = format!(
+            "**Nexus workflow {}**\n\n{}",
+            format!("{:?}", item.status).to_lowercase(),
+            item.error.unwrap_or(summary)
+        );
+
+        if let Err(e) = tracker.add_comment(&issue_key, &body).await {
+            log::warn!("Failed to comment on issue {}: {}", issue_key, e);
+        }
+    }
+
+    if let (Some(target), ExecutionStatus::Completed) = (&write_back.transition_on_success, item.status) {
+        if let Err(e) = tracker.transition(&issue_key, target).await {
+            log::warn!("Failed to transition issue {}: {}", issue_key, e);
+        }
+    }
+}
+
+/// Outputs of an execution's final level, joined and truncated for posting
+fn execution_result_text(execution_id: &Uuid) -> Option<String> {
+    let (state, context) = find_execution(execution_id)?;
+    let final_level = state.execution_levels.last()?;
+
+    let comment = final_level
+        .iter()
+        .filter_map(|node_id| {
+            context
+                .as_ref()
+                .and_then(|ctx| ctx.get_latest_output(node_id))
+                .map(|output| output.data.to_context_string())
+                .or_else(|| state.get_node_state(node_id).and_then(|ns| ns.output))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n---\n\n");
+
+    Some(text::truncate(&comment, MAX_COMMENT_CHARS, "\n\n_(truncated)_"))
+}
+
+// =============================================================================
+// Registry Sync Commands
+// =============================================================================
+
+// Global library and registry sync
+static REGISTRY_SYNC: OnceCell<RegistrySync> = OnceCell::new();
+
+pub(crate) fn get_registry_sync() -> &'static RegistrySync {
+    REGISTRY_SYNC.get_or_init(|| RegistrySync::new(RegistrySync::default_root()))
+}
+
+/// Set the shared registry and how conflicts are resolved
+#[tauri::command]
+pub async fn configure_sync(config: SyncConfig) -> Result<(), String> {
+    get_registry_sync().configure(&config).map_err(|e| e.to_string())
+}
+
+/// Get the sync configuration, if any
+#[tauri::command]
+pub async fn get_sync_config() -> Result<Option<SyncConfig>, String> {
+    get_registry_sync().config().map_err(|e| e.to_string())
+}
+
+/// Add or update a template, role definition or prompt in the local library
+#[tauri::command]
+pub async fn save_library_item(
+    kind: SyncItemKind,
+    id: String,
+    content: serde_json::Value,
+) -> Result<SyncItem, String> {
+    get_registry_sync()
+        .save_item(kind, &id, content)
+        .map_err(|e| e.to_string())
+}
+
+/// List items in the local library
+#[tauri::command]
+pub async fn list_library_items(kind: Option<SyncItemKind>) -> Result<Vec<SyncItem>, String> {
+    get_registry_sync().list_items(kind).map_err(|e| e.to_string())
+}
+
+/// Push and pull library changes with the shared registry
+#[tauri::command]
+pub async fn sync_library() -> Result<SyncReport, String> {
+    get_registry_sync().sync().await.map_err(|e| e.to_string())
+}
+
+/// Get the last sync result, pending changes and open conflicts
+#[tauri::command]
+pub async fn sync_status() -> Result<SyncStatus, String> {
+    get_registry_sync().status().map_err(|e| e.to_string())
+}
+
+/// Resolve a conflict by keeping the local or the registry copy
+#[tauri::command]
+pub async fn resolve_sync_conflict(key: String, keep_local: bool) -> Result<(), String> {
+    get_registry_sync()
+        .resolve_conflict(&key, keep_local)
+        .map_err(|e| e.to_string())
+}
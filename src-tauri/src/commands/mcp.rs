@@ -2,6 +2,8 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::env;
 
+use crate::settings::SETTINGS;
+
 /// MCP Server configuration
 const DEFAULT_MCP_SERVER_URL: &str = "http://localhost:9999";
 
@@ -15,9 +17,14 @@ pub struct McpToolResponse {
     pub error: Option<String>,
 }
 
-/// Get the MCP server URL from environment or use default
+/// Get the MCP server URL from settings or environment, or use default
 fn get_mcp_server_url() -> String {
-    env::var("MCP_SERVER_URL").unwrap_or_else(|_| DEFAULT_MCP_SERVER_URL.to_string())
+    SETTINGS
+        .get()
+        .backends
+        .mcp_server_url
+        .or_else(|| env::var("MCP_SERVER_URL").ok())
+        .unwrap_or_else(|| DEFAULT_MCP_SERVER_URL.to_string())
 }
 
 /// Call an MCP tool by name with arguments
@@ -1,5 +1,7 @@
 pub mod agent;
+pub mod integrations;
 pub mod mcp;
 pub mod project;
+pub mod settings;
 pub mod system;
 pub mod workflow;
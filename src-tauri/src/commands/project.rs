@@ -1,4 +1,10 @@
+use crate::process::egress::{NetworkPolicy, NETWORK_POLICIES};
+use crate::project::relocation::{self, PathStyle, RelocateMode, Relocation};
 use crate::project::workspace;
+use crate::project::{discovery, packages, DependencyGraph, ImpactedFile, Package, ProjectCandidate, ISOLATED_WORKSPACES};
+use crate::settings::{ItemKind, SETTINGS, USER_STATE};
+use crate::workflow::{conflicts, locale};
+use crate::workflow::patch::parse_unified_diff;
 use crate::state::AppState;
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
@@ -14,12 +20,19 @@ lazy_static::lazy_static! {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject), graphql(complex))]
 pub struct Project {
     pub id: Uuid,
     pub name: String,
     pub description: Option<String>,
     pub status: String,
     pub working_directory: String,
+    /// Language agents answer in and templates are listed in, e.g. `de`
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Main languages, most used first; set for imported repositories
+    #[serde(default)]
+    pub languages: Vec<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -33,6 +46,8 @@ pub struct CreateProjectRequest {
     /// Whether to initialize the project structure (README, .gitignore, etc.)
     #[serde(default = "default_true")]
     pub init_structure: bool,
+    /// Locale tag such as `de` or `pt-BR`; English when not set
+    pub locale: Option<String>,
 }
 
 fn default_true() -> bool {
@@ -44,6 +59,8 @@ pub struct UpdateProjectRequest {
     pub name: Option<String>,
     pub description: Option<String>,
     pub status: Option<String>,
+    /// New locale tag; an empty string goes back to English
+    pub locale: Option<String>,
 }
 
 #[derive(Debug, Serialize)]
@@ -54,6 +71,8 @@ pub struct ProjectResponse {
     pub description: Option<String>,
     pub status: String,
     pub working_directory: String,
+    pub locale: Option<String>,
+    pub languages: Vec<String>,
     pub created_at: String,
     pub updated_at: String,
 }
@@ -66,12 +85,19 @@ impl From<&Project> for ProjectResponse {
             description: p.description.clone(),
             status: p.status.clone(),
             working_directory: p.working_directory.clone(),
+            locale: p.locale.clone(),
+            languages: p.languages.clone(),
             created_at: p.created_at.to_rfc3339(),
             updated_at: p.updated_at.to_rfc3339(),
         }
     }
 }
 
+/// Projects in the in-memory store
+pub(crate) fn all_projects() -> Vec<Project> {
+    PROJECTS.iter().map(|entry| entry.value().clone()).collect()
+}
+
 /// Validate project name
 fn validate_project_name(name: &str) -> Result<(), String> {
     if name.is_empty() {
@@ -137,13 +163,14 @@ fn validate_status(status: &Option<String>) -> Result<(), String> {
 
 #[tauri::command]
 pub async fn create_project(
-    _state: State<'_, Arc<AppState>>,
+    state: State<'_, Arc<AppState>>,
     request: CreateProjectRequest,
 ) -> Result<ProjectResponse, String> {
     // Validate inputs
     validate_project_name(&request.name)?;
     validate_description(&request.description)?;
     validate_working_directory(&request.working_directory)?;
+    let locale = request.locale.as_deref().map(locale::normalize).transpose()?;
 
     // Create workspace directory
     let custom_path = request.working_directory.as_ref().map(PathBuf::from);
@@ -166,6 +193,8 @@ pub async fn create_project(
         description: request.description,
         status: "active".to_string(),
         working_directory: workspace.path.to_string_lossy().to_string(),
+        locale,
+        languages: Vec::new(),
         created_at: now,
         updated_at: now,
     };
@@ -177,6 +206,15 @@ pub async fn create_project(
     );
 
     let response = ProjectResponse::from(&project);
+    #[cfg(feature = "database")]
+    crate::db::write_queue::submit_in_background(
+        state.get_pool(),
+        crate::db::write_queue::PendingWrite::InsertProject {
+            project: crate::db::health::project_row(&project),
+        },
+    );
+    #[cfg(not(feature = "database"))]
+    let _ = state;
     PROJECTS.insert(project.id, project);
 
     Ok(response)
@@ -220,6 +258,11 @@ pub async fn update_project(
         validate_description(&request.description)?;
     }
     validate_status(&request.status)?;
+    let new_locale = match request.locale.as_deref().map(str::trim) {
+        Some("") => Some(None),
+        Some(tag) => Some(Some(locale::normalize(tag)?)),
+        None => None,
+    };
 
     let id = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
 
@@ -234,6 +277,9 @@ pub async fn update_project(
         if let Some(status) = request.status {
             project.status = status.to_lowercase();
         }
+        if let Some(locale) = new_locale {
+            project.locale = locale;
+        }
         project.updated_at = Utc::now();
         Ok(ProjectResponse::from(&*project))
     } else {
@@ -249,6 +295,7 @@ pub async fn delete_project(
     let id = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
 
     if PROJECTS.remove(&id).is_some() {
+        USER_STATE.forget(ItemKind::Project, &project_id);
         Ok(())
     } else {
         Err("Project not found".to_string())
@@ -264,9 +311,280 @@ pub async fn get_projects_base_directory() -> Result<String, String> {
     Ok(base_dir.to_string_lossy().to_string())
 }
 
+/// What changing the base directory did
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaseDirectoryChange {
+    pub base_directory: String,
+    /// Projects that moved along
+    pub relocated: Vec<Relocation>,
+    /// Git worktrees that couldn't be reconnected
+    pub warnings: Vec<String>,
+}
+
+/// Check a path could be the projects base directory
+#[tauri::command]
+pub async fn validate_projects_base_directory(path: String) -> Result<String, String> {
+    relocation::check_base_dir(&path, PathStyle::native()).map(|path| path.to_string_lossy().to_string())
+}
+
+/// Change the directory new projects are created in. With `relocate`, the
+/// projects inside the old one are moved along, or re-pointed when their
+/// files were moved already; either all of them or none are.
+#[tauri::command]
+pub async fn set_projects_base_directory(
+    state: State<'_, Arc<AppState>>,
+    path: String,
+    relocate: Option<RelocateMode>,
+) -> Result<BaseDirectoryChange, String> {
+    let style = PathStyle::native();
+    let new_base = relocation::check_base_dir(&path, style)?;
+    let old_base = workspace::get_projects_base_dir()
+        .map_err(|e| format!("Failed to get projects directory: {}", e))?;
+
+    let plan = match relocate {
+        Some(mode) => {
+            let projects: Vec<(Uuid, String)> = PROJECTS
+                .iter()
+                .map(|entry| (entry.id, entry.working_directory.clone()))
+                .collect();
+            let plan = relocation::plan(&projects, &old_base, &new_base, style);
+            relocation::check_plan(&plan, mode, style)?;
+            if mode == RelocateMode::Move {
+                relocation::check_idle(&plan, &active_work(&state))?;
+            }
+            plan
+        }
+        None => Vec::new(),
+    };
+
+    let moved = relocate == Some(RelocateMode::Move);
+    std::fs::create_dir_all(&new_base).map_err(|e| format!("Failed to create {}: {}", new_base.display(), e))?;
+    if moved {
+        let plan = plan.clone();
+        tokio::task::spawn_blocking(move || relocation::move_dirs(&plan))
+            .await
+            .map_err(|e| format!("Moving projects panicked: {}", e))??;
+    }
+
+    let base_directory = new_base.to_string_lossy().to_string();
+    if let Err(e) = SETTINGS.update(&serde_json::json!({ "projects": { "base_directory": base_directory } })) {
+        if moved {
+            relocation::undo_moves(&plan);
+        }
+        return Err(e.to_string());
+    }
+
+    // Stored paths change only once the files are in place
+    let now = Utc::now();
+    for relocation in &plan {
+        if let Some(mut project) = PROJECTS.get_mut(&relocation.project_id) {
+            project.working_directory = relocation.to.to_string_lossy().to_string();
+            project.updated_at = now;
+        }
+        ISOLATED_WORKSPACES.repoint_project(&relocation.from, &relocation.to);
+        #[cfg(feature = "database")]
+        crate::db::write_queue::submit_in_background(
+            state.get_pool(),
+            crate::db::write_queue::PendingWrite::UpdateProjectDirectory {
+                project_id: relocation.project_id,
+                working_directory: relocation.to.to_string_lossy().to_string(),
+                updated_at: now,
+            },
+        );
+    }
+    log::info!("Projects base directory is now {} ({} project(s) relocated)", base_directory, plan.len());
+
+    let warnings = if plan.is_empty() {
+        Vec::new()
+    } else {
+        let plan = plan.clone();
+        tokio::task::spawn_blocking(move || relocation::repair_worktrees(&plan, style))
+            .await
+            .map_err(|e| format!("Repairing worktrees panicked: {}", e))?
+    };
+    Ok(BaseDirectoryChange {
+        base_directory,
+        relocated: plan,
+        warnings,
+    })
+}
+
+/// Executions and agents still running, with the project each works in
+fn active_work(state: &AppState) -> Vec<(Uuid, String)> {
+    let executions = crate::commands::workflow::active_executions()
+        .into_iter()
+        .map(|execution| (execution.project_id, format!("execution {}", execution.execution_id)));
+    let agents = state
+        .agents
+        .iter()
+        .filter(|agent| !agent.status.is_terminal())
+        .filter_map(|agent| Some((agent.project_id?, format!("agent {} ({})", agent.name, agent.id))));
+    executions.chain(agents).collect()
+}
+
+/// A repository to import as a project
+#[derive(Debug, Deserialize)]
+pub struct ProjectImport {
+    pub path: String,
+    /// The name inferred from the repository when not set
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+/// A repository that wasn't imported, and why
+#[derive(Debug, Serialize)]
+pub struct SkippedImport {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportResult {
+    pub imported: Vec<ProjectResponse>,
+    pub skipped: Vec<SkippedImport>,
+}
+
+/// Git repositories under `root_path` that could be imported as projects
+#[tauri::command]
+pub async fn scan_for_projects(root_path: String, max_depth: Option<usize>) -> Result<Vec<ProjectCandidate>, String> {
+    let known: Vec<String> = PROJECTS.iter().map(|entry| entry.working_directory.clone()).collect();
+    tokio::task::spawn_blocking(move || {
+        discovery::scan(&PathBuf::from(root_path), max_depth, &known, PathStyle::native())
+    })
+    .await
+    .map_err(|e| format!("Project scan panicked: {}", e))?
+}
+
+/// Create a project for each repository, named and tagged with languages
+/// from its contents. Repositories that are projects already, or aren't
+/// directories, are skipped.
+#[tauri::command]
+pub async fn import_projects(projects: Vec<ProjectImport>) -> Result<ImportResult, String> {
+    let style = PathStyle::native();
+    tokio::task::spawn_blocking(move || {
+        let mut result = ImportResult {
+            imported: Vec::new(),
+            skipped: Vec::new(),
+        };
+        for import in projects {
+            let dir = PathBuf::from(&import.path);
+            let skip = if !dir.is_dir() {
+                Some("Not a directory".to_string())
+            } else if PROJECTS.iter().any(|entry| style.same(&entry.working_directory, &import.path)) {
+                Some("Already a project".to_string())
+            } else {
+                None
+            };
+            let name = import.name.clone().unwrap_or_else(|| discovery::infer_name(&dir));
+            let skip = skip.or_else(|| validate_project_name(&name).err()).or_else(|| validate_description(&import.description).err());
+            if let Some(reason) = skip {
+                result.skipped.push(SkippedImport { path: import.path, reason });
+                continue;
+            }
+
+            let now = Utc::now();
+            let project = Project {
+                id: Uuid::new_v4(),
+                name,
+                description: import.description,
+                status: "active".to_string(),
+                working_directory: import.path,
+                locale: None,
+                languages: discovery::detect_languages(&dir),
+                created_at: now,
+                updated_at: now,
+            };
+            log::info!("Imported project '{}' from {}", project.name, project.working_directory);
+            result.imported.push(ProjectResponse::from(&project));
+            PROJECTS.insert(project.id, project);
+        }
+        result
+    })
+    .await
+    .map_err(|e| format!("Project import panicked: {}", e))
+}
+
+/// The import graph of a project's source files
+#[tauri::command]
+pub async fn analyze_project_dependencies(project_id: String) -> Result<DependencyGraph, String> {
+    let root = project_root(&project_id)?;
+    tokio::task::spawn_blocking(move || DependencyGraph::build(&root))
+        .await
+        .map_err(|e| format!("Dependency scan panicked: {}", e))?
+        .map_err(|e| format!("Failed to scan project: {}", e))
+}
+
+/// Files impacted by a change: the files `diff` touches, or the project's
+/// uncommitted changes without one, plus every file depending on them
+#[tauri::command]
+pub async fn get_impacted_files(
+    project_id: String,
+    diff: Option<String>,
+    max_depth: Option<usize>,
+) -> Result<Vec<ImpactedFile>, String> {
+    let root = project_root(&project_id)?;
+    let changed: Vec<String> = match diff {
+        Some(diff) => parse_unified_diff(&diff)?
+            .into_iter()
+            .flat_map(|file| [file.old_path, file.new_path])
+            .flatten()
+            .collect(),
+        None => conflicts::snapshot(&root).await?.into_keys().collect(),
+    };
+
+    let graph = tokio::task::spawn_blocking(move || DependencyGraph::build(&root))
+        .await
+        .map_err(|e| format!("Dependency scan panicked: {}", e))?
+        .map_err(|e| format!("Failed to scan project: {}", e))?;
+    Ok(graph.impacted(&changed, max_depth))
+}
+
+/// The packages of a project's Cargo, npm/pnpm/yarn or Go workspaces
+#[tauri::command]
+pub async fn detect_project_packages(project_id: String) -> Result<Vec<Package>, String> {
+    let root = project_root(&project_id)?;
+    packages::detect_packages(&root)
+}
+
+/// Replace a project's network policy, recording the change in the audit
+/// log. Agents started afterwards go through its proxy.
+#[tauri::command]
+pub async fn set_network_policy(project_id: String, policy: NetworkPolicy) -> Result<NetworkPolicy, String> {
+    let id = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    if get_project_name(&id).is_none() {
+        return Err("Project not found".to_string());
+    }
+    NETWORK_POLICIES.set(id, policy)
+}
+
+/// A project's network policy; open if none was set
+#[tauri::command]
+pub async fn get_network_policy(project_id: String) -> Result<NetworkPolicy, String> {
+    let id = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    Ok(NETWORK_POLICIES.get(&id))
+}
+
+pub(crate) fn project_root(project_id: &str) -> Result<PathBuf, String> {
+    let id = Uuid::parse_str(project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    get_project_working_directory(&id)
+        .map(PathBuf::from)
+        .ok_or("Project not found".to_string())
+}
+
 /// Get a project's working directory by ID
 pub fn get_project_working_directory(project_id: &Uuid) -> Option<String> {
     PROJECTS
         .get(project_id)
         .map(|entry| entry.value().working_directory.clone())
 }
+
+/// Get a project's name by ID
+pub fn get_project_name(project_id: &Uuid) -> Option<String> {
+    PROJECTS.get(project_id).map(|entry| entry.value().name.clone())
+}
+
+/// Get a project's locale by ID, when it has one
+pub fn get_project_locale(project_id: &Uuid) -> Option<String> {
+    PROJECTS.get(project_id).and_then(|entry| entry.value().locale.clone())
+}
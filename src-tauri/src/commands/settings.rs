@@ -0,0 +1,81 @@
+use serde_json::Value;
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter, State};
+
+use crate::api::server;
+use crate::settings::{Favorite, ItemKind, RecentItem, Settings, SuggestedItem, SETTINGS, USER_STATE};
+use crate::state::AppState;
+
+/// Get the current settings, with secrets redacted
+#[tauri::command]
+pub async fn get_settings() -> Result<Settings, String> {
+    Ok(SETTINGS.get().redacted())
+}
+
+/// Apply a partial settings update and save it. Returns the new settings
+/// (redacted) and emits `settings-changed` with them.
+///
+/// Changing the API settings restarts the API server; resource defaults
+/// take effect on the next start.
+#[tauri::command]
+pub async fn update_settings(app: AppHandle, state: State<'_, Arc<AppState>>, patch: Value) -> Result<Settings, String> {
+    let previous_api = SETTINGS.get().api;
+    let settings = SETTINGS.update(&patch).map_err(|e| e.to_string())?.redacted();
+    let _ = app.emit("settings-changed", &settings);
+    if settings.api != previous_api {
+        server::restart_api_server(app, state.inner().clone()).await;
+    }
+    Ok(settings)
+}
+
+/// Recently used items, most recent first
+#[tauri::command]
+pub async fn list_recent_items(kind: Option<ItemKind>, limit: Option<usize>) -> Result<Vec<RecentItem>, String> {
+    Ok(USER_STATE.recent(kind, limit))
+}
+
+/// Record that an item was used, e.g. a project opened
+#[tauri::command]
+pub async fn record_recent_item(kind: ItemKind, id: String) -> Result<(), String> {
+    USER_STATE.record_use(kind, &id);
+    Ok(())
+}
+
+/// Clear recent items, of one kind or all
+#[tauri::command]
+pub async fn clear_recent_items(kind: Option<ItemKind>) -> Result<(), String> {
+    USER_STATE.clear_recent(kind);
+    Ok(())
+}
+
+/// Favorites in the user's order
+#[tauri::command]
+pub async fn list_favorites(kind: Option<ItemKind>) -> Result<Vec<Favorite>, String> {
+    Ok(USER_STATE.favorites(kind))
+}
+
+/// Pin an item as a favorite; false if it was pinned already
+#[tauri::command]
+pub async fn pin_favorite(kind: ItemKind, id: String) -> Result<bool, String> {
+    Ok(USER_STATE.pin(kind, &id))
+}
+
+/// Unpin a favorite; false if it wasn't pinned
+#[tauri::command]
+pub async fn unpin_favorite(kind: ItemKind, id: String) -> Result<bool, String> {
+    Ok(USER_STATE.unpin(kind, &id))
+}
+
+/// Put the favorites of a kind in the order of `ids`
+#[tauri::command]
+pub async fn reorder_favorites(kind: ItemKind, ids: Vec<String>) -> Result<Vec<Favorite>, String> {
+    USER_STATE.reorder_favorites(kind, &ids)?;
+    Ok(USER_STATE.favorites(Some(kind)))
+}
+
+/// Items to list first in a picker: favorites, then frequently and
+/// recently used ones
+#[tauri::command]
+pub async fn get_suggested_items(kind: ItemKind, limit: Option<usize>) -> Result<Vec<SuggestedItem>, String> {
+    Ok(USER_STATE.suggested(kind, limit.unwrap_or(10)))
+}
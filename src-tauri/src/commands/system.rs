@@ -1,7 +1,35 @@
+use crate::api::server::{self, ApiServerInfo};
+use crate::commands::workflow::{
+    active_executions, agent_executions, cancel_all_batches, checkpoint_execution, clear_resource_queue,
+};
+use crate::process::spawner::find_claude_path;
+use crate::process::registry::SpawnLock;
+use crate::process::AGENT_REGISTRY;
+use crate::settings::{SettingsStore, SETTINGS};
 use crate::state::AppState;
+use crate::workflow::history::PersistentHistoryStore;
+use crate::workflow::{CheckpointManager, PluginRegistry};
+use chrono::{DateTime, Utc};
 use serde::Serialize;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tauri::State;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, State};
+use tokio::process::Command;
+use uuid::Uuid;
+
+/// How long an external tool may take to report its version
+const VERSION_TIMEOUT_SECS: u64 = 10;
+
+/// How long agents get to exit after SIGTERM on shutdown
+const SHUTDOWN_GRACE_SECS: u64 = 5;
+
+/// Default reason recorded on executions cancelled by shutdown
+const SHUTDOWN_REASON: &str = "App shutdown";
+
+/// Default reason recorded by an emergency stop
+const EMERGENCY_STOP_REASON: &str = "Emergency stop";
 
 #[derive(Debug, Serialize)]
 pub struct SystemStatus {
@@ -16,6 +44,8 @@ pub struct DatabaseStatus {
     pub connected: bool,
     pub pool_size: Option<u32>,
     pub idle_connections: Option<u32>,
+    /// Writes waiting for the connection to come back
+    pub pending_writes: usize,
 }
 
 static START_TIME: std::sync::OnceLock<std::time::Instant> = std::sync::OnceLock::new();
@@ -32,7 +62,7 @@ pub async fn get_system_status(state: State<'_, Arc<AppState>>) -> Result<System
     Ok(SystemStatus {
         version: env!("CARGO_PKG_VERSION").to_string(),
         active_agents: state.agents.len(),
-        database_connected: state.has_db(),
+        database_connected: database_connected(&state),
         uptime_seconds: get_uptime(),
     })
 }
@@ -42,8 +72,511 @@ pub async fn get_database_status(
     state: State<'_, Arc<AppState>>,
 ) -> Result<DatabaseStatus, String> {
     Ok(DatabaseStatus {
-        connected: state.has_db(),
+        connected: database_connected(&state),
         pool_size: None,
         idle_connections: None,
+        pending_writes: pending_writes(),
+    })
+}
+
+/// Whether the database is connected and answering health checks
+fn database_connected(state: &AppState) -> bool {
+    #[cfg(feature = "database")]
+    {
+        crate::db::health::is_connected(state)
+    }
+    #[cfg(not(feature = "database"))]
+    {
+        state.has_db()
+    }
+}
+
+fn pending_writes() -> usize {
+    #[cfg(feature = "database")]
+    {
+        crate::db::write_queue::WRITE_QUEUE.len()
+    }
+    #[cfg(not(feature = "database"))]
+    {
+        0
+    }
+}
+
+/// Replay database writes queued while the connection was down, without
+/// waiting for the next periodic attempt. Returns how many are still queued.
+#[tauri::command]
+pub async fn replay_database_writes(state: State<'_, Arc<AppState>>) -> Result<usize, String> {
+    #[cfg(feature = "database")]
+    {
+        let pool = state.get_pool().ok_or("Not connected to a database")?;
+        Ok(crate::db::write_queue::WRITE_QUEUE.replay(&pool).await.remaining)
+    }
+
+    #[cfg(not(feature = "database"))]
+    {
+        let _ = state;
+        Err("Built without database support".to_string())
+    }
+}
+
+// =============================================================================
+// Diagnostics
+// =============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DiagnosticStatus {
+    Pass,
+    /// Works, but something is missing or degraded
+    Warn,
+    /// Something NEXUS needs is broken
+    Fail,
+}
+
+/// Result of one environment check
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticCheck {
+    pub id: String,
+    pub name: String,
+    pub status: DiagnosticStatus,
+    pub message: String,
+    /// What to do about a warning or failure
+    pub hint: Option<String>,
+}
+
+impl DiagnosticCheck {
+    fn new(id: &str, name: &str, status: DiagnosticStatus, message: impl Into<String>) -> Self {
+        Self {
+            id: id.to_string(),
+            name: name.to_string(),
+            status,
+            message: message.into(),
+            hint: None,
+        }
+    }
+
+    fn hint(mut self, hint: &str) -> Self {
+        self.hint = Some(hint.to_string());
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosticsReport {
+    pub checks: Vec<DiagnosticCheck>,
+    /// The worst status of any check
+    pub status: DiagnosticStatus,
+    pub ran_at: DateTime<Utc>,
+}
+
+/// Check the environment NEXUS depends on, for onboarding and troubleshooting
+#[tauri::command]
+pub async fn run_diagnostics(state: State<'_, Arc<AppState>>) -> Result<DiagnosticsReport, String> {
+    let mut checks = vec![check_claude_cli().await, check_api_key(), check_git().await];
+    checks.push(check_database(&state).await);
+    checks.extend(check_data_dirs());
+    checks.push(check_api_port().await);
+
+    let status = checks
+        .iter()
+        .map(|c| c.status)
+        .max_by_key(|status| match status {
+            DiagnosticStatus::Pass => 0,
+            DiagnosticStatus::Warn => 1,
+            DiagnosticStatus::Fail => 2,
+        })
+        .unwrap_or(DiagnosticStatus::Pass);
+
+    Ok(DiagnosticsReport {
+        checks,
+        status,
+        ran_at: Utc::now(),
     })
 }
+
+/// First line of `<program> --version`, or why it couldn't be run
+pub(crate) async fn tool_version(program: &str) -> Result<String, String> {
+    let output = tokio::time::timeout(
+        Duration::from_secs(VERSION_TIMEOUT_SECS),
+        Command::new(program).arg("--version").kill_on_drop(true).output(),
+    )
+    .await
+    .map_err(|_| format!("timed out after {}s", VERSION_TIMEOUT_SECS))?
+    .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).lines().next().unwrap_or_default().trim().to_string())
+}
+
+async fn check_claude_cli() -> DiagnosticCheck {
+    let name = "Claude CLI";
+    let Some(path) = find_claude_path() else {
+        return DiagnosticCheck::new("claude_cli", name, DiagnosticStatus::Fail, "Not found")
+            .hint("Install with: npm install -g @anthropic-ai/claude-code");
+    };
+
+    match tool_version(&path).await {
+        Ok(version) => DiagnosticCheck::new("claude_cli", name, DiagnosticStatus::Pass, format!("{} ({})", version, path)),
+        Err(e) => DiagnosticCheck::new("claude_cli", name, DiagnosticStatus::Fail, format!("{} does not run: {}", path, e))
+            .hint("Reinstall the Claude CLI"),
+    }
+}
+
+fn check_api_key() -> DiagnosticCheck {
+    let name = "Anthropic API key";
+    if SETTINGS.get().backends.anthropic_api_key.is_some() {
+        DiagnosticCheck::new("anthropic_api_key", name, DiagnosticStatus::Pass, "Set in settings")
+    } else if std::env::var("ANTHROPIC_API_KEY").is_ok() {
+        DiagnosticCheck::new("anthropic_api_key", name, DiagnosticStatus::Pass, "Set by ANTHROPIC_API_KEY")
+    } else {
+        DiagnosticCheck::new("anthropic_api_key", name, DiagnosticStatus::Warn, "Not set; agents use the Claude CLI login")
+            .hint("Run `claude` once to log in, or add an API key in settings")
+    }
+}
+
+async fn check_git() -> DiagnosticCheck {
+    match tool_version("git").await {
+        Ok(version) => DiagnosticCheck::new("git", "git", DiagnosticStatus::Pass, version),
+        Err(e) => DiagnosticCheck::new("git", "git", DiagnosticStatus::Fail, format!("Not available: {}", e))
+            .hint("Install git; conflict detection and library sync need it"),
+    }
+}
+
+async fn check_database(state: &AppState) -> DiagnosticCheck {
+    let name = "Database";
+
+    #[cfg(feature = "database")]
+    {
+        match state.get_pool() {
+            Some(pool) => match sqlx::query("SELECT 1").execute(&pool).await {
+                Ok(_) => DiagnosticCheck::new("database", name, DiagnosticStatus::Pass, "Connected"),
+                Err(e) => DiagnosticCheck::new("database", name, DiagnosticStatus::Fail, format!("Query failed: {}", e))
+                    .hint("Check that PostgreSQL is running"),
+            },
+            None if std::env::var("DATABASE_URL").is_ok() => {
+                DiagnosticCheck::new("database", name, DiagnosticStatus::Fail, "Not connected; retrying in the background")
+                    .hint("Check DATABASE_URL and that PostgreSQL is running")
+            }
+            None => DiagnosticCheck::new("database", name, DiagnosticStatus::Warn, "DATABASE_URL not set; running offline")
+                .hint("Set DATABASE_URL to keep projects and agents across restarts"),
+        }
+    }
+
+    #[cfg(not(feature = "database"))]
+    {
+        let _ = state;
+        DiagnosticCheck::new("database", name, DiagnosticStatus::Warn, "Built without database support; running offline")
+    }
+}
+
+fn check_data_dirs() -> Vec<DiagnosticCheck> {
+    let settings_path = SettingsStore::default_path();
+    let dirs = [
+        ("settings", settings_path.parent().unwrap_or(Path::new(".")).to_path_buf()),
+        ("checkpoints", CheckpointManager::default_checkpoint_dir()),
+        ("history", PersistentHistoryStore::default_store_dir()),
+        ("plugins", PluginRegistry::default_plugin_dir()),
+    ];
+
+    dirs.iter()
+        .map(|(label, dir)| {
+            let id = format!("data_dir_{}", label);
+            let name = format!("Data directory ({})", label);
+            match probe_writable(dir) {
+                Ok(()) => DiagnosticCheck::new(&id, &name, DiagnosticStatus::Pass, dir.display().to_string()),
+                Err(e) => DiagnosticCheck::new(&id, &name, DiagnosticStatus::Fail, format!("{} is not writable: {}", dir.display(), e))
+                    .hint("Fix the directory's permissions or free up disk space"),
+            }
+        })
+        .collect()
+}
+
+/// Create `dir` if needed and write and remove a probe file in it
+fn probe_writable(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(format!(".nexus-probe-{}", uuid::Uuid::new_v4()));
+    std::fs::write(&probe, b"ok")?;
+    std::fs::remove_file(probe)
+}
+
+async fn check_api_port() -> DiagnosticCheck {
+    let name = "API server port";
+    let api = SETTINGS.get().api;
+    let preferred = server::configured_port();
+
+    if let Some(error) = server::info().error {
+        return DiagnosticCheck::new("api_port", name, DiagnosticStatus::Fail, error)
+            .hint("Choose another port in settings; the server restarts when it changes");
+    }
+    if let Some(port) = server::bound_port() {
+        let check = DiagnosticCheck::new("api_port", name, DiagnosticStatus::Pass, format!("Listening on port {}", port));
+        return if port == preferred {
+            check
+        } else {
+            DiagnosticCheck {
+                status: DiagnosticStatus::Warn,
+                message: format!("Listening on port {} because {} was taken", port, preferred),
+                ..check
+            }
+            .hint("Point OpenDeck at the new port, or free the configured one")
+        };
+    }
+    if !api.enabled {
+        return DiagnosticCheck::new("api_port", name, DiagnosticStatus::Pass, "API server disabled in settings");
+    }
+
+    match server::find_available_port(preferred).await {
+        Some(port) if port == preferred => {
+            DiagnosticCheck::new("api_port", name, DiagnosticStatus::Pass, format!("Port {} is available", port))
+        }
+        Some(port) => DiagnosticCheck::new(
+            "api_port",
+            name,
+            DiagnosticStatus::Warn,
+            format!("Port {} is taken; the API server would use {}", preferred, port),
+        )
+        .hint("Choose another port in settings"),
+        None => DiagnosticCheck::new("api_port", name, DiagnosticStatus::Fail, format!("No free port from {}", preferred))
+            .hint("Choose another port in settings"),
+    }
+}
+
+// =============================================================================
+// Shutdown
+// =============================================================================
+
+/// Set once running executions have been drained and the app may exit
+static DRAINED: AtomicBool = AtomicBool::new(false);
+/// Set while a drain is in progress
+static DRAINING: AtomicBool = AtomicBool::new(false);
+
+/// An execution that would be interrupted by quitting
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveExecution {
+    pub execution_id: String,
+    pub workflow_id: String,
+    pub progress: u8,
+}
+
+/// What draining did before exit
+#[derive(Debug, Clone, Serialize)]
+pub struct ShutdownReport {
+    pub cancelled_executions: Vec<String>,
+    pub checkpoints_saved: usize,
+    pub agents_stopped: usize,
+    /// Standalone agents that will be reattached on the next launch
+    pub agents_detached: usize,
+}
+
+fn active_summaries() -> Vec<ActiveExecution> {
+    active_executions()
+        .iter()
+        .map(|state| ActiveExecution {
+            execution_id: state.execution_id.to_string(),
+            workflow_id: state.workflow_id.to_string(),
+            progress: state.get_overall_progress(),
+        })
+        .collect()
+}
+
+/// Called when the window closes or the app is asked to exit. Returns
+/// whether to let it; otherwise emits `shutdown-requested` with the running
+/// executions so the UI can ask before calling `shutdown_app`.
+pub fn allow_exit(app: &AppHandle) -> bool {
+    if DRAINED.load(Ordering::SeqCst) {
+        return true;
+    }
+
+    let active = active_summaries();
+    if active.is_empty() && !DRAINING.load(Ordering::SeqCst) {
+        return true;
+    }
+
+    let _ = app.emit("shutdown-requested", &active);
+    false
+}
+
+/// Executions that quitting now would interrupt
+#[tauri::command]
+pub async fn get_active_executions() -> Result<Vec<ActiveExecution>, String> {
+    Ok(active_summaries())
+}
+
+/// Drain running executions and exit: cancel each with a reason, checkpoint
+/// it so it can be resumed, stop its agents, then quit. Emits
+/// `shutdown-progress` as it goes.
+#[tauri::command]
+pub async fn shutdown_app(app: AppHandle, reason: Option<String>) -> Result<ShutdownReport, String> {
+    if DRAINING.swap(true, Ordering::SeqCst) {
+        return Err("Shutdown already in progress".to_string());
+    }
+    let reason = reason.unwrap_or_else(|| SHUTDOWN_REASON.to_string());
+    let active = active_executions();
+
+    let _ = app.emit("shutdown-progress", format!("Checkpointing {} execution(s)", active.len()));
+    let mut checkpoints_saved = 0;
+    for state in &active {
+        state.cancel_with_reason(&reason);
+        match checkpoint_execution(&state.execution_id) {
+            Some(Ok(_)) => checkpoints_saved += 1,
+            Some(Err(e)) => log::warn!("Could not checkpoint execution {}: {}", state.execution_id, e),
+            None => {}
+        }
+    }
+
+    // Agents outside workflows resume their sessions on the next launch;
+    // workflow agents come back when their execution is resumed
+    let workflow_agents = agent_executions();
+    let detached: Vec<Uuid> = AGENT_REGISTRY
+        .list_agents()
+        .into_iter()
+        .filter(|id| !workflow_agents.contains_key(id) && AGENT_REGISTRY.is_running(id))
+        .collect();
+    for id in &detached {
+        AGENT_REGISTRY.detach(*id);
+    }
+
+    let _ = app.emit("shutdown-progress", "Stopping agents");
+    let stopped = tokio::task::spawn_blocking(|| {
+        AGENT_REGISTRY.terminate_all(Duration::from_secs(SHUTDOWN_GRACE_SECS))
+    })
+    .await
+    .map_err(|e| format!("Failed to stop agents: {}", e))?;
+
+    let report = ShutdownReport {
+        cancelled_executions: active.iter().map(|s| s.execution_id.to_string()).collect(),
+        checkpoints_saved,
+        agents_stopped: stopped.len(),
+        agents_detached: detached.len(),
+    };
+    log::info!(
+        "Shutdown drained {} execution(s), saved {} checkpoint(s), stopped {} agent(s) ({} detached)",
+        report.cancelled_executions.len(),
+        report.checkpoints_saved,
+        report.agents_stopped,
+        report.agents_detached
+    );
+
+    DRAINED.store(true, Ordering::SeqCst);
+    let _ = app.emit("shutdown-progress", "Exiting");
+    app.exit(0);
+    Ok(report)
+}
+
+// =============================================================================
+// Emergency Stop
+// =============================================================================
+
+/// What an emergency stop did
+#[derive(Debug, Clone, Serialize)]
+pub struct EmergencyStopReport {
+    /// Spawning stays disabled until `resume_spawning` gets its code
+    pub lock: SpawnLock,
+    pub agents_stopped: usize,
+    pub cancelled_executions: Vec<String>,
+    pub cancelled_batches: usize,
+    pub cleared_queued_tasks: usize,
+    pub checkpoints_saved: usize,
+}
+
+/// Stop everything now: disable spawning, cancel executions, batches and
+/// queued tasks, kill every agent without a grace period and checkpoint the
+/// cancelled executions. Spawning stays off until `resume_spawning`.
+#[tauri::command]
+pub async fn emergency_stop(app: AppHandle, reason: Option<String>) -> Result<EmergencyStopReport, String> {
+    let reason = reason.unwrap_or_else(|| EMERGENCY_STOP_REASON.to_string());
+    log::warn!("Emergency stop: {}", reason);
+    let lock = AGENT_REGISTRY.lock_spawning(&reason);
+
+    let active = active_executions();
+    for state in &active {
+        state.cancel_with_reason(&reason);
+    }
+    let cancelled_batches = cancel_all_batches();
+    let cleared_queued_tasks = clear_resource_queue();
+
+    let stopped = tokio::task::spawn_blocking(|| AGENT_REGISTRY.terminate_all(Duration::ZERO))
+        .await
+        .map_err(|e| format!("Failed to stop agents: {}", e))?;
+
+    let mut checkpoints_saved = 0;
+    for state in &active {
+        match checkpoint_execution(&state.execution_id) {
+            Some(Ok(_)) => checkpoints_saved += 1,
+            Some(Err(e)) => log::warn!("Could not checkpoint execution {}: {}", state.execution_id, e),
+            None => {}
+        }
+    }
+
+    let report = EmergencyStopReport {
+        lock,
+        agents_stopped: stopped.len(),
+        cancelled_executions: active.iter().map(|s| s.execution_id.to_string()).collect(),
+        cancelled_batches,
+        cleared_queued_tasks,
+        checkpoints_saved,
+    };
+    log::warn!(
+        "Emergency stop killed {} agent(s), cancelled {} execution(s) and {} batch(es), cleared {} queued task(s)",
+        report.agents_stopped,
+        report.cancelled_executions.len(),
+        report.cancelled_batches,
+        report.cleared_queued_tasks
+    );
+
+    let _ = app.emit("emergency-stop", &report);
+    Ok(report)
+}
+
+/// Why spawning is disabled, if it is
+#[tauri::command]
+pub async fn get_spawn_lock() -> Result<Option<SpawnLock>, String> {
+    Ok(AGENT_REGISTRY.spawn_lock())
+}
+
+/// Re-enable spawning after an emergency stop. `confirmation` must be the
+/// code from the stop report.
+#[tauri::command]
+pub async fn resume_spawning(app: AppHandle, confirmation: String) -> Result<(), String> {
+    AGENT_REGISTRY.unlock_spawning(&confirmation)?;
+    let _ = app.emit("spawning-resumed", ());
+    Ok(())
+}
+
+/// The API server's port, URL and, if it failed to start, why
+#[tauri::command]
+pub async fn get_api_server_info() -> Result<ApiServerInfo, String> {
+    Ok(server::info())
+}
+
+/// Restart the API server with the current settings, e.g. after freeing
+/// its preferred port
+#[tauri::command]
+pub async fn restart_api_server(app: AppHandle, state: State<'_, Arc<AppState>>) -> Result<ApiServerInfo, String> {
+    Ok(server::restart_api_server(app, state.inner().clone()).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_probe_writable() {
+        let dir = std::env::temp_dir().join(format!("nexus-probe-{}", uuid::Uuid::new_v4()));
+        assert!(probe_writable(&dir.join("nested")).is_ok());
+        assert_eq!(std::fs::read_dir(dir.join("nested")).unwrap().count(), 0);
+
+        // A file where the directory should be
+        std::fs::write(dir.join("file"), b"").unwrap();
+        assert!(probe_writable(&dir.join("file")).is_err());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[tokio::test]
+    async fn test_tool_version() {
+        assert!(tool_version("git").await.unwrap().starts_with("git version"));
+        assert!(tool_version("nexus-no-such-tool").await.is_err());
+    }
+}
@@ -1,19 +1,41 @@
+use crate::integrations::{send_email, SmtpConfig};
+use crate::project::{packages, ImpactScope, MergeReport, PackageSelection, WorkspaceChange, WorkspaceInfo, ISOLATED_WORKSPACES};
+use crate::settings::{ItemKind, SETTINGS, USER_STATE};
 use crate::state::AppState;
+use crate::workflow::batch;
+use crate::workflow::{conflicts, forecast, lint, locale, node_library, output_format};
+use crate::workflow::dataset;
+use crate::workflow::env_vars::{self, EnvVar};
+use crate::workflow::guardrails::GuardrailConfig;
+use crate::workflow::develop::{self, DevSession, DEV_SESSIONS, DIAGNOSTICS_EVENT};
+use crate::workflow::project_health::{self, ActiveExecution, ProjectHealth};
+use crate::workflow::history::{AnnotationSeverity, NodeAnnotation, Rating};
+use crate::workflow::prompt_performance::{self, PromptPerformance};
+use crate::workflow::rollback::{self, RollbackPreview};
+use crate::workflow::run_presets::{RunPreset, RunPresetInput, RUN_PRESETS};
+use crate::workflow::schema;
+use crate::workflow::prompt_preview::{self, PromptInputs, PromptPreview};
+use crate::workflow::quick_search::{self, MatchKind, QuickMatch};
+use crate::workflow::simulation::{self, ConditionSimulation, Scenario};
+use crate::workflow::supply_chain::{SupplyChainReport, SUPPLY_CHAIN_REPORTS};
+use crate::workflow::text;
 use crate::workflow::{
-    CheckpointManager, CheckpointSummary, EnhancedExecutionConfig,
-    EnhancedNodeConfig, EnhancedWorkflowExecutor, ExecutionCondition, ExecutionStatus,
-    ExecutionHistoryStore, HistoryStatistics, MessageBusStore,
-    ResourceConfig, ResourceManager, ResourceStatsSnapshot,
-    RetryConfig, TemplateCategory, WorkflowExecutor, WorkflowGraph, WorkflowTemplate,
+    ActivityEntry, ACTIVITY_STORE, AgentOutput, AggregationPreview, AggregationStrategy, ApprovalDecision, ApprovalRequest, APPROVAL_STORE, AuditAction, AuditEntry, AUDIT_LOG, AutoCancelOverride, AutoCancelPolicy, AUTO_CANCEL_POLICIES, BatchBackend, ChaosConfig, BatchHandle, BatchItem, BatchRecord, BatchReport, BatchStore, CaseResult, CheckpointManager, CheckpointSummary, ConflictPolicy, CycleDiagnosis, DatasetFilter, DeadlineConfig, EnhancedExecutionConfig, EstimateConfig, ExecutionEstimate, EvalCase, EvaluationReport, EvaluationStore, EvaluationSuite,
+    EnhancedNodeConfig, EnhancedWorkflowExecutor, Encoding, EnvironmentFingerprint, EventsSince, EVENT_JOURNAL, EXECUTION_GROUPS, EXECUTION_LOG, ExecutionConcurrency, GATE_THRESHOLDS, GateThresholds, ExecutionCondition, ExecutionContext, ExecutionReport, ExecutionStatus, ExecutionTranscripts,
+    ExecutionHistoryStore, GroupInfo, HistoryStatistics, ImportFormat, LintConfig, LintFinding, LINT_CONFIGS, LockInfo, LockRequest, LogEntry, ModelPins, ModelPrice, NodeCacheConfig, NodePreset, NODE_LIBRARY, LogLevel, LogLevels, NodeDecision, NodeExecutionStatus, MessageBusConfig, OutputFormat, MessageContent, MessageFilter, MessageType, NodeAggregationConfig, OutputData,
+    LOCK_MANAGER, MESSAGE_BUS_STORE, PluginInfo, PluginRegistry, PLUGIN_REGISTRY, PublishedSection,
+    OutputTransform, CredentialRateLimit, FairShare, QueuePolicy, RateLimit, RateLimitStatus, QueuedTask, QueuedTaskInfo, TaskPriority, ReportFormat, resolve_output_path, ResourceConfig, ResourceManager, ResourceStatsSnapshot, RESULT_CACHE,
+    Redactor, RetryConfig, RetryPromptMode, SampleOutput, SchemaViolation, SelfCorrectionConfig, ShareBundle, SubstitutionReport, ShareFormat, SlaRule, SlaStatus, SLA_STORE, StageConfig, TemplateCategory, TestFormat, TokenCount, TOKENIZERS, WorkflowExecutionState, WorkflowExecutor, WorkflowGraph,
+    WorkflowTemplate,
 };
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
-use tauri::{AppHandle, State};
+use tauri::{AppHandle, Emitter, State};
 use uuid::Uuid;
 
 // In-memory workflow storage for offline mode
@@ -36,6 +58,15 @@ pub struct Workflow {
     pub graph: serde_json::Value,
     pub is_template: bool,
     pub created_at: DateTime<Utc>,
+    /// Group whose executions run one at a time, across workflows
+    #[serde(default)]
+    pub concurrency: Option<ExecutionConcurrency>,
+    /// Models its agent roles run on
+    #[serde(default)]
+    pub model_pins: Option<ModelPins>,
+    /// Environment defaults of its runs
+    #[serde(default)]
+    pub env: BTreeMap<String, EnvVar>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -44,6 +75,9 @@ pub struct CreateWorkflowRequest {
     pub description: Option<String>,
     pub graph: serde_json::Value,
     pub is_template: Option<bool>,
+    pub concurrency: Option<ExecutionConcurrency>,
+    pub model_pins: Option<ModelPins>,
+    pub env: Option<BTreeMap<String, EnvVar>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -54,6 +88,10 @@ pub struct WorkflowResponse {
     pub graph: serde_json::Value,
     pub is_template: bool,
     pub created_at: String,
+    pub concurrency: Option<ExecutionConcurrency>,
+    pub model_pins: Option<ModelPins>,
+    /// Secret values are redacted
+    pub env: BTreeMap<String, EnvVar>,
 }
 
 impl From<&Workflow> for WorkflowResponse {
@@ -65,30 +103,104 @@ impl From<&Workflow> for WorkflowResponse {
             graph: w.graph.clone(),
             is_template: w.is_template,
             created_at: w.created_at.to_rfc3339(),
+            concurrency: w.concurrency.clone(),
+            model_pins: w.model_pins.clone(),
+            env: env_vars::masked(&w.env),
         }
     }
 }
 
 #[tauri::command]
 pub async fn create_workflow(
-    _state: State<'_, Arc<AppState>>,
+    state: State<'_, Arc<AppState>>,
     request: CreateWorkflowRequest,
 ) -> Result<WorkflowResponse, String> {
+    // Store graphs in the current format so later loads skip migration
+    let graph = schema::migrate_graph(request.graph).map_err(|e| e.to_string())?;
+    let env = request.env.unwrap_or_default();
+    env_vars::validate(&env)?;
+
     let workflow = Workflow {
         id: Uuid::new_v4(),
         name: request.name,
         description: request.description,
-        graph: request.graph,
+        graph,
         is_template: request.is_template.unwrap_or(false),
         created_at: Utc::now(),
+        concurrency: request.concurrency,
+        model_pins: request.model_pins,
+        env,
     };
 
+    #[cfg(feature = "database")]
+    crate::db::notify::publish_workflow(&state, &workflow);
+    #[cfg(not(feature = "database"))]
+    let _ = state;
+
     let response = WorkflowResponse::from(&workflow);
     WORKFLOWS.insert(workflow.id, workflow);
 
     Ok(response)
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ImportWorkflowRequest {
+    /// Contents of the n8n JSON or GitHub Actions YAML file
+    pub content: String,
+    /// Detected from the content when omitted
+    pub format: Option<ImportFormat>,
+    /// Defaults to the imported workflow's own name
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportWorkflowResponse {
+    pub workflow: WorkflowResponse,
+    pub format: ImportFormat,
+    /// Parts of the source that were skipped or approximated
+    pub warnings: Vec<String>,
+}
+
+/// Convert an n8n or GitHub Actions workflow and save it as a new workflow
+#[tauri::command]
+pub async fn import_workflow(
+    state: State<'_, Arc<AppState>>,
+    request: ImportWorkflowRequest,
+) -> Result<ImportWorkflowResponse, String> {
+    let imported = crate::workflow::import::import_workflow(&request.content, request.format)
+        .map_err(|e| e.to_string())?;
+
+    let workflow = Workflow {
+        id: Uuid::new_v4(),
+        name: request
+            .name
+            .or(imported.name)
+            .unwrap_or_else(|| "Imported workflow".to_string()),
+        description: request.description,
+        graph: imported.graph,
+        is_template: false,
+        created_at: Utc::now(),
+        concurrency: None,
+        model_pins: None,
+        env: BTreeMap::new(),
+    };
+
+    #[cfg(feature = "database")]
+    crate::db::notify::publish_workflow(&state, &workflow);
+    #[cfg(not(feature = "database"))]
+    let _ = state;
+
+    let response = WorkflowResponse::from(&workflow);
+    WORKFLOWS.insert(workflow.id, workflow);
+
+    Ok(ImportWorkflowResponse {
+        workflow: response,
+        format: imported.format,
+        warnings: imported.warnings,
+    })
+}
+
 #[tauri::command]
 pub async fn get_workflow(
     _state: State<'_, Arc<AppState>>,
@@ -126,7 +238,31 @@ pub async fn execute_workflow(
     _state: State<'_, Arc<AppState>>,
     request: ExecuteWorkflowRequest,
 ) -> Result<String, String> {
-    let executor_lock = get_executor(&app);
+    start_workflow(&app, &request.workflow_id, &request.project_id, request.input_prompt)
+        .map(|execution_id| execution_id.to_string())
+}
+
+/// Start a saved workflow on the basic executor
+pub(crate) fn start_workflow(
+    app: &AppHandle,
+    workflow_id: &str,
+    project_id: &str,
+    input_prompt: String,
+) -> Result<Uuid, String> {
+    start_workflow_with(app, workflow_id, project_id, input_prompt, HashMap::new(), HashMap::new())
+}
+
+/// Start a saved workflow on the basic executor with variables and node
+/// configs
+fn start_workflow_with(
+    app: &AppHandle,
+    workflow_id: &str,
+    project_id: &str,
+    input_prompt: String,
+    variables: HashMap<String, serde_json::Value>,
+    node_configs: HashMap<String, EnhancedNodeConfig>,
+) -> Result<Uuid, String> {
+    let executor_lock = get_executor(app);
     let executor_guard = executor_lock.read();
 
     let executor = executor_guard
@@ -134,16 +270,14 @@ pub async fn execute_workflow(
         .ok_or_else(|| "Executor not initialized".to_string())?;
 
     let execution_id = executor
-        .execute(
-            &request.workflow_id,
-            &request.project_id,
-            request.input_prompt,
-        )
+        .execute_with(workflow_id, project_id, input_prompt, variables, node_configs)
         .map_err(|e| e.to_string())?;
 
     log::info!("Started workflow execution: {}", execution_id);
+    USER_STATE.record_use(ItemKind::Workflow, workflow_id);
+    USER_STATE.record_use(ItemKind::Project, project_id);
 
-    Ok(execution_id.to_string())
+    Ok(execution_id)
 }
 
 /// Execute an orchestrated workflow - the orchestrator creates a plan and wires up agents dynamically
@@ -170,9 +304,13 @@ pub async fn execute_orchestrated_workflow(
     Ok(execution_id.to_string())
 }
 
+/// Cancel an execution. While connected to a database, one this instance
+/// doesn't run is passed on to the other instances sharing it, and counts
+/// as cancelled.
 #[tauri::command]
 pub async fn cancel_workflow_execution(
     app: AppHandle,
+    state: State<'_, Arc<AppState>>,
     execution_id: String,
 ) -> Result<bool, String> {
     let uuid =
@@ -189,6 +327,17 @@ pub async fn cancel_workflow_execution(
 
     if cancelled {
         log::info!("Cancelled workflow execution: {}", execution_id);
+    } else if runs_on_another_instance(&state, &uuid) {
+        #[cfg(feature = "database")]
+        crate::db::notify::publish(
+            &state,
+            crate::db::notify::InstanceEvent::CancelExecution {
+                execution_id: uuid,
+                reason: "Cancelled from another instance".to_string(),
+            },
+        );
+        log::info!("Asked other instances to cancel workflow execution: {}", execution_id);
+        return Ok(true);
     } else {
         log::warn!(
             "Could not cancel workflow execution (not found): {}",
@@ -199,6 +348,19 @@ pub async fn cancel_workflow_execution(
     Ok(cancelled)
 }
 
+/// Whether another instance sharing the database runs `execution_id`
+fn runs_on_another_instance(state: &AppState, execution_id: &Uuid) -> bool {
+    #[cfg(feature = "database")]
+    {
+        state.has_db() && crate::db::notify::runs_elsewhere(execution_id)
+    }
+    #[cfg(not(feature = "database"))]
+    {
+        let _ = (state, execution_id);
+        false
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ExecutionStatusResponse {
     pub execution_id: String,
@@ -211,6 +373,7 @@ pub struct ExecutionStatusResponse {
     pub progress: u8,
     pub started_at: String,
     pub completed_at: Option<String>,
+    pub cancel_reason: Option<String>,
 }
 
 #[tauri::command]
@@ -243,13 +406,42 @@ pub async fn get_workflow_execution_status(
         progress: summary.progress,
         started_at: summary.started_at,
         completed_at: summary.completed_at,
+        cancel_reason: summary.cancel_reason,
     })
 }
 
+/// Forget executions of either executor that finished at least
+/// `older_than_secs` ago, with their contexts, logs, event journals,
+/// messages and activity. Running executions are kept. Returns the IDs
+/// removed.
+#[tauri::command]
+pub async fn purge_completed_executions(older_than_secs: u64) -> Result<Vec<String>, String> {
+    let older_than = chrono::Duration::from_std(std::time::Duration::from_secs(older_than_secs))
+        .map_err(|e| format!("Invalid age: {}", e))?;
+    let cutoff = Utc::now() - older_than;
+
+    let mut purged = Vec::new();
+    if let Some(lock) = EXECUTOR.get() {
+        if let Some(executor) = lock.read().as_ref() {
+            purged.extend(executor.engine().purge_finished(cutoff));
+        }
+    }
+    if let Some(lock) = ENHANCED_EXECUTOR.get() {
+        if let Some(executor) = lock.read().as_ref() {
+            purged.extend(executor.purge_finished(cutoff));
+        }
+    }
+
+    log::info!("Purged {} finished executions", purged.len());
+    Ok(purged.iter().map(Uuid::to_string).collect())
+}
+
 #[derive(Debug, Serialize)]
 pub struct WorkflowValidationResult {
     pub is_valid: bool,
     pub has_cycle: bool,
+    /// The cycle and the edges to remove to break it
+    pub cycle: Option<CycleDiagnosis>,
     pub disconnected_nodes: Vec<String>,
     pub root_nodes: Vec<String>,
     pub leaf_nodes: Vec<String>,
@@ -257,21 +449,32 @@ pub struct WorkflowValidationResult {
     pub total_edges: usize,
     pub execution_levels: Option<usize>,
     pub error: Option<String>,
+    /// Schema violations with JSON Pointer paths into the graph
+    pub schema_errors: Vec<SchemaViolation>,
+}
+
+/// The JSON Schema for workflow graphs (current version)
+#[tauri::command]
+pub async fn get_workflow_graph_schema() -> Result<serde_json::Value, String> {
+    Ok(schema::graph_schema().clone())
 }
 
 #[tauri::command]
 pub async fn validate_workflow(
     graph: serde_json::Value,
 ) -> Result<WorkflowValidationResult, String> {
-    use crate::workflow::graph::WorkflowGraph;
+    Ok(validate_graph(&graph))
+}
 
+fn validate_graph(graph: &serde_json::Value) -> WorkflowValidationResult {
     // Try to parse the graph
-    let workflow_graph = match WorkflowGraph::from_json(&graph) {
+    let workflow_graph = match WorkflowGraph::from_json(graph) {
         Ok(g) => g,
         Err(e) => {
-            return Ok(WorkflowValidationResult {
+            return WorkflowValidationResult {
                 is_valid: false,
                 has_cycle: false,
+                cycle: None,
                 disconnected_nodes: vec![],
                 root_nodes: vec![],
                 leaf_nodes: vec![],
@@ -279,7 +482,11 @@ pub async fn validate_workflow(
                 total_edges: 0,
                 execution_levels: None,
                 error: Some(format!("Failed to parse graph: {}", e)),
-            });
+                schema_errors: match e {
+                    crate::workflow::graph::GraphError::Schema(violations) => violations,
+                    _ => vec![],
+                },
+            };
         }
     };
 
@@ -295,43 +502,72 @@ pub async fn validate_workflow(
             let has_predecessors = workflow_graph
                 .predecessors
                 .get(*id)
-                .map_or(false, |p| !p.is_empty());
+                .is_some_and(|p| !p.is_empty());
             let has_successors = workflow_graph
                 .successors
                 .get(*id)
-                .map_or(false, |s| !s.is_empty());
+                .is_some_and(|s| !s.is_empty());
             !has_predecessors && !has_successors && workflow_graph.nodes.len() > 1
         })
         .cloned()
         .collect();
 
     // Check for cycles by attempting topological sort
-    let (has_cycle, execution_levels) = match workflow_graph.compute_execution_levels() {
-        Ok(levels) => (false, Some(levels.len())),
-        Err(crate::workflow::graph::GraphError::CycleDetected) => (true, None),
-        Err(_) => (false, None),
+    let (cycle, execution_levels) = match workflow_graph.compute_execution_levels() {
+        Ok(levels) => (None, Some(levels.len())),
+        Err(crate::workflow::graph::GraphError::CycleDetected(diagnosis)) => (Some(diagnosis), None),
+        Err(_) => (None, None),
     };
+    let has_cycle = cycle.is_some();
+
+    // Syntax-check script nodes
+    let script_errors: Vec<String> = workflow_graph
+        .nodes
+        .values()
+        .filter_map(|node| match &node.kind {
+            crate::workflow::NodeKind::Script { source } => crate::workflow::script::compile_check(source)
+                .err()
+                .map(|e| format!("{}: {}", node.id, e)),
+            _ => None,
+        })
+        .collect();
 
-    let is_valid = !has_cycle && disconnected_nodes.is_empty();
+    let is_valid = !has_cycle && disconnected_nodes.is_empty() && script_errors.is_empty();
     let disconnected_count = disconnected_nodes.len();
 
-    Ok(WorkflowValidationResult {
+    let error = if let Some(diagnosis) = &cycle {
+        let edges: Vec<String> = diagnosis
+            .breaking_edges
+            .iter()
+            .map(|e| format!("{} ({} -> {})", e.id, e.source, e.target))
+            .collect();
+        Some(format!(
+            "Workflow contains a cycle ({}) - agents cannot depend on each other circularly. Remove {}: {}",
+            diagnosis.path(),
+            if edges.len() == 1 { "this edge" } else { "these edges" },
+            edges.join(", ")
+        ))
+    } else if disconnected_count > 0 {
+        Some(format!("Workflow has {} disconnected node(s)", disconnected_count))
+    } else if !script_errors.is_empty() {
+        Some(script_errors.join("; "))
+    } else {
+        None
+    };
+
+    WorkflowValidationResult {
         is_valid,
         has_cycle,
+        cycle,
         disconnected_nodes,
         root_nodes,
         leaf_nodes,
         total_nodes: workflow_graph.node_count(),
         total_edges: workflow_graph.edges.len(),
         execution_levels,
-        error: if has_cycle {
-            Some("Workflow contains a cycle - agents cannot depend on each other circularly".to_string())
-        } else if disconnected_count > 0 {
-            Some(format!("Workflow has {} disconnected node(s)", disconnected_count))
-        } else {
-            None
-        },
-    })
+        error,
+        schema_errors: vec![],
+    }
 }
 
 // =============================================================================
@@ -345,6 +581,98 @@ fn get_enhanced_executor(app: &AppHandle) -> &RwLock<Option<EnhancedWorkflowExec
     ENHANCED_EXECUTOR.get_or_init(|| RwLock::new(Some(EnhancedWorkflowExecutor::new(app.clone()))))
 }
 
+/// Look up an execution in either executor, with its data-flow context
+pub(crate) fn find_execution(
+    execution_id: &Uuid,
+) -> Option<(Arc<WorkflowExecutionState>, Option<Arc<ExecutionContext>>)> {
+    if let Some(lock) = ENHANCED_EXECUTOR.get() {
+        if let Some(executor) = lock.read().as_ref() {
+            if let Some(state) = executor.execution_store().get(execution_id) {
+                return Some((state, executor.context_store().get(execution_id)));
+            }
+        }
+    }
+
+    let guard = EXECUTOR.get()?.read();
+    let engine = guard.as_ref()?.engine();
+    let state = engine.execution_store().get(execution_id)?;
+    Some((state, engine.context_store().get(execution_id)))
+}
+
+/// Executions held by either executor
+pub(crate) fn all_executions() -> Vec<Arc<WorkflowExecutionState>> {
+    let basic = EXECUTOR
+        .get()
+        .and_then(|lock| lock.read().as_ref().map(|e| e.store().list()))
+        .unwrap_or_default();
+    let enhanced = ENHANCED_EXECUTOR
+        .get()
+        .and_then(|lock| lock.read().as_ref().map(|e| e.execution_store().list()))
+        .unwrap_or_default();
+
+    basic.into_iter().chain(enhanced).collect()
+}
+
+/// Executions still pending or running, in either executor
+pub(crate) fn active_executions() -> Vec<Arc<WorkflowExecutionState>> {
+    all_executions()
+        .into_iter()
+        .filter(|state| matches!(state.get_status(), ExecutionStatus::Pending | ExecutionStatus::Running))
+        .collect()
+}
+
+/// Execution that spawned each workflow agent, from the agent recorded on
+/// each node (a retried node only remembers its latest agent)
+pub(crate) fn agent_executions() -> HashMap<Uuid, Uuid> {
+    all_executions()
+        .iter()
+        .flat_map(|state| {
+            state
+                .node_states
+                .iter()
+                .filter_map(|node| node.agent_id)
+                .map(|agent_id| (agent_id, state.execution_id))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Checkpoint an execution of either executor as it stands; `None` when
+/// neither holds it
+pub(crate) fn checkpoint_execution(execution_id: &Uuid) -> Option<Result<Uuid, String>> {
+    if let Some(lock) = ENHANCED_EXECUTOR.get() {
+        if let Some(executor) = lock.read().as_ref() {
+            if executor.execution_store().get(execution_id).is_some() {
+                return Some(executor.checkpoint_now(execution_id));
+            }
+        }
+    }
+
+    let guard = EXECUTOR.get()?.read();
+    let engine = guard.as_ref()?.engine();
+    engine.execution_store().get(execution_id)?;
+    Some(engine.checkpoint_now(execution_id))
+}
+
+/// ID of the most recently started execution of a saved workflow, other
+/// than `excluding`
+pub(crate) fn find_latest_execution(workflow_id: &Uuid, excluding: Option<&Uuid>) -> Option<Uuid> {
+    let basic = EXECUTOR
+        .get()
+        .and_then(|lock| lock.read().as_ref().and_then(|e| e.store().latest_for_workflow(workflow_id, excluding)));
+    let enhanced = ENHANCED_EXECUTOR.get().and_then(|lock| {
+        lock.read()
+            .as_ref()
+            .and_then(|e| e.execution_store().latest_for_workflow(workflow_id, excluding))
+    });
+
+    basic
+        .into_iter()
+        .chain(enhanced)
+        .max_by_key(|state| state.started_at)
+        .map(|state| state.execution_id)
+}
+
 /// Request for enhanced workflow execution
 #[derive(Debug, Deserialize)]
 pub struct EnhancedExecutionRequest {
@@ -359,6 +687,47 @@ pub struct EnhancedExecutionRequest {
     pub include_original_prompt: Option<bool>,
     /// Per-node configurations
     pub node_configs: Option<HashMap<String, NodeConfigRequest>>,
+    /// Maximum nodes running at once within a level
+    pub max_parallel_nodes_per_level: Option<usize>,
+    /// Limits for named concurrency groups (defaults to 1 per group)
+    pub concurrency_groups: Option<HashMap<String, usize>>,
+    /// Time budget for the execution in milliseconds
+    pub deadline_ms: Option<u64>,
+    /// Halve retry budgets when behind the deadline (default true)
+    pub reduce_retries_when_behind: Option<bool>,
+    /// Model for non-critical nodes when behind the deadline
+    pub fast_model: Option<String>,
+    /// How to handle parallel nodes editing the same files (default: off)
+    pub conflict_policy: Option<ConflictPolicy>,
+    /// Message cap, TTL and persistence for inter-agent messages
+    pub message_bus: Option<MessageBusConfig>,
+    /// Topics each agent role subscribes to, e.g. {"implementer": ["security-findings"]}
+    pub role_topics: Option<HashMap<String, Vec<String>>>,
+    /// Run agents in a copy of the project and merge changes back on approval
+    pub isolated_workspace: Option<bool>,
+    /// Conditions and output aggregation for the graph's stages
+    pub stage_configs: Option<HashMap<String, StageConfigRequest>>,
+    /// Run one at a time with other executions of the same group
+    pub concurrency: Option<ExecutionConcurrency>,
+    /// Models each agent role runs on, and what to do if one is unavailable
+    pub model_pins: Option<ModelPins>,
+    /// Template the graph was instantiated from; its node config defaults
+    /// apply to nodes with the IDs of its tasks
+    pub template_id: Option<String>,
+    /// Fail, delay or truncate some agent attempts on purpose, to test the
+    /// workflow's failure handling
+    pub chaos: Option<ChaosConfig>,
+    /// Time limits other than the project's auto-cancel policy; must be
+    /// confirmed, and is recorded in the audit log
+    pub auto_cancel_override: Option<AutoCancelOverride>,
+    /// Deny-patterns, personal data and destructive commands to hold agent
+    /// output for approval on
+    pub guardrails: Option<GuardrailConfig>,
+    /// Saved workflow the graph belongs to, whose environment defaults apply
+    pub workflow_id: Option<String>,
+    /// Environment variables for this run, over the workflow's defaults and
+    /// the project's `.env`
+    pub env: Option<BTreeMap<String, EnvVar>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -367,6 +736,8 @@ pub struct RetryConfigRequest {
     pub initial_delay_ms: Option<u64>,
     pub max_delay_ms: Option<u64>,
     pub backoff_multiplier: Option<f64>,
+    /// Re-send the failed prompt (default) or tell the retry what went wrong
+    pub prompt_mode: Option<RetryPromptMode>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -381,6 +752,134 @@ pub struct NodeConfigRequest {
     pub system_prompt_override: Option<String>,
     /// Tags to add to output
     pub output_tags: Option<Vec<String>>,
+    /// Input aggregation (strategy, filters and transform)
+    pub aggregation: Option<NodeAggregationConfig>,
+    /// Named concurrency group, e.g. "implementers"
+    pub concurrency_group: Option<String>,
+    /// Model override for this node's agent
+    pub model: Option<String>,
+    /// Test command and round limit for a test-and-fix loop
+    pub self_correction: Option<SelfCorrectionConfig>,
+    /// Named project resources the node needs, e.g. "package.json"
+    pub locks: Option<Vec<LockRequest>>,
+    /// Message topics delivered into this node's prompt
+    pub subscribe_topics: Option<Vec<String>>,
+    /// Topic to publish the node's output to
+    pub publish_topic: Option<String>,
+    /// Hold the node until it is approved
+    pub require_approval: Option<bool>,
+    /// Predecessors' sections to start on before they finish
+    pub start_on_published: Option<Vec<PublishedSection>>,
+    /// "markdown", "json" or "patch"; the agent is asked for it and
+    /// retried when its answer doesn't parse
+    pub expected_output_format: Option<OutputFormat>,
+    /// JSON Schema the answer must match; implies "json"
+    pub output_schema: Option<serde_json::Value>,
+    /// Runner whose output the node produces ("auto", "cargo", "pytest",
+    /// "jest" or "junit"), parsed into counts and failing tests
+    pub test_results: Option<TestFormat>,
+    /// Point the agent at the files a change impacts
+    pub impact_scope: Option<ImpactScope>,
+    /// Files the node reads; its result is reused while they're unchanged
+    pub cache: Option<NodeCacheConfig>,
+    /// Output checks replacing the workflow's guardrails
+    pub guardrails: Option<GuardrailConfig>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StageConfigRequest {
+    /// Condition for the whole stage, as for nodes
+    pub condition_type: Option<String>,
+    pub condition_params: Option<serde_json::Value>,
+    /// Combine the members' outputs into one under the stage ID
+    pub aggregation: Option<NodeAggregationConfig>,
+}
+
+/// Parse stage conditions from request parameters
+fn parse_stage_configs(
+    configs: Option<HashMap<String, StageConfigRequest>>,
+) -> Result<HashMap<String, StageConfig>, String> {
+    let mut stages = HashMap::new();
+    for (stage_id, request) in configs.unwrap_or_default() {
+        let mut stage = StageConfig {
+            aggregation: request.aggregation,
+            ..StageConfig::default()
+        };
+        if let Some(condition_type) = request.condition_type {
+            stage.condition = parse_condition(&condition_type, request.condition_params)?;
+        }
+        stages.insert(stage_id, stage);
+    }
+    Ok(stages)
+}
+
+/// Node configs from request parameters, starting from the defaults of
+/// the template the graph came from
+fn parse_node_configs(
+    configs: Option<HashMap<String, NodeConfigRequest>>,
+    template_id: Option<String>,
+) -> Result<HashMap<String, EnhancedNodeConfig>, String> {
+    let mut node_configs: HashMap<String, EnhancedNodeConfig> = match template_id {
+        Some(template_id) => crate::workflow::get_template(&template_id)
+            .ok_or_else(|| format!("Template not found: {}", template_id))?
+            .node_defaults(),
+        None => HashMap::new(),
+    };
+
+    if let Some(configs) = configs {
+        for (node_id, node_config) in configs {
+            let mut enhanced_config = node_configs.remove(&node_id).unwrap_or_default();
+
+            // Parse condition
+            if let Some(condition_type) = node_config.condition_type {
+                enhanced_config.condition = parse_condition(&condition_type, node_config.condition_params)?;
+            }
+
+            // Parse retry config
+            if let Some(retry) = node_config.retry_config {
+                enhanced_config.retry = Some(RetryConfig {
+                    max_attempts: retry.max_attempts.unwrap_or(3),
+                    initial_delay_ms: retry.initial_delay_ms.unwrap_or(1000),
+                    max_delay_ms: retry.max_delay_ms.unwrap_or(30000),
+                    backoff_multiplier: retry.backoff_multiplier.unwrap_or(2.0),
+                    prompt_mode: retry.prompt_mode.unwrap_or_default(),
+                    ..Default::default()
+                });
+            }
+
+            enhanced_config.system_prompt_override = node_config.system_prompt_override;
+            if let Some(output_tags) = node_config.output_tags {
+                enhanced_config.output_tags = output_tags;
+            }
+            if node_config.aggregation.is_some() {
+                enhanced_config.aggregation = node_config.aggregation;
+            }
+            enhanced_config.concurrency_group = node_config.concurrency_group;
+            enhanced_config.model = node_config.model;
+            enhanced_config.self_correction = node_config.self_correction;
+            enhanced_config.locks = node_config.locks.unwrap_or_default();
+            enhanced_config.subscribe_topics = node_config.subscribe_topics.unwrap_or_default();
+            enhanced_config.publish_topic = node_config.publish_topic;
+            enhanced_config.require_approval = node_config.require_approval.unwrap_or(false);
+            enhanced_config.start_on_published = node_config.start_on_published.unwrap_or_default();
+            enhanced_config.expected_output_format = node_config.expected_output_format;
+            if let Some(schema) = &node_config.output_schema {
+                output_format::validate_schema(schema).map_err(|e| format!("Node '{}': {}", node_id, e))?;
+            }
+            enhanced_config.output_schema = node_config.output_schema;
+            enhanced_config.test_results = node_config.test_results;
+            enhanced_config.impact_scope = node_config.impact_scope;
+            enhanced_config.cache = node_config.cache;
+            if let Some(guardrails) = node_config.guardrails {
+                guardrails.validate()?;
+                enhanced_config.guardrails = Some(guardrails);
+            }
+
+            node_configs.insert(node_id, enhanced_config);
+        }
+    }
+
+    Ok(node_configs)
 }
 
 /// Execute a workflow with enhanced orchestration features
@@ -407,6 +906,7 @@ pub async fn execute_enhanced_workflow(
             initial_delay_ms: retry.initial_delay_ms.unwrap_or(1000),
             max_delay_ms: retry.max_delay_ms.unwrap_or(30000),
             backoff_multiplier: retry.backoff_multiplier.unwrap_or(2.0),
+            prompt_mode: retry.prompt_mode.unwrap_or_default(),
             ..Default::default()
         };
     }
@@ -419,36 +919,50 @@ pub async fn execute_enhanced_workflow(
         config.include_original_prompt = include;
     }
 
-    // Build node configs
-    let mut node_configs: HashMap<String, EnhancedNodeConfig> = HashMap::new();
-
-    if let Some(configs) = request.node_configs {
-        for (node_id, node_config) in configs {
-            let mut enhanced_config = EnhancedNodeConfig::default();
-
-            // Parse condition
-            if let Some(condition_type) = node_config.condition_type {
-                enhanced_config.condition = parse_condition(&condition_type, node_config.condition_params)?;
-            }
-
-            // Parse retry config
-            if let Some(retry) = node_config.retry_config {
-                enhanced_config.retry = Some(RetryConfig {
-                    max_attempts: retry.max_attempts.unwrap_or(3),
-                    initial_delay_ms: retry.initial_delay_ms.unwrap_or(1000),
-                    max_delay_ms: retry.max_delay_ms.unwrap_or(30000),
-                    backoff_multiplier: retry.backoff_multiplier.unwrap_or(2.0),
-                    ..Default::default()
-                });
-            }
-
-            enhanced_config.system_prompt_override = node_config.system_prompt_override;
-            enhanced_config.output_tags = node_config.output_tags.unwrap_or_default();
+    config.max_parallel_nodes_per_level = request.max_parallel_nodes_per_level;
+    config.conflict_policy = request.conflict_policy;
+    config.message_bus = request.message_bus.unwrap_or_else(|| MessageBusConfig {
+        ttl_ms: SETTINGS.get().retention.message_ttl_hours.map(|hours| hours * 3_600_000),
+        ..Default::default()
+    });
+    config.role_topics = request.role_topics.unwrap_or_default();
+    config.concurrency_groups = request.concurrency_groups.unwrap_or_default();
+    config.isolated_workspace = request.isolated_workspace.unwrap_or(false);
+    config.stages = parse_stage_configs(request.stage_configs)?;
+    config.concurrency = request.concurrency;
+    config.model_pins = request.model_pins;
+    if let Some(chaos) = &request.chaos {
+        chaos.validate()?;
+    }
+    config.chaos = request.chaos;
+    if let Some(guardrails) = &request.guardrails {
+        guardrails.validate()?;
+    }
+    config.guardrails = request.guardrails;
+    if let Some(auto_cancel) = &request.auto_cancel_override {
+        auto_cancel.validate()?;
+    }
+    config.auto_cancel = request.auto_cancel_override.as_ref().map(|auto_cancel| auto_cancel.policy);
+    if let Some(workflow_id) = &request.workflow_id {
+        let uuid = Uuid::parse_str(workflow_id).map_err(|e| format!("Invalid workflow ID: {}", e))?;
+        let workflow = WORKFLOWS.get(&uuid).ok_or_else(|| format!("Workflow not found: {}", workflow_id))?;
+        config.env = workflow.env.clone();
+    }
+    config.env.extend(request.env.unwrap_or_default());
 
-            node_configs.insert(node_id, enhanced_config);
+    if let Some(deadline_ms) = request.deadline_ms {
+        let mut deadline = DeadlineConfig::new(deadline_ms);
+        if let Some(reduce) = request.reduce_retries_when_behind {
+            deadline.reduce_retries_when_behind = reduce;
         }
+        deadline.fast_model = request.fast_model;
+        config.deadline = Some(deadline);
     }
 
+    node_library::check_runnable(&graph)?;
+    let mut node_configs = parse_node_configs(request.node_configs, request.template_id)?;
+    node_library::apply_presets(&graph, &mut node_configs);
+
     // Get enhanced executor
     let executor_lock = get_enhanced_executor(&app);
     let executor_guard = executor_lock.read();
@@ -461,6 +975,9 @@ pub async fn execute_enhanced_workflow(
     let execution_id = executor
         .execute_enhanced(graph, project_id, request.input_prompt, config, node_configs)
         .map_err(|e| e.to_string())?;
+    if let Some(auto_cancel) = &request.auto_cancel_override {
+        auto_cancel.audit(project_id, execution_id, &AUTO_CANCEL_POLICIES.get(&project_id));
+    }
 
     log::info!("Started enhanced workflow execution: {}", execution_id);
 
@@ -517,6 +1034,30 @@ fn parse_condition(
                 .ok_or_else(|| "variable_truthy condition requires variable".to_string())?;
             Ok(ExecutionCondition::VariableTruthy { variable })
         }
+        "output_tagged" => {
+            let tag = params
+                .as_ref()
+                .and_then(|p| p.get("tag"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .ok_or_else(|| "output_tagged condition requires tag".to_string())?;
+            let predecessor_id = params
+                .as_ref()
+                .and_then(|p| p.get("predecessor_id"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            Ok(ExecutionCondition::OutputTagged { tag, predecessor_id })
+        }
+        "plugin" => {
+            let params = params.ok_or_else(|| "plugin condition requires params".to_string())?;
+            let name = params
+                .get("name")
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string())
+                .ok_or_else(|| "plugin condition requires name".to_string())?;
+            let plugin_params = params.get("params").cloned().unwrap_or_default();
+            Ok(ExecutionCondition::Plugin { name, params: plugin_params })
+        }
         _ => Err(format!("Unknown condition type: {}", condition_type)),
     }
 }
@@ -544,109 +1085,558 @@ pub async fn get_execution_context(
 
     let summary = context.get_execution_summary();
 
-    Ok(serde_json::to_value(summary)
-        .map_err(|e| format!("Failed to serialize context: {}", e))?)
+    serde_json::to_value(summary)
+        .map_err(|e| format!("Failed to serialize context: {}", e))
 }
 
-/// List available checkpoints
+/// Commands a node's agents ran and files they changed, oldest first
 #[tauri::command]
-pub async fn list_checkpoints() -> Result<Vec<CheckpointSummaryResponse>, String> {
-    let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
-        .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
-
-    let summaries = manager
-        .list()
-        .map_err(|e| format!("Failed to list checkpoints: {}", e))?;
+pub async fn get_node_activity(execution_id: String, node_id: String) -> Result<Vec<ActivityEntry>, String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
 
-    Ok(summaries.into_iter().map(CheckpointSummaryResponse::from).collect())
+    Ok(ACTIVITY_STORE.get(uuid, &node_id))
 }
 
-#[derive(Debug, Serialize)]
-pub struct CheckpointSummaryResponse {
-    pub id: String,
-    pub execution_id: String,
-    pub workflow_id: String,
-    pub status: String,
-    pub progress: f32,
-    pub total_nodes: usize,
-    pub completed_nodes: usize,
-    pub failed_nodes: usize,
-    pub pending_nodes: usize,
-    pub checkpoint_at: String,
-    pub current_level: usize,
-    pub total_levels: usize,
-}
+/// An execution's events after sequence number `seq` (0 for all), so a view
+/// opened mid-run can catch up before following live `workflow-event`s
+#[tauri::command]
+pub async fn get_events_since(execution_id: String, seq: Option<u64>) -> Result<EventsSince, String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
 
-impl From<CheckpointSummary> for CheckpointSummaryResponse {
-    fn from(s: CheckpointSummary) -> Self {
-        Self {
-            id: s.id.to_string(),
-            execution_id: s.execution_id.to_string(),
-            workflow_id: s.workflow_id.to_string(),
-            status: format!("{:?}", s.status),
-            progress: s.progress,
-            total_nodes: s.total_nodes,
-            completed_nodes: s.completed_nodes,
-            failed_nodes: s.failed_nodes,
-            pending_nodes: s.pending_nodes,
-            checkpoint_at: s.checkpoint_at.to_rfc3339(),
-            current_level: s.current_level,
-            total_levels: s.total_levels,
-        }
-    }
+    EVENT_JOURNAL
+        .since(&uuid, seq.unwrap_or(0))
+        .ok_or_else(|| format!("No events recorded for execution {}", execution_id))
 }
 
-/// List checkpoints for a specific execution
+/// Why a node ran or was skipped: the failed dependencies that skipped it,
+/// or its condition with the evaluation chain and the values it consulted
 #[tauri::command]
-pub async fn list_execution_checkpoints(
-    execution_id: String,
-) -> Result<Vec<CheckpointSummaryResponse>, String> {
+pub async fn explain_node_decision(execution_id: String, node_id: String) -> Result<NodeDecision, String> {
     let uuid = Uuid::parse_str(&execution_id)
         .map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let (state, context) = find_execution(&uuid).ok_or_else(|| format!("Execution not found: {}", execution_id))?;
 
-    let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
-        .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
-
-    let summaries = manager
-        .list_for_execution(&uuid)
-        .map_err(|e| format!("Failed to list checkpoints: {}", e))?;
+    if let Some(decision) = context.and_then(|context| context.get_decision(&node_id)) {
+        return Ok(decision);
+    }
 
-    Ok(summaries.into_iter().map(CheckpointSummaryResponse::from).collect())
+    // Every node reached has a decision recorded
+    match state.get_node_state(&node_id) {
+        Some(_) => Err(format!("Node {} hasn't been reached yet", node_id)),
+        None => Err(format!("Node not found in execution: {}", node_id)),
+    }
 }
 
-/// Clean up old checkpoints
+/// Which nodes would run or be skipped if nodes finished with
+/// `node_statuses` and the execution had `variables`, without running
+/// anything. Conditions are read from `node_configs` and `stage_configs` as
+/// for `execute_enhanced_workflow`; `outputs` feeds output conditions.
 #[tauri::command]
-pub async fn cleanup_checkpoints(
-    keep_per_execution: Option<usize>,
-) -> Result<usize, String> {
-    let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
-        .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
+pub async fn simulate_conditions(
+    graph: serde_json::Value,
+    node_statuses: Option<HashMap<String, NodeExecutionStatus>>,
+    variables: Option<HashMap<String, serde_json::Value>>,
+    node_configs: Option<HashMap<String, NodeConfigRequest>>,
+    stage_configs: Option<HashMap<String, StageConfigRequest>>,
+    outputs: Option<HashMap<String, String>>,
+) -> Result<ConditionSimulation, String> {
+    let graph = WorkflowGraph::from_json(&graph).map_err(|e| e.to_string())?;
+
+    let mut conditions = HashMap::new();
+    for (node_id, node_config) in node_configs.unwrap_or_default() {
+        if let Some(condition_type) = node_config.condition_type {
+            conditions.insert(node_id, parse_condition(&condition_type, node_config.condition_params)?);
+        }
+    }
 
-    let deleted = manager
-        .cleanup(keep_per_execution.unwrap_or(3))
-        .map_err(|e| format!("Failed to cleanup checkpoints: {}", e))?;
+    let scenario = Scenario {
+        node_statuses: node_statuses.unwrap_or_default(),
+        variables: variables.unwrap_or_default(),
+        outputs: outputs.unwrap_or_default(),
+    };
+    let stages = parse_stage_configs(stage_configs)?;
+    simulation::simulate(&graph, &conditions, &stages, &scenario).map_err(|e| e.to_string())
+}
 
-    Ok(deleted)
+/// Request for a node's prompt preview
+#[derive(Debug, Deserialize)]
+pub struct PromptPreviewRequest {
+    pub node_id: String,
+    /// A running or recorded execution to take outputs, variables and the
+    /// input from; its saved workflow's graph is used unless `graph` is given
+    pub execution_id: Option<String>,
+    pub graph: Option<serde_json::Value>,
+    /// Project whose locale applies, when not the execution's
+    pub project_id: Option<String>,
+    /// Replaces the execution's input
+    pub input_prompt: Option<String>,
+    /// Set on top of the execution's variables
+    pub sample_variables: Option<HashMap<String, serde_json::Value>>,
+    /// Text output by node, replacing the execution's outputs of those nodes
+    pub sample_outputs: Option<HashMap<String, String>>,
+    pub node_configs: Option<HashMap<String, NodeConfigRequest>>,
+    pub template_id: Option<String>,
+    pub enable_data_flow: Option<bool>,
+    pub include_original_prompt: Option<bool>,
 }
 
-/// Get available agent roles
+/// The system prompt and task `node_id`'s agent would be given, with the
+/// predecessor context and variables resolved, and token estimates.
+/// Predecessors without an output are shown as placeholders.
 #[tauri::command]
-pub async fn get_available_agent_roles() -> Result<Vec<AgentRoleInfo>, String> {
-    Ok(vec![
-        AgentRoleInfo {
-            id: "orchestrator".to_string(),
-            name: "Orchestrator".to_string(),
-            description: "Plans and coordinates work between other agents".to_string(),
-            capabilities: vec![
-                "Task decomposition".to_string(),
-                "Dependency analysis".to_string(),
-                "Agent coordination".to_string(),
-            ],
-        },
-        AgentRoleInfo {
-            id: "architect".to_string(),
-            name: "Architect".to_string(),
-            description: "Designs system architecture and technical specifications".to_string(),
+pub async fn preview_node_prompt(request: PromptPreviewRequest) -> Result<PromptPreview, String> {
+    let mut inputs = PromptInputs::default();
+    let mut workflow_id = None;
+    let mut project_id = request
+        .project_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|e| format!("Invalid project ID: {}", e))?;
+    if let Some(execution_id) = &request.execution_id {
+        let execution_id =
+            Uuid::parse_str(execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+        match find_execution(&execution_id) {
+            Some((state, context)) => {
+                workflow_id = Some(state.workflow_id);
+                project_id = project_id.or(Some(state.project_id));
+                inputs.input_prompt = state.input_prompt.clone();
+                if let Some(context) = context {
+                    for node_id in state.node_states.iter().map(|entry| entry.key().clone()) {
+                        inputs.outputs.insert(node_id.clone(), context.get_node_outputs(&node_id));
+                    }
+                    inputs.variables = context.get_all_variables();
+                }
+            }
+            None => {
+                let record = get_history_store()
+                    .get(&execution_id)
+                    .ok_or_else(|| format!("Execution not found: {}", execution_id))?;
+                workflow_id = record.workflow_id;
+                project_id = project_id.or(Some(record.project_id));
+                inputs.input_prompt = record.input_prompt;
+                inputs.outputs = record.outputs;
+            }
+        }
+    }
+
+    let graph = match (request.graph, workflow_id) {
+        (Some(graph), _) => graph,
+        (None, Some(workflow_id)) => WORKFLOWS
+            .get(&workflow_id)
+            .map(|workflow| workflow.graph.clone())
+            .ok_or_else(|| "The execution's workflow isn't saved; pass its graph".to_string())?,
+        (None, None) => return Err("Pass an execution ID or a graph".to_string()),
+    };
+    let graph = WorkflowGraph::from_json(&graph).map_err(|e| format!("Invalid graph: {}", e))?;
+
+    if let Some(input_prompt) = request.input_prompt {
+        inputs.input_prompt = input_prompt;
+    }
+    inputs.locale = project_id.and_then(|id| crate::commands::project::get_project_locale(&id));
+    inputs.variables.extend(request.sample_variables.unwrap_or_default());
+    for (node_id, text) in request.sample_outputs.unwrap_or_default() {
+        let agent_role = graph.get_node(&node_id).map(|node| node.agent_role.clone()).unwrap_or_default();
+        let output = AgentOutput {
+            agent_id: Uuid::nil(),
+            node_id: node_id.clone(),
+            agent_role,
+            data: OutputData::Text(text),
+            timestamp: Utc::now(),
+            tags: Vec::new(),
+        };
+        inputs.outputs.insert(node_id, vec![output]);
+    }
+
+    let mut config = EnhancedExecutionConfig::default();
+    if let Some(enable) = request.enable_data_flow {
+        config.enable_data_flow = enable;
+    }
+    if let Some(include) = request.include_original_prompt {
+        config.include_original_prompt = include;
+    }
+    let mut node_configs = parse_node_configs(request.node_configs, request.template_id)?;
+    node_library::apply_presets(&graph, &mut node_configs);
+    prompt_preview::preview(&graph, &request.node_id, &config, &node_configs, &inputs)
+}
+
+/// What an execution estimate assumes besides the graph
+#[derive(Debug, Default, Deserialize)]
+pub struct EstimateRequest {
+    pub node_configs: Option<HashMap<String, NodeConfigRequest>>,
+    pub template_id: Option<String>,
+    /// Pins to resolve models with; the saved workflow's when not given
+    pub model_pins: Option<ModelPins>,
+    /// Saved workflow the graph belongs to, whose past runs weigh most
+    pub workflow_id: Option<String>,
+    pub input_prompt: Option<String>,
+    /// Prices by model name prefix, on top of the built-in ones
+    pub prices: Option<HashMap<String, ModelPrice>>,
+    /// Estimates above this cost are flagged as needing confirmation
+    pub confirm_above_usd: Option<f64>,
+}
+
+/// Forecast the cost and duration of running `graph`, from the token usage
+/// and durations of past runs, before the user confirms it
+#[tauri::command]
+pub async fn estimate_execution(
+    graph: serde_json::Value,
+    config: Option<EstimateRequest>,
+) -> Result<ExecutionEstimate, String> {
+    let request = config.unwrap_or_default();
+    let workflow_id = request
+        .workflow_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    let graph = WorkflowGraph::from_json(&graph).map_err(|e| format!("Invalid graph: {}", e))?;
+
+    let mut node_configs = parse_node_configs(request.node_configs, request.template_id)?;
+    node_library::apply_presets(&graph, &mut node_configs);
+    let pins = request
+        .model_pins
+        .or_else(|| workflow_id.and_then(|id| WORKFLOWS.get(&id).and_then(|workflow| workflow.model_pins.clone())));
+    if let Some(pins) = pins {
+        // Priced as pinned, whether or not the backend offers the model
+        pins.apply(&graph, &mut node_configs, &[])?;
+    }
+
+    let config = EstimateConfig {
+        workflow_id,
+        input_prompt: request.input_prompt.unwrap_or_default(),
+        prices: request.prices.unwrap_or_default(),
+        confirm_above_usd: request.confirm_above_usd,
+    };
+    forecast::estimate(&graph, &node_configs, &get_history_store().list(), &config)
+}
+
+/// Tokens `text` takes up for `model`, the CLI's default when not given.
+/// `exact` is false for an estimate, which is always the case for Claude
+/// models
+#[tauri::command]
+pub async fn count_tokens(text: String, model: Option<String>) -> Result<TokenCount, String> {
+    Ok(TOKENIZERS.count(&text, Encoding::for_model(model.as_deref())))
+}
+
+/// Change how much an execution logs while it runs, for the whole execution
+/// or one node. `debug` (or `verbose`) adds condition evaluations,
+/// aggregation decisions and prompt assembly; `trace` adds full prompts.
+#[tauri::command]
+pub async fn set_execution_log_level(
+    execution_id: String,
+    level: String,
+    node_id: Option<String>,
+) -> Result<LogLevels, String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let level: LogLevel = level.parse()?;
+
+    let (state, _) = find_execution(&uuid).ok_or_else(|| format!("Execution not found: {}", execution_id))?;
+    if let Some(node_id) = &node_id {
+        if !state.node_states.contains_key(node_id) {
+            return Err(format!("Node not found in execution: {}", node_id));
+        }
+    }
+
+    EXECUTION_LOG.set_level(uuid, node_id.as_deref(), level);
+    Ok(EXECUTION_LOG.levels(&uuid))
+}
+
+/// An execution's log, oldest first, optionally for one node and down to
+/// `level` (default `trace`, i.e. everything recorded)
+#[tauri::command]
+pub async fn get_execution_log(
+    execution_id: String,
+    node_id: Option<String>,
+    level: Option<String>,
+) -> Result<Vec<LogEntry>, String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let level = level.map(|l| l.parse::<LogLevel>()).transpose()?.unwrap_or(LogLevel::Trace);
+
+    Ok(EXECUTION_LOG.entries(&uuid, node_id.as_deref(), level))
+}
+
+/// Working directory of a finished execution's project
+fn rollback_dir(execution_id: &Uuid) -> Result<std::path::PathBuf, String> {
+    let project_id = match find_execution(execution_id) {
+        Some((state, _)) if matches!(state.get_status(), ExecutionStatus::Pending | ExecutionStatus::Running) => {
+            return Err("Execution is still running; cancel it before undoing its changes".to_string());
+        }
+        Some((state, _)) => state.project_id,
+        None => get_history_store()
+            .get(execution_id)
+            .map(|record| record.project_id)
+            .ok_or_else(|| format!("Execution not found: {}", execution_id))?,
+    };
+
+    crate::commands::project::get_project_working_directory(&project_id)
+        .map(std::path::PathBuf::from)
+        .ok_or_else(|| format!("Project not found: {}", project_id))
+}
+
+/// Files the execution changed and what undoing it would do to each
+async fn rollback_preview(execution_id: &str) -> Result<(std::path::PathBuf, RollbackPreview), String> {
+    let uuid = Uuid::parse_str(execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let dir = rollback_dir(&uuid)?;
+
+    // Without an activity feed (e.g. after a restart) every difference is listed
+    let touched = ACTIVITY_STORE.touched_files(uuid);
+    let touched = (!touched.is_empty()).then_some(&touched);
+    let preview = rollback::preview(&dir, &uuid, touched).await?;
+    Ok((dir, preview))
+}
+
+/// Preview what `rollback_execution_changes` would undo
+#[tauri::command]
+pub async fn preview_rollback(execution_id: String) -> Result<RollbackPreview, String> {
+    Ok(rollback_preview(&execution_id).await?.1)
+}
+
+/// Put the files an execution changed back the way they were when it
+/// started, returning what was undone
+#[tauri::command]
+pub async fn rollback_execution_changes(execution_id: String) -> Result<RollbackPreview, String> {
+    let (dir, preview) = rollback_preview(&execution_id).await?;
+    rollback::apply(&dir, &preview).await?;
+    log::info!("Rolled back {} file(s) changed by execution {}", preview.files.len(), execution_id);
+    Ok(preview)
+}
+
+/// An isolated execution's workspace and the changes waiting in it
+#[derive(Debug, Serialize)]
+pub struct WorkspaceReview {
+    pub workspace: WorkspaceInfo,
+    pub changes: Vec<WorkspaceChange>,
+}
+
+/// Isolated workspaces that haven't been approved or discarded
+#[tauri::command]
+pub async fn list_isolated_workspaces() -> Result<Vec<WorkspaceInfo>, String> {
+    Ok(ISOLATED_WORKSPACES.list())
+}
+
+/// Changes an isolated execution made, for review before approving
+#[tauri::command]
+pub async fn get_workspace_changes(execution_id: String) -> Result<WorkspaceReview, String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
+
+    tokio::task::spawn_blocking(move || {
+        Ok(WorkspaceReview {
+            workspace: ISOLATED_WORKSPACES.get(&uuid).map_err(|e| e.to_string())?,
+            changes: ISOLATED_WORKSPACES.changes(&uuid).map_err(|e| e.to_string())?,
+        })
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Merge an isolated execution's changes into the project: all of them, or
+/// only `paths`. Files also changed in the project meanwhile are skipped
+/// unless `overwrite_conflicts`. Dependencies a supply-chain check flagged
+/// block the merge unless `accept_flagged_dependencies`. The workspace is
+/// removed once nothing is left to merge.
+#[tauri::command]
+pub async fn approve_workspace_changes(
+    execution_id: String,
+    paths: Option<Vec<String>>,
+    overwrite_conflicts: Option<bool>,
+    accept_flagged_dependencies: Option<bool>,
+) -> Result<MergeReport, String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let execution = find_execution(&uuid);
+    if execution.as_ref().is_some_and(|(state, _)| {
+        matches!(state.get_status(), ExecutionStatus::Pending | ExecutionStatus::Running)
+    }) {
+        return Err("Execution is still running".to_string());
+    }
+    if let Some(blocker) = SUPPLY_CHAIN_REPORTS.approval_blocker(&uuid) {
+        if !accept_flagged_dependencies.unwrap_or(false) {
+            return Err(format!("{}. Accept them to merge anyway.", blocker));
+        }
+        let mut entry = AuditEntry::new(AuditAction::FlaggedDependenciesAccepted, blocker)
+            .execution(uuid)
+            .details(serde_json::json!(SUPPLY_CHAIN_REPORTS.flagged(&uuid)));
+        if let Some((state, _)) = &execution {
+            entry = entry.project(state.project_id);
+        }
+        AUDIT_LOG.record(entry);
+    }
+
+    tokio::task::spawn_blocking(move || {
+        let report = ISOLATED_WORKSPACES
+            .merge(&uuid, paths.as_deref(), overwrite_conflicts.unwrap_or(false))
+            .map_err(|e| e.to_string())?;
+        if report.remaining == 0 {
+            ISOLATED_WORKSPACES.discard(&uuid).map_err(|e| e.to_string())?;
+        }
+        Ok(report)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Throw away an isolated execution's workspace without merging anything
+#[tauri::command]
+pub async fn discard_workspace(execution_id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
+    if find_execution(&uuid).is_some_and(|(state, _)| {
+        matches!(state.get_status(), ExecutionStatus::Pending | ExecutionStatus::Running)
+    }) {
+        return Err("Execution is still running; cancel it first".to_string());
+    }
+
+    tokio::task::spawn_blocking(move || ISOLATED_WORKSPACES.discard(&uuid).map_err(|e| e.to_string()))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// List available checkpoints
+#[tauri::command]
+pub async fn list_checkpoints() -> Result<Vec<CheckpointSummaryResponse>, String> {
+    let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
+        .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
+
+    let summaries = manager
+        .list()
+        .map_err(|e| format!("Failed to list checkpoints: {}", e))?;
+
+    Ok(summaries.into_iter().map(CheckpointSummaryResponse::from).collect())
+}
+
+#[derive(Debug, Serialize)]
+pub struct CheckpointSummaryResponse {
+    pub id: String,
+    pub execution_id: String,
+    pub workflow_id: String,
+    pub status: String,
+    pub cancel_reason: Option<String>,
+    pub progress: f32,
+    pub total_nodes: usize,
+    pub completed_nodes: usize,
+    pub failed_nodes: usize,
+    pub pending_nodes: usize,
+    pub checkpoint_at: String,
+    pub current_level: usize,
+    pub total_levels: usize,
+}
+
+impl From<CheckpointSummary> for CheckpointSummaryResponse {
+    fn from(s: CheckpointSummary) -> Self {
+        Self {
+            id: s.id.to_string(),
+            execution_id: s.execution_id.to_string(),
+            workflow_id: s.workflow_id.to_string(),
+            status: format!("{:?}", s.status),
+            cancel_reason: s.cancel_reason,
+            progress: s.progress,
+            total_nodes: s.total_nodes,
+            completed_nodes: s.completed_nodes,
+            failed_nodes: s.failed_nodes,
+            pending_nodes: s.pending_nodes,
+            checkpoint_at: s.checkpoint_at.to_rfc3339(),
+            current_level: s.current_level,
+            total_levels: s.total_levels,
+        }
+    }
+}
+
+/// List checkpoints for a specific execution
+#[tauri::command]
+pub async fn list_execution_checkpoints(
+    execution_id: String,
+) -> Result<Vec<CheckpointSummaryResponse>, String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
+
+    let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
+        .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
+
+    let summaries = manager
+        .list_for_execution(&uuid)
+        .map_err(|e| format!("Failed to list checkpoints: {}", e))?;
+
+    Ok(summaries.into_iter().map(CheckpointSummaryResponse::from).collect())
+}
+
+/// Clean up old checkpoints
+#[tauri::command]
+pub async fn cleanup_checkpoints(
+    keep_per_execution: Option<usize>,
+) -> Result<usize, String> {
+    let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
+        .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
+
+    let deleted = manager
+        .cleanup(keep_per_execution.unwrap_or_else(|| SETTINGS.get().retention.checkpoints_per_execution))
+        .map_err(|e| format!("Failed to cleanup checkpoints: {}", e))?;
+
+    Ok(deleted)
+}
+
+/// Export a checkpoint to a file (or into a directory) for moving the
+/// execution to another machine; returns the written path
+#[tauri::command]
+pub async fn export_checkpoint(checkpoint_id: String, path: String) -> Result<String, String> {
+    let uuid = Uuid::parse_str(&checkpoint_id)
+        .map_err(|e| format!("Invalid checkpoint ID: {}", e))?;
+
+    let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
+        .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
+
+    let written = manager
+        .export(&uuid, std::path::Path::new(&path))
+        .map_err(|e| format!("Failed to export checkpoint: {}", e))?;
+
+    Ok(written.to_string_lossy().to_string())
+}
+
+/// Import an exported checkpoint under fresh IDs, optionally moving it to
+/// a local project
+#[tauri::command]
+pub async fn import_checkpoint(
+    path: String,
+    project_id: Option<String>,
+) -> Result<CheckpointSummaryResponse, String> {
+    let project_id = project_id
+        .map(|id| Uuid::parse_str(&id).map_err(|e| format!("Invalid project ID: {}", e)))
+        .transpose()?;
+
+    let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
+        .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
+
+    let checkpoint = manager
+        .import(std::path::Path::new(&path), project_id)
+        .map_err(|e| format!("Failed to import checkpoint: {}", e))?;
+
+    Ok(CheckpointSummaryResponse::from(checkpoint.get_summary()))
+}
+
+/// Get available agent roles
+#[tauri::command]
+pub async fn get_available_agent_roles() -> Result<Vec<AgentRoleInfo>, String> {
+    Ok(vec![
+        AgentRoleInfo {
+            id: "orchestrator".to_string(),
+            name: "Orchestrator".to_string(),
+            description: "Plans and coordinates work between other agents".to_string(),
+            capabilities: vec![
+                "Task decomposition".to_string(),
+                "Dependency analysis".to_string(),
+                "Agent coordination".to_string(),
+            ],
+        },
+        AgentRoleInfo {
+            id: "architect".to_string(),
+            name: "Architect".to_string(),
+            description: "Designs system architecture and technical specifications".to_string(),
             capabilities: vec![
                 "System design".to_string(),
                 "API design".to_string(),
@@ -773,9 +1763,30 @@ pub async fn get_aggregation_strategies() -> Result<Vec<AggregationStrategyInfo>
             name: "Template".to_string(),
             description: "Use a custom template to combine outputs".to_string(),
         },
+        AggregationStrategyInfo {
+            id: "plugin".to_string(),
+            name: "Plugin".to_string(),
+            description: "Combine outputs with a WASM aggregator plugin".to_string(),
+        },
     ])
 }
 
+/// Run an aggregation strategy, and optionally a transform, on sample
+/// outputs, showing what a node would receive without running any agents
+#[tauri::command]
+pub async fn preview_aggregation(
+    strategy: AggregationStrategy,
+    sample_outputs: Vec<SampleOutput>,
+    transform: Option<OutputTransform>,
+) -> Result<AggregationPreview, String> {
+    let config = NodeAggregationConfig {
+        strategy,
+        transform,
+        ..Default::default()
+    };
+    Ok(config.preview(&sample_outputs))
+}
+
 #[derive(Debug, Serialize)]
 pub struct AggregationStrategyInfo {
     pub id: String,
@@ -835,6 +1846,12 @@ pub async fn get_condition_types() -> Result<Vec<ConditionTypeInfo>, String> {
             description: "Execute if a context variable is truthy".to_string(),
             params: vec!["variable".to_string()],
         },
+        ConditionTypeInfo {
+            id: "plugin".to_string(),
+            name: "Plugin".to_string(),
+            description: "Execute if a WASM condition plugin returns true".to_string(),
+            params: vec!["name".to_string(), "params".to_string()],
+        },
     ])
 }
 
@@ -846,35 +1863,101 @@ pub struct ConditionTypeInfo {
     pub params: Vec<String>,
 }
 
+// =============================================================================
+// Node Library Commands
+// =============================================================================
+
+/// Node presets, built-in ones first; with a query, those whose name,
+/// description or tags match it
+#[tauri::command]
+pub async fn list_node_presets(query: Option<String>) -> Result<Vec<NodePreset>, String> {
+    Ok(match query.filter(|query| !query.trim().is_empty()) {
+        Some(query) => NODE_LIBRARY.search(query.trim()),
+        None => NODE_LIBRARY.list(),
+    })
+}
+
+#[tauri::command]
+pub async fn get_node_preset(preset_id: String) -> Result<NodePreset, String> {
+    NODE_LIBRARY
+        .get(&preset_id)
+        .ok_or_else(|| format!("Preset not found: {}", preset_id))
+}
+
+/// Add or replace a user preset. Nodes referencing it pick up the change
+/// on their next run.
+#[tauri::command]
+pub async fn save_node_preset(preset: NodePreset) -> Result<NodePreset, String> {
+    NODE_LIBRARY.save(preset)
+}
+
+/// Delete a user preset; false if there was none
+#[tauri::command]
+pub async fn delete_node_preset(preset_id: String) -> Result<bool, String> {
+    NODE_LIBRARY.delete(&preset_id)
+}
+
 // =============================================================================
 // Template Commands
 // =============================================================================
 
+/// Templates as responses, named and described in the language of the
+/// project's locale when it has one
+fn localized_templates(
+    templates: Vec<WorkflowTemplate>,
+    project_id: Option<&str>,
+) -> Result<Vec<WorkflowTemplateResponse>, String> {
+    let project_locale = match project_id {
+        Some(project_id) => {
+            let id = Uuid::parse_str(project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+            crate::commands::project::get_project_locale(&id)
+        }
+        None => None,
+    };
+    Ok(templates
+        .into_iter()
+        .map(|mut template| {
+            if let Some(tag) = &project_locale {
+                locale::localize_template(&mut template, tag);
+            }
+            WorkflowTemplateResponse::from(template)
+        })
+        .collect())
+}
+
 /// List all available workflow templates
 #[tauri::command]
-pub async fn list_workflow_templates() -> Result<Vec<WorkflowTemplateResponse>, String> {
-    let templates = crate::workflow::get_builtin_templates();
-    Ok(templates.into_iter().map(WorkflowTemplateResponse::from).collect())
+pub async fn list_workflow_templates(project_id: Option<String>) -> Result<Vec<WorkflowTemplateResponse>, String> {
+    localized_templates(crate::workflow::get_builtin_templates(), project_id.as_deref())
 }
 
 /// Get a specific workflow template by ID
 #[tauri::command]
-pub async fn get_workflow_template(template_id: String) -> Result<WorkflowTemplateResponse, String> {
-    crate::workflow::get_template(&template_id)
-        .map(WorkflowTemplateResponse::from)
-        .ok_or_else(|| format!("Template not found: {}", template_id))
+pub async fn get_workflow_template(
+    template_id: String,
+    project_id: Option<String>,
+) -> Result<WorkflowTemplateResponse, String> {
+    let template = crate::workflow::get_template(&template_id)
+        .ok_or_else(|| format!("Template not found: {}", template_id))?;
+    let mut localized = localized_templates(vec![template], project_id.as_deref())?;
+    Ok(localized.remove(0))
 }
 
 /// Search workflow templates by query
 #[tauri::command]
-pub async fn search_workflow_templates(query: String) -> Result<Vec<WorkflowTemplateResponse>, String> {
-    let templates = crate::workflow::search_templates(&query);
-    Ok(templates.into_iter().map(WorkflowTemplateResponse::from).collect())
+pub async fn search_workflow_templates(
+    query: String,
+    project_id: Option<String>,
+) -> Result<Vec<WorkflowTemplateResponse>, String> {
+    localized_templates(crate::workflow::search_templates(&query), project_id.as_deref())
 }
 
 /// Get templates by category
 #[tauri::command]
-pub async fn get_templates_by_category(category: String) -> Result<Vec<WorkflowTemplateResponse>, String> {
+pub async fn get_templates_by_category(
+    category: String,
+    project_id: Option<String>,
+) -> Result<Vec<WorkflowTemplateResponse>, String> {
     let category = match category.to_lowercase().as_str() {
         "development" => TemplateCategory::Development,
         "testing" => TemplateCategory::Testing,
@@ -888,22 +1971,59 @@ pub async fn get_templates_by_category(category: String) -> Result<Vec<WorkflowT
         _ => return Err(format!("Unknown category: {}", category)),
     };
 
-    let templates = crate::workflow::get_templates_by_category(category);
-    Ok(templates.into_iter().map(WorkflowTemplateResponse::from).collect())
+    localized_templates(crate::workflow::get_templates_by_category(category), project_id.as_deref())
+}
+
+#[derive(Debug, Serialize)]
+pub struct TemplateInstanceResponse {
+    pub tasks: Vec<PlannedTaskResponse>,
+    /// Placeholders left unresolved and variables left unused
+    pub substitutions: SubstitutionReport,
 }
 
-/// Instantiate a template with variable values
+/// Instantiate a template with variable values. With
+/// `fail_on_unresolved`, placeholders no variable was given for are an
+/// error rather than left in the tasks.
 #[tauri::command]
 pub async fn instantiate_template(
     template_id: String,
     variables: HashMap<String, String>,
-) -> Result<Vec<PlannedTaskResponse>, String> {
+    fail_on_unresolved: Option<bool>,
+) -> Result<TemplateInstanceResponse, String> {
     let template = crate::workflow::get_template(&template_id)
         .ok_or_else(|| format!("Template not found: {}", template_id))?;
+    USER_STATE.record_use(ItemKind::Template, &template_id);
 
-    let tasks = template.instantiate(&variables);
-    Ok(tasks.into_iter().map(PlannedTaskResponse::from).collect())
-}
+    let secrets = SETTINGS.get().backends.secrets;
+    for variable in template.variables.iter().filter(|v| matches!(v.variable_type, crate::workflow::VariableType::Secret)) {
+        if let Some(name) = variables.get(&variable.name).filter(|name| !secrets.contains_key(*name)) {
+            return Err(format!("Secret '{}' for variable '{}' is not set", name, variable.name));
+        }
+    }
+
+    let substitutions = template.audit_substitution(&variables);
+    if fail_on_unresolved.unwrap_or(false) && !substitutions.is_clean() {
+        let unresolved: Vec<String> = substitutions
+            .unresolved
+            .iter()
+            .map(|(name, tasks)| format!("{{{{{}}}}} (in {})", name, tasks.join(", ")))
+            .collect();
+        return Err(format!("Unresolved placeholders: {}", unresolved.join("; ")));
+    }
+
+    let tasks = template
+        .instantiate(&variables)
+        .into_iter()
+        .map(|task| {
+            let node_config = template.node_configs.get(&task.id).cloned();
+            PlannedTaskResponse {
+                node_config,
+                ..PlannedTaskResponse::from(task)
+            }
+        })
+        .collect();
+    Ok(TemplateInstanceResponse { tasks, substitutions })
+}
 
 #[derive(Debug, Serialize)]
 pub struct WorkflowTemplateResponse {
@@ -934,6 +2054,9 @@ pub struct PlannedTaskResponse {
     pub agent_role: String,
     pub description: String,
     pub depends_on: Vec<String>,
+    /// Defaults the template ships for the task's node
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub node_config: Option<crate::workflow::TemplateNodeConfig>,
 }
 
 impl From<WorkflowTemplate> for WorkflowTemplateResponse {
@@ -952,6 +2075,8 @@ impl From<WorkflowTemplate> for WorkflowTemplateResponse {
                     crate::workflow::VariableType::Boolean => ("boolean".to_string(), None),
                     crate::workflow::VariableType::FilePath => ("file_path".to_string(), None),
                     crate::workflow::VariableType::Choice { options } => ("choice".to_string(), Some(options)),
+                    crate::workflow::VariableType::Secret => ("secret".to_string(), None),
+                    crate::workflow::VariableType::MultiLineText => ("multi_line_text".to_string(), None),
                 };
                 TemplateVariableResponse {
                     name: v.name,
@@ -975,6 +2100,7 @@ impl From<crate::workflow::orchestrator::PlannedTask> for PlannedTaskResponse {
             agent_role: t.agent_role,
             description: t.description,
             depends_on: t.depends_on,
+            node_config: None,
         }
     }
 }
@@ -986,8 +2112,8 @@ impl From<crate::workflow::orchestrator::PlannedTask> for PlannedTaskResponse {
 // Global history store
 static HISTORY_STORE: OnceCell<ExecutionHistoryStore> = OnceCell::new();
 
-fn get_history_store() -> &'static ExecutionHistoryStore {
-    HISTORY_STORE.get_or_init(|| ExecutionHistoryStore::new(1000))
+pub(crate) fn get_history_store() -> &'static ExecutionHistoryStore {
+    HISTORY_STORE.get_or_init(|| ExecutionHistoryStore::new(SETTINGS.get().retention.history_max_records))
 }
 
 /// Get execution history statistics
@@ -1023,6 +2149,630 @@ pub async fn search_execution_history(query: String) -> Result<Vec<ExecutionReco
     Ok(records.into_iter().map(ExecutionRecordSummary::from).collect())
 }
 
+/// Node configs of a run preset, checked as `execute_enhanced_workflow`
+/// checks them
+fn preset_node_configs(configs: &HashMap<String, serde_json::Value>) -> Result<HashMap<String, EnhancedNodeConfig>, String> {
+    let configs = configs
+        .iter()
+        .map(|(node_id, config)| {
+            serde_json::from_value::<NodeConfigRequest>(config.clone())
+                .map(|config| (node_id.clone(), config))
+                .map_err(|e| format!("Invalid config for node {}: {}", node_id, e))
+        })
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    parse_node_configs(Some(configs), None)
+}
+
+/// Save a run preset of a saved workflow, or replace the one with its ID
+#[tauri::command]
+pub async fn save_run_preset(preset: RunPresetInput) -> Result<RunPreset, String> {
+    if !WORKFLOWS.contains_key(&preset.workflow_id) {
+        return Err("Workflow not found".to_string());
+    }
+    if crate::commands::project::get_project_name(&preset.project_id).is_none() {
+        return Err("Project not found".to_string());
+    }
+    // Bad overrides fail when saved, not on some later run
+    preset_node_configs(&preset.node_configs)?;
+    RUN_PRESETS.save(preset)
+}
+
+/// Run presets of one saved workflow, or of all, by name
+#[tauri::command]
+pub async fn list_run_presets(workflow_id: Option<String>) -> Result<Vec<RunPreset>, String> {
+    let workflow_id = workflow_id
+        .map(|id| Uuid::parse_str(&id).map_err(|e| format!("Invalid workflow ID: {}", e)))
+        .transpose()?;
+    Ok(RUN_PRESETS.list(workflow_id.as_ref()))
+}
+
+/// Delete a run preset; false if there was none
+#[tauri::command]
+pub async fn delete_run_preset(preset_id: String) -> Result<bool, String> {
+    let uuid = Uuid::parse_str(&preset_id).map_err(|e| format!("Invalid preset ID: {}", e))?;
+    Ok(RUN_PRESETS.delete(&uuid))
+}
+
+/// Start the workflow of a run preset with its arguments
+pub(crate) fn start_preset(app: &AppHandle, preset_id: &Uuid) -> Result<Uuid, String> {
+    let preset = RUN_PRESETS.get(preset_id).ok_or_else(|| format!("Preset not found: {}", preset_id))?;
+    let execution_id = start_workflow_with(
+        app,
+        &preset.workflow_id.to_string(),
+        &preset.project_id.to_string(),
+        preset.prompt(),
+        preset.variables.clone(),
+        preset_node_configs(&preset.node_configs)?,
+    )?;
+    RUN_PRESETS.record_run(preset_id, execution_id);
+    Ok(execution_id)
+}
+
+/// Run a saved workflow with a preset's project, prompt, variables and
+/// node configs; returns the execution ID
+#[tauri::command]
+pub async fn run_preset(app: AppHandle, preset_id: String) -> Result<String, String> {
+    let uuid = Uuid::parse_str(&preset_id).map_err(|e| format!("Invalid preset ID: {}", e))?;
+    start_preset(&app, &uuid).map(|execution_id| execution_id.to_string())
+}
+
+/// Executions of the history searched by quick search
+const QUICK_SEARCH_EXECUTIONS: usize = 50;
+
+/// How far favorites and often used items of a kind move up in quick search
+fn usage_boosts(kind: ItemKind) -> HashMap<String, f64> {
+    USER_STATE
+        .suggested(kind, usize::MAX)
+        .into_iter()
+        .map(|item| (item.id, if item.favorite { 4.0 } else { 0.0 } + item.use_count.min(10) as f64 * 0.3))
+        .collect()
+}
+
+fn quick_search_candidates() -> Vec<quick_search::Candidate> {
+    let mut candidates = Vec::new();
+
+    let boosts = usage_boosts(ItemKind::Workflow);
+    candidates.extend(WORKFLOWS.iter().map(|entry| {
+        let workflow = entry.value();
+        let id = workflow.id.to_string();
+        quick_search::Candidate {
+            kind: MatchKind::Workflow,
+            title: workflow.name.clone(),
+            subtitle: workflow.description.clone(),
+            keywords: workflow.description.iter().cloned().collect(),
+            boost: boosts.get(&id).copied().unwrap_or_default(),
+            id,
+        }
+    }));
+
+    let boosts = usage_boosts(ItemKind::Template);
+    candidates.extend(crate::workflow::get_builtin_templates().into_iter().map(|template| {
+        let mut keywords = template.tags;
+        keywords.push(template.description.clone());
+        quick_search::Candidate {
+            kind: MatchKind::Template,
+            boost: boosts.get(&template.id).copied().unwrap_or_default(),
+            id: template.id,
+            title: template.name,
+            subtitle: Some(template.description),
+            keywords,
+        }
+    }));
+
+    let boosts = usage_boosts(ItemKind::Project);
+    candidates.extend(crate::commands::project::all_projects().into_iter().map(|project| {
+        let id = project.id.to_string();
+        let mut keywords = project.languages;
+        keywords.extend(project.description);
+        quick_search::Candidate {
+            kind: MatchKind::Project,
+            title: project.name,
+            subtitle: Some(project.working_directory),
+            keywords,
+            boost: boosts.get(&id).copied().unwrap_or_default(),
+            id,
+        }
+    }));
+
+    candidates.extend(get_history_store().list().into_iter().take(QUICK_SEARCH_EXECUTIONS).map(|record| {
+        let mut keywords = record.tags;
+        keywords.push(record.input_prompt);
+        quick_search::Candidate {
+            kind: MatchKind::Execution,
+            id: record.id.to_string(),
+            title: record.workflow_name,
+            subtitle: Some(format!(
+                "{} · {:?} · {}",
+                record.project_name,
+                record.status,
+                record.started_at.format("%Y-%m-%d %H:%M")
+            )),
+            keywords,
+            boost: 0.0,
+        }
+    }));
+    candidates
+}
+
+/// Workflows, templates, projects and recent executions matching `query`
+/// fuzzily, best first, for the command palette. Without a query,
+/// favorites and often used items.
+#[tauri::command]
+pub async fn quick_search(query: String, limit: Option<usize>) -> Result<Vec<QuickMatch>, String> {
+    Ok(quick_search::rank(&query, quick_search_candidates(), limit.unwrap_or(20)))
+}
+
+/// What `quick_run` started, or the workflows to choose from
+#[derive(Debug, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum QuickRunResult {
+    Started {
+        execution_id: String,
+        workflow_id: String,
+        workflow_name: String,
+        project_id: String,
+    },
+    /// Nothing was run: the query matched loosely or tied; pick one of these
+    Ambiguous { candidates: Vec<QuickMatch> },
+}
+
+/// Workflows offered when `quick_run` won't pick one itself
+const QUICK_RUN_CANDIDATES: usize = 5;
+
+/// Run the saved workflow best matching `query`, in `project_id` or else
+/// the most recently used project. Only a sure match that no other workflow
+/// ties is run; otherwise the closest workflows are returned to pick from,
+/// rather than guessing.
+#[tauri::command]
+pub async fn quick_run(
+    app: AppHandle,
+    query: String,
+    project_id: Option<String>,
+    input_prompt: String,
+) -> Result<QuickRunResult, String> {
+    if query.trim().is_empty() {
+        return Err("Query is empty".to_string());
+    }
+    let workflows: Vec<_> = quick_search_candidates()
+        .into_iter()
+        .filter(|candidate| candidate.kind == MatchKind::Workflow)
+        .collect();
+    let matches = quick_search::rank(&query, workflows, QUICK_RUN_CANDIDATES);
+    let best = match matches.as_slice() {
+        [] => return Err(format!("No workflow matches '{}'", query)),
+        [first, second, ..] if first.score == second.score => None,
+        [first, ..] => Some(first).filter(|first| quick_search::is_confident(&query, &first.title)),
+    };
+    let Some(best) = best else {
+        return Ok(QuickRunResult::Ambiguous { candidates: matches });
+    };
+
+    let project_id = match project_id {
+        Some(project_id) => project_id,
+        None => USER_STATE
+            .recent(Some(ItemKind::Project), Some(1))
+            .pop()
+            .map(|item| item.id)
+            .ok_or("No project given and none used recently".to_string())?,
+    };
+    let execution_id = start_workflow(&app, &best.id, &project_id, input_prompt)?;
+    Ok(QuickRunResult::Started {
+        execution_id: execution_id.to_string(),
+        workflow_id: best.id.clone(),
+        workflow_name: best.title.clone(),
+        project_id,
+    })
+}
+
+/// Annotate a node of a finished execution, e.g. to mark its output as
+/// wrong. Without an author, the OS user name is used.
+#[tauri::command]
+pub async fn annotate_execution_node(
+    execution_id: String,
+    node_id: String,
+    text: String,
+    severity: AnnotationSeverity,
+    author: Option<String>,
+) -> Result<NodeAnnotation, String> {
+    let uuid = Uuid::parse_str(&execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    get_history_store().annotate_node(&uuid, &node_id, &author_or_user(author), &text, severity)
+}
+
+/// The given author, else the OS user name
+fn author_or_user(author: Option<String>) -> String {
+    author
+        .or_else(|| std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok())
+        .unwrap_or_default()
+}
+
+/// Give a node's output a thumbs up or down, replacing the author's
+/// earlier rating; no rating takes it back
+#[tauri::command]
+pub async fn rate_execution_node(
+    execution_id: String,
+    node_id: String,
+    rating: Option<Rating>,
+    author: Option<String>,
+) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    get_history_store().rate_node(&uuid, &node_id, &author_or_user(author), rating)
+}
+
+/// How each system prompt version of a role fared across the execution
+/// history: failures, ratings, and outputs marked wrong
+#[tauri::command]
+pub async fn analyze_prompt_performance(role: String) -> Result<PromptPerformance, String> {
+    Ok(prompt_performance::analyze(&role, &get_history_store().list()))
+}
+
+/// Annotations of an execution's nodes, of one node or all, oldest first
+#[tauri::command]
+pub async fn list_execution_annotations(execution_id: String, node_id: Option<String>) -> Result<Vec<NodeAnnotation>, String> {
+    let uuid = Uuid::parse_str(&execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let record = get_history_store()
+        .get(&uuid)
+        .ok_or_else(|| format!("Execution record not found: {}", execution_id))?;
+    Ok(record
+        .annotations
+        .into_iter()
+        .filter(|annotation| node_id.as_ref().map_or(true, |node_id| annotation.node_id == *node_id))
+        .collect())
+}
+
+/// Remove a node annotation; false if there was none
+#[tauri::command]
+pub async fn remove_execution_annotation(execution_id: String, annotation_id: String) -> Result<bool, String> {
+    let uuid = Uuid::parse_str(&execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let annotation_id = Uuid::parse_str(&annotation_id).map_err(|e| format!("Invalid annotation ID: {}", e))?;
+    Ok(get_history_store().remove_annotation(&uuid, &annotation_id))
+}
+
+/// The versions, models and commit an execution started with, from its
+/// history record or, while it runs, its live state
+#[tauri::command]
+pub async fn get_execution_environment(execution_id: String) -> Result<EnvironmentFingerprint, String> {
+    let uuid = Uuid::parse_str(&execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let environment = match get_history_store().get(&uuid) {
+        Some(record) => record.environment,
+        None => find_execution(&uuid)
+            .ok_or_else(|| format!("Execution not found: {}", execution_id))?
+            .0
+            .environment
+            .read()
+            .clone(),
+    };
+    environment.ok_or_else(|| format!("No environment was captured for execution {}", execution_id))
+}
+
+/// Drop cached node results for a project, or for every project, so the
+/// nodes run again; returns how many were dropped
+#[tauri::command]
+pub async fn clear_node_result_cache(project_id: Option<String>) -> Result<usize, String> {
+    let project_id = project_id
+        .map(|id| Uuid::parse_str(&id).map_err(|e| format!("Invalid project ID: {}", e)))
+        .transpose()?;
+    Ok(RESULT_CACHE.clear(project_id))
+}
+
+/// Replace a saved workflow's model pins; `None` removes them
+#[tauri::command]
+pub async fn set_workflow_model_pins(
+    state: State<'_, Arc<AppState>>,
+    workflow_id: String,
+    pins: Option<ModelPins>,
+) -> Result<WorkflowResponse, String> {
+    let uuid = Uuid::parse_str(&workflow_id).map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    let mut workflow = WORKFLOWS.get_mut(&uuid).ok_or("Workflow not found".to_string())?;
+    workflow.model_pins = pins;
+
+    #[cfg(feature = "database")]
+    crate::db::notify::publish_workflow(&state, &workflow);
+    #[cfg(not(feature = "database"))]
+    let _ = state;
+
+    Ok(WorkflowResponse::from(workflow.value()))
+}
+
+/// Replace a saved workflow's environment defaults. Values that come back
+/// redacted keep what they were.
+#[tauri::command]
+pub async fn set_workflow_env(workflow_id: String, mut env: BTreeMap<String, EnvVar>) -> Result<WorkflowResponse, String> {
+    let uuid = Uuid::parse_str(&workflow_id).map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    let mut workflow = WORKFLOWS.get_mut(&uuid).ok_or("Workflow not found".to_string())?;
+    env_vars::restore_masked(&mut env, &workflow.env);
+    env_vars::validate(&env)?;
+    workflow.env = env;
+    Ok(WorkflowResponse::from(workflow.value()))
+}
+
+/// Replace a saved workflow's SLA rules; an empty list removes them
+#[tauri::command]
+pub async fn set_sla_rules(workflow_id: String, rules: Vec<SlaRule>) -> Result<SlaStatus, String> {
+    let uuid = Uuid::parse_str(&workflow_id).map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    if !WORKFLOWS.contains_key(&uuid) {
+        return Err("Workflow not found".to_string());
+    }
+    SLA_STORE.set_rules(uuid, rules);
+    Ok(SLA_STORE.status(uuid, get_history_store(), Utc::now()))
+}
+
+/// A saved workflow's SLA rules and how each stands against its history
+#[tauri::command]
+pub async fn get_sla_status(workflow_id: String) -> Result<SlaStatus, String> {
+    let uuid = Uuid::parse_str(&workflow_id).map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    Ok(SLA_STORE.status(uuid, get_history_store(), Utc::now()))
+}
+
+/// How a project is doing, for its dashboard card: latest results,
+/// failing workflows, stuck executions, open approvals and disk usage
+#[tauri::command]
+pub async fn get_project_health(project_id: String) -> Result<ProjectHealth, String> {
+    let uuid = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    let working_directory = crate::commands::project::get_project_working_directory(&uuid).ok_or("Project not found".to_string())?;
+
+    let active: Vec<ActiveExecution> = all_executions()
+        .into_iter()
+        .filter(|state| state.project_id == uuid)
+        .filter(|state| matches!(state.get_status(), ExecutionStatus::Pending | ExecutionStatus::Running))
+        .map(|state| ActiveExecution {
+            execution_id: state.execution_id,
+            workflow_name: WORKFLOWS
+                .get(&state.workflow_id)
+                .map(|workflow| workflow.name.clone())
+                .unwrap_or_else(|| "Orchestrated Workflow".to_string()),
+            status: state.get_status(),
+            started_at: state.started_at,
+        })
+        .collect();
+    let project_executions: Vec<Uuid> = all_executions()
+        .iter()
+        .filter(|state| state.project_id == uuid)
+        .map(|state| state.execution_id)
+        .collect();
+    let open_approvals = APPROVAL_STORE
+        .pending(None)
+        .into_iter()
+        .filter(|request| project_executions.contains(&request.execution_id))
+        .collect();
+
+    let disk_usage = tokio::task::spawn_blocking(move || project_health::disk_usage(std::path::Path::new(&working_directory)))
+        .await
+        .map_err(|e| format!("Disk usage scan panicked: {}", e))?
+        .map_err(|e| log::warn!("Failed to measure disk usage of project {}: {}", uuid, e))
+        .ok();
+
+    Ok(project_health::summarize(
+        uuid,
+        &get_history_store().list_for_project(&uuid),
+        &active,
+        open_approvals,
+        disk_usage,
+        Utc::now(),
+    ))
+}
+
+/// Replace a project's coverage and lint thresholds, used by its check gates
+#[tauri::command]
+pub async fn set_gate_thresholds(project_id: String, thresholds: GateThresholds) -> Result<GateThresholds, String> {
+    let uuid = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    if crate::commands::project::get_project_name(&uuid).is_none() {
+        return Err("Project not found".to_string());
+    }
+    if thresholds.min_coverage.is_some_and(|min| !(0.0..=100.0).contains(&min)) {
+        return Err("Minimum coverage must be between 0 and 100".to_string());
+    }
+    GATE_THRESHOLDS.set(uuid, thresholds.clone());
+    Ok(thresholds)
+}
+
+/// A project's gate thresholds, or the defaults if none were set
+#[tauri::command]
+pub async fn get_gate_thresholds(project_id: String) -> Result<GateThresholds, String> {
+    let uuid = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    Ok(GATE_THRESHOLDS.get(&uuid))
+}
+
+/// Replace a project's auto-cancel policy, recording the change in the
+/// audit log
+#[tauri::command]
+pub async fn set_auto_cancel_policy(project_id: String, policy: AutoCancelPolicy) -> Result<AutoCancelPolicy, String> {
+    let uuid = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    if crate::commands::project::get_project_name(&uuid).is_none() {
+        return Err("Project not found".to_string());
+    }
+    AUTO_CANCEL_POLICIES.set(uuid, policy)?;
+    Ok(policy)
+}
+
+/// A project's auto-cancel policy; without limits if none was set
+#[tauri::command]
+pub async fn get_auto_cancel_policy(project_id: String) -> Result<AutoCancelPolicy, String> {
+    let uuid = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    Ok(AUTO_CANCEL_POLICIES.get(&uuid))
+}
+
+/// Audit log entries, newest first, of one project when given
+#[tauri::command]
+pub async fn get_audit_log(project_id: Option<String>, limit: Option<usize>) -> Result<Vec<AuditEntry>, String> {
+    let uuid = project_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|e| format!("Invalid project ID: {}", e))?;
+    Ok(AUDIT_LOG.list(uuid.as_ref(), limit))
+}
+
+/// Lint findings for a graph and its node configs, as they would be passed
+/// to `execute_enhanced_workflow`, under the project's lint config
+#[tauri::command]
+pub async fn lint_workflow(
+    project_id: Option<String>,
+    graph: serde_json::Value,
+    node_configs: Option<HashMap<String, NodeConfigRequest>>,
+    template_id: Option<String>,
+) -> Result<Vec<LintFinding>, String> {
+    let config = match project_id {
+        Some(project_id) => {
+            let uuid = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+            LINT_CONFIGS.get(&uuid)
+        }
+        None => LintConfig::default(),
+    };
+    let graph = WorkflowGraph::from_json(&graph).map_err(|e| e.to_string())?;
+    let mut node_configs = parse_node_configs(node_configs, template_id)?;
+    node_library::apply_presets(&graph, &mut node_configs);
+    lint::lint_workflow(&graph, &node_configs, &config).map_err(|e| e.to_string())
+}
+
+/// Replace a project's lint rule toggles and severities
+#[tauri::command]
+pub async fn set_lint_config(project_id: String, config: LintConfig) -> Result<LintConfig, String> {
+    let uuid = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    if crate::commands::project::get_project_name(&uuid).is_none() {
+        return Err("Project not found".to_string());
+    }
+    LINT_CONFIGS.set(uuid, config.clone());
+    Ok(config)
+}
+
+/// A project's lint config, or the defaults if none was set
+#[tauri::command]
+pub async fn get_lint_config(project_id: String) -> Result<LintConfig, String> {
+    let uuid = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    Ok(LINT_CONFIGS.get(&uuid))
+}
+
+// =============================================================================
+// Watch Mode Commands
+// =============================================================================
+
+/// Checks of one revision of a watched workflow, also emitted as
+/// `workflow-diagnostics`
+#[derive(Debug, Serialize)]
+pub struct WorkflowDiagnostics {
+    pub session_id: Uuid,
+    pub workflow_id: Uuid,
+    /// 0 for the workflow as it was when watching started
+    pub revision: u64,
+    pub checked_at: DateTime<Utc>,
+    pub validation: WorkflowValidationResult,
+    /// Empty when the graph doesn't parse or has a cycle
+    pub lint: Vec<LintFinding>,
+    /// Which nodes would run or be skipped under the session's scenario;
+    /// `None` when the graph doesn't parse or has a cycle
+    pub dry_run: Option<ConditionSimulation>,
+}
+
+/// What every revision of a watched workflow is checked with
+struct DevelopInputs {
+    node_configs: HashMap<String, EnhancedNodeConfig>,
+    stages: HashMap<String, StageConfig>,
+    lint: LintConfig,
+    scenario: Scenario,
+}
+
+fn diagnose_workflow(session: &DevSession, workflow: &Workflow, revision: u64, inputs: &DevelopInputs) -> WorkflowDiagnostics {
+    let graph = WorkflowGraph::from_json(&workflow.graph).ok();
+    let mut node_configs = inputs.node_configs.clone();
+    if let Some(graph) = &graph {
+        node_library::apply_presets(graph, &mut node_configs);
+    }
+    let lint = graph
+        .as_ref()
+        .and_then(|graph| lint::lint_workflow(graph, &node_configs, &inputs.lint).ok())
+        .unwrap_or_default();
+    let conditions = node_configs
+        .iter()
+        .map(|(node_id, config)| (node_id.clone(), config.condition.clone()))
+        .collect();
+    let dry_run = graph
+        .as_ref()
+        .and_then(|graph| simulation::simulate(graph, &conditions, &inputs.stages, &inputs.scenario).ok());
+
+    WorkflowDiagnostics {
+        session_id: session.id,
+        workflow_id: workflow.id,
+        revision,
+        checked_at: Utc::now(),
+        validation: validate_graph(&workflow.graph),
+        lint,
+        dry_run,
+    }
+}
+
+/// Watch a saved workflow while it's being authored: each time it changes,
+/// validate, lint and dry-run it again and emit the diagnostics as
+/// `workflow-diagnostics`. Node and stage configs, the template defaults
+/// and the scenario apply to every revision. Replaces an earlier session
+/// for the same workflow; returns the diagnostics of the current revision.
+#[tauri::command]
+pub async fn develop_workflow(
+    app: AppHandle,
+    workflow_id: String,
+    project_id: Option<String>,
+    node_configs: Option<HashMap<String, NodeConfigRequest>>,
+    stage_configs: Option<HashMap<String, StageConfigRequest>>,
+    template_id: Option<String>,
+    scenario: Option<Scenario>,
+) -> Result<WorkflowDiagnostics, String> {
+    let uuid = Uuid::parse_str(&workflow_id).map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    let workflow = WORKFLOWS
+        .get(&uuid)
+        .map(|entry| entry.value().clone())
+        .ok_or("Workflow not found".to_string())?;
+    let lint = match project_id {
+        Some(project_id) => {
+            let project_id = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+            LINT_CONFIGS.get(&project_id)
+        }
+        None => LintConfig::default(),
+    };
+    let inputs = DevelopInputs {
+        node_configs: parse_node_configs(node_configs, template_id)?,
+        stages: parse_stage_configs(stage_configs)?,
+        lint,
+        scenario: scenario.unwrap_or_default(),
+    };
+
+    let session = DEV_SESSIONS.start(uuid);
+    let diagnostics = diagnose_workflow(&session, &workflow, 0, &inputs);
+    let watched = session.clone();
+    develop::spawn_watch(session, move |workflow, revision| {
+        let diagnostics = diagnose_workflow(&watched, workflow, revision, &inputs);
+        let _ = app.emit(DIAGNOSTICS_EVENT, &diagnostics);
+    });
+    Ok(diagnostics)
+}
+
+/// Stop watching a workflow; false if it wasn't being watched
+#[tauri::command]
+pub async fn stop_developing_workflow(workflow_id: String) -> Result<bool, String> {
+    let uuid = Uuid::parse_str(&workflow_id).map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    Ok(DEV_SESSIONS.stop(&uuid))
+}
+
+/// Replace a saved workflow's graph, migrated to the current format. The
+/// graph must parse; the change is saved and shared like a new workflow.
+#[tauri::command]
+pub async fn update_workflow_graph(
+    state: State<'_, Arc<AppState>>,
+    workflow_id: String,
+    graph: serde_json::Value,
+) -> Result<WorkflowResponse, String> {
+    let uuid = Uuid::parse_str(&workflow_id).map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    let graph = schema::migrate_graph(graph).map_err(|e| e.to_string())?;
+    WorkflowGraph::from_json(&graph).map_err(|e| format!("Invalid graph: {}", e))?;
+    let mut workflow = WORKFLOWS.get_mut(&uuid).ok_or("Workflow not found".to_string())?;
+    workflow.graph = graph;
+
+    #[cfg(feature = "database")]
+    crate::db::notify::publish_workflow(&state, &workflow);
+    #[cfg(not(feature = "database"))]
+    let _ = state;
+
+    Ok(WorkflowResponse::from(workflow.value()))
+}
+
 #[derive(Debug, Serialize)]
 pub struct ExecutionRecordSummary {
     pub id: String,
@@ -1037,6 +2787,10 @@ pub struct ExecutionRecordSummary {
     pub completed_nodes: usize,
     pub failed_nodes: usize,
     pub tags: Vec<String>,
+    /// How the run departed from earlier runs of its workflow
+    pub anomalies: Vec<crate::workflow::Anomaly>,
+    /// Pinned models that were unavailable, and what ran instead
+    pub model_substitutions: Vec<crate::workflow::ModelSubstitution>,
 }
 
 impl From<crate::workflow::ExecutionRecord> for ExecutionRecordSummary {
@@ -1045,11 +2799,7 @@ impl From<crate::workflow::ExecutionRecord> for ExecutionRecordSummary {
             id: r.id.to_string(),
             workflow_name: r.workflow_name,
             project_name: r.project_name,
-            input_prompt: if r.input_prompt.len() > 100 {
-                format!("{}...", &r.input_prompt[..100])
-            } else {
-                r.input_prompt
-            },
+            input_prompt: text::truncate(&r.input_prompt, 100, "..."),
             status: format!("{:?}", r.status).to_lowercase(),
             started_at: r.started_at.to_rfc3339(),
             completed_at: r.completed_at.map(|t| t.to_rfc3339()),
@@ -1058,6 +2808,8 @@ impl From<crate::workflow::ExecutionRecord> for ExecutionRecordSummary {
             completed_nodes: r.completed_nodes,
             failed_nodes: r.failed_nodes,
             tags: r.tags,
+            anomalies: r.anomalies,
+            model_substitutions: r.model_substitutions,
         }
     }
 }
@@ -1069,8 +2821,17 @@ impl From<crate::workflow::ExecutionRecord> for ExecutionRecordSummary {
 // Global resource manager
 static RESOURCE_MANAGER: OnceCell<ResourceManager> = OnceCell::new();
 
-fn get_resource_manager() -> &'static ResourceManager {
-    RESOURCE_MANAGER.get_or_init(|| ResourceManager::new(ResourceConfig::default()))
+pub(crate) fn get_resource_manager() -> &'static ResourceManager {
+    RESOURCE_MANAGER.get_or_init(|| {
+        let manager = ResourceManager::new(SETTINGS.get().resources);
+        manager.seed_from_history(&get_history_store().list());
+        manager
+    })
+}
+
+/// Drop tasks waiting for agent slots, returning how many there were
+pub(crate) fn clear_resource_queue() -> usize {
+    RESOURCE_MANAGER.get().map_or(0, |manager| manager.clear_queue())
 }
 
 /// Get resource manager statistics
@@ -1082,8 +2843,8 @@ pub async fn get_resource_stats() -> Result<ResourceStatsSnapshot, String> {
 /// Get current resource configuration
 #[tauri::command]
 pub async fn get_resource_config() -> Result<ResourceConfigResponse, String> {
-    // Return default config since we don't have access to the internal config
-    Ok(ResourceConfigResponse::from(ResourceConfig::default()))
+    // The manager is created from the settings at first use
+    Ok(ResourceConfigResponse::from(SETTINGS.get().resources))
 }
 
 /// Check resource availability
@@ -1098,13 +2859,103 @@ pub async fn check_resource_availability() -> Result<ResourceAvailability, Strin
     })
 }
 
+/// Tokens left in the overall, per-backend and per-credential rate limits
+#[tauri::command]
+pub async fn get_rate_limit_status() -> Result<Vec<RateLimitStatus>, String> {
+    Ok(get_resource_manager().rate_limit_status())
+}
+
+/// Tasks waiting for agent slots, next to run first
+#[tauri::command]
+pub async fn list_queued_tasks() -> Result<Vec<QueuedTaskInfo>, String> {
+    Ok(get_resource_manager().list_queued())
+}
+
+/// Take a task out of the queue before it runs
+#[tauri::command]
+pub async fn cancel_queued_task(task_id: String) -> Result<QueuedTask, String> {
+    let uuid = Uuid::parse_str(&task_id).map_err(|e| format!("Invalid task ID: {}", e))?;
+    get_resource_manager()
+        .cancel_queued(&uuid)
+        .ok_or_else(|| format!("Task {} is not queued", task_id))
+}
+
+/// Change the priority of a queued task
+#[tauri::command]
+pub async fn reprioritize_queued_task(task_id: String, priority: TaskPriority) -> Result<QueuedTask, String> {
+    let uuid = Uuid::parse_str(&task_id).map_err(|e| format!("Invalid task ID: {}", e))?;
+    get_resource_manager()
+        .reprioritize_queued(&uuid, priority)
+        .ok_or_else(|| format!("Task {} is not queued", task_id))
+}
+
+/// Execution concurrency groups with a running or queued execution
+#[tauri::command]
+pub async fn list_execution_groups() -> Result<Vec<GroupInfo>, String> {
+    Ok(EXECUTION_GROUPS.list())
+}
+
+/// Resource locks currently held in a project
+#[tauri::command]
+pub async fn get_project_locks(project_id: String) -> Result<Vec<LockInfo>, String> {
+    let uuid = Uuid::parse_str(&project_id).map_err(|e| format!("Invalid project ID: {}", e))?;
+    Ok(LOCK_MANAGER.held(&uuid))
+}
+
+/// Nodes waiting for approval, in one execution or all of them
+#[tauri::command]
+pub async fn list_pending_approvals(execution_id: Option<String>) -> Result<Vec<ApprovalRequest>, String> {
+    let execution_id = execution_id
+        .map(|id| Uuid::parse_str(&id).map_err(|e| format!("Invalid execution ID: {}", e)))
+        .transpose()?;
+    Ok(APPROVAL_STORE.pending(execution_id))
+}
+
+/// Let a node waiting for approval run. Refused while a supply-chain check
+/// of the execution flags dependencies.
+#[tauri::command]
+pub async fn approve_node(execution_id: String, node_id: String) -> Result<(), String> {
+    let uuid = Uuid::parse_str(&execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    if let Some(blocker) = SUPPLY_CHAIN_REPORTS.approval_blocker(&uuid) {
+        return Err(blocker);
+    }
+    decide_node(&execution_id, &node_id, ApprovalDecision::Approved)
+}
+
+/// What the execution's supply-chain check nodes found
+#[tauri::command]
+pub async fn get_supply_chain_reports(execution_id: String) -> Result<Vec<SupplyChainReport>, String> {
+    let uuid = Uuid::parse_str(&execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    Ok(SUPPLY_CHAIN_REPORTS.get(&uuid))
+}
+
+/// Fail a node waiting for approval
+#[tauri::command]
+pub async fn reject_node(execution_id: String, node_id: String, reason: Option<String>) -> Result<(), String> {
+    decide_node(&execution_id, &node_id, ApprovalDecision::Rejected { reason })
+}
+
+fn decide_node(execution_id: &str, node_id: &str, decision: ApprovalDecision) -> Result<(), String> {
+    let uuid = Uuid::parse_str(execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    if APPROVAL_STORE.decide(uuid, node_id, decision) {
+        Ok(())
+    } else {
+        Err(format!("Node '{}' is not waiting for approval", node_id))
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub struct ResourceConfigResponse {
     pub max_concurrent_agents: u32,
     pub max_queue_size: usize,
     pub rate_limit_per_minute: Option<u32>,
+    pub rate_limit_burst: Option<u32>,
+    pub backend_rate_limits: HashMap<String, RateLimit>,
+    pub credential_rate_limits: Vec<CredentialRateLimit>,
     pub acquire_timeout_ms: u64,
     pub enable_priority_queue: bool,
+    pub queue_policy: QueuePolicy,
+    pub fair_share: FairShare,
 }
 
 impl From<ResourceConfig> for ResourceConfigResponse {
@@ -1113,8 +2964,13 @@ impl From<ResourceConfig> for ResourceConfigResponse {
             max_concurrent_agents: c.max_concurrent_agents,
             max_queue_size: c.max_queue_size,
             rate_limit_per_minute: c.rate_limit_per_minute,
+            rate_limit_burst: c.rate_limit_burst,
+            backend_rate_limits: c.backend_rate_limits,
+            credential_rate_limits: c.credential_rate_limits,
             acquire_timeout_ms: c.acquire_timeout_ms,
             enable_priority_queue: c.enable_priority_queue,
+            queue_policy: c.queue_policy,
+            fair_share: c.fair_share,
         }
     }
 }
@@ -1128,17 +2984,588 @@ pub struct ResourceAvailability {
 }
 
 // =============================================================================
-// Messaging Commands
+// Batch Commands
 // =============================================================================
 
-// Global message bus store
-static MESSAGE_BUS_STORE: OnceCell<MessageBusStore> = OnceCell::new();
+// Global batch store
+static BATCH_STORE: OnceCell<BatchStore> = OnceCell::new();
+
+fn get_batch_store() -> &'static BatchStore {
+    BATCH_STORE.get_or_init(BatchStore::new)
+}
+
+/// Cancel every batch so no more items start, returning how many were live
+pub(crate) fn cancel_all_batches() -> usize {
+    BATCH_STORE.get().map_or(0, |store| store.cancel_all())
+}
+
+/// Runs batch items as enhanced executions of a saved workflow
+struct EnhancedBatchBackend {
+    app: AppHandle,
+    graph: WorkflowGraph,
+    project_id: Uuid,
+}
+
+impl BatchBackend for EnhancedBatchBackend {
+    fn start(&self, item: &BatchItem, prompt: String) -> Result<Uuid, String> {
+        let config = EnhancedExecutionConfig {
+            initial_variables: item.variables.clone(),
+            ..Default::default()
+        };
+
+        let executor_lock = get_enhanced_executor(&self.app);
+        let executor_guard = executor_lock.read();
+        let executor = executor_guard
+            .as_ref()
+            .ok_or_else(|| "Enhanced executor not initialized".to_string())?;
+
+        executor.execute_enhanced(self.graph.clone(), self.project_id, prompt, config, HashMap::new())
+    }
+
+    fn status(&self, execution_id: &Uuid) -> Option<ExecutionStatus> {
+        find_execution(execution_id).map(|(state, _)| state.get_status())
+    }
 
-fn get_message_bus_store() -> &'static MessageBusStore {
-    MESSAGE_BUS_STORE.get_or_init(MessageBusStore::new)
+    fn cancel(&self, execution_id: &Uuid) {
+        if let Some((state, _)) = find_execution(execution_id) {
+            state.cancel();
+        }
+    }
 }
 
-/// Get messages for an execution
+/// Request to run a workflow once per input item
+#[derive(Debug, Deserialize)]
+pub struct BatchExecutionRequest {
+    pub workflow_id: String,
+    pub project_id: String,
+    /// Prompt template; `{{name}}` is replaced with each item's variables
+    pub input_prompt: Option<String>,
+    pub items: Vec<BatchItem>,
+    /// Items running at once (capped by the resource manager's agent limit)
+    pub max_concurrent: Option<usize>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BatchStatusResponse {
+    pub batch: BatchRecord,
+    pub report: BatchReport,
+}
+
+impl From<BatchRecord> for BatchStatusResponse {
+    fn from(batch: BatchRecord) -> Self {
+        let report = batch.report();
+        Self { batch, report }
+    }
+}
+
+/// Run a saved workflow over a list of input items
+#[tauri::command]
+pub async fn execute_workflow_batch(
+    app: AppHandle,
+    request: BatchExecutionRequest,
+) -> Result<String, String> {
+    let workflow_id = Uuid::parse_str(&request.workflow_id)
+        .map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    let project_id = Uuid::parse_str(&request.project_id)
+        .map_err(|e| format!("Invalid project ID: {}", e))?;
+
+    let (handle, backend) =
+        prepare_batch(&app, workflow_id, project_id, &request.items, request.max_concurrent)?;
+    let batch_id = handle.id();
+    let prompt_template = request.input_prompt.unwrap_or_default();
+
+    log::info!("Starting batch {} with {} items", batch_id, request.items.len());
+    tokio::spawn(batch::run_batch(handle, request.items, prompt_template, backend));
+
+    Ok(batch_id.to_string())
+}
+
+/// Register a batch for a saved workflow, ready to pass to `batch::run_batch`
+pub(crate) fn prepare_batch(
+    app: &AppHandle,
+    workflow_id: Uuid,
+    project_id: Uuid,
+    items: &[BatchItem],
+    max_concurrent: Option<usize>,
+) -> Result<(Arc<BatchHandle>, Arc<dyn BatchBackend>), String> {
+    if items.is_empty() {
+        return Err("Batch has no items".to_string());
+    }
+
+    let graph_json = WORKFLOWS
+        .get(&workflow_id)
+        .map(|w| w.graph.clone())
+        .ok_or_else(|| format!("Workflow not found: {}", workflow_id))?;
+    let graph = WorkflowGraph::from_json(&graph_json)
+        .map_err(|e| format!("Invalid graph: {}", e))?;
+
+    let agent_limit = get_resource_manager().config().max_concurrent_agents as usize;
+    let max_concurrent = max_concurrent.unwrap_or(agent_limit).min(agent_limit);
+
+    let record = BatchRecord::new(workflow_id, project_id, items, max_concurrent);
+    let handle = get_batch_store().insert(record);
+    let backend = Arc::new(EnhancedBatchBackend {
+        app: app.clone(),
+        graph,
+        project_id,
+    });
+
+    Ok((handle, backend))
+}
+
+/// Request to run a workflow once per package of a monorepo
+#[derive(Debug, Deserialize)]
+pub struct PackageFanOutRequest {
+    pub workflow_id: String,
+    pub project_id: String,
+    /// Prompt template; `{{package_name}}`, `{{package_path}}` and
+    /// `{{package_kind}}` are filled in for each package
+    pub input_prompt: String,
+    #[serde(default)]
+    pub selection: PackageSelection,
+    pub max_concurrent: Option<usize>,
+}
+
+/// Run a saved workflow as a batch with one item per selected package,
+/// keyed by package name. Progress and the aggregate report come from
+/// `get_batch_status`.
+#[tauri::command]
+pub async fn fan_out_packages(app: AppHandle, request: PackageFanOutRequest) -> Result<String, String> {
+    let workflow_id = Uuid::parse_str(&request.workflow_id)
+        .map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    let project_id = Uuid::parse_str(&request.project_id)
+        .map_err(|e| format!("Invalid project ID: {}", e))?;
+    let root = crate::commands::project::project_root(&request.project_id)?;
+
+    let detected = packages::detect_packages(&root)?;
+    if detected.is_empty() {
+        return Err("No Cargo, npm/pnpm/yarn or Go workspace found in the project".to_string());
+    }
+    let changed: Vec<String> = if request.selection.affected_only {
+        conflicts::snapshot(&root).await?.into_keys().collect()
+    } else {
+        Vec::new()
+    };
+    let selected = packages::select(&detected, &request.selection, &changed);
+    if selected.is_empty() {
+        return Err("No packages match the selection".to_string());
+    }
+
+    let items: Vec<BatchItem> = selected
+        .iter()
+        .map(|package| {
+            let path = if package.path.is_empty() { "." } else { package.path.as_str() };
+            let mut variables = HashMap::new();
+            variables.insert("package_name".to_string(), serde_json::json!(package.name));
+            variables.insert("package_path".to_string(), serde_json::json!(path));
+            variables.insert("package_kind".to_string(), serde_json::json!(package.kind));
+            BatchItem {
+                key: Some(package.name.clone()),
+                input_prompt: None,
+                variables,
+            }
+        })
+        .collect();
+
+    let (handle, backend) = prepare_batch(&app, workflow_id, project_id, &items, request.max_concurrent)?;
+    let batch_id = handle.id();
+    let prompt_template = format!(
+        "{}\n\nThis run covers the {{{{package_name}}}} package in `{{{{package_path}}}}`; keep your changes inside it.",
+        request.input_prompt
+    );
+
+    log::info!("Fanning out batch {} over {} package(s)", batch_id, items.len());
+    tokio::spawn(batch::run_batch(handle, items, prompt_template, backend));
+
+    Ok(batch_id.to_string())
+}
+
+/// Get a batch's per-item status and aggregate report
+#[tauri::command]
+pub async fn get_batch_status(batch_id: String) -> Result<BatchStatusResponse, String> {
+    let uuid = Uuid::parse_str(&batch_id).map_err(|e| format!("Invalid batch ID: {}", e))?;
+
+    get_batch_store()
+        .get(&uuid)
+        .map(|handle| BatchStatusResponse::from(handle.record()))
+        .ok_or_else(|| format!("Batch not found: {}", batch_id))
+}
+
+/// List all batches, newest first
+#[tauri::command]
+pub async fn list_batches() -> Result<Vec<BatchStatusResponse>, String> {
+    Ok(get_batch_store()
+        .list()
+        .into_iter()
+        .map(BatchStatusResponse::from)
+        .collect())
+}
+
+/// Cancel a batch: pending items are skipped and running executions cancelled
+#[tauri::command]
+pub async fn cancel_batch(batch_id: String) -> Result<bool, String> {
+    let uuid = Uuid::parse_str(&batch_id).map_err(|e| format!("Invalid batch ID: {}", e))?;
+
+    match get_batch_store().get(&uuid) {
+        Some(handle) => {
+            handle.cancel();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
+
+// =============================================================================
+// Report Commands
+// =============================================================================
+
+/// A rendered report. PDF content is base64-encoded; other formats are text.
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionReportResponse {
+    pub execution_id: String,
+    pub format: ReportFormat,
+    pub mime_type: String,
+    pub content: String,
+    pub base64: bool,
+}
+
+fn build_report(execution_id: &str) -> Result<ExecutionReport, String> {
+    let uuid = Uuid::parse_str(execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let (state, context) = find_execution(&uuid).ok_or_else(|| format!("Execution not found: {}", execution_id))?;
+    Ok(ExecutionReport::build(&state, context.as_deref()))
+}
+
+/// Generate a report of an execution's plan, node outputs, metrics and diffs
+#[tauri::command]
+pub async fn generate_execution_report(
+    execution_id: String,
+    format: Option<ReportFormat>,
+) -> Result<ExecutionReportResponse, String> {
+    use base64::Engine;
+
+    let format = format.unwrap_or_default();
+    let rendered = build_report(&execution_id)?.render(format);
+    let base64 = format == ReportFormat::Pdf;
+    let content = if base64 {
+        base64::engine::general_purpose::STANDARD.encode(rendered)
+    } else {
+        String::from_utf8_lossy(&rendered).into_owned()
+    };
+
+    Ok(ExecutionReportResponse {
+        execution_id,
+        format,
+        mime_type: format.mime_type().to_string(),
+        content,
+        base64,
+    })
+}
+
+/// Write an execution report to disk. The path is relative to the project
+/// directory and may not leave it; defaults to `reports/<execution_id>.<ext>`
+/// there. Returns the written path.
+#[tauri::command]
+pub async fn export_execution_report(
+    execution_id: String,
+    format: Option<ReportFormat>,
+    output_path: Option<String>,
+) -> Result<String, String> {
+    let format = format.unwrap_or_default();
+    let report = build_report(&execution_id)?;
+
+    let working_dir = crate::commands::project::get_project_working_directory(&report.project_id)
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+    let path = match output_path {
+        Some(path) => resolve_output_path(&working_dir, &path)?,
+        None => report.default_path(&working_dir, format),
+    };
+
+    report
+        .write_to(&path, format)
+        .map_err(|e| format!("Failed to write report to {}: {}", path.display(), e))?;
+
+    Ok(path.to_string_lossy().to_string())
+}
+
+/// Email an execution report as an attachment. Uses the `SMTP_*`
+/// environment settings when no server is given.
+#[tauri::command]
+pub async fn email_execution_report(
+    execution_id: String,
+    to: Vec<String>,
+    format: Option<ReportFormat>,
+    subject: Option<String>,
+    smtp: Option<SmtpConfig>,
+) -> Result<(), String> {
+    let report = build_report(&execution_id)?;
+    let smtp = match smtp {
+        Some(smtp) => smtp,
+        None => SmtpConfig::from_env().map_err(|e| e.to_string())?,
+    };
+
+    send_email(&smtp, &report.email_message(to, subject, format.unwrap_or_default()))
+        .await
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExecutionShareResponse {
+    pub execution_id: String,
+    pub format: ShareFormat,
+    pub mime_type: String,
+    /// Suggested name for the file
+    pub file_name: String,
+    pub redactions: usize,
+    /// Set when the bundle was written to a file
+    pub path: Option<String>,
+    /// The bundle itself when no output path was given
+    pub content: Option<String>,
+}
+
+/// Bundle a finished execution's graph, timeline and outputs, redacted, as
+/// one HTML page or JSON file for people without NEXUS. Output paths are
+/// relative to the project directory and may not leave it.
+#[tauri::command]
+pub async fn export_execution_share(
+    execution_id: String,
+    format: Option<ShareFormat>,
+    output_path: Option<String>,
+    redact_patterns: Option<Vec<String>>,
+) -> Result<ExecutionShareResponse, String> {
+    let uuid = Uuid::parse_str(&execution_id).map_err(|e| format!("Invalid execution ID: {}", e))?;
+    let record = get_history_store().get(&uuid).ok_or_else(|| match find_execution(&uuid) {
+        Some(_) => "Execution hasn't finished yet; share it once it has".to_string(),
+        None => format!("Execution not found: {}", execution_id),
+    })?;
+    let graph = record
+        .workflow_id
+        .and_then(|workflow_id| WORKFLOWS.get(&workflow_id).map(|workflow| workflow.graph.clone()));
+    let redactor = Redactor::new(&redact_patterns.unwrap_or_default()).map_err(|e| e.to_string())?;
+
+    let format = format.unwrap_or_default();
+    let bundle = ShareBundle::build(&record, graph.as_ref(), &redactor);
+    let rendered = bundle.render(format);
+    let (path, content) = match output_path {
+        Some(path) => {
+            let working_dir = crate::commands::project::get_project_working_directory(&record.project_id)
+                .map(std::path::PathBuf::from)
+                .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+            let path = resolve_output_path(&working_dir, &path)?;
+            std::fs::write(&path, rendered).map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+            (Some(path.to_string_lossy().to_string()), None)
+        }
+        None => (None, Some(rendered)),
+    };
+
+    Ok(ExecutionShareResponse {
+        execution_id,
+        format,
+        mime_type: format.mime_type().to_string(),
+        file_name: bundle.file_name(format),
+        redactions: bundle.redactions,
+        path,
+        content,
+    })
+}
+
+// =============================================================================
+// Dataset Commands
+// =============================================================================
+
+/// Transcripts recorded by every execution the enhanced executor still tracks
+fn collect_transcripts() -> Vec<ExecutionTranscripts> {
+    let Some(lock) = ENHANCED_EXECUTOR.get() else {
+        return Vec::new();
+    };
+    let guard = lock.read();
+    let Some(executor) = guard.as_ref() else {
+        return Vec::new();
+    };
+
+    let mut states = executor.execution_store().list();
+    states.sort_by_key(|state| state.started_at);
+
+    states
+        .into_iter()
+        .filter_map(|state| {
+            let context = executor.context_store().get(&state.execution_id)?;
+            Some(ExecutionTranscripts {
+                execution_id: state.execution_id,
+                workflow_id: state.workflow_id,
+                project_id: state.project_id,
+                transcripts: context.get_transcripts(),
+                wrong_nodes: get_history_store()
+                    .get(&state.execution_id)
+                    .map(|record| {
+                        record
+                            .annotations
+                            .into_iter()
+                            .filter(|annotation| annotation.severity == AnnotationSeverity::Wrong)
+                            .map(|annotation| annotation.node_id)
+                            .collect()
+                    })
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Serialize)]
+pub struct DatasetExportResponse {
+    pub examples: usize,
+    pub skipped: usize,
+    pub redactions: usize,
+    /// Set when the dataset was written to a file
+    pub path: Option<String>,
+    /// The JSONL itself when no output path was given
+    pub content: Option<String>,
+}
+
+/// Export agent prompt/response pairs as OpenAI-style chat JSONL, with
+/// secrets and personal data redacted
+#[tauri::command]
+pub async fn export_execution_dataset(
+    filter: Option<DatasetFilter>,
+    output_path: Option<String>,
+) -> Result<DatasetExportResponse, String> {
+    let export = dataset::build_dataset(&collect_transcripts(), &filter.unwrap_or_default())
+        .map_err(|e| e.to_string())?;
+
+    let (path, content) = match output_path {
+        Some(path) => {
+            std::fs::write(&path, &export.jsonl).map_err(|e| format!("Failed to write {}: {}", path, e))?;
+            (Some(path), None)
+        }
+        None => (None, Some(export.jsonl)),
+    };
+
+    Ok(DatasetExportResponse {
+        examples: export.examples,
+        skipped: export.skipped,
+        redactions: export.redactions,
+        path,
+        content,
+    })
+}
+
+// =============================================================================
+// Evaluation Commands
+// =============================================================================
+
+// Global evaluation store
+static EVALUATION_STORE: OnceCell<EvaluationStore> = OnceCell::new();
+
+fn get_evaluation_store() -> &'static EvaluationStore {
+    EVALUATION_STORE.get_or_init(EvaluationStore::new)
+}
+
+/// Request to save an evaluation suite for a workflow
+#[derive(Debug, Deserialize)]
+pub struct CreateEvaluationSuiteRequest {
+    pub name: String,
+    pub workflow_id: String,
+    pub project_id: String,
+    /// Prompt template; `{{name}}` is replaced with each case's variables
+    pub input_prompt: Option<String>,
+    pub cases: Vec<EvalCase>,
+    pub max_concurrent: Option<usize>,
+}
+
+/// Save a set of cases to evaluate a workflow against
+#[tauri::command]
+pub async fn create_evaluation_suite(request: CreateEvaluationSuiteRequest) -> Result<EvaluationSuite, String> {
+    let workflow_id = Uuid::parse_str(&request.workflow_id)
+        .map_err(|e| format!("Invalid workflow ID: {}", e))?;
+    let project_id = Uuid::parse_str(&request.project_id)
+        .map_err(|e| format!("Invalid project ID: {}", e))?;
+
+    if !WORKFLOWS.contains_key(&workflow_id) {
+        return Err(format!("Workflow not found: {}", workflow_id));
+    }
+    if request.cases.is_empty() {
+        return Err("Evaluation suite has no cases".to_string());
+    }
+
+    let suite = EvaluationSuite {
+        id: Uuid::new_v4(),
+        name: request.name,
+        workflow_id,
+        project_id,
+        input_prompt: request.input_prompt,
+        cases: request.cases,
+        max_concurrent: request.max_concurrent,
+        created_at: Utc::now(),
+    };
+    get_evaluation_store().insert_suite(suite.clone());
+
+    Ok(suite)
+}
+
+/// List evaluation suites, newest first
+#[tauri::command]
+pub async fn list_evaluation_suites() -> Result<Vec<EvaluationSuite>, String> {
+    Ok(get_evaluation_store().list_suites())
+}
+
+/// Delete an evaluation suite and its reports
+#[tauri::command]
+pub async fn delete_evaluation_suite(suite_id: String) -> Result<bool, String> {
+    let uuid = Uuid::parse_str(&suite_id).map_err(|e| format!("Invalid suite ID: {}", e))?;
+    Ok(get_evaluation_store().remove_suite(&uuid))
+}
+
+/// Run every case of a suite and report pass rates. A case passes when its
+/// execution completes and all of its assert nodes pass.
+#[tauri::command]
+pub async fn evaluate_workflow(app: AppHandle, suite_id: String) -> Result<EvaluationReport, String> {
+    let uuid = Uuid::parse_str(&suite_id).map_err(|e| format!("Invalid suite ID: {}", e))?;
+    let suite = get_evaluation_store()
+        .get_suite(&uuid)
+        .ok_or_else(|| format!("Evaluation suite not found: {}", suite_id))?;
+
+    let items: Vec<BatchItem> = suite.cases.iter().map(EvalCase::to_batch_item).collect();
+    let (handle, backend) = prepare_batch(&app, suite.workflow_id, suite.project_id, &items, suite.max_concurrent)?;
+    let started_at = Utc::now();
+
+    log::info!("Evaluating suite {} ({} cases) as batch {}", suite.id, items.len(), handle.id());
+    batch::run_batch(handle.clone(), items, suite.input_prompt.clone().unwrap_or_default(), backend).await;
+
+    let record = handle.record();
+    let cases = suite
+        .cases
+        .iter()
+        .zip(&record.items)
+        .map(|(case, item)| {
+            let assertions = item
+                .execution_id
+                .and_then(|id| find_execution(&id))
+                .and_then(|(_, context)| context)
+                .map(|context| context.get_assertions())
+                .unwrap_or_default();
+            CaseResult::new(case.name.clone(), item.execution_id, item.status, assertions, item.error.clone())
+        })
+        .collect();
+
+    let report = EvaluationReport::new(suite.id, record.id, started_at, cases);
+    get_evaluation_store().add_report(report.clone());
+
+    Ok(report)
+}
+
+/// Past evaluation reports for a suite, newest first
+#[tauri::command]
+pub async fn get_evaluation_reports(suite_id: String) -> Result<Vec<EvaluationReport>, String> {
+    let uuid = Uuid::parse_str(&suite_id).map_err(|e| format!("Invalid suite ID: {}", e))?;
+    Ok(get_evaluation_store().reports_for(&uuid))
+}
+
+// =============================================================================
+// Messaging Commands
+// =============================================================================
+
+/// Get messages for an execution, falling back to those persisted in its
+/// checkpoints once the live bus is gone
 #[tauri::command]
 pub async fn get_execution_messages(
     execution_id: String,
@@ -1146,35 +3573,94 @@ pub async fn get_execution_messages(
     let uuid = Uuid::parse_str(&execution_id)
         .map_err(|e| format!("Invalid execution ID: {}", e))?;
 
-    let store = get_message_bus_store();
-
-    let bus = store.get(&uuid)
-        .ok_or_else(|| format!("No message bus found for execution: {}", execution_id))?;
+    let messages = match MESSAGE_BUS_STORE.get(&uuid) {
+        Some(bus) => bus.get_all_messages(),
+        None => {
+            let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
+                .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
+            let persisted = manager
+                .load_messages(&uuid)
+                .map_err(|e| format!("Failed to load persisted messages: {}", e))?;
+            if persisted.is_empty() {
+                return Err(format!("No message bus found for execution: {}", execution_id));
+            }
+            persisted
+        }
+    };
 
-    let messages = bus.get_all_messages();
     Ok(messages.into_iter().map(AgentMessageResponse::from).collect())
 }
 
-/// Get unread messages for an agent in an execution
+/// Get unread messages for an agent in an execution, optionally filtered
+/// by topic, type, sender or priority
 #[tauri::command]
 pub async fn get_unread_agent_messages(
     execution_id: String,
     agent_id: String,
+    filter: Option<MessageFilter>,
 ) -> Result<Vec<AgentMessageResponse>, String> {
     let exec_uuid = Uuid::parse_str(&execution_id)
         .map_err(|e| format!("Invalid execution ID: {}", e))?;
     let agent_uuid = Uuid::parse_str(&agent_id)
         .map_err(|e| format!("Invalid agent ID: {}", e))?;
 
-    let store = get_message_bus_store();
-
-    let bus = store.get(&exec_uuid)
+    let bus = MESSAGE_BUS_STORE.get(&exec_uuid)
         .ok_or_else(|| format!("No message bus found for execution: {}", execution_id))?;
 
-    let messages = bus.get_unread(&agent_uuid);
+    let messages = bus.get_unread_filtered(&agent_uuid, &filter.unwrap_or_default());
     Ok(messages.into_iter().map(AgentMessageResponse::from).collect())
 }
 
+/// Send a message from the user to the agents of an execution: to everyone,
+/// or to a topic's subscribers. Agents see it in their next prompt.
+#[tauri::command]
+pub async fn broadcast_execution_message(
+    execution_id: String,
+    text: String,
+    topic: Option<String>,
+) -> Result<String, String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
+
+    let bus = MESSAGE_BUS_STORE.get(&uuid)
+        .ok_or_else(|| format!("No message bus found for execution: {}", execution_id))?;
+
+    let message_id = match topic {
+        Some(topic) => bus.publish(Uuid::nil(), "user", "user", &topic, MessageType::Info, MessageContent::Text(text)),
+        None => bus.send_text(Uuid::nil(), "user", "user", None, None, &text),
+    };
+
+    Ok(message_id.to_string())
+}
+
+#[derive(Debug, Serialize)]
+pub struct PurgeMessagesResponse {
+    /// Messages dropped from the live bus
+    pub messages_removed: usize,
+    /// Checkpoints rewritten without their persisted messages
+    pub checkpoints_updated: usize,
+}
+
+/// Drop an execution's messages, both in memory and persisted
+#[tauri::command]
+pub async fn purge_execution_messages(execution_id: String) -> Result<PurgeMessagesResponse, String> {
+    let uuid = Uuid::parse_str(&execution_id)
+        .map_err(|e| format!("Invalid execution ID: {}", e))?;
+
+    let messages_removed = MESSAGE_BUS_STORE.purge(&uuid);
+
+    let manager = CheckpointManager::new(CheckpointManager::default_checkpoint_dir())
+        .map_err(|e| format!("Failed to initialize checkpoint manager: {}", e))?;
+    let checkpoints_updated = manager
+        .strip_messages(&uuid)
+        .map_err(|e| format!("Failed to purge persisted messages: {}", e))?;
+
+    Ok(PurgeMessagesResponse {
+        messages_removed,
+        checkpoints_updated,
+    })
+}
+
 #[derive(Debug, Serialize)]
 pub struct AgentMessageResponse {
     pub id: String,
@@ -1188,6 +3674,7 @@ pub struct AgentMessageResponse {
     pub timestamp: String,
     pub priority: String,
     pub read: bool,
+    pub topic: Option<String>,
 }
 
 impl From<crate::workflow::AgentMessage> for AgentMessageResponse {
@@ -1204,6 +3691,7 @@ impl From<crate::workflow::AgentMessage> for AgentMessageResponse {
             timestamp: m.timestamp.to_rfc3339(),
             priority: format!("{:?}", m.priority).to_lowercase(),
             read: m.read,
+            topic: m.topic,
         }
     }
 }
@@ -1261,3 +3749,50 @@ pub struct TemplateCategoryInfo {
     pub name: String,
     pub description: String,
 }
+
+// =============================================================================
+// Plugin Commands
+// =============================================================================
+
+/// Result of (re)loading plugins from disk
+#[derive(Debug, Serialize)]
+pub struct PluginLoadResult {
+    pub supported: bool,
+    pub plugin_dir: String,
+    pub loaded: Vec<PluginInfo>,
+    pub errors: Vec<String>,
+}
+
+/// List registered WASM plugins
+#[tauri::command]
+pub async fn list_plugins() -> Result<Vec<PluginInfo>, String> {
+    Ok(PLUGIN_REGISTRY.list())
+}
+
+/// Load all plugins from the plugins directory
+#[tauri::command]
+pub async fn reload_plugins() -> Result<PluginLoadResult, String> {
+    let dir = PluginRegistry::default_plugin_dir();
+    let (loaded, errors) = PLUGIN_REGISTRY.load_dir(&dir);
+
+    Ok(PluginLoadResult {
+        supported: PluginRegistry::is_supported(),
+        plugin_dir: dir.to_string_lossy().to_string(),
+        loaded,
+        errors,
+    })
+}
+
+/// Load a single plugin file
+#[tauri::command]
+pub async fn load_plugin(path: String) -> Result<PluginInfo, String> {
+    PLUGIN_REGISTRY
+        .load_file(std::path::Path::new(&path))
+        .map_err(|e| e.to_string())
+}
+
+/// Unregister a plugin by name
+#[tauri::command]
+pub async fn unload_plugin(name: String) -> Result<bool, String> {
+    Ok(PLUGIN_REGISTRY.unload(&name))
+}
@@ -0,0 +1,173 @@
+//! Database connection health.
+//!
+//! Startup tries `DATABASE_URL` once; this monitor keeps checking it for as
+//! long as the app runs. Provides:
+//! - Connecting later when the database wasn't reachable at startup, which
+//!   moves the app from offline to online mode
+//! - Copying projects and workflows created while offline into the database
+//! - Noticing when an established connection stops answering, and when it
+//!   recovers, replaying the writes queued meanwhile
+//! - Connectivity events for the UI on every change
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+use super::models::{Project, ProjectStatus, Workflow};
+use super::notify;
+use super::pool::create_pool;
+use super::queries;
+use super::write_queue::{self, WRITE_QUEUE};
+use crate::commands::project::all_projects;
+use crate::commands::workflow::WORKFLOWS;
+use crate::state::AppState;
+
+/// Event the UI receives when the database connects or disconnects
+pub const CONNECTIVITY_EVENT: &str = "database-connectivity";
+
+/// Seconds between checks
+const CHECK_INTERVAL_SECS: u64 = 30;
+
+/// Set while an established connection isn't answering
+static UNREACHABLE: AtomicBool = AtomicBool::new(false);
+
+/// A change in connectivity
+#[derive(Debug, Clone, Serialize)]
+pub struct Connectivity {
+    pub connected: bool,
+    /// Why the database can't be reached
+    pub error: Option<String>,
+    /// Offline projects and workflows copied into the database on connecting
+    pub migrated_projects: usize,
+    pub migrated_workflows: usize,
+    pub changed_at: DateTime<Utc>,
+}
+
+impl Connectivity {
+    fn new(connected: bool, error: Option<String>) -> Self {
+        Self {
+            connected,
+            error,
+            migrated_projects: 0,
+            migrated_workflows: 0,
+            changed_at: Utc::now(),
+        }
+    }
+}
+
+/// Whether the app has a database connection that is answering
+pub fn is_connected(state: &AppState) -> bool {
+    state.has_db() && !UNREACHABLE.load(Ordering::Relaxed)
+}
+
+/// Check `database_url` every few seconds, connecting when the app is
+/// offline and watching the connection when it isn't
+pub fn spawn_monitor(app: AppHandle, state: Arc<AppState>, database_url: String) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(CHECK_INTERVAL_SECS));
+        interval.tick().await;
+        loop {
+            interval.tick().await;
+            let was_connected = is_connected(&state);
+            let connectivity = match state.get_pool() {
+                Some(pool) => check(&pool).await,
+                None => connect(&app, &state, &database_url).await,
+            };
+            UNREACHABLE.store(state.has_db() && !connectivity.connected, Ordering::Relaxed);
+            if connectivity.connected == was_connected {
+                continue;
+            }
+
+            match &connectivity.error {
+                Some(e) => log::warn!("Lost the database connection: {}", e),
+                None => log::info!("Database connection is back"),
+            }
+            let _ = app.emit(CONNECTIVITY_EVENT, &connectivity);
+            if let (true, Some(pool)) = (connectivity.connected, state.get_pool()) {
+                WRITE_QUEUE.replay(&pool).await;
+            }
+        }
+    });
+}
+
+async fn check(pool: &PgPool) -> Connectivity {
+    match sqlx::query("SELECT 1").execute(pool).await {
+        Ok(_) => Connectivity::new(true, None),
+        Err(e) => Connectivity::new(false, Some(e.to_string())),
+    }
+}
+
+/// Create the pool and switch the app to online mode
+async fn connect(app: &AppHandle, state: &AppState, database_url: &str) -> Connectivity {
+    let pool = match create_pool(database_url).await {
+        Ok(pool) => pool,
+        Err(e) => {
+            log::debug!("Database still unreachable: {}", e);
+            return Connectivity::new(false, Some(e.to_string()));
+        }
+    };
+
+    let mut connectivity = Connectivity::new(true, None);
+    (connectivity.migrated_projects, connectivity.migrated_workflows) = migrate_offline_data(&pool).await;
+    log::info!(
+        "Connected to the database; copied {} project(s) and {} workflow(s) created offline",
+        connectivity.migrated_projects,
+        connectivity.migrated_workflows
+    );
+    write_queue::spawn_replay(pool.clone());
+    notify::spawn_listener(app.clone(), pool.clone());
+    state.set_pool(pool);
+    connectivity
+}
+
+/// Insert the in-memory projects and workflows the database doesn't have
+/// yet. Returns how many of each were inserted.
+async fn migrate_offline_data(pool: &PgPool) -> (usize, usize) {
+    let mut migrated_projects = 0;
+    for project in all_projects() {
+        let project = project_row(&project);
+        match queries::insert_project(pool, &project).await {
+            Ok(inserted) => migrated_projects += inserted as usize,
+            Err(e) => log::warn!("Failed to copy project {} into the database: {}", project.id, e),
+        }
+    }
+
+    let workflows: Vec<Workflow> = WORKFLOWS.iter().map(|entry| notify::workflow_row(&entry)).collect();
+    let mut migrated_workflows = 0;
+    for workflow in workflows {
+        match queries::insert_workflow(pool, &workflow).await {
+            Ok(inserted) => migrated_workflows += inserted as usize,
+            Err(e) => log::warn!("Failed to copy workflow {} into the database: {}", workflow.id, e),
+        }
+    }
+
+    (migrated_projects, migrated_workflows)
+}
+
+/// The `projects` row for an in-memory project
+pub fn project_row(project: &crate::commands::project::Project) -> Project {
+    Project {
+        id: project.id,
+        name: project.name.clone(),
+        description: project.description.clone(),
+        status: project_status(&project.status),
+        working_directory: project.working_directory.clone(),
+        created_at: Some(project.created_at),
+        updated_at: Some(project.updated_at),
+    }
+}
+
+fn project_status(status: &str) -> ProjectStatus {
+    match status.to_lowercase().as_str() {
+        "active" => ProjectStatus::Active,
+        "paused" => ProjectStatus::Paused,
+        "completed" => ProjectStatus::Completed,
+        "failed" => ProjectStatus::Failed,
+        "archived" => ProjectStatus::Archived,
+        _ => ProjectStatus::Pending,
+    }
+}
@@ -1,3 +1,6 @@
+pub mod health;
 pub mod models;
+pub mod notify;
 pub mod pool;
 pub mod queries;
+pub mod write_queue;
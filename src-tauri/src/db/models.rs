@@ -3,9 +3,10 @@ use serde::{Deserialize, Serialize};
 use sqlx::Type;
 use uuid::Uuid;
 
-#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Default)]
 #[sqlx(type_name = "varchar", rename_all = "lowercase")]
 pub enum ProjectStatus {
+    #[default]
     Pending,
     Active,
     Paused,
@@ -14,13 +15,7 @@ pub enum ProjectStatus {
     Archived,
 }
 
-impl Default for ProjectStatus {
-    fn default() -> Self {
-        Self::Pending
-    }
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Project {
     pub id: Uuid,
     pub name: String,
@@ -42,9 +37,10 @@ pub struct AgentTemplate {
     pub color: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq)]
+#[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq, Default)]
 #[sqlx(type_name = "varchar", rename_all = "lowercase")]
 pub enum AgentDbStatus {
+    #[default]
     Idle,
     Starting,
     Running,
@@ -54,12 +50,6 @@ pub enum AgentDbStatus {
     Killed,
 }
 
-impl Default for AgentDbStatus {
-    fn default() -> Self {
-        Self::Idle
-    }
-}
-
 #[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Agent {
     pub id: Uuid,
@@ -74,7 +64,7 @@ pub struct Agent {
     pub created_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, sqlx::FromRow)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, sqlx::FromRow)]
 pub struct Workflow {
     pub id: Uuid,
     pub name: String,
@@ -82,6 +72,12 @@ pub struct Workflow {
     pub graph: serde_json::Value,
     pub is_template: bool,
     pub created_at: DateTime<Utc>,
+    /// The workflow's `ExecutionConcurrency`, as JSON
+    #[serde(default)]
+    pub concurrency: Option<serde_json::Value>,
+    /// The workflow's `ModelPins`, as JSON
+    #[serde(default)]
+    pub model_pins: Option<serde_json::Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Type, PartialEq)]
@@ -0,0 +1,221 @@
+//! Coordination between NEXUS instances sharing a database.
+//!
+//! Each instance keeps workflows and executions in memory, so without this
+//! one instance only sees another's changes after a restart. Changes are
+//! announced with Postgres `NOTIFY` on one channel that every connected
+//! instance `LISTEN`s on. Provides:
+//! - Saving new and changed workflows, with their concurrency group and
+//!   model pins, to the database and announcing them. Environment defaults
+//!   may hold secrets and stay with the instance that set them.
+//! - Announcing executions as they start and finish
+//! - Forwarding cancellations to the instance running the execution
+//! - Applying other instances' announcements and passing them to the UI
+//!
+//! Notifications sent while an instance's listener is reconnecting are
+//! missed; workflows still reach it through the database on the next one.
+
+use dashmap::DashSet;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use sqlx::postgres::PgListener;
+use sqlx::PgPool;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use super::queries;
+use super::write_queue::{PendingWrite, WRITE_QUEUE};
+use crate::commands::workflow::{find_execution, Workflow, WORKFLOWS};
+use crate::state::AppState;
+
+/// Postgres channel the instances talk on
+const CHANNEL: &str = "nexus_instance_events";
+
+/// Event the UI receives for another instance's announcement
+pub const INSTANCE_EVENT: &str = "instance-event";
+
+/// Seconds to wait before listening again after the listener fails
+const RETRY_SECS: u64 = 10;
+
+lazy_static! {
+    /// Identifies this process, so it ignores its own notifications
+    pub static ref INSTANCE_ID: Uuid = Uuid::new_v4();
+
+    /// Executions other instances announced and haven't finished
+    static ref REMOTE_EXECUTIONS: DashSet<Uuid> = DashSet::new();
+}
+
+/// What an instance announces
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InstanceEvent {
+    /// Saved to the `workflows` table, new or changed; the graph is read
+    /// from there, since notification payloads are limited to 8000 bytes
+    WorkflowSaved { workflow_id: Uuid },
+    ExecutionStarted {
+        execution_id: Uuid,
+        workflow_id: Uuid,
+        workflow_name: String,
+        project_id: Uuid,
+    },
+    ExecutionFinished { execution_id: Uuid },
+    /// Cancel the execution on whichever instance runs it
+    CancelExecution { execution_id: Uuid, reason: String },
+}
+
+/// A notification's payload
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceNotification {
+    pub instance_id: Uuid,
+    pub event: InstanceEvent,
+}
+
+/// Announce `event` to the other instances, in the background. Does
+/// nothing while offline.
+pub fn publish(state: &AppState, event: InstanceEvent) {
+    let Some(pool) = state.get_pool() else {
+        return;
+    };
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = notify(&pool, event).await {
+            log::warn!("Failed to notify other instances: {}", e);
+        }
+    });
+}
+
+/// Whether another instance announced `execution_id` and hasn't
+/// finished it
+pub fn runs_elsewhere(execution_id: &Uuid) -> bool {
+    REMOTE_EXECUTIONS.contains(execution_id)
+}
+
+/// The `workflows` row for an in-memory workflow
+pub fn workflow_row(workflow: &Workflow) -> super::models::Workflow {
+    super::models::Workflow {
+        id: workflow.id,
+        name: workflow.name.clone(),
+        description: workflow.description.clone(),
+        graph: workflow.graph.clone(),
+        is_template: workflow.is_template,
+        created_at: workflow.created_at,
+        concurrency: workflow.concurrency.as_ref().and_then(|c| serde_json::to_value(c).ok()),
+        model_pins: workflow.model_pins.as_ref().and_then(|p| serde_json::to_value(p).ok()),
+    }
+}
+
+/// Save a new or changed workflow to the database and announce it
+pub fn publish_workflow(state: &AppState, workflow: &Workflow) {
+    let Some(pool) = state.get_pool() else {
+        return;
+    };
+    let row = workflow_row(workflow);
+    let workflow_id = row.id;
+    tauri::async_runtime::spawn(async move {
+        // Queued while the database is unreachable; the other instances
+        // then find it in the database once it is replayed
+        let saved = match WRITE_QUEUE.submit(&pool, PendingWrite::SaveWorkflow { workflow: row }).await {
+            Ok(true) => notify(&pool, InstanceEvent::WorkflowSaved { workflow_id }).await,
+            Ok(false) => Ok(()),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = saved {
+            log::warn!("Failed to share workflow {} with other instances: {}", workflow_id, e);
+        }
+    });
+}
+
+async fn notify(pool: &PgPool, event: InstanceEvent) -> Result<(), sqlx::Error> {
+    let payload = serde_json::to_string(&InstanceNotification {
+        instance_id: *INSTANCE_ID,
+        event,
+    })
+    .map_err(|e| sqlx::Error::Encode(Box::new(e)))?;
+    sqlx::query("SELECT pg_notify($1, $2)")
+        .bind(CHANNEL)
+        .bind(payload)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Listen for the other instances' announcements for as long as the app
+/// runs
+pub fn spawn_listener(app: AppHandle, pool: PgPool) {
+    tauri::async_runtime::spawn(async move {
+        loop {
+            if let Err(e) = listen(&app, &pool).await {
+                log::warn!("Stopped listening to other instances: {}", e);
+            }
+            tokio::time::sleep(Duration::from_secs(RETRY_SECS)).await;
+        }
+    });
+}
+
+async fn listen(app: &AppHandle, pool: &PgPool) -> Result<(), sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(CHANNEL).await?;
+    loop {
+        // Reconnects by itself when the connection drops
+        let notification = listener.recv().await?;
+        match serde_json::from_str::<InstanceNotification>(notification.payload()) {
+            Ok(received) if received.instance_id != *INSTANCE_ID => {
+                apply(pool, &received.event).await;
+                let _ = app.emit(INSTANCE_EVENT, &received);
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Ignoring malformed instance notification: {}", e),
+        }
+    }
+}
+
+/// A shared workflow's setting; one this instance can't read is dropped
+/// rather than failing the whole workflow
+fn shared_setting<T: serde::de::DeserializeOwned>(workflow_id: Uuid, name: &str, value: Option<serde_json::Value>) -> Option<T> {
+    value.and_then(|value| match serde_json::from_value(value) {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            log::warn!("Ignoring the {} of shared workflow {}: {}", name, workflow_id, e);
+            None
+        }
+    })
+}
+
+async fn apply(pool: &PgPool, event: &InstanceEvent) {
+    match event {
+        InstanceEvent::WorkflowSaved { workflow_id } => match queries::get_workflow_by_id(pool, *workflow_id).await {
+            Ok(Some(row)) => {
+                let concurrency = shared_setting(row.id, "concurrency", row.concurrency.clone());
+                let model_pins = shared_setting(row.id, "model pins", row.model_pins.clone());
+                let env = WORKFLOWS.get(&row.id).map(|w| w.env.clone()).unwrap_or_default();
+                WORKFLOWS.insert(
+                    row.id,
+                    Workflow {
+                        id: row.id,
+                        name: row.name,
+                        description: row.description,
+                        graph: row.graph,
+                        is_template: row.is_template,
+                        created_at: row.created_at,
+                        concurrency,
+                        model_pins,
+                        env,
+                    },
+                );
+            }
+            Ok(None) => log::warn!("Workflow {} announced by another instance is not in the database", workflow_id),
+            Err(e) => log::warn!("Failed to load workflow {} from the database: {}", workflow_id, e),
+        },
+        InstanceEvent::ExecutionStarted { execution_id, .. } => {
+            REMOTE_EXECUTIONS.insert(*execution_id);
+        }
+        InstanceEvent::ExecutionFinished { execution_id } => {
+            REMOTE_EXECUTIONS.remove(execution_id);
+        }
+        InstanceEvent::CancelExecution { execution_id, reason } => {
+            if let Some((state, _)) = find_execution(execution_id) {
+                log::info!("Cancelling execution {} at another instance's request", execution_id);
+                state.cancel_with_reason(reason);
+            }
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use super::models::*;
+use chrono::{DateTime, Utc};
 use sqlx::PgPool;
 use uuid::Uuid;
 
@@ -226,3 +227,110 @@ pub async fn get_logs_for_agent(
     .fetch_all(pool)
     .await
 }
+
+// Workflow queries
+pub async fn get_workflow_by_id(pool: &PgPool, id: Uuid) -> Result<Option<Workflow>, sqlx::Error> {
+    sqlx::query_as!(
+        Workflow,
+        r#"
+        SELECT id, name, description, graph, is_template as "is_template!", created_at as "created_at!",
+               concurrency, model_pins
+        FROM workflows
+        WHERE id = $1
+        "#,
+        id
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+pub async fn update_project_working_directory(
+    pool: &PgPool,
+    project_id: Uuid,
+    working_directory: &str,
+    updated_at: DateTime<Utc>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE projects
+        SET working_directory = $2, updated_at = $3
+        WHERE id = $1
+        "#,
+        project_id,
+        working_directory,
+        updated_at
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// Offline data migration
+pub async fn insert_project(pool: &PgPool, project: &Project) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO projects (id, name, description, status, working_directory, created_at, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+        project.id,
+        project.name,
+        project.description,
+        project.status.clone() as ProjectStatus,
+        project.working_directory,
+        project.created_at,
+        project.updated_at
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn insert_workflow(pool: &PgPool, workflow: &Workflow) -> Result<bool, sqlx::Error> {
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO workflows (id, name, description, graph, is_template, created_at, concurrency, model_pins)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (id) DO NOTHING
+        "#,
+        workflow.id,
+        workflow.name,
+        workflow.description,
+        workflow.graph,
+        workflow.is_template,
+        workflow.created_at,
+        workflow.concurrency,
+        workflow.model_pins
+    )
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Insert a workflow, or overwrite the stored one with the same ID
+pub async fn save_workflow(pool: &PgPool, workflow: &Workflow) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO workflows (id, name, description, graph, is_template, created_at, concurrency, model_pins)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        ON CONFLICT (id) DO UPDATE SET
+            name = EXCLUDED.name,
+            description = EXCLUDED.description,
+            graph = EXCLUDED.graph,
+            is_template = EXCLUDED.is_template,
+            concurrency = EXCLUDED.concurrency,
+            model_pins = EXCLUDED.model_pins
+        "#,
+        workflow.id,
+        workflow.name,
+        workflow.description,
+        workflow.graph,
+        workflow.is_template,
+        workflow.created_at,
+        workflow.concurrency,
+        workflow.model_pins
+    )
+    .execute(pool)
+    .await?;
+    Ok(())
+}
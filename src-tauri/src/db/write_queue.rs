@@ -0,0 +1,464 @@
+//! Write-behind queue for database writes.
+//!
+//! The pool is created once at startup, and a write that fails because the
+//! connection dropped afterwards would otherwise be lost. Such writes are
+//! buffered here, saved to disk so a restart doesn't lose them either, and
+//! replayed in order once the database answers again. Provides:
+//! - Submitting a write, which queues it when the database is unreachable;
+//!   project and workflow inserts and project relocations from commands
+//!   go through here
+//! - Replaying the queue, periodically and on demand
+//! - A conflict policy for queued updates to the same agent
+//!
+//! Writes rejected by the database itself, such as one referencing a
+//! deleted project, are dropped rather than retried.
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use parking_lot::{Mutex, RwLock};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::models::{AgentDbStatus, LogType, MessageType, Project, Workflow};
+use super::queries;
+
+/// Seconds between replay attempts while writes are queued
+const REPLAY_INTERVAL_SECS: u64 = 15;
+
+/// Writes kept at most; the oldest are dropped beyond this
+const MAX_PENDING: usize = 10_000;
+
+/// A write the app makes to the database
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PendingWrite {
+    CreateMessage {
+        project_id: Uuid,
+        from_agent_id: Option<Uuid>,
+        to_agent_id: Option<Uuid>,
+        message_type: MessageType,
+        content: String,
+        metadata: serde_json::Value,
+    },
+    CreateExecutionLog {
+        agent_id: Uuid,
+        log_type: LogType,
+        content: String,
+    },
+    UpdateAgentStatus {
+        agent_id: Uuid,
+        status: AgentDbStatus,
+    },
+    UpdateAgentProgress {
+        agent_id: Uuid,
+        progress: i32,
+    },
+    InsertProject {
+        project: Project,
+    },
+    UpdateProjectDirectory {
+        project_id: Uuid,
+        working_directory: String,
+        updated_at: DateTime<Utc>,
+    },
+    InsertWorkflow {
+        workflow: Workflow,
+    },
+    /// Insert or overwrite a workflow
+    SaveWorkflow {
+        workflow: Workflow,
+    },
+}
+
+impl PendingWrite {
+    /// The row and column an update overwrites; `None` for inserts
+    fn target(&self) -> Option<(Uuid, &'static str)> {
+        match self {
+            PendingWrite::UpdateAgentStatus { agent_id, .. } => Some((*agent_id, "status")),
+            PendingWrite::UpdateAgentProgress { agent_id, .. } => Some((*agent_id, "progress")),
+            PendingWrite::UpdateProjectDirectory { project_id, .. } => Some((*project_id, "working_directory")),
+            _ => None,
+        }
+    }
+
+    async fn execute(&self, pool: &PgPool) -> Result<(), sqlx::Error> {
+        match self.clone() {
+            PendingWrite::CreateMessage {
+                project_id,
+                from_agent_id,
+                to_agent_id,
+                message_type,
+                content,
+                metadata,
+            } => {
+                queries::create_message(pool, project_id, from_agent_id, to_agent_id, message_type, &content, metadata)
+                    .await
+                    .map(|_| ())
+            }
+            PendingWrite::CreateExecutionLog { agent_id, log_type, content } => {
+                queries::create_execution_log(pool, agent_id, log_type, &content).await.map(|_| ())
+            }
+            PendingWrite::UpdateAgentStatus { agent_id, status } => {
+                queries::update_agent_status(pool, agent_id, status).await
+            }
+            PendingWrite::UpdateAgentProgress { agent_id, progress } => {
+                queries::update_agent_progress(pool, agent_id, progress).await
+            }
+            PendingWrite::InsertProject { project } => queries::insert_project(pool, &project).await.map(|_| ()),
+            PendingWrite::UpdateProjectDirectory { project_id, working_directory, updated_at } => {
+                queries::update_project_working_directory(pool, project_id, &working_directory, updated_at).await
+            }
+            PendingWrite::InsertWorkflow { workflow } => queries::insert_workflow(pool, &workflow).await.map(|_| ()),
+            PendingWrite::SaveWorkflow { workflow } => queries::save_workflow(pool, &workflow).await,
+        }
+    }
+}
+
+/// How queued updates to the same agent are replayed. Inserts are always
+/// replayed, in order.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Replay every queued update, so the last one queued wins
+    ReplayAll,
+    /// Replay only the last update queued for each agent field, skipping
+    /// intermediate states nobody will read
+    #[default]
+    LatestOnly,
+}
+
+impl ConflictPolicy {
+    /// `NEXUS_DB_CONFLICT_POLICY` (`replay_all` or `latest_only`), else the
+    /// default
+    pub fn from_env() -> Self {
+        std::env::var("NEXUS_DB_CONFLICT_POLICY")
+            .ok()
+            .and_then(|policy| serde_json::from_value(serde_json::Value::String(policy)).ok())
+            .unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueuedWrite {
+    pub write: PendingWrite,
+    pub queued_at: DateTime<Utc>,
+}
+
+/// Outcome of one replay
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    /// Superseded by a later update under [`ConflictPolicy::LatestOnly`]
+    pub coalesced: usize,
+    /// Rejected by the database
+    pub dropped: usize,
+    /// Still queued because the database became unreachable again
+    pub remaining: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WriteQueueStatus {
+    pub pending: usize,
+    pub oldest_queued_at: Option<DateTime<Utc>>,
+    pub policy: ConflictPolicy,
+    pub last_replay: Option<ReplayReport>,
+}
+
+/// Whether `error` means the database couldn't be reached, so the write
+/// may succeed later
+pub fn is_connection_error(error: &sqlx::Error) -> bool {
+    matches!(
+        error,
+        sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolTimedOut | sqlx::Error::PoolClosed
+    )
+}
+
+/// Writes waiting for the database, oldest first
+pub struct WriteQueue {
+    pending: Mutex<VecDeque<QueuedWrite>>,
+    policy: RwLock<ConflictPolicy>,
+    last_replay: RwLock<Option<ReplayReport>>,
+    /// Held while replaying, so two replays don't write the same rows
+    replaying: tokio::sync::Mutex<()>,
+    path: Option<PathBuf>,
+}
+
+impl WriteQueue {
+    /// A queue saved to `path`, starting with the writes saved there
+    pub fn new(path: Option<PathBuf>) -> Self {
+        let pending: VecDeque<QueuedWrite> = path
+            .as_ref()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .and_then(|json| serde_json::from_str(&json).ok())
+            .unwrap_or_default();
+        if !pending.is_empty() {
+            log::info!("{} database write(s) queued from the last session", pending.len());
+        }
+        Self {
+            pending: Mutex::new(pending),
+            policy: RwLock::new(ConflictPolicy::from_env()),
+            last_replay: RwLock::new(None),
+            replaying: tokio::sync::Mutex::new(()),
+            path,
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+            .join("db_write_queue.json")
+    }
+
+    pub fn len(&self) -> usize {
+        self.pending.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending.lock().is_empty()
+    }
+
+    pub fn set_policy(&self, policy: ConflictPolicy) {
+        *self.policy.write() = policy;
+    }
+
+    pub fn status(&self) -> WriteQueueStatus {
+        let pending = self.pending.lock();
+        WriteQueueStatus {
+            pending: pending.len(),
+            oldest_queued_at: pending.front().map(|w| w.queued_at),
+            policy: *self.policy.read(),
+            last_replay: self.last_replay.read().clone(),
+        }
+    }
+
+    /// Write now, or queue the write if the database is unreachable. While
+    /// writes are waiting, new ones queue behind them so they land in
+    /// order. Returns whether the write was made now rather than queued;
+    /// errs only when the database rejects the write.
+    pub async fn submit(&self, pool: &PgPool, write: PendingWrite) -> Result<bool, sqlx::Error> {
+        if !self.is_empty() {
+            self.enqueue(write);
+            return Ok(false);
+        }
+        match write.execute(pool).await {
+            Ok(()) => Ok(true),
+            Err(e) if is_connection_error(&e) => {
+                log::warn!("Database unreachable, queueing write: {}", e);
+                self.enqueue(write);
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn enqueue(&self, write: PendingWrite) {
+        let mut pending = self.pending.lock();
+        if pending.len() >= MAX_PENDING {
+            pending.pop_front();
+            log::warn!("Database write queue is full; dropped the oldest write");
+        }
+        pending.push_back(QueuedWrite {
+            write,
+            queued_at: Utc::now(),
+        });
+        self.save(&pending);
+    }
+
+    /// Replay queued writes in order until the queue is empty or the
+    /// database becomes unreachable again
+    pub async fn replay(&self, pool: &PgPool) -> ReplayReport {
+        let _replaying = self.replaying.lock().await;
+        let mut report = ReplayReport::default();
+        {
+            let mut pending = self.pending.lock();
+            let before = pending.len();
+            *pending = coalesce(std::mem::take(&mut *pending), *self.policy.read());
+            report.coalesced = before - pending.len();
+        }
+
+        loop {
+            let Some(next) = self.pending.lock().front().cloned() else {
+                break;
+            };
+            match next.write.execute(pool).await {
+                Ok(()) => report.replayed += 1,
+                Err(e) if is_connection_error(&e) => break,
+                Err(e) => {
+                    log::warn!("Dropping queued database write rejected on replay: {}", e);
+                    report.dropped += 1;
+                }
+            }
+            // Unless a full queue already dropped it
+            let mut pending = self.pending.lock();
+            if pending.front() == Some(&next) {
+                pending.pop_front();
+            }
+        }
+
+        let pending = self.pending.lock();
+        report.remaining = pending.len();
+        self.save(&pending);
+        drop(pending);
+
+        if report.replayed + report.dropped > 0 {
+            log::info!(
+                "Replayed {} queued database write(s), dropped {}, {} still queued",
+                report.replayed,
+                report.dropped,
+                report.remaining
+            );
+        }
+        *self.last_replay.write() = Some(report.clone());
+        report
+    }
+
+    fn save(&self, pending: &VecDeque<QueuedWrite>) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let saved = if pending.is_empty() {
+            match fs::remove_file(path) {
+                Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+                _ => Ok(()),
+            }
+        } else {
+            path.parent()
+                .map_or(Ok(()), fs::create_dir_all)
+                .map_err(|e| e.to_string())
+                .and_then(|_| serde_json::to_string(pending).map_err(|e| e.to_string()))
+                .and_then(|json| fs::write(path, json).map_err(|e| e.to_string()))
+        };
+        if let Err(e) = saved {
+            log::warn!("Failed to save the database write queue to {:?}: {}", path, e);
+        }
+    }
+}
+
+/// `pending` as it will be replayed under `policy`
+fn coalesce(pending: VecDeque<QueuedWrite>, policy: ConflictPolicy) -> VecDeque<QueuedWrite> {
+    if policy == ConflictPolicy::ReplayAll {
+        return pending;
+    }
+    // Walk newest first, keeping the first update seen for each target
+    let mut seen: HashSet<(Uuid, &'static str)> = HashSet::new();
+    let mut kept: VecDeque<QueuedWrite> = VecDeque::with_capacity(pending.len());
+    for queued in pending.into_iter().rev() {
+        if queued.write.target().map_or(true, |target| seen.insert(target)) {
+            kept.push_front(queued);
+        }
+    }
+    kept
+}
+
+lazy_static! {
+    pub static ref WRITE_QUEUE: WriteQueue = WriteQueue::new(Some(WriteQueue::default_path()));
+}
+
+/// [`WriteQueue::submit`] to the global queue in the background. Does
+/// nothing while offline; what was made offline is copied over when the
+/// database connects.
+pub fn submit_in_background(pool: Option<PgPool>, write: PendingWrite) {
+    let Some(pool) = pool else {
+        return;
+    };
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = WRITE_QUEUE.submit(&pool, write).await {
+            log::warn!("Database rejected a write: {}", e);
+        }
+    });
+}
+
+/// Replay queued writes every few seconds while any are waiting
+pub fn spawn_replay(pool: PgPool) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(REPLAY_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            if !WRITE_QUEUE.is_empty() {
+                WRITE_QUEUE.replay(&pool).await;
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queued(write: PendingWrite) -> QueuedWrite {
+        QueuedWrite {
+            write,
+            queued_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_coalesce() {
+        let agent = Uuid::new_v4();
+        let log = |content: &str| {
+            queued(PendingWrite::CreateExecutionLog {
+                agent_id: agent,
+                log_type: LogType::Info,
+                content: content.to_string(),
+            })
+        };
+        let status = |status| queued(PendingWrite::UpdateAgentStatus { agent_id: agent, status });
+        let pending = VecDeque::from([
+            status(AgentDbStatus::Starting),
+            log("started"),
+            queued(PendingWrite::UpdateAgentProgress { agent_id: agent, progress: 50 }),
+            status(AgentDbStatus::Running),
+            log("done"),
+            status(AgentDbStatus::Completed),
+        ]);
+
+        assert_eq!(coalesce(pending.clone(), ConflictPolicy::ReplayAll).len(), 6);
+
+        let kept: Vec<PendingWrite> = coalesce(pending, ConflictPolicy::LatestOnly).into_iter().map(|q| q.write).collect();
+        assert_eq!(kept.len(), 4);
+        assert_eq!(kept[1], PendingWrite::UpdateAgentProgress { agent_id: agent, progress: 50 });
+        assert_eq!(
+            kept[3],
+            PendingWrite::UpdateAgentStatus {
+                agent_id: agent,
+                status: AgentDbStatus::Completed
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_queued_while_unreachable_and_replayed() {
+        let queue = WriteQueue::new(None);
+        let unreachable = sqlx::postgres::PgPoolOptions::new()
+            .acquire_timeout(Duration::from_millis(500))
+            .connect_lazy("postgres://postgres@127.0.0.1:1/nexus")
+            .unwrap();
+        let write = || PendingWrite::UpdateAgentProgress {
+            agent_id: Uuid::new_v4(),
+            progress: 40,
+        };
+
+        assert!(!queue.submit(&unreachable, write()).await.unwrap());
+        assert_eq!(queue.len(), 1);
+        // Later writes line up behind it, without trying the database
+        assert!(!queue.submit(&unreachable, write()).await.unwrap());
+        assert_eq!(queue.replay(&unreachable).await.remaining, 2);
+
+        // Replaying needs a database that answers
+        let Ok(url) = std::env::var("DATABASE_URL") else {
+            return;
+        };
+        let Ok(pool) = sqlx::PgPool::connect(&url).await else {
+            return;
+        };
+        let report = queue.replay(&pool).await;
+        assert_eq!((report.replayed, report.remaining), (2, 0));
+        assert!(queue.is_empty());
+    }
+}
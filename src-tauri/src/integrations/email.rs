@@ -0,0 +1,389 @@
+//! Minimal SMTP client for sending reports.
+//!
+//! Provides:
+//! - Plain, STARTTLS and implicit TLS connections
+//! - AUTH PLAIN login
+//! - MIME messages with a single attachment
+//! - Configuration from `SMTP_*` environment variables
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio_native_tls::{native_tls, TlsConnector};
+use uuid::Uuid;
+
+/// Limit for the whole SMTP conversation
+const SEND_TIMEOUT_SECS: u64 = 60;
+/// Base64 line length required by RFC 2045
+const BASE64_LINE_LEN: usize = 76;
+
+#[derive(Debug, Error)]
+pub enum EmailError {
+    #[error("SMTP not configured: {0}")]
+    Config(String),
+
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+
+    #[error("Connection failed: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("TLS error: {0}")]
+    Tls(#[from] native_tls::Error),
+
+    #[error("SMTP server returned {code}: {message}")]
+    Smtp { code: u16, message: String },
+
+    #[error("SMTP conversation timed out")]
+    Timeout,
+}
+
+/// How the connection to the server is secured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SmtpSecurity {
+    /// Unencrypted (local relays only)
+    None,
+    /// Upgrade a plain connection with STARTTLS
+    #[default]
+    StartTls,
+    /// TLS from the first byte
+    Tls,
+}
+
+impl SmtpSecurity {
+    fn default_port(&self) -> u16 {
+        match self {
+            SmtpSecurity::None => 25,
+            SmtpSecurity::StartTls => 587,
+            SmtpSecurity::Tls => 465,
+        }
+    }
+}
+
+/// SMTP server settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    /// Falls back to `SMTP_PASSWORD`
+    #[serde(default, skip_serializing)]
+    pub password: Option<String>,
+    /// Sender address
+    pub from: String,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+}
+
+impl SmtpConfig {
+    /// Read settings from `SMTP_HOST`, `SMTP_PORT`, `SMTP_USERNAME`,
+    /// `SMTP_PASSWORD`, `SMTP_FROM` and `SMTP_SECURITY`
+    pub fn from_env() -> Result<Self, EmailError> {
+        let host = env::var("SMTP_HOST").map_err(|_| EmailError::Config("SMTP_HOST is not set".to_string()))?;
+        let from = env::var("SMTP_FROM").map_err(|_| EmailError::Config("SMTP_FROM is not set".to_string()))?;
+
+        let port = match env::var("SMTP_PORT") {
+            Ok(port) => Some(
+                port.parse()
+                    .map_err(|_| EmailError::Config(format!("Invalid SMTP_PORT: {}", port)))?,
+            ),
+            Err(_) => None,
+        };
+
+        let security = match env::var("SMTP_SECURITY").as_deref() {
+            Ok("none") => SmtpSecurity::None,
+            Ok("tls") => SmtpSecurity::Tls,
+            Ok("starttls") | Err(_) => SmtpSecurity::StartTls,
+            Ok(other) => return Err(EmailError::Config(format!("Invalid SMTP_SECURITY: {}", other))),
+        };
+
+        Ok(Self {
+            host,
+            port,
+            username: env::var("SMTP_USERNAME").ok(),
+            password: None,
+            from,
+            security,
+        })
+    }
+
+    fn password(&self) -> Option<String> {
+        self.password.clone().or_else(|| env::var("SMTP_PASSWORD").ok())
+    }
+}
+
+/// A file attached to an email
+#[derive(Debug, Clone)]
+pub struct Attachment {
+    pub filename: String,
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+/// An email with an optional attachment
+#[derive(Debug, Clone)]
+pub struct EmailMessage {
+    pub to: Vec<String>,
+    pub subject: String,
+    pub body: String,
+    pub attachment: Option<Attachment>,
+}
+
+impl EmailMessage {
+    fn validate(&self, from: &str) -> Result<(), EmailError> {
+        if self.to.is_empty() {
+            return Err(EmailError::InvalidMessage("No recipients".to_string()));
+        }
+        for address in self.to.iter().map(String::as_str).chain([from]) {
+            let valid = address.contains('@') && !address.contains(['\r', '\n', '<', '>', ',']);
+            if !valid {
+                return Err(EmailError::InvalidMessage(format!("Invalid address: {:?}", address)));
+            }
+        }
+        if self.subject.contains(['\r', '\n']) {
+            return Err(EmailError::InvalidMessage("Subject contains a line break".to_string()));
+        }
+        if let Some(attachment) = &self.attachment {
+            if attachment.filename.contains(['\r', '\n', '"']) || attachment.mime_type.contains(['\r', '\n']) {
+                return Err(EmailError::InvalidMessage("Invalid attachment name or type".to_string()));
+            }
+        }
+        Ok(())
+    }
+
+    /// Build the RFC 5322 message, with CRLF line endings
+    pub fn to_mime(&self, from: &str) -> String {
+        let boundary = format!("nexus-{}", Uuid::new_v4().simple());
+        let subject = if self.subject.is_ascii() {
+            self.subject.clone()
+        } else {
+            format!("=?utf-8?B?{}?=", BASE64.encode(&self.subject))
+        };
+
+        let mut lines = vec![
+            format!("From: <{}>", from),
+            format!("To: {}", self.to.iter().map(|to| format!("<{}>", to)).collect::<Vec<_>>().join(", ")),
+            format!("Subject: {}", subject),
+            format!("Date: {}", chrono::Utc::now().to_rfc2822()),
+            "MIME-Version: 1.0".to_string(),
+            format!("Content-Type: multipart/mixed; boundary=\"{}\"", boundary),
+            String::new(),
+            format!("--{}", boundary),
+            "Content-Type: text/plain; charset=utf-8".to_string(),
+            "Content-Transfer-Encoding: base64".to_string(),
+            String::new(),
+        ];
+        lines.extend(wrapped_base64(self.body.as_bytes()));
+
+        if let Some(attachment) = &self.attachment {
+            lines.push(format!("--{}", boundary));
+            lines.push(format!("Content-Type: {}; name=\"{}\"", attachment.mime_type, attachment.filename));
+            lines.push(format!("Content-Disposition: attachment; filename=\"{}\"", attachment.filename));
+            lines.push("Content-Transfer-Encoding: base64".to_string());
+            lines.push(String::new());
+            lines.extend(wrapped_base64(&attachment.data));
+        }
+
+        lines.push(format!("--{}--", boundary));
+        lines.join("\r\n") + "\r\n"
+    }
+}
+
+fn wrapped_base64(data: &[u8]) -> Vec<String> {
+    let encoded = BASE64.encode(data);
+    encoded
+        .as_bytes()
+        .chunks(BASE64_LINE_LEN)
+        .map(|chunk| String::from_utf8_lossy(chunk).into_owned())
+        .collect()
+}
+
+/// Escape lines starting with '.' so they don't end the DATA section
+fn dot_stuff(message: &str) -> String {
+    message
+        .split("\r\n")
+        .map(|line| if line.starts_with('.') { format!(".{}", line) } else { line.to_string() })
+        .collect::<Vec<_>>()
+        .join("\r\n")
+}
+
+/// Send a message through the configured server
+pub async fn send_email(config: &SmtpConfig, message: &EmailMessage) -> Result<(), EmailError> {
+    message.validate(&config.from)?;
+
+    tokio::time::timeout(Duration::from_secs(SEND_TIMEOUT_SECS), deliver(config, message))
+        .await
+        .map_err(|_| EmailError::Timeout)?
+}
+
+async fn deliver(config: &SmtpConfig, message: &EmailMessage) -> Result<(), EmailError> {
+    let port = config.port.unwrap_or_else(|| config.security.default_port());
+    let tcp = TcpStream::connect((config.host.as_str(), port)).await?;
+
+    match config.security {
+        SmtpSecurity::None => session(BufReader::new(tcp), config, message, true).await,
+        SmtpSecurity::Tls => {
+            let tls = tls_connect(&config.host, tcp).await?;
+            session(BufReader::new(tls), config, message, true).await
+        }
+        SmtpSecurity::StartTls => {
+            let mut plain = BufReader::new(tcp);
+            read_reply(&mut plain, &[220]).await?;
+            command(&mut plain, "EHLO nexus", &[250]).await?;
+            command(&mut plain, "STARTTLS", &[220]).await?;
+
+            let tls = tls_connect(&config.host, plain.into_inner()).await?;
+            session(BufReader::new(tls), config, message, false).await
+        }
+    }
+}
+
+async fn tls_connect(
+    host: &str,
+    tcp: TcpStream,
+) -> Result<tokio_native_tls::TlsStream<TcpStream>, EmailError> {
+    let connector = TlsConnector::from(native_tls::TlsConnector::new()?);
+    Ok(connector.connect(host, tcp).await?)
+}
+
+/// Run the SMTP conversation from EHLO to QUIT
+async fn session<S>(
+    mut stream: S,
+    config: &SmtpConfig,
+    message: &EmailMessage,
+    expect_greeting: bool,
+) -> Result<(), EmailError>
+where
+    S: AsyncBufRead + AsyncWrite + Unpin,
+{
+    if expect_greeting {
+        read_reply(&mut stream, &[220]).await?;
+    }
+    command(&mut stream, "EHLO nexus", &[250]).await?;
+
+    if let Some(username) = &config.username {
+        let password = config
+            .password()
+            .ok_or_else(|| EmailError::Config("SMTP password is not set".to_string()))?;
+        let credentials = BASE64.encode(format!("\0{}\0{}", username, password));
+        command(&mut stream, &format!("AUTH PLAIN {}", credentials), &[235]).await?;
+    }
+
+    command(&mut stream, &format!("MAIL FROM:<{}>", config.from), &[250]).await?;
+    for to in &message.to {
+        command(&mut stream, &format!("RCPT TO:<{}>", to), &[250, 251]).await?;
+    }
+    command(&mut stream, "DATA", &[354]).await?;
+
+    let data = dot_stuff(&message.to_mime(&config.from));
+    stream.write_all(data.as_bytes()).await?;
+    command(&mut stream, ".", &[250]).await?;
+
+    // The message is accepted at this point; a failed QUIT doesn't matter
+    let _ = command(&mut stream, "QUIT", &[221]).await;
+    Ok(())
+}
+
+async fn command<S>(stream: &mut S, line: &str, expect: &[u16]) -> Result<String, EmailError>
+where
+    S: AsyncBufRead + AsyncWrite + Unpin,
+{
+    stream.write_all(format!("{}\r\n", line).as_bytes()).await?;
+    stream.flush().await?;
+    read_reply(stream, expect).await
+}
+
+/// Read a (possibly multi-line) reply and check its code
+async fn read_reply<S>(stream: &mut S, expect: &[u16]) -> Result<String, EmailError>
+where
+    S: AsyncBufRead + Unpin,
+{
+    let mut text = Vec::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(EmailError::Io(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+
+        let line = line.trim_end();
+        let code: u16 = line
+            .get(..3)
+            .and_then(|code| code.parse().ok())
+            .ok_or_else(|| EmailError::Smtp { code: 0, message: line.to_string() })?;
+        text.push(line.get(4..).unwrap_or("").to_string());
+
+        // "250-..." continues the reply, "250 ..." ends it
+        if line.as_bytes().get(3) != Some(&b'-') {
+            let message = text.join("\n");
+            return if expect.contains(&code) {
+                Ok(message)
+            } else {
+                Err(EmailError::Smtp { code, message })
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message() -> EmailMessage {
+        EmailMessage {
+            to: vec!["dev@example.com".to_string()],
+            subject: "Report".to_string(),
+            body: "See attached.".to_string(),
+            attachment: Some(Attachment {
+                filename: "report.md".to_string(),
+                mime_type: "text/markdown".to_string(),
+                data: b"# Report".to_vec(),
+            }),
+        }
+    }
+
+    #[test]
+    fn test_mime_structure() {
+        let mime = message().to_mime("nexus@example.com");
+
+        assert!(mime.contains("To: <dev@example.com>\r\n"));
+        assert!(mime.contains("Content-Disposition: attachment; filename=\"report.md\""));
+        assert!(mime.contains(&BASE64.encode("# Report")));
+        assert!(mime.lines().all(|line| line.len() <= 998));
+        assert!(mime.trim_end().ends_with("--"));
+    }
+
+    #[test]
+    fn test_rejects_header_injection() {
+        let mut msg = message();
+        msg.subject = "Hi\r\nBcc: evil@example.com".to_string();
+        assert!(msg.validate("nexus@example.com").is_err());
+
+        let mut msg = message();
+        msg.to = vec!["a@example.com>\r\nRCPT TO:<b@example.com".to_string()];
+        assert!(msg.validate("nexus@example.com").is_err());
+    }
+
+    #[test]
+    fn test_dot_stuffing() {
+        assert_eq!(dot_stuff("a\r\n.\r\n..b"), "a\r\n..\r\n...b");
+    }
+
+    #[tokio::test]
+    async fn test_multiline_reply() {
+        let mut reader = BufReader::new(&b"250-smtp.example.com\r\n250-AUTH PLAIN\r\n250 STARTTLS\r\n"[..]);
+        let reply = read_reply(&mut reader, &[250]).await.unwrap();
+        assert_eq!(reply, "smtp.example.com\nAUTH PLAIN\nSTARTTLS");
+
+        let mut reader = BufReader::new(&b"535 Authentication failed\r\n"[..]);
+        assert!(matches!(
+            read_reply(&mut reader, &[235]).await,
+            Err(EmailError::Smtp { code: 535, .. })
+        ));
+    }
+}
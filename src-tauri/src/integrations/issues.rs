@@ -0,0 +1,511 @@
+//! Issue tracker clients (GitHub Issues and Jira).
+//!
+//! Provides:
+//! - Fetching issues by filter, across as many pages as the limit needs
+//! - Converting issues into batch items for workflows
+//! - Writing results back as comments
+//! - Transitioning issue status
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::settings::SETTINGS;
+use crate::workflow::BatchItem;
+
+const GITHUB_API_URL: &str = "https://api.github.com";
+/// Default number of issues fetched
+const DEFAULT_LIMIT: usize = 50;
+/// Most issues the tracker APIs return per page
+const MAX_PAGE_SIZE: usize = 100;
+/// Request timeout for tracker APIs
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Error)]
+pub enum TrackerError {
+    #[error("Tracker not configured: {0}")]
+    Config(String),
+
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Tracker API returned {status}: {message}")]
+    Api { status: u16, message: String },
+
+    #[error("Unexpected response: {0}")]
+    Parse(String),
+
+    #[error("No transition to '{0}' is available")]
+    NoTransition(String),
+}
+
+/// Which tracker to talk to. Credentials fall back to environment variables
+/// (`GITHUB_TOKEN`, `JIRA_EMAIL`, `JIRA_API_TOKEN`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "provider", rename_all = "snake_case")]
+pub enum TrackerConfig {
+    #[serde(rename = "github")]
+    GitHub {
+        owner: String,
+        repo: String,
+        #[serde(default)]
+        token: Option<String>,
+    },
+    Jira {
+        base_url: String,
+        #[serde(default)]
+        email: Option<String>,
+        #[serde(default)]
+        token: Option<String>,
+        /// Project key used when the filter has no JQL
+        #[serde(default)]
+        project: Option<String>,
+    },
+}
+
+/// Which issues to fetch
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct IssueFilter {
+    /// "open"/"closed"/"all" on GitHub, a status name on Jira
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    #[serde(default)]
+    pub assignee: Option<String>,
+    /// Raw JQL (Jira only); overrides the other fields
+    #[serde(default)]
+    pub query: Option<String>,
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+/// An issue from any tracker
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Issue {
+    /// "42" on GitHub, "PROJ-42" on Jira
+    pub key: String,
+    pub title: String,
+    pub body: String,
+    pub state: String,
+    pub labels: Vec<String>,
+    pub assignee: Option<String>,
+    pub url: String,
+}
+
+impl Issue {
+    /// Batch item exposing the issue as `issue_*` variables
+    pub fn to_batch_item(&self) -> BatchItem {
+        let mut variables = HashMap::new();
+        variables.insert("issue_key".to_string(), json!(self.key));
+        variables.insert("issue_title".to_string(), json!(self.title));
+        variables.insert("issue_body".to_string(), json!(self.body));
+        variables.insert("issue_state".to_string(), json!(self.state));
+        variables.insert("issue_labels".to_string(), json!(self.labels.join(", ")));
+        variables.insert("issue_url".to_string(), json!(self.url));
+
+        BatchItem {
+            key: Some(self.key.clone()),
+            input_prompt: None,
+            variables,
+        }
+    }
+
+    /// Default prompt when the caller doesn't supply a template
+    pub fn default_prompt(&self) -> String {
+        format!("Issue {}: {}\n\n{}\n\n{}", self.key, self.title, self.url, self.body)
+    }
+}
+
+/// Parse an item from the GitHub issues API. Pull requests are skipped.
+pub fn parse_github_issue(value: &Value) -> Option<Issue> {
+    if value.get("pull_request").is_some() {
+        return None;
+    }
+
+    Some(Issue {
+        key: value.get("number")?.as_u64()?.to_string(),
+        title: value.get("title")?.as_str()?.to_string(),
+        body: value.get("body").and_then(Value::as_str).unwrap_or_default().to_string(),
+        state: value.get("state").and_then(Value::as_str).unwrap_or("open").to_string(),
+        labels: value
+            .get("labels")
+            .and_then(Value::as_array)
+            .map(|labels| {
+                labels
+                    .iter()
+                    .filter_map(|l| l.get("name").and_then(Value::as_str))
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        assignee: value
+            .pointer("/assignee/login")
+            .and_then(Value::as_str)
+            .map(String::from),
+        url: value.get("html_url").and_then(Value::as_str).unwrap_or_default().to_string(),
+    })
+}
+
+/// Parse an issue from the Jira v2 search API
+pub fn parse_jira_issue(value: &Value, base_url: &str) -> Option<Issue> {
+    let key = value.get("key")?.as_str()?.to_string();
+    let fields = value.get("fields")?;
+
+    Some(Issue {
+        url: format!("{}/browse/{}", base_url.trim_end_matches('/'), key),
+        key,
+        title: fields.get("summary")?.as_str()?.to_string(),
+        body: fields
+            .get("description")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        state: fields
+            .pointer("/status/name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_string(),
+        labels: fields
+            .get("labels")
+            .and_then(Value::as_array)
+            .map(|labels| labels.iter().filter_map(Value::as_str).map(String::from).collect())
+            .unwrap_or_default(),
+        assignee: fields
+            .pointer("/assignee/displayName")
+            .and_then(Value::as_str)
+            .map(String::from),
+    })
+}
+
+/// A JQL string literal holding `value`
+fn jql_string(value: &str) -> String {
+    format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Build the JQL for a filter
+fn jira_jql(filter: &IssueFilter, project: Option<&str>) -> String {
+    if let Some(query) = &filter.query {
+        return query.clone();
+    }
+
+    let mut clauses = Vec::new();
+    if let Some(project) = project {
+        clauses.push(format!("project = {}", jql_string(project)));
+    }
+    if let Some(state) = &filter.state {
+        clauses.push(format!("status = {}", jql_string(state)));
+    }
+    for label in &filter.labels {
+        clauses.push(format!("labels = {}", jql_string(label)));
+    }
+    if let Some(assignee) = &filter.assignee {
+        clauses.push(format!("assignee = {}", jql_string(assignee)));
+    }
+
+    format!("{} ORDER BY created DESC", clauses.join(" AND "))
+        .trim_start()
+        .to_string()
+}
+
+/// The `rel="next"` URL of a `Link` header, as GitHub sends for paged
+/// results
+fn next_page_url(link: &str) -> Option<String> {
+    link.split(',').find_map(|entry| {
+        let mut parts = entry.split(';');
+        let url = parts.next()?.trim().strip_prefix('<')?.strip_suffix('>')?;
+        parts.any(|param| param.trim() == "rel=\"next\"").then(|| url.to_string())
+    })
+}
+
+/// Client for a configured issue tracker
+pub struct IssueTracker {
+    config: TrackerConfig,
+    client: reqwest::Client,
+}
+
+impl IssueTracker {
+    pub fn new(config: TrackerConfig) -> Self {
+        Self {
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// Fetch issues matching a filter
+    pub async fn fetch_issues(&self, filter: &IssueFilter) -> Result<Vec<Issue>, TrackerError> {
+        let limit = filter.limit.unwrap_or(DEFAULT_LIMIT);
+
+        match &self.config {
+            TrackerConfig::GitHub { owner, repo, .. } => {
+                let mut query = vec![
+                    ("state", filter.state.clone().unwrap_or_else(|| "open".to_string())),
+                    ("per_page", limit.min(MAX_PAGE_SIZE).to_string()),
+                ];
+                if !filter.labels.is_empty() {
+                    query.push(("labels", filter.labels.join(",")));
+                }
+                if let Some(assignee) = &filter.assignee {
+                    query.push(("assignee", assignee.clone()));
+                }
+
+                let url = format!("{}/repos/{}/{}/issues", GITHUB_API_URL, owner, repo);
+                let mut request = self.request(reqwest::Method::GET, &url)?.query(&query);
+                let mut issues = Vec::new();
+                loop {
+                    let (body, next) = self.send_page(request).await?;
+                    let items = body
+                        .as_array()
+                        .ok_or_else(|| TrackerError::Parse("expected an array of issues".to_string()))?;
+                    issues.extend(items.iter().filter_map(parse_github_issue));
+
+                    // The next page's URL carries the query along
+                    match next {
+                        Some(next) if issues.len() < limit => request = self.request(reqwest::Method::GET, &next)?,
+                        _ => break,
+                    }
+                }
+                issues.truncate(limit);
+                Ok(issues)
+            }
+            TrackerConfig::Jira { base_url, project, .. } => {
+                let url = format!("{}/rest/api/2/search", base_url.trim_end_matches('/'));
+                let jql = jira_jql(filter, project.as_deref());
+                let mut issues = Vec::new();
+                while issues.len() < limit {
+                    let start_at = issues.len();
+                    let payload = json!({
+                        "jql": jql,
+                        "startAt": start_at,
+                        "maxResults": (limit - start_at).min(MAX_PAGE_SIZE),
+                        "fields": ["summary", "description", "status", "labels", "assignee"],
+                    });
+                    let body = self.send(self.request(reqwest::Method::POST, &url)?.json(&payload)).await?;
+
+                    let items = body
+                        .get("issues")
+                        .and_then(Value::as_array)
+                        .ok_or_else(|| TrackerError::Parse("missing 'issues' in search response".to_string()))?;
+                    let total = body.get("total").and_then(Value::as_u64).unwrap_or(0) as usize;
+                    issues.extend(items.iter().filter_map(|i| parse_jira_issue(i, base_url)));
+                    if items.is_empty() || start_at + items.len() >= total {
+                        break;
+                    }
+                }
+                Ok(issues)
+            }
+        }
+    }
+
+    /// Post a comment on an issue
+    pub async fn add_comment(&self, issue_key: &str, body: &str) -> Result<(), TrackerError> {
+        let (url, payload) = match &self.config {
+            TrackerConfig::GitHub { owner, repo, .. } => (
+                format!("{}/repos/{}/{}/issues/{}/comments", GITHUB_API_URL, owner, repo, issue_key),
+                json!({ "body": body }),
+            ),
+            TrackerConfig::Jira { base_url, .. } => (
+                format!("{}/rest/api/2/issue/{}/comment", base_url.trim_end_matches('/'), issue_key),
+                json!({ "body": body }),
+            ),
+        };
+
+        self.send(self.request(reqwest::Method::POST, &url)?.json(&payload)).await?;
+        Ok(())
+    }
+
+    /// Move an issue to a new state: "open"/"closed" on GitHub, a transition
+    /// or target status name on Jira
+    pub async fn transition(&self, issue_key: &str, target: &str) -> Result<(), TrackerError> {
+        match &self.config {
+            TrackerConfig::GitHub { owner, repo, .. } => {
+                let url = format!("{}/repos/{}/{}/issues/{}", GITHUB_API_URL, owner, repo, issue_key);
+                self.send(self.request(reqwest::Method::PATCH, &url)?.json(&json!({ "state": target })))
+                    .await?;
+                Ok(())
+            }
+            TrackerConfig::Jira { base_url, .. } => {
+                let url = format!("{}/rest/api/2/issue/{}/transitions", base_url.trim_end_matches('/'), issue_key);
+                let available = self.send(self.request(reqwest::Method::GET, &url)?).await?;
+
+                // Match either the transition name or the status it leads to
+                let transition_id = available
+                    .get("transitions")
+                    .and_then(Value::as_array)
+                    .and_then(|transitions| {
+                        transitions.iter().find(|t| {
+                            let name = t.get("name").and_then(Value::as_str);
+                            let to = t.pointer("/to/name").and_then(Value::as_str);
+                            [name, to].iter().flatten().any(|n| n.eq_ignore_ascii_case(target))
+                        })
+                    })
+                    .and_then(|t| t.get("id").and_then(Value::as_str))
+                    .ok_or_else(|| TrackerError::NoTransition(target.to_string()))?
+                    .to_string();
+
+                self.send(
+                    self.request(reqwest::Method::POST, &url)?
+                        .json(&json!({ "transition": { "id": transition_id } })),
+                )
+                .await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Build an authenticated request
+    fn request(&self, method: reqwest::Method, url: &str) -> Result<reqwest::RequestBuilder, TrackerError> {
+        let builder = self
+            .client
+            .request(method, url)
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .header(reqwest::header::USER_AGENT, "nexus");
+
+        match &self.config {
+            TrackerConfig::GitHub { token, .. } => {
+                let token = token
+                    .clone()
+                    .or_else(|| SETTINGS.get().backends.github_token)
+                    .or_else(|| env::var("GITHUB_TOKEN").ok());
+                let builder = builder.header(reqwest::header::ACCEPT, "application/vnd.github+json");
+                Ok(match token {
+                    Some(token) => builder.bearer_auth(token),
+                    None => builder,
+                })
+            }
+            TrackerConfig::Jira { email, token, .. } => {
+                let backends = SETTINGS.get().backends;
+                let email = email.clone().or(backends.jira_email).or_else(|| env::var("JIRA_EMAIL").ok());
+                let token = token.clone().or(backends.jira_api_token).or_else(|| env::var("JIRA_API_TOKEN").ok());
+                match (email, token) {
+                    (Some(email), Some(token)) => Ok(builder.basic_auth(email, Some(token))),
+                    _ => Err(TrackerError::Config(
+                        "Jira requires an email and API token (JIRA_EMAIL / JIRA_API_TOKEN)".to_string(),
+                    )),
+                }
+            }
+        }
+    }
+
+    /// Send a request and decode the JSON body (empty bodies become Null)
+    async fn send(&self, request: reqwest::RequestBuilder) -> Result<Value, TrackerError> {
+        self.send_page(request).await.map(|(body, _)| body)
+    }
+
+    /// [`send`](Self::send), also returning the next page's URL from the
+    /// `Link` header
+    async fn send_page(&self, request: reqwest::RequestBuilder) -> Result<(Value, Option<String>), TrackerError> {
+        let response = request.send().await?;
+        let status = response.status();
+        let next = response
+            .headers()
+            .get(reqwest::header::LINK)
+            .and_then(|link| link.to_str().ok())
+            .and_then(next_page_url);
+        let text = response.text().await?;
+
+        if !status.is_success() {
+            return Err(TrackerError::Api {
+                status: status.as_u16(),
+                message: text,
+            });
+        }
+
+        if text.trim().is_empty() {
+            return Ok((Value::Null, next));
+        }
+        let body = serde_json::from_str(&text).map_err(|e| TrackerError::Parse(e.to_string()))?;
+        Ok((body, next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_github_issue() {
+        let issue = parse_github_issue(&json!({
+            "number": 42,
+            "title": "Crash on save",
+            "body": null,
+            "state": "open",
+            "labels": [{"name": "bug"}, {"name": "p1"}],
+            "assignee": {"login": "octocat"},
+            "html_url": "https://github.com/acme/app/issues/42"
+        }))
+        .unwrap();
+
+        assert_eq!(issue.key, "42");
+        assert_eq!(issue.body, "");
+        assert_eq!(issue.labels, vec!["bug", "p1"]);
+        assert_eq!(issue.assignee.as_deref(), Some("octocat"));
+
+        // Pull requests come back from the issues API too
+        assert!(parse_github_issue(&json!({"number": 7, "title": "PR", "pull_request": {}})).is_none());
+    }
+
+    #[test]
+    fn test_parse_jira_issue() {
+        let issue = parse_jira_issue(
+            &json!({
+                "key": "APP-7",
+                "fields": {
+                    "summary": "Login times out",
+                    "description": "Steps to reproduce...",
+                    "status": {"name": "To Do"},
+                    "labels": ["backend"],
+                    "assignee": null
+                }
+            }),
+            "https://acme.atlassian.net/",
+        )
+        .unwrap();
+
+        assert_eq!(issue.url, "https://acme.atlassian.net/browse/APP-7");
+        assert_eq!(issue.state, "To Do");
+        assert!(issue.assignee.is_none());
+
+        let item = issue.to_batch_item();
+        assert_eq!(item.key.as_deref(), Some("APP-7"));
+        assert_eq!(item.variables["issue_title"], json!("Login times out"));
+    }
+
+    #[test]
+    fn test_jira_jql() {
+        let filter = IssueFilter {
+            state: Some("To Do".to_string()),
+            labels: vec!["triage".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            jira_jql(&filter, Some("APP")),
+            "project = \"APP\" AND status = \"To Do\" AND labels = \"triage\" ORDER BY created DESC"
+        );
+        assert_eq!(jira_jql(&IssueFilter::default(), None), "ORDER BY created DESC");
+
+        // Values can't close the string they sit in
+        let filter = IssueFilter {
+            assignee: Some("x\" OR reporter = \"y\\".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            jira_jql(&filter, None),
+            "assignee = \"x\\\" OR reporter = \\\"y\\\\\" ORDER BY created DESC"
+        );
+    }
+
+    #[test]
+    fn test_next_page_url() {
+        let link = "<https://api.github.com/repositories/1/issues?per_page=100&page=2>; rel=\"next\", \
+                    <https://api.github.com/repositories/1/issues?per_page=100&page=5>; rel=\"last\"";
+        assert_eq!(
+            next_page_url(link).as_deref(),
+            Some("https://api.github.com/repositories/1/issues?per_page=100&page=2")
+        );
+        assert!(next_page_url("<https://api.github.com/x?page=1>; rel=\"prev\"").is_none());
+    }
+}
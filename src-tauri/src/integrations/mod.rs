@@ -0,0 +1,7 @@
+pub mod email;
+pub mod issues;
+pub mod registry;
+
+pub use email::{send_email, Attachment, EmailError, EmailMessage, SmtpConfig, SmtpSecurity};
+pub use issues::{Issue, IssueFilter, IssueTracker, TrackerConfig, TrackerError};
+pub use registry::{RegistryBackend, RegistrySync, SyncConfig, SyncConflict, SyncError, SyncItem, SyncItemKind, SyncReport, SyncStatus};
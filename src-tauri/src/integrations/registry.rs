@@ -0,0 +1,665 @@
+//! Team sync of custom building blocks through a shared registry.
+//!
+//! Custom workflow templates, role definitions and prompt versions are kept
+//! as JSON items in a local library and synced with a git repository or an
+//! HTTP registry. Provides:
+//! - The local library (one file per item, under the app data dir)
+//! - Git and HTTP registry backends
+//! - Three-way sync against the last synced version of each item, with
+//!   configurable conflict resolution
+//! - Sync status for the UI
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::process::Command;
+
+/// Request timeout for HTTP registries
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Error)]
+pub enum SyncError {
+    #[error("Registry sync is not configured")]
+    NotConfigured,
+
+    #[error("Invalid item id '{0}': use letters, digits, '.', '-' and '_'")]
+    InvalidId(String),
+
+    #[error("No sync conflict for {0}")]
+    NoConflict(String),
+
+    #[error("File error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("Invalid JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("git {command} failed: {message}")]
+    Git { command: String, message: String },
+
+    #[error("Request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    #[error("Registry returned {status}: {message}")]
+    Api { status: u16, message: String },
+}
+
+/// Kinds of shareable items, each stored in its own directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncItemKind {
+    Template,
+    Role,
+    Prompt,
+}
+
+impl SyncItemKind {
+    pub const ALL: [SyncItemKind; 3] = [SyncItemKind::Template, SyncItemKind::Role, SyncItemKind::Prompt];
+
+    pub fn dir(&self) -> &'static str {
+        match self {
+            SyncItemKind::Template => "templates",
+            SyncItemKind::Role => "roles",
+            SyncItemKind::Prompt => "prompts",
+        }
+    }
+}
+
+/// A custom template, role definition or prompt version
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncItem {
+    pub kind: SyncItemKind,
+    pub id: String,
+    pub content: serde_json::Value,
+    pub updated_at: DateTime<Utc>,
+    #[serde(default)]
+    pub updated_by: Option<String>,
+}
+
+impl SyncItem {
+    /// `<kind dir>/<id>`, unique across kinds
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.kind.dir(), self.id)
+    }
+
+    /// Stable hash of the content; the same on every machine and build
+    pub fn content_hash(&self) -> String {
+        // FNV-1a over the canonical JSON (object keys are sorted)
+        let json = serde_json::to_string(&self.content).unwrap_or_default();
+        let hash = json
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+        format!("{:016x}", hash)
+    }
+}
+
+fn validate_id(id: &str) -> Result<(), SyncError> {
+    let valid = !id.is_empty()
+        && !id.starts_with('.')
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if valid {
+        Ok(())
+    } else {
+        Err(SyncError::InvalidId(id.to_string()))
+    }
+}
+
+fn default_branch() -> String {
+    "main".to_string()
+}
+
+/// Where the team's shared items live
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RegistryBackend {
+    /// A git repository; items are files under `path`
+    Git {
+        url: String,
+        #[serde(default = "default_branch")]
+        branch: String,
+        #[serde(default)]
+        path: Option<String>,
+    },
+    /// An HTTP registry serving `GET {url}/items` and
+    /// `PUT {url}/items/{kind}/{id}` (with `If-Match: <base hash>`)
+    Http {
+        url: String,
+        #[serde(default)]
+        token: Option<String>,
+    },
+}
+
+impl RegistryBackend {
+    fn name(&self) -> &'static str {
+        match self {
+            RegistryBackend::Git { .. } => "git",
+            RegistryBackend::Http { .. } => "http",
+        }
+    }
+}
+
+/// What to do when an item changed both locally and in the registry
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictResolution {
+    /// Keep both and report a conflict for the user to resolve
+    #[default]
+    Manual,
+    PreferLocal,
+    PreferRemote,
+    /// Keep whichever was updated last
+    Newest,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncConfig {
+    pub backend: RegistryBackend,
+    #[serde(default)]
+    pub resolution: ConflictResolution,
+    /// Recorded as `updated_by` on items saved here
+    #[serde(default)]
+    pub author: Option<String>,
+}
+
+/// An item changed on both sides since the last sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncConflict {
+    pub key: String,
+    pub local: SyncItem,
+    pub remote: SyncItem,
+    pub detected_at: DateTime<Utc>,
+}
+
+/// Sync bookkeeping, saved between runs
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState {
+    last_sync_at: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+    /// Content hash of each item as of its last sync
+    bases: HashMap<String, String>,
+    conflicts: Vec<SyncConflict>,
+}
+
+/// What a sync will do
+#[derive(Debug, Default)]
+pub struct SyncPlan {
+    pub push: Vec<SyncItem>,
+    pub pull: Vec<SyncItem>,
+    pub conflicts: Vec<SyncConflict>,
+    /// Items already identical on both sides, with their hash
+    pub in_sync: Vec<(String, String)>,
+}
+
+/// Compare local and remote items against the hashes of the last sync
+pub fn plan_sync(
+    local: &[SyncItem],
+    remote: &[SyncItem],
+    bases: &HashMap<String, String>,
+    resolution: ConflictResolution,
+) -> SyncPlan {
+    let mut plan = SyncPlan::default();
+    let mut remote_by_key: HashMap<String, &SyncItem> = remote.iter().map(|item| (item.key(), item)).collect();
+
+    for local_item in local {
+        let key = local_item.key();
+        let Some(remote_item) = remote_by_key.remove(&key) else {
+            plan.push.push(local_item.clone());
+            continue;
+        };
+
+        let local_hash = local_item.content_hash();
+        let remote_hash = remote_item.content_hash();
+        let base = bases.get(&key);
+
+        if local_hash == remote_hash {
+            plan.in_sync.push((key, local_hash));
+        } else if base == Some(&local_hash) {
+            plan.pull.push(remote_item.clone());
+        } else if base == Some(&remote_hash) {
+            plan.push.push(local_item.clone());
+        } else {
+            let keep_local = match resolution {
+                ConflictResolution::Manual => None,
+                ConflictResolution::PreferLocal => Some(true),
+                ConflictResolution::PreferRemote => Some(false),
+                ConflictResolution::Newest => Some(local_item.updated_at >= remote_item.updated_at),
+            };
+            match keep_local {
+                Some(true) => plan.push.push(local_item.clone()),
+                Some(false) => plan.pull.push(remote_item.clone()),
+                None => plan.conflicts.push(SyncConflict {
+                    key,
+                    local: local_item.clone(),
+                    remote: remote_item.clone(),
+                    detected_at: Utc::now(),
+                }),
+            }
+        }
+    }
+
+    // Items only the registry has
+    let mut remote_only: Vec<&SyncItem> = remote_by_key.into_values().collect();
+    remote_only.sort_by_key(|item| item.key());
+    plan.pull.extend(remote_only.into_iter().cloned());
+
+    plan
+}
+
+/// Result of one sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncReport {
+    pub pushed: Vec<String>,
+    pub pulled: Vec<String>,
+    pub conflicts: Vec<SyncConflict>,
+    pub synced_at: DateTime<Utc>,
+}
+
+/// Sync state for the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncStatus {
+    pub configured: bool,
+    pub backend: Option<String>,
+    pub last_sync_at: Option<DateTime<Utc>>,
+    pub last_error: Option<String>,
+    pub local_items: usize,
+    /// Local items changed since their last sync
+    pub pending_push: usize,
+    pub conflicts: Vec<SyncConflict>,
+}
+
+/// Local library plus sync with the configured registry
+pub struct RegistrySync {
+    root: PathBuf,
+    /// One sync at a time
+    running: tokio::sync::Mutex<()>,
+}
+
+impl RegistrySync {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            root,
+            running: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    pub fn default_root() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+    }
+
+    fn library_dir(&self) -> PathBuf {
+        self.root.join("library")
+    }
+
+    fn sync_dir(&self) -> PathBuf {
+        self.root.join("sync")
+    }
+
+    pub fn config(&self) -> Result<Option<SyncConfig>, SyncError> {
+        read_json(&self.sync_dir().join("config.json"))
+    }
+
+    pub fn configure(&self, config: &SyncConfig) -> Result<(), SyncError> {
+        write_json(&self.sync_dir().join("config.json"), config)
+    }
+
+    fn state(&self) -> Result<SyncState, SyncError> {
+        Ok(read_json(&self.sync_dir().join("state.json"))?.unwrap_or_default())
+    }
+
+    fn save_state(&self, state: &SyncState) -> Result<(), SyncError> {
+        write_json(&self.sync_dir().join("state.json"), state)
+    }
+
+    /// Add or update an item in the local library
+    pub fn save_item(
+        &self,
+        kind: SyncItemKind,
+        id: &str,
+        content: serde_json::Value,
+    ) -> Result<SyncItem, SyncError> {
+        validate_id(id)?;
+        let item = SyncItem {
+            kind,
+            id: id.to_string(),
+            content,
+            updated_at: Utc::now(),
+            updated_by: self.config()?.and_then(|c| c.author),
+        };
+        write_item(&self.library_dir(), &item)?;
+        Ok(item)
+    }
+
+    /// Items in the local library, optionally of one kind
+    pub fn list_items(&self, kind: Option<SyncItemKind>) -> Result<Vec<SyncItem>, SyncError> {
+        let kinds: Vec<SyncItemKind> = match kind {
+            Some(kind) => vec![kind],
+            None => SyncItemKind::ALL.to_vec(),
+        };
+        let mut items = Vec::new();
+        for kind in kinds {
+            items.extend(read_items(&self.library_dir(), kind)?);
+        }
+        Ok(items)
+    }
+
+    pub fn status(&self) -> Result<SyncStatus, SyncError> {
+        let config = self.config()?;
+        let state = self.state()?;
+        let items = self.list_items(None)?;
+        let pending_push = items
+            .iter()
+            .filter(|item| state.bases.get(&item.key()) != Some(&item.content_hash()))
+            .count();
+
+        Ok(SyncStatus {
+            configured: config.is_some(),
+            backend: config.map(|c| c.backend.name().to_string()),
+            last_sync_at: state.last_sync_at,
+            last_error: state.last_error,
+            local_items: items.len(),
+            pending_push,
+            conflicts: state.conflicts,
+        })
+    }
+
+    /// Pull registry changes into the library and push local changes
+    pub async fn sync(&self) -> Result<SyncReport, SyncError> {
+        let _running = self.running.lock().await;
+        let config = self.config()?.ok_or(SyncError::NotConfigured)?;
+        let mut state = self.state()?;
+
+        let result = self.sync_with(&config, &mut state).await;
+        state.last_error = result.as_ref().err().map(|e| e.to_string());
+        self.save_state(&state)?;
+        result
+    }
+
+    async fn sync_with(&self, config: &SyncConfig, state: &mut SyncState) -> Result<SyncReport, SyncError> {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+            .build()?;
+        let remote = match &config.backend {
+            RegistryBackend::Git { url, branch, path } => {
+                let checkout = self.sync_dir().join("checkout");
+                git_checkout(&checkout, url, branch).await?;
+                let items_dir = git_items_dir(&checkout, path.as_deref());
+                let mut items = Vec::new();
+                for kind in SyncItemKind::ALL {
+                    items.extend(read_items(&items_dir, kind)?);
+                }
+                items
+            }
+            RegistryBackend::Http { url, token } => http_fetch(&client, url, token.as_deref()).await?,
+        };
+
+        let local = self.list_items(None)?;
+        let plan = plan_sync(&local, &remote, &state.bases, config.resolution);
+
+        match &config.backend {
+            RegistryBackend::Git { branch, path, .. } => {
+                let checkout = self.sync_dir().join("checkout");
+                git_push(&checkout, branch, path.as_deref(), &plan.push).await?;
+            }
+            RegistryBackend::Http { url, token } => {
+                for item in &plan.push {
+                    http_put(&client, url, token.as_deref(), item, state.bases.get(&item.key())).await?;
+                }
+            }
+        }
+
+        for item in &plan.pull {
+            write_item(&self.library_dir(), item)?;
+        }
+        for item in plan.push.iter().chain(&plan.pull) {
+            state.bases.insert(item.key(), item.content_hash());
+        }
+        state.bases.extend(plan.in_sync.iter().cloned());
+
+        let synced_at = Utc::now();
+        state.last_sync_at = Some(synced_at);
+        state.conflicts = plan.conflicts.clone();
+
+        Ok(SyncReport {
+            pushed: plan.push.iter().map(SyncItem::key).collect(),
+            pulled: plan.pull.iter().map(SyncItem::key).collect(),
+            conflicts: plan.conflicts,
+            synced_at,
+        })
+    }
+
+    /// Settle a conflict by keeping one side. Keeping the remote copy
+    /// replaces the local item; keeping the local copy pushes it next sync.
+    pub fn resolve_conflict(&self, key: &str, keep_local: bool) -> Result<(), SyncError> {
+        let mut state = self.state()?;
+        let index = state
+            .conflicts
+            .iter()
+            .position(|c| c.key == key)
+            .ok_or_else(|| SyncError::NoConflict(key.to_string()))?;
+        let conflict = state.conflicts.remove(index);
+
+        if !keep_local {
+            write_item(&self.library_dir(), &conflict.remote)?;
+        }
+        state.bases.insert(conflict.key, conflict.remote.content_hash());
+        self.save_state(&state)
+    }
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<Option<T>, SyncError> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(Some(serde_json::from_str(&content)?)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<(), SyncError> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(value)?)?;
+    Ok(())
+}
+
+fn write_item(dir: &Path, item: &SyncItem) -> Result<(), SyncError> {
+    write_json(&dir.join(item.kind.dir()).join(format!("{}.json", item.id)), item)
+}
+
+fn read_items(dir: &Path, kind: SyncItemKind) -> Result<Vec<SyncItem>, SyncError> {
+    let kind_dir = dir.join(kind.dir());
+    if !kind_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut items = Vec::new();
+    for entry in std::fs::read_dir(kind_dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|e| e == "json") {
+            let item: Option<SyncItem> = read_json(&path)?;
+            // The file location decides the kind
+            items.extend(item.filter(|i| i.kind == kind && validate_id(&i.id).is_ok()));
+        }
+    }
+    items.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(items)
+}
+
+async fn git(dir: &Path, args: &[&str]) -> Result<String, SyncError> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .await
+        .map_err(|e| SyncError::Git {
+            command: args.first().unwrap_or(&"").to_string(),
+            message: e.to_string(),
+        })?;
+
+    if !output.status.success() {
+        return Err(SyncError::Git {
+            command: args.first().unwrap_or(&"").to_string(),
+            message: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn git_items_dir(checkout: &Path, path: Option<&str>) -> PathBuf {
+    match path {
+        Some(path) => checkout.join(path),
+        None => checkout.to_path_buf(),
+    }
+}
+
+/// Clone the registry, or reset an existing clone to the remote branch
+async fn git_checkout(checkout: &Path, url: &str, branch: &str) -> Result<(), SyncError> {
+    if checkout.join(".git").is_dir() {
+        git(checkout, &["fetch", "origin", branch]).await?;
+        git(checkout, &["reset", "--hard", &format!("origin/{}", branch)]).await?;
+    } else {
+        let parent = checkout.parent().unwrap_or(checkout);
+        std::fs::create_dir_all(parent)?;
+        let target = checkout.to_string_lossy().to_string();
+        git(parent, &["clone", "--branch", branch, url, &target]).await?;
+    }
+    Ok(())
+}
+
+async fn git_push(checkout: &Path, branch: &str, path: Option<&str>, items: &[SyncItem]) -> Result<(), SyncError> {
+    if items.is_empty() {
+        return Ok(());
+    }
+
+    let items_dir = git_items_dir(checkout, path);
+    for item in items {
+        write_item(&items_dir, item)?;
+    }
+
+    git(checkout, &["add", "-A"]).await?;
+    let message = format!("Sync {} item(s) from NEXUS", items.len());
+    git(checkout, &["commit", "-m", &message]).await?;
+    if let Err(e) = git(checkout, &["push", "origin", &format!("HEAD:{}", branch)]).await {
+        // Someone pushed first; drop our commit so the next sync replans
+        let _ = git(checkout, &["reset", "--hard", &format!("origin/{}", branch)]).await;
+        return Err(e);
+    }
+    Ok(())
+}
+
+fn http_request(
+    client: &reqwest::Client,
+    method: reqwest::Method,
+    url: &str,
+    token: Option<&str>,
+) -> reqwest::RequestBuilder {
+    let request = client.request(method, url);
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+async fn http_check(response: reqwest::Response) -> Result<reqwest::Response, SyncError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let message = response.text().await.unwrap_or_default();
+    Err(SyncError::Api {
+        status: status.as_u16(),
+        message: if status == reqwest::StatusCode::CONFLICT || status == reqwest::StatusCode::PRECONDITION_FAILED {
+            format!("item changed in the registry during sync; sync again ({})", message)
+        } else {
+            message
+        },
+    })
+}
+
+async fn http_fetch(client: &reqwest::Client, url: &str, token: Option<&str>) -> Result<Vec<SyncItem>, SyncError> {
+    let url = format!("{}/items", url.trim_end_matches('/'));
+    let response = http_request(client, reqwest::Method::GET, &url, token).send().await?;
+    Ok(http_check(response).await?.json().await?)
+}
+
+async fn http_put(
+    client: &reqwest::Client,
+    url: &str,
+    token: Option<&str>,
+    item: &SyncItem,
+    base: Option<&String>,
+) -> Result<(), SyncError> {
+    let url = format!("{}/items/{}/{}", url.trim_end_matches('/'), item.kind.dir(), item.id);
+    let mut request = http_request(client, reqwest::Method::PUT, &url, token).json(item);
+    if let Some(base) = base {
+        request = request.header(reqwest::header::IF_MATCH, base.as_str());
+    }
+    http_check(request.send().await?).await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(kind: SyncItemKind, id: &str, content: serde_json::Value) -> SyncItem {
+        SyncItem {
+            kind,
+            id: id.to_string(),
+            content,
+            updated_at: Utc::now(),
+            updated_by: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_sync_three_way() {
+        let base_prompt = item(SyncItemKind::Prompt, "review", json!({"text": "v1"}));
+        let base_role = item(SyncItemKind::Role, "auditor", json!({"system_prompt": "v1"}));
+        let base_template = item(SyncItemKind::Template, "release", json!({"tasks": 1}));
+        let bases: HashMap<String, String> = [&base_prompt, &base_role, &base_template]
+            .iter()
+            .map(|i| (i.key(), i.content_hash()))
+            .collect();
+
+        let local = vec![
+            item(SyncItemKind::Prompt, "review", json!({"text": "v2"})),   // changed here
+            base_role.clone(),                                              // changed remotely
+            item(SyncItemKind::Template, "release", json!({"tasks": 2})),  // changed on both sides
+            item(SyncItemKind::Prompt, "triage", json!({"text": "new"})),  // new here
+        ];
+        let remote = vec![
+            base_prompt.clone(),
+            item(SyncItemKind::Role, "auditor", json!({"system_prompt": "v2"})),
+            item(SyncItemKind::Template, "release", json!({"tasks": 3})),
+            item(SyncItemKind::Role, "writer", json!({"system_prompt": "docs"})), // new remotely
+        ];
+
+        let plan = plan_sync(&local, &remote, &bases, ConflictResolution::Manual);
+        let keys = |items: &[SyncItem]| items.iter().map(SyncItem::key).collect::<Vec<_>>();
+        assert_eq!(keys(&plan.push), vec!["prompts/review", "prompts/triage"]);
+        assert_eq!(keys(&plan.pull), vec!["roles/auditor", "roles/writer"]);
+        assert_eq!(plan.conflicts.len(), 1);
+        assert_eq!(plan.conflicts[0].key, "templates/release");
+
+        let plan = plan_sync(&local, &remote, &bases, ConflictResolution::PreferRemote);
+        assert!(plan.conflicts.is_empty());
+        assert!(keys(&plan.pull).contains(&"templates/release".to_string()));
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        let a = item(SyncItemKind::Prompt, "p", json!({"b": 1, "a": [1, 2]}));
+        let b = item(SyncItemKind::Prompt, "p", json!({"a": [1, 2], "b": 1}));
+        assert_eq!(a.content_hash(), b.content_hash());
+        assert_ne!(a.content_hash(), item(SyncItemKind::Prompt, "p", json!({"b": 2})).content_hash());
+        assert!(validate_id("../escape").is_err());
+        assert!(validate_id("code-review.v2").is_ok());
+    }
+}
@@ -1,7 +1,9 @@
 pub mod api;
 pub mod commands;
+pub mod integrations;
 pub mod process;
 pub mod project;
+pub mod settings;
 pub mod state;
 pub mod workflow;
 
@@ -12,7 +14,7 @@ pub mod messaging;
 
 use state::AppState;
 use std::sync::Arc;
-use tauri::Manager;
+use tauri::{Emitter, Manager};
 
 #[cfg(feature = "database")]
 use tokio::runtime::Runtime;
@@ -29,7 +31,21 @@ pub fn run() {
         }
     }
 
-    tauri::Builder::default()
+    let mut builder = tauri::Builder::default();
+
+    // Must be registered first: a second launch hands its arguments (and
+    // any deep link) to this instance and exits
+    #[cfg(desktop)]
+    {
+        builder = builder.plugin(tauri_plugin_single_instance::init(|app, argv, cwd| {
+            log::info!("Second instance launched with {:?} in {}", argv, cwd);
+            api::deeplink::focus_main_window(app);
+            let _ = app.emit("single-instance", serde_json::json!({ "argv": argv, "cwd": cwd }));
+        }));
+    }
+
+    builder
+        .plugin(tauri_plugin_deep_link::init())
         .plugin(
             tauri_plugin_log::Builder::new()
                 .level(log::LevelFilter::Info)
@@ -55,6 +71,9 @@ pub fn run() {
 
                         match pool_result {
                             Ok(pool) => {
+                                // Writes that failed while the connection was down
+                                db::write_queue::spawn_replay(pool.clone());
+                                db::notify::spawn_listener(app.handle().clone(), pool.clone());
                                 app_state = Arc::new(AppState::with_database(pool));
                                 log::info!("NEXUS initialized with database connection");
                             }
@@ -63,6 +82,9 @@ pub fn run() {
                                 app_state = Arc::new(AppState::new());
                             }
                         }
+
+                        // Reconnect when the database comes back, or first becomes reachable
+                        db::health::spawn_monitor(app.handle().clone(), app_state.clone(), database_url);
                     }
                     Err(_) => {
                         log::info!("DATABASE_URL not set. Running in offline mode.");
@@ -79,16 +101,57 @@ pub fn run() {
 
             app.manage(app_state.clone());
 
-            // Start the HTTP API server for OpenDeck/Stream Deck integration
-            let api_port = std::env::var("NEXUS_API_PORT")
-                .ok()
-                .and_then(|s| s.parse().ok());
+            // Bring back agents that finished before the last shutdown
+            let restored = process::archive::AGENT_ARCHIVE.restore(&app_state);
+            log::info!("Restored {} finished agent(s) from the archive", restored);
+
+            // and pick up agents that were still running in their sessions
+            let reattached = process::manager::AgentManager::new(app.handle().clone()).reattach_all(&app_state);
+            if reattached > 0 {
+                log::info!("Reattached {} agent(s) to their sessions", reattached);
+            }
 
-            api::server::spawn_api_server(
-                app.handle().clone(),
-                app_state,
-                api_port,
-            );
+            // Handle nexus:// links, including the one that launched the app
+            #[cfg(desktop)]
+            {
+                use tauri_plugin_deep_link::DeepLinkExt;
+
+                #[cfg(any(windows, target_os = "linux"))]
+                if let Err(e) = app.deep_link().register_all() {
+                    log::warn!("Failed to register nexus:// links: {}", e);
+                }
+
+                let handle = app.handle().clone();
+                app.deep_link().on_open_url(move |event| {
+                    api::deeplink::focus_main_window(&handle);
+                    for url in event.urls() {
+                        api::deeplink::handle(&handle, &url.to_string());
+                    }
+                });
+
+                if let Ok(Some(urls)) = app.deep_link().get_current() {
+                    for url in urls {
+                        api::deeplink::handle(app.handle(), &url.to_string());
+                    }
+                }
+            }
+
+            // Register WASM plugins dropped into the plugins directory
+            if workflow::PluginRegistry::is_supported() {
+                let plugin_dir = workflow::PluginRegistry::default_plugin_dir();
+                let (loaded, _) = workflow::PLUGIN_REGISTRY.load_dir(&plugin_dir);
+                log::info!("Loaded {} plugin(s) from {:?}", loaded.len(), plugin_dir);
+            }
+
+            // Alert when saved workflows break their SLA rules
+            workflow::sla::spawn_monitor(app.handle().clone(), commands::workflow::get_history_store());
+
+            // Start the HTTP API server for OpenDeck/Stream Deck integration
+            if settings::SETTINGS.get().api.enabled {
+                api::server::spawn_api_server(app.handle().clone(), app_state, None);
+            } else {
+                log::info!("HTTP API server disabled in settings");
+            }
 
             Ok(())
         })
@@ -100,10 +163,14 @@ pub fn run() {
             commands::agent::get_agent,
             commands::agent::send_to_agent,
             commands::agent::restart_agent,
+            commands::agent::reattach_agent,
             commands::agent::get_agent_output,
             commands::agent::get_agent_runtime,
             commands::agent::pause_agent,
             commands::agent::resume_agent,
+            commands::agent::kill_agents,
+            commands::agent::restart_agents,
+            commands::agent::cleanup_finished_agents,
             // Project commands
             commands::project::create_project,
             commands::project::get_project,
@@ -111,23 +178,53 @@ pub fn run() {
             commands::project::update_project,
             commands::project::delete_project,
             commands::project::get_projects_base_directory,
+            commands::project::validate_projects_base_directory,
+            commands::project::set_projects_base_directory,
+            commands::project::scan_for_projects,
+            commands::project::import_projects,
+            commands::project::analyze_project_dependencies,
+            commands::project::get_impacted_files,
+            commands::project::detect_project_packages,
+            commands::project::set_network_policy,
+            commands::project::get_network_policy,
             // Workflow commands
             commands::workflow::create_workflow,
+            commands::workflow::import_workflow,
             commands::workflow::get_workflow,
             commands::workflow::list_workflows,
             commands::workflow::execute_workflow,
             commands::workflow::execute_orchestrated_workflow,
             commands::workflow::cancel_workflow_execution,
             commands::workflow::get_workflow_execution_status,
+            commands::workflow::purge_completed_executions,
             commands::workflow::validate_workflow,
+            commands::workflow::get_workflow_graph_schema,
             // Enhanced orchestration commands
             commands::workflow::execute_enhanced_workflow,
             commands::workflow::get_execution_context,
+            commands::workflow::get_node_activity,
+            commands::workflow::get_events_since,
+            commands::workflow::explain_node_decision,
+            commands::workflow::simulate_conditions,
+            commands::workflow::preview_node_prompt,
+            commands::workflow::estimate_execution,
+            commands::workflow::count_tokens,
+            commands::workflow::set_execution_log_level,
+            commands::workflow::get_execution_log,
+            commands::workflow::preview_rollback,
+            commands::workflow::rollback_execution_changes,
+            commands::workflow::list_isolated_workspaces,
+            commands::workflow::get_workspace_changes,
+            commands::workflow::approve_workspace_changes,
+            commands::workflow::discard_workspace,
             commands::workflow::list_checkpoints,
             commands::workflow::list_execution_checkpoints,
             commands::workflow::cleanup_checkpoints,
+            commands::workflow::export_checkpoint,
+            commands::workflow::import_checkpoint,
             commands::workflow::get_available_agent_roles,
             commands::workflow::get_aggregation_strategies,
+            commands::workflow::preview_aggregation,
             commands::workflow::get_condition_types,
             // Template commands
             commands::workflow::list_workflow_templates,
@@ -140,22 +237,139 @@ pub fn run() {
             commands::workflow::get_execution_history_stats,
             commands::workflow::list_execution_history,
             commands::workflow::search_execution_history,
+            commands::workflow::get_execution_environment,
+            commands::workflow::clear_node_result_cache,
+            commands::workflow::set_workflow_model_pins,
+            commands::workflow::set_workflow_env,
+            commands::workflow::set_sla_rules,
+            commands::workflow::get_sla_status,
+            commands::workflow::get_project_health,
+            commands::workflow::quick_search,
+            commands::workflow::quick_run,
+            commands::workflow::save_run_preset,
+            commands::workflow::list_run_presets,
+            commands::workflow::delete_run_preset,
+            commands::workflow::run_preset,
+            commands::workflow::annotate_execution_node,
+            commands::workflow::list_execution_annotations,
+            commands::workflow::remove_execution_annotation,
+            commands::workflow::rate_execution_node,
+            commands::workflow::analyze_prompt_performance,
+            commands::workflow::set_gate_thresholds,
+            commands::workflow::get_gate_thresholds,
+            commands::workflow::lint_workflow,
+            commands::workflow::set_auto_cancel_policy,
+            commands::workflow::get_auto_cancel_policy,
+            commands::workflow::get_audit_log,
+            commands::workflow::set_lint_config,
+            commands::workflow::get_lint_config,
+            commands::workflow::develop_workflow,
+            commands::workflow::stop_developing_workflow,
+            commands::workflow::update_workflow_graph,
+            commands::workflow::list_node_presets,
+            commands::workflow::get_node_preset,
+            commands::workflow::save_node_preset,
+            commands::workflow::delete_node_preset,
             // Resource management commands
             commands::workflow::get_resource_stats,
             commands::workflow::get_resource_config,
             commands::workflow::check_resource_availability,
+            commands::workflow::get_rate_limit_status,
+            commands::workflow::list_queued_tasks,
+            commands::workflow::cancel_queued_task,
+            commands::workflow::reprioritize_queued_task,
+            commands::workflow::get_project_locks,
+            commands::workflow::list_execution_groups,
+            commands::workflow::list_pending_approvals,
+            commands::workflow::approve_node,
+            commands::workflow::get_supply_chain_reports,
+            commands::workflow::reject_node,
+            // Batch commands
+            commands::workflow::execute_workflow_batch,
+            commands::workflow::fan_out_packages,
+            commands::workflow::get_batch_status,
+            commands::workflow::list_batches,
+            commands::workflow::cancel_batch,
+            // Report commands
+            commands::workflow::generate_execution_report,
+            commands::workflow::export_execution_report,
+            commands::workflow::export_execution_share,
+            commands::workflow::email_execution_report,
+            // Dataset commands
+            commands::workflow::export_execution_dataset,
+            // Evaluation commands
+            commands::workflow::create_evaluation_suite,
+            commands::workflow::list_evaluation_suites,
+            commands::workflow::delete_evaluation_suite,
+            commands::workflow::evaluate_workflow,
+            commands::workflow::get_evaluation_reports,
             // Messaging commands
             commands::workflow::get_execution_messages,
             commands::workflow::get_unread_agent_messages,
+            commands::workflow::broadcast_execution_message,
+            commands::workflow::purge_execution_messages,
+            // Plugin commands
+            commands::workflow::list_plugins,
+            commands::workflow::reload_plugins,
+            commands::workflow::load_plugin,
+            commands::workflow::unload_plugin,
+            // Issue tracker commands
+            commands::integrations::fetch_tracker_issues,
+            commands::integrations::comment_on_issue,
+            commands::integrations::transition_issue,
+            commands::integrations::execute_workflow_for_issues,
+            // Registry sync commands
+            commands::integrations::configure_sync,
+            commands::integrations::get_sync_config,
+            commands::integrations::save_library_item,
+            commands::integrations::list_library_items,
+            commands::integrations::sync_library,
+            commands::integrations::sync_status,
+            commands::integrations::resolve_sync_conflict,
+            // Settings commands
+            commands::settings::get_settings,
+            commands::settings::update_settings,
+            commands::settings::list_recent_items,
+            commands::settings::record_recent_item,
+            commands::settings::clear_recent_items,
+            commands::settings::list_favorites,
+            commands::settings::pin_favorite,
+            commands::settings::unpin_favorite,
+            commands::settings::reorder_favorites,
+            commands::settings::get_suggested_items,
             // System commands
             commands::system::get_system_status,
             commands::system::get_database_status,
+            commands::system::replay_database_writes,
+            commands::system::run_diagnostics,
+            commands::system::get_active_executions,
+            commands::system::shutdown_app,
+            commands::system::emergency_stop,
+            commands::system::get_spawn_lock,
+            commands::system::resume_spawning,
+            commands::system::get_api_server_info,
+            commands::system::restart_api_server,
             // MCP commands
             commands::mcp::mcp_call_tool,
             commands::mcp::mcp_list_tools,
             commands::mcp::mcp_health_check,
             commands::mcp::mcp_server_info,
         ])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+        .on_window_event(|window, event| {
+            // Ask before closing over running executions
+            if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+                if !commands::system::allow_exit(window.app_handle()) {
+                    api.prevent_close();
+                }
+            }
+        })
+        .build(tauri::generate_context!())
+        .expect("error while building tauri application")
+        .run(|app, event| {
+            if let tauri::RunEvent::ExitRequested { api, .. } = event {
+                if !commands::system::allow_exit(app) {
+                    api.prevent_exit();
+                }
+            }
+        });
 }
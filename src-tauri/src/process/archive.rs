@@ -0,0 +1,302 @@
+//! Finished agents kept on disk.
+//!
+//! The registry and the app state only live as long as the process, so
+//! without this the UI forgets every finished agent on restart. Provides:
+//! - Saving an agent's metadata and output when its session ends
+//! - Reloading recently finished agents, and their restart configs, at
+//!   startup
+//! - Reading archived output on demand, and forgetting archived agents
+//! - Session records of running agents, so agents interrupted by a restart
+//!   can be reattached to their Claude Code session
+//!
+//! Each agent is a `<id>.json` record pointing at a `<id>.log` output file,
+//! so reloading metadata doesn't read every transcript. Session records
+//! live in `sessions/` until the agent finishes.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::manager::{AgentConfig, AgentInfo};
+use super::registry::AGENT_REGISTRY;
+use crate::state::AppState;
+
+/// Agents reloaded at startup, newest first
+pub const RELOAD_LIMIT: usize = 200;
+
+/// Archived agents older than this are deleted at startup
+pub const RETENTION_DAYS: i64 = 7;
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("Agent archive file operation failed: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid agent record: {0}")]
+    Record(#[from] serde_json::Error),
+}
+
+/// A finished agent as saved on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AgentRecord {
+    pub info: AgentInfo,
+    pub finished_at: DateTime<Utc>,
+    /// File holding the agent's output
+    pub output_path: PathBuf,
+    /// Config to restart the agent with
+    #[serde(default)]
+    pub config: Option<AgentConfig>,
+}
+
+/// What it takes to reattach a running agent after a restart
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionRecord {
+    pub info: AgentInfo,
+    /// Includes the working directory the session belongs to, and the
+    /// execution and node it worked for, if any
+    pub config: AgentConfig,
+    /// Claude Code session the agent runs in
+    pub session_id: String,
+    /// Process running the session when it was recorded
+    pub pid: u32,
+}
+
+/// Finished agents, one record and one output file each
+pub struct AgentArchive {
+    root: PathBuf,
+}
+
+impl AgentArchive {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn default_root() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+            .join("agents")
+    }
+
+    fn record_path(&self, agent_id: &Uuid) -> PathBuf {
+        self.root.join(format!("{}.json", agent_id))
+    }
+
+    fn output_path(&self, agent_id: &Uuid) -> PathBuf {
+        self.root.join(format!("{}.log", agent_id))
+    }
+
+    fn session_path(&self, agent_id: &Uuid) -> PathBuf {
+        self.root.join("sessions").join(format!("{}.json", agent_id))
+    }
+
+    /// Save a finished agent and its output
+    pub fn save(&self, info: &AgentInfo, output: &str) -> Result<AgentRecord, ArchiveError> {
+        fs::create_dir_all(&self.root)?;
+
+        let record = AgentRecord {
+            info: info.clone(),
+            finished_at: info.finished_at.unwrap_or_else(Utc::now),
+            output_path: self.output_path(&info.id),
+            config: AGENT_REGISTRY.get_config(&info.id),
+        };
+        fs::write(&record.output_path, output)?;
+        fs::write(self.record_path(&info.id), serde_json::to_vec_pretty(&record)?)?;
+        self.remove_session(&info.id);
+        Ok(record)
+    }
+
+    /// Record the session of an agent that just started
+    pub fn save_session(&self, record: &SessionRecord) -> Result<(), ArchiveError> {
+        let path = self.session_path(&record.info.id);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_vec_pretty(record)?)?;
+        Ok(())
+    }
+
+    /// Session record of an agent that hasn't finished
+    pub fn session(&self, agent_id: &Uuid) -> Option<SessionRecord> {
+        let bytes = fs::read(self.session_path(agent_id)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    /// Agents that were running when the app last stopped
+    pub fn sessions(&self) -> Vec<SessionRecord> {
+        let entries = match fs::read_dir(self.root.join("sessions")) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        entries
+            .filter_map(|e| e.ok())
+            .filter_map(|entry| {
+                let bytes = fs::read(entry.path()).ok()?;
+                serde_json::from_slice::<SessionRecord>(&bytes)
+                    .map_err(|e| log::warn!("Skipping session record {:?}: {}", entry.path(), e))
+                    .ok()
+            })
+            .collect()
+    }
+
+    pub fn remove_session(&self, agent_id: &Uuid) {
+        let _ = fs::remove_file(self.session_path(agent_id));
+    }
+
+    /// Agents that finished within the retention period, newest first and
+    /// at most `limit` of them. Older records are deleted.
+    pub fn load_recent(&self, limit: usize) -> Vec<AgentRecord> {
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(_) => return Vec::new(),
+        };
+        let cutoff = Utc::now() - Duration::days(RETENTION_DAYS);
+
+        let mut records = Vec::new();
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            if path.extension().map_or(true, |ext| ext != "json") {
+                continue;
+            }
+            let record = match fs::read(&path).map_err(ArchiveError::from).and_then(|bytes| {
+                serde_json::from_slice::<AgentRecord>(&bytes).map_err(ArchiveError::from)
+            }) {
+                Ok(record) => record,
+                Err(e) => {
+                    log::warn!("Skipping agent record {:?}: {}", path, e);
+                    continue;
+                }
+            };
+
+            if record.finished_at < cutoff {
+                self.remove(&record.info.id);
+            } else {
+                records.push(record);
+            }
+        }
+
+        records.sort_by_key(|r| std::cmp::Reverse(r.finished_at));
+        records.truncate(limit);
+        records
+    }
+
+    /// Put recently finished agents back into the app state, with their
+    /// configs so they can be restarted. Returns how many were restored.
+    pub fn restore(&self, state: &AppState) -> usize {
+        let records = self.load_recent(RELOAD_LIMIT);
+        let count = records.len();
+        for record in records {
+            if let Some(config) = record.config {
+                AGENT_REGISTRY.store_config(record.info.id, config);
+            }
+            state.agents.entry(record.info.id).or_insert(record.info);
+        }
+        count
+    }
+
+    /// Output of an archived agent
+    pub fn read_output(&self, agent_id: &Uuid) -> Option<String> {
+        fs::read_to_string(self.output_path(agent_id)).ok()
+    }
+
+    /// Forget an archived agent, and its session if it never finished
+    pub fn remove(&self, agent_id: &Uuid) {
+        let _ = fs::remove_file(self.record_path(agent_id));
+        let _ = fs::remove_file(self.output_path(agent_id));
+        self.remove_session(agent_id);
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref AGENT_ARCHIVE: AgentArchive = AgentArchive::new(AgentArchive::default_root());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::manager::AgentOwner;
+    use crate::process::manager::AgentStatus;
+
+    #[test]
+    fn test_save_and_reload() {
+        let root = std::env::temp_dir().join(format!("nexus-agents-{}", Uuid::new_v4()));
+        let archive = AgentArchive::new(root.clone());
+        assert!(archive.load_recent(10).is_empty());
+
+        let config = AgentConfig {
+            name: "reviewer".to_string(),
+            role: "tester".to_string(),
+            working_directory: "/tmp".to_string(),
+            project_id: None,
+            system_prompt: None,
+            assigned_task: None,
+            model: None,
+            env: Vec::new(),
+            owner: None,
+        };
+        let mut recent = AgentInfo::new(Uuid::new_v4(), &config);
+        recent.status = AgentStatus::Completed;
+        recent.finished_at = Some(Utc::now());
+        archive.save(&recent, "all tests pass").unwrap();
+
+        let mut stale = AgentInfo::new(Uuid::new_v4(), &config);
+        stale.status = AgentStatus::Failed;
+        stale.finished_at = Some(Utc::now() - Duration::days(RETENTION_DAYS + 1));
+        archive.save(&stale, "old").unwrap();
+
+        let records = archive.load_recent(10);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].info.id, recent.id);
+        assert_eq!(records[0].info.status, AgentStatus::Completed);
+        assert_eq!(archive.read_output(&recent.id).as_deref(), Some("all tests pass"));
+        // The expired agent was deleted
+        assert!(archive.read_output(&stale.id).is_none());
+
+        archive.remove(&recent.id);
+        assert!(archive.load_recent(10).is_empty());
+        let _ = fs::remove_dir_all(root);
+    }
+
+    #[test]
+    fn test_session_records_end_when_archived() {
+        let root = std::env::temp_dir().join(format!("nexus-agents-{}", Uuid::new_v4()));
+        let archive = AgentArchive::new(root.clone());
+        let config = AgentConfig {
+            name: "long-runner".to_string(),
+            role: "implementer".to_string(),
+            working_directory: "/tmp".to_string(),
+            project_id: None,
+            system_prompt: None,
+            assigned_task: Some("migrate the schema".to_string()),
+            model: None,
+            env: Vec::new(),
+            owner: Some(AgentOwner::node(Uuid::new_v4(), "migrate")),
+        };
+        let mut info = AgentInfo::new(Uuid::new_v4(), &config);
+        archive
+            .save_session(&SessionRecord {
+                info: info.clone(),
+                config,
+                session_id: info.id.to_string(),
+                pid: 4242,
+            })
+            .unwrap();
+
+        let sessions = archive.sessions();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].pid, 4242);
+        assert_eq!(sessions[0].config.owner.as_ref().and_then(|owner| owner.node_id.as_deref()), Some("migrate"));
+        assert_eq!(archive.session(&info.id).unwrap().session_id, info.id.to_string());
+        // Session records aren't mistaken for finished agents
+        assert!(archive.load_recent(10).is_empty());
+
+        info.status = AgentStatus::Completed;
+        archive.save(&info, "done").unwrap();
+        assert!(archive.sessions().is_empty());
+        let _ = fs::remove_dir_all(root);
+    }
+}
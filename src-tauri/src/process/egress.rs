@@ -0,0 +1,387 @@
+//! Network egress policy for agent processes.
+//!
+//! Agents working on proprietary code shouldn't reach arbitrary hosts.
+//! Provides:
+//! - A policy per project: open, deny-all, or an allowlist of domains
+//! - A local proxy per restricted project that enforces it; agents are
+//!   pointed at it through the usual proxy environment variables
+//! - An audit log entry for every connection the proxy refuses
+//!
+//! The model API (anthropic.com) and loopback addresses stay reachable
+//! under every policy, or agents couldn't run. Tools that ignore proxy
+//! variables aren't covered.
+
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::io;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+
+use crate::workflow::audit::{AuditAction, AuditEntry, AUDIT_LOG};
+
+/// Domains reachable under every policy
+const ALWAYS_ALLOWED: &[&str] = &["anthropic.com"];
+/// Longest request head the proxy reads before giving up
+const MAX_HEAD_BYTES: usize = 16 * 1024;
+/// Pause after a failed accept, such as when out of file descriptors,
+/// instead of retrying at once
+const ACCEPT_BACKOFF: std::time::Duration = std::time::Duration::from_millis(500);
+
+const FORBIDDEN: &[u8] =
+    b"HTTP/1.1 403 Forbidden\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\nBlocked by the project's network policy\n";
+const BAD_REQUEST: &[u8] = b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\n\r\n";
+const BAD_GATEWAY: &[u8] = b"HTTP/1.1 502 Bad Gateway\r\nConnection: close\r\n\r\n";
+const ESTABLISHED: &[u8] = b"HTTP/1.1 200 Connection established\r\n\r\n";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EgressMode {
+    /// No restrictions, and no proxy
+    #[default]
+    Open,
+    DenyAll,
+    /// Only the listed domains and their subdomains
+    Allowlist,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkPolicy {
+    pub mode: EgressMode,
+    /// `example.com` allows it and its subdomains, `*.example.com` only
+    /// the subdomains
+    pub allowed_domains: Vec<String>,
+}
+
+impl NetworkPolicy {
+    pub fn is_restricted(&self) -> bool {
+        self.mode != EgressMode::Open
+    }
+
+    /// Check the domains and bring them to lower case
+    pub fn normalized(mut self) -> Result<Self, String> {
+        for domain in &mut self.allowed_domains {
+            let trimmed = domain.trim().trim_end_matches('.').to_lowercase();
+            let bare = trimmed.strip_prefix("*.").unwrap_or(&trimmed);
+            if bare.is_empty() || bare.contains(['/', ':', '*']) || bare.chars().any(char::is_whitespace) {
+                return Err(format!("'{}' is not a domain", domain));
+            }
+            *domain = trimmed;
+        }
+        if self.mode == EgressMode::Allowlist && self.allowed_domains.is_empty() {
+            return Err("An allowlist needs at least one domain".to_string());
+        }
+        Ok(self)
+    }
+
+    /// Whether agents may connect to `host`
+    pub fn allows(&self, host: &str) -> bool {
+        let host = host.trim_end_matches('.').to_lowercase();
+        let within = |domain: &str| host == domain || host.ends_with(&format!(".{}", domain));
+        if self.mode == EgressMode::Open || is_loopback(&host) || ALWAYS_ALLOWED.iter().any(|domain| within(domain)) {
+            return true;
+        }
+        self.mode == EgressMode::Allowlist
+            && self.allowed_domains.iter().any(|domain| match domain.strip_prefix("*.") {
+                Some(parent) => host.ends_with(&format!(".{}", parent)),
+                None => within(domain),
+            })
+    }
+}
+
+fn is_loopback(host: &str) -> bool {
+    host == "localhost" || host.parse::<std::net::IpAddr>().is_ok_and(|ip| ip.is_loopback())
+}
+
+/// Every project's network policy, and the proxies enforcing them
+#[derive(Default)]
+pub struct NetworkPolicies {
+    policies: DashMap<Uuid, NetworkPolicy>,
+    /// Port of each project's proxy, once started
+    proxies: DashMap<Uuid, u16>,
+}
+
+impl NetworkPolicies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace a project's policy, recording the change. The proxy checks
+    /// each new connection against the current policy, so agents already
+    /// using it follow the change at once, except in tunnels they have
+    /// open. Agents started while the project was open have no proxy and
+    /// are only restricted once restarted.
+    pub fn set(&self, project_id: Uuid, policy: NetworkPolicy) -> Result<NetworkPolicy, String> {
+        let policy = policy.normalized()?;
+        let previous = self.policies.insert(project_id, policy.clone()).unwrap_or_default();
+        AUDIT_LOG.record(
+            AuditEntry::new(
+                AuditAction::NetworkPolicyChanged,
+                format!("Network policy of project {} changed", project_id),
+            )
+            .project(project_id)
+            .details(serde_json::json!({ "previous": previous, "policy": policy })),
+        );
+        Ok(policy)
+    }
+
+    /// A project's policy, open if it has none
+    pub fn get(&self, project_id: &Uuid) -> NetworkPolicy {
+        self.policies.get(project_id).map(|policy| policy.clone()).unwrap_or_default()
+    }
+
+    /// Environment variables pointing an agent of the project at its proxy,
+    /// starting the proxy if needed; none for an open project
+    pub fn proxy_env(&self, project_id: Uuid) -> Result<Vec<(String, String)>, String> {
+        if !self.get(&project_id).is_restricted() {
+            return Ok(Vec::new());
+        }
+        let port = match self.proxies.entry(project_id) {
+            dashmap::mapref::entry::Entry::Occupied(entry) => *entry.get(),
+            dashmap::mapref::entry::Entry::Vacant(entry) => {
+                let listener = std::net::TcpListener::bind(("127.0.0.1", 0))
+                    .and_then(|listener| listener.set_nonblocking(true).map(|_| listener))
+                    .map_err(|e| format!("Failed to start the network policy proxy: {}", e))?;
+                let port = listener.local_addr().map_err(|e| e.to_string())?.port();
+                tauri::async_runtime::spawn(serve(listener, project_id));
+                log::info!("Network policy proxy of project {} listening on port {}", project_id, port);
+                *entry.insert(port)
+            }
+        };
+
+        let proxy = format!("http://127.0.0.1:{}", port);
+        let no_proxy = "localhost,127.0.0.1,::1".to_string();
+        Ok(["HTTP_PROXY", "HTTPS_PROXY", "ALL_PROXY"]
+            .into_iter()
+            .flat_map(|name| [(name.to_string(), proxy.clone()), (name.to_lowercase(), proxy.clone())])
+            .chain([("NO_PROXY".to_string(), no_proxy.clone()), ("no_proxy".to_string(), no_proxy)])
+            .collect())
+    }
+}
+
+lazy_static! {
+    pub static ref NETWORK_POLICIES: NetworkPolicies = NetworkPolicies::new();
+}
+
+async fn serve(listener: std::net::TcpListener, project_id: Uuid) {
+    let listener = match TcpListener::from_std(listener) {
+        Ok(listener) => listener,
+        Err(e) => {
+            log::error!("Network policy proxy of project {} failed: {}", project_id, e);
+            return;
+        }
+    };
+    loop {
+        let client = match listener.accept().await {
+            Ok((client, _)) => client,
+            Err(e) => {
+                log::warn!("Network policy proxy of project {} failed to accept a connection: {}", project_id, e);
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        tokio::spawn(async move {
+            if let Err(e) = handle(client, project_id).await {
+                log::debug!("Proxy connection of project {} ended: {}", project_id, e);
+            }
+        });
+    }
+}
+
+/// Where a proxied request goes
+#[derive(Debug, PartialEq)]
+struct Target {
+    host: String,
+    port: u16,
+    /// A CONNECT tunnel, rather than a plain HTTP request
+    tunnel: bool,
+}
+
+/// The target of a request head's first line
+fn parse_target(head: &str) -> Option<Target> {
+    let mut parts = head.lines().next()?.split_whitespace();
+    let (method, uri) = (parts.next()?, parts.next()?);
+    let (authority, tunnel, default_port) = if method.eq_ignore_ascii_case("CONNECT") {
+        (uri, true, 443)
+    } else {
+        let rest = uri.strip_prefix("http://")?;
+        (rest.split('/').next()?, false, 80)
+    };
+    let authority = authority.rsplit('@').next()?;
+    let (host, port) = match authority.strip_prefix('[') {
+        // [::1]:443
+        Some(bracketed) => {
+            let (host, rest) = bracketed.split_once(']')?;
+            (host, rest.strip_prefix(':'))
+        }
+        None => match authority.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (authority, None),
+        },
+    };
+    let port = match port {
+        Some(port) => port.parse().ok()?,
+        None => default_port,
+    };
+    (!host.is_empty()).then(|| Target {
+        host: host.to_string(),
+        port,
+        tunnel,
+    })
+}
+
+/// A plain HTTP request head, asking for the connection to close after
+/// it so the next request is checked too
+fn closing_head(head: &str) -> String {
+    let mut lines: Vec<&str> = head
+        .trim_end_matches("\r\n")
+        .split("\r\n")
+        .filter(|line| {
+            let name = line.split(':').next().unwrap_or("").trim();
+            !name.eq_ignore_ascii_case("connection") && !name.eq_ignore_ascii_case("proxy-connection")
+        })
+        .collect();
+    lines.push("Connection: close");
+    format!("{}\r\n\r\n", lines.join("\r\n"))
+}
+
+async fn handle(mut client: TcpStream, project_id: Uuid) -> io::Result<()> {
+    let mut head = Vec::new();
+    let mut buffer = [0u8; 2048];
+    let end = loop {
+        let read = client.read(&mut buffer).await?;
+        if read == 0 {
+            return Ok(());
+        }
+        head.extend_from_slice(&buffer[..read]);
+        if let Some(position) = head.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+        if head.len() > MAX_HEAD_BYTES {
+            return client.write_all(BAD_REQUEST).await;
+        }
+    };
+    let request = String::from_utf8_lossy(&head[..end]).into_owned();
+    let Some(target) = parse_target(&request) else {
+        return client.write_all(BAD_REQUEST).await;
+    };
+
+    let policy = NETWORK_POLICIES.get(&project_id);
+    if !policy.allows(&target.host) {
+        AUDIT_LOG.record(
+            AuditEntry::new(
+                AuditAction::EgressBlocked,
+                format!("Blocked an agent of project {} from connecting to {}:{}", project_id, target.host, target.port),
+            )
+            .project(project_id)
+            .details(serde_json::json!({ "host": target.host, "port": target.port, "mode": policy.mode })),
+        );
+        return client.write_all(FORBIDDEN).await;
+    }
+
+    let Ok(mut upstream) = TcpStream::connect((target.host.as_str(), target.port)).await else {
+        return client.write_all(BAD_GATEWAY).await;
+    };
+    if target.tunnel {
+        client.write_all(ESTABLISHED).await?;
+    } else {
+        upstream.write_all(closing_head(&request).as_bytes()).await?;
+    }
+    upstream.write_all(&head[end..]).await?;
+    tokio::io::copy_bidirectional(&mut client, &mut upstream).await.map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network_policy() {
+        let policy = NetworkPolicy {
+            mode: EgressMode::Allowlist,
+            allowed_domains: vec!["GitHub.com.".to_string(), "*.npmjs.org".to_string()],
+        }
+        .normalized()
+        .unwrap();
+        assert_eq!(policy.allowed_domains, ["github.com", "*.npmjs.org"]);
+        assert!(policy.allows("github.com") && policy.allows("api.github.com"));
+        assert!(policy.allows("registry.npmjs.org") && !policy.allows("npmjs.org"));
+        assert!(!policy.allows("notgithub.com") && !policy.allows("example.com"));
+        assert!(policy.allows("api.anthropic.com") && policy.allows("127.0.0.1") && policy.allows("localhost"));
+
+        let deny_all = NetworkPolicy {
+            mode: EgressMode::DenyAll,
+            ..Default::default()
+        };
+        assert!(!deny_all.allows("github.com") && deny_all.allows("api.anthropic.com"));
+        assert!(NetworkPolicy::default().allows("example.com"));
+        let invalid = |domain: &str| NetworkPolicy {
+            mode: EgressMode::Allowlist,
+            allowed_domains: vec![domain.to_string()],
+        };
+        assert!(invalid("https://github.com").normalized().is_err());
+        assert!(invalid(" ").normalized().is_err());
+
+        assert_eq!(
+            parse_target("CONNECT api.github.com:443 HTTP/1.1\r\n"),
+            Some(Target { host: "api.github.com".to_string(), port: 443, tunnel: true })
+        );
+        assert_eq!(
+            parse_target("GET http://[::1]:8080/x HTTP/1.1\r\n"),
+            Some(Target { host: "::1".to_string(), port: 8080, tunnel: false })
+        );
+        assert_eq!(parse_target("GET http://example.com/ HTTP/1.1\r\n").map(|t| t.port), Some(80));
+        assert_eq!(parse_target("GET /relative HTTP/1.1\r\n"), None);
+        assert_eq!(
+            closing_head("GET http://a.test/ HTTP/1.1\r\nHost: a.test\r\nProxy-Connection: keep-alive\r\n\r\n"),
+            "GET http://a.test/ HTTP/1.1\r\nHost: a.test\r\nConnection: close\r\n\r\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_proxy_enforces_policy() {
+        let project_id = Uuid::new_v4();
+        NETWORK_POLICIES
+            .set(project_id, NetworkPolicy { mode: EgressMode::DenyAll, ..Default::default() })
+            .unwrap();
+        let env = NETWORK_POLICIES.proxy_env(project_id).unwrap();
+        let proxy = env.iter().find(|(name, _)| name == "HTTPS_PROXY").unwrap().1.clone();
+        let proxy = proxy.trim_start_matches("http://").to_string();
+        assert!(NETWORK_POLICIES.proxy_env(Uuid::new_v4()).unwrap().is_empty());
+
+        let request = |target: String| {
+            let proxy = proxy.clone();
+            async move {
+                let mut stream = TcpStream::connect(proxy).await.unwrap();
+                let head = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n\r\n", target, target);
+                stream.write_all(head.as_bytes()).await.unwrap();
+                let mut response = vec![0u8; 256];
+                let read = stream.read(&mut response).await.unwrap();
+                (stream, String::from_utf8_lossy(&response[..read]).into_owned())
+            }
+        };
+
+        let (_, response) = request("internal.example.com:443".to_string()).await;
+        assert!(response.starts_with("HTTP/1.1 403"));
+        let blocked = AUDIT_LOG.list(Some(&project_id), None);
+        assert_eq!(blocked[0].action, AuditAction::EgressBlocked);
+
+        // Loopback stays reachable, tunnelled both ways
+        let upstream = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = upstream.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut socket, _) = upstream.accept().await.unwrap();
+            let mut ping = [0u8; 4];
+            socket.read_exact(&mut ping).await.unwrap();
+            socket.write_all(b"pong").await.unwrap();
+        });
+        let (mut stream, response) = request(format!("127.0.0.1:{}", port)).await;
+        assert!(response.starts_with("HTTP/1.1 200"));
+        stream.write_all(b"ping").await.unwrap();
+        let mut pong = [0u8; 4];
+        stream.read_exact(&mut pong).await.unwrap();
+        assert_eq!(&pong, b"pong");
+    }
+}
@@ -0,0 +1,314 @@
+//! Stopping a process together with everything it started.
+//!
+//! Agents and test commands start test runners, dev servers and builds of
+//! their own. Killing only the top process leaves those running, holding
+//! ports and files.
+//! Provides:
+//! - `isolate`: start a command as the leader of its own process group
+//!   (Unix) or process group and tree (Windows). PTY agents already lead
+//!   one, as the PTY starts them in a new session.
+//! - `track`: put a started process in a Job Object (Windows), so its
+//!   whole tree can be ended at once; a no-op elsewhere
+//! - `signal`: send a signal to the whole group, falling back to the
+//!   process alone when it leads none
+//! - `GroupGuard`: kills whatever is left of a group when dropped, so a
+//!   timed out or cancelled command takes its children with it
+//! - `output_within`: run a command to completion under a timeout
+//!
+//! Windows has no process groups to signal. A tracked process is killed
+//! with its job, and the job is created with
+//! `JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE`, so releasing it ends whatever is
+//! left too. Untracked processes fall back to `taskkill /T`. Pausing is
+//! not supported.
+
+use std::io;
+use std::process::{Output, Stdio};
+use std::time::Duration;
+
+/// Puts a console process in a new process group on Windows
+#[cfg(windows)]
+const CREATE_NEW_PROCESS_GROUP: u32 = 0x0000_0200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Signal {
+    /// Ask to shut down (SIGTERM, or `taskkill` without `/F`)
+    Terminate,
+    /// Force (SIGKILL, or ending the process's Job Object)
+    Kill,
+    /// Pause (SIGSTOP); Unix only
+    Stop,
+    /// Continue after a pause (SIGCONT); Unix only
+    Continue,
+}
+
+impl Signal {
+    #[cfg(unix)]
+    fn flag(self) -> &'static str {
+        match self {
+            Signal::Terminate => "-TERM",
+            Signal::Kill => "-KILL",
+            Signal::Stop => "-STOP",
+            Signal::Continue => "-CONT",
+        }
+    }
+}
+
+/// Make `cmd` start its own process group, so [`signal`] reaches the
+/// processes it starts
+pub fn isolate(cmd: &mut std::process::Command) -> &mut std::process::Command {
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::CommandExt;
+        cmd.process_group(0);
+    }
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+    }
+    cmd
+}
+
+/// [`isolate`] for a tokio command
+pub fn isolate_async(cmd: &mut tokio::process::Command) -> &mut tokio::process::Command {
+    #[cfg(unix)]
+    cmd.process_group(0);
+    #[cfg(windows)]
+    cmd.creation_flags(CREATE_NEW_PROCESS_GROUP);
+    cmd
+}
+
+/// Job Objects of tracked processes, by process id. Dropping one closes
+/// its handle, which ends every process still in the job.
+#[cfg(windows)]
+mod jobs {
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use windows_sys::Win32::Foundation::{CloseHandle, HANDLE};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation, SetInformationJobObject,
+        TerminateJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION, JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+    use windows_sys::Win32::System::Threading::{OpenProcess, PROCESS_SET_QUOTA, PROCESS_TERMINATE};
+
+    pub struct Job(HANDLE);
+
+    // A job handle may be used and closed from any thread
+    unsafe impl Send for Job {}
+
+    impl Job {
+        /// A kill-on-close job holding `pid`, or `None` if either fails
+        pub fn assign(pid: u32) -> Option<Self> {
+            unsafe {
+                let job = Job(CreateJobObjectW(std::ptr::null(), std::ptr::null()));
+                if job.0.is_null() {
+                    return None;
+                }
+                let mut info = JOBOBJECT_EXTENDED_LIMIT_INFORMATION::default();
+                info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+                let set = SetInformationJobObject(
+                    job.0,
+                    JobObjectExtendedLimitInformation,
+                    &info as *const _ as *const core::ffi::c_void,
+                    std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+                );
+                if set == 0 {
+                    return None;
+                }
+                let process = OpenProcess(PROCESS_SET_QUOTA | PROCESS_TERMINATE, 0, pid);
+                if process.is_null() {
+                    return None;
+                }
+                let assigned = AssignProcessToJobObject(job.0, process);
+                CloseHandle(process);
+                (assigned != 0).then_some(job)
+            }
+        }
+
+        pub fn terminate(&self) -> bool {
+            unsafe { TerminateJobObject(self.0, 1) != 0 }
+        }
+    }
+
+    impl Drop for Job {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    lazy_static::lazy_static! {
+        pub static ref JOBS: Mutex<HashMap<u32, Job>> = Mutex::new(HashMap::new());
+    }
+}
+
+/// Put the started process `pid` in a Job Object, so [`signal`] ends the
+/// processes it starts with it. Returns whether it was tracked; always
+/// false outside Windows, where [`isolate`] is enough.
+#[cfg(windows)]
+pub fn track(pid: u32) -> bool {
+    match jobs::Job::assign(pid) {
+        Some(job) => {
+            jobs::JOBS.lock().unwrap_or_else(|e| e.into_inner()).insert(pid, job);
+            true
+        }
+        None => {
+            log::warn!("Could not put process {} in a job; its children may outlive it", pid);
+            false
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn track(_pid: u32) -> bool {
+    false
+}
+
+/// Stop tracking `pid`, ending whatever is left of its job
+#[cfg(windows)]
+pub fn release(pid: u32) {
+    jobs::JOBS.lock().unwrap_or_else(|e| e.into_inner()).remove(&pid);
+}
+
+#[cfg(not(windows))]
+pub fn release(_pid: u32) {}
+
+/// Send `signal` to the process group `pid` leads, or to `pid` alone if
+/// it leads none. Returns whether anything received it.
+#[cfg(unix)]
+pub fn signal(pid: u32, signal: Signal) -> bool {
+    let send = |target: &str| {
+        std::process::Command::new("kill")
+            .args([signal.flag(), "--", target])
+            .output()
+            .is_ok_and(|output| output.status.success())
+    };
+    send(&format!("-{}", pid)) || send(&pid.to_string())
+}
+
+/// End the process tree under `pid`: killing ends its job if it is
+/// [`track`]ed, and asking to terminate uses `taskkill /T`. Stopping and
+/// continuing are not supported, and return false.
+#[cfg(windows)]
+pub fn signal(pid: u32, signal: Signal) -> bool {
+    let mut cmd = std::process::Command::new("taskkill");
+    cmd.args(["/T", "/PID", &pid.to_string()]);
+    match signal {
+        Signal::Terminate => {}
+        Signal::Kill => {
+            if let Some(job) = jobs::JOBS.lock().unwrap_or_else(|e| e.into_inner()).remove(&pid) {
+                return job.terminate();
+            }
+            cmd.arg("/F");
+        }
+        Signal::Stop | Signal::Continue => return false,
+    }
+    cmd.output().is_ok_and(|output| output.status.success())
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn signal(_pid: u32, _signal: Signal) -> bool {
+    false
+}
+
+/// Whether any process of the group `pid` leads is left. A leader that
+/// exited but was not yet waited for still counts.
+#[cfg(unix)]
+pub fn is_alive(pid: u32) -> bool {
+    std::process::Command::new("kill")
+        .args(["-0", "--", &format!("-{}", pid)])
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[cfg(windows)]
+pub fn is_alive(pid: u32) -> bool {
+    std::process::Command::new("tasklist")
+        .args(["/NH", "/FI", &format!("PID eq {}", pid)])
+        .output()
+        .is_ok_and(|output| {
+            String::from_utf8_lossy(&output.stdout)
+                .split_whitespace()
+                .any(|word| word == pid.to_string())
+        })
+}
+
+#[cfg(not(any(unix, windows)))]
+pub fn is_alive(_pid: u32) -> bool {
+    false
+}
+
+/// Kills what is left of a process group when dropped
+pub struct GroupGuard {
+    pid: Option<u32>,
+}
+
+impl GroupGuard {
+    pub fn new(pid: Option<u32>) -> Self {
+        Self { pid }
+    }
+}
+
+impl Drop for GroupGuard {
+    fn drop(&mut self) {
+        let Some(pid) = self.pid else { return };
+        if is_alive(pid) {
+            log::debug!("Killing what is left of process group {}", pid);
+            signal(pid, Signal::Kill);
+        }
+        release(pid);
+    }
+}
+
+/// Run an [`isolate`]d `cmd` and collect its output like
+/// `Command::output`, or `None` if it takes longer than `timeout`. Either
+/// way, and if the caller stops waiting, nothing it started is left
+/// running.
+pub async fn output_within(cmd: &mut tokio::process::Command, timeout: Duration) -> Option<io::Result<Output>> {
+    let child = match isolate_async(cmd)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => return Some(Err(e)),
+    };
+    if let Some(pid) = child.id() {
+        track(pid);
+    }
+    let _guard = GroupGuard::new(child.id());
+    tokio::time::timeout(timeout, child.wait_with_output()).await.ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_output_within_kills_children() {
+        let pid_file = std::env::temp_dir().join(format!("nexus-group-{}", uuid::Uuid::new_v4()));
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg(format!("sleep 30 & echo $! > {}; wait", pid_file.display()));
+
+        assert!(output_within(&mut cmd, Duration::from_millis(500)).await.is_none());
+        let grandchild: u32 = std::fs::read_to_string(&pid_file).unwrap().trim().parse().unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+        // Killed processes may linger as zombies until reaped
+        std::thread::sleep(Duration::from_millis(100));
+        let state = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", &grandchild.to_string()])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&state.stdout);
+        assert!(state.trim().is_empty() || state.starts_with('Z'), "sleep {} outlived its shell", grandchild);
+
+        let mut cmd = tokio::process::Command::new("sh");
+        cmd.arg("-c").arg("echo done");
+        let output = output_within(&mut cmd, Duration::from_secs(5)).await.unwrap().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "done");
+    }
+}
@@ -5,10 +5,16 @@ use tauri::{AppHandle, Emitter, Manager};
 use tokio::sync::mpsc;
 use uuid::Uuid;
 
+use crate::settings::secrets;
+use crate::workflow::{activity, locale};
+
+use super::archive::{SessionRecord, AGENT_ARCHIVE};
+use super::egress::NETWORK_POLICIES;
 use super::registry::{AgentCompletion, AGENT_REGISTRY};
-use super::spawner::{start_pty_reader, PtyHandle};
+use super::spawner::{start_pty_reader, PtyHandle, Session};
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
 pub enum AgentStatus {
     Starting,
     Running,
@@ -18,6 +24,13 @@ pub enum AgentStatus {
     Killed,
 }
 
+impl AgentStatus {
+    /// Whether the agent has finished, one way or another
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Killed)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentConfig {
     pub name: String,
@@ -26,9 +39,37 @@ pub struct AgentConfig {
     pub project_id: Option<Uuid>,
     pub system_prompt: Option<String>,
     pub assigned_task: Option<String>,
+    /// Model override passed to the CLI (None = CLI default)
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Environment variables of the execution the agent works for. Not
+    /// saved with the session, as values may be secret.
+    #[serde(skip)]
+    pub env: Vec<(String, String)>,
+    /// The execution the agent works for; none for standalone agents
+    #[serde(default)]
+    pub owner: Option<AgentOwner>,
+}
+
+/// The execution, and node of it, an agent works for
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AgentOwner {
+    pub execution_id: Uuid,
+    /// None for the execution's own agents, like its orchestrator
+    pub node_id: Option<String>,
+}
+
+impl AgentOwner {
+    pub fn node(execution_id: Uuid, node_id: &str) -> Self {
+        Self {
+            execution_id,
+            node_id: Some(node_id.to_string()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject), graphql(complex))]
 pub struct AgentInfo {
     pub id: Uuid,
     pub name: String,
@@ -38,6 +79,10 @@ pub struct AgentInfo {
     pub assigned_task: Option<String>,
     pub progress: u8,
     pub pid: Option<u32>,
+    #[serde(default = "chrono::Utc::now")]
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    #[serde(default)]
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 impl AgentInfo {
@@ -51,10 +96,46 @@ impl AgentInfo {
             assigned_task: config.assigned_task.clone(),
             progress: 0,
             pid: None,
+            started_at: chrono::Utc::now(),
+            finished_at: None,
         }
     }
 }
 
+/// Sent to a resumed session, which already holds the agent's task
+const RESUME_PROMPT: &str =
+    "The session was interrupted by an application restart. Continue the task from where you left off.";
+
+/// Stop what's left of a recorded session's process. The pid is only
+/// trusted if its command line still names the session, since pids get
+/// reused after a restart.
+#[cfg(unix)]
+fn stop_orphaned_session(record: &SessionRecord) {
+    let pid = record.pid.to_string();
+    let running_session = std::process::Command::new("ps")
+        .args(["-o", "args=", "-p", &pid])
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).contains(&record.session_id));
+    if running_session {
+        log::info!("Stopping orphaned session process {} of agent {}", pid, record.info.id);
+        // The session leads its process group, so what it started goes too
+        super::group::signal(record.pid, super::group::Signal::Terminate);
+    }
+}
+
+#[cfg(not(unix))]
+fn stop_orphaned_session(_record: &SessionRecord) {}
+
+/// The execution a session's agent worked for, if it is gone or over.
+/// Resumed on its own, the agent would run as a standalone one with
+/// nothing to take its output.
+fn ended_owner(record: &SessionRecord) -> Option<&AgentOwner> {
+    record.config.owner.as_ref().filter(|owner| {
+        crate::commands::workflow::find_execution(&owner.execution_id)
+            .map_or(true, |(state, _)| state.completed_at.read().is_some())
+    })
+}
+
 /// Holds a PTY writer handle for sending input to an agent
 pub struct AgentPtyWriter(pub Arc<Mutex<Box<dyn Write + Send>>>);
 
@@ -68,8 +149,75 @@ impl AgentManager {
     }
 
     pub fn spawn_agent(&self, config: AgentConfig) -> Result<AgentInfo, String> {
-        let id = Uuid::new_v4();
+        self.launch(Uuid::new_v4(), config, None)
+    }
+
+    /// Continue an agent interrupted by a restart in its Claude Code session,
+    /// under the same agent ID. The old process can't be handed back its
+    /// terminal, so if it is somehow still running it is stopped first.
+    /// Agents of an execution that is gone or over aren't continued.
+    pub fn reattach_agent(&self, record: &SessionRecord) -> Result<AgentInfo, String> {
+        if let Some(owner) = ended_owner(record) {
+            return Err(format!(
+                "Agent worked for execution {}, which is no longer running; resume the execution instead",
+                owner.execution_id
+            ));
+        }
+        stop_orphaned_session(record);
+        self.launch(record.info.id, record.config.clone(), Some(record))
+    }
+
+    /// Start every agent left running by the last app session. Agents that
+    /// can't be reattached are added as failed, keeping their session record
+    /// so `reattach_agent` can be retried. Agents of executions that are gone
+    /// are stopped and added as killed. Returns how many were reattached.
+    pub fn reattach_all(&self, state: &crate::state::AppState) -> usize {
+        let mut reattached = 0;
+        for record in AGENT_ARCHIVE.sessions() {
+            let agent_id = record.info.id;
+            if state.agents.contains_key(&agent_id) {
+                continue;
+            }
+            if let Some(owner) = ended_owner(&record) {
+                log::info!(
+                    "Not reattaching agent {}: execution {} it worked for is no longer running",
+                    agent_id,
+                    owner.execution_id
+                );
+                stop_orphaned_session(&record);
+                AGENT_ARCHIVE.remove_session(&agent_id);
+                let mut info = record.info;
+                info.status = AgentStatus::Killed;
+                info.finished_at = Some(chrono::Utc::now());
+                state.agents.insert(agent_id, info);
+                continue;
+            }
+            match self.reattach_agent(&record) {
+                Ok(info) => {
+                    state.agents.insert(agent_id, info);
+                    reattached += 1;
+                }
+                Err(e) => {
+                    log::warn!("Could not reattach agent {}: {}", agent_id, e);
+                    let mut info = record.info;
+                    info.status = AgentStatus::Failed;
+                    info.finished_at = Some(chrono::Utc::now());
+                    state.agents.insert(agent_id, info);
+                }
+            }
+        }
+        reattached
+    }
+
+    fn launch(&self, id: Uuid, config: AgentConfig, resume: Option<&SessionRecord>) -> Result<AgentInfo, String> {
+        if let Some(lock) = AGENT_REGISTRY.spawn_lock() {
+            return Err(format!("Agent spawning is disabled after an emergency stop: {}", lock.reason));
+        }
+
         let mut info = AgentInfo::new(id, &config);
+        if let Some(record) = resume {
+            info.started_at = record.info.started_at;
+        }
 
         // Verify working directory exists
         let working_dir = std::path::Path::new(&config.working_directory);
@@ -79,20 +227,33 @@ impl AgentManager {
             log::info!("Created working directory: {:?}", working_dir);
         }
 
-        // Build initial prompt for Claude Code
-        let initial_prompt = match (&config.system_prompt, &config.assigned_task) {
-            (Some(sys), Some(task)) => Some(format!("{}\n\nTask: {}", sys, task)),
-            (Some(sys), None) => Some(sys.clone()),
-            (None, Some(task)) => Some(format!("Task: {}", task)),
-            (None, None) => None,
+        // Build initial prompt for Claude Code, in the project's language; a
+        // resumed session already has its task
+        let system_prompt = locale::project_system_prompt(config.project_id, config.system_prompt.clone());
+        let initial_prompt = match (resume, &system_prompt, &config.assigned_task) {
+            (Some(_), _, _) => Some(RESUME_PROMPT.to_string()),
+            (None, Some(sys), Some(task)) => Some(format!("{}\n\nTask: {}", sys, task)),
+            (None, Some(sys), None) => Some(sys.clone()),
+            (None, None, Some(task)) => Some(format!("Task: {}", task)),
+            (None, None, None) => None,
         };
+        // The only place secret references are resolved, so their values
+        // go to the agent process and nowhere else
+        let initial_prompt = initial_prompt.map(|prompt| secrets::resolve(&prompt)).transpose()?;
 
         // Emit starting event
+        let starting = match resume {
+            Some(record) => format!(
+                "🔄 Reattaching Claude Code agent '{}' ({}) to session {}...\n",
+                config.name, config.role, record.session_id
+            ),
+            None => format!("🚀 Starting Claude Code agent '{}' ({})...\n", config.name, config.role),
+        };
         let _ = self.app.emit(
             "agent-output",
             serde_json::json!({
                 "agentId": id.to_string(),
-                "output": format!("🚀 Starting Claude Code agent '{}' ({})...\n", config.name, config.role),
+                "output": starting,
                 "stream": "system",
                 "timestamp": chrono::Utc::now().timestamp_millis(),
             }),
@@ -130,14 +291,44 @@ impl AgentManager {
             }),
         );
 
-        // Spawn Claude Code in a PTY
-        match PtyHandle::spawn_claude_pty(&config.working_directory, initial_prompt.as_deref()) {
+        // Spawn Claude Code in a PTY, logging its commands and edits for the activity feed
+        let settings = activity::hook_settings(&id);
+        let session_id = resume.map_or_else(|| id.to_string(), |record| record.session_id.clone());
+        let session = match resume {
+            Some(_) => Session::Resume(&session_id),
+            None => Session::New(&session_id),
+        };
+        // A restricted project's agents only get out through its proxy,
+        // whatever the execution's environment says
+        let mut env = config.env.clone();
+        if let Some(project_id) = config.project_id {
+            env.extend(NETWORK_POLICIES.proxy_env(project_id)?);
+        }
+        match PtyHandle::spawn_claude_pty(
+            &config.working_directory,
+            initial_prompt.as_deref(),
+            config.model.as_deref(),
+            settings.as_deref(),
+            session,
+            &env,
+        ) {
             Ok(pty_handle) => {
                 let pid = pty_handle.id();
                 info.pid = Some(pid);
                 info.status = AgentStatus::Running;
                 info.progress = 5;
 
+                // Enough to pick the session back up if the app goes away first
+                let record = SessionRecord {
+                    info: info.clone(),
+                    config: config.clone(),
+                    session_id,
+                    pid,
+                };
+                if let Err(e) = AGENT_ARCHIVE.save_session(&record) {
+                    log::warn!("Failed to record session of agent {}: {}", id, e);
+                }
+
                 // Get reader for output streaming
                 let reader = pty_handle.take_reader().map_err(|e| e.to_string())?;
 
@@ -199,13 +390,28 @@ impl AgentManager {
                         }),
                     );
 
-                    // Update agent status
-                    if let Some(app_state) =
+                    // Update agent status, unless the app is shutting down
+                    // and the agent will be reattached on the next launch.
+                    // An agent killed or failed before its stream closed
+                    // keeps that status.
+                    let mut status = AgentStatus::Completed;
+                    if AGENT_REGISTRY.is_detached(&agent_id) {
+                        log::info!("Agent {} detached for shutdown", agent_id);
+                    } else if let Some(app_state) =
                         app.try_state::<std::sync::Arc<crate::state::AppState>>()
                     {
                         if let Some(mut agent) = app_state.agents.get_mut(&agent_id) {
-                            agent.status = AgentStatus::Completed;
+                            if matches!(agent.status, AgentStatus::Killed | AgentStatus::Failed) {
+                                status = agent.status;
+                            }
+                            agent.status = status;
                             agent.progress = 100;
+                            agent.finished_at.get_or_insert_with(chrono::Utc::now);
+
+                            // Keep the finished agent across restarts
+                            if let Err(e) = AGENT_ARCHIVE.save(&agent, &output) {
+                                log::warn!("Failed to archive agent {}: {}", agent_id, e);
+                            }
                         }
                     }
 
@@ -221,17 +427,23 @@ impl AgentManager {
                         "agent-status",
                         serde_json::json!({
                             "agentId": agent_id.to_string(),
-                            "status": "Completed",
+                            "status": format!("{:?}", status),
                         }),
                     );
 
                     // Notify completion
+                    let success = status == AgentStatus::Completed;
+                    let error = match status {
+                        AgentStatus::Killed => Some("Agent was killed".to_string()),
+                        AgentStatus::Failed => Some("Agent failed".to_string()),
+                        _ => None,
+                    };
                     AGENT_REGISTRY.notify_completion(
                         &agent_id,
                         AgentCompletion {
-                            success: true,
+                            success,
                             output,
-                            error: None,
+                            error,
                         },
                     );
                 });
@@ -292,3 +504,34 @@ impl AgentManager {
         AGENT_REGISTRY.subscribe_completion(agent_id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_agents_of_ended_executions_are_not_resumed() {
+        let config = AgentConfig {
+            name: "workflow-build".to_string(),
+            role: "implementer".to_string(),
+            working_directory: "/tmp".to_string(),
+            project_id: None,
+            system_prompt: None,
+            assigned_task: None,
+            model: None,
+            env: Vec::new(),
+            owner: Some(AgentOwner::node(Uuid::new_v4(), "build")),
+        };
+        let mut record = SessionRecord {
+            info: AgentInfo::new(Uuid::new_v4(), &config),
+            config,
+            session_id: "session".to_string(),
+            pid: 4242,
+        };
+        // No executor holds the execution, as after a restart
+        assert!(ended_owner(&record).is_some());
+
+        record.config.owner = None;
+        assert!(ended_owner(&record).is_none());
+    }
+}
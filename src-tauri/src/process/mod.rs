@@ -1,4 +1,8 @@
+pub mod archive;
+pub mod egress;
+pub mod group;
 pub mod manager;
+pub mod query;
 pub mod registry;
 pub mod spawner;
 pub mod stream;
@@ -0,0 +1,178 @@
+//! Finding agents among many.
+//!
+//! Workflows spawn agents by the dozen, so the agent list needs more than a
+//! full dump. Provides:
+//! - `AgentFilter` matching agents by status, role, project, execution and age
+//! - Sort keys for the agent list
+//! - CPU and memory sampling of agent processes
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::manager::AgentInfo;
+
+/// Which agents to act on. Empty lists and unset fields match everything.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::InputObject))]
+pub struct AgentFilter {
+    /// Status names ("Running", "completed", ...), case-insensitive
+    #[serde(default)]
+    #[cfg_attr(feature = "graphql", graphql(default))]
+    pub statuses: Vec<String>,
+    #[serde(default)]
+    #[cfg_attr(feature = "graphql", graphql(default))]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub project_id: Option<Uuid>,
+    /// Agents spawned by this workflow execution
+    #[serde(default)]
+    pub execution_id: Option<Uuid>,
+    /// Only agents started at least this many seconds ago
+    #[serde(default)]
+    pub min_age_secs: Option<u64>,
+    /// Only agents started at most this many seconds ago
+    #[serde(default)]
+    pub max_age_secs: Option<u64>,
+}
+
+impl AgentFilter {
+    /// Whether nothing is set, so the filter matches every agent
+    pub fn is_empty(&self) -> bool {
+        self.statuses.is_empty()
+            && self.roles.is_empty()
+            && self.project_id.is_none()
+            && self.execution_id.is_none()
+            && self.min_age_secs.is_none()
+            && self.max_age_secs.is_none()
+    }
+
+    /// Whether `agent`, spawned by `execution_id` if any, matches at `now`
+    pub fn matches(&self, agent: &AgentInfo, execution_id: Option<Uuid>, now: DateTime<Utc>) -> bool {
+        let status = format!("{:?}", agent.status);
+        let age = (now - agent.started_at).num_seconds().max(0) as u64;
+
+        (self.statuses.is_empty() || self.statuses.iter().any(|s| s.eq_ignore_ascii_case(&status)))
+            && (self.roles.is_empty() || self.roles.iter().any(|r| r.eq_ignore_ascii_case(&agent.role)))
+            && self.project_id.map_or(true, |id| agent.project_id == Some(id))
+            && self.execution_id.map_or(true, |id| execution_id == Some(id))
+            && self.min_age_secs.map_or(true, |min| age >= min)
+            && self.max_age_secs.map_or(true, |max| age <= max)
+    }
+}
+
+/// What to order the agent list by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgentSortField {
+    Name,
+    Role,
+    Status,
+    #[default]
+    StartedAt,
+    Runtime,
+    LastOutput,
+}
+
+/// CPU and memory use of an agent process
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub cpu_percent: f32,
+    pub memory_kb: u64,
+}
+
+/// Sample CPU and memory for processes with a single `ps` call. Processes
+/// that have exited are missing from the result.
+#[cfg(unix)]
+pub fn resource_usage(pids: &[u32]) -> HashMap<u32, ResourceUsage> {
+    if pids.is_empty() {
+        return HashMap::new();
+    }
+
+    let list = pids.iter().map(u32::to_string).collect::<Vec<_>>().join(",");
+    let output = match std::process::Command::new("ps")
+        .args(["-o", "pid=,%cpu=,rss=", "-p", &list])
+        .output()
+    {
+        Ok(output) => output,
+        Err(e) => {
+            log::warn!("Failed to sample agent resource usage: {}", e);
+            return HashMap::new();
+        }
+    };
+
+    parse_ps_output(&String::from_utf8_lossy(&output.stdout))
+}
+
+#[cfg(not(unix))]
+pub fn resource_usage(_pids: &[u32]) -> HashMap<u32, ResourceUsage> {
+    HashMap::new()
+}
+
+/// Parse `ps -o pid=,%cpu=,rss=` lines
+fn parse_ps_output(output: &str) -> HashMap<u32, ResourceUsage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let pid = fields.next()?.parse().ok()?;
+            let cpu_percent = fields.next()?.parse().ok()?;
+            let memory_kb = fields.next()?.parse().ok()?;
+            Some((pid, ResourceUsage { cpu_percent, memory_kb }))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::process::manager::{AgentConfig, AgentStatus};
+
+    #[test]
+    fn test_filter_matches() {
+        let project = Uuid::new_v4();
+        let execution = Uuid::new_v4();
+        let config = AgentConfig {
+            name: "workflow-1-implement".to_string(),
+            role: "implementer".to_string(),
+            working_directory: "/tmp".to_string(),
+            project_id: Some(project),
+            system_prompt: None,
+            assigned_task: None,
+            model: None,
+            env: Vec::new(),
+            owner: None,
+        };
+        let mut agent = AgentInfo::new(Uuid::new_v4(), &config);
+        agent.status = AgentStatus::Running;
+        let now = agent.started_at + chrono::Duration::seconds(600);
+
+        assert!(AgentFilter::default().is_empty());
+        assert!(AgentFilter::default().matches(&agent, None, now));
+
+        let filter = AgentFilter {
+            statuses: vec!["running".to_string(), "paused".to_string()],
+            roles: vec!["Implementer".to_string()],
+            project_id: Some(project),
+            execution_id: Some(execution),
+            min_age_secs: Some(300),
+            max_age_secs: Some(900),
+        };
+        assert!(!filter.is_empty());
+        assert!(filter.matches(&agent, Some(execution), now));
+        assert!(!filter.matches(&agent, None, now));
+        assert!(!filter.matches(&agent, Some(execution), agent.started_at));
+
+        agent.status = AgentStatus::Completed;
+        assert!(!filter.matches(&agent, Some(execution), now));
+    }
+
+    #[test]
+    fn test_parse_ps_output() {
+        let usage = parse_ps_output("  123  12.5 20480\n 456   0.0   512\ngarbage\n");
+        assert_eq!(usage.len(), 2);
+        assert_eq!(usage[&123], ResourceUsage { cpu_percent: 12.5, memory_kb: 20480 });
+        assert_eq!(usage[&456].memory_kb, 512);
+    }
+}
@@ -1,5 +1,7 @@
-use dashmap::DashMap;
+use chrono::{DateTime, Utc};
+use dashmap::{DashMap, DashSet};
 use parking_lot::RwLock;
+use serde::Serialize;
 use std::io::Write;
 use std::process::Child;
 use std::sync::Arc;
@@ -9,6 +11,8 @@ use uuid::Uuid;
 
 use std::sync::Mutex;
 
+use super::group::{self, Signal};
+
 /// PTY writer handle type
 pub type PtyWriter = Arc<Mutex<Box<dyn Write + Send>>>;
 
@@ -24,8 +28,17 @@ pub struct AgentRegistry {
     configs: DashMap<Uuid, super::manager::AgentConfig>,
     /// Agent start times for timeout tracking
     start_times: DashMap<Uuid, Instant>,
+    /// When each agent finished, so its runtime stops growing
+    end_times: DashMap<Uuid, Instant>,
+    /// When each agent last produced output
+    last_output: DashMap<Uuid, DateTime<Utc>>,
     /// PTY writers for sending input to PTY-based agents
     pty_writers: DashMap<Uuid, PtyWriter>,
+    /// Agents stopped by app shutdown, to be reattached on the next launch
+    /// rather than recorded as finished
+    detached: DashSet<Uuid>,
+    /// Set by an emergency stop; no agents spawn while it is held
+    spawn_lock: RwLock<Option<SpawnLock>>,
 }
 
 pub struct AgentProcess {
@@ -34,6 +47,14 @@ pub struct AgentProcess {
     pub stdin: Option<std::process::ChildStdin>,
 }
 
+/// Why spawning is disabled and the code that re-enables it
+#[derive(Debug, Clone, Serialize)]
+pub struct SpawnLock {
+    pub reason: String,
+    pub confirmation_code: String,
+    pub locked_at: DateTime<Utc>,
+}
+
 #[derive(Debug, Clone)]
 pub struct AgentCompletion {
     pub success: bool,
@@ -49,7 +70,11 @@ impl AgentRegistry {
             completion_channels: DashMap::new(),
             configs: DashMap::new(),
             start_times: DashMap::new(),
+            end_times: DashMap::new(),
+            last_output: DashMap::new(),
             pty_writers: DashMap::new(),
+            detached: DashSet::new(),
+            spawn_lock: RwLock::new(None),
         }
     }
 
@@ -161,7 +186,21 @@ impl AgentRegistry {
 
     /// Get agent runtime duration
     pub fn get_runtime(&self, agent_id: &Uuid) -> Option<Duration> {
-        self.start_times.get(agent_id).map(|start| start.elapsed())
+        let start = *self.start_times.get(agent_id)?;
+        Some(match self.end_times.get(agent_id) {
+            Some(end) => end.duration_since(start),
+            None => start.elapsed(),
+        })
+    }
+
+    /// How long ago an agent finished; `None` while it is still running
+    pub fn time_since_finish(&self, agent_id: &Uuid) -> Option<Duration> {
+        self.end_times.get(agent_id).map(|end| end.elapsed())
+    }
+
+    /// When an agent last produced output
+    pub fn last_output_at(&self, agent_id: &Uuid) -> Option<DateTime<Utc>> {
+        self.last_output.get(agent_id).map(|at| *at)
     }
 
     /// Get the output buffer for an agent (for appending output)
@@ -180,6 +219,7 @@ impl AgentRegistry {
     pub fn append_output(&self, agent_id: &Uuid, output: &str) {
         if let Some(buffer) = self.output_buffers.get(agent_id) {
             buffer.write().push_str(output);
+            self.last_output.insert(*agent_id, Utc::now());
         }
     }
 
@@ -189,68 +229,10 @@ impl AgentRegistry {
         self.kill_graceful(agent_id, Duration::from_secs(2))
     }
 
-    /// Kill with configurable grace period
+    /// Kill with configurable grace period. Returns whether the agent was
+    /// running.
     pub fn kill_graceful(&self, agent_id: &Uuid, grace_period: Duration) -> bool {
-        if let Some(mut entry) = self.processes.get_mut(agent_id) {
-            let pid = entry.pid;
-
-            // First try SIGTERM for graceful shutdown
-            #[cfg(unix)]
-            {
-                use std::process::Command;
-                log::info!("Sending SIGTERM to agent {} (pid {})", agent_id, pid);
-                let _ = Command::new("kill")
-                    .arg("-TERM")
-                    .arg(pid.to_string())
-                    .output();
-
-                // Wait for grace period
-                std::thread::sleep(grace_period);
-
-                // Check if still running
-                if let Some(ref mut child) = entry.child {
-                    match child.try_wait() {
-                        Ok(Some(_)) => {
-                            log::info!("Agent {} terminated gracefully", agent_id);
-                            return true;
-                        }
-                        Ok(None) => {
-                            // Still running, force kill
-                            log::info!("Agent {} didn't terminate, sending SIGKILL", agent_id);
-                            match child.kill() {
-                                Ok(_) => {
-                                    log::info!("Force killed agent {}", agent_id);
-                                    return true;
-                                }
-                                Err(e) => {
-                                    log::error!("Failed to kill agent {}: {}", agent_id, e);
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            log::error!("Error checking agent {} status: {}", agent_id, e);
-                        }
-                    }
-                }
-            }
-
-            #[cfg(not(unix))]
-            {
-                // On non-Unix, just kill directly
-                if let Some(ref mut child) = entry.child {
-                    match child.kill() {
-                        Ok(_) => {
-                            log::info!("Killed agent process {}", agent_id);
-                            return true;
-                        }
-                        Err(e) => {
-                            log::error!("Failed to kill agent {}: {}", agent_id, e);
-                        }
-                    }
-                }
-            }
-        }
-        false
+        !self.terminate(&[*agent_id], grace_period).is_empty()
     }
 
     /// Check if a process has exited
@@ -269,12 +251,17 @@ impl AgentRegistry {
 
     /// Remove an agent from the registry (full cleanup)
     pub fn remove(&self, agent_id: &Uuid) {
-        self.processes.remove(agent_id);
+        if let Some((_, process)) = self.processes.remove(agent_id) {
+            group::release(process.pid);
+        }
         self.output_buffers.remove(agent_id);
         self.completion_channels.remove(agent_id);
         self.configs.remove(agent_id);
         self.start_times.remove(agent_id);
+        self.end_times.remove(agent_id);
+        self.last_output.remove(agent_id);
         self.pty_writers.remove(agent_id);
+        self.detached.remove(agent_id);
     }
 
     /// Cleanup completed/failed agents older than the specified duration
@@ -314,13 +301,14 @@ impl AgentRegistry {
         let (tx, rx) = oneshot::channel();
         self.completion_channels
             .entry(agent_id)
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(tx);
         rx
     }
 
     /// Notify that an agent has completed
     pub fn notify_completion(&self, agent_id: &Uuid, completion: AgentCompletion) {
+        self.end_times.entry(*agent_id).or_insert_with(Instant::now);
         if let Some((_, channels)) = self.completion_channels.remove(agent_id) {
             for tx in channels {
                 let _ = tx.send(completion.clone());
@@ -338,12 +326,10 @@ impl AgentRegistry {
     pub fn pause(&self, agent_id: &Uuid) -> Result<(), String> {
         if let Some(entry) = self.processes.get(agent_id) {
             let pid = entry.pid;
-            use std::process::Command;
-            Command::new("kill")
-                .arg("-STOP")
-                .arg(pid.to_string())
-                .output()
-                .map_err(|e| format!("Failed to pause agent: {}", e))?;
+            // Along with the tests or servers it is running
+            if !group::signal(pid, Signal::Stop) {
+                return Err(format!("Failed to pause agent: pid {} not found", pid));
+            }
             log::info!("Paused agent {} (pid {})", agent_id, pid);
             Ok(())
         } else {
@@ -361,12 +347,9 @@ impl AgentRegistry {
     pub fn resume(&self, agent_id: &Uuid) -> Result<(), String> {
         if let Some(entry) = self.processes.get(agent_id) {
             let pid = entry.pid;
-            use std::process::Command;
-            Command::new("kill")
-                .arg("-CONT")
-                .arg(pid.to_string())
-                .output()
-                .map_err(|e| format!("Failed to resume agent: {}", e))?;
+            if !group::signal(pid, Signal::Continue) {
+                return Err(format!("Failed to resume agent: pid {} not found", pid));
+            }
             log::info!("Resumed agent {} (pid {})", agent_id, pid);
             Ok(())
         } else {
@@ -378,6 +361,121 @@ impl AgentRegistry {
     pub fn resume(&self, agent_id: &Uuid) -> Result<(), String> {
         Err("Resume not supported on this platform".to_string())
     }
+
+    /// Whether an agent's process is still running
+    pub fn is_running(&self, agent_id: &Uuid) -> bool {
+        let Some(mut entry) = self.processes.get_mut(agent_id) else {
+            return false;
+        };
+        if let Some(ref mut child) = entry.child {
+            return matches!(child.try_wait(), Ok(None));
+        }
+
+        // PTY agents are only tracked by pid
+        #[cfg(unix)]
+        {
+            std::process::Command::new("kill")
+                .arg("-0")
+                .arg(entry.pid.to_string())
+                .output()
+                .is_ok_and(|output| output.status.success())
+        }
+        #[cfg(not(unix))]
+        {
+            false
+        }
+    }
+
+    /// Stop every running agent, e.g. on app shutdown. Returns the agents
+    /// that were running.
+    pub fn terminate_all(&self, grace_period: Duration) -> Vec<Uuid> {
+        self.terminate(&self.list_agents(), grace_period)
+    }
+
+    /// Stop a set of agents and the processes they started. They all get
+    /// SIGTERM at once and share one grace period before what is left of
+    /// their process groups gets SIGKILL. Returns the agents that were
+    /// running.
+    pub fn terminate(&self, agent_ids: &[Uuid], grace_period: Duration) -> Vec<Uuid> {
+        let running: Vec<(Uuid, u32)> = agent_ids
+            .iter()
+            .filter(|id| self.is_running(id))
+            .filter_map(|id| Some((*id, self.get_pid(id)?)))
+            .collect();
+
+        // Windows can only end a process tree by force
+        #[cfg(unix)]
+        {
+            for (agent_id, pid) in &running {
+                log::info!("Sending SIGTERM to agent {} (pid {}) and its process group", agent_id, pid);
+                group::signal(*pid, Signal::Terminate);
+                // Paused agents only see the signal once continued
+                group::signal(*pid, Signal::Continue);
+            }
+
+            let deadline = Instant::now() + grace_period;
+            while Instant::now() < deadline && running.iter().any(|(id, _)| self.is_running(id)) {
+                std::thread::sleep(Duration::from_millis(100));
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = grace_period;
+
+        // Test runners and servers an agent started can outlive it
+        for (agent_id, pid) in &running {
+            if group::is_alive(*pid) {
+                log::info!("Sending SIGKILL to what is left of agent {} (pid {})", agent_id, pid);
+                group::signal(*pid, Signal::Kill);
+            }
+            if let Some(mut entry) = self.processes.get_mut(agent_id) {
+                if let Some(ref mut child) = entry.child {
+                    let _ = child.kill();
+                }
+            }
+        }
+
+        running.into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Refuse to spawn agents until [`Self::unlock_spawning`] is called with
+    /// the returned confirmation code. Locking again keeps the first code.
+    pub fn lock_spawning(&self, reason: &str) -> SpawnLock {
+        let mut lock = self.spawn_lock.write();
+        lock.get_or_insert_with(|| SpawnLock {
+            reason: reason.to_string(),
+            confirmation_code: Uuid::new_v4().simple().to_string()[..6].to_uppercase(),
+            locked_at: Utc::now(),
+        })
+        .clone()
+    }
+
+    /// Re-enable spawning if `confirmation` matches the lock's code
+    pub fn unlock_spawning(&self, confirmation: &str) -> Result<(), String> {
+        let mut lock = self.spawn_lock.write();
+        match lock.as_ref() {
+            None => Ok(()),
+            Some(held) if held.confirmation_code.eq_ignore_ascii_case(confirmation.trim()) => {
+                log::info!("Agent spawning re-enabled");
+                *lock = None;
+                Ok(())
+            }
+            Some(_) => Err("Confirmation code does not match".to_string()),
+        }
+    }
+
+    /// Mark an agent as detached before its process is stopped for shutdown
+    pub fn detach(&self, agent_id: Uuid) {
+        self.detached.insert(agent_id);
+    }
+
+    pub fn is_detached(&self, agent_id: &Uuid) -> bool {
+        self.detached.contains(agent_id)
+    }
+
+    /// The active spawn lock, if spawning is disabled
+    pub fn spawn_lock(&self) -> Option<SpawnLock> {
+        self.spawn_lock.read().clone()
+    }
 }
 
 impl Default for AgentRegistry {
@@ -390,3 +488,97 @@ impl Default for AgentRegistry {
 lazy_static::lazy_static! {
     pub static ref AGENT_REGISTRY: AgentRegistry = AgentRegistry::new();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_terminate_all() {
+        let registry = AgentRegistry::new();
+        let child = std::process::Command::new("sleep").arg("30").spawn().unwrap();
+        let pid = child.id();
+        let agent_id = Uuid::new_v4();
+        registry.register(agent_id, child, pid);
+
+        let stopped = registry.terminate_all(Duration::from_secs(2));
+        assert_eq!(stopped, vec![agent_id]);
+        assert!(!registry.is_running(&agent_id));
+
+        // Nothing left to stop
+        assert!(registry.terminate_all(Duration::from_secs(2)).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_kill_takes_started_processes() {
+        let registry = AgentRegistry::new();
+        let pid_file = std::env::temp_dir().join(format!("nexus-agent-{}", Uuid::new_v4()));
+        // Ignores SIGTERM, like a stuck agent, and starts a server of its own
+        let script = format!("trap '' TERM; sleep 30 & echo $! > {}; wait", pid_file.display());
+        let mut cmd = std::process::Command::new("sh");
+        cmd.arg("-c").arg(script);
+        let child = group::isolate(&mut cmd).spawn().unwrap();
+        let pid = child.id();
+        let agent_id = Uuid::new_v4();
+        registry.register(agent_id, child, pid);
+        while !pid_file.exists() {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        std::thread::sleep(Duration::from_millis(50));
+
+        assert!(registry.kill_graceful(&agent_id, Duration::from_millis(300)));
+        assert!(!registry.is_running(&agent_id));
+        let server = std::fs::read_to_string(&pid_file).unwrap();
+        let _ = std::fs::remove_file(&pid_file);
+        std::thread::sleep(Duration::from_millis(100));
+        let state = std::process::Command::new("ps")
+            .args(["-o", "stat=", "-p", server.trim()])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&state.stdout);
+        assert!(state.trim().is_empty() || state.starts_with('Z'), "server {} outlived the agent", server.trim());
+    }
+
+    #[test]
+    fn test_runtime_stops_at_completion() {
+        let registry = AgentRegistry::new();
+        let agent_id = Uuid::new_v4();
+        registry.register_pty_agent(agent_id, u32::MAX);
+        assert!(registry.time_since_finish(&agent_id).is_none());
+
+        registry.append_output(&agent_id, "working\n");
+        assert!(registry.last_output_at(&agent_id).is_some());
+
+        registry.notify_completion(
+            &agent_id,
+            AgentCompletion { success: true, output: String::new(), error: None },
+        );
+        let runtime = registry.get_runtime(&agent_id).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(registry.get_runtime(&agent_id).unwrap(), runtime);
+        assert!(registry.time_since_finish(&agent_id).is_some());
+
+        registry.remove(&agent_id);
+        assert!(registry.last_output_at(&agent_id).is_none());
+    }
+
+    #[test]
+    fn test_spawn_lock_requires_confirmation() {
+        let registry = AgentRegistry::new();
+        assert!(registry.spawn_lock().is_none());
+
+        let lock = registry.lock_spawning("agent deleted files");
+        assert_eq!(lock.confirmation_code.len(), 6);
+        // A second stop keeps the original reason and code
+        assert_eq!(registry.lock_spawning("again").confirmation_code, lock.confirmation_code);
+        assert_eq!(registry.spawn_lock().unwrap().reason, "agent deleted files");
+
+        assert!(registry.unlock_spawning("nope").is_err());
+        assert!(registry.spawn_lock().is_some());
+
+        registry.unlock_spawning(&lock.confirmation_code.to_lowercase()).unwrap();
+        assert!(registry.spawn_lock().is_none());
+    }
+}
@@ -5,6 +5,9 @@ use std::sync::{Arc, Mutex};
 use thiserror::Error;
 use tokio::sync::mpsc;
 
+use super::group::{self, Signal};
+use crate::settings::SETTINGS;
+
 #[derive(Error, Debug)]
 pub enum SpawnerError {
     #[error("Failed to spawn process: {0}")]
@@ -18,7 +21,7 @@ pub enum SpawnerError {
 }
 
 /// Find the claude CLI executable
-fn find_claude_path() -> Option<String> {
+pub fn find_claude_path() -> Option<String> {
     // First try PATH using 'which'
     if let Ok(output) = std::process::Command::new("which").arg("claude").output() {
         if output.status.success() {
@@ -59,6 +62,16 @@ fn find_claude_path() -> Option<String> {
     None
 }
 
+/// The Claude Code conversation an agent runs in. Sessions are saved by the
+/// CLI, so one can be resumed by a new process after the old one is gone.
+#[derive(Debug, Clone, Copy)]
+pub enum Session<'a> {
+    /// Start a session with this ID (a UUID)
+    New(&'a str),
+    /// Continue an earlier session
+    Resume(&'a str),
+}
+
 /// A handle to a Claude Code terminal session running in a PTY
 pub struct PtyHandle {
     pty_pair: PtyPair,
@@ -72,6 +85,10 @@ impl PtyHandle {
     pub fn spawn_claude_pty(
         working_dir: &str,
         initial_prompt: Option<&str>,
+        model: Option<&str>,
+        settings: Option<&str>,
+        session: Session,
+        env: &[(String, String)],
     ) -> Result<Self, SpawnerError> {
         let claude_path = find_claude_path().ok_or_else(|| {
             SpawnerError::ClaudeNotFound(
@@ -96,17 +113,51 @@ impl PtyHandle {
             .map_err(|e| SpawnerError::SpawnError(format!("Failed to create PTY: {}", e)))?;
 
         // Build command
-        let mut cmd = CommandBuilder::new(&claude_path);
+        let mut args: Vec<String> = Vec::new();
 
-        // Add initial prompt if provided - use print mode for streaming output
-        if let Some(prompt) = initial_prompt {
+        // With an initial prompt, use print mode for streaming output
+        if initial_prompt.is_some() {
             // Use -p (print mode) for streaming text output without TUI
-            cmd.arg("-p");
-            cmd.arg(prompt);
+            args.push("-p".to_string());
             // Skip permission prompts for autonomous operation
-            cmd.arg("--dangerously-skip-permissions");
+            args.push("--dangerously-skip-permissions".to_string());
+        }
+
+        if let Some(model) = model {
+            args.push("--model".to_string());
+            args.push(model.to_string());
+        }
+
+        // Extra settings, e.g. hooks, as a JSON string
+        if let Some(settings) = settings {
+            args.push("--settings".to_string());
+            args.push(settings.to_string());
         }
 
+        match session {
+            Session::New(id) => {
+                args.push("--session-id".to_string());
+                args.push(id.to_string());
+            }
+            Session::Resume(id) => {
+                args.push("--resume".to_string());
+                args.push(id.to_string());
+            }
+        }
+
+        // The prompt goes in on stdin, since it may hold resolved secrets
+        // that anyone listing processes could read from the arguments
+        let argv = match initial_prompt {
+            Some(prompt) => {
+                let prompt_file = write_prompt_file(prompt)
+                    .map_err(|e| SpawnerError::IoError(format!("Failed to write prompt file: {}", e)))?;
+                prompt_from_file_argv(&claude_path, &args, &prompt_file)
+            }
+            None => std::iter::once(claude_path.clone()).chain(args).collect(),
+        };
+        let mut cmd = CommandBuilder::new(&argv[0]);
+        cmd.args(&argv[1..]);
+
         // Set working directory
         cmd.cwd(working_dir);
 
@@ -121,9 +172,15 @@ impl PtyHandle {
         cmd.env("PATH", enhanced_path);
         cmd.env("TERM", "xterm-256color");
         cmd.env("COLORTERM", "truecolor");
-        if let Ok(key) = env::var("ANTHROPIC_API_KEY") {
+        let api_key = SETTINGS.get().backends.anthropic_api_key;
+        if let Some(key) = api_key.or_else(|| env::var("ANTHROPIC_API_KEY").ok()) {
             cmd.env("ANTHROPIC_API_KEY", key);
         }
+        // Extra variables: the execution's environment, and the proxy of a
+        // restricted project
+        for (name, value) in env {
+            cmd.env(name, value);
+        }
 
         // Spawn the child process in the PTY
         let child = pty_pair
@@ -133,6 +190,9 @@ impl PtyHandle {
 
         let pid = child.process_id().unwrap_or(0);
         log::info!("Claude Code PTY spawned with PID: {}", pid);
+        if pid != 0 {
+            group::track(pid);
+        }
 
         // Get writer for sending input
         let writer = pty_pair
@@ -188,8 +248,10 @@ impl PtyHandle {
             .map_err(|e| SpawnerError::IoError(format!("Wait error: {}", e)))
     }
 
-    /// Kill the process
+    /// Kill the process and everything it started; the PTY made it the
+    /// leader of a new session
     pub fn kill(&mut self) -> Result<(), SpawnerError> {
+        group::signal(self.pid, Signal::Kill);
         self.child
             .kill()
             .map_err(|e| SpawnerError::IoError(format!("Kill error: {}", e)))
@@ -214,6 +276,43 @@ impl PtyHandle {
     }
 }
 
+/// Shell script running `"$@"` with the file `$1` as its stdin, removing
+/// the file as soon as it is open
+#[cfg(unix)]
+const PROMPT_FROM_FILE: &str = r#"exec <"$1" && rm -f "$1" && shift && exec "$@""#;
+
+/// Write a prompt to a new file only the current user can read
+fn write_prompt_file(prompt: &str) -> std::io::Result<std::path::PathBuf> {
+    let path = env::temp_dir().join(format!("nexus-prompt-{}", uuid::Uuid::new_v4()));
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options.open(&path)?.write_all(prompt.as_bytes())?;
+    Ok(path)
+}
+
+/// Command line running `program` with `args` and `prompt_file` as its
+/// stdin, through the platform shell, which deletes the file: right away on
+/// Unix, once the program exits on Windows
+fn prompt_from_file_argv(program: &str, args: &[String], prompt_file: &std::path::Path) -> Vec<String> {
+    let prompt_file = prompt_file.to_string_lossy().to_string();
+    #[cfg(unix)]
+    {
+        let mut argv = vec!["sh".to_string(), "-c".to_string(), PROMPT_FROM_FILE.to_string(), "sh".to_string(), prompt_file];
+        argv.push(program.to_string());
+        argv.extend(args.iter().cloned());
+        argv
+    }
+    #[cfg(not(unix))]
+    {
+        let mut argv = vec!["cmd".to_string(), "/C".to_string(), program.to_string()];
+        argv.extend(args.iter().cloned());
+        argv.extend(["<".to_string(), prompt_file.clone(), "&".to_string(), "del".to_string(), prompt_file]);
+        argv
+    }
+}
+
 /// Start reading from a PTY and sending output through a channel
 pub fn start_pty_reader(
     mut reader: Box<dyn Read + Send>,
@@ -277,9 +376,10 @@ impl ProcessHandle {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        let child = cmd
+        let child = group::isolate(&mut cmd)
             .spawn()
             .map_err(|e| SpawnerError::SpawnError(format!("Failed to spawn: {}", e)))?;
+        group::track(child.id());
         Ok(Self { child: Some(child) })
     }
 
@@ -307,9 +407,10 @@ impl ProcessHandle {
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        let child = cmd
+        let child = group::isolate(&mut cmd)
             .spawn()
             .map_err(|e| SpawnerError::SpawnError(format!("Failed to spawn: {}", e)))?;
+        group::track(child.id());
         Ok(Self { child: Some(child) })
     }
 
@@ -338,6 +439,7 @@ impl ProcessHandle {
 
     pub fn kill(&mut self) -> Result<(), SpawnerError> {
         if let Some(ref mut child) = self.child {
+            group::signal(child.id(), Signal::Kill);
             child
                 .kill()
                 .map_err(|e| SpawnerError::IoError(e.to_string()))
@@ -396,3 +498,20 @@ pub fn start_output_reader<R: std::io::Read + Send + 'static>(
         }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_prompt_goes_in_on_stdin() {
+        let prompt_file = write_prompt_file("Use the key sk-secret").unwrap();
+        let argv = prompt_from_file_argv("cat", &["-".to_string()], &prompt_file);
+        assert!(!argv.iter().any(|arg| arg.contains("sk-secret")));
+
+        let output = Command::new(&argv[0]).args(&argv[1..]).output().unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "Use the key sk-secret");
+        assert!(!prompt_file.exists());
+    }
+}
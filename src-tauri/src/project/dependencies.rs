@@ -0,0 +1,513 @@
+//! Project dependency graph.
+//!
+//! Reads the import statements of a project's source files to find which
+//! files depend on which, so a workflow can narrow an agent's attention to
+//! the files a change can affect. Provides:
+//! - Import parsing for Rust (`mod`, `use crate::`/`super::`/`self::`),
+//!   JavaScript/TypeScript (relative `import`/`export ... from`/`require`)
+//!   and Python (`import`, `from ... import`)
+//! - Resolving imports to project files; imports of external packages are
+//!   left out
+//! - The files impacted by a set of changed files: the changed files plus
+//!   everything that imports them, directly or transitively
+//! - The prompt section that scopes an agent to those files
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Never scanned: VCS data, dependencies and build output
+const SKIPPED_DIRS: &[&str] = &[".git", "node_modules", "target", "dist", "build", "__pycache__", ".venv", "venv"];
+/// Bigger files are generated or vendored, not worth parsing
+const MAX_FILE_BYTES: u64 = 1_000_000;
+/// Stop scanning a project after this many source files
+const MAX_FILES: usize = 20_000;
+
+/// Impacted files listed in a prompt unless the node says otherwise
+const DEFAULT_SCOPE_FILES: usize = 50;
+
+const JS_EXTENSIONS: &[&str] = &["ts", "tsx", "js", "jsx", "mjs", "cjs"];
+
+lazy_static! {
+    static ref RUST_MOD: Regex = Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?mod\s+(\w+)\s*;").unwrap();
+    static ref RUST_USE: Regex = Regex::new(r"(?m)^\s*(?:pub(?:\([^)]*\))?\s+)?use\s+((?:crate|super|self)\b[^;]*);").unwrap();
+    static ref RUST_RENAME: Regex = Regex::new(r"\s+as\s+\w+").unwrap();
+    static ref JS_FROM: Regex = Regex::new(r#"(?:import|export)\s[^'";]*?from\s*['"]([^'"]+)['"]"#).unwrap();
+    static ref JS_BARE_IMPORT: Regex = Regex::new(r#"(?m)^\s*import\s*['"]([^'"]+)['"]"#).unwrap();
+    static ref JS_CALL: Regex = Regex::new(r#"\b(?:require|import)\(\s*['"]([^'"]+)['"]\s*\)"#).unwrap();
+    static ref PY_FROM: Regex =
+        Regex::new(r"(?m)^\s*from\s+(\.*)([\w.]*)\s+import\s+(?:\(([^)]*)\)|([\w \t,.*]+))").unwrap();
+    static ref PY_IMPORT: Regex = Regex::new(r"(?m)^\s*import\s+([\w., ]+)").unwrap();
+}
+
+/// The source languages whose imports are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Rust,
+    JavaScript,
+    Python,
+}
+
+impl Language {
+    fn of(path: &str) -> Option<Self> {
+        match path.rsplit_once('.')?.1 {
+            "rs" => Some(Language::Rust),
+            "py" => Some(Language::Python),
+            ext if JS_EXTENSIONS.contains(&ext) => Some(Language::JavaScript),
+            _ => None,
+        }
+    }
+}
+
+/// A file reached from a changed file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImpactedFile {
+    /// Relative to the project root, with `/` separators
+    pub path: String,
+    /// Import hops from the nearest changed file; 0 for changed files
+    pub distance: usize,
+    /// The file it imports that brought it in (`None` for changed files)
+    pub via: Option<String>,
+}
+
+/// Scopes an agent node to the files a change impacts
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ImpactScope {
+    /// Node whose output holds the change as a unified diff; defaults to
+    /// the project's uncommitted changes
+    pub diff_node: Option<String>,
+    /// Import hops to follow from the changed files (unlimited if unset)
+    pub max_depth: Option<usize>,
+    /// Files listed in the prompt, nearest first
+    pub max_files: Option<usize>,
+}
+
+impl ImpactScope {
+    /// The section added to the agent's task
+    pub fn prompt_section(&self, impacted: &[ImpactedFile]) -> String {
+        let changed = impacted.iter().filter(|file| file.distance == 0).count();
+        let limit = self.max_files.unwrap_or(DEFAULT_SCOPE_FILES);
+
+        let mut section = format!(
+            "## Scope\n\nThis change touches {} file(s); {} more depend on them. \
+             Limit your work and review to these files:\n\n",
+            changed,
+            impacted.len() - changed
+        );
+        for file in impacted.iter().take(limit) {
+            match &file.via {
+                Some(via) => section.push_str(&format!("- {} (imports {})\n", file.path, via)),
+                None => section.push_str(&format!("- {} (changed)\n", file.path)),
+            }
+        }
+        if impacted.len() > limit {
+            section.push_str(&format!("- ... and {} more, further from the change\n", impacted.len() - limit));
+        }
+        section
+    }
+}
+
+/// Which project files import which
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DependencyGraph {
+    /// Each source file's imports of other project files
+    pub imports: BTreeMap<String, BTreeSet<String>>,
+    #[serde(skip)]
+    importers: BTreeMap<String, BTreeSet<String>>,
+}
+
+impl DependencyGraph {
+    /// Scan the source files under `root`
+    pub fn build(root: &Path) -> io::Result<Self> {
+        let mut sources = Vec::new();
+        collect_sources(root, root, &mut sources)?;
+        Ok(Self::from_sources(sources))
+    }
+
+    /// Build from (path, contents) pairs, paths relative to the project root
+    pub fn from_sources(sources: impl IntoIterator<Item = (String, String)>) -> Self {
+        let sources: BTreeMap<String, String> = sources.into_iter().collect();
+        let files: BTreeSet<&str> = sources.keys().map(String::as_str).collect();
+
+        let mut graph = Self::default();
+        for (path, contents) in &sources {
+            let imports = match Language::of(path) {
+                Some(Language::Rust) => rust_imports(path, contents, &files),
+                Some(Language::JavaScript) => js_imports(path, contents, &files),
+                Some(Language::Python) => python_imports(path, contents, &files),
+                None => BTreeSet::new(),
+            };
+            for import in imports.iter().filter(|import| *import != path) {
+                graph.importers.entry(import.clone()).or_default().insert(path.clone());
+            }
+            graph.imports.insert(path.clone(), imports);
+        }
+        graph
+    }
+
+    pub fn file_count(&self) -> usize {
+        self.imports.len()
+    }
+
+    pub fn edge_count(&self) -> usize {
+        self.imports.values().map(BTreeSet::len).sum()
+    }
+
+    /// Files that import `path` directly
+    pub fn importers_of(&self, path: &str) -> Vec<String> {
+        self.importers.get(path).map(|set| set.iter().cloned().collect()).unwrap_or_default()
+    }
+
+    /// The changed files and every file that depends on them, nearest
+    /// first. `max_depth` limits how many import hops away to look.
+    pub fn impacted(&self, changed: &[String], max_depth: Option<usize>) -> Vec<ImpactedFile> {
+        let mut seen: BTreeMap<String, ImpactedFile> = BTreeMap::new();
+        let mut queue = VecDeque::new();
+
+        for path in changed {
+            let path = normalize(path);
+            if !seen.contains_key(&path) {
+                seen.insert(path.clone(), ImpactedFile { path: path.clone(), distance: 0, via: None });
+                queue.push_back((path, 0));
+            }
+        }
+
+        while let Some((path, distance)) = queue.pop_front() {
+            if max_depth.is_some_and(|max| distance >= max) {
+                continue;
+            }
+            for importer in self.importers.get(&path).into_iter().flatten() {
+                if seen.contains_key(importer) {
+                    continue;
+                }
+                seen.insert(
+                    importer.clone(),
+                    ImpactedFile {
+                        path: importer.clone(),
+                        distance: distance + 1,
+                        via: Some(path.clone()),
+                    },
+                );
+                queue.push_back((importer.clone(), distance + 1));
+            }
+        }
+
+        let mut impacted: Vec<ImpactedFile> = seen.into_values().collect();
+        impacted.sort_by(|a, b| a.distance.cmp(&b.distance).then_with(|| a.path.cmp(&b.path)));
+        impacted
+    }
+}
+
+/// Source files under `dir` with their contents, paths relative to `root`
+fn collect_sources(root: &Path, dir: &Path, sources: &mut Vec<(String, String)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        if sources.len() >= MAX_FILES {
+            return Ok(());
+        }
+        let entry = entry?;
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        let name = entry.file_name();
+
+        if file_type.is_dir() {
+            if !SKIPPED_DIRS.iter().any(|skipped| name == *skipped) {
+                collect_sources(root, &path, sources)?;
+            }
+            continue;
+        }
+
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        if !file_type.is_file() || Language::of(&relative).is_none() || entry.metadata()?.len() > MAX_FILE_BYTES {
+            continue;
+        }
+        // Skip files that aren't UTF-8 rather than failing the scan
+        if let Ok(contents) = fs::read_to_string(&path) {
+            sources.push((relative, contents));
+        }
+    }
+    Ok(())
+}
+
+/// `path` with `.` and `..` resolved and `/` separators
+fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// Directory of a file, "" at the project root
+fn parent_dir(path: &str) -> &str {
+    path.rsplit_once('/').map_or("", |(dir, _)| dir)
+}
+
+fn join(dir: &str, rest: &str) -> String {
+    if dir.is_empty() {
+        normalize(rest)
+    } else {
+        normalize(&format!("{}/{}", dir, rest))
+    }
+}
+
+/// Directory holding the submodules of the module a Rust file defines:
+/// its own directory for `mod.rs`, `lib.rs` and `main.rs`, else a
+/// directory named after it
+fn rust_module_dir(path: &str) -> String {
+    let dir = parent_dir(path);
+    let file = path.rsplit('/').next().unwrap_or(path);
+    match file {
+        "mod.rs" | "lib.rs" | "main.rs" => dir.to_string(),
+        _ => join(dir, file.trim_end_matches(".rs")),
+    }
+}
+
+/// The crate's root directory: the nearest ancestor holding `lib.rs` or
+/// `main.rs`
+fn rust_crate_dir(path: &str, files: &BTreeSet<&str>) -> String {
+    let mut dir = parent_dir(path).to_string();
+    loop {
+        if ["lib.rs", "main.rs"].iter().any(|root| files.contains(join(&dir, root).as_str())) {
+            return dir;
+        }
+        if dir.is_empty() {
+            return parent_dir(path).to_string();
+        }
+        dir = parent_dir(&dir).to_string();
+    }
+}
+
+/// The file of the deepest module along `segments` from `base`
+fn resolve_rust_path(base: &str, segments: &[&str], files: &BTreeSet<&str>) -> Option<String> {
+    (1..=segments.len()).rev().find_map(|len| {
+        let module = join(base, &segments[..len].join("/"));
+        [format!("{}.rs", module), format!("{}/mod.rs", module)]
+            .into_iter()
+            .find(|candidate| files.contains(candidate.as_str()))
+    })
+}
+
+/// Every path a `use` tree names, e.g. `crate::a::{b, c::d}` gives
+/// `crate::a::b` and `crate::a::c::d`
+fn expand_use_tree(tree: &str) -> Vec<String> {
+    let tree: String = RUST_RENAME.replace_all(tree, "").chars().filter(|c| !c.is_whitespace()).collect();
+    let Some(open) = tree.find('{') else {
+        return vec![tree];
+    };
+    let prefix = &tree[..open];
+    let Some(close) = tree.rfind('}') else {
+        return vec![prefix.trim_end_matches("::").to_string()];
+    };
+
+    // Split the group on its top-level commas
+    let mut items = Vec::new();
+    let (mut depth, mut start) = (0, open + 1);
+    for (i, c) in tree[open + 1..close].char_indices().map(|(i, c)| (i + open + 1, c)) {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ',' if depth == 0 => {
+                items.push(&tree[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&tree[start..close]);
+
+    items
+        .into_iter()
+        .filter(|item| !item.is_empty())
+        .flat_map(|item| match item {
+            "self" => vec![prefix.trim_end_matches("::").to_string()],
+            item => expand_use_tree(&format!("{}{}", prefix, item)),
+        })
+        .collect()
+}
+
+fn rust_imports(path: &str, contents: &str, files: &BTreeSet<&str>) -> BTreeSet<String> {
+    let module_dir = rust_module_dir(path);
+    let mut imports = BTreeSet::new();
+
+    for caps in RUST_MOD.captures_iter(contents) {
+        imports.extend(resolve_rust_path(&module_dir, &[&caps[1]], files));
+    }
+
+    for caps in RUST_USE.captures_iter(contents) {
+        for use_path in expand_use_tree(&caps[1]) {
+            let mut segments: Vec<&str> = use_path.split("::").filter(|s| !s.is_empty()).collect();
+            let mut base = match segments.first().copied() {
+                Some("crate") => rust_crate_dir(path, files),
+                Some("self") => module_dir.clone(),
+                Some("super") => module_dir.clone(),
+                _ => continue,
+            };
+            if segments[0] != "super" {
+                segments.remove(0);
+            }
+            while segments.first() == Some(&"super") {
+                segments.remove(0);
+                base = parent_dir(&base).to_string();
+            }
+            imports.extend(resolve_rust_path(&base, &segments, files));
+        }
+    }
+
+    imports
+}
+
+fn js_imports(path: &str, contents: &str, files: &BTreeSet<&str>) -> BTreeSet<String> {
+    let dir = parent_dir(path);
+    let specifiers = JS_FROM
+        .captures_iter(contents)
+        .chain(JS_BARE_IMPORT.captures_iter(contents))
+        .chain(JS_CALL.captures_iter(contents))
+        .map(|caps| caps[1].to_string());
+
+    specifiers
+        // Package imports aren't project files
+        .filter(|spec| spec.starts_with('.'))
+        .filter_map(|spec| {
+            let target = join(dir, &spec);
+            // TypeScript sources are imported by their compiled `.js` name
+            let stem = target.strip_suffix(".js").unwrap_or(&target);
+            std::iter::once(target.clone())
+                .chain(JS_EXTENSIONS.iter().map(|ext| format!("{}.{}", stem, ext)))
+                .chain(JS_EXTENSIONS.iter().map(|ext| format!("{}/index.{}", target, ext)))
+                .find(|candidate| files.contains(candidate.as_str()))
+        })
+        .collect()
+}
+
+fn python_imports(path: &str, contents: &str, files: &BTreeSet<&str>) -> BTreeSet<String> {
+    // Absolute imports resolve from the project root or a `src` layout
+    let resolve = |base: &str, module: &str| -> Option<String> {
+        let module = module.replace('.', "/");
+        [format!("{}.py", module), format!("{}/__init__.py", module)]
+            .into_iter()
+            .map(|candidate| join(base, &candidate))
+            .find(|candidate| files.contains(candidate.as_str()))
+    };
+    let absolute = |module: &str| resolve("", module).or_else(|| resolve("src", module));
+    let mut imports = BTreeSet::new();
+
+    for caps in PY_FROM.captures_iter(contents) {
+        let (dots, module) = (caps[1].len(), &caps[2]);
+        let names = caps.get(3).or_else(|| caps.get(4)).map_or("", |m| m.as_str()).split(',').map(|name| name.split_whitespace().next().unwrap_or_default());
+
+        if dots == 0 {
+            // `from pkg import mod` may name a submodule rather than an attribute
+            for name in names.filter(|name| !name.is_empty() && *name != "*") {
+                imports.extend(absolute(&format!("{}.{}", module, name)));
+            }
+            imports.extend(absolute(module));
+        } else {
+            let mut base = parent_dir(path).to_string();
+            for _ in 1..dots {
+                base = parent_dir(&base).to_string();
+            }
+            for name in names.filter(|name| !name.is_empty() && *name != "*") {
+                let submodule = if module.is_empty() { name.to_string() } else { format!("{}.{}", module, name) };
+                imports.extend(resolve(&base, &submodule));
+            }
+            if !module.is_empty() {
+                imports.extend(resolve(&base, module));
+            }
+        }
+    }
+
+    for caps in PY_IMPORT.captures_iter(contents) {
+        for module in caps[1].split(',') {
+            if let Some(module) = module.split_whitespace().next() {
+                imports.extend(absolute(module));
+            }
+        }
+    }
+
+    imports
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sources(files: &[(&str, &str)]) -> DependencyGraph {
+        DependencyGraph::from_sources(files.iter().map(|(path, contents)| (path.to_string(), contents.to_string())))
+    }
+
+    #[test]
+    fn test_rust_imports() {
+        let graph = sources(&[
+            ("src/lib.rs", "pub mod workflow;\nmod util;\n"),
+            ("src/util.rs", "use std::fs;\n"),
+            ("src/workflow/mod.rs", "pub mod context;\npub mod executor;\n"),
+            ("src/workflow/context.rs", "use crate::util;\n"),
+            (
+                "src/workflow/executor.rs",
+                "use super::{context::ExecutionContext, graph};\npub(crate) use crate::util::{self, helper as h};\n",
+            ),
+        ]);
+
+        let executor = &graph.imports["src/workflow/executor.rs"];
+        assert_eq!(executor.iter().collect::<Vec<_>>(), vec!["src/util.rs", "src/workflow/context.rs"]);
+        assert!(graph.imports["src/lib.rs"].contains("src/workflow/mod.rs"));
+        assert_eq!(graph.importers_of("src/util.rs"), vec!["src/lib.rs", "src/workflow/context.rs", "src/workflow/executor.rs"]);
+    }
+
+    #[test]
+    fn test_js_and_python_imports() {
+        let graph = sources(&[
+            ("src/App.tsx", "import React from 'react';\nimport { api } from './services/api';\nimport './styles.css';"),
+            ("src/services/api.ts", "export * from '../types';\nconst x = require('./http.js');"),
+            ("src/services/http.ts", ""),
+            ("src/types/index.ts", ""),
+            ("app/main.py", "from app.models import User\nfrom . import views\nimport os, app.db"),
+            ("app/models.py", ""),
+            ("app/views.py", "from .models import User"),
+            ("app/db/__init__.py", ""),
+        ]);
+
+        assert_eq!(graph.imports["src/App.tsx"].iter().collect::<Vec<_>>(), vec!["src/services/api.ts"]);
+        assert_eq!(graph.imports["src/services/api.ts"].iter().collect::<Vec<_>>(), vec!["src/services/http.ts", "src/types/index.ts"]);
+        assert_eq!(
+            graph.imports["app/main.py"].iter().collect::<Vec<_>>(),
+            vec!["app/db/__init__.py", "app/models.py", "app/views.py"]
+        );
+    }
+
+    #[test]
+    fn test_impacted() {
+        let graph = sources(&[
+            ("a.ts", "import './b';"),
+            ("b.ts", "import './c';"),
+            ("c.ts", ""),
+            ("d.ts", "import './c';"),
+            ("e.ts", ""),
+        ]);
+
+        let impacted = graph.impacted(&["./c.ts".to_string()], None);
+        let found: Vec<(&str, usize)> = impacted.iter().map(|f| (f.path.as_str(), f.distance)).collect();
+        assert_eq!(found, vec![("c.ts", 0), ("b.ts", 1), ("d.ts", 1), ("a.ts", 2)]);
+        assert_eq!(impacted[3].via.as_deref(), Some("b.ts"));
+
+        assert_eq!(graph.impacted(&["c.ts".to_string()], Some(1)).len(), 3);
+
+        let scope = ImpactScope { max_files: Some(2), ..ImpactScope::default() };
+        let section = scope.prompt_section(&impacted);
+        assert!(section.contains("touches 1 file(s); 3 more depend on them"));
+        assert!(section.contains("- c.ts (changed)\n- b.ts (imports c.ts)\n- ... and 2 more"));
+    }
+}
@@ -0,0 +1,219 @@
+//! Finding existing repositories to import as projects.
+//!
+//! Users with many repositories shouldn't have to create a project for
+//! each by hand. Provides:
+//! - Scanning a directory tree for git repositories, without descending
+//!   into them
+//! - Each candidate's name, from its Cargo.toml, package.json,
+//!   pyproject.toml or go.mod, else its directory name
+//! - Its main languages, by how many source files of each it has
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use super::relocation::PathStyle;
+
+/// Never scanned: dependencies and build output. Hidden directories are
+/// skipped too.
+const SKIPPED_DIRS: &[&str] = &["node_modules", "target", "dist", "build", "vendor", "__pycache__", "venv"];
+/// Directory levels below the root searched for repositories
+const DEFAULT_MAX_DEPTH: usize = 4;
+/// Files looked at per repository to tell its languages
+const MAX_SAMPLED_FILES: usize = 5_000;
+/// Languages listed per repository
+const MAX_LANGUAGES: usize = 3;
+
+/// A repository that could become a project
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProjectCandidate {
+    pub path: String,
+    pub name: String,
+    /// Most used first
+    pub languages: Vec<String>,
+    /// A project has this working directory already
+    pub imported: bool,
+}
+
+/// The git repositories under `root`, sorted by path. `known` are the
+/// working directories of existing projects.
+pub fn scan(root: &Path, max_depth: Option<usize>, known: &[String], style: PathStyle) -> Result<Vec<ProjectCandidate>, String> {
+    if !root.is_dir() {
+        return Err(format!("{} is not a directory", root.display()));
+    }
+    let max_depth = max_depth.unwrap_or(DEFAULT_MAX_DEPTH);
+    let mut candidates = Vec::new();
+    let mut pending = vec![(root.to_path_buf(), 0)];
+
+    while let Some((dir, depth)) = pending.pop() {
+        if dir.join(".git").exists() {
+            let path = dir.to_string_lossy().into_owned();
+            candidates.push(ProjectCandidate {
+                name: infer_name(&dir),
+                languages: detect_languages(&dir),
+                imported: known.iter().any(|known| style.same(known, &path)),
+                path,
+            });
+            continue;
+        }
+        if depth >= max_depth {
+            continue;
+        }
+        // Unreadable directories are passed over, not fatal
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) && !skipped(&entry.file_name().to_string_lossy()) {
+                pending.push((entry.path(), depth + 1));
+            }
+        }
+    }
+
+    candidates.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(candidates)
+}
+
+fn skipped(name: &str) -> bool {
+    name.starts_with('.') || SKIPPED_DIRS.contains(&name)
+}
+
+/// The package name of the repository's manifest, else its directory name
+pub fn infer_name(dir: &Path) -> String {
+    let read = |file: &str| fs::read_to_string(dir.join(file)).ok();
+    let toml_name = |file: &str, keys: &[&[&str]]| -> Option<String> {
+        let table = read(file)?.parse::<toml::Table>().ok()?;
+        keys.iter().find_map(|path| {
+            let mut value = table.get(path[0])?;
+            for key in &path[1..] {
+                value = value.get(key)?;
+            }
+            value.as_str().map(str::to_string)
+        })
+    };
+
+    let name = toml_name("Cargo.toml", &[&["package", "name"]])
+        .or_else(|| {
+            let manifest: serde_json::Value = serde_json::from_str(&read("package.json")?).ok()?;
+            // Without the scope of `@scope/name`
+            let name = manifest.get("name")?.as_str()?;
+            Some(name.rsplit('/').next().unwrap_or(name).to_string())
+        })
+        .or_else(|| toml_name("pyproject.toml", &[&["project", "name"], &["tool", "poetry", "name"]]))
+        .or_else(|| {
+            let module = read("go.mod")?.lines().find_map(|line| line.trim().strip_prefix("module ").map(str::trim).map(str::to_string))?;
+            module.rsplit('/').next().map(str::to_string)
+        })
+        .filter(|name| !name.trim().is_empty());
+
+    name.unwrap_or_else(|| {
+        dir.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| dir.to_string_lossy().into_owned())
+    })
+}
+
+fn language_of(extension: &str) -> Option<&'static str> {
+    Some(match extension {
+        "rs" => "Rust",
+        "ts" | "tsx" => "TypeScript",
+        "js" | "jsx" | "mjs" | "cjs" => "JavaScript",
+        "py" => "Python",
+        "go" => "Go",
+        "java" => "Java",
+        "kt" | "kts" => "Kotlin",
+        "swift" => "Swift",
+        "rb" => "Ruby",
+        "php" => "PHP",
+        "cs" => "C#",
+        "c" | "h" => "C",
+        "cpp" | "cc" | "cxx" | "hpp" => "C++",
+        "scala" => "Scala",
+        "dart" => "Dart",
+        "ex" | "exs" => "Elixir",
+        "vue" => "Vue",
+        "svelte" => "Svelte",
+        "sh" | "bash" => "Shell",
+        _ => return None,
+    })
+}
+
+/// The languages most of the repository's source files are in
+pub fn detect_languages(dir: &Path) -> Vec<String> {
+    let mut counts: HashMap<&'static str, usize> = HashMap::new();
+    let mut pending = vec![dir.to_path_buf()];
+    let mut sampled = 0;
+
+    'scan: while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir).into_iter().flatten().flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if file_type.is_dir() {
+                if !skipped(&name) {
+                    pending.push(entry.path());
+                }
+            } else if file_type.is_file() {
+                if let Some(language) = name.rsplit_once('.').and_then(|(_, ext)| language_of(ext)) {
+                    *counts.entry(language).or_insert(0) += 1;
+                }
+                sampled += 1;
+                if sampled >= MAX_SAMPLED_FILES {
+                    break 'scan;
+                }
+            }
+        }
+    }
+
+    let mut languages: Vec<(&str, usize)> = counts.into_iter().collect();
+    languages.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    languages.into_iter().take(MAX_LANGUAGES).map(|(language, _)| language.to_string()).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_scan_for_repositories() {
+        let root = std::env::temp_dir().join(format!("nexus-discovery-{}", Uuid::new_v4()));
+        let write = |path: &str, contents: &str| {
+            let path = root.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        };
+        write("work/api/.git/HEAD", "ref: refs/heads/main");
+        write("work/api/Cargo.toml", "[package]\nname = \"orders-api\"\n");
+        write("work/api/src/main.rs", "");
+        write("work/api/src/lib.rs", "");
+        write("work/api/scripts/seed.py", "");
+        // Not searched inside a repository
+        write("work/api/vendored/.git/HEAD", "");
+        write("work/web/.git/HEAD", "");
+        write("work/web/package.json", r#"{"name": "@shop/storefront"}"#);
+        write("work/web/src/app.tsx", "");
+        write("work/web/node_modules/dep/index.js", "");
+        write("notes/plain/.git/HEAD", "");
+        write("notes/readme.md", "");
+        write(".cache/hidden/.git/HEAD", "");
+
+        let known = vec![root.join("work/web").to_string_lossy().into_owned()];
+        let style = PathStyle::native();
+        let candidates = scan(&root, None, &known, style).unwrap();
+        let summary: Vec<(&str, Vec<&str>, bool)> = candidates
+            .iter()
+            .map(|c| (c.name.as_str(), c.languages.iter().map(String::as_str).collect(), c.imported))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("plain", vec![], false),
+                ("orders-api", vec!["Rust", "Python"], false),
+                ("storefront", vec!["TypeScript"], true),
+            ]
+        );
+        assert!(scan(&root, Some(1), &known, style).unwrap().is_empty());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,435 @@
+//! Isolated execution workspaces.
+//!
+//! An isolated execution runs its agents in a copy of the project rather
+//! than the live working tree, and nothing reaches the project until the
+//! user approves it. Works for any directory, git repository or not.
+//! Provides:
+//! - Copying the project into a scratch workspace, as copy-on-write clones
+//!   where the filesystem supports them
+//! - A manifest of content hashes taken at copy time, used to list what the
+//!   agents changed and which of those files the user changed meanwhile
+//! - Merging approved changes back, and discarding workspaces
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::workflow::FileChange;
+
+/// Copied into the workspace but never compared or merged back
+const UNMERGED_DIRS: &[&str] = &[".git", "node_modules", "target"];
+
+#[derive(Debug, Error)]
+pub enum IsolationError {
+    #[error("No isolated workspace for execution {0}")]
+    NotFound(Uuid),
+
+    #[error("Project directory not found: {0}")]
+    ProjectMissing(PathBuf),
+
+    #[error("Workspace file operation failed: {0}")]
+    Io(#[from] io::Error),
+
+    #[error("Invalid workspace manifest: {0}")]
+    Manifest(#[from] serde_json::Error),
+}
+
+/// An execution's scratch copy of its project
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WorkspaceInfo {
+    pub execution_id: Uuid,
+    pub project_dir: PathBuf,
+    /// Where the agents work
+    pub workspace_dir: PathBuf,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Saved next to the copy
+#[derive(Serialize, Deserialize)]
+struct WorkspaceManifest {
+    info: WorkspaceInfo,
+    /// Content hash of every file at copy time
+    files: BTreeMap<String, u64>,
+}
+
+/// A file the agents changed in the workspace
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WorkspaceChange {
+    pub path: String,
+    pub change: FileChange,
+    /// The project's copy changed too since the workspace was made;
+    /// approving it would overwrite that change
+    pub conflict: bool,
+}
+
+/// What approving merged back
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergeReport {
+    pub applied: Vec<String>,
+    /// Conflicting files left alone
+    pub skipped: Vec<String>,
+    /// Changes still waiting in the workspace
+    pub remaining: usize,
+}
+
+/// Isolated workspaces, one directory per execution
+pub struct WorkspaceStore {
+    root: PathBuf,
+}
+
+impl WorkspaceStore {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    pub fn default_root() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+            .join("workspaces")
+    }
+
+    fn manifest_path(&self, execution_id: &Uuid) -> PathBuf {
+        self.root.join(execution_id.to_string()).join("manifest.json")
+    }
+
+    fn tree_path(&self, execution_id: &Uuid) -> PathBuf {
+        self.root.join(execution_id.to_string()).join("tree")
+    }
+
+    /// Copy `project_dir` into a new workspace for the execution
+    pub fn create(&self, execution_id: Uuid, project_dir: &Path) -> Result<WorkspaceInfo, IsolationError> {
+        if !project_dir.is_dir() {
+            return Err(IsolationError::ProjectMissing(project_dir.to_path_buf()));
+        }
+
+        let workspace_dir = self.tree_path(&execution_id);
+        copy_tree(project_dir, &workspace_dir)?;
+
+        let info = WorkspaceInfo {
+            execution_id,
+            project_dir: project_dir.to_path_buf(),
+            workspace_dir,
+            created_at: Utc::now(),
+        };
+        let manifest = WorkspaceManifest {
+            info: info.clone(),
+            files: hash_tree(&info.workspace_dir)?,
+        };
+        self.save(&manifest)?;
+        log::info!("Created isolated workspace {:?} for execution {}", info.workspace_dir, execution_id);
+        Ok(info)
+    }
+
+    fn load(&self, execution_id: &Uuid) -> Result<WorkspaceManifest, IsolationError> {
+        let content = fs::read_to_string(self.manifest_path(execution_id)).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => IsolationError::NotFound(*execution_id),
+            _ => IsolationError::Io(e),
+        })?;
+        Ok(serde_json::from_str(&content)?)
+    }
+
+    fn save(&self, manifest: &WorkspaceManifest) -> Result<(), IsolationError> {
+        let content = serde_json::to_string(manifest)?;
+        fs::write(self.manifest_path(&manifest.info.execution_id), content)?;
+        Ok(())
+    }
+
+    pub fn get(&self, execution_id: &Uuid) -> Result<WorkspaceInfo, IsolationError> {
+        Ok(self.load(execution_id)?.info)
+    }
+
+    /// Where the execution's agents should work, if it is isolated
+    pub fn working_dir(&self, execution_id: &Uuid) -> Option<PathBuf> {
+        let tree = self.tree_path(execution_id);
+        tree.is_dir().then_some(tree)
+    }
+
+    /// All workspaces, newest first
+    pub fn list(&self) -> Vec<WorkspaceInfo> {
+        let mut workspaces: Vec<WorkspaceInfo> = fs::read_dir(&self.root)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| Uuid::parse_str(&entry.file_name().to_string_lossy()).ok())
+            .filter_map(|execution_id| self.get(&execution_id).ok())
+            .collect();
+        workspaces.sort_by_key(|info| std::cmp::Reverse(info.created_at));
+        workspaces
+    }
+
+    /// Files the agents changed, sorted by path
+    pub fn changes(&self, execution_id: &Uuid) -> Result<Vec<WorkspaceChange>, IsolationError> {
+        let manifest = self.load(execution_id)?;
+        let current = hash_tree(&manifest.info.workspace_dir)?;
+        Ok(diff(&manifest, &current))
+    }
+
+    /// Copy approved changes into the project. `paths` limits the merge to
+    /// those files; conflicting files are skipped unless
+    /// `overwrite_conflicts` is set.
+    pub fn merge(
+        &self,
+        execution_id: &Uuid,
+        paths: Option<&[String]>,
+        overwrite_conflicts: bool,
+    ) -> Result<MergeReport, IsolationError> {
+        let mut manifest = self.load(execution_id)?;
+        let current = hash_tree(&manifest.info.workspace_dir)?;
+        let changes = diff(&manifest, &current);
+        let mut report = MergeReport::default();
+
+        for change in &changes {
+            if paths.is_some_and(|paths| !paths.contains(&change.path)) {
+                report.remaining += 1;
+                continue;
+            }
+            if change.conflict && !overwrite_conflicts {
+                report.skipped.push(change.path.clone());
+                report.remaining += 1;
+                continue;
+            }
+
+            let target = manifest.info.project_dir.join(&change.path);
+            match change.change {
+                FileChange::Deleted => {
+                    if let Err(e) = fs::remove_file(&target) {
+                        if e.kind() != io::ErrorKind::NotFound {
+                            return Err(e.into());
+                        }
+                    }
+                    manifest.files.remove(&change.path);
+                }
+                FileChange::Created | FileChange::Modified => {
+                    if let Some(parent) = target.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::copy(manifest.info.workspace_dir.join(&change.path), &target)?;
+                    // Merged files are the new baseline
+                    if let Some(&hash) = current.get(&change.path) {
+                        manifest.files.insert(change.path.clone(), hash);
+                    }
+                }
+            }
+            report.applied.push(change.path.clone());
+        }
+
+        self.save(&manifest)?;
+        log::info!(
+            "Merged {} file(s) from workspace of execution {} ({} skipped)",
+            report.applied.len(),
+            execution_id,
+            report.skipped.len()
+        );
+        Ok(report)
+    }
+
+    /// Merge into `to` from now on what was merged into `from`, after the
+    /// project moved; returns how many workspaces were updated
+    pub fn repoint_project(&self, from: &Path, to: &Path) -> usize {
+        let mut updated = 0;
+        for info in self.list().into_iter().filter(|info| info.project_dir == from) {
+            let Ok(mut manifest) = self.load(&info.execution_id) else {
+                continue;
+            };
+            manifest.info.project_dir = to.to_path_buf();
+            match self.save(&manifest) {
+                Ok(()) => updated += 1,
+                Err(e) => log::warn!("Failed to re-point workspace of execution {}: {}", info.execution_id, e),
+            }
+        }
+        updated
+    }
+
+    /// Delete the workspace and everything the agents left in it
+    pub fn discard(&self, execution_id: &Uuid) -> Result<(), IsolationError> {
+        let dir = self.root.join(execution_id.to_string());
+        if !dir.exists() {
+            return Err(IsolationError::NotFound(*execution_id));
+        }
+        fs::remove_dir_all(dir)?;
+        Ok(())
+    }
+}
+
+/// Changes between a manifest and the workspace's current hashes
+fn diff(manifest: &WorkspaceManifest, current: &BTreeMap<String, u64>) -> Vec<WorkspaceChange> {
+    let created_or_modified = current.iter().filter_map(|(path, hash)| match manifest.files.get(path) {
+        None => Some((path, FileChange::Created)),
+        Some(original) if original != hash => Some((path, FileChange::Modified)),
+        Some(_) => None,
+    });
+    let deleted = manifest
+        .files
+        .keys()
+        .filter(|path| !current.contains_key(*path))
+        .map(|path| (path, FileChange::Deleted));
+
+    let mut changes: Vec<WorkspaceChange> = created_or_modified
+        .chain(deleted)
+        .map(|(path, change)| {
+            let in_project = hash_file(&manifest.info.project_dir.join(path)).ok();
+            WorkspaceChange {
+                path: path.clone(),
+                change,
+                conflict: in_project != manifest.files.get(path).copied(),
+            }
+        })
+        .collect();
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    changes
+}
+
+/// Copy a directory's contents, cloning files where the filesystem can
+pub(super) fn copy_tree(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+
+    // `cp` knows how to make copy-on-write clones (reflinks on Btrfs/XFS,
+    // clonefile on APFS); fall back to a plain copy
+    let clone_args: &[&str] = if cfg!(target_os = "linux") {
+        &["-a", "--reflink=auto"]
+    } else if cfg!(target_os = "macos") {
+        &["-c", "-R", "-p"]
+    } else {
+        &[]
+    };
+    if !clone_args.is_empty() {
+        let cloned = std::process::Command::new("cp")
+            .args(clone_args)
+            .arg(from.join("."))
+            .arg(to)
+            .status()
+            .is_ok_and(|status| status.success());
+        if cloned {
+            return Ok(());
+        }
+    }
+
+    copy_recursive(from, to)
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            copy_recursive(&entry.path(), &target)?;
+        } else if file_type.is_symlink() {
+            #[cfg(unix)]
+            std::os::unix::fs::symlink(fs::read_link(entry.path())?, &target)?;
+        } else {
+            fs::copy(entry.path(), &target)?;
+        }
+    }
+    Ok(())
+}
+
+/// Content hashes of the regular files under `root`, by relative path
+fn hash_tree(root: &Path) -> io::Result<BTreeMap<String, u64>> {
+    let mut files = BTreeMap::new();
+    let mut pending = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if !UNMERGED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref()) {
+                    pending.push(entry.path());
+                }
+            } else if file_type.is_file() {
+                let path = entry.path();
+                let relative = path.strip_prefix(root).unwrap_or(&path).to_string_lossy().into_owned();
+                files.insert(relative, hash_file(&path)?);
+            }
+        }
+    }
+
+    Ok(files)
+}
+
+/// FNV-1a of a file's contents; stable across builds, unlike `DefaultHasher`
+fn hash_file(path: &Path) -> io::Result<u64> {
+    let bytes = fs::read(path)?;
+    Ok(bytes.iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x100000001b3)
+    }))
+}
+
+// Global workspace store
+lazy_static::lazy_static! {
+    pub static ref ISOLATED_WORKSPACES: WorkspaceStore = WorkspaceStore::new(WorkspaceStore::default_root());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_isolated_workspace_review_and_merge() {
+        let base = std::env::temp_dir().join(format!("nexus-isolation-{}", Uuid::new_v4()));
+        let project = base.join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::create_dir_all(project.join("node_modules/dep")).unwrap();
+        fs::write(project.join("src/lib.rs"), "fn original() {}").unwrap();
+        fs::write(project.join("README.md"), "readme").unwrap();
+        fs::write(project.join("shared.txt"), "v1").unwrap();
+        fs::write(project.join("node_modules/dep/index.js"), "dep").unwrap();
+
+        let store = WorkspaceStore::new(base.join("workspaces"));
+        let execution_id = Uuid::new_v4();
+        let info = store.create(execution_id, &project).unwrap();
+        assert_eq!(store.working_dir(&execution_id), Some(info.workspace_dir.clone()));
+        assert_eq!(fs::read_to_string(info.workspace_dir.join("src/lib.rs")).unwrap(), "fn original() {}");
+
+        // Agents work in the copy; the user edits shared.txt in the project
+        let tree = &info.workspace_dir;
+        fs::write(tree.join("src/lib.rs"), "fn changed() {}").unwrap();
+        fs::write(tree.join("src/new.rs"), "fn new() {}").unwrap();
+        fs::remove_file(tree.join("README.md")).unwrap();
+        fs::write(tree.join("shared.txt"), "agent").unwrap();
+        fs::write(tree.join("node_modules/dep/index.js"), "patched").unwrap();
+        fs::write(project.join("shared.txt"), "user").unwrap();
+        assert_eq!(fs::read_to_string(project.join("src/lib.rs")).unwrap(), "fn original() {}");
+
+        let changes = store.changes(&execution_id).unwrap();
+        let summary: Vec<(&str, FileChange, bool)> =
+            changes.iter().map(|c| (c.path.as_str(), c.change, c.conflict)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("README.md", FileChange::Deleted, false),
+                ("shared.txt", FileChange::Modified, true),
+                ("src/lib.rs", FileChange::Modified, false),
+                ("src/new.rs", FileChange::Created, false),
+            ]
+        );
+
+        let report = store.merge(&execution_id, None, false).unwrap();
+        assert_eq!(report.applied.len(), 3);
+        assert_eq!(report.skipped, vec!["shared.txt".to_string()]);
+        assert_eq!(report.remaining, 1);
+        assert_eq!(fs::read_to_string(project.join("src/lib.rs")).unwrap(), "fn changed() {}");
+        assert!(project.join("src/new.rs").exists());
+        assert!(!project.join("README.md").exists());
+        assert_eq!(fs::read_to_string(project.join("shared.txt")).unwrap(), "user");
+        assert_eq!(fs::read_to_string(project.join("node_modules/dep/index.js")).unwrap(), "dep");
+
+        // Only the skipped conflict is left to review
+        assert_eq!(store.changes(&execution_id).unwrap().len(), 1);
+        assert_eq!(store.list().len(), 1);
+
+        store.discard(&execution_id).unwrap();
+        assert!(store.working_dir(&execution_id).is_none());
+        assert!(matches!(store.changes(&execution_id), Err(IsolationError::NotFound(_))));
+        let _ = fs::remove_dir_all(base);
+    }
+}
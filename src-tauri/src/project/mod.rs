@@ -1,5 +1,15 @@
+pub mod dependencies;
+pub mod discovery;
+pub mod isolation;
+pub mod packages;
+pub mod relocation;
 pub mod workspace;
 
+pub use dependencies::{DependencyGraph, ImpactScope, ImpactedFile};
+pub use discovery::ProjectCandidate;
+pub use isolation::{IsolationError, MergeReport, WorkspaceChange, WorkspaceInfo, WorkspaceStore, ISOLATED_WORKSPACES};
+pub use packages::{Package, PackageKind, PackageSelection};
+pub use relocation::{PathStyle, RelocateMode, Relocation};
 pub use workspace::{
     create_project_workspace, get_projects_base_dir, init_project_structure, ProjectWorkspace,
 };
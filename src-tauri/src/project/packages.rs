@@ -0,0 +1,399 @@
+//! Monorepo packages.
+//!
+//! Finds the packages of a workspace so a workflow can run once per
+//! package. Provides:
+//! - Detection of Cargo workspaces, npm/pnpm/yarn workspaces and Go
+//!   workspaces (`go.work`)
+//! - Each package's dependencies on the other packages of the workspace
+//! - Selecting packages by name, by depending on a given package, or by
+//!   being affected by a set of changed files
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs;
+use std::path::Path;
+
+/// The workspace tooling a package belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PackageKind {
+    Cargo,
+    /// npm, pnpm or yarn
+    Node,
+    Go,
+}
+
+/// One package of a workspace
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Package {
+    /// Crate name, `package.json` name or Go module path
+    pub name: String,
+    /// Directory relative to the project root, "" for the root itself
+    pub path: String,
+    pub kind: PackageKind,
+    /// Other packages of the workspace it depends on
+    pub dependencies: Vec<String>,
+}
+
+/// Which packages a fan-out covers; every filter set must match
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackageSelection {
+    /// Only these packages (all when empty)
+    #[serde(default)]
+    pub names: Vec<String>,
+    /// Only packages depending on this one, directly or transitively
+    pub depends_on: Option<String>,
+    /// Only packages holding a changed file, or depending on one that does
+    #[serde(default)]
+    pub affected_only: bool,
+}
+
+/// Every package of the workspaces rooted at `root`, sorted by path. A
+/// project without workspaces has none.
+pub fn detect_packages(root: &Path) -> Result<Vec<Package>, String> {
+    let mut packages: BTreeMap<String, Package> = BTreeMap::new();
+    for package in cargo_packages(root)?.into_iter().chain(node_packages(root)?).chain(go_packages(root)?) {
+        packages.entry(package.path.clone()).or_insert(package);
+    }
+
+    // Keep only dependencies on packages of the workspace
+    let names: BTreeSet<String> = packages.values().map(|p| p.name.clone()).collect();
+    let mut packages: Vec<Package> = packages.into_values().collect();
+    for package in &mut packages {
+        package.dependencies.retain(|dep| names.contains(dep) && *dep != package.name);
+        package.dependencies.sort();
+        package.dependencies.dedup();
+    }
+    Ok(packages)
+}
+
+/// The packages `selection` picks. `changed_files` is only consulted when
+/// it asks for affected packages.
+pub fn select(packages: &[Package], selection: &PackageSelection, changed_files: &[String]) -> Vec<Package> {
+    let importers = selection.depends_on.as_deref().map(|name| dependents(packages, &[name.to_string()]));
+    let affected = selection.affected_only.then(|| {
+        let owners: Vec<String> = changed_files
+            .iter()
+            .filter_map(|file| owner(packages, file))
+            .map(|package| package.name.clone())
+            .collect();
+        let mut affected = dependents(packages, &owners);
+        affected.extend(owners);
+        affected
+    });
+
+    packages
+        .iter()
+        .filter(|p| selection.names.is_empty() || selection.names.contains(&p.name))
+        .filter(|p| importers.as_ref().map_or(true, |set| set.contains(&p.name)))
+        .filter(|p| affected.as_ref().map_or(true, |set| set.contains(&p.name)))
+        .cloned()
+        .collect()
+}
+
+/// Packages depending on any of `names`, directly or transitively
+pub fn dependents(packages: &[Package], names: &[String]) -> BTreeSet<String> {
+    let mut found = BTreeSet::new();
+    let mut frontier: Vec<String> = names.to_vec();
+
+    while let Some(name) = frontier.pop() {
+        for package in packages.iter().filter(|p| p.dependencies.contains(&name)) {
+            if found.insert(package.name.clone()) {
+                frontier.push(package.name.clone());
+            }
+        }
+    }
+    found
+}
+
+/// The package whose directory most closely holds `file`
+pub fn owner<'a>(packages: &'a [Package], file: &str) -> Option<&'a Package> {
+    let file = file.trim_start_matches("./");
+    packages
+        .iter()
+        .filter(|p| p.path.is_empty() || file.strip_prefix(p.path.as_str()).is_some_and(|rest| rest.starts_with('/')))
+        .max_by_key(|p| p.path.len())
+}
+
+/// Directories matching workspace member patterns, relative to `root`.
+/// Patterns starting with `!` exclude.
+fn expand_members(root: &Path, patterns: &[String], manifest: &str) -> Vec<String> {
+    let mut members = BTreeSet::new();
+    let mut excluded = BTreeSet::new();
+
+    for pattern in patterns {
+        let (pattern, target) = match pattern.strip_prefix('!') {
+            Some(pattern) => (pattern, &mut excluded),
+            None => (pattern.as_str(), &mut members),
+        };
+        let full = root.join(pattern.trim_start_matches("./").trim_end_matches('/'));
+        let Ok(paths) = glob::glob(&full.to_string_lossy()) else {
+            continue;
+        };
+        for dir in paths.flatten().filter(|dir| dir.join(manifest).is_file()) {
+            if let Ok(relative) = dir.strip_prefix(root) {
+                target.insert(relative.to_string_lossy().replace('\\', "/"));
+            }
+        }
+    }
+
+    members.difference(&excluded).cloned().collect()
+}
+
+fn read_optional(path: &Path) -> Result<Option<String>, String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+fn cargo_packages(root: &Path) -> Result<Vec<Package>, String> {
+    let parse = |dir: &Path| -> Result<Option<toml::Table>, String> {
+        let path = dir.join("Cargo.toml");
+        read_optional(&path)?
+            .map(|contents| contents.parse::<toml::Table>().map_err(|e| format!("Invalid {}: {}", path.display(), e)))
+            .transpose()
+    };
+    let Some(manifest) = parse(root)? else {
+        return Ok(Vec::new());
+    };
+    let Some(workspace) = manifest.get("workspace").and_then(|w| w.as_table()) else {
+        return Ok(Vec::new());
+    };
+
+    let strings = |key: &str| -> Vec<String> {
+        workspace
+            .get(key)
+            .and_then(|v| v.as_array())
+            .map(|items| items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    };
+    let mut patterns = strings("members");
+    patterns.extend(strings("exclude").into_iter().map(|pattern| format!("!{}", pattern)));
+
+    let mut members = expand_members(root, &patterns, "Cargo.toml");
+    // A root manifest with a [package] is a member too
+    if manifest.contains_key("package") {
+        members.insert(0, String::new());
+    }
+
+    let mut packages = Vec::new();
+    for path in members {
+        let Some(member) = parse(&root.join(&path))? else {
+            continue;
+        };
+        let Some(name) = member.get("package").and_then(|p| p.get("name")).and_then(|n| n.as_str()) else {
+            continue;
+        };
+
+        let mut tables: Vec<&toml::Table> = ["dependencies", "dev-dependencies", "build-dependencies"]
+            .iter()
+            .filter_map(|key| member.get(*key).and_then(|t| t.as_table()))
+            .collect();
+        if let Some(targets) = member.get("target").and_then(|t| t.as_table()) {
+            for target in targets.values().filter_map(|t| t.as_table()) {
+                tables.extend(
+                    ["dependencies", "dev-dependencies", "build-dependencies"]
+                        .iter()
+                        .filter_map(|key| target.get(*key).and_then(|t| t.as_table())),
+                );
+            }
+        }
+        // A renamed dependency names its crate in `package`
+        let dependencies = tables
+            .into_iter()
+            .flat_map(|table| table.iter())
+            .map(|(key, spec)| spec.get("package").and_then(|p| p.as_str()).unwrap_or(key).to_string())
+            .collect();
+
+        packages.push(Package {
+            name: name.to_string(),
+            path,
+            kind: PackageKind::Cargo,
+            dependencies,
+        });
+    }
+    Ok(packages)
+}
+
+fn node_packages(root: &Path) -> Result<Vec<Package>, String> {
+    let parse = |dir: &Path| -> Result<Option<serde_json::Value>, String> {
+        let path = dir.join("package.json");
+        read_optional(&path)?
+            .map(|contents| serde_json::from_str(&contents).map_err(|e| format!("Invalid {}: {}", path.display(), e)))
+            .transpose()
+    };
+    let strings = |value: Option<&serde_json::Value>| -> Vec<String> {
+        value
+            .and_then(|v| v.as_array())
+            .map(|items| items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+            .unwrap_or_default()
+    };
+
+    // pnpm lists its workspaces in pnpm-workspace.yaml, npm and yarn in package.json
+    let mut patterns = match read_optional(&root.join("pnpm-workspace.yaml"))? {
+        Some(contents) => {
+            let yaml: serde_yaml::Value =
+                serde_yaml::from_str(&contents).map_err(|e| format!("Invalid pnpm-workspace.yaml: {}", e))?;
+            yaml.get("packages")
+                .and_then(|p| p.as_sequence())
+                .map(|items| items.iter().filter_map(|v| v.as_str().map(str::to_string)).collect())
+                .unwrap_or_default()
+        }
+        None => Vec::new(),
+    };
+    if let Some(manifest) = parse(root)? {
+        let workspaces = manifest.get("workspaces");
+        patterns.extend(strings(workspaces));
+        patterns.extend(strings(workspaces.and_then(|w| w.get("packages"))));
+    }
+
+    let mut packages = Vec::new();
+    for path in expand_members(root, &patterns, "package.json") {
+        let Some(member) = parse(&root.join(&path))? else {
+            continue;
+        };
+        let name = member
+            .get("name")
+            .and_then(|n| n.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| path.rsplit('/').next().unwrap_or(&path).to_string());
+        let dependencies = ["dependencies", "devDependencies", "peerDependencies", "optionalDependencies"]
+            .iter()
+            .filter_map(|key| member.get(*key).and_then(|d| d.as_object()))
+            .flat_map(|deps| deps.keys().cloned())
+            .collect();
+
+        packages.push(Package {
+            name,
+            path,
+            kind: PackageKind::Node,
+            dependencies,
+        });
+    }
+    Ok(packages)
+}
+
+fn go_packages(root: &Path) -> Result<Vec<Package>, String> {
+    let Some(work) = read_optional(&root.join("go.work"))? else {
+        return Ok(Vec::new());
+    };
+
+    let mut packages = Vec::new();
+    for path in go_directives(&work, "use") {
+        let path = path.trim_start_matches("./").trim_end_matches('/');
+        let path = if path == "." { "" } else { path };
+        let Some(module) = read_optional(&root.join(path).join("go.mod"))? else {
+            continue;
+        };
+        let Some(name) = go_directives(&module, "module").into_iter().next() else {
+            continue;
+        };
+
+        packages.push(Package {
+            name,
+            path: path.to_string(),
+            kind: PackageKind::Go,
+            dependencies: go_directives(&module, "require"),
+        });
+    }
+    Ok(packages)
+}
+
+/// The first word of each entry of a go.mod/go.work directive, in either
+/// its one-line or its parenthesized block form
+fn go_directives(contents: &str, directive: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut in_block = false;
+
+    for line in contents.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        if in_block {
+            if line == ")" {
+                in_block = false;
+            } else if let Some(value) = line.split_whitespace().next() {
+                values.push(value.to_string());
+            }
+        } else if let Some(rest) = line.strip_prefix(directive).filter(|rest| rest.starts_with([' ', '\t', '('])) {
+            match rest.trim() {
+                "(" => in_block = true,
+                rest => values.extend(rest.split_whitespace().next().map(str::to_string)),
+            }
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(root: &Path, path: &str, contents: &str) {
+        let path = root.join(path);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[test]
+    fn test_detect_workspaces() {
+        let root = std::env::temp_dir().join(format!("nexus-packages-{}", uuid::Uuid::new_v4()));
+        write(&root, "Cargo.toml", "[workspace]\nmembers = [\"crates/*\"]\nexclude = [\"crates/scratch\"]\n");
+        write(&root, "crates/core/Cargo.toml", "[package]\nname = \"core\"\n[dependencies]\nserde = \"1\"\n");
+        write(
+            &root,
+            "crates/cli/Cargo.toml",
+            "[package]\nname = \"cli\"\n[dependencies]\nengine = { path = \"../core\", package = \"core\" }\n",
+        );
+        write(&root, "crates/scratch/Cargo.toml", "[package]\nname = \"scratch\"\n");
+        write(&root, "package.json", r#"{"private": true, "workspaces": ["web/*"]}"#);
+        write(&root, "web/ui/package.json", r#"{"name": "@acme/ui", "dependencies": {"react": "^18"}}"#);
+        write(&root, "web/app/package.json", r#"{"name": "@acme/app", "devDependencies": {"@acme/ui": "workspace:*"}}"#);
+        write(&root, "go.work", "go 1.22\n\nuse (\n\t./svc // api server\n)\n");
+        write(&root, "svc/go.mod", "module example.com/svc\n\nrequire (\n\tgithub.com/lib/pq v1.10.9\n)\n");
+
+        let packages = detect_packages(&root).unwrap();
+        fs::remove_dir_all(&root).unwrap();
+
+        let summary: Vec<(&str, &str, PackageKind)> =
+            packages.iter().map(|p| (p.name.as_str(), p.path.as_str(), p.kind)).collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("cli", "crates/cli", PackageKind::Cargo),
+                ("core", "crates/core", PackageKind::Cargo),
+                ("example.com/svc", "svc", PackageKind::Go),
+                ("@acme/app", "web/app", PackageKind::Node),
+                ("@acme/ui", "web/ui", PackageKind::Node),
+            ]
+        );
+        assert_eq!(packages[0].dependencies, vec!["core"]);
+        assert_eq!(packages[3].dependencies, vec!["@acme/ui"]);
+        assert!(packages[2].dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_select() {
+        let package = |name: &str, path: &str, deps: &[&str]| Package {
+            name: name.to_string(),
+            path: path.to_string(),
+            kind: PackageKind::Node,
+            dependencies: deps.iter().map(|d| d.to_string()).collect(),
+        };
+        let packages = vec![
+            package("utils", "packages/utils", &[]),
+            package("api", "packages/api", &["utils"]),
+            package("web", "apps/web", &["api"]),
+            package("docs", "apps/docs", &[]),
+        ];
+        let names = |selected: Vec<Package>| selected.into_iter().map(|p| p.name).collect::<Vec<_>>();
+
+        let importers = PackageSelection { depends_on: Some("utils".to_string()), ..PackageSelection::default() };
+        assert_eq!(names(select(&packages, &importers, &[])), vec!["api", "web"]);
+
+        let affected = PackageSelection { affected_only: true, ..PackageSelection::default() };
+        let changed = vec!["packages/api/src/index.ts".to_string(), "README.md".to_string()];
+        assert_eq!(names(select(&packages, &affected, &changed)), vec!["api", "web"]);
+        assert_eq!(owner(&packages, "packages/api-v2/x.ts"), None);
+    }
+}
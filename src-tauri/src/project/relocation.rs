@@ -0,0 +1,380 @@
+//! Changing the projects base directory.
+//!
+//! New projects are created under the base directory; changing it leaves
+//! the existing ones behind unless they are relocated with it. Provides:
+//! - Checking a base directory path for the platform, including UNC paths
+//!   on Windows, and comparing paths on case-insensitive file systems
+//! - Planning where the projects inside the old base directory go, and
+//!   refusing the whole relocation if any of them can't go there, or if
+//!   an execution or agent is still working in one of them
+//! - Moving their directories, putting back the ones already moved when
+//!   one fails, or only re-pointing them when the user moved the files
+//! - Repairing the git worktree links a move breaks
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+use super::isolation::copy_tree;
+
+/// What happens to the projects inside the old base directory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RelocateMode {
+    /// Move their directories into the new base directory
+    Move,
+    /// Their directories were moved already; only update the stored paths
+    Repoint,
+}
+
+/// How the platform spells and compares paths
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PathStyle {
+    /// Drive letters, UNC paths and `\` separators
+    pub windows: bool,
+    /// Whether `Projects` and `projects` are the same directory, as on the
+    /// default file systems of Windows and macOS
+    pub case_insensitive: bool,
+}
+
+/// Device names Windows reserves in every directory
+const RESERVED_NAMES: &[&str] = &[
+    "con", "prn", "aux", "nul", "com1", "com2", "com3", "com4", "com5", "com6", "com7", "com8", "com9", "lpt1",
+    "lpt2", "lpt3", "lpt4", "lpt5", "lpt6", "lpt7", "lpt8", "lpt9",
+];
+
+impl PathStyle {
+    pub fn native() -> Self {
+        Self {
+            windows: cfg!(windows),
+            case_insensitive: cfg!(any(windows, target_os = "macos")),
+        }
+    }
+
+    fn components<'a>(&self, path: &'a str) -> Vec<&'a str> {
+        path.split(|c| c == '/' || (self.windows && c == '\\'))
+            .filter(|component| !component.is_empty() && *component != ".")
+            .collect()
+    }
+
+    fn component_eq(&self, a: &str, b: &str) -> bool {
+        if self.case_insensitive {
+            a.to_lowercase() == b.to_lowercase()
+        } else {
+            a == b
+        }
+    }
+
+    /// The components of `path` below `base`, if it is inside it
+    pub fn relative_to<'a>(&self, path: &'a str, base: &str) -> Option<Vec<&'a str>> {
+        let (path, base) = (self.components(path), self.components(base));
+        (path.len() >= base.len() && path.iter().zip(&base).all(|(a, b)| self.component_eq(a, b)))
+            .then(|| path[base.len()..].to_vec())
+    }
+
+    pub fn same(&self, a: &str, b: &str) -> bool {
+        self.relative_to(a, b).is_some_and(|rest| rest.is_empty())
+    }
+
+    /// Problems with `path` as a base directory
+    pub fn check(&self, path: &str) -> Result<(), String> {
+        if path.trim().is_empty() {
+            return Err("Path is empty".to_string());
+        }
+        if path.contains('\0') {
+            return Err("Path contains a NUL character".to_string());
+        }
+
+        let rest = if self.windows {
+            let bytes = path.as_bytes();
+            if let Some(unc) = path.strip_prefix(r"\\").or_else(|| path.strip_prefix("//")) {
+                if unc.starts_with('?') || unc.starts_with('.') {
+                    return Err(r"Device paths like \\?\ are not supported".to_string());
+                }
+                if self.components(unc).len() < 2 {
+                    return Err(r"UNC paths need a server and a share, like \\server\share\projects".to_string());
+                }
+                unc
+            } else if bytes.len() >= 3
+                && bytes[0].is_ascii_alphabetic()
+                && bytes[1] == b':'
+                && (bytes[2] == b'\\' || bytes[2] == b'/')
+            {
+                &path[3..]
+            } else {
+                return Err(r"Path must be absolute, like C:\projects or \\server\share\projects".to_string());
+            }
+        } else if let Some(rest) = path.strip_prefix('/') {
+            rest
+        } else {
+            return Err("Path must be absolute".to_string());
+        };
+
+        for component in self.components(rest) {
+            if component == ".." {
+                return Err("Path can't contain '..'".to_string());
+            }
+            if self.windows {
+                if let Some(c) = component.chars().find(|c| r#"<>:"|?*"#.contains(*c) || c.is_control()) {
+                    return Err(format!("'{}' contains '{}', which Windows doesn't allow", component, c));
+                }
+                let stem = component.split('.').next().unwrap_or_default().trim_end().to_lowercase();
+                if RESERVED_NAMES.contains(&stem.as_str()) {
+                    return Err(format!("'{}' is a reserved name on Windows", component));
+                }
+                if component.ends_with('.') || component.ends_with(' ') {
+                    return Err(format!("'{}' ends in a dot or space, which Windows drops", component));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check `path` as a base directory; it may not exist yet, but must not
+/// be a file
+pub fn check_base_dir(path: &str, style: PathStyle) -> Result<PathBuf, String> {
+    style.check(path)?;
+    let path = PathBuf::from(path);
+    if path.exists() && !path.is_dir() {
+        return Err(format!("{} is a file, not a directory", path.display()));
+    }
+    Ok(path)
+}
+
+/// A project directory moving from one base directory to another
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Relocation {
+    pub project_id: Uuid,
+    pub from: PathBuf,
+    pub to: PathBuf,
+}
+
+/// Where the projects inside `old_base` go under `new_base`; projects
+/// elsewhere stay where they are
+pub fn plan(projects: &[(Uuid, String)], old_base: &Path, new_base: &Path, style: PathStyle) -> Vec<Relocation> {
+    let old_base = old_base.to_string_lossy();
+    if style.same(&old_base, &new_base.to_string_lossy()) {
+        return Vec::new();
+    }
+    projects
+        .iter()
+        .filter_map(|(project_id, dir)| {
+            let rest = style.relative_to(dir, &old_base)?;
+            (!rest.is_empty()).then(|| Relocation {
+                project_id: *project_id,
+                from: PathBuf::from(dir),
+                to: rest.iter().fold(new_base.to_path_buf(), |path, component| path.join(component)),
+            })
+        })
+        .collect()
+}
+
+/// Check every relocation of `plan` can be done, before any is
+pub fn check_plan(plan: &[Relocation], mode: RelocateMode, style: PathStyle) -> Result<(), String> {
+    for relocation in plan {
+        let (from, to) = (&relocation.from, &relocation.to);
+        match mode {
+            RelocateMode::Move => {
+                if !from.is_dir() {
+                    return Err(format!("Project directory {} doesn't exist", from.display()));
+                }
+                if to.exists() {
+                    return Err(format!("{} already exists", to.display()));
+                }
+                if style.relative_to(&to.to_string_lossy(), &from.to_string_lossy()).is_some() {
+                    return Err(format!("Can't move {} into itself", from.display()));
+                }
+            }
+            RelocateMode::Repoint => {
+                if !to.is_dir() {
+                    return Err(format!("Project directory {} doesn't exist; move it there first", to.display()));
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Refuse to move projects that are in use; `busy` names the active
+/// executions and agents and the project each works in
+pub fn check_idle(plan: &[Relocation], busy: &[(Uuid, String)]) -> Result<(), String> {
+    let in_use: Vec<&str> = busy
+        .iter()
+        .filter(|(project_id, _)| plan.iter().any(|relocation| relocation.project_id == *project_id))
+        .map(|(_, what)| what.as_str())
+        .collect();
+    if in_use.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Can't move projects while work runs in them; stop or wait for {} first",
+            in_use.join(", ")
+        ))
+    }
+}
+
+/// Move a directory, copying it where a rename can't, e.g. to another drive
+fn move_dir(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    if let Err(e) = copy_tree(from, to) {
+        let _ = fs::remove_dir_all(to);
+        return Err(e);
+    }
+    fs::remove_dir_all(from)
+}
+
+/// Move the directories of `plan`; if one fails, the ones moved before it
+/// are moved back
+pub fn move_dirs(plan: &[Relocation]) -> Result<(), String> {
+    for (index, relocation) in plan.iter().enumerate() {
+        if let Err(e) = move_dir(&relocation.from, &relocation.to) {
+            undo_moves(&plan[..index]);
+            return Err(format!("Failed to move {}: {}", relocation.from.display(), e));
+        }
+        log::info!("Moved project directory {:?} to {:?}", relocation.from, relocation.to);
+    }
+    Ok(())
+}
+
+/// Move the directories of `plan` back
+pub fn undo_moves(plan: &[Relocation]) {
+    for relocation in plan.iter().rev() {
+        if let Err(e) = move_dir(&relocation.to, &relocation.from) {
+            log::error!("Failed to move {:?} back to {:?}: {}", relocation.to, relocation.from, e);
+        }
+    }
+}
+
+/// Where `path` is after `plan`
+fn relocated(plan: &[Relocation], path: &Path, style: PathStyle) -> PathBuf {
+    let path_str = path.to_string_lossy();
+    plan.iter()
+        .find_map(|relocation| {
+            let rest = style.relative_to(&path_str, &relocation.from.to_string_lossy())?;
+            Some(rest.iter().fold(relocation.to.clone(), |path, component| path.join(component)))
+        })
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// Reconnect git worktrees the relocation disconnected from their
+/// repository: repositories first, with their worktrees' new paths, then
+/// worktrees whose repository stayed. Returns what couldn't be repaired.
+pub fn repair_worktrees(plan: &[Relocation], style: PathStyle) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let mut repair = |dir: &Path, worktrees: &[PathBuf]| {
+        let output = Command::new("git").args(["worktree", "repair"]).args(worktrees).current_dir(dir).output();
+        match output {
+            Ok(output) if output.status.success() => {}
+            Ok(output) => warnings.push(format!(
+                "git worktree repair failed in {}: {}",
+                dir.display(),
+                String::from_utf8_lossy(&output.stderr).trim()
+            )),
+            Err(e) => warnings.push(format!("Couldn't run git in {}: {}", dir.display(), e)),
+        }
+    };
+
+    for relocation in plan {
+        let worktrees_dir = relocation.to.join(".git").join("worktrees");
+        let Ok(entries) = fs::read_dir(&worktrees_dir) else {
+            continue;
+        };
+        // Each worktree's `gitdir` file holds the path of its `.git` file
+        let worktrees: Vec<PathBuf> = entries
+            .flatten()
+            .filter_map(|entry| fs::read_to_string(entry.path().join("gitdir")).ok())
+            .filter_map(|gitdir| Path::new(gitdir.trim()).parent().map(Path::to_path_buf))
+            .map(|worktree| relocated(plan, &worktree, style))
+            .collect();
+        repair(&relocation.to, &worktrees);
+    }
+    for relocation in plan.iter().filter(|relocation| relocation.to.join(".git").is_file()) {
+        repair(&relocation.to, &[]);
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WINDOWS: PathStyle = PathStyle {
+        windows: true,
+        case_insensitive: true,
+    };
+    const UNIX: PathStyle = PathStyle {
+        windows: false,
+        case_insensitive: false,
+    };
+
+    #[test]
+    fn test_paths_per_platform() {
+        assert!(WINDOWS.check(r"C:\Users\dev\nexus-projects").is_ok());
+        assert!(WINDOWS.check(r"\\fileserver\team\projects").is_ok());
+        assert!(WINDOWS.check(r"\\fileserver").is_err());
+        assert!(WINDOWS.check(r"\\?\C:\projects").is_err());
+        assert!(WINDOWS.check(r"projects").is_err());
+        assert!(WINDOWS.check(r"C:\projects\aux").is_err());
+        assert!(WINDOWS.check(r"C:\projects\a|b").is_err());
+        assert!(UNIX.check("/home/dev/projects").is_ok());
+        assert!(UNIX.check("/home/dev/../projects").is_err());
+        assert!(UNIX.check("C:/projects").is_err());
+
+        assert!(WINDOWS.same(r"C:\Projects\", "c:/projects"));
+        assert!(!UNIX.same("/home/Projects", "/home/projects"));
+        assert_eq!(WINDOWS.relative_to(r"\\srv\share\Nexus\App", r"\\SRV\share\nexus"), Some(vec!["App"]));
+        assert_eq!(UNIX.relative_to("/home/projects-old/app", "/home/projects"), None);
+    }
+
+    #[test]
+    fn test_move_projects() {
+        let root = std::env::temp_dir().join(format!("nexus-relocation-{}", Uuid::new_v4()));
+        let (old_base, new_base) = (root.join("old"), root.join("new"));
+        fs::create_dir_all(old_base.join("app/src")).unwrap();
+        fs::write(old_base.join("app/src/main.rs"), "fn main() {}").unwrap();
+        fs::create_dir_all(old_base.join("lib")).unwrap();
+        let elsewhere = root.join("custom");
+        let projects = [
+            (Uuid::new_v4(), old_base.join("app").to_string_lossy().into_owned()),
+            (Uuid::new_v4(), old_base.join("lib").to_string_lossy().into_owned()),
+            (Uuid::new_v4(), elsewhere.to_string_lossy().into_owned()),
+        ];
+
+        let plan = plan(&projects, &old_base, &new_base, UNIX);
+        assert_eq!(plan.len(), 2);
+        assert_eq!(plan[0].to, new_base.join("app"));
+        assert!(check_plan(&plan, RelocateMode::Repoint, UNIX).is_err());
+
+        // One target taken: nothing moves
+        fs::create_dir_all(new_base.join("lib")).unwrap();
+        assert!(check_plan(&plan, RelocateMode::Move, UNIX).is_err());
+        fs::remove_dir(new_base.join("lib")).unwrap();
+
+        // An execution in a project that moves holds everything up
+        let busy = [
+            (projects[2].0, "agent reviewer".to_string()),
+            (projects[0].0, "execution 1234".to_string()),
+        ];
+        assert!(check_idle(&plan, &busy).unwrap_err().contains("execution 1234"));
+        assert!(check_idle(&plan, &busy[..1]).is_ok());
+
+        check_plan(&plan, RelocateMode::Move, UNIX).unwrap();
+        move_dirs(&plan).unwrap();
+        assert!(new_base.join("app/src/main.rs").is_file());
+        assert!(!old_base.join("app").exists());
+        assert!(check_plan(&plan, RelocateMode::Repoint, UNIX).is_ok());
+
+        undo_moves(&plan);
+        assert!(old_base.join("app/src/main.rs").is_file());
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
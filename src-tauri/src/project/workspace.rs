@@ -4,6 +4,8 @@ use std::path::{Path, PathBuf};
 use chrono::Utc;
 use thiserror::Error;
 
+use crate::settings::SETTINGS;
+
 #[derive(Debug, Error)]
 pub enum WorkspaceError {
     #[error("Failed to get home directory")]
@@ -26,11 +28,13 @@ pub struct ProjectWorkspace {
 /// Default base directory name for NEXUS projects
 const NEXUS_PROJECTS_DIR: &str = "nexus-projects";
 
-/// Get the base directory for all NEXUS projects
-/// Creates it if it doesn't exist
+/// Get the base directory for all NEXUS projects: the one in the settings,
+/// or `~/nexus-projects`. Creates it if it doesn't exist
 pub fn get_projects_base_dir() -> Result<PathBuf, WorkspaceError> {
-    let home = dirs::home_dir().ok_or(WorkspaceError::NoHomeDir)?;
-    let base_dir = home.join(NEXUS_PROJECTS_DIR);
+    let base_dir = match SETTINGS.get().projects.base_directory {
+        Some(dir) => PathBuf::from(dir),
+        None => dirs::home_dir().ok_or(WorkspaceError::NoHomeDir)?.join(NEXUS_PROJECTS_DIR),
+    };
 
     if !base_dir.exists() {
         fs::create_dir_all(&base_dir)?;
@@ -0,0 +1,18 @@
+//! Application settings.
+//!
+//! Typed settings saved as JSON in the app data directory, replacing
+//! scattered environment variables and hardcoded defaults. Provides:
+//! - The settings schema with defaults and validation
+//! - Partial updates that keep stored secrets the UI only sees redacted
+//! - Change notifications for code that reacts to new settings
+//! - Named secrets for template variables, resolved only for agent processes
+//! - Favorites and recently used items, in a file of their own
+
+mod schema;
+pub mod secrets;
+mod store;
+pub mod user_state;
+
+pub use schema::{ApiSettings, BackendSettings, NotificationSettings, ProjectSettings, RetentionSettings, Settings, DEFAULT_API_PORT, REDACTED};
+pub use store::{SettingsError, SettingsStore, SETTINGS};
+pub use user_state::{Favorite, ItemKind, RecentItem, SuggestedItem, USER_STATE};
@@ -0,0 +1,241 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::project::PathStyle;
+use crate::workflow::ResourceConfig;
+
+/// Shown in place of stored secrets; sending it back keeps the stored value
+pub const REDACTED: &str = "********";
+
+/// Default port of the local HTTP API
+pub const DEFAULT_API_PORT: u16 = 9999;
+
+/// All user-editable application settings
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub api: ApiSettings,
+    /// Defaults for the shared resource manager; applied at startup
+    pub resources: ResourceConfig,
+    pub backends: BackendSettings,
+    pub retention: RetentionSettings,
+    pub notifications: NotificationSettings,
+    pub projects: ProjectSettings,
+}
+
+/// Local HTTP API (OpenDeck/Stream Deck integration)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ApiSettings {
+    pub enabled: bool,
+    /// Preferred port; the next free port is used if it is taken.
+    /// `NEXUS_API_PORT` overrides it.
+    pub port: u16,
+}
+
+impl Default for ApiSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            port: DEFAULT_API_PORT,
+        }
+    }
+}
+
+/// Credentials and endpoints for agent backends and integrations. Unset
+/// values fall back to the matching environment variables.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BackendSettings {
+    /// `ANTHROPIC_API_KEY` for spawned agents
+    pub anthropic_api_key: Option<String>,
+    /// `MCP_SERVER_URL`
+    pub mcp_server_url: Option<String>,
+    /// `GITHUB_TOKEN`
+    pub github_token: Option<String>,
+    /// `JIRA_EMAIL`
+    pub jira_email: Option<String>,
+    /// `JIRA_API_TOKEN`
+    pub jira_api_token: Option<String>,
+    /// Named secrets for template variables of type secret
+    pub secrets: BTreeMap<String, String>,
+    /// Models the agent backend offers, checked against workflows' pinned
+    /// models; while empty, workflows that pin models don't start
+    pub available_models: Vec<String>,
+}
+
+impl BackendSettings {
+    fn secrets_mut(&mut self) -> [&mut Option<String>; 3] {
+        [&mut self.anthropic_api_key, &mut self.github_token, &mut self.jira_api_token]
+    }
+}
+
+/// Where projects live
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ProjectSettings {
+    /// Directory new projects are created in; `~/nexus-projects` when unset.
+    /// Change it with `set_projects_base_directory` to relocate projects too.
+    pub base_directory: Option<String>,
+}
+
+/// How long execution data is kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RetentionSettings {
+    /// Execution history records kept in memory
+    pub history_max_records: usize,
+    /// Checkpoints kept per execution by cleanup
+    pub checkpoints_per_execution: usize,
+    /// Agent messages older than this are dropped; `None` keeps them
+    pub message_ttl_hours: Option<u64>,
+}
+
+impl Default for RetentionSettings {
+    fn default() -> Self {
+        Self {
+            history_max_records: 1000,
+            checkpoints_per_execution: 3,
+            message_ttl_hours: None,
+        }
+    }
+}
+
+/// When and how the user is told about executions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NotificationSettings {
+    /// Show desktop notifications
+    pub desktop: bool,
+    pub on_completion: bool,
+    pub on_failure: bool,
+    /// Addresses that receive execution reports by email
+    pub email_recipients: Vec<String>,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            desktop: true,
+            on_completion: true,
+            on_failure: true,
+            email_recipients: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Every problem with these settings, as `field: message`
+    pub fn validate(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut check = |ok: bool, field: &str, message: &str| {
+            if !ok {
+                problems.push(format!("{}: {}", field, message));
+            }
+        };
+
+        check(self.api.port >= 1024, "api.port", "must be 1024 or higher");
+
+        let resources = &self.resources;
+        check(resources.max_concurrent_agents > 0, "resources.max_concurrent_agents", "must be at least 1");
+        check(resources.max_queue_size > 0, "resources.max_queue_size", "must be at least 1");
+        check(
+            resources.rate_limit_per_minute != Some(0),
+            "resources.rate_limit_per_minute",
+            "must be at least 1, or unset for no limit",
+        );
+        check(resources.rate_limit_burst != Some(0), "resources.rate_limit_burst", "must be at least 1, or unset");
+        for (backend, limit) in &resources.backend_rate_limits {
+            if let Err(message) = limit.validate() {
+                check(false, &format!("resources.backend_rate_limits.{}", backend), &message);
+            }
+        }
+        for limit in &resources.credential_rate_limits {
+            let field = format!("resources.credential_rate_limits.{}.{}", limit.backend, limit.credential);
+            check(!limit.backend.is_empty() && !limit.credential.is_empty(), &field, "needs a backend and a credential");
+            if let Err(message) = limit.limit.validate() {
+                check(false, &field, &message);
+            }
+        }
+        check(resources.acquire_timeout_ms > 0, "resources.acquire_timeout_ms", "must be greater than 0");
+        check(
+            resources.max_agents_per_role.values().all(|&max| max > 0),
+            "resources.max_agents_per_role",
+            "limits must be at least 1",
+        );
+
+        check(
+            self.backends.mcp_server_url.as_deref().map_or(true, |url| {
+                url.starts_with("http://") || url.starts_with("https://")
+            }),
+            "backends.mcp_server_url",
+            "must be an http:// or https:// URL",
+        );
+        check(
+            self.backends.jira_email.as_deref().map_or(true, |email| email.contains('@')),
+            "backends.jira_email",
+            "must be an email address",
+        );
+        check(
+            self.backends.secrets.keys().all(|name| super::secrets::is_valid_name(name)),
+            "backends.secrets",
+            "names may only contain letters, digits, '_', '.' and '-'",
+        );
+
+        check(self.retention.history_max_records > 0, "retention.history_max_records", "must be at least 1");
+        check(
+            self.retention.checkpoints_per_execution > 0,
+            "retention.checkpoints_per_execution",
+            "must be at least 1",
+        );
+        check(self.retention.message_ttl_hours != Some(0), "retention.message_ttl_hours", "must be at least 1, or unset");
+
+        if let Some(dir) = &self.projects.base_directory {
+            if let Err(message) = PathStyle::native().check(dir) {
+                check(false, "projects.base_directory", &message);
+            }
+        }
+
+        for email in &self.notifications.email_recipients {
+            check(
+                email.contains('@') && !email.contains(char::is_whitespace),
+                "notifications.email_recipients",
+                &format!("'{}' is not an email address", email),
+            );
+        }
+
+        problems
+    }
+
+    /// A copy with secrets replaced by [`REDACTED`], for the UI
+    pub fn redacted(&self) -> Settings {
+        let mut settings = self.clone();
+        for secret in settings.backends.secrets_mut() {
+            if secret.is_some() {
+                *secret = Some(REDACTED.to_string());
+            }
+        }
+        for value in settings.backends.secrets.values_mut() {
+            *value = REDACTED.to_string();
+        }
+        settings
+    }
+
+    /// Put back secrets that came in as [`REDACTED`]
+    pub(super) fn restore_secrets(&mut self, current: &Settings) {
+        let mut current = current.backends.clone();
+        for (secret, stored) in self.backends.secrets_mut().into_iter().zip(current.secrets_mut()) {
+            if secret.as_deref() == Some(REDACTED) {
+                *secret = stored.take();
+            }
+        }
+        for (name, value) in self.backends.secrets.iter_mut() {
+            if value == REDACTED {
+                if let Some(stored) = current.secrets.remove(name) {
+                    *value = stored;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Secrets referenced from agent tasks.
+//!
+//! A template variable of type secret puts a `{{secret:NAME}}` reference
+//! into the task instead of the value. The reference is resolved from the
+//! named secrets in the settings only when the prompt is written to the
+//! agent process, so the graph, the execution log and session records only
+//! ever hold the name. Provides:
+//! - Building and resolving references
+//! - Redacting secret values an agent echoed back before results are
+//!   persisted to history or checkpoints
+
+use std::collections::BTreeMap;
+
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::SETTINGS;
+
+/// Replaces secret values in persisted results
+pub const REDACTED_SECRET: &str = "[REDACTED:secret]";
+
+/// Shorter values are not redacted; they would match ordinary text
+const MIN_REDACTED_LEN: usize = 4;
+
+lazy_static! {
+    static ref SECRET_REF: Regex = Regex::new(r"\{\{secret:([A-Za-z0-9_.-]+)\}\}").unwrap();
+}
+
+/// Whether `name` can be used in a reference
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// The placeholder a task holds for the secret `name`
+pub fn reference(name: &str) -> String {
+    format!("{{{{secret:{}}}}}", name)
+}
+
+/// Names of the secrets `text` references
+pub fn referenced(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for caps in SECRET_REF.captures_iter(text) {
+        if !names.iter().any(|name| name == &caps[1]) {
+            names.push(caps[1].to_string());
+        }
+    }
+    names
+}
+
+/// `text` with its secret references replaced by the stored values
+pub fn resolve(text: &str) -> Result<String, String> {
+    resolve_with(text, &SETTINGS.get().backends.secrets)
+}
+
+fn resolve_with(text: &str, secrets: &BTreeMap<String, String>) -> Result<String, String> {
+    let missing: Vec<String> = referenced(text).into_iter().filter(|name| !secrets.contains_key(name)).collect();
+    if !missing.is_empty() {
+        return Err(format!("Secrets not set: {}", missing.join(", ")));
+    }
+    Ok(SECRET_REF
+        .replace_all(text, |caps: &Captures| secrets[&caps[1]].clone())
+        .into_owned())
+}
+
+/// A copy of `value` with every stored secret value in its strings
+/// replaced by [`REDACTED_SECRET`]
+pub fn redacted<T: Serialize + DeserializeOwned + Clone>(value: &T) -> T {
+    redacted_with(value, &SETTINGS.get().backends.secrets)
+}
+
+fn redacted_with<T: Serialize + DeserializeOwned + Clone>(value: &T, secrets: &BTreeMap<String, String>) -> T {
+    let values: Vec<&str> = secrets.values().map(String::as_str).collect();
+    redacted_values(value, &values)
+}
+
+/// A copy of `value` with each of `values` in its strings replaced by
+/// [`REDACTED_SECRET`], for secrets kept outside the settings
+pub fn redacted_values<T: Serialize + DeserializeOwned + Clone>(value: &T, values: &[&str]) -> T {
+    let mut values: Vec<&str> = values.iter().copied().filter(|value| value.len() >= MIN_REDACTED_LEN).collect();
+    if values.is_empty() {
+        return value.clone();
+    }
+    // Longest first, so a secret containing another is redacted whole
+    values.sort_by_key(|value| std::cmp::Reverse(value.len()));
+
+    let Ok(mut json) = serde_json::to_value(value) else {
+        return value.clone();
+    };
+    redact_json(&mut json, &values);
+    serde_json::from_value(json).unwrap_or_else(|_| value.clone())
+}
+
+fn redact_json(json: &mut Value, values: &[&str]) {
+    match json {
+        Value::String(text) => {
+            for value in values {
+                if text.contains(value) {
+                    *text = text.replace(value, REDACTED_SECRET);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| redact_json(item, values)),
+        Value::Object(map) => map.values_mut().for_each(|item| redact_json(item, values)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn secrets() -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("deploy_token".to_string(), "tok-12345".to_string()),
+            ("pin".to_string(), "42".to_string()),
+        ])
+    }
+
+    #[test]
+    fn test_resolve() {
+        let task = format!("Deploy with {} as the token", reference("deploy_token"));
+        assert_eq!(referenced(&task), vec!["deploy_token"]);
+        assert_eq!(resolve_with(&task, &secrets()).unwrap(), "Deploy with tok-12345 as the token");
+
+        let err = resolve_with("Use {{secret:missing}}", &secrets()).unwrap_err();
+        assert!(err.contains("missing"));
+        assert!(!is_valid_name("has space"));
+    }
+
+    #[test]
+    fn test_redacted() {
+        let outputs = HashMap::from([("deploy".to_string(), vec!["Logged in with tok-12345, answer 42".to_string()])]);
+        let redacted = redacted_with(&outputs, &secrets());
+        assert_eq!(redacted["deploy"][0], "Logged in with [REDACTED:secret], answer 42");
+    }
+}
@@ -0,0 +1,166 @@
+use parking_lot::RwLock;
+use serde_json::Value;
+use std::path::PathBuf;
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use super::schema::Settings;
+
+#[derive(Debug, Error)]
+pub enum SettingsError {
+    #[error("Invalid settings: {}", .0.join("; "))]
+    Invalid(Vec<String>),
+
+    #[error("Invalid settings JSON: {0}")]
+    Json(#[from] serde_json::Error),
+
+    #[error("Failed to save settings: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Settings loaded from and saved to a JSON file
+pub struct SettingsStore {
+    path: PathBuf,
+    current: RwLock<Settings>,
+    changes: broadcast::Sender<Settings>,
+}
+
+impl SettingsStore {
+    /// Load settings from `path`. A missing file gives the defaults; an
+    /// unreadable one is logged and replaced by the defaults on next save.
+    pub fn load(path: PathBuf) -> Self {
+        let settings = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid settings file {:?}: {}", path, e);
+                Settings::default()
+            }),
+            Err(_) => Settings::default(),
+        };
+
+        let (changes, _) = broadcast::channel(16);
+        Self {
+            path,
+            current: RwLock::new(settings),
+            changes,
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+            .join("settings.json")
+    }
+
+    pub fn get(&self) -> Settings {
+        self.current.read().clone()
+    }
+
+    /// Apply a partial update (JSON merge patch: objects merge, `null`
+    /// clears a value), validate and save. Returns the new settings.
+    pub fn update(&self, patch: &Value) -> Result<Settings, SettingsError> {
+        let mut current = self.current.write();
+
+        let mut merged = serde_json::to_value(&*current)?;
+        merge_patch(&mut merged, patch);
+        let mut updated: Settings = serde_json::from_value(merged)?;
+        updated.restore_secrets(&current);
+
+        let problems = updated.validate();
+        if !problems.is_empty() {
+            return Err(SettingsError::Invalid(problems));
+        }
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&updated)?)?;
+
+        *current = updated.clone();
+        let _ = self.changes.send(updated.clone());
+        Ok(updated)
+    }
+
+    /// Receive the new settings after every update
+    pub fn subscribe(&self) -> broadcast::Receiver<Settings> {
+        self.changes.subscribe()
+    }
+}
+
+/// RFC 7396 merge patch
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Default::default());
+    }
+    if let Value::Object(target) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                target.remove(key);
+            } else {
+                merge_patch(target.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+// Global settings, loaded on first use
+lazy_static::lazy_static! {
+    pub static ref SETTINGS: SettingsStore = SettingsStore::load(SettingsStore::default_path());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::REDACTED;
+    use serde_json::json;
+
+    fn temp_store() -> (SettingsStore, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("nexus-settings-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("settings.json");
+        (SettingsStore::load(path.clone()), dir)
+    }
+
+    #[test]
+    fn test_update_merges_validates_and_persists() {
+        let (store, dir) = temp_store();
+        let mut changes = store.subscribe();
+
+        let updated = store
+            .update(&json!({
+                "api": {"port": 8080},
+                "backends": {"github_token": "ghp_secret", "secrets": {"deploy": "tok-123"}},
+            }))
+            .unwrap();
+        assert_eq!(updated.api.port, 8080);
+        assert!(updated.api.enabled);
+        assert_eq!(changes.try_recv().unwrap().api.port, 8080);
+
+        // Invalid values are rejected and nothing changes
+        let err = store
+            .update(&json!({"api": {"port": 80}, "retention": {"history_max_records": 0}}))
+            .unwrap_err();
+        assert!(err.to_string().contains("api.port"));
+        assert!(err.to_string().contains("retention.history_max_records"));
+        assert_eq!(store.get().api.port, 8080);
+
+        // The UI sends back redacted secrets untouched
+        let redacted = store.get().redacted();
+        assert_eq!(redacted.backends.github_token.as_deref(), Some(REDACTED));
+        store.update(&serde_json::to_value(&redacted).unwrap()).unwrap();
+        assert_eq!(store.get().backends.github_token.as_deref(), Some("ghp_secret"));
+        assert_eq!(redacted.backends.secrets["deploy"], REDACTED);
+        assert_eq!(store.get().backends.secrets["deploy"], "tok-123");
+
+        // null clears an optional value
+        store.update(&json!({"backends": {"github_token": null}})).unwrap();
+        assert_eq!(store.get().backends.github_token, None);
+
+        let reloaded = SettingsStore::load(dir.join("settings.json"));
+        assert_eq!(reloaded.get(), store.get());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
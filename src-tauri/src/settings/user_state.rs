@@ -0,0 +1,312 @@
+//! Favorites and recently used items.
+//!
+//! Kept apart from the settings: it changes on every run and isn't
+//! something users edit. Provides:
+//! - Recently used workflows, templates and projects, with how often each
+//!   was used
+//! - Favorites pinned by the user, in the order they arranged them
+//! - Suggestions for pickers: favorites first, then the most used recent
+//!   items, weighted by how recently they were used
+
+use chrono::{DateTime, Duration, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Recent items kept per kind
+const MAX_RECENT: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemKind {
+    Workflow,
+    Template,
+    Project,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentItem {
+    pub kind: ItemKind,
+    pub id: String,
+    pub last_used_at: DateTime<Utc>,
+    pub use_count: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Favorite {
+    pub kind: ItemKind,
+    pub id: String,
+    pub pinned_at: DateTime<Utc>,
+}
+
+/// An item offered first in a picker
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SuggestedItem {
+    pub kind: ItemKind,
+    pub id: String,
+    pub favorite: bool,
+    pub use_count: u32,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+struct UserState {
+    /// Most recently used first
+    recent: Vec<RecentItem>,
+    /// In the user's order
+    favorites: Vec<Favorite>,
+}
+
+/// Uses of an item, counting recent ones more
+fn frecency(item: &RecentItem, now: DateTime<Utc>) -> f64 {
+    let age = now - item.last_used_at;
+    let weight = if age < Duration::days(1) {
+        4.0
+    } else if age < Duration::weeks(1) {
+        2.0
+    } else if age < Duration::days(30) {
+        1.0
+    } else {
+        0.5
+    };
+    item.use_count as f64 * weight
+}
+
+/// Favorites and recent items, saved to a JSON file on every change
+pub struct UserStateStore {
+    path: PathBuf,
+    state: RwLock<UserState>,
+}
+
+impl UserStateStore {
+    /// Load from `path`; a missing or unreadable file starts empty
+    pub fn load(path: PathBuf) -> Self {
+        let state = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid user state file {:?}: {}", path, e);
+                UserState::default()
+            }),
+            Err(_) => UserState::default(),
+        };
+        Self {
+            path,
+            state: RwLock::new(state),
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+            .join("user_state.json")
+    }
+
+    /// Change the state and save it; a failed save is logged, the change kept
+    fn update<T>(&self, change: impl FnOnce(&mut UserState) -> T) -> T {
+        let mut state = self.state.write();
+        let result = change(&mut state);
+        let saved = self
+            .path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&self.path, serde_json::to_string_pretty(&*state)?));
+        if let Err(e) = saved {
+            log::warn!("Failed to save user state to {:?}: {}", self.path, e);
+        }
+        result
+    }
+
+    /// Record that an item was just used
+    pub fn record_use(&self, kind: ItemKind, id: &str) {
+        self.record_use_at(kind, id, Utc::now());
+    }
+
+    fn record_use_at(&self, kind: ItemKind, id: &str, now: DateTime<Utc>) {
+        self.update(|state| {
+            let use_count = match state.recent.iter().position(|item| item.kind == kind && item.id == id) {
+                Some(index) => state.recent.remove(index).use_count + 1,
+                None => 1,
+            };
+            state.recent.insert(
+                0,
+                RecentItem {
+                    kind,
+                    id: id.to_string(),
+                    last_used_at: now,
+                    use_count,
+                },
+            );
+            // Drop the oldest of this kind past the limit
+            if let Some(index) = state.recent.iter().enumerate().filter(|(_, item)| item.kind == kind).nth(MAX_RECENT).map(|(index, _)| index) {
+                state.recent.remove(index);
+            }
+        });
+    }
+
+    /// Recently used items, most recent first
+    pub fn recent(&self, kind: Option<ItemKind>, limit: Option<usize>) -> Vec<RecentItem> {
+        self.state
+            .read()
+            .recent
+            .iter()
+            .filter(|item| kind.map_or(true, |kind| item.kind == kind))
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+
+    /// Clear recent items, of one kind or all
+    pub fn clear_recent(&self, kind: Option<ItemKind>) {
+        self.update(|state| state.recent.retain(|item| kind.is_some_and(|kind| item.kind != kind)));
+    }
+
+    /// Favorites in the user's order
+    pub fn favorites(&self, kind: Option<ItemKind>) -> Vec<Favorite> {
+        self.state
+            .read()
+            .favorites
+            .iter()
+            .filter(|favorite| kind.map_or(true, |kind| favorite.kind == kind))
+            .cloned()
+            .collect()
+    }
+
+    /// Pin an item at the end of the favorites; false if it was pinned
+    pub fn pin(&self, kind: ItemKind, id: &str) -> bool {
+        self.update(|state| {
+            if state.favorites.iter().any(|favorite| favorite.kind == kind && favorite.id == id) {
+                return false;
+            }
+            state.favorites.push(Favorite {
+                kind,
+                id: id.to_string(),
+                pinned_at: Utc::now(),
+            });
+            true
+        })
+    }
+
+    /// Unpin an item; false if it wasn't pinned
+    pub fn unpin(&self, kind: ItemKind, id: &str) -> bool {
+        self.update(|state| {
+            let before = state.favorites.len();
+            state.favorites.retain(|favorite| !(favorite.kind == kind && favorite.id == id));
+            state.favorites.len() != before
+        })
+    }
+
+    /// Reorder the favorites of a kind to `ids`; favorites left out keep
+    /// their order after the listed ones
+    pub fn reorder_favorites(&self, kind: ItemKind, ids: &[String]) -> Result<(), String> {
+        self.update(|state| {
+            if let Some(unknown) = ids.iter().find(|id| !state.favorites.iter().any(|f| f.kind == kind && &f.id == *id)) {
+                return Err(format!("'{}' is not a favorite", unknown));
+            }
+            let (mut ordered, others): (Vec<Favorite>, Vec<Favorite>) =
+                state.favorites.drain(..).partition(|favorite| favorite.kind == kind);
+            ordered.sort_by_key(|favorite| ids.iter().position(|id| *id == favorite.id).unwrap_or(ids.len()));
+            state.favorites = others.into_iter().chain(ordered).collect();
+            Ok(())
+        })
+    }
+
+    /// Forget a deleted item
+    pub fn forget(&self, kind: ItemKind, id: &str) {
+        self.update(|state| {
+            state.recent.retain(|item| !(item.kind == kind && item.id == id));
+            state.favorites.retain(|favorite| !(favorite.kind == kind && favorite.id == id));
+        });
+    }
+
+    /// Items of a kind to offer first: favorites in the user's order, then
+    /// recent items by how often and how recently they were used
+    pub fn suggested(&self, kind: ItemKind, limit: usize) -> Vec<SuggestedItem> {
+        self.suggested_at(kind, limit, Utc::now())
+    }
+
+    fn suggested_at(&self, kind: ItemKind, limit: usize, now: DateTime<Utc>) -> Vec<SuggestedItem> {
+        let state = self.state.read();
+        let recent = |id: &str| state.recent.iter().find(|item| item.kind == kind && item.id == id);
+
+        let mut suggested: Vec<SuggestedItem> = state
+            .favorites
+            .iter()
+            .filter(|favorite| favorite.kind == kind)
+            .map(|favorite| SuggestedItem {
+                kind,
+                id: favorite.id.clone(),
+                favorite: true,
+                use_count: recent(&favorite.id).map_or(0, |item| item.use_count),
+                last_used_at: recent(&favorite.id).map(|item| item.last_used_at),
+            })
+            .collect();
+
+        let mut used: Vec<&RecentItem> = state
+            .recent
+            .iter()
+            .filter(|item| item.kind == kind && !suggested.iter().any(|s| s.id == item.id))
+            .collect();
+        used.sort_by(|a, b| frecency(b, now).total_cmp(&frecency(a, now)).then(b.last_used_at.cmp(&a.last_used_at)));
+        suggested.extend(used.into_iter().map(|item| SuggestedItem {
+            kind,
+            id: item.id.clone(),
+            favorite: false,
+            use_count: item.use_count,
+            last_used_at: Some(item.last_used_at),
+        }));
+
+        suggested.truncate(limit);
+        suggested
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref USER_STATE: UserStateStore = UserStateStore::load(UserStateStore::default_path());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_favorites_and_recent() {
+        let path = std::env::temp_dir().join(format!("nexus-user-state-{}.json", Uuid::new_v4()));
+        let store = UserStateStore::load(path.clone());
+        let now = Utc::now();
+        let ids = |items: Vec<SuggestedItem>| items.into_iter().map(|item| item.id).collect::<Vec<_>>();
+
+        // Used often, but a month ago
+        for _ in 0..3 {
+            store.record_use_at(ItemKind::Workflow, "release", now - Duration::days(40));
+        }
+        store.record_use_at(ItemKind::Workflow, "review", now - Duration::hours(2));
+        store.record_use_at(ItemKind::Workflow, "review", now - Duration::hours(1));
+        store.record_use_at(ItemKind::Workflow, "lint", now);
+        store.record_use_at(ItemKind::Project, "shop", now);
+        assert_eq!(store.recent(Some(ItemKind::Workflow), None)[0].id, "lint");
+        assert_eq!(store.recent(None, Some(1))[0].kind, ItemKind::Project);
+        assert_eq!(ids(store.suggested_at(ItemKind::Workflow, 10, now)), ["review", "lint", "release"]);
+
+        assert!(store.pin(ItemKind::Workflow, "release"));
+        assert!(store.pin(ItemKind::Workflow, "deploy"));
+        assert!(!store.pin(ItemKind::Workflow, "deploy"));
+        assert_eq!(ids(store.suggested_at(ItemKind::Workflow, 3, now)), ["release", "deploy", "review"]);
+        store.reorder_favorites(ItemKind::Workflow, &["deploy".to_string()]).unwrap();
+        assert!(store.reorder_favorites(ItemKind::Workflow, &["lint".to_string()]).is_err());
+
+        // Saved on every change
+        let reloaded = UserStateStore::load(path.clone());
+        let favorites: Vec<String> = reloaded.favorites(None).into_iter().map(|favorite| favorite.id).collect();
+        assert_eq!(favorites, ["deploy", "release"]);
+        assert_eq!(reloaded.recent(Some(ItemKind::Workflow), None)[2].use_count, 3);
+
+        reloaded.forget(ItemKind::Workflow, "release");
+        assert!(reloaded.unpin(ItemKind::Workflow, "deploy"));
+        reloaded.clear_recent(Some(ItemKind::Workflow));
+        assert!(reloaded.favorites(None).is_empty());
+        assert_eq!(reloaded.recent(None, None).len(), 1);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
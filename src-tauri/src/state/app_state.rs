@@ -2,13 +2,17 @@ use crate::process::manager::AgentInfo;
 use dashmap::DashMap;
 use uuid::Uuid;
 
+#[cfg(feature = "database")]
+use parking_lot::RwLock;
 #[cfg(feature = "database")]
 use sqlx::PgPool;
 
 pub struct AppState {
     pub agents: DashMap<Uuid, AgentInfo>,
+    /// Set at startup, or later by the health monitor once the database
+    /// can be reached
     #[cfg(feature = "database")]
-    pub db_pool: Option<PgPool>,
+    pub db_pool: RwLock<Option<PgPool>>,
 }
 
 impl AppState {
@@ -16,7 +20,7 @@ impl AppState {
         Self {
             agents: DashMap::new(),
             #[cfg(feature = "database")]
-            db_pool: None,
+            db_pool: RwLock::new(None),
         }
     }
 
@@ -24,14 +28,14 @@ impl AppState {
     pub fn with_database(pool: PgPool) -> Self {
         Self {
             agents: DashMap::new(),
-            db_pool: Some(pool),
+            db_pool: RwLock::new(Some(pool)),
         }
     }
 
     pub fn has_db(&self) -> bool {
         #[cfg(feature = "database")]
         {
-            self.db_pool.is_some()
+            self.db_pool.read().is_some()
         }
         #[cfg(not(feature = "database"))]
         {
@@ -40,8 +44,14 @@ impl AppState {
     }
 
     #[cfg(feature = "database")]
-    pub fn get_pool(&self) -> Option<&PgPool> {
-        self.db_pool.as_ref()
+    pub fn get_pool(&self) -> Option<PgPool> {
+        self.db_pool.read().clone()
+    }
+
+    /// Switch from offline to online mode
+    #[cfg(feature = "database")]
+    pub fn set_pool(&self, pool: PgPool) {
+        *self.db_pool.write() = Some(pool);
     }
 }
 
@@ -0,0 +1,477 @@
+//! Per-node activity feed.
+//!
+//! Records what each agent did so users can audit what it changed and ran.
+//! Provides:
+//! - A Claude Code hook that appends every shell command and file edit an
+//!   agent makes to a per-agent log
+//! - File changes found by diffing working tree snapshots around a node,
+//!   which also catches edits made by scripts and shell commands
+//! - The files a node's own agents wrote, for telling apart the edits of
+//!   nodes running in parallel in the same tree
+//! - A store of the feed per execution and node, keeping the latest
+//!   `MAX_ENTRIES_PER_EXECUTION` entries of each execution
+
+use chrono::{DateTime, TimeZone, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+use uuid::Uuid;
+
+use super::conflicts::{self, FileSnapshot};
+
+/// Tools whose calls the activity hook logs
+const HOOKED_TOOLS: &str = "Bash|Write|Edit|MultiEdit|NotebookEdit";
+
+pub const MAX_ENTRIES_PER_EXECUTION: usize = 10_000;
+
+/// How a file changed while a node ran
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChange {
+    Created,
+    Modified,
+    Deleted,
+}
+
+/// Something an agent did
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Activity {
+    /// A shell command the agent ran
+    Command {
+        command: String,
+        description: Option<String>,
+    },
+    /// A file the agent wrote with one of its editing tools
+    FileWrite { path: String, tool: String },
+    /// A file that differs between the snapshots taken before and after the
+    /// node. Nodes running in parallel in the same directory can see each
+    /// other's changes.
+    FileChange { path: String, change: FileChange },
+}
+
+/// One item of a node's activity feed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActivityEntry {
+    pub timestamp: DateTime<Utc>,
+    /// The agent that did it; unknown for snapshot diffs
+    pub agent_id: Option<Uuid>,
+    #[serde(flatten)]
+    pub activity: Activity,
+}
+
+/// Where the hook logs an agent's tool calls
+pub fn hook_log_path(agent_id: &Uuid) -> PathBuf {
+    std::env::temp_dir().join("nexus-activity").join(format!("{}.log", agent_id))
+}
+
+/// Whether agents get the activity hook; it is a shell command
+pub fn hooks_supported() -> bool {
+    cfg!(unix)
+}
+
+/// `--settings` JSON for an agent with a `PostToolUse` hook that appends each
+/// hooked tool call to its log as `<unix time> <hook input JSON>`
+pub fn hook_settings(agent_id: &Uuid) -> Option<String> {
+    if !hooks_supported() {
+        return None;
+    }
+
+    let path = hook_log_path(agent_id);
+    std::fs::create_dir_all(path.parent()?).ok()?;
+    let quoted = format!("'{}'", path.to_string_lossy().replace('\'', r"'\''"));
+    let command = format!("{{ printf '%s ' \"$(date +%s)\"; cat; echo; }} >> {}", quoted);
+
+    let settings = serde_json::json!({
+        "hooks": {
+            "PostToolUse": [{
+                "matcher": HOOKED_TOOLS,
+                "hooks": [{ "type": "command", "command": command }],
+            }],
+        },
+    });
+    Some(settings.to_string())
+}
+
+/// Entries from an agent's hook log
+pub fn parse_hook_log(log: &str, agent_id: Uuid) -> Vec<ActivityEntry> {
+    let mut entries = Vec::new();
+    let mut rest = log;
+
+    while let Some((stamp, tail)) = rest.trim_start().split_once(' ') {
+        // The hook input may span lines, so read exactly one JSON value
+        let mut values = serde_json::Deserializer::from_str(tail).into_iter::<Value>();
+        let Some(Ok(input)) = values.next() else {
+            break;
+        };
+        rest = &tail[values.byte_offset()..];
+
+        let timestamp = stamp
+            .parse()
+            .ok()
+            .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+            .unwrap_or_else(Utc::now);
+        if let Some(activity) = tool_activity(&input) {
+            entries.push(ActivityEntry {
+                timestamp,
+                agent_id: Some(agent_id),
+                activity,
+            });
+        }
+    }
+
+    entries
+}
+
+/// What a hooked tool call did, with paths relative to the agent's directory
+fn tool_activity(input: &Value) -> Option<Activity> {
+    let tool = input["tool_name"].as_str()?;
+    let tool_input = &input["tool_input"];
+
+    if tool == "Bash" {
+        return Some(Activity::Command {
+            command: tool_input["command"].as_str()?.to_string(),
+            description: tool_input["description"].as_str().map(str::to_string),
+        });
+    }
+
+    let path = tool_input["file_path"].as_str().or(tool_input["notebook_path"].as_str())?;
+    let path = input["cwd"]
+        .as_str()
+        .and_then(|cwd| Path::new(path).strip_prefix(cwd).ok())
+        .map_or_else(|| path.to_string(), |relative| relative.to_string_lossy().into_owned());
+    Some(Activity::FileWrite {
+        path,
+        tool: tool.to_string(),
+    })
+}
+
+/// Files changed between two snapshots of `dir`, with how they changed
+pub async fn file_changes(dir: &Path, before: &FileSnapshot, after: &FileSnapshot) -> Vec<(String, FileChange)> {
+    let changed = conflicts::changed_files(before, after);
+
+    // Files missing from the first snapshot were either clean or didn't exist
+    let unseen: Vec<&String> = changed.iter().filter(|path| !before.contains_key(*path)).collect();
+    let tracked = tracked_files(dir, &unseen).await;
+
+    changed
+        .iter()
+        .map(|path| {
+            let existed = match before.get(path) {
+                Some(hash) => hash.is_some(),
+                None => tracked.contains(path),
+            };
+            let change = match (existed, dir.join(path).exists()) {
+                (_, false) => FileChange::Deleted,
+                (false, true) => FileChange::Created,
+                (true, true) => FileChange::Modified,
+            };
+            (path.clone(), change)
+        })
+        .collect()
+}
+
+/// Which of `paths` git tracks in the repository at `dir`
+async fn tracked_files(dir: &Path, paths: &[&String]) -> HashSet<String> {
+    if paths.is_empty() {
+        return HashSet::new();
+    }
+
+    let output = Command::new("git")
+        .args(["ls-files", "-z", "--"])
+        .args(paths)
+        .current_dir(dir)
+        .output()
+        .await;
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .split('\0')
+            .filter(|path| !path.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => HashSet::new(),
+    }
+}
+
+/// Activity feeds keyed by execution and node
+pub struct ActivityStore {
+    feeds: DashMap<(Uuid, String), Vec<ActivityEntry>>,
+}
+
+impl ActivityStore {
+    pub fn new() -> Self {
+        Self {
+            feeds: DashMap::new(),
+        }
+    }
+
+    /// Add entries to a node's feed, keeping it in time order
+    pub fn record(&self, execution_id: Uuid, node_id: &str, entries: Vec<ActivityEntry>) {
+        if entries.is_empty() {
+            return;
+        }
+        {
+            let mut feed = self.feeds.entry((execution_id, node_id.to_string())).or_default();
+            feed.extend(entries);
+            feed.sort_by_key(|entry| entry.timestamp);
+        }
+        self.trim(execution_id);
+    }
+
+    /// Drop an execution's oldest entries, from whichever nodes have them,
+    /// until it is within `MAX_ENTRIES_PER_EXECUTION`
+    fn trim(&self, execution_id: Uuid) {
+        let total: usize = self
+            .feeds
+            .iter()
+            .filter(|feed| feed.key().0 == execution_id)
+            .map(|feed| feed.len())
+            .sum();
+        for _ in MAX_ENTRIES_PER_EXECUTION..total {
+            let oldest = self
+                .feeds
+                .iter()
+                .filter(|feed| feed.key().0 == execution_id)
+                .filter_map(|feed| Some((feed.first()?.timestamp, feed.key().clone())))
+                .min()
+                .map(|(_, key)| key);
+            match oldest.and_then(|key| self.feeds.get_mut(&key)) {
+                Some(mut feed) => {
+                    feed.remove(0);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Move an agent's hook log into the feed of the node it ran for
+    pub fn record_agent_log(&self, execution_id: Uuid, node_id: &str, agent_id: Uuid) {
+        let path = hook_log_path(&agent_id);
+        let Ok(log) = std::fs::read_to_string(&path) else {
+            return;
+        };
+        let _ = std::fs::remove_file(&path);
+        self.record(execution_id, node_id, parse_hook_log(&log, agent_id));
+    }
+
+    /// Add the files that changed in `dir` between two snapshots
+    pub async fn record_file_changes(
+        &self,
+        execution_id: Uuid,
+        node_id: &str,
+        dir: &Path,
+        before: &FileSnapshot,
+        after: &FileSnapshot,
+    ) {
+        let entries = file_changes(dir, before, after)
+            .await
+            .into_iter()
+            .map(|(path, change)| {
+                let modified = std::fs::metadata(dir.join(&path)).and_then(|meta| meta.modified()).ok();
+                ActivityEntry {
+                    timestamp: modified.map_or_else(Utc::now, DateTime::<Utc>::from),
+                    agent_id: None,
+                    activity: Activity::FileChange { path, change },
+                }
+            })
+            .collect();
+        self.record(execution_id, node_id, entries);
+    }
+
+    /// Files written or changed during an execution, with the nodes that
+    /// touched each
+    pub fn touched_files(&self, execution_id: Uuid) -> BTreeMap<String, Vec<String>> {
+        let mut touched: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        for feed in self.feeds.iter().filter(|feed| feed.key().0 == execution_id) {
+            let node_id = &feed.key().1;
+            for entry in feed.value() {
+                let (Activity::FileWrite { path, .. } | Activity::FileChange { path, .. }) = &entry.activity else {
+                    continue;
+                };
+                let nodes = touched.entry(path.clone()).or_default();
+                if !nodes.contains(node_id) {
+                    nodes.push(node_id.clone());
+                }
+            }
+        }
+        touched
+    }
+
+    /// Files a node's agents wrote with their editing tools, as their hooks
+    /// logged them. Unlike the snapshot diffs in its feed, these leave out
+    /// what other nodes changed while it ran.
+    pub fn agent_writes(&self, execution_id: Uuid, node_id: &str) -> BTreeSet<String> {
+        self.get(execution_id, node_id)
+            .into_iter()
+            .filter_map(|entry| match entry.activity {
+                Activity::FileWrite { path, .. } if entry.agent_id.is_some() => Some(path),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Drop every feed of an execution
+    pub fn remove_execution(&self, execution_id: &Uuid) {
+        self.feeds.retain(|(id, _), _| id != execution_id);
+    }
+
+    /// A node's feed, oldest first
+    pub fn get(&self, execution_id: Uuid, node_id: &str) -> Vec<ActivityEntry> {
+        self.feeds
+            .get(&(execution_id, node_id.to_string()))
+            .map(|feed| feed.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl Default for ActivityStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Global activity store
+lazy_static::lazy_static! {
+    pub static ref ACTIVITY_STORE: ActivityStore = ActivityStore::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hook_log() {
+        let agent_id = Uuid::new_v4();
+        let log = concat!(
+            r#"1700000000 {"cwd":"/repo","tool_name":"Bash","tool_input":{"command":"rm -rf build","description":"Clean"}}"#,
+            "\n",
+            "1700000005 {\"cwd\":\"/repo\",\n\"tool_name\":\"Edit\",\"tool_input\":{\"file_path\":\"/repo/src/main.rs\"}}\n",
+            r#"1700000009 {"cwd":"/repo","tool_name":"Write","tool_input":{"file_path":"/tmp/notes.md"}}"#,
+            "\n",
+            "1700000010 {\"tool_name\":\"Bash\"",
+        );
+
+        let entries = parse_hook_log(log, agent_id);
+        let activities: Vec<&Activity> = entries.iter().map(|e| &e.activity).collect();
+        assert_eq!(
+            activities,
+            vec![
+                &Activity::Command {
+                    command: "rm -rf build".to_string(),
+                    description: Some("Clean".to_string()),
+                },
+                &Activity::FileWrite {
+                    path: "src/main.rs".to_string(),
+                    tool: "Edit".to_string(),
+                },
+                // Outside the working directory stays absolute
+                &Activity::FileWrite {
+                    path: "/tmp/notes.md".to_string(),
+                    tool: "Write".to_string(),
+                },
+            ]
+        );
+        assert_eq!(entries[0].timestamp.timestamp(), 1_700_000_000);
+        assert_eq!(entries[0].agent_id, Some(agent_id));
+    }
+
+    #[test]
+    fn test_feeds_are_capped_per_execution() {
+        let store = ActivityStore::new();
+        let execution_id = Uuid::new_v4();
+        let start = Utc::now();
+        let commands = |from: usize, count: usize| -> Vec<ActivityEntry> {
+            (from..from + count)
+                .map(|i| ActivityEntry {
+                    timestamp: start + chrono::Duration::seconds(i as i64),
+                    agent_id: None,
+                    activity: Activity::Command {
+                        command: i.to_string(),
+                        description: None,
+                    },
+                })
+                .collect()
+        };
+
+        // The oldest entries go, whichever node recorded them
+        store.record(execution_id, "build", commands(0, 10));
+        store.record(execution_id, "test", commands(10, MAX_ENTRIES_PER_EXECUTION - 5));
+        store.record(Uuid::new_v4(), "build", commands(0, 10));
+
+        let build = store.get(execution_id, "build");
+        assert_eq!(build.len(), 5);
+        assert_eq!(build[0].activity, commands(5, 1)[0].activity);
+        assert_eq!(build.len() + store.get(execution_id, "test").len(), MAX_ENTRIES_PER_EXECUTION);
+    }
+
+    #[test]
+    fn test_agent_writes() {
+        let store = ActivityStore::new();
+        let (execution_id, agent_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let entry = |agent_id: Option<Uuid>, activity: Activity| ActivityEntry {
+            timestamp: Utc::now(),
+            agent_id,
+            activity,
+        };
+        let write = |path: &str| Activity::FileWrite {
+            path: path.to_string(),
+            tool: "Edit".to_string(),
+        };
+        store.record(
+            execution_id,
+            "api",
+            vec![
+                entry(Some(agent_id), write("src/api.rs")),
+                entry(Some(agent_id), write("src/lib.rs")),
+                // Changed by a node running alongside, seen in the snapshots
+                entry(
+                    None,
+                    Activity::FileChange {
+                        path: "src/ui.rs".to_string(),
+                        change: FileChange::Modified,
+                    },
+                ),
+            ],
+        );
+
+        let writes: Vec<String> = store.agent_writes(execution_id, "api").into_iter().collect();
+        assert_eq!(writes, ["src/api.rs", "src/lib.rs"]);
+        assert!(store.agent_writes(execution_id, "ui").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_file_changes() {
+        let dir = std::env::temp_dir().join(format!("nexus-activity-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let git = |args: &[&str]| {
+            std::process::Command::new("git").args(args).current_dir(&dir).output().unwrap();
+        };
+        git(&["init", "-q"]);
+        std::fs::write(dir.join("clean.txt"), "a").unwrap();
+        std::fs::write(dir.join("gone.txt"), "b").unwrap();
+        git(&["add", "."]);
+        git(&["-c", "user.name=t", "-c", "user.email=t@t", "commit", "-qm", "init"]);
+        std::fs::write(dir.join("dirty.txt"), "untracked").unwrap();
+
+        let before = conflicts::snapshot(&dir).await.unwrap();
+        std::fs::write(dir.join("clean.txt"), "changed").unwrap();
+        std::fs::remove_file(dir.join("gone.txt")).unwrap();
+        std::fs::write(dir.join("new.txt"), "new").unwrap();
+        std::fs::write(dir.join("dirty.txt"), "edited again").unwrap();
+        let after = conflicts::snapshot(&dir).await.unwrap();
+
+        assert_eq!(
+            file_changes(&dir, &before, &after).await,
+            vec![
+                ("clean.txt".to_string(), FileChange::Modified),
+                ("dirty.txt".to_string(), FileChange::Modified),
+                ("gone.txt".to_string(), FileChange::Deleted),
+                ("new.txt".to_string(), FileChange::Created),
+            ]
+        );
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
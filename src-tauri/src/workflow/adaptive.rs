@@ -9,11 +9,12 @@
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use uuid::Uuid;
 
 use super::context::AgentOutput;
 use super::orchestrator::PlannedTask;
+use super::text;
 
 /// Configuration for adaptive planning behavior
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -248,11 +249,7 @@ pub fn build_replan_context(
         context.push_str(&format!(
             "--- {} ---\n{}\n\n",
             task_id,
-            if output.len() > 500 {
-                format!("{}...", &output[..500])
-            } else {
-                output.clone()
-            }
+            text::truncate(output, 500, "...")
         ));
     }
 
@@ -444,40 +441,40 @@ pub fn validate_modifications(
     let mut tasks = tasks.to_vec();
     apply_modifications(&mut tasks, modifications);
 
-    // Check for cycles using DFS
-    let mut visited: HashMap<String, bool> = HashMap::new();
-    let mut rec_stack: HashMap<String, bool> = HashMap::new();
-
-    fn has_cycle(
-        task_id: &str,
-        tasks: &[PlannedTask],
-        visited: &mut HashMap<String, bool>,
-        rec_stack: &mut HashMap<String, bool>,
+    // Check for cycles using DFS, looking tasks up by ID so large plans
+    // stay linear
+    let by_id: HashMap<&str, &PlannedTask> = tasks.iter().map(|t| (t.id.as_str(), t)).collect();
+    let mut visited: HashSet<&str> = HashSet::new();
+    let mut rec_stack: HashSet<&str> = HashSet::new();
+
+    fn has_cycle<'a>(
+        task_id: &'a str,
+        by_id: &HashMap<&'a str, &'a PlannedTask>,
+        visited: &mut HashSet<&'a str>,
+        rec_stack: &mut HashSet<&'a str>,
     ) -> bool {
-        visited.insert(task_id.to_string(), true);
-        rec_stack.insert(task_id.to_string(), true);
+        visited.insert(task_id);
+        rec_stack.insert(task_id);
 
-        if let Some(task) = tasks.iter().find(|t| t.id == task_id) {
+        if let Some(&task) = by_id.get(task_id) {
             for dep in &task.depends_on {
-                if !visited.get(dep).copied().unwrap_or(false) {
-                    if has_cycle(dep, tasks, visited, rec_stack) {
+                if !visited.contains(dep.as_str()) {
+                    if has_cycle(dep, by_id, visited, rec_stack) {
                         return true;
                     }
-                } else if rec_stack.get(dep).copied().unwrap_or(false) {
+                } else if rec_stack.contains(dep.as_str()) {
                     return true;
                 }
             }
         }
 
-        rec_stack.insert(task_id.to_string(), false);
+        rec_stack.remove(task_id);
         false
     }
 
     for task in &tasks {
-        if !visited.get(&task.id).copied().unwrap_or(false) {
-            if has_cycle(&task.id, &tasks, &mut visited, &mut rec_stack) {
-                return Err("Modifications would create a cycle in the workflow".to_string());
-            }
+        if !visited.contains(task.id.as_str()) && has_cycle(&task.id, &by_id, &mut visited, &mut rec_stack) {
+            return Err("Modifications would create a cycle in the workflow".to_string());
         }
     }
 
@@ -487,6 +484,7 @@ pub fn validate_modifications(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::workflow::context::OutputData;
 
     fn create_test_tasks() -> Vec<PlannedTask> {
         vec![
@@ -7,6 +7,8 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use super::context::{AgentOutput, OutputData};
+use super::plugins::{PluginError, PLUGIN_REGISTRY};
+use super::text;
 
 /// Strategy for aggregating outputs from multiple predecessor nodes
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -57,6 +59,13 @@ pub enum AggregationStrategy {
 
     /// Structured summary with metadata
     StructuredSummary,
+
+    /// Delegate to a registered WASM aggregator plugin
+    Plugin {
+        name: String,
+        #[serde(default)]
+        params: serde_json::Value,
+    },
 }
 
 impl Default for AggregationStrategy {
@@ -266,11 +275,7 @@ impl AggregationStrategy {
             AggregationStrategy::StructuredSummary => {
                 let output_summaries: Vec<serde_json::Value> = outputs.iter().map(|o| {
                     let content = o.data.to_context_string();
-                    let preview = if content.len() > 200 {
-                        format!("{}...", &content[..200])
-                    } else {
-                        content.clone()
-                    };
+                    let preview = text::truncate(&content, 200, "...");
                     serde_json::json!({
                         "node_id": o.node_id.clone(),
                         "agent_role": o.agent_role.clone(),
@@ -288,6 +293,14 @@ impl AggregationStrategy {
 
                 OutputData::Json(summary)
             }
+
+            AggregationStrategy::Plugin { name, params } => {
+                let input = serde_json::json!({
+                    "outputs": outputs,
+                    "params": params,
+                });
+                plugin_output_data(name, PLUGIN_REGISTRY.call_aggregate(name, &input))
+            }
         };
 
         AggregatedOutput {
@@ -345,7 +358,7 @@ impl AggregatedOutput {
 }
 
 /// Configuration for a node's input aggregation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct NodeAggregationConfig {
     /// The aggregation strategy to use
     pub strategy: AggregationStrategy,
@@ -359,18 +372,91 @@ pub struct NodeAggregationConfig {
     pub transform: Option<OutputTransform>,
 }
 
-impl Default for NodeAggregationConfig {
-    fn default() -> Self {
-        Self {
-            strategy: AggregationStrategy::default(),
-            filter_tags: None,
-            only_from: None,
-            exclude_from: None,
-            transform: None,
+impl NodeAggregationConfig {
+    /// Outputs this node consumes: from allowed predecessors, and carrying
+    /// at least one of `filter_tags` when set
+    pub fn select(&self, outputs: Vec<AgentOutput>) -> Vec<AgentOutput> {
+        outputs
+            .into_iter()
+            .filter(|o| self.only_from.as_ref().map_or(true, |ids| ids.contains(&o.node_id)))
+            .filter(|o| !self.exclude_from.as_ref().is_some_and(|ids| ids.contains(&o.node_id)))
+            .filter(|o| self.filter_tags.as_ref().map_or(true, |tags| tags.iter().any(|t| o.has_tag(t))))
+            .collect()
+    }
+
+    /// Select, aggregate and transform; `None` when nothing matched
+    pub fn apply(&self, outputs: Vec<AgentOutput>) -> Option<AggregatedOutput> {
+        let selected = self.select(outputs);
+        if selected.is_empty() {
+            return None;
+        }
+
+        let mut aggregated = self.strategy.aggregate(&selected);
+        if let Some(transform) = &self.transform {
+            aggregated.data = transform.apply(&aggregated.data);
+        }
+        Some(aggregated)
+    }
+
+    /// Run on hand-written outputs instead of agents' ones, so a strategy
+    /// or template can be tried out before a workflow runs it
+    pub fn preview(&self, samples: &[SampleOutput]) -> AggregationPreview {
+        // Fixed timestamps, a second apart, keep previews reproducible
+        let outputs: Vec<AgentOutput> = samples
+            .iter()
+            .enumerate()
+            .map(|(i, sample)| sample.to_agent_output(chrono::DateTime::from_timestamp(i as i64, 0).unwrap_or_default()))
+            .collect();
+        let selected = self.select(outputs.clone()).into_iter().map(|o| o.node_id).collect();
+        let output = self.apply(outputs);
+        AggregationPreview {
+            selected,
+            prompt_context: output.as_ref().map(|o| o.to_prompt_context()),
+            output,
+        }
+    }
+}
+
+/// A predecessor's output written by hand, for previews
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SampleOutput {
+    pub node_id: String,
+    /// Defaults to the node ID
+    #[serde(default)]
+    pub agent_role: Option<String>,
+    /// A string is taken as text, anything else as JSON
+    pub content: serde_json::Value,
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl SampleOutput {
+    fn to_agent_output(&self, timestamp: chrono::DateTime<chrono::Utc>) -> AgentOutput {
+        AgentOutput {
+            agent_id: uuid::Uuid::nil(),
+            node_id: self.node_id.clone(),
+            agent_role: self.agent_role.clone().unwrap_or_else(|| self.node_id.clone()),
+            data: match &self.content {
+                serde_json::Value::String(text) => OutputData::Text(text.clone()),
+                other => OutputData::Json(other.clone()),
+            },
+            timestamp,
+            tags: self.tags.clone(),
         }
     }
 }
 
+/// What a node would receive from the sample outputs
+#[derive(Debug, Clone, Serialize)]
+pub struct AggregationPreview {
+    /// Samples left after the filters, in order
+    pub selected: Vec<String>,
+    /// `None` when the filters left nothing
+    pub output: Option<AggregatedOutput>,
+    /// The text that goes into the node's prompt
+    pub prompt_context: Option<String>,
+}
+
 /// Transformation to apply after aggregation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
@@ -383,6 +469,12 @@ pub enum OutputTransform {
     Template { template: String },
     /// Truncate to max length
     Truncate { max_length: usize, suffix: String },
+    /// Delegate to a registered WASM transform plugin
+    Plugin {
+        name: String,
+        #[serde(default)]
+        params: serde_json::Value,
+    },
 }
 
 impl OutputTransform {
@@ -427,16 +519,38 @@ impl OutputTransform {
 
             OutputTransform::Truncate { max_length, suffix } => {
                 let content = data.to_context_string();
-                if content.len() > *max_length {
-                    OutputData::Text(format!("{}{}", &content[..*max_length], suffix))
+                if text::grapheme_count(&content) > *max_length {
+                    OutputData::Text(text::truncate(&content, *max_length, suffix))
                 } else {
                     data.clone()
                 }
             }
+
+            OutputTransform::Plugin { name, params } => {
+                let input = serde_json::json!({
+                    "data": data,
+                    "params": params,
+                });
+                plugin_output_data(name, PLUGIN_REGISTRY.call_transform(name, &input))
+            }
         }
     }
 }
 
+/// Interpret plugin output: tagged `OutputData` JSON is used as-is,
+/// any other JSON becomes `Json`, everything else `Text`.
+fn plugin_output_data(name: &str, result: Result<String, PluginError>) -> OutputData {
+    match result {
+        Ok(raw) => serde_json::from_str::<OutputData>(&raw)
+            .or_else(|_| serde_json::from_str::<serde_json::Value>(&raw).map(OutputData::Json))
+            .unwrap_or(OutputData::Text(raw)),
+        Err(e) => OutputData::Error {
+            message: format!("Plugin '{}' failed", name),
+            details: Some(e.to_string()),
+        },
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -493,6 +607,36 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_node_config_filters_by_tag_and_source() {
+        let config = NodeAggregationConfig {
+            filter_tags: Some(vec!["security".to_string()]),
+            ..Default::default()
+        };
+        let selected = config.select(create_test_outputs());
+        assert_eq!(selected.len(), 1);
+        assert_eq!(selected[0].node_id, "security");
+
+        let config = NodeAggregationConfig {
+            exclude_from: Some(vec!["security".to_string()]),
+            transform: Some(OutputTransform::Wrap {
+                prefix: "<".to_string(),
+                suffix: ">".to_string(),
+            }),
+            ..Default::default()
+        };
+        let text = config.apply(create_test_outputs()).unwrap().data.to_context_string();
+        assert!(text.starts_with('<') && text.ends_with('>'));
+        assert!(text.contains("microservices"));
+        assert!(!text.contains("OAuth2"));
+
+        let config = NodeAggregationConfig {
+            only_from: Some(vec!["qa".to_string()]),
+            ..Default::default()
+        };
+        assert!(config.apply(create_test_outputs()).is_none());
+    }
+
     #[test]
     fn test_select_one_strategy() {
         let outputs = create_test_outputs();
@@ -532,4 +676,30 @@ mod tests {
         // "Implement OAuth2 for authentication" is longer than "Use microservices architecture"
         assert!(text.contains("OAuth2"));
     }
+
+    /// Previews of every case in `testdata/aggregation/cases.json` on the
+    /// samples next to it must match the case's `.golden` file. Run with
+    /// `UPDATE_GOLDEN=1` to rewrite the golden files after a deliberate change.
+    #[test]
+    fn test_golden_previews() {
+        let dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("src/workflow/testdata/aggregation");
+        let read = |name: &str| std::fs::read_to_string(dir.join(name)).unwrap();
+        let samples: Vec<SampleOutput> = serde_json::from_str(&read("samples.json")).unwrap();
+        let cases: std::collections::BTreeMap<String, NodeAggregationConfig> =
+            serde_json::from_str(&read("cases.json")).unwrap();
+        let update = std::env::var("UPDATE_GOLDEN").is_ok();
+
+        let mut mismatched = Vec::new();
+        for (name, config) in &cases {
+            let preview = config.preview(&samples);
+            let actual = preview.prompt_context.unwrap_or_default() + "\n";
+            let path = dir.join(format!("{}.golden", name));
+            if update {
+                std::fs::write(&path, &actual).unwrap();
+            } else if std::fs::read_to_string(&path).ok().as_deref() != Some(actual.as_str()) {
+                mismatched.push(format!("{}:\n{}", name, actual));
+            }
+        }
+        assert!(mismatched.is_empty(), "Previews differ from their golden files:\n\n{}", mismatched.join("\n\n"));
+    }
 }
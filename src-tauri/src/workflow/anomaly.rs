@@ -0,0 +1,284 @@
+//! Anomaly detection on finished executions.
+//!
+//! A finishing execution is compared with the earlier runs of its workflow
+//! in the history, to catch prompt drift or a provider regression the
+//! first time it shows rather than when someone notices the trend.
+//! Provides:
+//! - Duration and token usage well above the workflow's median
+//! - Nodes failing that almost always succeed
+//! - Delivery of the anomalies to the UI and by email
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use crate::integrations::{send_email, EmailMessage, SmtpConfig};
+use crate::settings::SETTINGS;
+
+use super::chaos::CHAOS_TAG;
+use super::history::{ExecutionHistoryStore, ExecutionRecord};
+use super::state::{ExecutionStatus, NodeExecutionStatus};
+
+/// Event the UI receives for an execution with anomalies
+pub const ANOMALY_EVENT: &str = "execution-anomaly";
+
+/// Earlier runs the baseline is drawn from, newest first
+const BASELINE_RUNS: usize = 20;
+
+/// What about an execution stood out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnomalyKind {
+    Duration,
+    Tokens,
+    /// A node failed that succeeds in nearly every earlier run
+    NodeFailure,
+}
+
+/// One way an execution departed from its workflow's baseline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Anomaly {
+    pub kind: AnomalyKind,
+    /// The node, for node failures
+    pub node_id: Option<String>,
+    pub observed: f64,
+    /// Median of earlier runs, or for node failures their success rate
+    pub baseline: f64,
+    pub message: String,
+}
+
+/// How far from the baseline counts as an anomaly
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnomalyThresholds {
+    /// Multiple of the median duration of completed runs
+    pub duration_factor: f64,
+    /// Multiple of the median token usage
+    pub token_factor: f64,
+    /// Success rate (0.0-1.0) above which a node failing stands out
+    pub node_success_rate: f64,
+    /// Earlier runs needed before anything is compared
+    pub min_baseline_runs: usize,
+}
+
+impl Default for AnomalyThresholds {
+    fn default() -> Self {
+        Self {
+            duration_factor: 3.0,
+            token_factor: 5.0,
+            node_success_rate: 0.9,
+            min_baseline_runs: 5,
+        }
+    }
+}
+
+/// Earlier finished runs of the record's workflow, newest first. Runs
+/// without a saved workflow are matched by workflow name.
+pub fn baseline(history: &ExecutionHistoryStore, record: &ExecutionRecord) -> Vec<ExecutionRecord> {
+    history
+        .list()
+        .into_iter()
+        .filter(|r| r.id != record.id && r.started_at < record.started_at)
+        .filter(|r| match record.workflow_id {
+            Some(workflow_id) => r.workflow_id == Some(workflow_id),
+            None => r.workflow_id.is_none() && r.workflow_name == record.workflow_name,
+        })
+        .filter(|r| r.status != ExecutionStatus::Cancelled && !r.tags.iter().any(|tag| tag == CHAOS_TAG))
+        .take(BASELINE_RUNS)
+        .collect()
+}
+
+/// How `record` departs from `baseline`
+pub fn detect(record: &ExecutionRecord, baseline: &[ExecutionRecord], thresholds: &AnomalyThresholds) -> Vec<Anomaly> {
+    let mut anomalies = Vec::new();
+    if baseline.len() < thresholds.min_baseline_runs {
+        return anomalies;
+    }
+
+    // Failed runs stop early, so only completed runs set the usual duration
+    let durations: Vec<f64> = baseline
+        .iter()
+        .filter(|r| r.status == ExecutionStatus::Completed)
+        .filter_map(|r| r.duration_ms)
+        .map(|ms| ms as f64)
+        .collect();
+    if let (Some(observed), Some(median)) = (record.duration_ms, median(&durations, thresholds.min_baseline_runs)) {
+        let observed = observed as f64;
+        if median > 0.0 && observed > median * thresholds.duration_factor {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::Duration,
+                node_id: None,
+                observed,
+                baseline: median,
+                message: format!(
+                    "Took {:.1}x the usual time ({}s against a median of {}s)",
+                    observed / median,
+                    (observed / 1000.0).round(),
+                    (median / 1000.0).round()
+                ),
+            });
+        }
+    }
+
+    let tokens: Vec<f64> = baseline.iter().filter_map(|r| r.metrics.total_tokens).map(|t| t as f64).collect();
+    if let (Some(observed), Some(median)) = (record.metrics.total_tokens, median(&tokens, thresholds.min_baseline_runs)) {
+        let observed = observed as f64;
+        if median > 0.0 && observed > median * thresholds.token_factor {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::Tokens,
+                node_id: None,
+                observed,
+                baseline: median,
+                message: format!(
+                    "Used {:.1}x the usual tokens ({} against a median of {})",
+                    observed / median,
+                    observed,
+                    median
+                ),
+            });
+        }
+    }
+
+    for node in record.node_records.iter().filter(|n| n.status == NodeExecutionStatus::Failed) {
+        let earlier: Vec<bool> = baseline
+            .iter()
+            .filter_map(|r| r.node_records.iter().find(|n| n.node_id == node.node_id))
+            .filter(|n| matches!(n.status, NodeExecutionStatus::Completed | NodeExecutionStatus::Failed))
+            .map(|n| n.status == NodeExecutionStatus::Completed)
+            .collect();
+        if earlier.len() < thresholds.min_baseline_runs {
+            continue;
+        }
+        let succeeded = earlier.iter().filter(|&&ok| ok).count();
+        let rate = succeeded as f64 / earlier.len() as f64;
+        if rate >= thresholds.node_success_rate {
+            anomalies.push(Anomaly {
+                kind: AnomalyKind::NodeFailure,
+                node_id: Some(node.node_id.clone()),
+                observed: 0.0,
+                baseline: rate,
+                message: format!(
+                    "'{}' failed but succeeded in {} of the previous {} runs",
+                    node.node_name,
+                    succeeded,
+                    earlier.len()
+                ),
+            });
+        }
+    }
+
+    anomalies
+}
+
+/// `None` with fewer than `min_values` values
+fn median(values: &[f64], min_values: usize) -> Option<f64> {
+    if values.is_empty() || values.len() < min_values {
+        return None;
+    }
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let mid = sorted.len() / 2;
+    Some(if sorted.len() % 2 == 0 { (sorted[mid - 1] + sorted[mid]) / 2.0 } else { sorted[mid] })
+}
+
+/// What the UI receives
+#[derive(Debug, Clone, Serialize)]
+pub struct AnomalyAlert {
+    pub execution_id: Uuid,
+    pub workflow_id: Option<Uuid>,
+    pub workflow_name: String,
+    pub anomalies: Vec<Anomaly>,
+}
+
+/// Tell the UI and, when recipients are configured, email them
+pub async fn deliver(app: &AppHandle, record: &ExecutionRecord) {
+    let messages: Vec<&str> = record.anomalies.iter().map(|a| a.message.as_str()).collect();
+    log::warn!("Execution {} of '{}' looks anomalous: {}", record.id, record.workflow_name, messages.join("; "));
+    let notifications = SETTINGS.get().notifications;
+
+    if notifications.desktop {
+        let alert = AnomalyAlert {
+            execution_id: record.id,
+            workflow_id: record.workflow_id,
+            workflow_name: record.workflow_name.clone(),
+            anomalies: record.anomalies.clone(),
+        };
+        let _ = app.emit(ANOMALY_EVENT, alert);
+    }
+
+    if !notifications.email_recipients.is_empty() {
+        let message = EmailMessage {
+            to: notifications.email_recipients,
+            subject: format!("Nexus: unusual run of '{}'", record.workflow_name),
+            body: format!("Execution {}\n\n- {}\n", record.id, messages.join("\n- ")),
+            attachment: None,
+        };
+        let sent = match SmtpConfig::from_env() {
+            Ok(smtp) => send_email(&smtp, &message).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = sent {
+            log::warn!("Failed to email anomaly alert: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::history::{ExecutionRecordBuilder, NodeExecutionRecord};
+    use chrono::{Duration, Utc};
+
+    fn run(workflow_id: Uuid, status: ExecutionStatus, minutes_ago: i64, duration_ms: i64, node_ok: bool) -> ExecutionRecord {
+        let started_at = Utc::now() - Duration::minutes(minutes_ago);
+        let mut builder = ExecutionRecordBuilder::new(Uuid::new_v4(), Uuid::new_v4(), "Project".to_string(), "Run".to_string())
+            .workflow(workflow_id, "Nightly".to_string())
+            .started_at(started_at);
+        builder.add_node_record(NodeExecutionRecord {
+            node_id: "deploy".to_string(),
+            node_name: "Deploy".to_string(),
+            agent_role: "devops".to_string(),
+            agent_id: None,
+            status: if node_ok { NodeExecutionStatus::Completed } else { NodeExecutionStatus::Failed },
+            started_at: None,
+            completed_at: None,
+            duration_ms: None,
+            queue_wait_ms: None,
+            retry_count: 0,
+            output_summary: None,
+            error: None,
+            partial_output_summary: None,
+            retry_attempts: Vec::new(),
+            tokens: None,
+            model: None,
+            prompt_version: None,
+        });
+        builder.build(status, started_at + Duration::milliseconds(duration_ms))
+    }
+
+    #[test]
+    fn test_detect() {
+        let workflow_id = Uuid::new_v4();
+        let history = ExecutionHistoryStore::new(100);
+        for i in 0..6 {
+            history.add(run(workflow_id, ExecutionStatus::Completed, 100 + i, 10_000 + i * 100, true));
+        }
+        // Another workflow's runs don't count
+        history.add(run(Uuid::new_v4(), ExecutionStatus::Completed, 50, 1_000, true));
+
+        let mut slow = run(workflow_id, ExecutionStatus::Failed, 1, 40_000, false);
+        slow.metrics.total_tokens = Some(1_000_000);
+        let baseline = baseline(&history, &slow);
+        assert_eq!(baseline.len(), 6);
+
+        let anomalies = detect(&slow, &baseline, &AnomalyThresholds::default());
+        let kinds: Vec<AnomalyKind> = anomalies.iter().map(|a| a.kind).collect();
+        // No earlier run counted tokens, so there is nothing to compare them to
+        assert_eq!(kinds, vec![AnomalyKind::Duration, AnomalyKind::NodeFailure]);
+        assert!(anomalies[1].message.contains("6 of the previous 6"));
+
+        let usual = run(workflow_id, ExecutionStatus::Completed, 1, 12_000, true);
+        assert!(detect(&usual, &baseline, &AnomalyThresholds::default()).is_empty());
+        assert!(detect(&slow, &baseline[..3], &AnomalyThresholds::default()).is_empty());
+    }
+}
@@ -0,0 +1,123 @@
+//! Human sign-off on individual nodes.
+//!
+//! A node configured with `require_approval` waits in the
+//! `waiting_for_approval` status until someone decides on it. Provides:
+//! - Pending requests across executions, for the UI
+//! - Approving or rejecting a node, which releases it to run or fails it
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+/// A node waiting for sign-off
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ApprovalRequest {
+    pub execution_id: Uuid,
+    pub node_id: String,
+    /// What the reviewer is approving, when the node says
+    #[serde(default)]
+    pub summary: Option<String>,
+    pub requested_at: DateTime<Utc>,
+}
+
+/// What the reviewer decided
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "decision", rename_all = "snake_case")]
+pub enum ApprovalDecision {
+    Approved,
+    Rejected { reason: Option<String> },
+}
+
+struct PendingApproval {
+    request: ApprovalRequest,
+    respond: oneshot::Sender<ApprovalDecision>,
+}
+
+/// Nodes waiting for sign-off, keyed by execution and node
+#[derive(Default)]
+pub struct ApprovalStore {
+    pending: DashMap<(Uuid, String), PendingApproval>,
+}
+
+impl ApprovalStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask for sign-off on a node. The receiver gets the decision, or an
+    /// error if the request is withdrawn.
+    pub fn request(&self, execution_id: Uuid, node_id: &str, summary: Option<String>) -> oneshot::Receiver<ApprovalDecision> {
+        let (respond, receiver) = oneshot::channel();
+        let request = ApprovalRequest {
+            execution_id,
+            node_id: node_id.to_string(),
+            summary,
+            requested_at: Utc::now(),
+        };
+        self.pending
+            .insert((execution_id, node_id.to_string()), PendingApproval { request, respond });
+        receiver
+    }
+
+    /// Decide on a pending node; false if it wasn't waiting
+    pub fn decide(&self, execution_id: Uuid, node_id: &str, decision: ApprovalDecision) -> bool {
+        match self.pending.remove(&(execution_id, node_id.to_string())) {
+            Some((_, pending)) => pending.respond.send(decision).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Stop waiting for a decision on a node
+    pub fn withdraw(&self, execution_id: Uuid, node_id: &str) {
+        self.pending.remove(&(execution_id, node_id.to_string()));
+    }
+
+    /// Pending requests, oldest first, optionally for one execution
+    pub fn pending(&self, execution_id: Option<Uuid>) -> Vec<ApprovalRequest> {
+        let mut requests: Vec<ApprovalRequest> = self
+            .pending
+            .iter()
+            .filter(|entry| execution_id.map_or(true, |id| entry.key().0 == id))
+            .map(|entry| entry.value().request.clone())
+            .collect();
+        requests.sort_by_key(|request| request.requested_at);
+        requests
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref APPROVAL_STORE: ApprovalStore = ApprovalStore::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_approve_and_reject() {
+        let store = ApprovalStore::new();
+        let execution = Uuid::new_v4();
+        let deploy = store.request(execution, "deploy", None);
+        let publish = store.request(execution, "publish", Some("Publish 1.2.0".to_string()));
+        store.request(Uuid::new_v4(), "other", None);
+
+        let ids: Vec<String> = store.pending(Some(execution)).into_iter().map(|r| r.node_id).collect();
+        assert_eq!(ids, vec!["deploy", "publish"]);
+        assert_eq!(store.pending(None).len(), 3);
+
+        assert!(store.decide(execution, "deploy", ApprovalDecision::Approved));
+        assert_eq!(deploy.await.unwrap(), ApprovalDecision::Approved);
+        assert!(!store.decide(execution, "deploy", ApprovalDecision::Approved));
+
+        let rejected = ApprovalDecision::Rejected { reason: Some("Not on a Friday".to_string()) };
+        assert!(store.decide(execution, "publish", rejected.clone()));
+        assert_eq!(publish.await.unwrap(), rejected);
+
+        let withdrawn = store.request(execution, "notify", None);
+        store.withdraw(execution, "notify");
+        assert!(withdrawn.await.is_err());
+        assert!(store.pending(Some(execution)).is_empty());
+    }
+}
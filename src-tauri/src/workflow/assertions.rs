@@ -0,0 +1,265 @@
+//! Assertion checks for evaluation workflows.
+//!
+//! Supports:
+//! - Regex matches against a node's output
+//! - Equality at a JSON path
+//! - Numeric thresholds
+//! - LLM-judge rubrics (an agent scores the output; prompt and score
+//!   parsing live here, spawning lives in the executor)
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Score an LLM judge must reach when the rubric sets no minimum
+const DEFAULT_MIN_SCORE: f64 = 7.0;
+/// Top of the judge's scoring scale
+pub const JUDGE_MAX_SCORE: f64 = 10.0;
+
+pub const JUDGE_SYSTEM_PROMPT: &str = "You are an impartial evaluator. Grade the output you are given \
+strictly against the rubric. Do not modify any files. End your reply with a single line of the form \
+`SCORE: <number from 0 to 10>`.";
+
+pub(crate) fn default_min_score() -> f64 {
+    DEFAULT_MIN_SCORE
+}
+
+/// What an assert node checks
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "check", rename_all = "snake_case")]
+pub enum AssertionCheck {
+    /// Output (as text) matches the pattern, or doesn't when `negate` is set
+    Regex {
+        pattern: String,
+        #[serde(default)]
+        negate: bool,
+    },
+    /// Value at `path` equals `expected`
+    JsonPathEquals { path: String, expected: Value },
+    /// Number at `path` (or the whole output) lies within `[min, max]`
+    Threshold {
+        path: Option<String>,
+        min: Option<f64>,
+        max: Option<f64>,
+    },
+    /// An agent grades the output against a rubric on a 0-10 scale
+    LlmJudge {
+        rubric: String,
+        #[serde(default = "default_min_score")]
+        min_score: f64,
+        model: Option<String>,
+    },
+}
+
+/// Result of one check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CheckResult {
+    pub passed: bool,
+    pub message: String,
+    /// Judge score or measured value, when there is one
+    pub score: Option<f64>,
+}
+
+/// An assert node's recorded result within an execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionOutcome {
+    pub node_id: String,
+    /// Node whose output was checked
+    pub target: String,
+    pub passed: bool,
+    pub message: String,
+    pub score: Option<f64>,
+}
+
+/// Run a deterministic check against an output. LLM-judge checks need an
+/// agent and are rejected here.
+pub fn evaluate(check: &AssertionCheck, output: &Value) -> Result<CheckResult, String> {
+    match check {
+        AssertionCheck::Regex { pattern, negate } => {
+            let re = Regex::new(pattern).map_err(|e| format!("Invalid pattern '{}': {}", pattern, e))?;
+            let found = re.is_match(&output_text(output));
+            let passed = found != *negate;
+            let message = match (found, negate) {
+                (true, false) => format!("Output matches /{}/", pattern),
+                (false, false) => format!("Output does not match /{}/", pattern),
+                (true, true) => format!("Output unexpectedly matches /{}/", pattern),
+                (false, true) => format!("Output does not match /{}/, as required", pattern),
+            };
+            Ok(CheckResult { passed, message, score: None })
+        }
+        AssertionCheck::JsonPathEquals { path, expected } => {
+            let actual = as_json(output).and_then(|json| lookup(&json, path).cloned());
+            Ok(match actual {
+                Some(actual) if &actual == expected => CheckResult {
+                    passed: true,
+                    message: format!("{} equals {}", path, expected),
+                    score: None,
+                },
+                Some(actual) => CheckResult {
+                    passed: false,
+                    message: format!("{} is {}, expected {}", path, actual, expected),
+                    score: None,
+                },
+                None => CheckResult {
+                    passed: false,
+                    message: format!("{} not found in output", path),
+                    score: None,
+                },
+            })
+        }
+        AssertionCheck::Threshold { path, min, max } => {
+            let value = match path {
+                Some(path) => as_json(output).and_then(|json| lookup(&json, path).and_then(number)),
+                None => number(output).or_else(|| as_json(output).as_ref().and_then(number)),
+            };
+            let label = path.as_deref().unwrap_or("output");
+
+            Ok(match value {
+                Some(value) => {
+                    let passed = min.map_or(true, |min| value >= min) && max.map_or(true, |max| value <= max);
+                    let bounds = match (min, max) {
+                        (Some(min), Some(max)) => format!("[{}, {}]", min, max),
+                        (Some(min), None) => format!(">= {}", min),
+                        (None, Some(max)) => format!("<= {}", max),
+                        (None, None) => "any value".to_string(),
+                    };
+                    CheckResult {
+                        passed,
+                        message: format!("{} = {} ({} {})", label, value, if passed { "within" } else { "outside" }, bounds),
+                        score: Some(value),
+                    }
+                }
+                None => CheckResult {
+                    passed: false,
+                    message: format!("{} is not a number", label),
+                    score: None,
+                },
+            })
+        }
+        AssertionCheck::LlmJudge { .. } => Err("LLM-judge checks must be run by a judge agent".to_string()),
+    }
+}
+
+/// Task given to a judge agent
+pub fn judge_prompt(rubric: &str, output: &Value) -> String {
+    format!(
+        "## Rubric\n\n{}\n\n## Output to grade\n\n{}\n\n\
+         Explain your reasoning briefly, then finish with `SCORE: <0-{}>`.",
+        rubric,
+        output_text(output),
+        JUDGE_MAX_SCORE
+    )
+}
+
+/// Read the last `SCORE: n` line from a judge's reply, clamped to the scale
+pub fn parse_judge_score(reply: &str) -> Option<f64> {
+    reply.lines().rev().find_map(|line| {
+        let line = line.trim().trim_matches('`').trim_matches('*');
+        let rest = line.get(..6).filter(|p| p.eq_ignore_ascii_case("score:")).map(|_| &line[6..])?;
+        let number = rest.trim().split('/').next()?.trim();
+        number.parse::<f64>().ok().map(|n| n.clamp(0.0, JUDGE_MAX_SCORE))
+    })
+}
+
+/// A judge's reply without its score line, for feeding back to the graded agent
+pub fn judge_critique(reply: &str) -> String {
+    reply
+        .lines()
+        .filter(|line| parse_judge_score(line).is_none())
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Output as plain text; JSON strings are unwrapped
+pub fn output_text(output: &Value) -> String {
+    match output {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// Structured view of an output: JSON as-is, or JSON parsed from text
+/// (the whole text, or its first ```json block)
+fn as_json(output: &Value) -> Option<Value> {
+    let Value::String(text) = output else {
+        return Some(output.clone());
+    };
+
+    if let Ok(json) = serde_json::from_str(text.trim()) {
+        return Some(json);
+    }
+
+    let start = text.find("```json")? + "```json".len();
+    let end = text[start..].find("```")? + start;
+    serde_json::from_str(text[start..end].trim()).ok()
+}
+
+/// Resolve `$.a.b[0]`, `a.b.0` or JSON Pointer (`/a/b/0`) paths
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    if path.starts_with('/') {
+        return value.pointer(path);
+    }
+
+    let path = path.trim_start_matches('$').trim_start_matches('.');
+    let normalized = path.replace('[', ".").replace(']', "");
+    normalized
+        .split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| match current {
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            Value::Object(map) => map.get(segment),
+            _ => None,
+        })
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_regex_and_json_path() {
+        let check = AssertionCheck::Regex { pattern: r"(?i)all \d+ tests pass".to_string(), negate: false };
+        assert!(evaluate(&check, &json!("All 12 tests passed")).unwrap().passed);
+
+        let negated = AssertionCheck::Regex { pattern: "TODO".to_string(), negate: true };
+        assert!(!evaluate(&negated, &json!("TODO: finish")).unwrap().passed);
+
+        let output = json!("Result:\n```json\n{\"summary\": {\"files\": [\"a.rs\"]}}\n```");
+        let check = AssertionCheck::JsonPathEquals { path: "$.summary.files[0]".to_string(), expected: json!("a.rs") };
+        assert!(evaluate(&check, &output).unwrap().passed);
+
+        let pointer = AssertionCheck::JsonPathEquals { path: "/summary/files/1".to_string(), expected: json!("b.rs") };
+        assert!(!evaluate(&pointer, &output).unwrap().passed);
+    }
+
+    #[test]
+    fn test_threshold() {
+        let check = AssertionCheck::Threshold { path: Some("coverage".to_string()), min: Some(80.0), max: None };
+        let result = evaluate(&check, &json!({"coverage": 72.5})).unwrap();
+        assert!(!result.passed);
+        assert_eq!(result.score, Some(72.5));
+
+        let bare = AssertionCheck::Threshold { path: None, min: Some(0.0), max: Some(1.0) };
+        assert!(evaluate(&bare, &json!(" 0.93 ")).unwrap().passed);
+    }
+
+    #[test]
+    fn test_parse_judge_score() {
+        assert_eq!(parse_judge_score("Looks good.\nSCORE: 8"), Some(8.0));
+        assert_eq!(parse_judge_score("**Score: 6.5/10**"), Some(6.5));
+        assert_eq!(parse_judge_score("SCORE: 42"), Some(JUDGE_MAX_SCORE));
+        assert_eq!(parse_judge_score("no score here"), None);
+        assert_eq!(judge_critique("Misses error handling.\n\nSCORE: 4\n"), "Misses error handling.");
+    }
+}
@@ -0,0 +1,212 @@
+//! Audit log of safety-relevant decisions.
+//!
+//! Records who loosened a safety limit, and what the engine stopped on its
+//! own, so both can be reviewed after the fact. Provides:
+//! - Entries by project and execution, newest first
+//! - A cap on how many entries are kept
+//!
+//! Every entry is also written to the app log, and appended to a JSON
+//! Lines file the log is loaded from on start.
+
+use chrono::{DateTime, Utc};
+use lazy_static::lazy_static;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::io::Write;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+/// Entries kept before the oldest are dropped
+const MAX_ENTRIES: usize = 10_000;
+
+/// What an entry records
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditAction {
+    /// A project's auto-cancel policy was replaced
+    AutoCancelPolicyChanged,
+    /// An execution ran with limits other than its project's, as confirmed
+    AutoCancelOverridden,
+    /// An execution ran past its time limit and was cancelled
+    ExecutionAutoCancelled,
+    /// A node ran past its time limit and was stopped
+    NodeAutoCancelled,
+    /// A project's network policy was replaced
+    NetworkPolicyChanged,
+    /// An agent tried to reach a host its project's network policy denies
+    EgressBlocked,
+    /// An isolated workspace was merged despite dependencies the
+    /// supply-chain check flagged
+    FlaggedDependenciesAccepted,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    pub action: AuditAction,
+    pub project_id: Option<Uuid>,
+    pub execution_id: Option<Uuid>,
+    pub node_id: Option<String>,
+    pub message: String,
+    /// The settings involved, e.g. the limits before and after
+    pub details: Option<Value>,
+}
+
+impl AuditEntry {
+    pub fn new(action: AuditAction, message: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            action,
+            project_id: None,
+            execution_id: None,
+            node_id: None,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn project(mut self, project_id: Uuid) -> Self {
+        self.project_id = Some(project_id);
+        self
+    }
+
+    pub fn execution(mut self, execution_id: Uuid) -> Self {
+        self.execution_id = Some(execution_id);
+        self
+    }
+
+    pub fn node(mut self, node_id: &str) -> Self {
+        self.node_id = Some(node_id.to_string());
+        self
+    }
+
+    pub fn details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+}
+
+#[derive(Default)]
+pub struct AuditLog {
+    entries: RwLock<VecDeque<AuditEntry>>,
+    /// File entries are appended to; `None` keeps them in memory
+    path: Option<PathBuf>,
+}
+
+impl AuditLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The newest entries saved at `path`; the file is rewritten without
+    /// the older ones when it holds more than the cap
+    pub fn load(path: PathBuf) -> Self {
+        let content = std::fs::read_to_string(&path).unwrap_or_default();
+        let mut entries: VecDeque<AuditEntry> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .filter_map(|line| match serde_json::from_str(line) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    log::warn!("Skipping invalid audit log line in {:?}: {}", path, e);
+                    None
+                }
+            })
+            .collect();
+        if entries.len() > MAX_ENTRIES {
+            entries.drain(..entries.len() - MAX_ENTRIES);
+            let kept: String = entries
+                .iter()
+                .filter_map(|entry| serde_json::to_string(entry).ok())
+                .map(|line| line + "\n")
+                .collect();
+            if let Err(e) = std::fs::write(&path, kept) {
+                log::warn!("Failed to trim audit log {:?}: {}", path, e);
+            }
+        }
+        Self {
+            entries: RwLock::new(entries),
+            path: Some(path),
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+            .join("audit_log.jsonl")
+    }
+
+    /// Append `entry` to the log's file, if it has one; a failure is logged
+    fn append(&self, entry: &AuditEntry) {
+        let Some(path) = &self.path else {
+            return;
+        };
+        let written = serde_json::to_string(entry).map_err(std::io::Error::from).and_then(|line| {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut file = std::fs::OpenOptions::new().create(true).append(true).open(path)?;
+            writeln!(file, "{}", line)
+        });
+        if let Err(e) = written {
+            log::warn!("Failed to write audit log {:?}: {}", path, e);
+        }
+    }
+
+    pub fn record(&self, entry: AuditEntry) {
+        log::info!("Audit: {}", entry.message);
+        self.append(&entry);
+        let mut entries = self.entries.write();
+        if entries.len() >= MAX_ENTRIES {
+            entries.pop_front();
+        }
+        entries.push_back(entry);
+    }
+
+    /// Newest entries first, of one project when given
+    pub fn list(&self, project_id: Option<&Uuid>, limit: Option<usize>) -> Vec<AuditEntry> {
+        self.entries
+            .read()
+            .iter()
+            .rev()
+            .filter(|entry| project_id.map_or(true, |id| entry.project_id.as_ref() == Some(id)))
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect()
+    }
+}
+
+lazy_static! {
+    pub static ref AUDIT_LOG: AuditLog = if cfg!(test) {
+        AuditLog::new()
+    } else {
+        AuditLog::load(AuditLog::default_path())
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_survive_reload() {
+        let dir = std::env::temp_dir().join(format!("nexus-audit-{}", Uuid::new_v4()));
+        let path = dir.join("audit_log.jsonl");
+        let project_id = Uuid::new_v4();
+
+        let log = AuditLog::load(path.clone());
+        log.record(AuditEntry::new(AuditAction::AutoCancelPolicyChanged, "first").project(project_id));
+        log.record(AuditEntry::new(AuditAction::NodeAutoCancelled, "second").project(project_id));
+
+        let entries = AuditLog::load(path).list(Some(&project_id), None);
+        let messages: Vec<&str> = entries.iter().map(|entry| entry.message.as_str()).collect();
+        assert_eq!(messages, vec!["second", "first"]);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
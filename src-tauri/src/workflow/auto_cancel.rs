@@ -0,0 +1,275 @@
+//! Project-wide time limits that cancel runaway executions.
+//!
+//! Deadlines, retries and node timeouts are set per workflow and can be
+//! generous or missing. A project's auto-cancel policy is a safety net
+//! under all of them. Provides:
+//! - A limit on how long any execution of the project may run
+//! - A limit on how long any one node may take, retries included,
+//!   whatever its own config allows
+//! - Overrides for a single execution, which must be confirmed and are
+//!   recorded in the audit log
+//!
+//! Every cancellation the policy causes is recorded in the audit log too.
+//! Policies are saved to a JSON file, so they outlive a restart.
+
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+use super::audit::{AuditAction, AuditEntry, AUDIT_LOG};
+use super::state::{ExecutionStatus, WorkflowExecutionState};
+
+/// How often a running execution is compared with its limit
+const WATCH_INTERVAL_MS: u64 = 1_000;
+
+/// Time limits of a project's executions; no limit where unset
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AutoCancelPolicy {
+    /// Longest an execution may run before it is cancelled
+    pub max_execution_ms: Option<u64>,
+    /// Longest a node may take, over all its attempts, before it is stopped
+    pub max_node_ms: Option<u64>,
+}
+
+impl AutoCancelPolicy {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_execution_ms == Some(0) || self.max_node_ms == Some(0) {
+            return Err("Time limits must be above zero".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Other limits for a single execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoCancelOverride {
+    pub policy: AutoCancelPolicy,
+    /// Set by the user confirming the override; unconfirmed ones are refused
+    #[serde(default)]
+    pub confirmed: bool,
+    #[serde(default)]
+    pub reason: Option<String>,
+}
+
+impl AutoCancelOverride {
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.confirmed {
+            return Err("Overriding the project's auto-cancel policy needs confirmation".to_string());
+        }
+        self.policy.validate()
+    }
+
+    /// Record that `execution_id` runs under this override instead of `project_policy`
+    pub fn audit(&self, project_id: Uuid, execution_id: Uuid, project_policy: &AutoCancelPolicy) {
+        AUDIT_LOG.record(
+            AuditEntry::new(
+                AuditAction::AutoCancelOverridden,
+                format!(
+                    "Execution {} overrides the auto-cancel policy: {}",
+                    execution_id,
+                    self.reason.as_deref().unwrap_or("no reason given")
+                ),
+            )
+            .project(project_id)
+            .execution(execution_id)
+            .details(serde_json::json!({ "project": project_policy, "execution": self.policy })),
+        );
+    }
+}
+
+/// Every project's auto-cancel policy
+#[derive(Default)]
+pub struct AutoCancelPolicies {
+    policies: DashMap<Uuid, AutoCancelPolicy>,
+    /// File the policies are saved to; `None` keeps them in memory
+    path: Option<PathBuf>,
+}
+
+impl AutoCancelPolicies {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The policies saved at `path`
+    pub fn load(path: PathBuf) -> Self {
+        let policies: std::collections::HashMap<Uuid, AutoCancelPolicy> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid auto-cancel policy file {:?}: {}", path, e);
+                Default::default()
+            }),
+            Err(_) => Default::default(),
+        };
+        Self {
+            policies: policies.into_iter().collect(),
+            path: Some(path),
+        }
+    }
+
+    pub fn default_path() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+            .join("auto_cancel_policies.json")
+    }
+
+    /// Write the policies to the store's file, if it has one
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let policies: std::collections::BTreeMap<Uuid, AutoCancelPolicy> =
+            self.policies.iter().map(|entry| (*entry.key(), *entry.value())).collect();
+        let content = serde_json::to_string_pretty(&policies).map_err(|e| e.to_string())?;
+        path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(path, content))
+            .map_err(|e| format!("Failed to save auto-cancel policies to {:?}: {}", path, e))
+    }
+
+    /// Replace a project's policy, recording the change
+    pub fn set(&self, project_id: Uuid, policy: AutoCancelPolicy) -> Result<(), String> {
+        policy.validate()?;
+        let replaced = self.policies.insert(project_id, policy);
+        if let Err(e) = self.persist() {
+            match replaced {
+                Some(replaced) => self.policies.insert(project_id, replaced),
+                None => self.policies.remove(&project_id).map(|(_, policy)| policy),
+            };
+            return Err(e);
+        }
+        let previous = replaced.unwrap_or_default();
+        AUDIT_LOG.record(
+            AuditEntry::new(
+                AuditAction::AutoCancelPolicyChanged,
+                format!("Auto-cancel policy of project {} changed", project_id),
+            )
+            .project(project_id)
+            .details(serde_json::json!({ "previous": previous, "policy": policy })),
+        );
+        Ok(())
+    }
+
+    /// A project's policy, without limits if it has none
+    pub fn get(&self, project_id: &Uuid) -> AutoCancelPolicy {
+        self.policies.get(project_id).map(|p| *p).unwrap_or_default()
+    }
+}
+
+lazy_static! {
+    pub static ref AUTO_CANCEL_POLICIES: AutoCancelPolicies = AutoCancelPolicies::load(AutoCancelPolicies::default_path());
+}
+
+/// `ms` in minutes, or seconds when not whole minutes, for messages
+pub fn describe(ms: u64) -> String {
+    match ms {
+        ms if ms >= 60_000 && ms % 60_000 == 0 => format!("{} minute(s)", ms / 60_000),
+        ms => format!("{} second(s)", ms.div_ceil(1_000)),
+    }
+}
+
+/// Cancel `state` once it has run for `max_ms`, unless it finished first
+pub fn watch_execution(state: Arc<WorkflowExecutionState>, max_ms: u64) {
+    let deadline = Instant::now() + Duration::from_millis(max_ms);
+    tokio::spawn(async move {
+        loop {
+            if state.get_status() != ExecutionStatus::Running {
+                return;
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            tokio::time::sleep((deadline - now).min(Duration::from_millis(WATCH_INTERVAL_MS))).await;
+        }
+        let reason = format!("Ran longer than the project's limit of {}", describe(max_ms));
+        state.cancel_with_reason(&reason);
+        AUDIT_LOG.record(
+            AuditEntry::new(
+                AuditAction::ExecutionAutoCancelled,
+                format!("Execution {} cancelled: {}", state.execution_id, reason),
+            )
+            .project(state.project_id)
+            .execution(state.execution_id)
+            .details(serde_json::json!({ "max_execution_ms": max_ms })),
+        );
+    });
+}
+
+/// Record that `node_id` was stopped for running past `max_ms`; returns
+/// the node's error
+pub fn node_stopped(state: &WorkflowExecutionState, node_id: &str, max_ms: u64) -> String {
+    AUDIT_LOG.record(
+        AuditEntry::new(
+            AuditAction::NodeAutoCancelled,
+            format!(
+                "Node {} of execution {} stopped after the project's limit of {}",
+                node_id,
+                state.execution_id,
+                describe(max_ms)
+            ),
+        )
+        .project(state.project_id)
+        .execution(state.execution_id)
+        .node(node_id)
+        .details(serde_json::json!({ "max_node_ms": max_ms })),
+    );
+    format!("Stopped after the project's node limit of {}", describe(max_ms))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_policies_and_overrides() {
+        let policies = AutoCancelPolicies::new();
+        let project_id = Uuid::new_v4();
+        assert_eq!(policies.get(&project_id), AutoCancelPolicy::default());
+
+        let policy = AutoCancelPolicy {
+            max_execution_ms: Some(4 * 3_600_000),
+            max_node_ms: Some(30 * 60_000),
+        };
+        policies.set(project_id, policy).unwrap();
+        assert_eq!(policies.get(&project_id), policy);
+        assert!(policies
+            .set(project_id, AutoCancelPolicy { max_node_ms: Some(0), ..policy })
+            .is_err());
+        let changes = AUDIT_LOG.list(Some(&project_id), None);
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].action, AuditAction::AutoCancelPolicyChanged);
+
+        assert_eq!(describe(30 * 60_000), "30 minute(s)");
+        assert_eq!(describe(90_500), "91 second(s)");
+
+        let mut longer = AutoCancelOverride {
+            policy: AutoCancelPolicy { max_node_ms: None, ..policy },
+            confirmed: false,
+            reason: Some("Full test suite".to_string()),
+        };
+        assert!(longer.validate().is_err());
+        longer.confirmed = true;
+        assert!(longer.validate().is_ok());
+    }
+
+    #[test]
+    fn test_policies_survive_reload() {
+        let dir = std::env::temp_dir().join(format!("nexus-auto-cancel-{}", Uuid::new_v4()));
+        let path = dir.join("auto_cancel_policies.json");
+        let project_id = Uuid::new_v4();
+        let policy = AutoCancelPolicy {
+            max_execution_ms: None,
+            max_node_ms: Some(60_000),
+        };
+
+        AutoCancelPolicies::load(path.clone()).set(project_id, policy).unwrap();
+        assert_eq!(AutoCancelPolicies::load(path).get(&project_id), policy);
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,460 @@
+//! Batch execution of a workflow over a dataset.
+//!
+//! Runs the same workflow once per input item. Provides:
+//! - Per-item input prompts and context variables
+//! - Bounded concurrency across items
+//! - Per-item status tracking and an aggregate report
+//! - Cancellation of the remaining items
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use uuid::Uuid;
+
+use super::state::ExecutionStatus;
+
+/// Polling interval for checking item executions
+const POLL_INTERVAL_MS: u64 = 500;
+
+/// One input item of a batch
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BatchItem {
+    /// Caller-supplied identifier, e.g. an issue number
+    #[serde(default)]
+    pub key: Option<String>,
+    /// Prompt for this item (defaults to the batch prompt template)
+    #[serde(default)]
+    pub input_prompt: Option<String>,
+    /// Variables seeded into this item's execution context
+    #[serde(default)]
+    pub variables: HashMap<String, serde_json::Value>,
+}
+
+/// Status of a single batch item
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchItemRecord {
+    pub index: usize,
+    pub key: Option<String>,
+    pub execution_id: Option<Uuid>,
+    pub status: ExecutionStatus,
+    pub error: Option<String>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+impl BatchItemRecord {
+    /// Duration in milliseconds, once the item has finished
+    pub fn duration_ms(&self) -> Option<u64> {
+        match (self.started_at, self.completed_at) {
+            (Some(start), Some(end)) => Some((end - start).num_milliseconds().max(0) as u64),
+            _ => None,
+        }
+    }
+
+    fn finish(&mut self, status: ExecutionStatus, error: Option<String>) {
+        self.status = status;
+        self.error = error;
+        self.completed_at = Some(Utc::now());
+    }
+}
+
+/// Record of a whole batch run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchRecord {
+    pub id: Uuid,
+    pub workflow_id: Uuid,
+    pub project_id: Uuid,
+    pub max_concurrent: usize,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub items: Vec<BatchItemRecord>,
+}
+
+impl BatchRecord {
+    pub fn new(workflow_id: Uuid, project_id: Uuid, items: &[BatchItem], max_concurrent: usize) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            workflow_id,
+            project_id,
+            max_concurrent: max_concurrent.max(1),
+            created_at: Utc::now(),
+            completed_at: None,
+            items: items
+                .iter()
+                .enumerate()
+                .map(|(index, item)| BatchItemRecord {
+                    index,
+                    key: item.key.clone(),
+                    execution_id: None,
+                    status: ExecutionStatus::Pending,
+                    error: None,
+                    started_at: None,
+                    completed_at: None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Aggregate counts and timings over all items
+    pub fn report(&self) -> BatchReport {
+        let count = |status: ExecutionStatus| self.items.iter().filter(|i| i.status == status).count();
+
+        let completed = count(ExecutionStatus::Completed);
+        let failed = count(ExecutionStatus::Failed);
+        let finished = completed + failed;
+
+        let durations: Vec<u64> = self.items.iter().filter_map(|i| i.duration_ms()).collect();
+
+        BatchReport {
+            total: self.items.len(),
+            pending: count(ExecutionStatus::Pending),
+            running: count(ExecutionStatus::Running),
+            completed,
+            failed,
+            cancelled: count(ExecutionStatus::Cancelled),
+            success_rate: if finished == 0 {
+                0.0
+            } else {
+                completed as f64 / finished as f64
+            },
+            avg_item_duration_ms: if durations.is_empty() {
+                None
+            } else {
+                Some(durations.iter().sum::<u64>() / durations.len() as u64)
+            },
+            failed_items: self
+                .items
+                .iter()
+                .filter(|i| i.status == ExecutionStatus::Failed)
+                .map(|i| i.index)
+                .collect(),
+        }
+    }
+}
+
+/// Aggregate report for a batch
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchReport {
+    pub total: usize,
+    pub pending: usize,
+    pub running: usize,
+    pub completed: usize,
+    pub failed: usize,
+    pub cancelled: usize,
+    /// Completed / (completed + failed)
+    pub success_rate: f64,
+    pub avg_item_duration_ms: Option<u64>,
+    pub failed_items: Vec<usize>,
+}
+
+/// Replace `{{name}}` placeholders with the item's variables
+pub fn render_prompt(template: &str, variables: &HashMap<String, serde_json::Value>) -> String {
+    variables.iter().fold(template.to_string(), |prompt, (key, value)| {
+        let replacement = match value {
+            serde_json::Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        prompt.replace(&format!("{{{{{}}}}}", key), &replacement)
+    })
+}
+
+/// Starts and tracks the executions behind batch items
+pub trait BatchBackend: Send + Sync {
+    /// Start an execution for an item, returning its execution ID
+    fn start(&self, item: &BatchItem, prompt: String) -> Result<Uuid, String>;
+    /// Current status of an execution
+    fn status(&self, execution_id: &Uuid) -> Option<ExecutionStatus>;
+    /// Cancel a running execution
+    fn cancel(&self, execution_id: &Uuid);
+}
+
+/// A batch being tracked
+pub struct BatchHandle {
+    record: RwLock<BatchRecord>,
+    cancelled: AtomicBool,
+}
+
+impl BatchHandle {
+    pub fn new(record: BatchRecord) -> Self {
+        Self {
+            record: RwLock::new(record),
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    pub fn id(&self) -> Uuid {
+        self.record.read().id
+    }
+
+    /// Snapshot of the batch record
+    pub fn record(&self) -> BatchRecord {
+        self.record.read().clone()
+    }
+
+    /// Stop starting new items and cancel running ones
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    fn update_item<F: FnOnce(&mut BatchItemRecord)>(&self, index: usize, f: F) {
+        if let Some(item) = self.record.write().items.get_mut(index) {
+            f(item);
+        }
+    }
+}
+
+/// Store for batch runs
+pub struct BatchStore {
+    batches: DashMap<Uuid, Arc<BatchHandle>>,
+}
+
+impl BatchStore {
+    pub fn new() -> Self {
+        Self {
+            batches: DashMap::new(),
+        }
+    }
+
+    pub fn insert(&self, record: BatchRecord) -> Arc<BatchHandle> {
+        let handle = Arc::new(BatchHandle::new(record));
+        self.batches.insert(handle.id(), handle.clone());
+        handle
+    }
+
+    pub fn get(&self, batch_id: &Uuid) -> Option<Arc<BatchHandle>> {
+        self.batches.get(batch_id).map(|b| b.clone())
+    }
+
+    /// Cancel every batch, returning how many weren't cancelled already
+    pub fn cancel_all(&self) -> usize {
+        let mut cancelled = 0;
+        for batch in self.batches.iter().filter(|batch| !batch.is_cancelled()) {
+            batch.cancel();
+            cancelled += 1;
+        }
+        cancelled
+    }
+
+    /// All batch records, newest first
+    pub fn list(&self) -> Vec<BatchRecord> {
+        let mut records: Vec<BatchRecord> = self.batches.iter().map(|b| b.record()).collect();
+        records.sort_by_key(|r| std::cmp::Reverse(r.created_at));
+        records
+    }
+}
+
+impl Default for BatchStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Run every item of a batch, at most `max_concurrent` at a time
+pub async fn run_batch(
+    handle: Arc<BatchHandle>,
+    items: Vec<BatchItem>,
+    prompt_template: String,
+    backend: Arc<dyn BatchBackend>,
+) {
+    let semaphore = Arc::new(Semaphore::new(handle.record.read().max_concurrent));
+    let mut tasks = Vec::new();
+
+    for (index, item) in items.into_iter().enumerate() {
+        let handle = handle.clone();
+        let backend = backend.clone();
+        let semaphore = semaphore.clone();
+        let prompt = item
+            .input_prompt
+            .clone()
+            .unwrap_or_else(|| render_prompt(&prompt_template, &item.variables));
+
+        tasks.push(tokio::spawn(async move {
+            let Ok(_permit) = semaphore.acquire_owned().await else {
+                return;
+            };
+            run_item(&handle, index, &item, prompt, backend.as_ref()).await;
+        }));
+    }
+
+    for task in tasks {
+        let _ = task.await;
+    }
+
+    handle.record.write().completed_at = Some(Utc::now());
+}
+
+async fn run_item(handle: &BatchHandle, index: usize, item: &BatchItem, prompt: String, backend: &dyn BatchBackend) {
+    if handle.is_cancelled() {
+        handle.update_item(index, |r| r.finish(ExecutionStatus::Cancelled, None));
+        return;
+    }
+
+    let execution_id = match backend.start(item, prompt) {
+        Ok(id) => id,
+        Err(e) => {
+            handle.update_item(index, |r| {
+                r.started_at = Some(Utc::now());
+                r.finish(ExecutionStatus::Failed, Some(e));
+            });
+            return;
+        }
+    };
+
+    handle.update_item(index, |r| {
+        r.execution_id = Some(execution_id);
+        r.status = ExecutionStatus::Running;
+        r.started_at = Some(Utc::now());
+    });
+
+    loop {
+        match backend.status(&execution_id) {
+            Some(status @ (ExecutionStatus::Completed | ExecutionStatus::Failed | ExecutionStatus::Cancelled)) => {
+                handle.update_item(index, |r| r.finish(status, None));
+                return;
+            }
+            None => {
+                handle.update_item(index, |r| {
+                    r.finish(ExecutionStatus::Failed, Some("Execution not found".to_string()))
+                });
+                return;
+            }
+            Some(_) => {}
+        }
+
+        if handle.is_cancelled() {
+            backend.cancel(&execution_id);
+            handle.update_item(index, |r| r.finish(ExecutionStatus::Cancelled, None));
+            return;
+        }
+
+        tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicUsize;
+
+    /// Backend whose executions run until their first status poll and
+    /// finish on the next; items with `fail` set fail
+    struct PolledBackend {
+        running: AtomicUsize,
+        peak: AtomicUsize,
+        prompts: parking_lot::Mutex<Vec<String>>,
+        /// Final status, and whether the execution is still running
+        statuses: DashMap<Uuid, (ExecutionStatus, bool)>,
+    }
+
+    impl PolledBackend {
+        fn new() -> Self {
+            Self {
+                running: AtomicUsize::new(0),
+                peak: AtomicUsize::new(0),
+                prompts: parking_lot::Mutex::new(Vec::new()),
+                statuses: DashMap::new(),
+            }
+        }
+    }
+
+    impl BatchBackend for PolledBackend {
+        fn start(&self, item: &BatchItem, prompt: String) -> Result<Uuid, String> {
+            let now = self.running.fetch_add(1, Ordering::SeqCst) + 1;
+            self.peak.fetch_max(now, Ordering::SeqCst);
+            self.prompts.lock().push(prompt);
+
+            let id = Uuid::new_v4();
+            let status = if item.variables.contains_key("fail") {
+                ExecutionStatus::Failed
+            } else {
+                ExecutionStatus::Completed
+            };
+            self.statuses.insert(id, (status, true));
+            Ok(id)
+        }
+
+        fn status(&self, execution_id: &Uuid) -> Option<ExecutionStatus> {
+            let mut entry = self.statuses.get_mut(execution_id)?;
+            let (status, running) = entry.value_mut();
+            if *running {
+                *running = false;
+                return Some(ExecutionStatus::Running);
+            }
+            // The batch stops polling once it sees the execution finish
+            self.running.fetch_sub(1, Ordering::SeqCst);
+            Some(*status)
+        }
+
+        fn cancel(&self, _execution_id: &Uuid) {}
+    }
+
+    fn item(issue: u32, fail: bool) -> BatchItem {
+        let mut variables = HashMap::new();
+        variables.insert("issue".to_string(), serde_json::json!(issue));
+        if fail {
+            variables.insert("fail".to_string(), serde_json::json!(true));
+        }
+        BatchItem {
+            key: Some(format!("#{}", issue)),
+            input_prompt: None,
+            variables,
+        }
+    }
+
+    #[test]
+    fn test_render_prompt() {
+        let mut vars = HashMap::new();
+        vars.insert("title".to_string(), serde_json::json!("Crash on save"));
+        vars.insert("number".to_string(), serde_json::json!(42));
+
+        assert_eq!(
+            render_prompt("Triage #{{number}}: {{title}} {{missing}}", &vars),
+            "Triage #42: Crash on save {{missing}}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_batch() {
+        let items = vec![item(1, false), item(2, true), item(3, false)];
+        let handle = Arc::new(BatchHandle::new(BatchRecord::new(Uuid::new_v4(), Uuid::new_v4(), &items, 2)));
+        let backend = Arc::new(PolledBackend::new());
+
+        run_batch(handle.clone(), items, "Triage issue {{issue}}".to_string(), backend.clone()).await;
+
+        let record = handle.record();
+        assert!(record.completed_at.is_some());
+        assert!(record.items.iter().all(|i| i.execution_id.is_some()));
+
+        let report = record.report();
+        assert_eq!(report.total, 3);
+        assert_eq!(report.completed, 2);
+        assert_eq!(report.failed, 1);
+        assert_eq!(report.failed_items, vec![1]);
+        // Items overlap, but never more than the batch allows
+        assert_eq!(backend.peak.load(Ordering::SeqCst), 2);
+        assert_eq!(backend.running.load(Ordering::SeqCst), 0);
+        assert!(backend.prompts.lock().contains(&"Triage issue 2".to_string()));
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_batch_skips_items() {
+        let items = vec![item(1, false), item(2, false)];
+        let handle = Arc::new(BatchHandle::new(BatchRecord::new(Uuid::new_v4(), Uuid::new_v4(), &items, 1)));
+        handle.cancel();
+
+        let backend = Arc::new(PolledBackend::new());
+        run_batch(handle.clone(), items, String::new(), backend).await;
+
+        assert_eq!(handle.record().report().cancelled, 2);
+    }
+}
@@ -0,0 +1,156 @@
+//! Failure injection for testing how workflows cope with failures.
+//!
+//! Retry budgets, fallbacks and on-failure edges are only exercised when
+//! something goes wrong, which in practice is rare and hard to provoke.
+//! Chaos mode makes it happen on purpose. Provides:
+//! - Per-attempt faults for agent nodes: failing before the agent starts,
+//!   delaying its start, or cutting its answer short
+//! - Rates for each fault, optionally limited to some nodes
+//! - Reproducible runs: faults follow from a seed, the execution ID unless
+//!   one is given, plus the node and attempt
+//!
+//! Injected failures read like API errors, so the retry config decides
+//! whether they are retried as it would for real ones.
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use uuid::Uuid;
+
+/// Error of an injected failure
+pub const INJECTED_ERROR: &str = "Chaos mode: injected API error";
+
+/// Tag of history records of executions run in chaos mode
+pub const CHAOS_TAG: &str = "chaos";
+
+/// Which faults to inject, and how often
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChaosConfig {
+    /// Share of attempts that fail without starting an agent, 0 to 1
+    pub failure_rate: f64,
+    /// Share of attempts whose agent starts late
+    pub delay_rate: f64,
+    /// Longest delay; each delay is picked up to this
+    pub max_delay_ms: u64,
+    /// Share of successful attempts whose answer is cut in half
+    pub truncate_rate: f64,
+    /// Seed for the faults; one derived from the execution ID when not set
+    pub seed: Option<u64>,
+    /// Nodes faults are injected into; every agent node when empty
+    pub nodes: Vec<String>,
+}
+
+/// Faults for one attempt
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Faults {
+    pub delay: Option<Duration>,
+    pub fail: bool,
+    pub truncate: bool,
+}
+
+impl Faults {
+    pub fn is_empty(&self) -> bool {
+        self.delay.is_none() && !self.fail && !self.truncate
+    }
+}
+
+impl ChaosConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, rate) in [
+            ("Failure", self.failure_rate),
+            ("Delay", self.delay_rate),
+            ("Truncation", self.truncate_rate),
+        ] {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(format!("{} rate must be between 0 and 1", name));
+            }
+        }
+        if self.delay_rate > 0.0 && self.max_delay_ms == 0 {
+            return Err("Delays need a maximum delay".to_string());
+        }
+        Ok(())
+    }
+
+    /// The seed faults of `execution_id` follow from
+    pub fn seed_for(&self, execution_id: &Uuid) -> u64 {
+        // 53 bits, so the frontend can pass it back without losing precision
+        self.seed.unwrap_or_else(|| execution_id.as_u64_pair().0 >> 11)
+    }
+
+    /// Faults for attempt `attempt` of `node_id`, the same every time for
+    /// the same seed
+    pub fn faults(&self, execution_id: &Uuid, node_id: &str, attempt: u32) -> Faults {
+        if !self.nodes.is_empty() && !self.nodes.iter().any(|node| node == node_id) {
+            return Faults::default();
+        }
+        let key = format!("{}:{}:{}", self.seed_for(execution_id), node_id, attempt);
+        let seed = key
+            .bytes()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+        let mut rng = StdRng::seed_from_u64(seed);
+
+        // Drawn in a fixed order so changing one rate leaves the others' picks alone
+        let fail = rng.gen::<f64>() < self.failure_rate;
+        let delayed = rng.gen::<f64>() < self.delay_rate;
+        let delay_ms = rng.gen_range(0..=self.max_delay_ms);
+        let truncate = rng.gen::<f64>() < self.truncate_rate;
+        Faults {
+            delay: delayed.then(|| Duration::from_millis(delay_ms)),
+            fail,
+            truncate: truncate && !fail,
+        }
+    }
+}
+
+/// The first half of `output`, as a truncated answer
+pub fn truncate(output: &str) -> String {
+    let half = output.chars().count() / 2;
+    output.chars().take(half).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_faults_are_reproducible() {
+        let execution_id = Uuid::new_v4();
+        let config = ChaosConfig {
+            failure_rate: 0.3,
+            delay_rate: 0.5,
+            max_delay_ms: 2_000,
+            truncate_rate: 0.3,
+            seed: Some(42),
+            nodes: Vec::new(),
+        };
+        assert!(config.validate().is_ok());
+
+        let plan = |config: &ChaosConfig, execution_id: &Uuid| -> Vec<Faults> {
+            (1..=200).map(|attempt| config.faults(execution_id, "build", attempt)).collect()
+        };
+        // A seed makes the execution ID irrelevant
+        assert_eq!(plan(&config, &execution_id), plan(&config, &Uuid::new_v4()));
+        let faults = plan(&config, &execution_id);
+        let failures = faults.iter().filter(|f| f.fail).count();
+        assert!((30..=90).contains(&failures), "{} failures", failures);
+        assert!(faults.iter().all(|f| !(f.fail && f.truncate)));
+        assert!(faults.iter().filter_map(|f| f.delay).all(|delay| delay <= Duration::from_millis(2_000)));
+
+        let targeted = ChaosConfig {
+            failure_rate: 1.0,
+            nodes: vec!["deploy".to_string()],
+            ..Default::default()
+        };
+        assert!(targeted.faults(&execution_id, "deploy", 1).fail);
+        assert!(targeted.faults(&execution_id, "build", 1).is_empty());
+
+        let invalid = ChaosConfig {
+            delay_rate: 0.5,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+        assert_eq!(truncate("abcdef"), "abc");
+    }
+}
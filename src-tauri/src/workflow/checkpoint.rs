@@ -5,16 +5,20 @@
 //! - Resuming interrupted executions
 //! - Viewing execution history
 //! - Debugging failed workflows
+//! - Moving executions between machines (export/import with ID remapping)
 
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use uuid::Uuid;
 
 use super::context::AgentOutput;
+use super::env_vars::EXECUTION_ENVS;
+use super::messaging::AgentMessage;
 use super::retry::RetryAttemptError;
 use super::state::{ExecutionStatus, NodeExecutionStatus};
+use crate::settings::secrets;
 
 /// A checkpoint of the entire workflow execution state
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -31,6 +35,9 @@ pub struct ExecutionCheckpoint {
     pub original_prompt: String,
     /// Current overall status
     pub status: ExecutionStatus,
+    /// Why the execution was cancelled, e.g. app shutdown
+    #[serde(default)]
+    pub cancel_reason: Option<String>,
     /// State of each node
     pub node_states: HashMap<String, NodeCheckpointState>,
     /// Execution levels (preserved for resume)
@@ -39,6 +46,9 @@ pub struct ExecutionCheckpoint {
     pub variables: HashMap<String, serde_json::Value>,
     /// All agent outputs so far
     pub outputs: HashMap<String, Vec<AgentOutput>>,
+    /// Inter-agent messages, when the execution persists its message bus
+    #[serde(default)]
+    pub messages: Vec<AgentMessage>,
     /// When execution started
     pub started_at: DateTime<Utc>,
     /// When this checkpoint was created
@@ -47,6 +57,9 @@ pub struct ExecutionCheckpoint {
     pub current_level: usize,
     /// Checkpoint version for compatibility
     pub version: u32,
+    /// Execution this checkpoint was imported from, on another machine
+    #[serde(default)]
+    pub imported_from: Option<Uuid>,
 }
 
 /// State of a single node in a checkpoint
@@ -60,6 +73,8 @@ pub struct NodeCheckpointState {
     pub completed_at: Option<DateTime<Utc>>,
     pub output: Option<String>,
     pub error: Option<String>,
+    #[serde(default)]
+    pub partial_output: Option<String>,
     pub retry_attempts: Vec<RetryAttemptError>,
 }
 
@@ -67,6 +82,7 @@ const CHECKPOINT_VERSION: u32 = 1;
 
 impl ExecutionCheckpoint {
     /// Create a new checkpoint from the current execution state
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         execution_id: Uuid,
         workflow_id: Uuid,
@@ -87,14 +103,63 @@ impl ExecutionCheckpoint {
             project_id,
             original_prompt,
             status,
+            cancel_reason: None,
             node_states,
             execution_levels,
             variables,
             outputs,
+            messages: Vec::new(),
             started_at,
             checkpoint_at: Utc::now(),
             current_level,
             version: CHECKPOINT_VERSION,
+            imported_from: None,
+        }
+    }
+
+    /// Check that this build can resume the checkpoint
+    pub fn validate(&self) -> Result<(), String> {
+        if self.version == 0 || self.version > CHECKPOINT_VERSION {
+            return Err(format!(
+                "Checkpoint version {} is not supported (this build reads versions 1 to {})",
+                self.version, CHECKPOINT_VERSION
+            ));
+        }
+
+        if self.current_level > self.execution_levels.len() {
+            return Err(format!(
+                "Checkpoint is at level {} but the execution has only {} levels",
+                self.current_level,
+                self.execution_levels.len()
+            ));
+        }
+
+        let leveled: std::collections::HashSet<&String> = self.execution_levels.iter().flatten().collect();
+        if let Some(node_id) = self.node_states.keys().find(|id| !leveled.contains(id)) {
+            return Err(format!("Node '{}' has state but is not in any execution level", node_id));
+        }
+
+        Ok(())
+    }
+
+    /// Give an imported checkpoint fresh IDs so it can't collide with the
+    /// execution it came from, and point it at a local project. Agents of
+    /// unfinished nodes ran on the other machine, so they are dropped.
+    pub fn remap_for_import(&mut self, project_id: Option<Uuid>) {
+        self.imported_from = Some(self.execution_id);
+        self.id = Uuid::new_v4();
+        self.execution_id = Uuid::new_v4();
+        if let Some(project_id) = project_id {
+            self.project_id = project_id;
+        }
+
+        for message in &mut self.messages {
+            message.execution_id = self.execution_id;
+        }
+        for state in self.node_states.values_mut() {
+            if !matches!(state.status, NodeExecutionStatus::Completed | NodeExecutionStatus::Skipped) {
+                state.agent_id = None;
+            }
         }
     }
 
@@ -111,7 +176,7 @@ impl ExecutionCheckpoint {
     pub fn get_pending_nodes(&self) -> Vec<String> {
         self.node_states
             .iter()
-            .filter(|(_, state)| state.status == NodeExecutionStatus::Pending)
+            .filter(|(_, state)| state.status.is_waiting())
             .map(|(id, _)| id.clone())
             .collect()
     }
@@ -155,7 +220,8 @@ impl ExecutionCheckpoint {
             id: self.id,
             execution_id: self.execution_id,
             workflow_id: self.workflow_id,
-            status: self.status.clone(),
+            status: self.status,
+            cancel_reason: self.cancel_reason.clone(),
             progress: self.get_progress(),
             total_nodes: self.node_states.len(),
             completed_nodes: self.get_completed_nodes().len(),
@@ -175,6 +241,7 @@ pub struct CheckpointSummary {
     pub execution_id: Uuid,
     pub workflow_id: Uuid,
     pub status: ExecutionStatus,
+    pub cancel_reason: Option<String>,
     pub progress: f32,
     pub total_nodes: usize,
     pub completed_nodes: usize,
@@ -210,6 +277,7 @@ impl Default for ResumeOptions {
 }
 
 /// Checkpoint storage manager
+#[derive(Clone)]
 pub struct CheckpointManager {
     /// Directory to store checkpoints
     checkpoint_dir: PathBuf,
@@ -232,8 +300,9 @@ impl CheckpointManager {
             .join("checkpoints")
     }
 
-    /// Save a checkpoint to disk
+    /// Save a checkpoint to disk, with secret values redacted
     pub fn save(&self, checkpoint: &ExecutionCheckpoint) -> std::io::Result<PathBuf> {
+        let checkpoint = &EXECUTION_ENVS.redacted(&checkpoint.execution_id, &secrets::redacted(checkpoint));
         let filename = format!(
             "{}_{}.checkpoint.json",
             checkpoint.execution_id,
@@ -252,21 +321,19 @@ impl CheckpointManager {
 
     /// Load a checkpoint from disk
     pub fn load(&self, checkpoint_id: &Uuid) -> std::io::Result<ExecutionCheckpoint> {
-        // Find the checkpoint file
+        // File names carry the execution ID, so match on the contents
         let entries = std::fs::read_dir(&self.checkpoint_dir)?;
 
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().map(|e| e == "json").unwrap_or(false) {
-                if path.file_name()
-                    .and_then(|n| n.to_str())
-                    .map(|n| n.contains(&checkpoint_id.to_string()))
-                    .unwrap_or(false)
-                {
-                    let content = std::fs::read_to_string(&path)?;
-                    let checkpoint: ExecutionCheckpoint = serde_json::from_str(&content)
-                        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
-                    return Ok(checkpoint);
+            if path.extension().is_some_and(|e| e == "json") {
+                let Ok(content) = std::fs::read_to_string(&path) else {
+                    continue;
+                };
+                if let Ok(checkpoint) = serde_json::from_str::<ExecutionCheckpoint>(&content) {
+                    if checkpoint.id == *checkpoint_id {
+                        return Ok(checkpoint);
+                    }
                 }
             }
         }
@@ -277,6 +344,37 @@ impl CheckpointManager {
         ))
     }
 
+    /// Write a checkpoint to `dest` for moving it to another machine
+    pub fn export(&self, checkpoint_id: &Uuid, dest: &Path) -> std::io::Result<PathBuf> {
+        let checkpoint = self.load(checkpoint_id)?;
+        let path = if dest.is_dir() {
+            dest.join(format!("{}.checkpoint.json", checkpoint.id))
+        } else {
+            dest.to_path_buf()
+        };
+
+        let json = serde_json::to_string_pretty(&checkpoint)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        std::fs::write(&path, json)?;
+
+        Ok(path)
+    }
+
+    /// Read an exported checkpoint, validate it, give it fresh IDs and save it
+    /// here, ready to resume
+    pub fn import(&self, src: &Path, project_id: Option<Uuid>) -> std::io::Result<ExecutionCheckpoint> {
+        let content = std::fs::read_to_string(src)?;
+        let mut checkpoint: ExecutionCheckpoint = serde_json::from_str(&content)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+        checkpoint
+            .validate()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        checkpoint.remap_for_import(project_id);
+        self.save(&checkpoint)?;
+        Ok(checkpoint)
+    }
+
     /// Load the latest checkpoint for an execution
     pub fn load_latest(&self, execution_id: &Uuid) -> std::io::Result<ExecutionCheckpoint> {
         let entries = std::fs::read_dir(&self.checkpoint_dir)?;
@@ -285,29 +383,28 @@ impl CheckpointManager {
 
         for entry in entries.flatten() {
             let path = entry.path();
-            if path.extension().map(|e| e == "json").unwrap_or(false) {
-                if path.file_name()
+            if path.extension().map(|e| e == "json").unwrap_or(false)
+                && path.file_name()
                     .and_then(|n| n.to_str())
                     .map(|n| n.starts_with(&execution_id.to_string()))
                     .unwrap_or(false)
-                {
-                    // Extract timestamp from filename
-                    if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
-                        if let Some(ts_str) = name.split('_').nth(1) {
-                            if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(
-                                ts_str.trim_end_matches(".checkpoint.json"),
-                                "%Y%m%d_%H%M%S",
-                            ) {
-                                let dt = dt.and_utc();
-                                checkpoints.push((path, dt));
-                            }
+            {
+                // Extract timestamp from filename
+                if let Some(name) = path.file_name().and_then(|n| n.to_str()) {
+                    if let Some(ts_str) = name.split('_').nth(1) {
+                        if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(
+                            ts_str.trim_end_matches(".checkpoint.json"),
+                            "%Y%m%d_%H%M%S",
+                        ) {
+                            let dt = dt.and_utc();
+                            checkpoints.push((path, dt));
                         }
                     }
                 }
             }
         }
 
-        checkpoints.sort_by(|a, b| b.1.cmp(&a.1));
+        checkpoints.sort_by_key(|(_, at)| std::cmp::Reverse(*at));
 
         if let Some((path, _)) = checkpoints.first() {
             let content = std::fs::read_to_string(path)?;
@@ -340,7 +437,7 @@ impl CheckpointManager {
         }
 
         // Sort by checkpoint time, newest first
-        summaries.sort_by(|a, b| b.checkpoint_at.cmp(&a.checkpoint_at));
+        summaries.sort_by_key(|s| std::cmp::Reverse(s.checkpoint_at));
 
         Ok(summaries)
     }
@@ -368,7 +465,7 @@ impl CheckpointManager {
 
         for (execution_id, mut checkpoints) in by_execution {
             // Sort by time, newest first
-            checkpoints.sort_by(|a, b| b.checkpoint_at.cmp(&a.checkpoint_at));
+            checkpoints.sort_by_key(|c| std::cmp::Reverse(c.checkpoint_at));
 
             // Delete all but the first N
             for checkpoint in checkpoints.into_iter().skip(keep_per_execution) {
@@ -387,6 +484,55 @@ impl CheckpointManager {
         Ok(deleted)
     }
 
+    /// Checkpoint files saved for an execution
+    fn execution_files(&self, execution_id: &Uuid) -> std::io::Result<Vec<PathBuf>> {
+        let prefix = execution_id.to_string();
+        Ok(std::fs::read_dir(&self.checkpoint_dir)?
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|n| n.starts_with(&prefix) && n.ends_with(".json"))
+            })
+            .collect())
+    }
+
+    /// Messages persisted with the newest checkpoint of an execution
+    pub fn load_messages(&self, execution_id: &Uuid) -> std::io::Result<Vec<AgentMessage>> {
+        let latest = self
+            .execution_files(execution_id)?
+            .iter()
+            .filter_map(|path| std::fs::read_to_string(path).ok())
+            .filter_map(|content| serde_json::from_str::<ExecutionCheckpoint>(&content).ok())
+            .max_by_key(|checkpoint| checkpoint.checkpoint_at);
+
+        Ok(latest.map(|checkpoint| checkpoint.messages).unwrap_or_default())
+    }
+
+    /// Remove persisted messages from an execution's checkpoints; returns
+    /// the number of checkpoints rewritten
+    pub fn strip_messages(&self, execution_id: &Uuid) -> std::io::Result<usize> {
+        let mut rewritten = 0;
+
+        for path in self.execution_files(execution_id)? {
+            let content = std::fs::read_to_string(&path)?;
+            let mut checkpoint: ExecutionCheckpoint = serde_json::from_str(&content)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            if checkpoint.messages.is_empty() {
+                continue;
+            }
+
+            checkpoint.messages.clear();
+            let json = serde_json::to_string_pretty(&checkpoint)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+            std::fs::write(&path, json)?;
+            rewritten += 1;
+        }
+
+        Ok(rewritten)
+    }
+
     /// Delete all checkpoints for an execution
     pub fn delete_for_execution(&self, execution_id: &Uuid) -> std::io::Result<usize> {
         let entries = std::fs::read_dir(&self.checkpoint_dir)?;
@@ -399,10 +545,9 @@ impl CheckpointManager {
                 .and_then(|n| n.to_str())
                 .map(|n| n.starts_with(&execution_id.to_string()))
                 .unwrap_or(false)
+                && std::fs::remove_file(&path).is_ok()
             {
-                if std::fs::remove_file(&path).is_ok() {
-                    deleted += 1;
-                }
+                deleted += 1;
             }
         }
 
@@ -411,9 +556,10 @@ impl CheckpointManager {
 }
 
 /// Automatic checkpoint trigger conditions
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub enum CheckpointTrigger {
     /// Checkpoint after each level completes
+    #[default]
     AfterLevel,
     /// Checkpoint after N nodes complete
     AfterNNodes { count: usize },
@@ -425,12 +571,6 @@ pub enum CheckpointTrigger {
     Manual,
 }
 
-impl Default for CheckpointTrigger {
-    fn default() -> Self {
-        Self::AfterLevel
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -449,6 +589,7 @@ mod tests {
                 completed_at: Some(Utc::now()),
                 output: None,
                 error: None,
+                partial_output: None,
                 retry_attempts: vec![],
             },
         );
@@ -463,6 +604,7 @@ mod tests {
                 completed_at: None,
                 output: None,
                 error: None,
+                partial_output: None,
                 retry_attempts: vec![],
             },
         );
@@ -502,6 +644,7 @@ mod tests {
                 completed_at: None,
                 output: None,
                 error: None,
+                partial_output: None,
                 retry_attempts: vec![],
             },
         );
@@ -524,4 +667,100 @@ mod tests {
         assert_eq!(interrupted.len(), 1);
         assert_eq!(interrupted[0], "running-node");
     }
+
+    #[test]
+    fn test_persisted_messages_can_be_stripped() {
+        let dir = std::env::temp_dir().join(format!("nexus-checkpoints-{}", Uuid::new_v4()));
+        let manager = CheckpointManager::new(dir.clone()).unwrap();
+        let execution_id = Uuid::new_v4();
+
+        let bus = super::super::messaging::MessageBus::new(execution_id);
+        bus.broadcast_status(Uuid::new_v4(), "node-1", "architect", "Design ready");
+
+        let mut checkpoint = ExecutionCheckpoint::new(
+            execution_id,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "Test".to_string(),
+            ExecutionStatus::Running,
+            HashMap::new(),
+            vec![],
+            HashMap::new(),
+            HashMap::new(),
+            Utc::now(),
+            0,
+        );
+        checkpoint.messages = bus.get_all_messages();
+        manager.save(&checkpoint).unwrap();
+
+        assert_eq!(manager.load_messages(&execution_id).unwrap().len(), 1);
+        assert_eq!(manager.strip_messages(&execution_id).unwrap(), 1);
+        assert!(manager.load_messages(&execution_id).unwrap().is_empty());
+        assert!(manager.load_messages(&Uuid::new_v4()).unwrap().is_empty());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn test_export_import_remaps_ids() {
+        let source_dir = std::env::temp_dir().join(format!("nexus-checkpoints-{}", Uuid::new_v4()));
+        let target_dir = std::env::temp_dir().join(format!("nexus-checkpoints-{}", Uuid::new_v4()));
+        let source = CheckpointManager::new(source_dir.clone()).unwrap();
+        let target = CheckpointManager::new(target_dir.clone()).unwrap();
+
+        let mut node_states = HashMap::new();
+        for (id, status) in [("design", NodeExecutionStatus::Completed), ("build", NodeExecutionStatus::Running)] {
+            node_states.insert(
+                id.to_string(),
+                NodeCheckpointState {
+                    node_id: id.to_string(),
+                    status,
+                    agent_id: Some(Uuid::new_v4()),
+                    progress: 0,
+                    started_at: None,
+                    completed_at: None,
+                    output: None,
+                    error: None,
+                    partial_output: None,
+                    retry_attempts: vec![],
+                },
+            );
+        }
+        let checkpoint = ExecutionCheckpoint::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "Test".to_string(),
+            ExecutionStatus::Running,
+            node_states,
+            vec![vec!["design".to_string()], vec!["build".to_string()]],
+            HashMap::new(),
+            HashMap::new(),
+            Utc::now(),
+            1,
+        );
+        source.save(&checkpoint).unwrap();
+
+        let exported = source.export(&checkpoint.id, &source_dir.join("moved.json")).unwrap();
+        let project_id = Uuid::new_v4();
+        let imported = target.import(&exported, Some(project_id)).unwrap();
+
+        assert_ne!(imported.id, checkpoint.id);
+        assert_ne!(imported.execution_id, checkpoint.execution_id);
+        assert_eq!(imported.imported_from, Some(checkpoint.execution_id));
+        assert_eq!(imported.project_id, project_id);
+        assert!(imported.node_states["design"].agent_id.is_some());
+        assert!(imported.node_states["build"].agent_id.is_none());
+        assert_eq!(target.load(&imported.id).unwrap().execution_id, imported.execution_id);
+
+        // Checkpoints from a newer build are refused
+        let mut newer = checkpoint.clone();
+        newer.version = CHECKPOINT_VERSION + 1;
+        std::fs::write(&exported, serde_json::to_string(&newer).unwrap()).unwrap();
+        let error = target.import(&exported, None).unwrap_err();
+        assert!(error.to_string().contains("not supported"));
+
+        std::fs::remove_dir_all(source_dir).unwrap();
+        std::fs::remove_dir_all(target_dir).unwrap();
+    }
 }
@@ -0,0 +1,257 @@
+//! Concurrency groups across executions.
+//!
+//! Executions naming the same group run one at a time, whichever workflow
+//! they belong to, as with `concurrency:` in GitHub Actions. Provides:
+//! - Queueing newcomers, cancelling what is in progress, or rejecting them
+//! - Which execution holds each group and which wait, for the UI
+
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::{broadcast, Mutex as AsyncMutex, OwnedMutexGuard};
+use uuid::Uuid;
+
+use super::state::{ExecutionStatus, WorkflowExecutionState};
+
+/// What happens to an execution whose group is busy
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupPolicy {
+    /// Wait for the executions ahead of it
+    #[default]
+    Queue,
+    /// Cancel the running and waiting executions, then run
+    CancelInProgress,
+    /// Fail to start
+    Reject,
+}
+
+/// The group an execution belongs to
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionConcurrency {
+    pub group: String,
+    #[serde(default)]
+    pub policy: GroupPolicy,
+}
+
+/// Who holds a group and who waits for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GroupInfo {
+    pub name: String,
+    pub running: Option<Uuid>,
+    /// In the order they will run
+    pub queued: Vec<Uuid>,
+}
+
+struct Member {
+    state: Arc<WorkflowExecutionState>,
+    running: bool,
+}
+
+struct Group {
+    turn: Arc<AsyncMutex<()>>,
+    members: Mutex<Vec<Member>>,
+}
+
+impl Group {
+    fn leave(&self, execution_id: &Uuid) {
+        self.members.lock().retain(|m| m.state.execution_id != *execution_id);
+    }
+}
+
+/// An execution's place in its group, until it gets its turn
+pub struct GroupTicket {
+    group: Arc<Group>,
+    state: Arc<WorkflowExecutionState>,
+    cancel_rx: broadcast::Receiver<()>,
+}
+
+impl GroupTicket {
+    /// Wait for the executions ahead; `None` if this one was cancelled
+    /// while it waited
+    pub async fn wait(mut self) -> Option<GroupTurn> {
+        let execution_id = self.state.execution_id;
+        let guard = if self.state.get_status() == ExecutionStatus::Cancelled {
+            None
+        } else {
+            tokio::select! {
+                guard = self.group.turn.clone().lock_owned() => Some(guard),
+                _ = self.cancel_rx.recv() => None,
+            }
+        };
+
+        let Some(guard) = guard else {
+            self.group.leave(&execution_id);
+            return None;
+        };
+        if let Some(member) = self.group.members.lock().iter_mut().find(|m| m.state.execution_id == execution_id) {
+            member.running = true;
+        }
+        Some(GroupTurn {
+            group: self.group,
+            execution_id,
+            _guard: guard,
+        })
+    }
+}
+
+/// Holding the group; the next execution runs once this is dropped
+pub struct GroupTurn {
+    group: Arc<Group>,
+    execution_id: Uuid,
+    _guard: OwnedMutexGuard<()>,
+}
+
+impl Drop for GroupTurn {
+    fn drop(&mut self) {
+        self.group.leave(&self.execution_id);
+    }
+}
+
+/// Concurrency groups by name
+#[derive(Default)]
+pub struct ExecutionGroups {
+    groups: DashMap<String, Arc<Group>>,
+}
+
+impl ExecutionGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Put an execution in its group according to the group policy.
+    /// Rejection leaves the group untouched.
+    pub fn join(&self, concurrency: &ExecutionConcurrency, state: &Arc<WorkflowExecutionState>) -> Result<GroupTicket, String> {
+        let group = self
+            .groups
+            .entry(concurrency.group.clone())
+            .or_insert_with(|| {
+                Arc::new(Group {
+                    turn: Arc::new(AsyncMutex::new(())),
+                    members: Mutex::new(Vec::new()),
+                })
+            })
+            .clone();
+
+        let mut members = group.members.lock();
+        match concurrency.policy {
+            GroupPolicy::Queue => {}
+            GroupPolicy::Reject => {
+                if let Some(busy) = members.first() {
+                    return Err(format!(
+                        "Concurrency group '{}' is busy with execution {}",
+                        concurrency.group, busy.state.execution_id
+                    ));
+                }
+            }
+            GroupPolicy::CancelInProgress => {
+                let reason = format!("Superseded by execution {} in group '{}'", state.execution_id, concurrency.group);
+                for member in members.iter() {
+                    member.state.cancel_with_reason(&reason);
+                }
+            }
+        }
+
+        let cancel_rx = state.subscribe_cancel();
+        members.push(Member {
+            state: state.clone(),
+            running: false,
+        });
+        drop(members);
+
+        Ok(GroupTicket {
+            group,
+            state: state.clone(),
+            cancel_rx,
+        })
+    }
+
+    /// Groups with a running or waiting execution
+    pub fn list(&self) -> Vec<GroupInfo> {
+        let mut groups: Vec<GroupInfo> = self
+            .groups
+            .iter()
+            .filter_map(|entry| {
+                let members = entry.value().members.lock();
+                if members.is_empty() {
+                    return None;
+                }
+                Some(GroupInfo {
+                    name: entry.key().clone(),
+                    running: members.iter().find(|m| m.running).map(|m| m.state.execution_id),
+                    queued: members.iter().filter(|m| !m.running).map(|m| m.state.execution_id).collect(),
+                })
+            })
+            .collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        groups
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref EXECUTION_GROUPS: ExecutionGroups = ExecutionGroups::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execution() -> Arc<WorkflowExecutionState> {
+        Arc::new(WorkflowExecutionState::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "Deploy".to_string(),
+            vec![vec!["deploy".to_string()]],
+        ))
+    }
+
+    fn group(policy: GroupPolicy) -> ExecutionConcurrency {
+        ExecutionConcurrency {
+            group: "deploy".to_string(),
+            policy,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_queue_then_run() {
+        let groups = ExecutionGroups::new();
+        let (first, second) = (execution(), execution());
+
+        let turn = groups.join(&group(GroupPolicy::Queue), &first).unwrap().wait().await.unwrap();
+        let waiting = tokio::spawn(groups.join(&group(GroupPolicy::Queue), &second).unwrap().wait());
+        tokio::task::yield_now().await;
+
+        let info = groups.list();
+        assert_eq!(info[0].running, Some(first.execution_id));
+        assert_eq!(info[0].queued, vec![second.execution_id]);
+        assert!(groups.join(&group(GroupPolicy::Reject), &execution()).is_err());
+
+        drop(turn);
+        let second_turn = waiting.await.unwrap().unwrap();
+        assert_eq!(groups.list()[0].running, Some(second.execution_id));
+        drop(second_turn);
+        assert!(groups.list().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_in_progress() {
+        let groups = ExecutionGroups::new();
+        let (running, queued, newest) = (execution(), execution(), execution());
+
+        let turn = groups.join(&group(GroupPolicy::Queue), &running).unwrap().wait().await.unwrap();
+        let waiting = tokio::spawn(groups.join(&group(GroupPolicy::Queue), &queued).unwrap().wait());
+        tokio::task::yield_now().await;
+
+        let ticket = groups.join(&group(GroupPolicy::CancelInProgress), &newest).unwrap();
+        assert_eq!(running.get_status(), ExecutionStatus::Cancelled);
+        assert!(running.get_cancel_reason().unwrap().starts_with("Superseded"));
+        // The waiting execution gives up its place
+        assert!(waiting.await.unwrap().is_none());
+
+        // The newest runs once the cancelled one has stopped
+        drop(turn);
+        assert!(ticket.wait().await.is_some());
+    }
+}
@@ -10,13 +10,16 @@ use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 use super::context::ExecutionContext;
+use super::plugins::PLUGIN_REGISTRY;
 use super::state::NodeExecutionStatus;
+use super::text;
 
 /// A condition that determines whether a node should execute
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 #[serde(tag = "type")]
 pub enum ExecutionCondition {
     /// Always execute (default)
+    #[default]
     Always,
 
     /// Never execute (disabled node)
@@ -57,6 +60,14 @@ pub enum ExecutionCondition {
         expected_value: Option<serde_json::Value>,
     },
 
+    /// Execute if an output carries a tag (roles and `node:<id>` are added
+    /// automatically); checks all predecessors unless one is named
+    OutputTagged {
+        tag: String,
+        #[serde(default)]
+        predecessor_id: Option<String>,
+    },
+
     /// Combine multiple conditions with AND
     And { conditions: Vec<ExecutionCondition> },
 
@@ -68,20 +79,132 @@ pub enum ExecutionCondition {
 
     /// Custom expression (evaluated at runtime)
     Expression { expr: String },
-}
 
-impl Default for ExecutionCondition {
-    fn default() -> Self {
-        Self::Always
-    }
+    /// Evaluate with a registered WASM condition plugin
+    Plugin {
+        name: String,
+        #[serde(default)]
+        params: serde_json::Value,
+    },
 }
 
 /// Result of evaluating a condition
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ConditionResult {
     pub should_execute: bool,
     pub reason: String,
     pub evaluated_conditions: Vec<String>,
+    /// What the evaluation looked at, in the order it looked
+    #[serde(default)]
+    pub consulted: Vec<ConsultedValue>,
+}
+
+/// A value a condition looked at, as it was at the time
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ConsultedValue {
+    Variable {
+        name: String,
+        /// Unset if the variable wasn't set
+        value: Option<serde_json::Value>,
+    },
+    NodeStatus {
+        node_id: String,
+        status: Option<NodeExecutionStatus>,
+    },
+    Output {
+        node_id: String,
+        /// Start of the node's latest output, if it has one
+        preview: Option<String>,
+        tags: Vec<String>,
+    },
+}
+
+/// Characters of output kept in `ConsultedValue::Output`
+const OUTPUT_PREVIEW_CHARS: usize = 200;
+
+/// Why a node ran or was skipped
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeDecision {
+    pub node_id: String,
+    pub should_execute: bool,
+    pub reason: String,
+    /// Predecessors whose failure skipped the node before its condition
+    /// was checked
+    #[serde(default)]
+    pub failed_dependencies: Vec<String>,
+    /// The node's condition and how it evaluated, if it was checked
+    pub condition: Option<ExecutionCondition>,
+    pub result: Option<ConditionResult>,
+    pub decided_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl NodeDecision {
+    /// A node skipped because predecessors failed
+    pub fn dependency_failed(node_id: &str, failed_dependencies: Vec<String>) -> Self {
+        Self {
+            node_id: node_id.to_string(),
+            should_execute: false,
+            reason: "Dependency failed".to_string(),
+            failed_dependencies,
+            condition: None,
+            result: None,
+            decided_at: chrono::Utc::now(),
+        }
+    }
+
+    /// A node whose condition decided whether it runs
+    pub fn evaluated(node_id: &str, condition: &ExecutionCondition, result: &ConditionResult) -> Self {
+        Self {
+            node_id: node_id.to_string(),
+            should_execute: result.should_execute,
+            reason: result.reason.clone(),
+            failed_dependencies: Vec::new(),
+            condition: Some(condition.clone()),
+            result: Some(result.clone()),
+            decided_at: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Collects the steps of an evaluation
+#[derive(Default)]
+struct Trace {
+    evaluated: Vec<String>,
+    consulted: Vec<ConsultedValue>,
+}
+
+impl Trace {
+    fn consult(&mut self, value: ConsultedValue) {
+        if !self.consulted.contains(&value) {
+            self.consulted.push(value);
+        }
+    }
+
+    fn status(&mut self, node_id: &str, node_statuses: &HashMap<String, NodeExecutionStatus>) {
+        self.consult(ConsultedValue::NodeStatus {
+            node_id: node_id.to_string(),
+            status: node_statuses.get(node_id).copied(),
+        });
+    }
+
+    fn variable(&mut self, name: &str, context: &ExecutionContext) {
+        self.consult(ConsultedValue::Variable {
+            name: name.to_string(),
+            value: context.get_variable(name),
+        });
+    }
+
+    fn output(&mut self, node_id: &str, context: &ExecutionContext) {
+        let output = context.get_latest_output(node_id);
+        self.consult(ConsultedValue::Output {
+            node_id: node_id.to_string(),
+            preview: output
+                .as_ref()
+                .map(|o| text::prefix(&o.data.to_context_string(), OUTPUT_PREVIEW_CHARS).to_string()),
+            tags: output.map(|o| o.tags).unwrap_or_default(),
+        });
+    }
 }
 
 impl ExecutionCondition {
@@ -92,19 +215,20 @@ impl ExecutionCondition {
         node_statuses: &HashMap<String, NodeExecutionStatus>,
         predecessor_ids: &[String],
     ) -> ConditionResult {
-        let mut evaluated = Vec::new();
+        let mut trace = Trace::default();
 
         let (should_execute, reason) = self.evaluate_inner(
             context,
             node_statuses,
             predecessor_ids,
-            &mut evaluated,
+            &mut trace,
         );
 
         ConditionResult {
             should_execute,
             reason,
-            evaluated_conditions: evaluated,
+            evaluated_conditions: trace.evaluated,
+            consulted: trace.consulted,
         }
     }
 
@@ -113,21 +237,22 @@ impl ExecutionCondition {
         context: &ExecutionContext,
         node_statuses: &HashMap<String, NodeExecutionStatus>,
         predecessor_ids: &[String],
-        evaluated: &mut Vec<String>,
+        trace: &mut Trace,
     ) -> (bool, String) {
         match self {
             ExecutionCondition::Always => {
-                evaluated.push("Always".to_string());
+                trace.evaluated.push("Always".to_string());
                 (true, "Always execute".to_string())
             }
 
             ExecutionCondition::Never => {
-                evaluated.push("Never".to_string());
+                trace.evaluated.push("Never".to_string());
                 (false, "Node is disabled".to_string())
             }
 
             ExecutionCondition::OnSuccess { predecessor_id } => {
-                evaluated.push(format!("OnSuccess({})", predecessor_id));
+                trace.evaluated.push(format!("OnSuccess({})", predecessor_id));
+                trace.status(predecessor_id, node_statuses);
                 match node_statuses.get(predecessor_id) {
                     Some(NodeExecutionStatus::Completed) => {
                         (true, format!("Predecessor {} succeeded", predecessor_id))
@@ -142,7 +267,8 @@ impl ExecutionCondition {
             }
 
             ExecutionCondition::OnFailure { predecessor_id } => {
-                evaluated.push(format!("OnFailure({})", predecessor_id));
+                trace.evaluated.push(format!("OnFailure({})", predecessor_id));
+                trace.status(predecessor_id, node_statuses);
                 match node_statuses.get(predecessor_id) {
                     Some(NodeExecutionStatus::Failed) => {
                         (true, format!("Predecessor {} failed", predecessor_id))
@@ -157,7 +283,10 @@ impl ExecutionCondition {
             }
 
             ExecutionCondition::AllPredecessorsSucceeded => {
-                evaluated.push("AllPredecessorsSucceeded".to_string());
+                trace.evaluated.push("AllPredecessorsSucceeded".to_string());
+                for id in predecessor_ids {
+                    trace.status(id, node_statuses);
+                }
                 let all_succeeded = predecessor_ids.iter().all(|id| {
                     matches!(node_statuses.get(id), Some(NodeExecutionStatus::Completed))
                 });
@@ -173,7 +302,10 @@ impl ExecutionCondition {
             }
 
             ExecutionCondition::AnyPredecessorSucceeded => {
-                evaluated.push("AnyPredecessorSucceeded".to_string());
+                trace.evaluated.push("AnyPredecessorSucceeded".to_string());
+                for id in predecessor_ids {
+                    trace.status(id, node_statuses);
+                }
                 let any_succeeded = predecessor_ids.iter().any(|id| {
                     matches!(node_statuses.get(id), Some(NodeExecutionStatus::Completed))
                 });
@@ -185,7 +317,8 @@ impl ExecutionCondition {
             }
 
             ExecutionCondition::VariableEquals { variable, value } => {
-                evaluated.push(format!("VariableEquals({}, {:?})", variable, value));
+                trace.evaluated.push(format!("VariableEquals({}, {:?})", variable, value));
+                trace.variable(variable, context);
                 match context.get_variable(variable) {
                     Some(v) if &v == value => {
                         (true, format!("Variable {} equals {:?}", variable, value))
@@ -200,7 +333,8 @@ impl ExecutionCondition {
             }
 
             ExecutionCondition::VariableTruthy { variable } => {
-                evaluated.push(format!("VariableTruthy({})", variable));
+                trace.evaluated.push(format!("VariableTruthy({})", variable));
+                trace.variable(variable, context);
                 match context.get_variable(variable) {
                     Some(v) => {
                         let truthy = match &v {
@@ -228,7 +362,8 @@ impl ExecutionCondition {
                 pattern,
                 case_sensitive,
             } => {
-                evaluated.push(format!("OutputContains({}, {})", predecessor_id, pattern));
+                trace.evaluated.push(format!("OutputContains({}, {})", predecessor_id, pattern));
+                trace.output(predecessor_id, context);
                 match context.get_latest_output(predecessor_id) {
                     Some(output) => {
                         let content = output.data.to_context_string();
@@ -254,7 +389,8 @@ impl ExecutionCondition {
                 path,
                 expected_value,
             } => {
-                evaluated.push(format!("OutputJsonPath({}, {})", predecessor_id, path));
+                trace.evaluated.push(format!("OutputJsonPath({}, {})", predecessor_id, path));
+                trace.output(predecessor_id, context);
                 match context.get_latest_output(predecessor_id) {
                     Some(output) => {
                         // Try to parse output as JSON
@@ -292,14 +428,30 @@ impl ExecutionCondition {
                 }
             }
 
+            ExecutionCondition::OutputTagged { tag, predecessor_id } => {
+                trace.evaluated.push(format!("OutputTagged({})", tag));
+                let sources = match predecessor_id {
+                    Some(id) => std::slice::from_ref(id),
+                    None => predecessor_ids,
+                };
+                for id in sources {
+                    trace.output(id, context);
+                }
+                let tagged = context.get_tagged_outputs(sources, tag);
+                match tagged.first() {
+                    Some(output) => (true, format!("Output from {} is tagged '{}'", output.node_id, tag)),
+                    None => (false, format!("No predecessor output tagged '{}'", tag)),
+                }
+            }
+
             ExecutionCondition::And { conditions } => {
-                evaluated.push("And".to_string());
+                trace.evaluated.push("And".to_string());
                 for condition in conditions {
                     let (result, reason) = condition.evaluate_inner(
                         context,
                         node_statuses,
                         predecessor_ids,
-                        evaluated,
+                        trace,
                     );
                     if !result {
                         return (false, format!("AND failed: {}", reason));
@@ -309,13 +461,13 @@ impl ExecutionCondition {
             }
 
             ExecutionCondition::Or { conditions } => {
-                evaluated.push("Or".to_string());
+                trace.evaluated.push("Or".to_string());
                 for condition in conditions {
                     let (result, _reason) = condition.evaluate_inner(
                         context,
                         node_statuses,
                         predecessor_ids,
-                        evaluated,
+                        trace,
                     );
                     if result {
                         return (true, "OR condition passed".to_string());
@@ -325,23 +477,40 @@ impl ExecutionCondition {
             }
 
             ExecutionCondition::Not { condition } => {
-                evaluated.push("Not".to_string());
+                trace.evaluated.push("Not".to_string());
                 let (result, reason) = condition.evaluate_inner(
                     context,
                     node_statuses,
                     predecessor_ids,
-                    evaluated,
+                    trace,
                 );
                 (!result, format!("NOT({})", reason))
             }
 
             ExecutionCondition::Expression { expr } => {
-                evaluated.push(format!("Expression({})", expr));
+                trace.evaluated.push(format!("Expression({})", expr));
+                for variable in expression_variables(expr) {
+                    trace.variable(variable, context);
+                }
                 // Simple expression evaluation
                 // Supports: true, false, and variable references like $var
                 let result = evaluate_expression(expr, context);
                 (result, format!("Expression '{}' evaluated to {}", expr, result))
             }
+
+            ExecutionCondition::Plugin { name, params } => {
+                trace.evaluated.push(format!("Plugin({})", name));
+                let input = serde_json::json!({
+                    "params": params,
+                    "variables": context.get_all_variables(),
+                    "node_statuses": node_statuses,
+                    "predecessor_ids": predecessor_ids,
+                });
+                match PLUGIN_REGISTRY.call_condition(name, &input) {
+                    Ok(result) => (result, format!("Plugin '{}' evaluated to {}", name, result)),
+                    Err(e) => (false, format!("Plugin '{}' failed: {}", name, e)),
+                }
+            }
         }
     }
 }
@@ -367,6 +536,14 @@ fn get_json_path(json: &serde_json::Value, path: &str) -> Option<serde_json::Val
     Some(current)
 }
 
+/// Variables an expression refers to (`$name`)
+fn expression_variables(expr: &str) -> Vec<&str> {
+    expr.split(|c: char| c.is_whitespace() || c == '=' || c == '!')
+        .filter_map(|token| token.strip_prefix('$'))
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
 /// Simple expression evaluator
 fn evaluate_expression(expr: &str, context: &ExecutionContext) -> bool {
     let expr = expr.trim();
@@ -437,9 +614,10 @@ fn evaluate_operand(operand: &str, context: &ExecutionContext) -> String {
 }
 
 /// Edge type for conditional workflow connections
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub enum EdgeType {
     /// Standard data flow edge
+    #[default]
     DataFlow,
     /// Conditional edge (only followed if condition is true)
     Conditional { condition: ExecutionCondition },
@@ -449,12 +627,6 @@ pub enum EdgeType {
     OnSuccess,
 }
 
-impl Default for EdgeType {
-    fn default() -> Self {
-        Self::DataFlow
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -573,4 +745,81 @@ mod tests {
         let result = condition.evaluate(&ctx, &statuses, &[]);
         assert!(result.should_execute); // NOT(failed) = should execute
     }
+
+    #[test]
+    fn test_output_tagged_condition() {
+        let ctx = create_test_context();
+        let statuses = create_test_statuses();
+        ctx.store_output(crate::workflow::context::AgentOutput {
+            agent_id: Uuid::new_v4(),
+            node_id: "node-1".to_string(),
+            agent_role: "security".to_string(),
+            data: crate::workflow::context::OutputData::Text("No findings".to_string()),
+            timestamp: chrono::Utc::now(),
+            tags: vec![],
+        });
+        let predecessors = ["node-1".to_string(), "node-2".to_string()];
+
+        let condition = ExecutionCondition::OutputTagged {
+            tag: "security".to_string(),
+            predecessor_id: None,
+        };
+        assert!(condition.evaluate(&ctx, &statuses, &predecessors).should_execute);
+
+        let condition = ExecutionCondition::OutputTagged {
+            tag: "security".to_string(),
+            predecessor_id: Some("node-2".to_string()),
+        };
+        assert!(!condition.evaluate(&ctx, &statuses, &predecessors).should_execute);
+
+        let condition = ExecutionCondition::OutputTagged {
+            tag: "node:node-1".to_string(),
+            predecessor_id: None,
+        };
+        assert!(condition.evaluate(&ctx, &statuses, &predecessors).should_execute);
+    }
+
+    #[test]
+    fn test_result_records_consulted_values() {
+        let ctx = create_test_context();
+        let statuses = create_test_statuses();
+
+        let condition = ExecutionCondition::Or {
+            conditions: vec![
+                ExecutionCondition::Expression {
+                    expr: "$name == other".to_string(),
+                },
+                ExecutionCondition::VariableTruthy {
+                    variable: "missing".to_string(),
+                },
+                ExecutionCondition::OnSuccess {
+                    predecessor_id: "node-1".to_string(),
+                },
+            ],
+        };
+        let result = condition.evaluate(&ctx, &statuses, &[]);
+        assert!(result.should_execute);
+        assert_eq!(result.evaluated_conditions.len(), 4);
+        assert_eq!(
+            result.consulted,
+            vec![
+                ConsultedValue::Variable {
+                    name: "name".to_string(),
+                    value: Some(serde_json::json!("test")),
+                },
+                ConsultedValue::Variable {
+                    name: "missing".to_string(),
+                    value: None,
+                },
+                ConsultedValue::NodeStatus {
+                    node_id: "node-1".to_string(),
+                    status: Some(NodeExecutionStatus::Completed),
+                },
+            ]
+        );
+
+        // Carried in events and decisions as JSON
+        let json = serde_json::to_value(&result).unwrap();
+        assert_eq!(json["consulted"][2]["kind"], "node_status");
+    }
 }
@@ -0,0 +1,233 @@
+//! File conflict detection between parallel agent nodes.
+//!
+//! Each agent node's changes are the files its agents wrote, as their
+//! activity hooks logged them. Where agents get no hook, they are found by
+//! diffing `git status` snapshots (with content hashes) taken before and
+//! after the node runs, which also hold the edits of nodes running
+//! alongside. Provides:
+//! - Working tree snapshots and the files changed between two of them
+//! - Pairwise overlap detection across the nodes of a level
+//! - Resolution policies: report only, re-run the later node, or hand the
+//!   overlap to a merge agent
+
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+use std::path::Path;
+use tokio::process::Command;
+
+/// What to do when parallel nodes edit the same files
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "policy", rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    /// Emit `ConflictDetected` and carry on
+    Report,
+    /// Re-run the node that finished later, now that the other's edits are in place
+    Serialize,
+    /// Spawn an agent to reconcile the overlapping edits
+    Merge {
+        #[serde(default = "default_merge_role")]
+        role: String,
+        system_prompt: Option<String>,
+        model: Option<String>,
+    },
+}
+
+fn default_merge_role() -> String {
+    "implementer".to_string()
+}
+
+impl ConflictPolicy {
+    /// Short name used in events
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConflictPolicy::Report => "report",
+            ConflictPolicy::Serialize => "serialize",
+            ConflictPolicy::Merge { .. } => "merge",
+        }
+    }
+}
+
+/// Dirty files in a working tree with a hash of their contents
+/// (`None` for deleted files)
+pub type FileSnapshot = HashMap<String, Option<u64>>;
+
+/// Two nodes that changed the same files while running in parallel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileConflict {
+    /// The node that finished first
+    pub first: String,
+    /// The node that finished later; re-run under the serialize policy
+    pub second: String,
+    pub files: Vec<String>,
+}
+
+/// Snapshot the uncommitted files of the git repository at `dir`
+pub async fn snapshot(dir: &Path) -> Result<FileSnapshot, String> {
+    let output = Command::new("git")
+        .args(["status", "--porcelain=v1", "-z", "--untracked-files=all"])
+        .current_dir(dir)
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git status: {}", e))?;
+
+    if !output.status.success() {
+        return Err(format!("git status failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+
+    let paths = parse_porcelain(&String::from_utf8_lossy(&output.stdout));
+    Ok(paths
+        .into_iter()
+        .map(|path| {
+            let hash = std::fs::read(dir.join(&path)).ok().map(|bytes| {
+                let mut hasher = DefaultHasher::new();
+                bytes.hash(&mut hasher);
+                hasher.finish()
+            });
+            (path, hash)
+        })
+        .collect())
+}
+
+/// Paths from `git status --porcelain=v1 -z`; renames list both paths
+fn parse_porcelain(output: &str) -> Vec<String> {
+    let mut paths = Vec::new();
+    let mut entries = output.split('\0').filter(|e| !e.is_empty());
+
+    while let Some(entry) = entries.next() {
+        let Some((status, path)) = entry.get(..2).zip(entry.get(3..)) else {
+            continue;
+        };
+        paths.push(path.to_string());
+        // Renames and copies are followed by the original path
+        if status.contains('R') || status.contains('C') {
+            paths.extend(entries.next().map(str::to_string));
+        }
+    }
+
+    paths
+}
+
+/// Files whose state differs between two snapshots
+pub fn changed_files(before: &FileSnapshot, after: &FileSnapshot) -> BTreeSet<String> {
+    let appeared_or_changed = after.iter().filter(|(path, hash)| before.get(*path) != Some(hash));
+    let reverted = before.keys().filter(|path| !after.contains_key(*path));
+
+    appeared_or_changed
+        .map(|(path, _)| path.clone())
+        .chain(reverted.cloned())
+        .collect()
+}
+
+/// Overlaps between the files touched by each node, given in completion order
+pub fn detect_conflicts(touched: &[(String, BTreeSet<String>)]) -> Vec<FileConflict> {
+    let mut conflicts = Vec::new();
+
+    for (i, (first, first_files)) in touched.iter().enumerate() {
+        for (second, second_files) in &touched[i + 1..] {
+            let files: Vec<String> = first_files.intersection(second_files).cloned().collect();
+            if !files.is_empty() {
+                conflicts.push(FileConflict {
+                    first: first.clone(),
+                    second: second.clone(),
+                    files,
+                });
+            }
+        }
+    }
+
+    conflicts
+}
+
+/// Task for re-running a node after others edited the same files
+pub fn reconcile_prompt(task: &str, conflicts: &[&FileConflict]) -> String {
+    let mut by_node: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+    for conflict in conflicts {
+        by_node
+            .entry(&conflict.first)
+            .or_default()
+            .extend(conflict.files.iter().map(String::as_str));
+    }
+
+    let details: Vec<String> = by_node
+        .iter()
+        .map(|(node, files)| format!("- `{}` also changed: {}", node, files.iter().copied().collect::<Vec<_>>().join(", ")))
+        .collect();
+
+    format!(
+        "{}\n\n## Concurrent edits\n\nWhile you worked, other agents edited the same files:\n{}\n\n\
+         Re-read those files as they are now and redo your changes on top of theirs, \
+         keeping both sets of changes working.",
+        task,
+        details.join("\n")
+    )
+}
+
+/// Task for a merge agent reconciling every conflict of a level
+pub fn merge_prompt(original_prompt: &str, conflicts: &[FileConflict]) -> String {
+    let details: Vec<String> = conflicts
+        .iter()
+        .map(|c| format!("- `{}` and `{}`: {}", c.first, c.second, c.files.join(", ")))
+        .collect();
+
+    format!(
+        "Agents working in parallel on the task below edited the same files:\n{}\n\n\
+         Review each file, resolve any clashing or half-applied edits so that both \
+         agents' intended changes are present, and make sure the project still builds.\n\n\
+         ## Original task\n\n{}",
+        details.join("\n"),
+        original_prompt
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn files(paths: &[&str]) -> BTreeSet<String> {
+        paths.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_porcelain() {
+        let output = " M src/lib.rs\0?? notes.md\0R  new.rs\0old.rs\0D  gone.rs\0";
+        assert_eq!(parse_porcelain(output), vec!["src/lib.rs", "notes.md", "new.rs", "old.rs", "gone.rs"]);
+    }
+
+    #[test]
+    fn test_changed_files() {
+        let before: FileSnapshot = [("a.rs".to_string(), Some(1)), ("b.rs".to_string(), Some(2))].into();
+        let after: FileSnapshot = [
+            ("a.rs".to_string(), Some(1)),
+            ("c.rs".to_string(), Some(3)),
+            ("b.rs".to_string(), None),
+        ]
+        .into();
+        assert_eq!(changed_files(&before, &after), files(&["b.rs", "c.rs"]));
+
+        // A file reverted to its committed state drops out of git status
+        let reverted: FileSnapshot = [("a.rs".to_string(), Some(1))].into();
+        assert_eq!(changed_files(&before, &reverted), files(&["b.rs"]));
+    }
+
+    #[test]
+    fn test_detect_conflicts() {
+        let touched = vec![
+            ("api".to_string(), files(&["package.json", "src/api.ts"])),
+            ("ui".to_string(), files(&["src/app.tsx"])),
+            ("deps".to_string(), files(&["package.json", "src/app.tsx"])),
+        ];
+
+        let conflicts = detect_conflicts(&touched);
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].first, "api");
+        assert_eq!(conflicts[0].second, "deps");
+        assert_eq!(conflicts[0].files, vec!["package.json"]);
+        assert_eq!((conflicts[1].first.as_str(), conflicts[1].second.as_str()), ("ui", "deps"));
+
+        let prompt = reconcile_prompt("Upgrade deps", &conflicts.iter().collect::<Vec<_>>());
+        assert!(prompt.contains("- `api` also changed: package.json"));
+        assert!(prompt.contains("- `ui` also changed: src/app.tsx"));
+    }
+}
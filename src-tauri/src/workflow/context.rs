@@ -8,10 +8,22 @@
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
+use super::assertions::AssertionOutcome;
+use super::conditions::NodeDecision;
+use super::state::ExecutionStore;
+use super::streaming::{Publications, PUBLISHED_TAG};
+use super::test_results::TEST_RESULTS_TAG;
+
+/// Tag on what an agent produced before it failed or timed out. Such
+/// output is left out of a node's latest output and of aggregation, and
+/// is reached by asking for outputs with this tag.
+pub const PARTIAL_OUTPUT_TAG: &str = "partial";
+
 /// A single piece of data produced by an agent
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AgentOutput {
@@ -25,10 +37,23 @@ pub struct AgentOutput {
     pub data: OutputData,
     /// When this output was produced
     pub timestamp: DateTime<Utc>,
-    /// Optional tags for categorization
+    /// Tags for categorization and routing; the executor adds the agent's
+    /// role and `node:<node_id>` automatically
     pub tags: Vec<String>,
 }
 
+impl AgentOutput {
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t == tag)
+    }
+
+    /// Whether this is what the node produced, rather than partial output,
+    /// a section it published while running or test results parsed from it
+    pub fn is_complete(&self) -> bool {
+        ![PARTIAL_OUTPUT_TAG, PUBLISHED_TAG, TEST_RESULTS_TAG].iter().any(|tag| self.has_tag(tag))
+    }
+}
+
 /// Types of data an agent can output
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value")]
@@ -76,6 +101,23 @@ impl OutputData {
     }
 }
 
+/// The prompt an agent was given and what it answered, for one attempt
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeTranscript {
+    pub node_id: String,
+    pub agent_role: String,
+    pub system_prompt: Option<String>,
+    pub prompt: String,
+    pub response: Option<String>,
+    /// Model the agent was asked to run on; `None` for the CLI's default
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Attempt number, starting at 1
+    pub attempt: u32,
+    pub success: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
 /// Shared context for workflow execution
 pub struct ExecutionContext {
     /// Unique execution ID
@@ -90,6 +132,14 @@ pub struct ExecutionContext {
     variables: DashMap<String, serde_json::Value>,
     /// Execution metadata
     metadata: DashMap<String, String>,
+    /// Agent prompts and responses, keyed by node_id
+    transcripts: DashMap<String, Vec<NodeTranscript>>,
+    /// Results of assert nodes, in evaluation order
+    assertions: RwLock<Vec<AssertionOutcome>>,
+    /// Why each reached node ran or was skipped, keyed by node_id
+    decisions: DashMap<String, NodeDecision>,
+    /// Sections running nodes published for downstream nodes to start on
+    publications: Publications,
     /// When execution started
     pub started_at: DateTime<Utc>,
 }
@@ -104,15 +154,26 @@ impl ExecutionContext {
             outputs: DashMap::new(),
             variables: DashMap::new(),
             metadata: DashMap::new(),
+            transcripts: DashMap::new(),
+            assertions: RwLock::new(Vec::new()),
+            decisions: DashMap::new(),
+            publications: Publications::new(),
             started_at: Utc::now(),
         }
     }
 
-    /// Store output from an agent
-    pub fn store_output(&self, output: AgentOutput) {
+    /// Store output from an agent, tagging it with its role and node
+    pub fn store_output(&self, mut output: AgentOutput) {
+        let automatic = [output.agent_role.clone(), format!("node:{}", output.node_id)];
+        for tag in automatic {
+            if !tag.is_empty() && !output.has_tag(&tag) {
+                output.tags.push(tag);
+            }
+        }
+
         self.outputs
             .entry(output.node_id.clone())
-            .or_insert_with(Vec::new)
+            .or_default()
             .push(output);
     }
 
@@ -124,18 +185,31 @@ impl ExecutionContext {
             .unwrap_or_default()
     }
 
-    /// Get the latest output from a node
+    /// Get the latest complete output from a node, skipping partial ones
+    /// and published sections
     pub fn get_latest_output(&self, node_id: &str) -> Option<AgentOutput> {
         self.outputs
             .get(node_id)
-            .and_then(|v| v.last().cloned())
+            .and_then(|v| v.iter().rev().find(|o| o.is_complete()).cloned())
     }
 
-    /// Get outputs from multiple predecessor nodes (for aggregation)
+    /// Get the complete outputs from multiple predecessor nodes (for
+    /// aggregation)
     pub fn get_predecessor_outputs(&self, predecessor_ids: &[String]) -> Vec<AgentOutput> {
         predecessor_ids
             .iter()
             .flat_map(|id| self.get_node_outputs(id))
+            .filter(AgentOutput::is_complete)
+            .collect()
+    }
+
+    /// Outputs from the given nodes that carry `tag`, including those left
+    /// out of a node's output
+    pub fn get_tagged_outputs(&self, node_ids: &[String], tag: &str) -> Vec<AgentOutput> {
+        node_ids
+            .iter()
+            .flat_map(|id| self.get_node_outputs(id))
+            .filter(|o| o.has_tag(tag))
             .collect()
     }
 
@@ -160,6 +234,10 @@ impl ExecutionContext {
         context
     }
 
+    pub fn publications(&self) -> &Publications {
+        &self.publications
+    }
+
     /// Set a global variable
     pub fn set_variable(&self, key: &str, value: serde_json::Value) {
         self.variables.insert(key.to_string(), value);
@@ -188,6 +266,45 @@ impl ExecutionContext {
         self.metadata.get(key).map(|v| v.clone())
     }
 
+    /// Record one agent attempt
+    pub fn record_transcript(&self, transcript: NodeTranscript) {
+        self.transcripts
+            .entry(transcript.node_id.clone())
+            .or_default()
+            .push(transcript);
+    }
+
+    /// All recorded attempts, oldest first
+    pub fn get_transcripts(&self) -> Vec<NodeTranscript> {
+        let mut transcripts: Vec<NodeTranscript> = self
+            .transcripts
+            .iter()
+            .flat_map(|entry| entry.value().clone())
+            .collect();
+        transcripts.sort_by_key(|t| t.timestamp);
+        transcripts
+    }
+
+    /// Record why a node runs or is skipped, replacing an earlier decision
+    pub fn record_decision(&self, decision: NodeDecision) {
+        self.decisions.insert(decision.node_id.clone(), decision);
+    }
+
+    /// Why a node ran or was skipped, once it has been reached
+    pub fn get_decision(&self, node_id: &str) -> Option<NodeDecision> {
+        self.decisions.get(node_id).map(|d| d.clone())
+    }
+
+    /// Record an assert node's result
+    pub fn record_assertion(&self, outcome: AssertionOutcome) {
+        self.assertions.write().push(outcome);
+    }
+
+    /// All assertion results so far
+    pub fn get_assertions(&self) -> Vec<AssertionOutcome> {
+        self.assertions.read().clone()
+    }
+
     /// Build a prompt context for a downstream agent
     /// Includes relevant outputs from predecessor nodes
     pub fn build_agent_prompt(
@@ -195,6 +312,18 @@ impl ExecutionContext {
         base_task: &str,
         predecessor_ids: &[String],
         include_original_prompt: bool,
+    ) -> String {
+        let predecessor_context = self.aggregate_predecessor_context(predecessor_ids);
+        self.build_prompt_with_context(base_task, &predecessor_context, include_original_prompt)
+    }
+
+    /// Build a prompt around predecessor context that was already selected
+    /// and aggregated (e.g. by a node's aggregation config)
+    pub fn build_prompt_with_context(
+        &self,
+        base_task: &str,
+        predecessor_context: &str,
+        include_original_prompt: bool,
     ) -> String {
         let mut prompt = String::new();
 
@@ -207,9 +336,8 @@ impl ExecutionContext {
         }
 
         // Include context from predecessors
-        let predecessor_context = self.aggregate_predecessor_context(predecessor_ids);
         if !predecessor_context.is_empty() {
-            prompt.push_str(&predecessor_context);
+            prompt.push_str(predecessor_context);
         }
 
         // Include relevant variables
@@ -219,7 +347,7 @@ impl ExecutionContext {
             for (k, v) in vars {
                 prompt.push_str(&format!("{}: {}\n", k, v));
             }
-            prompt.push_str("\n");
+            prompt.push('\n');
         }
 
         // Add the actual task
@@ -294,6 +422,14 @@ impl ContextStore {
     pub fn remove(&self, execution_id: &Uuid) -> Option<Arc<ExecutionContext>> {
         self.contexts.remove(execution_id).map(|(_, v)| v)
     }
+
+    /// Drop contexts of executions `executions` no longer holds, so contexts
+    /// are bounded the same way executions are. Returns how many were dropped.
+    pub fn retain_executions(&self, executions: &ExecutionStore) -> usize {
+        let before = self.contexts.len();
+        self.contexts.retain(|execution_id, _| executions.get(execution_id).is_some());
+        before - self.contexts.len()
+    }
 }
 
 impl Default for ContextStore {
@@ -326,6 +462,59 @@ mod tests {
         assert_eq!(retrieved.unwrap().node_id, "node-1");
     }
 
+    #[test]
+    fn test_outputs_tagged_with_role_and_node() {
+        let ctx = ExecutionContext::new(Uuid::new_v4(), Uuid::new_v4(), "Test prompt".to_string());
+
+        ctx.store_output(AgentOutput {
+            agent_id: Uuid::new_v4(),
+            node_id: "audit".to_string(),
+            agent_role: "security".to_string(),
+            data: OutputData::Text("SQL injection in login".to_string()),
+            timestamp: Utc::now(),
+            tags: vec!["security".to_string()],
+        });
+
+        let output = ctx.get_latest_output("audit").unwrap();
+        assert_eq!(output.tags, vec!["security", "node:audit"]);
+
+        let ids = ["audit".to_string()];
+        assert_eq!(ctx.get_tagged_outputs(&ids, "security").len(), 1);
+        assert!(ctx.get_tagged_outputs(&ids, "performance").is_empty());
+    }
+
+    #[test]
+    fn test_partial_outputs_kept_apart() {
+        let ctx = ExecutionContext::new(Uuid::new_v4(), Uuid::new_v4(), "Test prompt".to_string());
+        let output = |text: &str, tags: Vec<String>| AgentOutput {
+            agent_id: Uuid::new_v4(),
+            node_id: "build".to_string(),
+            agent_role: "implementer".to_string(),
+            data: OutputData::Text(text.to_string()),
+            timestamp: Utc::now(),
+            tags,
+        };
+
+        ctx.store_output(output("Compiled 3 of 8 crates", vec![PARTIAL_OUTPUT_TAG.to_string()]));
+        let ids = ["build".to_string()];
+        assert!(ctx.get_latest_output("build").is_none());
+        assert!(ctx.get_predecessor_outputs(&ids).is_empty());
+        assert!(ctx.aggregate_predecessor_context(&ids).is_empty());
+        assert_eq!(ctx.get_tagged_outputs(&ids, PARTIAL_OUTPUT_TAG).len(), 1);
+
+        ctx.store_output(output("Build finished", Vec::new()));
+        ctx.store_output(output("Retry stopped at linking", vec![PARTIAL_OUTPUT_TAG.to_string()]));
+        assert_eq!(ctx.get_latest_output("build").unwrap().data.to_context_string(), "Build finished");
+        assert_eq!(ctx.get_predecessor_outputs(&ids).len(), 1);
+        assert_eq!(ctx.get_node_outputs("build").len(), 3);
+
+        // A section published while running neither shadows nor joins it
+        ctx.store_output(output("## API\nGET /users", vec![PUBLISHED_TAG.to_string(), "section:api".to_string()]));
+        assert_eq!(ctx.get_latest_output("build").unwrap().data.to_context_string(), "Build finished");
+        assert_eq!(ctx.get_predecessor_outputs(&ids).len(), 1);
+        assert_eq!(ctx.get_tagged_outputs(&ids, PUBLISHED_TAG).len(), 1);
+    }
+
     #[test]
     fn test_predecessor_aggregation() {
         let ctx = ExecutionContext::new(Uuid::new_v4(), Uuid::new_v4(), "Test prompt".to_string());
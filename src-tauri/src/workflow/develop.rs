@@ -0,0 +1,192 @@
+//! Watch mode for workflows being authored.
+//!
+//! While a workflow is being developed, every change to the stored copy
+//! (its graph, pins or concurrency group) is re-checked straight away, so
+//! the author sees new problems without starting a run. Provides:
+//! - One session per workflow; starting another replaces it
+//! - A polling task that notices changes by fingerprint and hands each new
+//!   revision to a callback until the session stops or the workflow is gone
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::commands::workflow::{Workflow, WORKFLOWS};
+
+/// Event carrying a revision's diagnostics
+pub const DIAGNOSTICS_EVENT: &str = "workflow-diagnostics";
+
+/// How often the stored workflow is compared with the last revision
+const POLL_INTERVAL_MS: u64 = 250;
+
+/// A workflow being watched
+pub struct DevSession {
+    pub id: Uuid,
+    pub workflow_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    revision: AtomicU64,
+    stopped: AtomicBool,
+}
+
+impl DevSession {
+    /// Revisions checked so far, the one at start included
+    pub fn revision(&self) -> u64 {
+        self.revision.load(Ordering::SeqCst)
+    }
+
+    fn next_revision(&self) -> u64 {
+        self.revision.fetch_add(1, Ordering::SeqCst) + 1
+    }
+
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.load(Ordering::SeqCst)
+    }
+
+    fn stop(&self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+
+    pub fn info(&self) -> DevSessionInfo {
+        DevSessionInfo {
+            session_id: self.id,
+            workflow_id: self.workflow_id,
+            started_at: self.started_at,
+            revision: self.revision(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DevSessionInfo {
+    pub session_id: Uuid,
+    pub workflow_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub revision: u64,
+}
+
+/// Watch sessions by workflow
+#[derive(Default)]
+pub struct DevSessions {
+    sessions: DashMap<Uuid, Arc<DevSession>>,
+}
+
+impl DevSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start watching `workflow_id`, at revision 0, stopping any session
+    /// already watching it
+    pub fn start(&self, workflow_id: Uuid) -> Arc<DevSession> {
+        let session = Arc::new(DevSession {
+            id: Uuid::new_v4(),
+            workflow_id,
+            started_at: Utc::now(),
+            revision: AtomicU64::new(0),
+            stopped: AtomicBool::new(false),
+        });
+        if let Some(previous) = self.sessions.insert(workflow_id, session.clone()) {
+            previous.stop();
+        }
+        session
+    }
+
+    /// Stop watching `workflow_id`; false if it wasn't watched
+    pub fn stop(&self, workflow_id: &Uuid) -> bool {
+        match self.sessions.remove(workflow_id) {
+            Some((_, session)) => {
+                session.stop();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget `session` once its task ends, unless a newer one replaced it
+    fn finish(&self, session: &DevSession) {
+        self.sessions.remove_if(&session.workflow_id, |_, current| current.id == session.id);
+    }
+
+    pub fn list(&self) -> Vec<DevSessionInfo> {
+        self.sessions.iter().map(|entry| entry.value().info()).collect()
+    }
+}
+
+lazy_static! {
+    pub static ref DEV_SESSIONS: DevSessions = DevSessions::new();
+}
+
+/// Hash of everything about `workflow` that a check looks at
+pub fn fingerprint(workflow: &Workflow) -> u64 {
+    let json = serde_json::to_vec(&(&workflow.graph, &workflow.concurrency, &workflow.model_pins)).unwrap_or_default();
+    json.iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// Call `on_change` with each new revision of the session's workflow. The
+/// workflow as it was when the session started counts as seen.
+pub fn spawn_watch<F>(session: Arc<DevSession>, mut on_change: F)
+where
+    F: FnMut(&Workflow, u64) + Send + 'static,
+{
+    let mut seen = WORKFLOWS.get(&session.workflow_id).map(|workflow| fingerprint(&workflow));
+    tauri::async_runtime::spawn(async move {
+        while !session.is_stopped() {
+            tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+            let Some(workflow) = WORKFLOWS.get(&session.workflow_id).map(|entry| entry.value().clone()) else {
+                log::info!("Workflow {} was removed; stopped watching it", session.workflow_id);
+                break;
+            };
+            let current = fingerprint(&workflow);
+            if seen != Some(current) && !session.is_stopped() {
+                seen = Some(current);
+                on_change(&workflow, session.next_revision());
+            }
+        }
+        DEV_SESSIONS.finish(&session);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sessions_and_fingerprints() {
+        let sessions = DevSessions::new();
+        let workflow_id = Uuid::new_v4();
+        let first = sessions.start(workflow_id);
+        let second = sessions.start(workflow_id);
+        assert!(first.is_stopped() && !second.is_stopped());
+
+        // The replaced session ending leaves the new one alone
+        sessions.finish(&first);
+        assert_eq!(sessions.list().len(), 1);
+        assert_eq!(second.next_revision(), 1);
+        assert!(sessions.stop(&workflow_id));
+        assert!(second.is_stopped() && !sessions.stop(&workflow_id));
+
+        let mut workflow = Workflow {
+            id: workflow_id,
+            name: "Draft".to_string(),
+            description: None,
+            graph: json!({"nodes": [], "edges": []}),
+            is_template: false,
+            created_at: Utc::now(),
+            concurrency: None,
+            model_pins: None,
+            env: Default::default(),
+        };
+        let before = fingerprint(&workflow);
+        workflow.name = "Renamed".to_string();
+        assert_eq!(fingerprint(&workflow), before);
+        workflow.graph["nodes"] = json!([{"id": "a", "data": {"label": "A"}}]);
+        assert_ne!(fingerprint(&workflow), before);
+    }
+}
@@ -1,41 +1,97 @@
-//! Enhanced workflow executor with full orchestration capabilities.
+//! The workflow execution engine. Both executors run on it: the basic one
+//! with `EnhancedExecutionConfig::basic()`, so a fix to the level loop,
+//! polling or events applies to every execution.
 //!
-//! Integrates:
+//! Integrates, as the execution's config enables them:
 //! - Inter-agent data flow via ExecutionContext
 //! - Retry logic with exponential backoff
 //! - Conditional execution and branching
 //! - Checkpointing for recovery
 //! - Output aggregation from parallel nodes
 //! - Adaptive replanning
-
-use std::collections::HashMap;
+//! - In-process script nodes (Rhai)
+//! - Wait-for-execution nodes for cross-execution pipelines
+//! - Per-level and per-group parallelism limits
+//! - Agent slots shared with every other execution, from the resource manager
+//! - Named resource locks that serialize nodes within a level
+//! - Deadline-aware scheduling
+//! - Report nodes that write or email an execution summary
+//! - Assert nodes for evaluation runs (deterministic checks or an LLM judge)
+//! - Quality gates that re-run a node with a judge's critique until it passes
+//! - Patch nodes that apply an agent's unified diff to the project
+//! - Test-driven self-correction rounds for agent nodes
+//! - Detection of parallel nodes editing the same files
+//! - Early starts on sections a running predecessor publishes
+//! - Orchestrator-planned executions
+
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::Duration;
 
-use chrono::Utc;
-use tauri::{AppHandle, Emitter, Manager};
-use tokio::sync::broadcast;
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use tauri::{AppHandle, Manager};
+use tokio::sync::{broadcast, Semaphore};
 use uuid::Uuid;
 
-use crate::commands::project::get_project_working_directory;
-use crate::process::manager::{AgentConfig, AgentManager, AgentStatus};
+use crate::commands::project::{get_project_name, get_project_working_directory};
+use crate::commands::workflow::{get_history_store, get_resource_manager};
+use crate::project::{DependencyGraph, ImpactScope, ImpactedFile, ISOLATED_WORKSPACES};
+use crate::integrations::{send_email, SmtpConfig};
+use crate::process::manager::{AgentConfig, AgentManager, AgentOwner, AgentStatus};
 use crate::process::AGENT_REGISTRY;
+use crate::settings::SETTINGS;
 use crate::state::AppState;
 
 use super::adaptive::AdaptivePlanningConfig;
-use super::aggregation::{AggregationStrategy, NodeAggregationConfig};
+use super::activity::{self, ACTIVITY_STORE};
+use super::aggregation::{AggregatedOutput, AggregationStrategy, NodeAggregationConfig};
+use super::anomaly::{self, AnomalyThresholds};
+use super::approvals::{ApprovalDecision, APPROVAL_STORE};
+use super::assertions::{self, AssertionCheck, AssertionOutcome, CheckResult};
+use super::auto_cancel::{self, AutoCancelPolicy, AUTO_CANCEL_POLICIES};
+use super::chaos::{self, ChaosConfig};
 use super::checkpoint::{CheckpointManager, CheckpointTrigger, ExecutionCheckpoint, NodeCheckpointState};
-use super::conditions::ExecutionCondition;
-use super::context::{AgentOutput, ContextStore, ExecutionContext, OutputData};
-use super::events::{WorkflowEvent, WORKFLOW_EVENT_NAME};
-use super::graph::WorkflowGraph;
+use super::concurrency::{ExecutionConcurrency, GroupTicket, EXECUTION_GROUPS};
+use super::conditions::{ExecutionCondition, NodeDecision};
+use super::conflicts::{self, ConflictPolicy, FileConflict};
+use super::env_vars::{EnvVar, ExecutionEnv, EXECUTION_ENVS};
+use super::environment;
+use super::context::{AgentOutput, ContextStore, ExecutionContext, NodeTranscript, OutputData, PARTIAL_OUTPUT_TAG};
+use super::events::WorkflowEvent;
+use super::execution_log::{LogLevel, EXECUTION_LOG};
+use super::gates::{self, GateResult, GATE_THRESHOLDS};
+use super::guardrails::{self, GuardrailConfig};
+use super::history;
+use super::journal;
+use super::locale;
+use super::graph::{NodeKind, ParsedNode, WorkflowGraph};
+use super::locks::{LockRequest, LOCK_MANAGER};
+use super::orchestrator;
+use super::output_format::{self, OutputFormat};
+use super::patch::{self, PatchMode};
+use super::model_pins::ModelPins;
+use super::messaging::{self, MessageBusConfig, MessageContent, MessageType, TopicSubscriber, MESSAGE_BUS_STORE};
+use super::report::{resolve_output_path, ExecutionReport};
+use super::result_cache::{self, NodeCacheConfig, CACHED_TAG, RESULT_CACHE};
+use super::rate_limit::{credential_label, RateLimitKey};
+use super::resources::{ResourceError, ResourceManager, ResourcePermit, TaskPriority};
 use super::retry::{FallbackStrategy, RetryConfig, RetryDecision, RetryState};
-use super::state::{ExecutionStatus, ExecutionStore, NodeExecutionStatus, WorkflowExecutionState};
+use super::rollback;
+use super::script::{self, ScriptInput, ScriptLimits};
+use super::self_correction::{self, SelfCorrectionConfig};
+use super::stages::{self, StageConfig, StageGate, StageProgress};
+use super::state::{release_execution, ExecutionStatus, ExecutionStore, NodeExecutionStatus, WorkflowExecutionState};
+use super::streaming::{self, PublishedSection, PUBLISHED_TAG};
+use super::supply_chain::{self, SUPPLY_CHAIN_REPORTS};
+use super::test_results::{self, TestFormat, TEST_RESULTS_TAG};
 
 /// Polling interval for checking agent completion
 const POLL_INTERVAL_MS: u64 = 500;
 /// Maximum time to wait for an agent to complete (10 minutes)
 const MAX_AGENT_WAIT_MS: u64 = 600_000;
+/// Workflow ID events report for orchestrated executions
+const ORCHESTRATED: &str = "orchestrated";
 
 /// Enhanced configuration for workflow execution
 #[derive(Debug, Clone)]
@@ -54,6 +110,39 @@ pub struct EnhancedExecutionConfig {
     pub include_original_prompt: bool,
     /// Pass predecessor outputs to downstream agents
     pub enable_data_flow: bool,
+    /// Maximum nodes running at once within a level (None = unlimited)
+    pub max_parallel_nodes_per_level: Option<usize>,
+    /// Limits for named concurrency groups declared on nodes.
+    /// Groups without an entry here allow one node at a time.
+    pub concurrency_groups: HashMap<String, usize>,
+    /// Optional execution deadline
+    pub deadline: Option<DeadlineConfig>,
+    /// Variables seeded into the execution context before the first level
+    pub initial_variables: HashMap<String, serde_json::Value>,
+    /// Detect parallel agent nodes editing the same files (None = off)
+    pub conflict_policy: Option<ConflictPolicy>,
+    /// Message cap, TTL and persistence for the execution's message bus
+    pub message_bus: MessageBusConfig,
+    /// Topics each agent role subscribes to
+    pub role_topics: HashMap<String, Vec<String>>,
+    /// Run agents in a copy of the project; changes reach the project only
+    /// once approved
+    pub isolated_workspace: bool,
+    /// Conditions and output aggregation for the graph's stages
+    pub stages: HashMap<String, StageConfig>,
+    /// Group the execution runs in one at a time with other executions
+    pub concurrency: Option<ExecutionConcurrency>,
+    /// Models pinned per agent role
+    pub model_pins: Option<ModelPins>,
+    /// Faults to inject into agent nodes, for testing failure handling
+    pub chaos: Option<ChaosConfig>,
+    /// Time limits replacing the project's auto-cancel policy
+    pub auto_cancel: Option<AutoCancelPolicy>,
+    /// Checks on every agent node's output before it is used
+    pub guardrails: Option<GuardrailConfig>,
+    /// Environment for agents and commands: the workflow's defaults with
+    /// the run's overrides on top. The project's `.env` goes below both.
+    pub env: BTreeMap<String, EnvVar>,
 }
 
 impl Default for EnhancedExecutionConfig {
@@ -69,6 +158,117 @@ impl Default for EnhancedExecutionConfig {
             },
             include_original_prompt: true,
             enable_data_flow: true,
+            max_parallel_nodes_per_level: None,
+            concurrency_groups: HashMap::new(),
+            deadline: None,
+            initial_variables: HashMap::new(),
+            conflict_policy: None,
+            message_bus: MessageBusConfig::default(),
+            role_topics: HashMap::new(),
+            isolated_workspace: false,
+            stages: HashMap::new(),
+            concurrency: None,
+            model_pins: None,
+            chaos: None,
+            auto_cancel: None,
+            guardrails: None,
+            env: BTreeMap::new(),
+        }
+    }
+}
+
+impl EnhancedExecutionConfig {
+    /// How the basic executor runs workflows: agents only see their own
+    /// task, failed nodes aren't retried and nothing is checkpointed
+    pub fn basic() -> Self {
+        Self {
+            retry: RetryConfig::no_retry(),
+            checkpoint_trigger: CheckpointTrigger::Manual,
+            adaptive: AdaptivePlanningConfig {
+                enabled: false,
+                ..AdaptivePlanningConfig::default()
+            },
+            enable_data_flow: false,
+            ..Self::default()
+        }
+    }
+}
+
+/// Copy the execution's project into an isolated workspace
+async fn create_isolated_workspace(state: &WorkflowExecutionState) -> Result<(), String> {
+    let project_dir = get_project_working_directory(&state.project_id)
+        .ok_or_else(|| format!("Project not found: {}", state.project_id))?;
+    let execution_id = state.execution_id;
+
+    tokio::task::spawn_blocking(move || {
+        ISOLATED_WORKSPACES
+            .create(execution_id, std::path::Path::new(&project_dir))
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Where an execution's agents work: its isolated workspace if it has one,
+/// otherwise the project directory
+fn execution_working_directory(state: &WorkflowExecutionState) -> Option<String> {
+    ISOLATED_WORKSPACES
+        .working_dir(&state.execution_id)
+        .map(|dir| dir.to_string_lossy().into_owned())
+        .or_else(|| get_project_working_directory(&state.project_id))
+}
+
+/// Deadline-aware scheduling options
+#[derive(Debug, Clone)]
+pub struct DeadlineConfig {
+    /// Time budget for the whole execution in milliseconds
+    pub deadline_ms: u64,
+    /// Halve retry budgets once the execution falls behind
+    pub reduce_retries_when_behind: bool,
+    /// Model for non-critical nodes once behind (None = keep the node's model)
+    pub fast_model: Option<String>,
+}
+
+impl DeadlineConfig {
+    pub fn new(deadline_ms: u64) -> Self {
+        Self {
+            deadline_ms,
+            reduce_retries_when_behind: true,
+            fast_model: None,
+        }
+    }
+
+    /// Project the total run time from the levels finished so far.
+    /// Returns the projection if it overruns the deadline.
+    pub fn projected_overrun(&self, elapsed_ms: u64, levels_done: usize, total_levels: usize) -> Option<u64> {
+        let projected_ms = if levels_done == 0 {
+            elapsed_ms
+        } else {
+            elapsed_ms.saturating_mul(total_levels as u64) / levels_done as u64
+        };
+
+        if projected_ms > self.deadline_ms || elapsed_ms > self.deadline_ms {
+            Some(projected_ms)
+        } else {
+            None
+        }
+    }
+
+    /// Tighten a node's settings while the execution is behind schedule
+    fn apply_pressure(&self, on_critical_path: bool, config: &mut EnhancedExecutionConfig, node_config: &mut EnhancedNodeConfig) {
+        if self.reduce_retries_when_behind {
+            let halve = |retry: &mut RetryConfig| retry.max_attempts = (retry.max_attempts / 2).max(1);
+            halve(&mut config.retry);
+            if let Some(retry) = node_config.retry.as_mut() {
+                halve(retry);
+            }
+        }
+
+        if !on_critical_path {
+            if let Some(model) = &self.fast_model {
+                node_config.model = Some(model.clone());
+            }
         }
     }
 }
@@ -88,9 +288,89 @@ pub struct EnhancedNodeConfig {
     pub system_prompt_override: Option<String>,
     /// Tags to add to output
     pub output_tags: Vec<String>,
+    /// Named concurrency group this node runs in
+    pub concurrency_group: Option<String>,
+    /// Model override for the agent
+    pub model: Option<String>,
+    /// Run a test command after the agent and feed failures back to it
+    pub self_correction: Option<SelfCorrectionConfig>,
+    /// Project resources locked while the node runs
+    pub locks: Vec<LockRequest>,
+    /// Message topics this node receives in its prompt
+    pub subscribe_topics: Vec<String>,
+    /// Topic the node's output is published to when it succeeds
+    pub publish_topic: Option<String>,
+    /// Wait for someone to approve the node before it runs
+    pub require_approval: bool,
+    /// Start once these sections are published instead of waiting for
+    /// their nodes to finish
+    pub start_on_published: Vec<PublishedSection>,
+    /// Format the agent must answer in; other answers fail the attempt
+    pub expected_output_format: Option<OutputFormat>,
+    /// JSON Schema the answer must match; implies the JSON format
+    pub output_schema: Option<serde_json::Value>,
+    /// Parse the node's output as test runner output
+    pub test_results: Option<TestFormat>,
+    /// List the files the change impacts in the prompt and ask the agent
+    /// to stay within them
+    pub impact_scope: Option<ImpactScope>,
+    /// Reuse the result of an earlier run while the files it reads are
+    /// unchanged
+    pub cache: Option<NodeCacheConfig>,
+    /// Output checks (overrides workflow default)
+    pub guardrails: Option<GuardrailConfig>,
+}
+
+impl EnhancedNodeConfig {
+    /// Format the agent is asked for, JSON when only a schema is set
+    pub fn output_format(&self) -> Option<OutputFormat> {
+        self.expected_output_format
+            .or_else(|| self.output_schema.as_ref().map(|_| OutputFormat::Json))
+    }
+
+    /// Format instructions for the prompt, with the schema if there is one
+    pub fn output_instructions(&self) -> Option<String> {
+        let format = self.output_format()?;
+        Some(match &self.output_schema {
+            Some(schema) => format!("{}\n{}", format.instructions(), output_format::schema_instructions(schema)),
+            None => format.instructions().to_string(),
+        })
+    }
+}
+
+/// The workflow an execution runs, as its events report it
+#[derive(Debug, Clone)]
+pub struct WorkflowIdentity {
+    /// Saved workflow, or nil for a graph run directly
+    pub id: Uuid,
+    /// Reported as the workflow ID in events
+    pub label: String,
+    pub name: String,
+}
+
+impl WorkflowIdentity {
+    /// A saved workflow, reported by its ID
+    pub fn saved(id: Uuid, name: String) -> Self {
+        Self {
+            id,
+            label: id.to_string(),
+            name,
+        }
+    }
+
+    /// A graph with no saved workflow behind it
+    pub fn dynamic(label: &str, name: &str) -> Self {
+        Self {
+            id: Uuid::nil(),
+            label: label.to_string(),
+            name: name.to_string(),
+        }
+    }
 }
 
-/// Enhanced workflow executor
+/// The workflow engine. The basic executor runs on it too, with
+/// `EnhancedExecutionConfig::basic()`.
+#[derive(Clone)]
 pub struct EnhancedWorkflowExecutor {
     app: AppHandle,
     store: Arc<ExecutionStore>,
@@ -119,6 +399,20 @@ impl EnhancedWorkflowExecutor {
         input_prompt: String,
         config: EnhancedExecutionConfig,
         node_configs: HashMap<String, EnhancedNodeConfig>,
+    ) -> Result<Uuid, String> {
+        let workflow = WorkflowIdentity::dynamic("enhanced", "Enhanced Workflow");
+        self.execute_graph(workflow, graph, project_id, input_prompt, config, node_configs)
+    }
+
+    /// Execute `graph` on behalf of `workflow`
+    pub fn execute_graph(
+        &self,
+        workflow: WorkflowIdentity,
+        graph: WorkflowGraph,
+        project_id: Uuid,
+        input_prompt: String,
+        config: EnhancedExecutionConfig,
+        mut node_configs: HashMap<String, EnhancedNodeConfig>,
     ) -> Result<Uuid, String> {
         // Validate graph
         if graph.is_empty() {
@@ -129,60 +423,268 @@ impl EnhancedWorkflowExecutor {
         let execution_levels = graph.compute_execution_levels()
             .map_err(|e| e.to_string())?;
 
-        // Create execution ID
-        let execution_id = Uuid::new_v4();
-
-        // Create execution state
-        let state = WorkflowExecutionState::new(
-            execution_id,
-            Uuid::nil(), // Dynamic workflow
-            project_id,
-            input_prompt.clone(),
-            execution_levels.clone(),
-        );
+        let project_dir = get_project_working_directory(&project_id);
+        let env = ExecutionEnv::resolve(project_dir.as_deref().map(std::path::Path::new), &config.env)?;
 
-        let execution_state = self.store.insert(state);
+        let substitutions = match &config.model_pins {
+            Some(pins) => pins.apply(&graph, &mut node_configs, &SETTINGS.get().backends.available_models)?,
+            None => Vec::new(),
+        };
 
-        // Create execution context for data flow
-        let context = self.context_store.create(execution_id, project_id, input_prompt.clone());
+        let execution_id = Uuid::new_v4();
+        let (execution_state, context) =
+            self.register(execution_id, workflow.id, project_id, &input_prompt, execution_levels, &config);
+        for substitution in &substitutions {
+            log::warn!(
+                "Execution {}: model '{}' pinned for role '{}' is unavailable, using {}",
+                execution_id,
+                substitution.pinned,
+                substitution.role,
+                substitution.used.as_deref().unwrap_or("the default model")
+            );
+        }
+        *execution_state.model_substitutions.write() = substitutions;
+        let ticket = match &config.concurrency {
+            Some(concurrency) => match EXECUTION_GROUPS.join(concurrency, &execution_state) {
+                Ok(ticket) => Some(ticket),
+                Err(e) => {
+                    self.unregister(&execution_id);
+                    return Err(e);
+                }
+            },
+            None => None,
+        };
+        for warning in env.warnings() {
+            log::warn!("Execution {}: environment variable left out: {}", execution_id, warning);
+            EXECUTION_LOG.log(
+                execution_id,
+                None,
+                LogLevel::Warn,
+                format!("Environment variable left out: {}", warning),
+                None,
+            );
+        }
+        EXECUTION_ENVS.set(execution_id, env);
+        subscribe_node_topics(&execution_id, &node_configs);
 
         // Emit execution started event
-        let total_nodes = graph.node_count();
         self.emit_event(WorkflowEvent::ExecutionStarted {
             execution_id: execution_id.to_string(),
-            workflow_id: "enhanced".to_string(),
-            workflow_name: "Enhanced Workflow".to_string(),
-            total_nodes,
+            workflow_id: workflow.label.clone(),
+            workflow_name: workflow.name.clone(),
+            total_nodes: graph.node_count(),
         });
+        #[cfg(feature = "database")]
+        if let Some(app_state) = self.app.try_state::<Arc<AppState>>() {
+            crate::db::notify::publish(
+                &app_state,
+                crate::db::notify::InstanceEvent::ExecutionStarted {
+                    execution_id,
+                    workflow_id: workflow.id,
+                    workflow_name: workflow.name.clone(),
+                    project_id,
+                },
+            );
+        }
 
-        // Clone what we need for the async task
+        // Shared by every node task rather than copied into each
+        let graph = Arc::new(graph);
+        let engine = self.clone();
         let app = self.app.clone();
-        let store = self.store.clone();
-        let context_store = self.context_store.clone();
-        let checkpoint_manager = self.checkpoint_manager.as_ref().map(|_| {
-            CheckpointManager::new(CheckpointManager::default_checkpoint_dir()).ok()
-        }).flatten();
+        let chaos = config.chaos.is_some();
 
         // Spawn the execution task
         tokio::spawn(async move {
             run_enhanced_execution(
-                app,
-                store,
-                context_store,
-                checkpoint_manager,
-                execution_state,
-                context,
-                graph,
+                engine.app,
+                engine.checkpoint_manager,
+                workflow.label,
+                execution_state.clone(),
+                context.clone(),
+                graph.clone(),
                 input_prompt,
                 config,
                 node_configs,
+                ticket,
             )
             .await;
+            record_history(&app, &execution_state, &context, &graph, &workflow.name, chaos).await;
+            #[cfg(feature = "database")]
+            if let Some(app_state) = app.try_state::<Arc<AppState>>() {
+                crate::db::notify::publish(
+                    &app_state,
+                    crate::db::notify::InstanceEvent::ExecutionFinished { execution_id },
+                );
+            }
         });
 
         Ok(execution_id)
     }
 
+    /// Have the orchestrator plan the work for `input_prompt`, then execute
+    /// the plan. The execution is only registered once there is a plan.
+    pub fn execute_orchestrated(&self, project_id: Uuid, input_prompt: String, config: EnhancedExecutionConfig) -> Uuid {
+        let execution_id = Uuid::new_v4();
+
+        self.emit_event(WorkflowEvent::ExecutionStarted {
+            execution_id: execution_id.to_string(),
+            workflow_id: ORCHESTRATED.to_string(),
+            workflow_name: "Orchestrated Workflow".to_string(),
+            total_nodes: 1, // Just orchestrator initially
+        });
+
+        let engine = self.clone();
+        tokio::spawn(async move {
+            engine.run_orchestrated(execution_id, project_id, input_prompt, config).await;
+        });
+
+        execution_id
+    }
+
+    /// Plan with the orchestrator agent, then run the plan's graph
+    async fn run_orchestrated(self, execution_id: Uuid, project_id: Uuid, input_prompt: String, config: EnhancedExecutionConfig) {
+        let app = &self.app;
+        let execution_id_str = execution_id.to_string();
+        let fail = |error: String, failed_nodes: Vec<String>| {
+            emit_event(app, WorkflowEvent::ExecutionFailed {
+                execution_id: execution_id_str.clone(),
+                workflow_id: ORCHESTRATED.to_string(),
+                error,
+                failed_nodes,
+            });
+        };
+
+        // Phase 1: Run orchestrator to create a plan
+        emit_event(app, WorkflowEvent::NodeStatusChanged {
+            execution_id: execution_id_str.clone(),
+            node_id: "orchestrator".to_string(),
+            status: NodeExecutionStatus::Running,
+            progress: 0,
+            agent_id: None,
+            error: None,
+        });
+
+        log::info!("Starting orchestrator planning phase for execution {}", execution_id);
+
+        let plan = match orchestrator::run_orchestrator_planning(app, &execution_id_str, project_id, &input_prompt).await {
+            Ok(plan) => {
+                emit_event(app, WorkflowEvent::NodeCompleted {
+                    execution_id: execution_id_str.clone(),
+                    node_id: "orchestrator".to_string(),
+                    output: Some(plan.project_summary.clone()),
+                });
+                plan
+            }
+            Err(e) => {
+                log::error!("Orchestrator planning failed: {}", e);
+                emit_event(app, WorkflowEvent::NodeFailed {
+                    execution_id: execution_id_str.clone(),
+                    node_id: "orchestrator".to_string(),
+                    error: e.clone(),
+                });
+                fail(format!("Orchestrator planning failed: {}", e), vec!["orchestrator".to_string()]);
+                return;
+            }
+        };
+
+        // Phase 2: Convert plan to graph
+        let graph = orchestrator::plan_to_graph(&plan);
+        orchestrator::emit_dynamic_graph_events(app, &execution_id_str, &graph);
+
+        let execution_levels = match graph.compute_execution_levels() {
+            Ok(levels) => levels,
+            Err(e) => {
+                log::error!("Failed to compute execution levels: {}", e);
+                fail(format!("Invalid plan graph: {}", e), Vec::new());
+                return;
+            }
+        };
+
+        let (execution_state, context) =
+            self.register(execution_id, Uuid::nil(), project_id, &input_prompt, execution_levels, &config);
+        let ticket = match &config.concurrency {
+            Some(concurrency) => match EXECUTION_GROUPS.join(concurrency, &execution_state) {
+                Ok(ticket) => Some(ticket),
+                Err(e) => {
+                    execution_state.set_status(ExecutionStatus::Failed);
+                    fail(e, Vec::new());
+                    return;
+                }
+            },
+            None => None,
+        };
+
+        emit_event(app, WorkflowEvent::ProgressUpdate {
+            execution_id: execution_id_str.clone(),
+            completed_nodes: 1, // Orchestrator done
+            total_nodes: graph.node_count() + 1, // +1 for orchestrator
+            progress_percent: (100 / (graph.node_count() + 1)) as u8,
+        });
+
+        log::info!("Orchestrator created plan with {} tasks, starting execution", graph.node_count());
+
+        // Phase 3: Execute the dynamic graph
+        let graph = Arc::new(graph);
+        let chaos = config.chaos.is_some();
+        run_enhanced_execution(
+            app.clone(),
+            self.checkpoint_manager,
+            ORCHESTRATED.to_string(),
+            execution_state.clone(),
+            context.clone(),
+            graph.clone(),
+            input_prompt,
+            config,
+            HashMap::new(),
+            ticket,
+        )
+        .await;
+        record_history(app, &execution_state, &context, &graph, "Orchestrated Workflow", chaos).await;
+    }
+
+    /// Store the state, data-flow context and message bus of a new execution
+    fn register(
+        &self,
+        execution_id: Uuid,
+        workflow_id: Uuid,
+        project_id: Uuid,
+        input_prompt: &str,
+        execution_levels: Vec<Vec<String>>,
+        config: &EnhancedExecutionConfig,
+    ) -> (Arc<WorkflowExecutionState>, Arc<ExecutionContext>) {
+        let state = WorkflowExecutionState::new(
+            execution_id,
+            workflow_id,
+            project_id,
+            input_prompt.to_string(),
+            execution_levels,
+        );
+        let execution_state = self.store.insert(state);
+
+        // Create execution context for data flow
+        let context = self.context_store.create(execution_id, project_id, input_prompt.to_string());
+        // Inserting may have evicted old executions; their contexts go too
+        self.context_store.retain_executions(&self.store);
+        for (key, value) in &config.initial_variables {
+            context.set_variable(key, value.clone());
+        }
+        let bus = MESSAGE_BUS_STORE.create_with_config(execution_id, config.message_bus.clone());
+        for (role, topics) in &config.role_topics {
+            for topic in topics {
+                bus.subscribe_topic(topic, TopicSubscriber::Role(role.clone()));
+            }
+        }
+
+        (execution_state, context)
+    }
+
+    /// Undo `register` for an execution that never started
+    fn unregister(&self, execution_id: &Uuid) {
+        self.store.remove(execution_id);
+        self.context_store.remove(execution_id);
+        release_execution(execution_id);
+    }
+
     /// Get the context store
     pub fn context_store(&self) -> &Arc<ContextStore> {
         &self.context_store
@@ -193,30 +695,151 @@ impl EnhancedWorkflowExecutor {
         &self.store
     }
 
+    /// Drop executions that finished at or before `cutoff` with their
+    /// contexts and everything else they left behind; returns their IDs
+    pub fn purge_finished(&self, cutoff: DateTime<Utc>) -> Vec<Uuid> {
+        let purged = self.store.purge_finished(cutoff);
+        self.context_store.retain_executions(&self.store);
+        purged
+    }
+
+    /// Save a checkpoint of an execution as it stands, resuming at the
+    /// first level that still has unfinished nodes
+    pub fn checkpoint_now(&self, execution_id: &Uuid) -> Result<Uuid, String> {
+        let manager = self
+            .checkpoint_manager
+            .as_ref()
+            .ok_or_else(|| "Checkpoint directory unavailable".to_string())?;
+        let state = self
+            .store
+            .get(execution_id)
+            .ok_or_else(|| format!("Execution not found: {}", execution_id))?;
+        let context = self
+            .context_store
+            .get(execution_id)
+            .ok_or_else(|| format!("No context for execution {}", execution_id))?;
+
+        let current_level = state
+            .execution_levels
+            .iter()
+            .position(|level| {
+                level
+                    .iter()
+                    .any(|node_id| state.get_node_state(node_id).map_or(true, |ns| !ns.is_terminal()))
+            })
+            .unwrap_or(state.execution_levels.len());
+
+        let checkpoint = create_checkpoint(&state, &context, current_level)?;
+        manager
+            .save(&checkpoint)
+            .map_err(|e| format!("Failed to save checkpoint: {}", e))?;
+        Ok(checkpoint.id)
+    }
+
     fn emit_event(&self, event: WorkflowEvent) {
-        let _ = self.app.emit(WORKFLOW_EVENT_NAME, &event);
+        journal::publish(&self.app, event);
+    }
+}
+
+/// Subscribe nodes to the topics their config names
+fn subscribe_node_topics(execution_id: &Uuid, node_configs: &HashMap<String, EnhancedNodeConfig>) {
+    let Some(bus) = MESSAGE_BUS_STORE.get(execution_id) else {
+        return;
+    };
+    for (node_id, node_config) in node_configs {
+        for topic in &node_config.subscribe_topics {
+            bus.subscribe_topic(topic, TopicSubscriber::Node(node_id.clone()));
+        }
     }
 }
 
-/// Main enhanced execution loop
+/// Main execution loop
+#[allow(clippy::too_many_arguments)]
 async fn run_enhanced_execution(
     app: AppHandle,
-    _store: Arc<ExecutionStore>,
-    _context_store: Arc<ContextStore>,
     checkpoint_manager: Option<CheckpointManager>,
+    workflow_label: String,
     state: Arc<WorkflowExecutionState>,
     context: Arc<ExecutionContext>,
-    graph: WorkflowGraph,
+    graph: Arc<WorkflowGraph>,
     input_prompt: String,
-    config: EnhancedExecutionConfig,
+    mut config: EnhancedExecutionConfig,
     node_configs: HashMap<String, EnhancedNodeConfig>,
+    ticket: Option<GroupTicket>,
 ) {
     let execution_id = state.execution_id;
     let _project_id = state.project_id;
+    // Shared with quality gates, which re-run other nodes with their config
+    let node_configs = Arc::new(node_configs);
+
+    // Stay pending until the concurrency group is free, holding it to the end
+    let _turn = match ticket {
+        Some(ticket) => match ticket.wait().await {
+            Some(turn) => Some(turn),
+            None => {
+                log::info!("Execution {} cancelled while queued in its concurrency group", execution_id);
+                emit_event(&app, WorkflowEvent::ExecutionCancelled {
+                    execution_id: execution_id.to_string(),
+                    workflow_id: workflow_label.clone(),
+                    reason: state.get_cancel_reason(),
+                });
+                return;
+            }
+        },
+        None => None,
+    };
 
     // Mark as running
     state.set_status(ExecutionStatus::Running);
 
+    // The project's limits, unless the execution was started with others
+    let limits = *config.auto_cancel.get_or_insert_with(|| AUTO_CANCEL_POLICIES.get(&state.project_id));
+    if let Some(max_ms) = limits.max_execution_ms {
+        auto_cancel::watch_execution(state.clone(), max_ms);
+    }
+
+    if let Some(chaos) = &config.chaos {
+        EXECUTION_LOG.log(
+            execution_id,
+            None,
+            LogLevel::Warn,
+            format!("Chaos mode is on; agent nodes will fail, stall and truncate on purpose (seed {})", chaos.seed_for(&execution_id)),
+            Some(serde_json::json!(chaos)),
+        );
+    }
+
+    if config.isolated_workspace {
+        if let Err(e) = create_isolated_workspace(&state).await {
+            log::error!("Execution {} could not be isolated: {}", execution_id, e);
+            state.set_status(ExecutionStatus::Failed);
+            emit_event(&app, WorkflowEvent::ExecutionFailed {
+                execution_id: execution_id.to_string(),
+                workflow_id: workflow_label.clone(),
+                error: format!("Failed to create isolated workspace: {}", e),
+                failed_nodes: Vec::new(),
+            });
+            return;
+        }
+    }
+
+    // Snapshotted around agent nodes for the activity feed and conflict detection
+    let project_dir = execution_working_directory(&state).map(std::path::PathBuf::from);
+
+    let node_models = node_configs
+        .iter()
+        .filter_map(|(node_id, config)| Some((node_id.clone(), config.model.clone()?)))
+        .collect();
+    let fast_model = config.deadline.as_ref().and_then(|deadline| deadline.fast_model.clone());
+    *state.environment.write() = Some(environment::capture(project_dir.as_deref(), node_models, fast_model).await);
+
+    // Keep the working tree as it is now so the execution can be undone.
+    // An isolated execution is undone by discarding its workspace.
+    if let (Some(dir), false) = (&project_dir, config.isolated_workspace) {
+        if let Err(e) = rollback::record_base(dir, &execution_id).await {
+            log::debug!("No rollback point for execution {}: {}", execution_id, e);
+        }
+    }
+
     // Get cancellation receiver
     let mut cancel_rx = state.subscribe_cancel();
 
@@ -226,9 +849,36 @@ async fn run_enhanced_execution(
 
     // Track node statuses for condition evaluation
     let mut node_statuses: HashMap<String, NodeExecutionStatus> = HashMap::new();
+    let mut stage_progress = StageProgress::default();
 
     let start_time = std::time::Instant::now();
 
+    // Semaphores for named concurrency groups, shared across levels
+    let group_semaphores: HashMap<String, Arc<Semaphore>> = node_configs
+        .values()
+        .filter_map(|c| c.concurrency_group.clone())
+        .map(|group| {
+            let limit = config.concurrency_groups.get(&group).copied().unwrap_or(1).max(1);
+            (group, Arc::new(Semaphore::new(limit)))
+        })
+        .collect();
+
+    // Critical path for deadline scheduling
+    let critical_path = match config.deadline {
+        Some(_) => graph.critical_path_in_levels(&state.execution_levels, |_| 1),
+        None => Vec::new(),
+    };
+    let critical_nodes: std::collections::HashSet<&String> = critical_path.iter().collect();
+    let total_levels = state.execution_levels.len();
+    let mut behind_schedule = false;
+
+    // Only the output of nodes others start on is scanned for sections
+    for section in node_configs.values().flat_map(|c| &c.start_on_published) {
+        context.publications().watch(&section.node_id);
+    }
+    // Nodes of later levels already started on published sections
+    let mut early_handles: HashMap<String, tokio::task::JoinHandle<Result<(), String>>> = HashMap::new();
+
     // Execute level by level
     for (level_idx, level_node_ids) in state.execution_levels.iter().enumerate() {
         // Check for cancellation before starting level
@@ -236,22 +886,82 @@ async fn run_enhanced_execution(
             log::info!("Execution {} cancelled before level {}", execution_id, level_idx);
             emit_event(&app, WorkflowEvent::ExecutionCancelled {
                 execution_id: execution_id.to_string(),
-                workflow_id: "enhanced".to_string(),
+                workflow_id: workflow_label.clone(),
+                reason: state.get_cancel_reason(),
             });
             return;
         }
 
+        // Re-check the deadline projection, warning when we first fall behind
+        if let Some(ref deadline) = config.deadline {
+            let elapsed_ms = start_time.elapsed().as_millis() as u64;
+            let overrun = deadline.projected_overrun(elapsed_ms, level_idx, total_levels);
+
+            if let (Some(projected_ms), false) = (overrun, behind_schedule) {
+                log::warn!("Execution {} projected to take {}ms, deadline is {}ms",
+                          execution_id, projected_ms, deadline.deadline_ms);
+                emit_event(&app, WorkflowEvent::DeadlineAtRisk {
+                    execution_id: execution_id.to_string(),
+                    deadline_ms: deadline.deadline_ms,
+                    elapsed_ms,
+                    projected_ms,
+                    critical_path: critical_path.clone(),
+                });
+            }
+            behind_schedule = overrun.is_some();
+        }
+
         // Determine which nodes to run, skip, or exclude based on conditions
         let mut nodes_to_run = Vec::new();
         let mut nodes_to_skip = Vec::new();
+        let mut started_early = Vec::new();
 
         for node_id in level_node_ids {
+            if early_handles.contains_key(node_id) {
+                started_early.push(node_id.clone());
+                continue;
+            }
+
+            // A stage is decided when its first member is reached, after
+            // all of the stage's dependencies
+            match stage_progress.gate(&graph, node_id, &config.stages, &context, &node_statuses) {
+                StageGate::Open => {}
+                StageGate::Entered { stage_id, .. } => {
+                    if let Some(stage) = graph.get_stage(&stage_id) {
+                        emit_event(&app, WorkflowEvent::StageStarted {
+                            execution_id: execution_id.to_string(),
+                            stage_id: stage.id.clone(),
+                            label: stage.label.clone(),
+                            node_ids: stage.nodes.clone(),
+                        });
+                    }
+                }
+                StageGate::Closed { stage_id, result, first } => {
+                    if first {
+                        emit_event(&app, WorkflowEvent::StageSkipped {
+                            execution_id: execution_id.to_string(),
+                            stage_id: stage_id.clone(),
+                            reason: result.reason.clone(),
+                            condition: result.clone(),
+                        });
+                    }
+                    let condition = config.stages.get(&stage_id).map(|c| c.condition.clone()).unwrap_or_default();
+                    let mut decision = NodeDecision::evaluated(node_id, &condition, &result);
+                    decision.reason = StageGate::skip_reason(&stage_id, &result);
+                    nodes_to_skip.push((node_id.clone(), decision.reason.clone(), Some(result)));
+                    context.record_decision(decision);
+                    continue;
+                }
+            }
+
             // Check if any predecessor failed
-            let deps = graph.get_dependencies(node_id);
+            let deps = graph.dependencies(node_id);
             let has_failed_dep = deps.iter().any(|dep| failed_nodes.contains(dep));
 
             if has_failed_dep {
-                nodes_to_skip.push((node_id.clone(), "Dependency failed".to_string()));
+                let failed = deps.iter().filter(|dep| failed_nodes.contains(*dep)).cloned().collect();
+                context.record_decision(NodeDecision::dependency_failed(node_id, failed));
+                nodes_to_skip.push((node_id.clone(), "Dependency failed".to_string(), None));
                 continue;
             }
 
@@ -261,10 +971,27 @@ async fn run_enhanced_execution(
                 .map(|c| &c.condition)
                 .unwrap_or(&ExecutionCondition::Always);
 
-            let condition_result = condition.evaluate(&context, &node_statuses, &deps);
+            let condition_result = condition.evaluate(&context, &node_statuses, deps);
+            if EXECUTION_LOG.enabled(&execution_id, Some(node_id), LogLevel::Debug) {
+                EXECUTION_LOG.log(
+                    execution_id,
+                    Some(node_id),
+                    LogLevel::Debug,
+                    format!(
+                        "Condition {}: {}",
+                        if condition_result.should_execute { "met" } else { "not met" },
+                        condition_result.reason
+                    ),
+                    Some(serde_json::json!({
+                        "condition": condition,
+                        "result": condition_result,
+                    })),
+                );
+            }
+            context.record_decision(NodeDecision::evaluated(node_id, condition, &condition_result));
 
             if !condition_result.should_execute {
-                nodes_to_skip.push((node_id.clone(), condition_result.reason));
+                nodes_to_skip.push((node_id.clone(), condition_result.reason.clone(), Some(condition_result)));
                 continue;
             }
 
@@ -272,36 +999,69 @@ async fn run_enhanced_execution(
         }
 
         // Skip nodes
-        for (node_id, reason) in &nodes_to_skip {
+        for (node_id, reason, condition_result) in &nodes_to_skip {
             skipped_nodes.insert(node_id.clone());
             node_statuses.insert(node_id.clone(), NodeExecutionStatus::Skipped);
 
             state.update_node_state(node_id, |ns| {
                 ns.skip(reason.clone());
             });
+            context.publications().close(node_id);
 
             emit_event(&app, WorkflowEvent::NodeSkipped {
                 execution_id: execution_id.to_string(),
                 node_id: node_id.clone(),
                 reason: reason.clone(),
+                condition: condition_result.clone(),
             });
         }
 
-        if nodes_to_run.is_empty() {
+        if nodes_to_run.is_empty() && started_early.is_empty() {
+            complete_stages(&app, &graph, &config, &context, &mut stage_progress, &node_statuses);
             continue;
         }
 
+        // Start critical-path nodes first so they get level and group slots
+        nodes_to_run.sort_by_key(|id| !critical_nodes.contains(id));
+
         // Emit level started event
         emit_event(&app, WorkflowEvent::LevelStarted {
             execution_id: execution_id.to_string(),
             level: level_idx,
-            node_ids: nodes_to_run.clone(),
+            node_ids: nodes_to_run.iter().chain(&started_early).cloned().collect(),
         });
 
-        // Spawn all nodes in this level concurrently
+        // Spawn all nodes in this level concurrently, bounded by the level limit
+        let level_semaphore = config
+            .max_parallel_nodes_per_level
+            .map(|limit| Arc::new(Semaphore::new(limit.max(1))));
         let mut handles = Vec::new();
 
-        for node_id in nodes_to_run {
+        // Files touched by each agent node of this level, in completion order
+        let parallel_agents = nodes_to_run
+            .iter()
+            .filter(|id| graph.get_node(id).is_some_and(|n| n.kind.is_agent()))
+            .count();
+        let file_tracker = match (&config.conflict_policy, &project_dir) {
+            (Some(_), Some(_)) if parallel_agents > 1 => Some(Arc::new(Mutex::new(Vec::new()))),
+            _ => None,
+        };
+
+        // Later nodes that start on sections this level's nodes publish
+        let early_nodes: Vec<String> = state.execution_levels[level_idx + 1..]
+            .iter()
+            .flatten()
+            .filter(|id| can_start_early(&graph, id, &node_configs, &nodes_to_run, &node_statuses))
+            .cloned()
+            .collect();
+        for node_id in &early_nodes {
+            let condition = ExecutionCondition::Always;
+            let result = condition.evaluate(&context, &node_statuses, graph.dependencies(node_id));
+            context.record_decision(NodeDecision::evaluated(node_id, &condition, &result));
+        }
+
+        let level_nodes = nodes_to_run.into_iter().map(|id| (id, false));
+        for (node_id, early) in level_nodes.chain(early_nodes.into_iter().map(|id| (id, true))) {
             let node = match graph.get_node(&node_id) {
                 Some(n) => n.clone(),
                 None => continue,
@@ -311,37 +1071,259 @@ async fn run_enhanced_execution(
             let state_clone = state.clone();
             let context_clone = context.clone();
             let graph_clone = graph.clone();
-            let config_clone = config.clone();
-            let node_config = node_configs.get(&node_id).cloned().unwrap_or_default();
+            let mut config_clone = config.clone();
+            let mut node_config = node_configs.get(&node_id).cloned().unwrap_or_default();
+            if let (Some(ref deadline), true) = (&config.deadline, behind_schedule) {
+                deadline.apply_pressure(critical_nodes.contains(&node_id), &mut config_clone, &mut node_config);
+            }
             let execution_id_str = execution_id.to_string();
             let input = input_prompt.clone();
             let cancel_rx = state.subscribe_cancel();
+            // A wait node only polls, so it takes no slot a working node could use
+            let polls = matches!(node.kind, NodeKind::WaitForExecution { .. });
+            let group_semaphore = node_config
+                .concurrency_group
+                .as_ref()
+                .filter(|_| !polls)
+                .and_then(|group| group_semaphores.get(group).cloned());
+            // An early node belongs to a later level, so takes neither its
+            // slots nor part in its conflict detection
+            let level_semaphore = if early || polls { None } else { level_semaphore.clone() };
+            let gate_configs = node_configs.clone();
+            let file_tracker = if early { None } else { file_tracker.clone() };
+            let working_dir = project_dir.clone();
 
             let handle = tokio::spawn(async move {
-                spawn_enhanced_node_execution(
-                    app_clone,
-                    state_clone,
-                    context_clone,
-                    graph_clone,
-                    execution_id_str,
-                    node.id.clone(),
-                    node.agent_role.clone(),
-                    node.system_prompt.clone(),
-                    node.assigned_task.clone().or(Some(input)),
-                    config_clone,
-                    node_config,
-                    cancel_rx,
-                )
-                .await
+                // Sign-off comes before any slot, so nobody waits on a person
+                if node_config.require_approval {
+                    if let Err(e) = wait_for_approval(&app_clone, &state_clone, &node.id, None).await {
+                        let role = node.agent_role.clone();
+                        return finish_inline_node(&app_clone, &state_clone, &context_clone, node.id, &role, Err(e), Vec::new());
+                    }
+                }
+
+                if early {
+                    if let Err(e) = wait_for_sections(&app_clone, &state_clone, &context_clone, &node.id, &node_config.start_on_published).await {
+                        let role = node.agent_role.clone();
+                        return finish_inline_node(&app_clone, &state_clone, &context_clone, node.id, &role, Err(e), Vec::new());
+                    }
+                }
+
+                // Group slot and resource locks first, then level slot, so a node
+                // that is waiting doesn't hold a level slot another node could use
+                let queued = group_semaphore.is_some() || !node_config.locks.is_empty() || level_semaphore.is_some();
+                if queued {
+                    mark_node_waiting(&app_clone, &state_clone, &node.id, NodeExecutionStatus::Queued);
+                }
+                let _group_permit = match group_semaphore {
+                    Some(sem) => Some(sem.acquire_owned().await.map_err(|e| e.to_string())?),
+                    None => None,
+                };
+                let _locks = if node_config.locks.is_empty() {
+                    None
+                } else {
+                    Some(LOCK_MANAGER.acquire(state_clone.project_id, state_clone.execution_id, &node.id, &node_config.locks).await)
+                };
+                let _level_permit = match level_semaphore {
+                    Some(sem) => Some(sem.acquire_owned().await.map_err(|e| e.to_string())?),
+                    None => None,
+                };
+                if queued {
+                    state_clone.update_node_state(&node.id, |ns| ns.dequeue());
+                }
+
+                // Held until the node ends, retries and correction rounds included
+                let _agent_slot = if matches!(node.kind, NodeKind::Agent) {
+                    let mark_queued = || mark_node_waiting(&app_clone, &state_clone, &node.id, NodeExecutionStatus::Queued);
+                    let rate_limit_key = agent_rate_limit_key(&state_clone.execution_id);
+                    match acquire_agent_slot(
+                        get_resource_manager(),
+                        &rate_limit_key,
+                        &state_clone,
+                        &node.id,
+                        &node.agent_role,
+                        mark_queued,
+                    )
+                    .await
+                    {
+                        Ok(slot) => {
+                            state_clone.update_node_state(&node.id, |ns| ns.dequeue());
+                            Some(slot)
+                        }
+                        Err(e) => {
+                            let role = node.agent_role.clone();
+                            return finish_inline_node(&app_clone, &state_clone, &context_clone, node.id, &role, Err(e), Vec::new());
+                        }
+                    }
+                } else {
+                    None
+                };
+
+                // The project's node limit covers all the node does from here
+                // on, whatever its kind: attempts, correction rounds, test runs
+                let node_limit = config_clone.auto_cancel.and_then(|policy| policy.max_node_ms);
+                let limited = (app_clone.clone(), state_clone.clone(), context_clone.clone(), node.id.clone(), node.agent_role.clone());
+                let run = async move {
+                    match node.kind {
+                        NodeKind::Script { .. } => {
+                            return run_script_node(
+                                app_clone,
+                                state_clone,
+                                context_clone,
+                                graph_clone,
+                                node,
+                                node_config,
+                            )
+                            .await;
+                        }
+                        NodeKind::WaitForExecution { .. } => {
+                            return run_wait_node(
+                                app_clone,
+                                state_clone,
+                                context_clone,
+                                node,
+                                node_config,
+                                cancel_rx,
+                            )
+                            .await;
+                        }
+                        NodeKind::Report { .. } => {
+                            return run_report_node(app_clone, state_clone, context_clone, node, node_config).await;
+                        }
+                        NodeKind::Assert { .. } => {
+                            return run_assert_node(
+                                app_clone,
+                                state_clone,
+                                context_clone,
+                                graph_clone,
+                                node,
+                                node_config,
+                                cancel_rx,
+                            )
+                            .await;
+                        }
+                        NodeKind::QualityGate { .. } => {
+                            return run_quality_gate_node(
+                                app_clone,
+                                state_clone,
+                                context_clone,
+                                graph_clone,
+                                node,
+                                config_clone,
+                                gate_configs,
+                            )
+                            .await;
+                        }
+                        NodeKind::ApplyPatch { .. } => {
+                            return run_apply_patch_node(app_clone, state_clone, context_clone, graph_clone, node, node_config).await;
+                        }
+                        NodeKind::CheckGate { .. } => {
+                            return run_check_gate_node(app_clone, state_clone, context_clone, node, node_config).await;
+                        }
+                        NodeKind::SupplyChainCheck { .. } => {
+                            return run_supply_chain_node(app_clone, state_clone, context_clone, node, node_config).await;
+                        }
+                        NodeKind::Agent => {}
+                    }
+
+                    // Snapshot the working tree around the agent to see which files it touched
+                    let before = match &working_dir {
+                        Some(dir) => conflicts::snapshot(dir).await.ok(),
+                        None => None,
+                    };
+                    let tracked_id = node.id.clone();
+
+                    let result = if node_config.self_correction.is_some() {
+                        run_self_correcting_node(
+                            app_clone,
+                            state_clone,
+                            context_clone,
+                            graph_clone,
+                            node,
+                            config_clone,
+                            node_config,
+                        )
+                        .await
+                    } else {
+                        spawn_enhanced_node_execution(
+                            app_clone,
+                            state_clone,
+                            context_clone,
+                            graph_clone,
+                            execution_id_str,
+                            node.id.clone(),
+                            node.agent_role.clone(),
+                            node.system_prompt.clone(),
+                            node.assigned_task.clone().or(Some(input)),
+                            config_clone,
+                            node_config,
+                            false,
+                            cancel_rx,
+                        )
+                        .await
+                    };
+
+                    let mut changed = None;
+                    if let (Some(dir), Some(before)) = (working_dir, before) {
+                        if let Ok(after) = conflicts::snapshot(&dir).await {
+                            ACTIVITY_STORE
+                                .record_file_changes(execution_id, &tracked_id, &dir, &before, &after)
+                                .await;
+                            changed = Some(conflicts::changed_files(&before, &after));
+                        }
+                    }
+                    if let (Some(tracker), true) = (file_tracker, result.is_ok()) {
+                        // The snapshots of nodes sharing the tree hold each other's
+                        // edits, so go by what the node's own agents logged
+                        let touched = if activity::hooks_supported() {
+                            Some(ACTIVITY_STORE.agent_writes(execution_id, &tracked_id))
+                        } else {
+                            changed
+                        };
+                        if let Some(touched) = touched {
+                            tracker.lock().push((tracked_id, touched));
+                        }
+                    }
+
+                    result
+                };
+                let Some(max_ms) = node_limit else {
+                    return run.await;
+                };
+                match tokio::time::timeout(Duration::from_millis(max_ms), run).await {
+                    Ok(result) => result,
+                    Err(_) => {
+                        let (app, state, context, node_id, role) = limited;
+                        stop_node_agents(&app, &state, &node_id);
+                        let error = auto_cancel::node_stopped(&state, &node_id, max_ms);
+                        finish_inline_node(&app, &state, &context, node_id, &role, Err(error), Vec::new())
+                    }
+                }
             });
 
-            handles.push((node_id, handle));
+            if early {
+                early_handles.insert(node_id, handle);
+            } else {
+                handles.push((node_id, handle));
+            }
+        }
+        for node_id in started_early {
+            if let Some(handle) = early_handles.remove(&node_id) {
+                handles.push((node_id, handle));
+            }
         }
 
         // Wait for all nodes in this level to complete
         for (node_id, handle) in handles {
-            match handle.await {
+            let result = handle.await;
+            context.publications().close(&node_id);
+            match result {
                 Ok(Ok(())) => {
+                    if let Some(config) = node_configs.get(&node_id) {
+                        if let Some(format) = config.test_results {
+                            record_test_results(&context, &node_id, format, &config.output_tags);
+                        }
+                    }
                     node_statuses.insert(node_id.clone(), NodeExecutionStatus::Completed);
                 }
                 Ok(Err(e)) => {
@@ -357,6 +1339,34 @@ async fn run_enhanced_execution(
             }
         }
 
+        // Resolve files edited by more than one node of this level
+        if let Some(tracker) = file_tracker {
+            let touched = std::mem::take(&mut *tracker.lock());
+            let found = conflicts::detect_conflicts(&touched);
+            let policy = config.conflict_policy.as_ref().map_or("report", ConflictPolicy::name);
+
+            for conflict in &found {
+                log::warn!("Nodes {} and {} both edited {:?}", conflict.first, conflict.second, conflict.files);
+                emit_event(&app, WorkflowEvent::ConflictDetected {
+                    execution_id: execution_id.to_string(),
+                    level: level_idx,
+                    first_node: conflict.first.clone(),
+                    second_node: conflict.second.clone(),
+                    files: conflict.files.clone(),
+                    resolution: policy.to_string(),
+                });
+            }
+
+            if !found.is_empty() {
+                for node_id in resolve_conflicts(&app, &state, &context, &graph, &config, &node_configs, found).await {
+                    failed_nodes.insert(node_id.clone());
+                    node_statuses.insert(node_id, NodeExecutionStatus::Failed);
+                }
+            }
+        }
+
+        complete_stages(&app, &graph, &config, &context, &mut stage_progress, &node_statuses);
+
         // Check for adaptive replanning after level
         if config.adaptive.enabled && config.adaptive.replan_after_level && !failed_nodes.is_empty() {
             // Would trigger replan here (simplified for now)
@@ -386,36 +1396,61 @@ async fn run_enhanced_execution(
         });
     }
 
+    // The agents' changes wait in the workspace for review; announced
+    // before the outcome so listeners that stop at it still see them
+    if config.isolated_workspace {
+        let changes = tokio::task::spawn_blocking(move || ISOLATED_WORKSPACES.changes(&execution_id)).await;
+        if let (Some(dir), Ok(Ok(changes))) = (&project_dir, changes) {
+            emit_event(&app, WorkflowEvent::WorkspaceReady {
+                execution_id: execution_id.to_string(),
+                workspace_dir: dir.to_string_lossy().into_owned(),
+                changed_files: changes.len(),
+            });
+        }
+    }
+
     let duration_ms = start_time.elapsed().as_millis() as u64;
 
     // Determine final status
-    if failed_nodes.is_empty() {
+    if state.get_status() == ExecutionStatus::Cancelled {
+        // Cancelled while the last level ran
+        emit_event(&app, WorkflowEvent::ExecutionCancelled {
+            execution_id: execution_id.to_string(),
+            workflow_id: workflow_label.clone(),
+            reason: state.get_cancel_reason(),
+        });
+        log::info!("Workflow execution {} cancelled after {}ms", execution_id, duration_ms);
+    } else if failed_nodes.is_empty() {
         state.set_status(ExecutionStatus::Completed);
         emit_event(&app, WorkflowEvent::ExecutionCompleted {
             execution_id: execution_id.to_string(),
-            workflow_id: "enhanced".to_string(),
+            workflow_id: workflow_label.clone(),
             duration_ms,
         });
-        log::info!("Enhanced workflow execution {} completed successfully in {}ms", execution_id, duration_ms);
+        log::info!("Workflow execution {} completed successfully in {}ms", execution_id, duration_ms);
     } else {
         state.set_status(ExecutionStatus::Failed);
         let failed_list: Vec<String> = failed_nodes.into_iter().collect();
         emit_event(&app, WorkflowEvent::ExecutionFailed {
             execution_id: execution_id.to_string(),
-            workflow_id: "enhanced".to_string(),
+            workflow_id: workflow_label.clone(),
             error: format!("{} node(s) failed", failed_list.len()),
             failed_nodes: failed_list,
         });
-        log::warn!("Enhanced workflow execution {} failed after {}ms", execution_id, duration_ms);
+        log::warn!("Workflow execution {} failed after {}ms", execution_id, duration_ms);
     }
+
 }
 
-/// Execute a single node with enhanced capabilities
+/// Execute a single node with enhanced capabilities. With `hold_completion`
+/// a node that succeeds is left running, for the caller to complete once
+/// its own checks pass, and neither reuses nor caches results.
+#[allow(clippy::too_many_arguments)]
 async fn spawn_enhanced_node_execution(
     app: AppHandle,
     state: Arc<WorkflowExecutionState>,
     context: Arc<ExecutionContext>,
-    graph: WorkflowGraph,
+    graph: Arc<WorkflowGraph>,
     execution_id: String,
     node_id: String,
     agent_role: String,
@@ -423,13 +1458,14 @@ async fn spawn_enhanced_node_execution(
     assigned_task: Option<String>,
     config: EnhancedExecutionConfig,
     node_config: EnhancedNodeConfig,
+    hold_completion: bool,
     mut cancel_rx: broadcast::Receiver<()>,
 ) -> Result<(), String> {
     // Get app state for agent management
     let app_state: tauri::State<'_, Arc<AppState>> = app.state();
 
     // Get working directory from project
-    let working_directory = get_project_working_directory(&state.project_id)
+    let working_directory = execution_working_directory(&state)
         .unwrap_or_else(|| {
             std::env::current_dir()
                 .map(|p| p.to_string_lossy().to_string())
@@ -437,43 +1473,235 @@ async fn spawn_enhanced_node_execution(
         });
 
     // Build enhanced prompt with predecessor context
-    let predecessor_ids = graph.get_dependencies(&node_id);
+    let verbose = EXECUTION_LOG.enabled(&state.execution_id, Some(&node_id), LogLevel::Debug);
+    let predecessor_ids = graph.dependencies(&node_id);
     let enhanced_task = if config.enable_data_flow && !predecessor_ids.is_empty() {
-        // Aggregate outputs from predecessors
-        let aggregation = node_config.aggregation
-            .map(|a| a.strategy)
-            .unwrap_or(config.default_aggregation.clone());
-
-        let predecessor_outputs = context.get_predecessor_outputs(&predecessor_ids);
-        let _aggregated = aggregation.aggregate(&predecessor_outputs);
-
-        // Build context-aware prompt
         let base_task = assigned_task.as_deref().unwrap_or("");
-        Some(context.build_agent_prompt(base_task, &predecessor_ids, config.include_original_prompt))
+        match &node_config.aggregation {
+            // Only the selected predecessor outputs, aggregated and transformed
+            Some(aggregation) => {
+                let outputs = context.get_predecessor_outputs(predecessor_ids);
+                let available = outputs.len();
+                let aggregated = aggregation.apply(outputs);
+                if verbose {
+                    log_aggregation(state.execution_id, &node_id, aggregation, available, aggregated.as_ref());
+                }
+                let predecessor_context = aggregated
+                    .map(|aggregated| aggregated.to_prompt_context())
+                    .unwrap_or_default();
+                Some(context.build_prompt_with_context(base_task, &predecessor_context, config.include_original_prompt))
+            }
+            None => {
+                if verbose {
+                    EXECUTION_LOG.log(
+                        state.execution_id,
+                        Some(&node_id),
+                        LogLevel::Debug,
+                        format!("Passing on all outputs of {} predecessor(s)", predecessor_ids.len()),
+                        Some(serde_json::json!({ "predecessors": predecessor_ids })),
+                    );
+                }
+                Some(context.build_agent_prompt(base_task, predecessor_ids, config.include_original_prompt))
+            }
+        }
     } else {
         assigned_task.clone()
     };
 
-    // Get retry config
-    let retry_config = node_config.retry.clone().unwrap_or(config.retry.clone());
-    let mut retry_state = RetryState::new(retry_config);
+    // Put messages the node hasn't seen yet ahead of its task
+    let bus = MESSAGE_BUS_STORE.get(&state.execution_id);
+    let mut delivered_messages = 0;
+    let enhanced_task = match (enhanced_task, &bus) {
+        (Some(task), Some(bus)) => {
+            let messages = bus.deliver_to_node(&node_id, &agent_role);
+            delivered_messages = messages.len();
+            Some(format!("{}{}", messaging::format_for_prompt(&messages), task))
+        }
+        (task, _) => task,
+    };
+
+    let enhanced_task = match (&node_config.impact_scope, enhanced_task) {
+        (Some(scope), Some(task)) => match resolve_impact(&context, scope, std::path::Path::new(&working_directory)).await {
+            Ok(impacted) if !impacted.is_empty() => {
+                let paths: Vec<&str> = impacted.iter().map(|file| file.path.as_str()).collect();
+                context.set_variable("impacted_files", serde_json::json!(paths));
+                Some(format!("{}\n\n{}", task, scope.prompt_section(&impacted)))
+            }
+            Ok(_) => Some(task),
+            Err(e) => {
+                EXECUTION_LOG.log(
+                    state.execution_id,
+                    Some(&node_id),
+                    LogLevel::Warn,
+                    format!("Running unscoped, impacted files unknown: {}", e),
+                    None,
+                );
+                Some(task)
+            }
+        },
+        (_, task) => task,
+    };
+
+    let expected_format = node_config.output_format();
+    let enhanced_task = match node_config.output_instructions() {
+        Some(instructions) => enhanced_task.map(|task| format!("{}\n\n{}", task, instructions)),
+        None => enhanced_task,
+    };
+
+    // Get retry config
+    let retry_config = node_config.retry.clone().unwrap_or(config.retry.clone());
+    let guardrails = node_config
+        .guardrails
+        .clone()
+        .or_else(|| config.guardrails.clone())
+        .filter(GuardrailConfig::is_enabled);
+    let prompt_mode = retry_config.prompt_mode.clone();
+    let mut retry_state = RetryState::new(retry_config);
+
+    let system_prompt = node_config.system_prompt_override.clone().or(system_prompt);
+    let system_prompt = locale::project_system_prompt(Some(state.project_id), system_prompt);
+    if verbose {
+        let prompt = enhanced_task.as_deref().unwrap_or("");
+        EXECUTION_LOG.log(
+            state.execution_id,
+            Some(&node_id),
+            LogLevel::Debug,
+            format!("Assembled a {}-character prompt", prompt.chars().count()),
+            Some(serde_json::json!({
+                "task_chars": assigned_task.as_deref().map_or(0, |t| t.chars().count()),
+                "data_flow": config.enable_data_flow,
+                "predecessors": predecessor_ids,
+                "include_original_prompt": config.include_original_prompt,
+                "messages": delivered_messages,
+                "system_prompt_override": node_config.system_prompt_override.is_some(),
+                "model": node_config.model,
+            })),
+        );
+    }
+    if EXECUTION_LOG.enabled(&state.execution_id, Some(&node_id), LogLevel::Trace) {
+        EXECUTION_LOG.log(
+            state.execution_id,
+            Some(&node_id),
+            LogLevel::Trace,
+            "Prompt",
+            Some(serde_json::json!({
+                "system_prompt": system_prompt,
+                "prompt": enhanced_task,
+            })),
+        );
+    }
+
+    let cache = result_cache_config(&config, &node_config).filter(|_| !hold_completion);
+    let cache_key = cache.map(|_| {
+        result_cache::cache_key(
+            state.project_id,
+            &node_id,
+            &agent_role,
+            system_prompt.as_deref(),
+            enhanced_task.as_deref(),
+            node_config.model.as_deref(),
+        )
+    });
+    if let (Some(cache), Some(key)) = (cache, &cache_key) {
+        if let Some(output) = RESULT_CACHE.lookup(key, cache, std::path::Path::new(&working_directory)) {
+            EXECUTION_LOG.log(
+                state.execution_id,
+                Some(&node_id),
+                LogLevel::Info,
+                "Reusing the cached result; the files the node reads are unchanged",
+                None,
+            );
+            // Checked like a fresh answer, as the guardrails may have changed
+            // since it was stored
+            start_inline_node(&app, &state, &node_id);
+            if let Some(guardrails) = &guardrails {
+                if let Err(e) = hold_for_guardrails(&app, &state, &node_id, guardrails, &output).await {
+                    return finish_inline_node(&app, &state, &context, node_id, &agent_role, Err(e), Vec::new());
+                }
+            }
+            if let (Some(bus), Some(topic)) = (&bus, &node_config.publish_topic) {
+                bus.publish(
+                    Uuid::nil(),
+                    &node_id,
+                    &agent_role,
+                    topic,
+                    MessageType::Data,
+                    MessageContent::Text(output.clone()),
+                );
+            }
+            let data = expected_format
+                .and_then(|format| format.parse(&output).ok())
+                .unwrap_or(OutputData::Text(output));
+            let mut tags = node_config.output_tags.clone();
+            tags.push(CACHED_TAG.to_string());
+            return finish_inline_node(&app, &state, &context, node_id, &agent_role, Ok(data), tags);
+        }
+    }
+
+    let mut attempt = 0;
+    // Retries may be told what went wrong with the attempt before
+    let mut attempt_task = enhanced_task.clone();
+    let record_attempt = |attempt: u32, prompt: Option<String>, response: Option<String>, success: bool| {
+        context.record_transcript(NodeTranscript {
+            node_id: node_id.clone(),
+            agent_role: agent_role.clone(),
+            system_prompt: system_prompt.clone(),
+            prompt: prompt.unwrap_or_default(),
+            response,
+            model: node_config.model.clone(),
+            attempt,
+            success,
+            timestamp: Utc::now(),
+        });
+    };
+
+    // Retry loop
+    loop {
+        attempt += 1;
 
-    // Retry loop
-    loop {
         // Create agent config
         let agent_config = AgentConfig {
             name: format!("workflow-{}-{}", &execution_id[..8], node_id),
             role: agent_role.clone(),
             working_directory: working_directory.clone(),
             project_id: Some(state.project_id),
-            system_prompt: node_config.system_prompt_override.clone().or(system_prompt.clone()),
-            assigned_task: enhanced_task.clone(),
+            system_prompt: system_prompt.clone(),
+            assigned_task: attempt_task.clone(),
+            model: node_config.model.clone(),
+            env: EXECUTION_ENVS.pairs(&state.execution_id),
+            owner: Some(AgentOwner::node(state.execution_id, &node_id)),
         };
 
+        let faults = config
+            .chaos
+            .as_ref()
+            .map(|chaos| chaos.faults(&state.execution_id, &node_id, attempt))
+            .unwrap_or_default();
+        if !faults.is_empty() {
+            EXECUTION_LOG.log(
+                state.execution_id,
+                Some(&node_id),
+                LogLevel::Warn,
+                format!("Chaos mode: injecting faults into attempt {}", attempt),
+                Some(serde_json::json!({
+                    "delay_ms": faults.delay.map(|delay| delay.as_millis() as u64),
+                    "fail": faults.fail,
+                    "truncate": faults.truncate,
+                })),
+            );
+        }
+        if let Some(delay) = faults.delay {
+            tokio::time::sleep(delay).await;
+        }
+
         // Create agent manager and spawn agent
         let manager = AgentManager::new(app.clone());
 
-        let spawn_result = manager.spawn_agent(agent_config);
+        let spawn_result = if faults.fail {
+            Err(chaos::INJECTED_ERROR.to_string())
+        } else {
+            manager.spawn_agent(agent_config)
+        };
 
         match spawn_result {
             Ok(agent_info) => {
@@ -486,6 +1714,9 @@ async fn spawn_enhanced_node_execution(
 
                 // Store agent in app state
                 app_state.agents.insert(agent_id, agent_info);
+                if let Some(bus) = &bus {
+                    bus.register_member(agent_id, &node_id, &agent_role);
+                }
 
                 // Emit node started event
                 emit_event(&app, WorkflowEvent::NodeStarted {
@@ -504,21 +1735,104 @@ async fn spawn_enhanced_node_execution(
                 });
 
                 // Wait for agent completion
-                let result = wait_for_agent_completion(&app, agent_id, &mut cancel_rx).await;
+                let publisher = context.publications().is_watched(&node_id).then(|| SectionPublisher {
+                    context: &context,
+                    node_id: &node_id,
+                    agent_role: &agent_role,
+                    tags: &node_config.output_tags,
+                });
+                let mut result = wait_for_agent_completion(&app, agent_id, &mut cancel_rx, publisher.as_ref()).await;
+                ACTIVITY_STORE.record_agent_log(state.execution_id, &node_id, agent_id);
+                if faults.truncate {
+                    if let Ok(Some(output)) = &mut result {
+                        *output = chaos::truncate(output);
+                    }
+                }
+
+                // An answer in the wrong format is retried like a rejected one
+                let response = result.clone().ok().flatten();
+                let mut wrong_format = false;
+                let result = match (result, expected_format) {
+                    (Ok(output), Some(format)) => match format.parse(output.as_deref().unwrap_or_default()).and_then(|data| {
+                        match &node_config.output_schema {
+                            Some(schema) => output_format::check_schema(&data, schema).map(|_| data),
+                            None => Ok(data),
+                        }
+                    }) {
+                        Ok(data) => Ok((output, Some(data))),
+                        Err(e) => {
+                            wrong_format = true;
+                            Err(e)
+                        }
+                    },
+                    (result, _) => result.map(|output| (output, None)),
+                };
+
+                record_attempt(attempt, attempt_task.clone(), response, result.is_ok());
 
                 match result {
-                    Ok(output) => {
+                    Ok((output, parsed)) => {
+                        if let (Some(guardrails), Some(output_text)) = (&guardrails, &output) {
+                            if let Err(e) = hold_for_guardrails(&app, &state, &node_id, guardrails, output_text).await {
+                                state.update_node_state(&node_id, |ns| ns.fail(e.clone()));
+                                emit_event(&app, WorkflowEvent::NodeFailed {
+                                    execution_id: execution_id.clone(),
+                                    node_id: node_id.clone(),
+                                    error: e.clone(),
+                                });
+                                emit_event(&app, WorkflowEvent::NodeStatusChanged {
+                                    execution_id,
+                                    node_id,
+                                    status: NodeExecutionStatus::Failed,
+                                    progress: 0,
+                                    agent_id: Some(agent_id.to_string()),
+                                    error: Some(e.clone()),
+                                });
+                                return Err(e);
+                            }
+                        }
+
                         // Store output in context for downstream agents
                         if let Some(output_text) = &output {
                             let agent_output = AgentOutput {
                                 agent_id,
                                 node_id: node_id.clone(),
                                 agent_role: agent_role.clone(),
-                                data: OutputData::Text(output_text.clone()),
+                                data: parsed.unwrap_or_else(|| OutputData::Text(output_text.clone())),
                                 timestamp: Utc::now(),
                                 tags: node_config.output_tags.clone(),
                             };
                             context.store_output(agent_output);
+
+                            if let (Some(bus), Some(topic)) = (&bus, &node_config.publish_topic) {
+                                bus.publish(
+                                    agent_id,
+                                    &node_id,
+                                    &agent_role,
+                                    topic,
+                                    MessageType::Data,
+                                    MessageContent::Text(output_text.clone()),
+                                );
+                            }
+                        }
+
+                        if let (Some(cache), Some(key), Some(output_text)) = (cache, &cache_key, &output) {
+                            let stored = RESULT_CACHE.store(
+                                key.clone(),
+                                state.project_id,
+                                &node_id,
+                                cache,
+                                std::path::Path::new(&working_directory),
+                                output_text.clone(),
+                            );
+                            if let Err(e) = stored {
+                                log::warn!("Not caching the result of node {}: {}", node_id, e);
+                            }
+                        }
+
+                        if hold_completion {
+                            let _ = retry_state.mark_success();
+                            return Ok(());
                         }
 
                         state.update_node_state(&node_id, |ns| {
@@ -547,17 +1861,33 @@ async fn spawn_enhanced_node_execution(
                         let error_msg = e.to_string();
 
                         // Check if we should retry
-                        match retry_state.should_retry(&error_msg) {
+                        let decision = if wrong_format {
+                            retry_state.should_retry_rejection(&error_msg)
+                        } else {
+                            retry_state.should_retry(&error_msg)
+                        };
+                        match decision {
                             RetryDecision::Retry { delay, attempt } => {
                                 log::warn!(
                                     "Node {} attempt {} failed: {}. Retrying in {:?}...",
                                     node_id, attempt, error_msg, delay
                                 );
+                                record_retry_attempt(&state, &node_id, &retry_state);
+                                let output = AGENT_REGISTRY.get_output(&agent_id);
+                                attempt_task = Some(prompt_mode.next_task(
+                                    enhanced_task.as_deref().unwrap_or(""),
+                                    attempt,
+                                    &error_msg,
+                                    output.as_deref(),
+                                ));
                                 tokio::time::sleep(delay).await;
                                 continue; // Retry
                             }
                             RetryDecision::NoRetry { reason: _ } | RetryDecision::Exhausted { .. } => {
                                 // Final failure
+                                let _ = retry_state.mark_failure(&error_msg);
+                                record_retry_attempt(&state, &node_id, &retry_state);
+                                capture_partial_output(&state, &context, &node_id, &agent_role, agent_id, &node_config.output_tags);
                                 state.update_node_state(&node_id, |ns| {
                                     ns.fail(error_msg.clone());
                                 });
@@ -577,7 +1907,6 @@ async fn spawn_enhanced_node_execution(
                                     error: Some(error_msg.clone()),
                                 });
 
-                                let _ = retry_state.mark_failure(&error_msg);
                                 return Err(error_msg);
                             }
                         }
@@ -592,10 +1921,13 @@ async fn spawn_enhanced_node_execution(
                             "Node {} spawn attempt {} failed: {}. Retrying in {:?}...",
                             node_id, attempt, e, delay
                         );
+                        record_retry_attempt(&state, &node_id, &retry_state);
                         tokio::time::sleep(delay).await;
                         continue;
                     }
                     _ => {
+                        let _ = retry_state.mark_failure(&e);
+                        record_retry_attempt(&state, &node_id, &retry_state);
                         state.update_node_state(&node_id, |ns| {
                             ns.fail(e.clone());
                         });
@@ -606,7 +1938,6 @@ async fn spawn_enhanced_node_execution(
                             error: e.clone(),
                         });
 
-                        let _ = retry_state.mark_failure(&e);
                         return Err(e);
                     }
                 }
@@ -615,120 +1946,1761 @@ async fn spawn_enhanced_node_execution(
     }
 }
 
-/// Wait for agent completion with cancellation support
-async fn wait_for_agent_completion(
-    app: &AppHandle,
-    agent_id: Uuid,
-    cancel_rx: &mut broadcast::Receiver<()>,
-) -> Result<Option<String>, String> {
-    let app_state: tauri::State<'_, Arc<AppState>> = app.state();
-    let start = std::time::Instant::now();
+/// Run an agent node, then its test command; on failure, re-run the agent
+/// with the test output until the tests pass or the rounds run out
+async fn run_self_correcting_node(
+    app: AppHandle,
+    state: Arc<WorkflowExecutionState>,
+    context: Arc<ExecutionContext>,
+    graph: Arc<WorkflowGraph>,
+    node: ParsedNode,
+    config: EnhancedExecutionConfig,
+    node_config: EnhancedNodeConfig,
+) -> Result<(), String> {
+    let correction = node_config
+        .self_correction
+        .clone()
+        .ok_or_else(|| format!("Node '{}' has no self-correction settings", node.id))?;
+    let working_dir = execution_working_directory(&state)
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+    let base_task = node.assigned_task.clone().unwrap_or_else(|| context.original_prompt.clone());
+    let mut task = base_task.clone();
+    let mut round = 0;
 
     loop {
-        // Check for cancellation
-        if cancel_rx.try_recv().is_ok() {
-            AGENT_REGISTRY.kill(&agent_id);
-            if let Some(mut agent) = app_state.agents.get_mut(&agent_id) {
-                agent.status = AgentStatus::Killed;
+        spawn_enhanced_node_execution(
+            app.clone(),
+            state.clone(),
+            context.clone(),
+            graph.clone(),
+            state.execution_id.to_string(),
+            node.id.clone(),
+            node.agent_role.clone(),
+            node.system_prompt.clone(),
+            Some(task),
+            config.clone(),
+            node_config.clone(),
+            true,
+            state.subscribe_cancel(),
+        )
+        .await?;
+
+        let run = self_correction::run_tests(&correction, &working_dir, &EXECUTION_ENVS.pairs(&state.execution_id)).await;
+
+        emit_event(&app, WorkflowEvent::SelfCorrectionRound {
+            execution_id: state.execution_id.to_string(),
+            node_id: node.id.clone(),
+            round,
+            max_rounds: correction.max_rounds,
+            passed: run.as_ref().is_ok_and(|run| run.passed),
+            exit_code: run.as_ref().ok().and_then(|run| run.exit_code),
+        });
+
+        let run = match run {
+            Ok(run) if run.passed => {
+                // Held until now, so nothing downstream starts on untested work
+                let latest = context.get_latest_output(&node.id);
+                let output = latest.as_ref().map(|output| output.data.to_context_string());
+                state.update_node_state(&node.id, |ns| ns.complete(output.clone()));
+                emit_event(&app, WorkflowEvent::NodeCompleted {
+                    execution_id: state.execution_id.to_string(),
+                    node_id: node.id.clone(),
+                    output,
+                });
+                emit_event(&app, WorkflowEvent::NodeStatusChanged {
+                    execution_id: state.execution_id.to_string(),
+                    node_id: node.id,
+                    status: NodeExecutionStatus::Completed,
+                    progress: 100,
+                    agent_id: latest.map(|output| output.agent_id.to_string()),
+                    error: None,
+                });
+                return Ok(());
             }
-            return Err("Execution cancelled".to_string());
+            Ok(run) => run,
+            Err(e) => return finish_inline_node(&app, &state, &context, node.id, &node.agent_role, Err(e), Vec::new()),
+        };
+
+        if round >= correction.max_rounds {
+            let error = format!(
+                "Tests still failing after {} correction round(s): `{}` exited with {:?}",
+                correction.max_rounds, correction.test_command, run.exit_code
+            );
+            return finish_inline_node(&app, &state, &context, node.id, &node.agent_role, Err(error), Vec::new());
         }
 
-        // Check agent status
-        if let Some(agent) = app_state.agents.get(&agent_id) {
-            match agent.status {
-                AgentStatus::Completed => {
-                    // Get output from registry
-                    let output = AGENT_REGISTRY.get_output(&agent_id);
-                    return Ok(output);
-                }
-                AgentStatus::Failed => {
-                    return Err("Agent execution failed".to_string());
-                }
-                AgentStatus::Killed => {
-                    return Err("Agent was killed".to_string());
-                }
-                _ => {
-                    // Still running
-                }
+        round += 1;
+        log::info!("Node {} failed its tests; starting correction round {}", node.id, round);
+        task = self_correction::correction_prompt(&base_task, &correction.test_command, &run, round, correction.max_rounds);
+    }
+}
+
+/// The result cache a node uses, if any. A chaos run neither reuses
+/// results, which would skip its faults, nor stores its faulty ones.
+fn result_cache_config<'a>(config: &EnhancedExecutionConfig, node_config: &'a EnhancedNodeConfig) -> Option<&'a NodeCacheConfig> {
+    node_config
+        .cache
+        .as_ref()
+        .filter(|cache| !cache.reads.is_empty() && config.chaos.is_none())
+}
+
+/// How long a node waits before asking again for an agent slot it was
+/// refused, for lack of rate limit tokens, over its role's limit or with
+/// the queue full
+const SLOT_RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// An agent slot of the resource manager, handed back when dropped
+struct AgentSlot<'a> {
+    manager: &'a ResourceManager,
+    permit: Option<ResourcePermit>,
+}
+
+impl Drop for AgentSlot<'_> {
+    fn drop(&mut self) {
+        if let Some(permit) = self.permit.take() {
+            self.manager.release(permit);
+        }
+    }
+}
+
+/// The rate limit bucket an execution's agents draw from: the Anthropic
+/// backend, with the API key they run with unless they use the Claude CLI
+/// login
+fn agent_rate_limit_key(execution_id: &Uuid) -> RateLimitKey {
+    let api_key = EXECUTION_ENVS
+        .pairs(execution_id)
+        .into_iter()
+        .find(|(name, _)| name == "ANTHROPIC_API_KEY")
+        .map(|(_, value)| value)
+        .or_else(|| SETTINGS.get().backends.anthropic_api_key)
+        .or_else(|| std::env::var("ANTHROPIC_API_KEY").ok());
+    let key = RateLimitKey::backend("anthropic");
+    match api_key {
+        Some(api_key) => key.credential(&credential_label(&api_key)),
+        None => key,
+    }
+}
+
+/// Wait for an agent slot for an agent node, however long the line is;
+/// `on_wait` runs when the node has to wait. Fails if the execution is
+/// cancelled meanwhile.
+async fn acquire_agent_slot<'a>(
+    manager: &'a ResourceManager,
+    rate_limit_key: &RateLimitKey,
+    state: &WorkflowExecutionState,
+    node_id: &str,
+    agent_role: &str,
+    on_wait: impl FnOnce(),
+) -> Result<AgentSlot<'a>, String> {
+    let mut cancel_rx = state.subscribe_cancel();
+    let mut on_wait = Some(on_wait);
+    loop {
+        if state.get_status() == ExecutionStatus::Cancelled {
+            return Err("Execution cancelled".to_string());
+        }
+        if !manager.is_available() {
+            if let Some(on_wait) = on_wait.take() {
+                on_wait();
             }
-        } else {
-            // Agent not found - might have completed and been cleaned up
-            let output = AGENT_REGISTRY.get_output(&agent_id);
-            return Ok(output);
         }
+        let acquired = tokio::select! {
+            acquired = manager.acquire_for_while(
+                rate_limit_key,
+                state.execution_id,
+                node_id,
+                agent_role,
+                TaskPriority::Normal,
+                || state.get_status() != ExecutionStatus::Cancelled,
+            ) => acquired,
+            _ = cancel_rx.recv() => return Err("Execution cancelled".to_string()),
+        };
+        let retry_in = match acquired {
+            Ok(permit) => {
+                return Ok(AgentSlot {
+                    manager,
+                    permit: Some(permit),
+                })
+            }
+            // Stopped waiting as the execution was cancelled
+            Err(ResourceError::Timeout) => return Err("Execution cancelled".to_string()),
+            Err(ResourceError::RateLimited | ResourceError::RoleLimitExceeded { .. } | ResourceError::QueueFull) => SLOT_RETRY_DELAY,
+            Err(e) => return Err(format!("No agent slot: {}", e)),
+        };
+        if let Some(on_wait) = on_wait.take() {
+            on_wait();
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(retry_in) => {}
+            _ = cancel_rx.recv() => return Err("Execution cancelled".to_string()),
+        }
+    }
+}
 
-        // Check timeout
-        if start.elapsed().as_millis() as u64 > MAX_AGENT_WAIT_MS {
-            return Err(format!(
-                "Agent {} did not complete within {} seconds",
-                agent_id,
-                MAX_AGENT_WAIT_MS / 1000
-            ));
+/// Put a node in a waiting status and tell the UI
+fn mark_node_waiting(app: &AppHandle, state: &WorkflowExecutionState, node_id: &str, status: NodeExecutionStatus) {
+    state.update_node_state(node_id, |ns| match status {
+        NodeExecutionStatus::Queued => ns.queue(),
+        NodeExecutionStatus::WaitingForApproval => ns.await_approval(),
+        _ => ns.status = status,
+    });
+
+    emit_event(app, WorkflowEvent::NodeStatusChanged {
+        execution_id: state.execution_id.to_string(),
+        node_id: node_id.to_string(),
+        status,
+        progress: 0,
+        agent_id: None,
+        error: None,
+    });
+}
+
+/// Hold a node until someone approves it; a rejection or cancellation
+/// fails it
+async fn wait_for_approval(
+    app: &AppHandle,
+    state: &WorkflowExecutionState,
+    node_id: &str,
+    summary: Option<String>,
+) -> Result<(), String> {
+    let mut cancel_rx = state.subscribe_cancel();
+    if state.get_status() == ExecutionStatus::Cancelled {
+        return Err("Execution cancelled".to_string());
+    }
+
+    mark_node_waiting(app, state, node_id, NodeExecutionStatus::WaitingForApproval);
+    let decision = APPROVAL_STORE.request(state.execution_id, node_id, summary);
+
+    tokio::select! {
+        decision = decision => match decision {
+            Ok(ApprovalDecision::Approved) => Ok(()),
+            Ok(ApprovalDecision::Rejected { reason: Some(reason) }) => Err(format!("Rejected: {}", reason)),
+            Ok(ApprovalDecision::Rejected { reason: None }) => Err("Rejected".to_string()),
+            Err(_) => Err("Approval request was withdrawn".to_string()),
+        },
+        _ = cancel_rx.recv() => {
+            APPROVAL_STORE.withdraw(state.execution_id, node_id);
+            Err("Execution cancelled".to_string())
         }
+    }
+}
 
-        tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+/// Check an agent's output against the guardrails; if it trips any, hold
+/// the node until someone approves the output anyway
+async fn hold_for_guardrails(
+    app: &AppHandle,
+    state: &WorkflowExecutionState,
+    node_id: &str,
+    guardrails: &GuardrailConfig,
+    output: &str,
+) -> Result<(), String> {
+    let violations = guardrails.check(output)?;
+    if violations.is_empty() {
+        return Ok(());
     }
+    let summary = guardrails::approval_summary(&violations);
+    emit_event(app, WorkflowEvent::GuardrailTriggered {
+        execution_id: state.execution_id.to_string(),
+        node_id: node_id.to_string(),
+        violations,
+    });
+    wait_for_approval(app, state, node_id, Some(summary.clone()))
+        .await
+        .map_err(|e| format!("{} ({})", e, summary))
 }
 
-/// Create a checkpoint from current state
-fn create_checkpoint(
+/// Whether a node of a later level can start alongside `starting`, the
+/// nodes of this level. It can if it starts on sections of some of them,
+/// every other predecessor has completed, and nothing but its predecessors
+/// decides whether it runs.
+fn can_start_early(
+    graph: &WorkflowGraph,
+    node_id: &str,
+    node_configs: &HashMap<String, EnhancedNodeConfig>,
+    starting: &[String],
+    node_statuses: &HashMap<String, NodeExecutionStatus>,
+) -> bool {
+    let Some(config) = node_configs.get(node_id) else {
+        return false;
+    };
+    if config.start_on_published.is_empty()
+        || !matches!(config.condition, ExecutionCondition::Always)
+        || graph.stage_of(node_id).is_some()
+    {
+        return false;
+    }
+
+    let deps = graph.dependencies(node_id);
+    let streamed = |dep: &String| config.start_on_published.iter().any(|s| &s.node_id == dep);
+    config.start_on_published.iter().all(|s| starting.contains(&s.node_id) && deps.contains(&s.node_id))
+        && deps.iter().all(|dep| {
+            node_statuses.get(dep) == Some(&NodeExecutionStatus::Completed) || (starting.contains(dep) && streamed(dep))
+        })
+}
+
+/// Hold an early node until every section it starts on is published; a
+/// section whose node finishes without it fails the node
+async fn wait_for_sections(
+    app: &AppHandle,
     state: &WorkflowExecutionState,
     context: &ExecutionContext,
-    current_level: usize,
-) -> Result<ExecutionCheckpoint, String> {
-    let mut node_states = HashMap::new();
+    node_id: &str,
+    sections: &[PublishedSection],
+) -> Result<(), String> {
+    let mut cancel_rx = state.subscribe_cancel();
+    if state.get_status() == ExecutionStatus::Cancelled {
+        return Err("Execution cancelled".to_string());
+    }
 
-    for entry in state.node_states.iter() {
-        let ns = entry.value();
-        node_states.insert(
-            entry.key().clone(),
-            NodeCheckpointState {
-                node_id: ns.node_id.clone(),
-                status: ns.status,
-                agent_id: ns.agent_id,
-                progress: ns.progress,
-                started_at: ns.started_at,
-                completed_at: ns.completed_at,
-                output: ns.output.clone(),
-                error: ns.error.clone(),
-                retry_attempts: vec![],
-            },
-        );
+    mark_node_waiting(app, state, node_id, NodeExecutionStatus::Queued);
+    for section in sections {
+        tokio::select! {
+            published = context.publications().wait(section) => {
+                if published.is_none() {
+                    return Err(format!("Node '{}' finished without publishing '{}'", section.node_id, section.section));
+                }
+            }
+            _ = cancel_rx.recv() => return Err("Execution cancelled".to_string()),
+        }
     }
+    state.update_node_state(node_id, |ns| ns.dequeue());
+    Ok(())
+}
 
-    // Get outputs from context
-    let outputs = HashMap::new();
-    // Note: In a full implementation, we'd iterate over context outputs
+/// The files of a node's change and the project files that depend on them
+async fn resolve_impact(context: &ExecutionContext, scope: &ImpactScope, working_dir: &std::path::Path) -> Result<Vec<ImpactedFile>, String> {
+    let changed: Vec<String> = match &scope.diff_node {
+        Some(diff_node) => {
+            let output = context
+                .get_latest_output(diff_node)
+                .ok_or_else(|| format!("Node '{}' has no output to scope to", diff_node))?;
+            let (_, files) = output_format::extract_patch(&output.data.to_context_string())?;
+            files.into_iter().flat_map(|file| [file.old_path, file.new_path]).flatten().collect()
+        }
+        None => conflicts::snapshot(working_dir).await?.into_keys().collect(),
+    };
+    if changed.is_empty() {
+        return Ok(Vec::new());
+    }
 
-    Ok(ExecutionCheckpoint::new(
-        state.execution_id,
-        state.workflow_id,
-        state.project_id,
-        context.original_prompt.clone(),
-        state.get_status(),
-        node_states,
-        state.execution_levels.clone(),
-        context.get_all_variables(),
-        outputs,
-        state.started_at,
-        current_level,
-    ))
+    let root = working_dir.to_path_buf();
+    let graph = tokio::task::spawn_blocking(move || DependencyGraph::build(&root))
+        .await
+        .map_err(|e| format!("Dependency scan panicked: {}", e))?
+        .map_err(|e| format!("Failed to scan project imports: {}", e))?;
+    Ok(graph.impacted(&changed, scope.max_depth))
 }
 
-fn emit_event(app: &AppHandle, event: WorkflowEvent) {
-    let _ = app.emit(WORKFLOW_EVENT_NAME, &event);
+/// Mark an in-process node as running
+fn start_inline_node(app: &AppHandle, state: &WorkflowExecutionState, node_id: &str) {
+    state.update_node_state(node_id, |ns| ns.start_inline());
+
+    emit_event(app, WorkflowEvent::NodeStatusChanged {
+        execution_id: state.execution_id.to_string(),
+        node_id: node_id.to_string(),
+        status: NodeExecutionStatus::Running,
+        progress: 0,
+        agent_id: None,
+        error: None,
+    });
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Record the result of an in-process node and emit its completion events
+fn finish_inline_node(
+    app: &AppHandle,
+    state: &WorkflowExecutionState,
+    context: &ExecutionContext,
+    node_id: String,
+    role: &str,
+    result: Result<OutputData, String>,
+    tags: Vec<String>,
+) -> Result<(), String> {
+    let execution_id = state.execution_id.to_string();
 
-    #[test]
-    fn test_default_config() {
-        let config = EnhancedExecutionConfig::default();
-        assert!(config.enable_data_flow);
-        assert!(config.include_original_prompt);
-        assert_eq!(config.retry.max_attempts, 3);
+    match result {
+        Ok(data) => {
+            let output_text = data.to_context_string();
+            context.store_output(AgentOutput {
+                agent_id: Uuid::nil(),
+                node_id: node_id.clone(),
+                agent_role: role.to_string(),
+                data,
+                timestamp: Utc::now(),
+                tags,
+            });
+
+            state.update_node_state(&node_id, |ns| {
+                ns.complete(Some(output_text.clone()));
+            });
+
+            emit_event(app, WorkflowEvent::NodeCompleted {
+                execution_id: execution_id.clone(),
+                node_id: node_id.clone(),
+                output: Some(output_text),
+            });
+
+            emit_event(app, WorkflowEvent::NodeStatusChanged {
+                execution_id,
+                node_id,
+                status: NodeExecutionStatus::Completed,
+                progress: 100,
+                agent_id: None,
+                error: None,
+            });
+
+            Ok(())
+        }
+        Err(e) => {
+            state.update_node_state(&node_id, |ns| {
+                ns.fail(e.clone());
+            });
+
+            emit_event(app, WorkflowEvent::NodeFailed {
+                execution_id: execution_id.clone(),
+                node_id: node_id.clone(),
+                error: e.clone(),
+            });
+
+            emit_event(app, WorkflowEvent::NodeStatusChanged {
+                execution_id,
+                node_id,
+                status: NodeExecutionStatus::Failed,
+                progress: 0,
+                agent_id: None,
+                error: Some(e.clone()),
+            });
+
+            Err(e)
+        }
+    }
+}
+
+/// Execute a script node in-process
+async fn run_script_node(
+    app: AppHandle,
+    state: Arc<WorkflowExecutionState>,
+    context: Arc<ExecutionContext>,
+    graph: Arc<WorkflowGraph>,
+    node: ParsedNode,
+    node_config: EnhancedNodeConfig,
+) -> Result<(), String> {
+    let NodeKind::Script { source } = node.kind else {
+        return Err(format!("Node '{}' is not a script node", node.id));
+    };
+    let node_id = node.id;
+
+    start_inline_node(&app, &state, &node_id);
+
+    // Expose each predecessor's latest output, keeping JSON structured
+    let inputs = graph
+        .dependencies(&node_id)
+        .iter()
+        .filter_map(|pred_id| {
+            context
+                .get_latest_output(pred_id)
+                .map(|output| (pred_id.clone(), output_value(output.data)))
+        })
+        .collect();
+
+    let input = ScriptInput {
+        prompt: context.original_prompt.clone(),
+        inputs,
+        variables: context.get_all_variables(),
+    };
+
+    let result = tokio::task::spawn_blocking(move || {
+        script::run_script(&source, &input, &ScriptLimits::default())
+    })
+    .await
+    .map_err(|e| format!("Script task panicked: {}", e))
+    .and_then(|r| r)
+    .map(|result| {
+        for (key, value) in result.changed_variables {
+            context.set_variable(&key, value);
+        }
+        result.output
+    });
+
+    finish_inline_node(&app, &state, &context, node_id, "script", result, node_config.output_tags)
+}
+
+/// Block until another execution completes, then import its outputs
+async fn run_wait_node(
+    app: AppHandle,
+    state: Arc<WorkflowExecutionState>,
+    context: Arc<ExecutionContext>,
+    node: ParsedNode,
+    node_config: EnhancedNodeConfig,
+    mut cancel_rx: broadcast::Receiver<()>,
+) -> Result<(), String> {
+    let NodeKind::WaitForExecution { execution_id: target_id, workflow_id, outputs, timeout_ms } = node.kind else {
+        return Err(format!("Node '{}' is not a wait-for-execution node", node.id));
+    };
+    let node_id = node.id;
+
+    start_inline_node(&app, &state, &node_id);
+
+    let result = async {
+        let parse = |id: &str| Uuid::parse_str(id).map_err(|e| format!("Invalid id '{}': {}", id, e));
+        let target_id = target_id.as_deref().map(parse).transpose()?;
+        let workflow_id = workflow_id.as_deref().map(parse).transpose()?;
+
+        let timeout = timeout_ms.unwrap_or(MAX_AGENT_WAIT_MS);
+        let start = std::time::Instant::now();
+
+        loop {
+            if cancel_rx.try_recv().is_ok() {
+                return Err("Execution cancelled".to_string());
+            }
+
+            // Resolve the target each poll: "latest run" may not exist yet.
+            // Waiting on its own workflow means its latest other run.
+            let target = target_id.or_else(|| workflow_id.and_then(|id| {
+                crate::commands::workflow::find_latest_execution(&id, Some(&state.execution_id))
+            }));
+
+            if target == Some(state.execution_id) {
+                return Err("A node cannot wait for its own execution".to_string());
+            }
+
+            if let Some((target_state, target_context)) = target.and_then(|id| {
+                crate::commands::workflow::find_execution(&id)
+            }) {
+                match target_state.get_status() {
+                    ExecutionStatus::Completed => {
+                        return import_execution_outputs(&target_state, target_context.as_deref(), &outputs);
+                    }
+                    status @ (ExecutionStatus::Failed | ExecutionStatus::Cancelled) => {
+                        return Err(format!("Execution {} ended with status {:?}", target_state.execution_id, status));
+                    }
+                    ExecutionStatus::Pending | ExecutionStatus::Running => {}
+                }
+            }
+
+            if start.elapsed().as_millis() as u64 > timeout {
+                return Err(format!("Timed out after {}ms waiting for execution", timeout));
+            }
+
+            tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+        }
+    }
+    .await;
+
+    finish_inline_node(&app, &state, &context, node_id, "wait_for_execution", result, node_config.output_tags)
+}
+
+/// Write a report of the execution so far and optionally email it
+async fn run_report_node(
+    app: AppHandle,
+    state: Arc<WorkflowExecutionState>,
+    context: Arc<ExecutionContext>,
+    node: ParsedNode,
+    node_config: EnhancedNodeConfig,
+) -> Result<(), String> {
+    let NodeKind::Report { format, output_path, email_to, subject } = node.kind else {
+        return Err(format!("Node '{}' is not a report node", node.id));
+    };
+    let node_id = node.id;
+
+    start_inline_node(&app, &state, &node_id);
+
+    let result = async {
+        let report = ExecutionReport::build(&state, Some(&context));
+
+        let working_dir = execution_working_directory(&state)
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+        let path = match output_path {
+            Some(path) => resolve_output_path(&working_dir, &path)?,
+            None => report.default_path(&working_dir, format),
+        };
+        report
+            .write_to(&path, format)
+            .map_err(|e| format!("Failed to write report to {}: {}", path.display(), e))?;
+
+        if !email_to.is_empty() {
+            let smtp = SmtpConfig::from_env().map_err(|e| e.to_string())?;
+            send_email(&smtp, &report.email_message(email_to, subject, format))
+                .await
+                .map_err(|e| format!("Failed to email report: {}", e))?;
+        }
+
+        Ok(OutputData::FilePath(path.to_string_lossy().to_string()))
+    }
+    .await;
+
+    finish_inline_node(&app, &state, &context, node_id, "report", result, node_config.output_tags)
+}
+
+/// Check another node's output. A failed check is recorded as an
+/// assertion outcome; it only fails the node when `fail_execution` is set.
+async fn run_assert_node(
+    app: AppHandle,
+    state: Arc<WorkflowExecutionState>,
+    context: Arc<ExecutionContext>,
+    graph: Arc<WorkflowGraph>,
+    node: ParsedNode,
+    node_config: EnhancedNodeConfig,
+    mut cancel_rx: broadcast::Receiver<()>,
+) -> Result<(), String> {
+    let NodeKind::Assert { assertion, target, fail_execution } = node.kind else {
+        return Err(format!("Node '{}' is not an assert node", node.id));
+    };
+    let node_id = node.id;
+
+    start_inline_node(&app, &state, &node_id);
+
+    let checked = match resolve_target(&graph, &node_id, target) {
+        Ok(target) => {
+            let output = context
+                .get_latest_output(&target)
+                .map(|output| output_value(output.data))
+                .ok_or_else(|| format!("Node '{}' has no output to check", target));
+
+            let result = match (output, &assertion) {
+                (Err(e), _) => Err(e),
+                (Ok(output), AssertionCheck::LlmJudge { rubric, min_score, model }) => {
+                    let task = assertions::judge_prompt(rubric, &output);
+                    run_judge_agent(&app, &state, &node_id, task, model.clone(), &mut cancel_rx)
+                        .await
+                        .map(|(score, _)| CheckResult {
+                            passed: score >= *min_score,
+                            message: format!("Judge scored {} (minimum {})", score, min_score),
+                            score: Some(score),
+                        })
+                }
+                (Ok(output), check) => assertions::evaluate(check, &output),
+            };
+            result.map(|result| (target, result))
+        }
+        Err(e) => Err(e),
+    };
+
+    let result = checked.and_then(|(target, result)| {
+        let outcome = AssertionOutcome {
+            node_id: node_id.clone(),
+            target,
+            passed: result.passed,
+            message: result.message,
+            score: result.score,
+        };
+        context.record_assertion(outcome.clone());
+
+        emit_event(&app, WorkflowEvent::AssertionEvaluated {
+            execution_id: state.execution_id.to_string(),
+            node_id: node_id.clone(),
+            target: outcome.target.clone(),
+            passed: outcome.passed,
+            message: outcome.message.clone(),
+            score: outcome.score,
+        });
+
+        if !outcome.passed && fail_execution {
+            return Err(format!("Assertion failed: {}", outcome.message));
+        }
+        serde_json::to_value(&outcome)
+            .map(OutputData::Json)
+            .map_err(|e| e.to_string())
+    });
+
+    finish_inline_node(&app, &state, &context, node_id, "assert", result, node_config.output_tags)
+}
+
+/// Apply a unified diff from another node's output to the project, or
+/// only check that it applies. Reports every hunk either way.
+async fn run_apply_patch_node(
+    app: AppHandle,
+    state: Arc<WorkflowExecutionState>,
+    context: Arc<ExecutionContext>,
+    graph: Arc<WorkflowGraph>,
+    node: ParsedNode,
+    node_config: EnhancedNodeConfig,
+) -> Result<(), String> {
+    let NodeKind::ApplyPatch { target, mode, allow_partial } = node.kind else {
+        return Err(format!("Node '{}' is not a patch node", node.id));
+    };
+    let node_id = node.id;
+
+    start_inline_node(&app, &state, &node_id);
+
+    let working_dir = execution_working_directory(&state)
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+    let files = resolve_target(&graph, &node_id, target).and_then(|target| {
+        let output = context
+            .get_latest_output(&target)
+            .ok_or_else(|| format!("Node '{}' has no output to apply", target))?;
+        output_format::extract_patch(&output.data.to_context_string()).map(|(_, files)| files)
+    });
+
+    let result = match files {
+        Ok(files) => {
+            let (mut report, planned) = patch::plan(&working_dir, &files);
+            let total = report.applied_hunks + report.rejected_hunks;
+
+            let written = if report.rejected_hunks > 0 && !allow_partial {
+                Err(format!("{} of {} hunk(s) do not apply:\n{}", report.rejected_hunks, total, report.rejections()))
+            } else {
+                match mode {
+                    PatchMode::Check => Ok(false),
+                    PatchMode::Apply => patch::write(&working_dir, &planned).map(|_| true),
+                    PatchMode::Stage => {
+                        let paths: Vec<String> = planned.iter().map(|f| f.path.to_string_lossy().into_owned()).collect();
+                        let summary = format!("Apply {} of {} hunk(s) to {}", report.applied_hunks, total, paths.join(", "));
+                        match wait_for_approval(&app, &state, &node_id, Some(summary)).await {
+                            Ok(()) => {
+                                start_inline_node(&app, &state, &node_id);
+                                patch::write(&working_dir, &planned).map(|_| true)
+                            }
+                            Err(e) => Err(e),
+                        }
+                    }
+                }
+            };
+            report.written = matches!(written, Ok(true));
+
+            let data = serde_json::to_value(&report).map(OutputData::Json).map_err(|e| e.to_string());
+            match (written, data) {
+                (Ok(_), data) => data,
+                // Downstream nodes still see which hunks were rejected
+                (Err(e), Ok(data)) => {
+                    context.store_output(AgentOutput {
+                        agent_id: Uuid::nil(),
+                        node_id: node_id.clone(),
+                        agent_role: "patch".to_string(),
+                        data,
+                        timestamp: Utc::now(),
+                        tags: node_config.output_tags.clone(),
+                    });
+                    Err(e)
+                }
+                (Err(e), Err(_)) => Err(e),
+            }
+        }
+        Err(e) => Err(e),
+    };
+
+    finish_inline_node(&app, &state, &context, node_id, "patch", result, node_config.output_tags)
+}
+
+/// Run a coverage tool or linter and judge its numbers against the
+/// project's thresholds. The verdict is recorded as an assertion outcome
+/// for the report, and the node's JSON output carries it for conditions.
+async fn run_check_gate_node(
+    app: AppHandle,
+    state: Arc<WorkflowExecutionState>,
+    context: Arc<ExecutionContext>,
+    node: ParsedNode,
+    node_config: EnhancedNodeConfig,
+) -> Result<(), String> {
+    let NodeKind::CheckGate { tool, command, timeout_ms, fail_execution } = node.kind else {
+        return Err(format!("Node '{}' is not a check gate node", node.id));
+    };
+    let node_id = node.id;
+
+    start_inline_node(&app, &state, &node_id);
+
+    let working_dir = execution_working_directory(&state)
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| std::env::current_dir().unwrap_or_else(|_| ".".into()));
+    let command = command.unwrap_or_else(|| tool.default_command().to_string());
+
+    let result = match gates::run_gate(tool, &command, &working_dir, timeout_ms, &EXECUTION_ENVS.pairs(&state.execution_id)).await {
+        Ok(metrics) => {
+            let gate = GateResult::judge(tool, command, metrics, GATE_THRESHOLDS.get(&state.project_id));
+
+            match gate.metrics.coverage_percent {
+                Some(coverage) => context.set_variable("coverage_percent", serde_json::json!(coverage)),
+                None => {
+                    context.set_variable("lint_errors", serde_json::json!(gate.metrics.errors));
+                    context.set_variable("lint_warnings", serde_json::json!(gate.metrics.warnings));
+                }
+            }
+
+            let outcome = AssertionOutcome {
+                node_id: node_id.clone(),
+                target: format!("{:?}", tool).to_lowercase(),
+                passed: gate.passed,
+                message: gate.summary(),
+                score: gate.metrics.coverage_percent,
+            };
+            context.record_assertion(outcome.clone());
+
+            emit_event(&app, WorkflowEvent::AssertionEvaluated {
+                execution_id: state.execution_id.to_string(),
+                node_id: node_id.clone(),
+                target: outcome.target,
+                passed: outcome.passed,
+                message: outcome.message.clone(),
+                score: outcome.score,
+            });
+
+            if !gate.passed && fail_execution {
+                Err(outcome.message)
+            } else {
+                serde_json::to_value(&gate).map(OutputData::Json).map_err(|e| e.to_string())
+            }
+        }
+        Err(e) => Err(e),
+    };
+
+    finish_inline_node(&app, &state, &context, node_id, "check_gate", result, node_config.output_tags)
+}
+
+/// Check the dependencies the execution added for advisories and unknown
+/// packages. The report is kept for the execution, where flagged packages
+/// hold up approvals, and recorded as an assertion outcome.
+async fn run_supply_chain_node(
+    app: AppHandle,
+    state: Arc<WorkflowExecutionState>,
+    context: Arc<ExecutionContext>,
+    node: ParsedNode,
+    node_config: EnhancedNodeConfig,
+) -> Result<(), String> {
+    let NodeKind::SupplyChainCheck { lockfiles, allow_unknown, ignore_advisories } = node.kind else {
+        return Err(format!("Node '{}' is not a supply-chain check node", node.id));
+    };
+    let node_id = node.id;
+
+    start_inline_node(&app, &state, &node_id);
+
+    let result = match execution_working_directory(&state) {
+        Some(dir) => {
+            let dir = std::path::PathBuf::from(dir);
+            supply_chain::check(&dir, state.execution_id, &node_id, &lockfiles, allow_unknown, &ignore_advisories).await
+        }
+        None => Err("The project has no working directory to check".to_string()),
+    };
+
+    let result = result.and_then(|report| {
+        let outcome = AssertionOutcome {
+            node_id: node_id.clone(),
+            target: "supply_chain".to_string(),
+            passed: report.flagged().next().is_none(),
+            message: report.summary(),
+            score: None,
+        };
+        context.record_assertion(outcome.clone());
+
+        emit_event(&app, WorkflowEvent::AssertionEvaluated {
+            execution_id: state.execution_id.to_string(),
+            node_id: node_id.clone(),
+            target: outcome.target,
+            passed: outcome.passed,
+            message: outcome.message.clone(),
+            score: None,
+        });
+
+        let output = serde_json::to_value(&report).map(OutputData::Json).map_err(|e| e.to_string());
+        SUPPLY_CHAIN_REPORTS.record(report);
+        if outcome.passed {
+            output
+        } else {
+            Err(outcome.message)
+        }
+    });
+
+    finish_inline_node(&app, &state, &context, node_id, "supply_chain", result, node_config.output_tags)
+}
+
+/// Node whose output an assert, quality-gate or patch node uses: the
+/// explicit target, or else the node's only predecessor
+fn resolve_target(graph: &WorkflowGraph, node_id: &str, target: Option<String>) -> Result<String, String> {
+    if let Some(target) = target {
+        return Ok(target);
+    }
+
+    match graph.dependencies(node_id) {
+        [single] => Ok(single.clone()),
+        [] => Err(format!("Node '{}' has no target and no predecessor", node_id)),
+        _ => Err(format!("Node '{}' has several predecessors; set `target`", node_id)),
+    }
+}
+
+/// Re-run an agent node until a judge scores its output at or above the
+/// gate's minimum. Rejections draw on the target's retry budget, then its
+/// fallback strategy; each re-run gets the judge's critique appended.
+async fn run_quality_gate_node(
+    app: AppHandle,
+    state: Arc<WorkflowExecutionState>,
+    context: Arc<ExecutionContext>,
+    graph: Arc<WorkflowGraph>,
+    node: ParsedNode,
+    config: EnhancedExecutionConfig,
+    node_configs: Arc<HashMap<String, EnhancedNodeConfig>>,
+) -> Result<(), String> {
+    let NodeKind::QualityGate { rubric, min_score, model, target } = node.kind else {
+        return Err(format!("Node '{}' is not a quality gate node", node.id));
+    };
+    let node_id = node.id;
+    let mut cancel_rx = state.subscribe_cancel();
+    let mut verdict: Option<AssertionOutcome> = None;
+
+    start_inline_node(&app, &state, &node_id);
+
+    let result = async {
+        let target_id = resolve_target(&graph, &node_id, target)?;
+        let target = graph
+            .get_node(&target_id)
+            .filter(|n| n.kind.is_agent())
+            .cloned()
+            .ok_or_else(|| format!("Quality gate target '{}' is not an agent node", target_id))?;
+        let target_config = node_configs.get(&target_id).cloned().unwrap_or_default();
+        let base_task = target.assigned_task.clone().unwrap_or_else(|| context.original_prompt.clone());
+
+        let mut retry_state = RetryState::new(target_config.retry.clone().unwrap_or_else(|| config.retry.clone()));
+        let (mut role, mut system_prompt) = (target.agent_role.clone(), target.system_prompt.clone());
+        let mut fallback_used = false;
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            let output = context
+                .get_latest_output(&target_id)
+                .ok_or_else(|| format!("Node '{}' has no output to judge", target_id))?;
+            let task = assertions::judge_prompt(&rubric, &output_value(output.data.clone()));
+            let (score, reply) = run_judge_agent(&app, &state, &node_id, task, model.clone(), &mut cancel_rx).await?;
+
+            let outcome = AssertionOutcome {
+                node_id: node_id.clone(),
+                target: target_id.clone(),
+                passed: score >= min_score,
+                message: format!("Attempt {} scored {} (minimum {})", attempt, score, min_score),
+                score: Some(score),
+            };
+            emit_event(&app, WorkflowEvent::AssertionEvaluated {
+                execution_id: state.execution_id.to_string(),
+                node_id: node_id.clone(),
+                target: target_id.clone(),
+                passed: outcome.passed,
+                message: outcome.message.clone(),
+                score: outcome.score,
+            });
+            let rejection = format!("Quality gate rejected output: {}", outcome.message);
+            let passed = outcome.passed;
+            verdict = Some(outcome);
+
+            if passed {
+                return Ok(output.data);
+            }
+
+            match retry_state.should_retry_rejection(&rejection) {
+                RetryDecision::Retry { delay, .. } => {
+                    record_retry_attempt(&state, &target_id, &retry_state);
+                    tokio::time::sleep(delay).await
+                }
+                RetryDecision::NoRetry { .. } | RetryDecision::Exhausted { .. } if !fallback_used => {
+                    fallback_used = true;
+                    match target_config.fallback.clone().unwrap_or_else(|| config.fallback.clone()) {
+                        // Let the output through as it is
+                        FallbackStrategy::Skip => return Ok(output.data),
+                        FallbackStrategy::UseDefault { value } => {
+                            context.store_output(AgentOutput {
+                                agent_id: Uuid::nil(),
+                                node_id: target_id.clone(),
+                                agent_role: target.agent_role.clone(),
+                                data: OutputData::Json(value.clone()),
+                                timestamp: Utc::now(),
+                                tags: target_config.output_tags.clone(),
+                            });
+                            return Ok(OutputData::Json(value));
+                        }
+                        FallbackStrategy::AlternativeAgent { role: alt_role, system_prompt: alt_prompt } => {
+                            role = alt_role;
+                            system_prompt = alt_prompt;
+                        }
+                        FallbackStrategy::PauseForIntervention | FallbackStrategy::FailWorkflow => {
+                            return Err(rejection);
+                        }
+                    }
+                }
+                RetryDecision::NoRetry { .. } | RetryDecision::Exhausted { .. } => return Err(rejection),
+            }
+
+            log::info!("Quality gate {} re-running {} (attempt {})", node_id, target_id, attempt + 1);
+            let revised_task = format!(
+                "{}\n\n## Reviewer feedback\n\nYour previous output scored {} out of {} against this rubric:\n\n{}\n\n\
+                 Critique:\n\n{}\n\nRevise your work to address the critique.",
+                base_task,
+                verdict.as_ref().and_then(|v| v.score).unwrap_or_default(),
+                assertions::JUDGE_MAX_SCORE,
+                rubric,
+                assertions::judge_critique(&reply)
+            );
+            spawn_enhanced_node_execution(
+                app.clone(),
+                state.clone(),
+                context.clone(),
+                graph.clone(),
+                state.execution_id.to_string(),
+                target_id.clone(),
+                role.clone(),
+                system_prompt.clone(),
+                Some(revised_task),
+                config.clone(),
+                target_config.clone(),
+                false,
+                state.subscribe_cancel(),
+            )
+            .await?;
+        }
+    }
+    .await;
+
+    if let Some(outcome) = verdict {
+        context.record_assertion(outcome);
+    }
+
+    let tags = node_configs.get(&node_id).map(|c| c.output_tags.clone()).unwrap_or_default();
+    finish_inline_node(&app, &state, &context, node_id, "quality_gate", result, tags)
+}
+
+/// Spawn a judge agent, returning its score and full reply
+async fn run_judge_agent(
+    app: &AppHandle,
+    state: &WorkflowExecutionState,
+    node_id: &str,
+    task: String,
+    model: Option<String>,
+    cancel_rx: &mut broadcast::Receiver<()>,
+) -> Result<(f64, String), String> {
+    let agent_config = AgentConfig {
+        name: format!("judge-{}-{}", &state.execution_id.to_string()[..8], node_id),
+        role: "judge".to_string(),
+        working_directory: execution_working_directory(state).unwrap_or_else(|| ".".to_string()),
+        project_id: Some(state.project_id),
+        system_prompt: Some(assertions::JUDGE_SYSTEM_PROMPT.to_string()),
+        assigned_task: Some(task),
+        model,
+        env: EXECUTION_ENVS.pairs(&state.execution_id),
+        owner: Some(AgentOwner::node(state.execution_id, node_id)),
+    };
+
+    let reply = run_helper_agent(app, agent_config, cancel_rx).await?;
+    let score = assertions::parse_judge_score(&reply).ok_or_else(|| "Judge reply has no SCORE line".to_string())?;
+    Ok((score, reply))
+}
+
+/// Spawn an agent that isn't a graph node and wait for its reply
+async fn run_helper_agent(
+    app: &AppHandle,
+    agent_config: AgentConfig,
+    cancel_rx: &mut broadcast::Receiver<()>,
+) -> Result<String, String> {
+    let app_state: tauri::State<'_, Arc<AppState>> = app.state();
+    let name = agent_config.name.clone();
+
+    let agent_info = AgentManager::new(app.clone()).spawn_agent(agent_config)?;
+    let agent_id = agent_info.id;
+    app_state.agents.insert(agent_id, agent_info);
+
+    wait_for_agent_completion(app, agent_id, cancel_rx, None)
+        .await?
+        .ok_or_else(|| format!("Agent {} produced no output", name))
+}
+
+/// Apply the conflict policy to a level's overlapping edits. Returns the
+/// nodes whose edits could not be reconciled.
+async fn resolve_conflicts(
+    app: &AppHandle,
+    state: &Arc<WorkflowExecutionState>,
+    context: &Arc<ExecutionContext>,
+    graph: &Arc<WorkflowGraph>,
+    config: &EnhancedExecutionConfig,
+    node_configs: &HashMap<String, EnhancedNodeConfig>,
+    found: Vec<FileConflict>,
+) -> Vec<String> {
+    let mut failed = Vec::new();
+
+    match &config.conflict_policy {
+        None | Some(ConflictPolicy::Report) => {}
+        Some(ConflictPolicy::Serialize) => {
+            let mut seconds: Vec<&String> = Vec::new();
+            for conflict in &found {
+                if !seconds.contains(&&conflict.second) {
+                    seconds.push(&conflict.second);
+                }
+            }
+
+            // One at a time, so each re-run sees the previous one's edits
+            for node_id in seconds {
+                let Some(node) = graph.get_node(node_id) else {
+                    continue;
+                };
+                let overlaps: Vec<&FileConflict> = found.iter().filter(|c| &c.second == node_id).collect();
+                let base_task = node.assigned_task.clone().unwrap_or_else(|| context.original_prompt.clone());
+                log::info!("Re-running {} after conflicting edits", node_id);
+
+                let result = spawn_enhanced_node_execution(
+                    app.clone(),
+                    state.clone(),
+                    context.clone(),
+                    graph.clone(),
+                    state.execution_id.to_string(),
+                    node_id.clone(),
+                    node.agent_role.clone(),
+                    node.system_prompt.clone(),
+                    Some(conflicts::reconcile_prompt(&base_task, &overlaps)),
+                    config.clone(),
+                    node_configs.get(node_id).cloned().unwrap_or_default(),
+                    false,
+                    state.subscribe_cancel(),
+                )
+                .await;
+
+                if let Err(e) = result {
+                    log::error!("Re-run of {} failed: {}", node_id, e);
+                    failed.push(node_id.clone());
+                }
+            }
+        }
+        Some(ConflictPolicy::Merge { role, system_prompt, model }) => {
+            let agent_config = AgentConfig {
+                name: format!("merge-{}-{}", &state.execution_id.to_string()[..8], found[0].second),
+                role: role.clone(),
+                working_directory: execution_working_directory(state).unwrap_or_else(|| ".".to_string()),
+                project_id: Some(state.project_id),
+                system_prompt: system_prompt.clone(),
+                assigned_task: Some(conflicts::merge_prompt(&context.original_prompt, &found)),
+                model: model.clone(),
+                env: EXECUTION_ENVS.pairs(&state.execution_id),
+                owner: Some(AgentOwner::node(state.execution_id, &found[0].second)),
+            };
+
+            if let Err(e) = run_helper_agent(app, agent_config, &mut state.subscribe_cancel()).await {
+                log::error!("Merge agent failed: {}", e);
+                for conflict in &found {
+                    let error = format!("Conflicting edits with {} could not be merged: {}", conflict.first, e);
+                    state.update_node_state(&conflict.second, |ns| ns.fail(error.clone()));
+                    failed.push(conflict.second.clone());
+                }
+            }
+        }
+    }
+
+    failed
+}
+
+/// Collect outputs from a finished execution as a JSON object keyed by node ID
+fn import_execution_outputs(
+    target: &WorkflowExecutionState,
+    target_context: Option<&ExecutionContext>,
+    node_ids: &[String],
+) -> Result<OutputData, String> {
+    let node_ids: Vec<String> = if node_ids.is_empty() {
+        target
+            .node_states
+            .iter()
+            .filter(|entry| entry.status == NodeExecutionStatus::Completed)
+            .map(|entry| entry.key().clone())
+            .collect()
+    } else {
+        node_ids.to_vec()
+    };
+
+    let mut imported = serde_json::Map::new();
+    for node_id in node_ids {
+        let value = target_context
+            .and_then(|ctx| ctx.get_latest_output(&node_id))
+            .map(|output| output_value(output.data))
+            .or_else(|| {
+                target
+                    .get_node_state(&node_id)
+                    .and_then(|ns| ns.output)
+                    .map(serde_json::Value::String)
+            })
+            .ok_or_else(|| format!("Execution {} has no output for node '{}'", target.execution_id, node_id))?;
+        imported.insert(node_id, value);
+    }
+
+    Ok(OutputData::Json(serde_json::Value::Object(imported)))
+}
+
+/// Convert output data to JSON, keeping structured outputs as-is
+fn output_value(data: OutputData) -> serde_json::Value {
+    match data {
+        OutputData::Json(value) => value,
+        other => serde_json::Value::String(other.to_context_string()),
+    }
+}
+
+/// Where a running agent's published sections go
+struct SectionPublisher<'a> {
+    context: &'a ExecutionContext,
+    node_id: &'a str,
+    agent_role: &'a str,
+    tags: &'a [String],
+}
+
+impl SectionPublisher<'_> {
+    /// Store the sections finished since the last look as outputs of the
+    /// node, then wake the nodes waiting on them
+    fn scan(&self, app: &AppHandle, agent_id: Uuid) {
+        let Some(output) = AGENT_REGISTRY.get_output(&agent_id) else {
+            return;
+        };
+        let publications = self.context.publications();
+
+        for (section, content) in streaming::parse_sections(&output) {
+            let key = PublishedSection {
+                node_id: self.node_id.to_string(),
+                section: section.clone(),
+            };
+            if publications.get(&key).is_some() {
+                continue;
+            }
+
+            let mut tags = self.tags.to_vec();
+            tags.push(PUBLISHED_TAG.to_string());
+            tags.push(format!("section:{}", section));
+            self.context.store_output(AgentOutput {
+                agent_id,
+                node_id: self.node_id.to_string(),
+                agent_role: self.agent_role.to_string(),
+                data: OutputData::Text(content.clone()),
+                timestamp: Utc::now(),
+                tags,
+            });
+            publications.publish(self.node_id, &section, content);
+
+            emit_event(app, WorkflowEvent::SectionPublished {
+                execution_id: self.context.execution_id.to_string(),
+                node_id: self.node_id.to_string(),
+                section,
+            });
+        }
+    }
+}
+
+/// Kill the running agents working for `node_id`, once the node is given up on
+fn stop_node_agents(app: &AppHandle, state: &WorkflowExecutionState, node_id: &str) {
+    let app_state: tauri::State<'_, Arc<AppState>> = app.state();
+    let owner = AgentOwner::node(state.execution_id, node_id);
+    for mut agent in app_state.agents.iter_mut() {
+        let owned = AGENT_REGISTRY.get_config(agent.key()).is_some_and(|config| config.owner.as_ref() == Some(&owner));
+        if owned && !agent.status.is_terminal() {
+            AGENT_REGISTRY.kill(agent.key());
+            agent.status = AgentStatus::Killed;
+        }
+    }
+}
+
+/// Wait for agent completion with cancellation support, publishing
+/// sections as they appear when given a publisher
+async fn wait_for_agent_completion(
+    app: &AppHandle,
+    agent_id: Uuid,
+    cancel_rx: &mut broadcast::Receiver<()>,
+    publisher: Option<&SectionPublisher<'_>>,
+) -> Result<Option<String>, String> {
+    let app_state: tauri::State<'_, Arc<AppState>> = app.state();
+    let start = std::time::Instant::now();
+
+    loop {
+        if let Some(publisher) = publisher {
+            publisher.scan(app, agent_id);
+        }
+
+        // Check for cancellation
+        if cancel_rx.try_recv().is_ok() {
+            AGENT_REGISTRY.kill(&agent_id);
+            if let Some(mut agent) = app_state.agents.get_mut(&agent_id) {
+                agent.status = AgentStatus::Killed;
+            }
+            return Err("Execution cancelled".to_string());
+        }
+
+        // Check agent status
+        if let Some(agent) = app_state.agents.get(&agent_id) {
+            match agent.status {
+                AgentStatus::Completed => {
+                    if let Some(publisher) = publisher {
+                        publisher.scan(app, agent_id);
+                    }
+                    // Get output from registry
+                    let output = AGENT_REGISTRY.get_output(&agent_id);
+                    return Ok(output);
+                }
+                AgentStatus::Failed => {
+                    return Err("Agent execution failed".to_string());
+                }
+                AgentStatus::Killed => {
+                    return Err("Agent was killed".to_string());
+                }
+                _ => {
+                    // Still running
+                }
+            }
+        } else {
+            // Agent not found - might have completed and been cleaned up
+            let output = AGENT_REGISTRY.get_output(&agent_id);
+            return Ok(output);
+        }
+
+        // Check timeout, stopping the agent so what it produced so far stays put
+        if start.elapsed().as_millis() as u64 > MAX_AGENT_WAIT_MS {
+            AGENT_REGISTRY.kill(&agent_id);
+            if let Some(mut agent) = app_state.agents.get_mut(&agent_id) {
+                agent.status = AgentStatus::Killed;
+            }
+            return Err(format!(
+                "Agent {} did not complete within {} seconds",
+                agent_id,
+                MAX_AGENT_WAIT_MS / 1000
+            ));
+        }
+
+        tokio::time::sleep(Duration::from_millis(POLL_INTERVAL_MS)).await;
+    }
+}
+
+/// Keep a finished execution in the history store, flagging how it
+/// departs from earlier runs of the workflow
+async fn record_history(
+    app: &AppHandle,
+    state: &WorkflowExecutionState,
+    context: &ExecutionContext,
+    graph: &WorkflowGraph,
+    workflow_name: &str,
+    chaos: bool,
+) {
+    if !matches!(
+        state.get_status(),
+        ExecutionStatus::Completed | ExecutionStatus::Failed | ExecutionStatus::Cancelled
+    ) {
+        return;
+    }
+    let project_name = get_project_name(&state.project_id).unwrap_or_default();
+    let history = get_history_store();
+    let mut record = history::record_execution(state, context, graph, workflow_name, project_name);
+    // Failures injected on purpose are no anomaly
+    if chaos {
+        record.tags.push(chaos::CHAOS_TAG.to_string());
+    } else {
+        record.anomalies = anomaly::detect(&record, &anomaly::baseline(history, &record), &AnomalyThresholds::default());
+    }
+    let anomalous = !record.anomalies.is_empty();
+    history.add(record.clone());
+    if anomalous {
+        anomaly::deliver(app, &record).await;
+    }
+}
+
+/// Keep what an agent produced before it failed or timed out on the node's
+/// state and, tagged as partial, in the context for downstream nodes
+fn capture_partial_output(
+    state: &WorkflowExecutionState,
+    context: &ExecutionContext,
+    node_id: &str,
+    agent_role: &str,
+    agent_id: Uuid,
+    output_tags: &[String],
+) {
+    let Some(output) = AGENT_REGISTRY.get_output(&agent_id).filter(|output| !output.trim().is_empty()) else {
+        return;
+    };
+
+    let mut tags = output_tags.to_vec();
+    tags.push(PARTIAL_OUTPUT_TAG.to_string());
+    context.store_output(AgentOutput {
+        agent_id,
+        node_id: node_id.to_string(),
+        agent_role: agent_role.to_string(),
+        data: OutputData::Text(output.clone()),
+        timestamp: Utc::now(),
+        tags,
+    });
+    state.update_node_state(node_id, |ns| ns.partial_output = Some(output));
+}
+
+/// Parse a finished node's output as test runner output. The results are
+/// stored beside the node's output with the `test_results` tag, leaving
+/// what downstream nodes and aggregation see as it was. For conditions
+/// such as `$tests.failed_tests == 0` they also become the node's
+/// `<node_id>.passed_tests`, `.failed_tests`, `.skipped_tests`,
+/// `.total_tests` and `.failed_test_names` variables, so test nodes don't
+/// overwrite each other.
+fn record_test_results(context: &ExecutionContext, node_id: &str, format: TestFormat, tags: &[String]) {
+    let Some(output) = context.get_latest_output(node_id) else {
+        return;
+    };
+    let Some(results) = test_results::parse(format, &output.data.to_context_string()) else {
+        EXECUTION_LOG.log(
+            context.execution_id,
+            Some(node_id),
+            LogLevel::Warn,
+            "Output holds no test results that could be parsed",
+            None,
+        );
+        return;
+    };
+
+    let variables = [
+        ("passed_tests", serde_json::json!(results.passed)),
+        ("failed_tests", serde_json::json!(results.failed)),
+        ("skipped_tests", serde_json::json!(results.skipped)),
+        ("total_tests", serde_json::json!(results.total)),
+        ("failed_test_names", serde_json::json!(results.failed_tests)),
+    ];
+    for (name, value) in variables {
+        context.set_variable(&format!("{}.{}", node_id, name), value);
+    }
+
+    let mut tags = tags.to_vec();
+    tags.push(TEST_RESULTS_TAG.to_string());
+    if let Ok(data) = serde_json::to_value(&results) {
+        context.store_output(AgentOutput {
+            agent_id: output.agent_id,
+            node_id: node_id.to_string(),
+            agent_role: output.agent_role,
+            data: OutputData::Json(data),
+            timestamp: Utc::now(),
+            tags,
+        });
+    }
+}
+
+/// Copy the attempt `retry_state` recorded last onto the node's state,
+/// where checkpoints and history pick it up
+fn record_retry_attempt(state: &WorkflowExecutionState, node_id: &str, retry_state: &RetryState) {
+    if let Some(attempt) = retry_state.get_errors().last() {
+        state.update_node_state(node_id, |ns| ns.retry_attempts.push(attempt.clone()));
+    }
+}
+
+/// Create a checkpoint from current state
+fn create_checkpoint(
+    state: &WorkflowExecutionState,
+    context: &ExecutionContext,
+    current_level: usize,
+) -> Result<ExecutionCheckpoint, String> {
+    let mut node_states = HashMap::new();
+
+    for entry in state.node_states.iter() {
+        let ns = entry.value();
+        node_states.insert(
+            entry.key().clone(),
+            NodeCheckpointState {
+                node_id: ns.node_id.clone(),
+                status: ns.status,
+                agent_id: ns.agent_id,
+                progress: ns.progress,
+                started_at: ns.started_at,
+                completed_at: ns.completed_at,
+                output: ns.output.clone(),
+                error: ns.error.clone(),
+                partial_output: ns.partial_output.clone(),
+                retry_attempts: ns.retry_attempts.clone(),
+            },
+        );
+    }
+
+    // Get outputs from context
+    let outputs = HashMap::new();
+    // Note: In a full implementation, we'd iterate over context outputs
+
+    let mut checkpoint = ExecutionCheckpoint::new(
+        state.execution_id,
+        state.workflow_id,
+        state.project_id,
+        context.original_prompt.clone(),
+        state.get_status(),
+        node_states,
+        state.execution_levels.clone(),
+        context.get_all_variables(),
+        outputs,
+        state.started_at,
+        current_level,
+    );
+
+    checkpoint.cancel_reason = state.get_cancel_reason();
+
+    if let Some(bus) = MESSAGE_BUS_STORE.get(&state.execution_id).filter(|bus| bus.config().persist) {
+        checkpoint.messages = bus.get_all_messages();
+    }
+
+    Ok(checkpoint)
+}
+
+/// Announce stages whose members have all finished, storing each one's
+/// aggregated output under the stage ID
+fn complete_stages(
+    app: &AppHandle,
+    graph: &WorkflowGraph,
+    config: &EnhancedExecutionConfig,
+    context: &ExecutionContext,
+    progress: &mut StageProgress,
+    node_statuses: &HashMap<String, NodeExecutionStatus>,
+) {
+    for finished in progress.finish(graph, node_statuses) {
+        let aggregation = config.stages.get(&finished.stage_id).and_then(|c| c.aggregation.as_ref());
+        let output = match (graph.get_stage(&finished.stage_id), aggregation) {
+            (Some(stage), Some(aggregation)) => stages::aggregate_stage(context, stage, aggregation).map(|output| {
+                let text = output.data.to_context_string();
+                context.store_output(output);
+                text
+            }),
+            _ => None,
+        };
+
+        emit_event(app, WorkflowEvent::StageCompleted {
+            execution_id: context.execution_id.to_string(),
+            stage_id: finished.stage_id,
+            status: finished.status,
+            duration_ms: finished.duration_ms,
+            output,
+        });
+    }
+}
+
+/// Record which predecessor outputs went into a node's prompt, and how
+fn log_aggregation(
+    execution_id: Uuid,
+    node_id: &str,
+    config: &NodeAggregationConfig,
+    available: usize,
+    aggregated: Option<&AggregatedOutput>,
+) {
+    let (message, details) = match aggregated {
+        Some(aggregated) => (
+            format!(
+                "Aggregated {} of {} predecessor output(s) with {}",
+                aggregated.sources.len(),
+                available,
+                aggregated.strategy_used
+            ),
+            serde_json::json!({
+                "config": config,
+                "sources": aggregated.sources,
+                "context_chars": aggregated.data.to_context_string().chars().count(),
+            }),
+        ),
+        None => (
+            format!("None of {} predecessor output(s) passed the aggregation filters", available),
+            serde_json::json!({ "config": config }),
+        ),
+    };
+    EXECUTION_LOG.log(execution_id, Some(node_id), LogLevel::Debug, message, Some(details));
+}
+
+fn emit_event(app: &AppHandle, event: WorkflowEvent) {
+    journal::publish(app, event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::{RateLimit, ResourceConfig};
+
+    #[test]
+    fn test_default_config() {
+        let config = EnhancedExecutionConfig::default();
+        assert!(config.enable_data_flow);
+        assert!(config.include_original_prompt);
+        assert_eq!(config.retry.max_attempts, 3);
+        assert!(config.max_parallel_nodes_per_level.is_none());
+        assert!(config.concurrency_groups.is_empty());
+        assert!(config.deadline.is_none());
+    }
+
+    #[test]
+    fn test_basic_config() {
+        let config = EnhancedExecutionConfig::basic();
+        assert!(!config.enable_data_flow);
+        assert!(!config.adaptive.enabled);
+        assert!(matches!(config.checkpoint_trigger, CheckpointTrigger::Manual));
+
+        let mut retry = RetryState::new(config.retry);
+        assert!(matches!(retry.should_retry("rate limit"), RetryDecision::Exhausted { .. }));
+    }
+
+    #[test]
+    fn test_deadline_projection() {
+        let deadline = DeadlineConfig::new(10_000);
+
+        // Two of four levels done in 4s projects to 8s: on track
+        assert_eq!(deadline.projected_overrun(4_000, 2, 4), None);
+        // Two of four levels done in 6s projects to 12s: at risk
+        assert_eq!(deadline.projected_overrun(6_000, 2, 4), Some(12_000));
+        // Nothing finished yet but already past the deadline
+        assert_eq!(deadline.projected_overrun(11_000, 0, 4), Some(11_000));
+    }
+
+    #[test]
+    fn test_deadline_pressure() {
+        let deadline = DeadlineConfig {
+            fast_model: Some("haiku".to_string()),
+            ..DeadlineConfig::new(10_000)
+        };
+        let mut config = EnhancedExecutionConfig::default();
+        let mut node_config = EnhancedNodeConfig::default();
+
+        deadline.apply_pressure(false, &mut config, &mut node_config);
+        assert_eq!(config.retry.max_attempts, 1);
+        assert_eq!(node_config.model.as_deref(), Some("haiku"));
+
+        let mut critical_config = EnhancedNodeConfig::default();
+        deadline.apply_pressure(true, &mut config, &mut critical_config);
+        assert!(critical_config.model.is_none());
+    }
+
+    #[test]
+    fn test_can_start_early() {
+        let graph = WorkflowGraph::from_json(&serde_json::json!({
+            "nodes": [
+                {"id": "design", "data": {"label": "Design", "agentRole": "architect"}},
+                {"id": "lint", "data": {"label": "Lint", "agentRole": "tester"}},
+                {"id": "build", "data": {"label": "Build", "agentRole": "implementer"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "design", "target": "build"},
+                {"id": "e2", "source": "lint", "target": "build"}
+            ]
+        }))
+        .unwrap();
+        let api = PublishedSection {
+            node_id: "design".to_string(),
+            section: "api".to_string(),
+        };
+        let configs = HashMap::from([(
+            "build".to_string(),
+            EnhancedNodeConfig {
+                start_on_published: vec![api],
+                ..Default::default()
+            },
+        )]);
+        let starting = vec!["design".to_string(), "lint".to_string()];
+
+        // Lint is still running and isn't streamed
+        assert!(!can_start_early(&graph, "build", &configs, &starting, &HashMap::new()));
+        let statuses = HashMap::from([("lint".to_string(), NodeExecutionStatus::Completed)]);
+        assert!(can_start_early(&graph, "build", &configs, &starting[..1], &statuses));
+        assert!(!can_start_early(&graph, "lint", &configs, &starting[..1], &statuses));
+    }
+
+    #[test]
+    fn test_no_result_cache_under_chaos() {
+        let node_config = EnhancedNodeConfig {
+            cache: Some(NodeCacheConfig {
+                reads: vec!["src/**/*.rs".to_string()],
+            }),
+            ..Default::default()
+        };
+        let mut config = EnhancedExecutionConfig::default();
+        assert!(result_cache_config(&config, &node_config).is_some());
+        assert!(result_cache_config(&config, &EnhancedNodeConfig::default()).is_none());
+
+        config.chaos = Some(ChaosConfig {
+            truncate_rate: 1.0,
+            ..Default::default()
+        });
+        assert!(result_cache_config(&config, &node_config).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_agent_slots_shared_between_executions() {
+        let manager = ResourceManager::new(ResourceConfig {
+            max_concurrent_agents: 1,
+            rate_limit_per_minute: None,
+            backend_rate_limits: HashMap::from([("anthropic".to_string(), RateLimit { per_minute: 3, burst: None })]),
+            acquire_timeout_ms: 50,
+            ..Default::default()
+        });
+        let key = RateLimitKey::backend("anthropic").credential("...wxyz");
+        let execution = || {
+            Arc::new(WorkflowExecutionState::new(
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+                "Build it".to_string(),
+                vec![vec!["build".to_string()]],
+            ))
+        };
+        let (first, second, third) = (execution(), execution(), execution());
+
+        let slot = acquire_agent_slot(&manager, &key, &first, "build", "implementer", || panic!("slot was free"))
+            .await
+            .unwrap();
+        assert_eq!(manager.get_stats().active_per_execution[&first.execution_id], 1);
+
+        // The second execution waits past the acquire timeout until the
+        // first one's node ends
+        let waited = std::sync::atomic::AtomicBool::new(false);
+        let waiting = acquire_agent_slot(&manager, &key, &second, "build", "implementer", || {
+            waited.store(true, std::sync::atomic::Ordering::Relaxed)
+        });
+        let release = async {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            let queued = manager.list_queued();
+            assert_eq!(queued.len(), 1);
+            assert_eq!((queued[0].task.execution_id, queued[0].task.node_id.as_str()), (second.execution_id, "build"));
+            drop(slot);
+        };
+        let (second_slot, ()) = tokio::join!(waiting, release);
+        let second_slot = second_slot.unwrap();
+        assert!(waited.load(std::sync::atomic::Ordering::Relaxed));
+        let stats = manager.get_stats();
+        assert_eq!(stats.active_per_execution.get(&first.execution_id), None);
+        assert_eq!(stats.active_per_execution[&second.execution_id], 1);
+        assert_eq!((stats.current_active, stats.total_released), (1, 1));
+
+        // A cancelled execution stops waiting
+        let cancel = async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            third.cancel();
+        };
+        let (cancelled, ()) = tokio::join!(acquire_agent_slot(&manager, &key, &third, "build", "tester", || {}), cancel);
+        assert!(cancelled.is_err());
+        assert!(manager.list_queued().is_empty());
+        // Waits that timed out or were cancelled took no rate limit token
+        let status = manager.rate_limit_status();
+        let bucket = status.iter().find(|s| s.credential.as_deref() == Some("...wxyz")).unwrap();
+        assert_eq!((bucket.allowed, bucket.tokens_remaining), (2, 1));
+        drop(second_slot);
+        assert_eq!(manager.available_permits(), 1);
+    }
+
+    #[test]
+    fn test_import_execution_outputs() {
+        let target = WorkflowExecutionState::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "Build it".to_string(),
+            vec![vec!["build".to_string(), "lint".to_string()]],
+        );
+        target.update_node_state("build", |ns| ns.complete(Some("artifact.tar".to_string())));
+
+        let imported = import_execution_outputs(&target, None, &[]).unwrap();
+        assert!(matches!(imported, OutputData::Json(ref v) if v == &serde_json::json!({"build": "artifact.tar"})));
+
+        // Explicitly requested outputs must exist
+        assert!(import_execution_outputs(&target, None, &["lint".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_test_results_are_kept_per_node() {
+        let context = ExecutionContext::new(Uuid::new_v4(), Uuid::new_v4(), "Test it".to_string());
+        for (node_id, summary) in [("unit", "3 passed; 1 failed"), ("integration", "2 passed; 0 failed")] {
+            context.store_output(AgentOutput {
+                agent_id: Uuid::new_v4(),
+                node_id: node_id.to_string(),
+                agent_role: "tester".to_string(),
+                data: OutputData::Text(format!("test result: ok. {}; 0 ignored; 0 measured; 0 filtered out", summary)),
+                timestamp: Utc::now(),
+                tags: Vec::new(),
+            });
+            record_test_results(&context, node_id, TestFormat::Cargo, &[]);
+        }
+
+        assert_eq!(context.get_variable("unit.failed_tests"), Some(serde_json::json!(1)));
+        assert_eq!(context.get_variable("integration.failed_tests"), Some(serde_json::json!(0)));
+        assert_eq!(context.get_variable("failed_tests"), None);
+
+        // The agent's own output stays the node's output
+        assert!(context.get_latest_output("unit").unwrap().data.to_context_string().starts_with("test result"));
+        assert_eq!(context.get_predecessor_outputs(&["unit".to_string()]).len(), 1);
+        let results = context.get_tagged_outputs(&["unit".to_string()], TEST_RESULTS_TAG);
+        assert!(matches!(&results[0].data, OutputData::Json(json) if json["failed"] == 1));
     }
 }
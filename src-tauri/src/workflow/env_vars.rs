@@ -0,0 +1,403 @@
+//! Environment variables of an execution.
+//!
+//! Agents and command nodes often need settings like a database URL or an
+//! API token. An execution's environment is merged from, lowest first, the
+//! project's `.env` file, the saved workflow's defaults and the overrides
+//! given for the run. Provides:
+//! - Parsing of `.env` files: comments, `export`, quoted and multi-line
+//!   values, and `# secret` after a value to mark it
+//! - Templating of values: `${NAME}` from the merged environment or the
+//!   app's own, `{{secret:NAME}}` from the stored secrets
+//! - Secret values (marked, named like credentials, or built from a stored
+//!   secret) redacted from the execution's events, log and history
+//!
+//! A `.env` line that can't be parsed, or a variable whose references can't
+//! be filled in, is left out with a warning rather than failing the run.
+
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use uuid::Uuid;
+
+use crate::settings::{secrets, REDACTED};
+
+/// Read from the project directory
+pub const DOTENV_FILE: &str = ".env";
+
+/// Variables named with one of these hold credentials by convention, and
+/// are secret without being marked
+const SECRET_NAME_PARTS: [&str; 7] = ["SECRET", "TOKEN", "PASSWORD", "PASSWD", "API_KEY", "PRIVATE_KEY", "CREDENTIAL"];
+
+lazy_static! {
+    static ref VAR_REF: Regex = Regex::new(r"\$\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap();
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvVar {
+    /// May hold `${NAME}` and `{{secret:NAME}}` references
+    pub value: String,
+    /// Redacted from logs and history
+    #[serde(default)]
+    pub secret: bool,
+}
+
+impl EnvVar {
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            secret: false,
+        }
+    }
+}
+
+fn valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_') && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Check that every name can be set on a process
+pub fn validate(vars: &BTreeMap<String, EnvVar>) -> Result<(), String> {
+    match vars.keys().find(|name| !valid_name(name)) {
+        Some(name) => Err(format!("Invalid environment variable name '{}'", name)),
+        None => Ok(()),
+    }
+}
+
+fn secret_name(name: &str) -> bool {
+    let upper = name.to_ascii_uppercase();
+    SECRET_NAME_PARTS.iter().any(|part| upper.contains(part))
+}
+
+/// The variables of a `.env` file, and a warning for each line left out
+pub fn parse_dotenv(content: &str) -> (BTreeMap<String, EnvVar>, Vec<String>) {
+    let mut vars = BTreeMap::new();
+    let mut warnings = Vec::new();
+    let mut lines = content.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((name, rest)) = line.split_once('=') else {
+            warnings.push(format!("line {}: expected NAME=value", index + 1));
+            continue;
+        };
+        let name = name.trim();
+        if !valid_name(name) {
+            warnings.push(format!("line {}: invalid name '{}'", index + 1, name));
+            continue;
+        }
+
+        let rest = rest.trim_start();
+        let (value, comment) = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                // A quoted value runs on to the closing quote, across lines
+                let mut raw = rest[1..].to_string();
+                let end = loop {
+                    if let Some(end) = closing_quote(&raw, quote) {
+                        break Some(end);
+                    }
+                    let Some((_, next)) = lines.next() else {
+                        break None;
+                    };
+                    raw.push('\n');
+                    raw.push_str(next);
+                };
+                let Some(end) = end else {
+                    warnings.push(format!("line {}: unterminated quote", index + 1));
+                    break;
+                };
+                let value = if quote == '"' { unescape(&raw[..end]) } else { raw[..end].to_string() };
+                let after = raw[end + 1..].trim();
+                (value, after.strip_prefix('#').unwrap_or("").trim().to_string())
+            }
+            _ => match rest.split_once(" #") {
+                Some((value, comment)) => (value.trim().to_string(), comment.trim().to_string()),
+                None => (rest.trim().to_string(), String::new()),
+            },
+        };
+        let secret = comment.eq_ignore_ascii_case("secret");
+        vars.insert(name.to_string(), EnvVar { value, secret });
+    }
+    (vars, warnings)
+}
+
+/// Byte index of the quote closing a value; `\"` doesn't close a
+/// double-quoted one
+fn closing_quote(raw: &str, quote: char) -> Option<usize> {
+    let mut escaped = false;
+    for (index, c) in raw.char_indices() {
+        match c {
+            '\\' if quote == '"' && !escaped => escaped = true,
+            c if c == quote && !escaped => return Some(index),
+            _ => escaped = false,
+        }
+    }
+    None
+}
+
+fn unescape(raw: &str) -> String {
+    let mut value = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => value.push('\n'),
+            Some('t') => value.push('\t'),
+            Some(c @ ('"' | '\\')) => value.push(c),
+            Some(c) => {
+                value.push('\\');
+                value.push(c);
+            }
+            None => value.push('\\'),
+        }
+    }
+    value
+}
+
+/// `vars` with their references filled in, leaving out those that can't
+/// be, with a warning each
+fn expand(vars: &BTreeMap<String, EnvVar>) -> (BTreeMap<String, EnvVar>, Vec<String>) {
+    let mut expanded = BTreeMap::new();
+    let mut warnings = Vec::new();
+    for name in vars.keys() {
+        if let Err(e) = expand_var(name, vars, &mut expanded, &mut Vec::new()) {
+            warnings.push(format!("{}: {}", name, e));
+        }
+    }
+    (expanded, warnings)
+}
+
+fn expand_var(
+    name: &str,
+    vars: &BTreeMap<String, EnvVar>,
+    expanded: &mut BTreeMap<String, EnvVar>,
+    stack: &mut Vec<String>,
+) -> Result<EnvVar, String> {
+    if let Some(var) = expanded.get(name) {
+        return Ok(var.clone());
+    }
+    if stack.iter().any(|outer| outer == name) {
+        stack.push(name.to_string());
+        return Err(format!("Environment variables refer to each other: {}", stack.join(" -> ")));
+    }
+
+    let var = &vars[name];
+    stack.push(name.to_string());
+    let mut secret = var.secret || secret_name(name);
+    let mut value = String::with_capacity(var.value.len());
+    let mut last = 0;
+    for caps in VAR_REF.captures_iter(&var.value) {
+        let reference = caps.get(0).unwrap();
+        value.push_str(&var.value[last..reference.start()]);
+        let referenced = &caps[1];
+        if vars.contains_key(referenced) {
+            let inner = expand_var(referenced, vars, expanded, stack)?;
+            secret |= inner.secret;
+            value.push_str(&inner.value);
+        } else {
+            let inner = std::env::var(referenced)
+                .map_err(|_| format!("Environment variable {} refers to {}, which is not set", name, referenced))?;
+            secret |= secret_name(referenced);
+            value.push_str(&inner);
+        }
+        last = reference.end();
+    }
+    value.push_str(&var.value[last..]);
+    stack.pop();
+
+    // Stored secrets go in last, so nothing in their values is expanded
+    if !secrets::referenced(&value).is_empty() {
+        value = secrets::resolve(&value)?;
+        secret = true;
+    }
+    let var = EnvVar { value, secret };
+    expanded.insert(name.to_string(), var.clone());
+    Ok(var)
+}
+
+/// The environment an execution's agents and commands run with
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionEnv {
+    vars: BTreeMap<String, EnvVar>,
+    /// Lines and variables left out
+    warnings: Vec<String>,
+}
+
+impl ExecutionEnv {
+    /// The `.env` file in `project_dir` with `vars` on top, references
+    /// filled in. Only invalid names in `vars` are an error.
+    pub fn resolve(project_dir: Option<&Path>, vars: &BTreeMap<String, EnvVar>) -> Result<Self, String> {
+        validate(vars)?;
+        let mut merged = BTreeMap::new();
+        let mut warnings = Vec::new();
+        if let Some(path) = project_dir.map(|dir| dir.join(DOTENV_FILE)).filter(|path| path.is_file()) {
+            match std::fs::read_to_string(&path) {
+                Ok(content) => {
+                    let (dotenv, skipped) = parse_dotenv(&content);
+                    merged = dotenv;
+                    warnings.extend(skipped.into_iter().map(|warning| format!("{}, {}", path.display(), warning)));
+                }
+                Err(e) => warnings.push(format!("Failed to read {}: {}", path.display(), e)),
+            }
+        }
+        merged.extend(vars.iter().map(|(name, var)| (name.clone(), var.clone())));
+        let (vars, skipped) = expand(&merged);
+        warnings.extend(skipped);
+        Ok(Self { vars, warnings })
+    }
+
+    /// What was left out of the environment, and why
+    pub fn warnings(&self) -> &[String] {
+        &self.warnings
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.vars.is_empty()
+    }
+
+    /// Names and values to set on a process
+    pub fn pairs(&self) -> Vec<(String, String)> {
+        self.vars.iter().map(|(name, var)| (name.clone(), var.value.clone())).collect()
+    }
+
+    /// A copy of `value` with the secret values in its strings redacted
+    pub fn redacted<T: Serialize + DeserializeOwned + Clone>(&self, value: &T) -> T {
+        let values: Vec<&str> = self.vars.values().filter(|var| var.secret).map(|var| var.value.as_str()).collect();
+        secrets::redacted_values(value, &values)
+    }
+}
+
+/// A copy with secret values replaced by [`REDACTED`], for the UI
+pub fn masked(vars: &BTreeMap<String, EnvVar>) -> BTreeMap<String, EnvVar> {
+    vars.iter()
+        .map(|(name, var)| {
+            let value = if var.secret || secret_name(name) { REDACTED.to_string() } else { var.value.clone() };
+            (name.clone(), EnvVar { value, secret: var.secret })
+        })
+        .collect()
+}
+
+/// Put back values that came in as [`REDACTED`] from `current`
+pub fn restore_masked(vars: &mut BTreeMap<String, EnvVar>, current: &BTreeMap<String, EnvVar>) {
+    for (name, var) in vars.iter_mut() {
+        if var.value == REDACTED {
+            if let Some(stored) = current.get(name) {
+                var.value = stored.value.clone();
+            }
+        }
+    }
+}
+
+/// Environments of the executions, kept until they are released
+#[derive(Default)]
+pub struct ExecutionEnvStore {
+    envs: DashMap<Uuid, ExecutionEnv>,
+}
+
+impl ExecutionEnvStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, execution_id: Uuid, env: ExecutionEnv) {
+        if !env.is_empty() {
+            self.envs.insert(execution_id, env);
+        }
+    }
+
+    /// Names and values for the execution's processes
+    pub fn pairs(&self, execution_id: &Uuid) -> Vec<(String, String)> {
+        self.envs.get(execution_id).map(|env| env.pairs()).unwrap_or_default()
+    }
+
+    /// A copy of `value` with the execution's secret values redacted
+    pub fn redacted<T: Serialize + DeserializeOwned + Clone>(&self, execution_id: &Uuid, value: &T) -> T {
+        match self.envs.get(execution_id) {
+            Some(env) => env.redacted(value),
+            None => value.clone(),
+        }
+    }
+
+    pub fn remove(&self, execution_id: &Uuid) {
+        self.envs.remove(execution_id);
+    }
+}
+
+lazy_static! {
+    pub static ref EXECUTION_ENVS: ExecutionEnvStore = ExecutionEnvStore::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dotenv_and_templating() {
+        let dotenv = "# Local settings\n\
+            export DB_HOST=localhost\n\
+            DB_URL=\"postgres://app:${DB_PASSWORD}@${DB_HOST}/shop\"\n\
+            DB_PASSWORD='p@ss w0rd' # secret\n\
+            GREETING=\"Hello\\n  world\"\n\
+            CERT=\"-----BEGIN-----\n\
+            abc\n\
+            -----END-----\"\n\
+            PORT=8080 # the dev server\n";
+        let (vars, warnings) = parse_dotenv(dotenv);
+        assert!(warnings.is_empty());
+        assert_eq!(vars["DB_PASSWORD"], EnvVar { value: "p@ss w0rd".to_string(), secret: true });
+        assert_eq!(vars["GREETING"].value, "Hello\n  world");
+        assert_eq!(vars["CERT"].value, "-----BEGIN-----\nabc\n-----END-----");
+        assert_eq!(vars["PORT"], EnvVar::new("8080"));
+        // Bad lines are left out, the rest still read
+        let (vars, warnings) = parse_dotenv("1BAD=x\nno value\nGOOD=1\nOPEN=\"never closed\nLOST=2");
+        assert_eq!(vars.keys().collect::<Vec<_>>(), ["GOOD"]);
+        assert_eq!(warnings, ["line 1: invalid name '1BAD'", "line 2: expected NAME=value", "line 4: unterminated quote"]);
+
+        let dir = std::env::temp_dir().join(format!("nexus-env-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(DOTENV_FILE), dotenv).unwrap();
+        // The run overrides the project's host; the URL picks that up
+        let overrides = BTreeMap::from([
+            ("DB_HOST".to_string(), EnvVar::new("db.internal")),
+            ("GITHUB_TOKEN".to_string(), EnvVar::new("ghp_abc123")),
+        ]);
+        let env = ExecutionEnv::resolve(Some(&dir), &overrides).unwrap();
+        let pairs: BTreeMap<String, String> = env.pairs().into_iter().collect();
+        assert_eq!(pairs["DB_URL"], "postgres://app:p@ss w0rd@db.internal/shop");
+
+        // The URL holds the password, and the token is named like one
+        let log = vec![format!("connecting to {}", pairs["DB_URL"]), "pushed with ghp_abc123".to_string()];
+        assert_eq!(
+            env.redacted(&log),
+            ["connecting to [REDACTED:secret]", "pushed with [REDACTED:secret]"]
+        );
+
+        // Variables that can't be filled in are left out with a warning
+        let looping = BTreeMap::from([("A".to_string(), EnvVar::new("${B}")), ("B".to_string(), EnvVar::new("${A}"))]);
+        let env = ExecutionEnv::resolve(None, &looping).unwrap();
+        assert!(env.is_empty());
+        assert!(env.warnings()[0].contains("A -> B -> A"));
+        let unset = BTreeMap::from([
+            ("A".to_string(), EnvVar::new("${NEXUS_SURELY_UNSET_VARIABLE}")),
+            ("B".to_string(), EnvVar::new("b")),
+        ]);
+        let env = ExecutionEnv::resolve(None, &unset).unwrap();
+        assert_eq!(env.pairs(), [("B".to_string(), "b".to_string())]);
+        assert_eq!(env.warnings().len(), 1);
+        assert!(ExecutionEnv::resolve(None, &BTreeMap::from([("1A".to_string(), EnvVar::new("x"))])).is_err());
+
+        let mut shown = masked(&overrides);
+        assert_eq!(shown["GITHUB_TOKEN"].value, REDACTED);
+        restore_masked(&mut shown, &overrides);
+        assert_eq!(shown, overrides);
+    }
+}
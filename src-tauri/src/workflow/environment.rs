@@ -0,0 +1,112 @@
+//! The environment an execution ran in.
+//!
+//! Captured when an execution starts and kept on its history record, so a
+//! result can be traced back to the tools, models and code that produced
+//! it, and the run repeated under the same conditions. Provides:
+//! - Versions of NEXUS and the agent CLI
+//! - The models agents were configured with
+//! - The project's git commit, and whether the tree had local changes
+//! - A hash of the custom role definitions in the local library
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use crate::commands::integrations::get_registry_sync;
+use crate::commands::system::tool_version;
+use crate::integrations::SyncItemKind;
+use crate::process::spawner::find_claude_path;
+
+use super::rollback::git;
+
+/// Model key for agents without an override, which use the CLI's default
+pub const DEFAULT_MODEL_KEY: &str = "default";
+/// Model key for the model nodes switch to when behind the deadline
+pub const FAST_MODEL_KEY: &str = "deadline_fast_model";
+
+/// What an execution started with
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EnvironmentFingerprint {
+    pub captured_at: Option<DateTime<Utc>>,
+    pub nexus_version: String,
+    /// e.g. `linux-x86_64`
+    pub platform: String,
+    /// Agent backends by name, with the version they report
+    pub backends: BTreeMap<String, String>,
+    /// Model by node ID, plus the default under [`DEFAULT_MODEL_KEY`] and
+    /// any deadline fallback under [`FAST_MODEL_KEY`]
+    pub models: BTreeMap<String, String>,
+    /// `HEAD` of the project, if it is a git repository
+    pub git_commit: Option<String>,
+    /// The working tree had uncommitted changes, so the commit alone
+    /// doesn't reproduce it
+    pub git_dirty: bool,
+    /// Changes whenever a custom role definition does
+    pub role_registry_hash: Option<String>,
+}
+
+/// Fingerprint the environment for an execution in `project_dir` whose
+/// agent nodes override their model as in `node_models`
+pub async fn capture(
+    project_dir: Option<&Path>,
+    node_models: BTreeMap<String, String>,
+    fast_model: Option<String>,
+) -> EnvironmentFingerprint {
+    let mut models = node_models;
+    if let Some(fast_model) = fast_model {
+        models.insert(FAST_MODEL_KEY.to_string(), fast_model);
+    }
+    models.insert(
+        DEFAULT_MODEL_KEY.to_string(),
+        std::env::var("ANTHROPIC_MODEL").unwrap_or_else(|_| DEFAULT_MODEL_KEY.to_string()),
+    );
+
+    let mut backends = BTreeMap::new();
+    if let Some(claude) = find_claude_path() {
+        match tool_version(&claude).await {
+            Ok(version) => backends.insert("claude".to_string(), version),
+            Err(e) => backends.insert("claude".to_string(), format!("unknown ({})", e)),
+        };
+    }
+
+    let (git_commit, git_dirty) = match project_dir {
+        Some(dir) => match git(dir, &["rev-parse", "HEAD"], None).await {
+            Ok(commit) => {
+                let status = git(dir, &["status", "--porcelain"], None).await.unwrap_or_default();
+                (Some(commit.trim().to_string()), !status.trim().is_empty())
+            }
+            Err(_) => (None, false),
+        },
+        None => (None, false),
+    };
+
+    EnvironmentFingerprint {
+        captured_at: Some(Utc::now()),
+        nexus_version: env!("CARGO_PKG_VERSION").to_string(),
+        platform: format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+        backends,
+        models,
+        git_commit,
+        git_dirty,
+        role_registry_hash: role_registry_hash(),
+    }
+}
+
+/// FNV-1a over the sorted role keys and their content hashes; `None` if
+/// the library can't be read
+fn role_registry_hash() -> Option<String> {
+    let mut roles: Vec<String> = get_registry_sync()
+        .list_items(Some(SyncItemKind::Role))
+        .ok()?
+        .iter()
+        .map(|item| format!("{}={}", item.key(), item.content_hash()))
+        .collect();
+    roles.sort();
+    let hash = roles
+        .join("\n")
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3));
+    Some(format!("{:016x}", hash))
+}
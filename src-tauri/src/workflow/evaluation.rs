@@ -0,0 +1,274 @@
+//! Evaluation suites: run a workflow over a set of cases and score it.
+//!
+//! Provides:
+//! - Suites of named cases (prompt and variables per case)
+//! - Per-case verdicts from the assert nodes each execution recorded
+//! - Pass rates overall and per assert node
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use super::assertions::AssertionOutcome;
+use super::batch::BatchItem;
+use super::state::ExecutionStatus;
+
+/// One case of an evaluation suite
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvalCase {
+    pub name: String,
+    /// Prompt for this case (defaults to the suite's prompt template)
+    #[serde(default)]
+    pub input_prompt: Option<String>,
+    #[serde(default)]
+    pub variables: HashMap<String, serde_json::Value>,
+}
+
+impl EvalCase {
+    pub fn to_batch_item(&self) -> BatchItem {
+        BatchItem {
+            key: Some(self.name.clone()),
+            input_prompt: self.input_prompt.clone(),
+            variables: self.variables.clone(),
+        }
+    }
+}
+
+/// A saved set of cases to run a workflow against
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationSuite {
+    pub id: Uuid,
+    pub name: String,
+    pub workflow_id: Uuid,
+    pub project_id: Uuid,
+    /// Prompt template; `{{name}}` is replaced with each case's variables
+    #[serde(default)]
+    pub input_prompt: Option<String>,
+    pub cases: Vec<EvalCase>,
+    /// Cases running at once
+    #[serde(default)]
+    pub max_concurrent: Option<usize>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Verdict for one case
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseResult {
+    pub name: String,
+    pub execution_id: Option<Uuid>,
+    pub status: ExecutionStatus,
+    pub assertions: Vec<AssertionOutcome>,
+    /// Execution completed and every assertion passed
+    pub passed: bool,
+    pub error: Option<String>,
+}
+
+impl CaseResult {
+    pub fn new(
+        name: String,
+        execution_id: Option<Uuid>,
+        status: ExecutionStatus,
+        assertions: Vec<AssertionOutcome>,
+        error: Option<String>,
+    ) -> Self {
+        let passed = status == ExecutionStatus::Completed && assertions.iter().all(|a| a.passed);
+        Self {
+            name,
+            execution_id,
+            status,
+            assertions,
+            passed,
+            error,
+        }
+    }
+}
+
+/// Pass rate of one assert node across all cases
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AssertionStats {
+    pub node_id: String,
+    pub evaluated: usize,
+    pub passed: usize,
+    pub pass_rate: f64,
+    /// Mean judge score or measured value, when the check produces one
+    pub mean_score: Option<f64>,
+}
+
+/// Result of running a suite
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EvaluationReport {
+    pub id: Uuid,
+    pub suite_id: Uuid,
+    pub batch_id: Uuid,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: DateTime<Utc>,
+    pub total_cases: usize,
+    pub passed_cases: usize,
+    /// Passed cases / total cases
+    pub pass_rate: f64,
+    pub assertions: Vec<AssertionStats>,
+    pub cases: Vec<CaseResult>,
+}
+
+impl EvaluationReport {
+    pub fn new(suite_id: Uuid, batch_id: Uuid, started_at: DateTime<Utc>, cases: Vec<CaseResult>) -> Self {
+        let rate = |passed: usize, total: usize| if total == 0 { 0.0 } else { passed as f64 / total as f64 };
+
+        let mut by_node: BTreeMap<&str, Vec<&AssertionOutcome>> = BTreeMap::new();
+        for outcome in cases.iter().flat_map(|case| &case.assertions) {
+            by_node.entry(&outcome.node_id).or_default().push(outcome);
+        }
+
+        let assertions = by_node
+            .into_iter()
+            .map(|(node_id, outcomes)| {
+                let passed = outcomes.iter().filter(|o| o.passed).count();
+                let scores: Vec<f64> = outcomes.iter().filter_map(|o| o.score).collect();
+                AssertionStats {
+                    node_id: node_id.to_string(),
+                    evaluated: outcomes.len(),
+                    passed,
+                    pass_rate: rate(passed, outcomes.len()),
+                    mean_score: if scores.is_empty() {
+                        None
+                    } else {
+                        Some(scores.iter().sum::<f64>() / scores.len() as f64)
+                    },
+                }
+            })
+            .collect();
+
+        let passed_cases = cases.iter().filter(|case| case.passed).count();
+
+        Self {
+            id: Uuid::new_v4(),
+            suite_id,
+            batch_id,
+            started_at,
+            completed_at: Utc::now(),
+            total_cases: cases.len(),
+            passed_cases,
+            pass_rate: rate(passed_cases, cases.len()),
+            assertions,
+            cases,
+        }
+    }
+}
+
+/// Store for suites and their reports
+pub struct EvaluationStore {
+    suites: DashMap<Uuid, EvaluationSuite>,
+    reports: RwLock<Vec<EvaluationReport>>,
+}
+
+impl EvaluationStore {
+    pub fn new() -> Self {
+        Self {
+            suites: DashMap::new(),
+            reports: RwLock::new(Vec::new()),
+        }
+    }
+
+    pub fn insert_suite(&self, suite: EvaluationSuite) {
+        self.suites.insert(suite.id, suite);
+    }
+
+    pub fn get_suite(&self, suite_id: &Uuid) -> Option<EvaluationSuite> {
+        self.suites.get(suite_id).map(|s| s.clone())
+    }
+
+    /// Remove a suite and its reports
+    pub fn remove_suite(&self, suite_id: &Uuid) -> bool {
+        self.reports.write().retain(|r| &r.suite_id != suite_id);
+        self.suites.remove(suite_id).is_some()
+    }
+
+    /// All suites, newest first
+    pub fn list_suites(&self) -> Vec<EvaluationSuite> {
+        let mut suites: Vec<EvaluationSuite> = self.suites.iter().map(|s| s.clone()).collect();
+        suites.sort_by_key(|s| std::cmp::Reverse(s.created_at));
+        suites
+    }
+
+    pub fn add_report(&self, report: EvaluationReport) {
+        self.reports.write().push(report);
+    }
+
+    /// Reports for a suite, newest first
+    pub fn reports_for(&self, suite_id: &Uuid) -> Vec<EvaluationReport> {
+        let mut reports: Vec<EvaluationReport> = self
+            .reports
+            .read()
+            .iter()
+            .filter(|r| &r.suite_id == suite_id)
+            .cloned()
+            .collect();
+        reports.sort_by_key(|r| std::cmp::Reverse(r.completed_at));
+        reports
+    }
+}
+
+impl Default for EvaluationStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(node_id: &str, passed: bool, score: Option<f64>) -> AssertionOutcome {
+        AssertionOutcome {
+            node_id: node_id.to_string(),
+            target: "impl".to_string(),
+            passed,
+            message: String::new(),
+            score,
+        }
+    }
+
+    #[test]
+    fn test_report_pass_rates() {
+        let cases = vec![
+            CaseResult::new(
+                "all pass".to_string(),
+                Some(Uuid::new_v4()),
+                ExecutionStatus::Completed,
+                vec![outcome("format", true, None), outcome("judge", true, Some(9.0))],
+                None,
+            ),
+            CaseResult::new(
+                "judge fails".to_string(),
+                Some(Uuid::new_v4()),
+                ExecutionStatus::Completed,
+                vec![outcome("format", true, None), outcome("judge", false, Some(4.0))],
+                None,
+            ),
+            CaseResult::new(
+                "execution failed".to_string(),
+                Some(Uuid::new_v4()),
+                ExecutionStatus::Failed,
+                vec![],
+                Some("Agent execution failed".to_string()),
+            ),
+        ];
+
+        let report = EvaluationReport::new(Uuid::new_v4(), Uuid::new_v4(), Utc::now(), cases);
+
+        assert_eq!(report.total_cases, 3);
+        assert_eq!(report.passed_cases, 1);
+        assert!((report.pass_rate - 1.0 / 3.0).abs() < 1e-9);
+
+        let judge = report.assertions.iter().find(|a| a.node_id == "judge").unwrap();
+        assert_eq!((judge.evaluated, judge.passed), (2, 1));
+        assert_eq!(judge.mean_score, Some(6.5));
+
+        let format = report.assertions.iter().find(|a| a.node_id == "format").unwrap();
+        assert_eq!(format.pass_rate, 1.0);
+        assert_eq!(format.mean_score, None);
+    }
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use super::conditions::ConditionResult;
+use super::guardrails::GuardrailViolation;
 use super::state::NodeExecutionStatus;
 
 /// Events emitted during workflow execution for frontend updates
@@ -45,11 +47,14 @@ pub enum WorkflowEvent {
         error: String,
     },
 
-    /// Node was skipped due to dependency failure
+    /// Node was skipped due to dependency failure or its condition
     NodeSkipped {
         execution_id: String,
         node_id: String,
         reason: String,
+        /// How the node's condition evaluated, when that's why it was skipped
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        condition: Option<ConditionResult>,
     },
 
     /// Execution level started (all nodes in level running in parallel)
@@ -65,6 +70,33 @@ pub enum WorkflowEvent {
         level: usize,
     },
 
+    /// The first member of a stage was reached and its condition met
+    StageStarted {
+        execution_id: String,
+        stage_id: String,
+        label: String,
+        node_ids: Vec<String>,
+    },
+
+    /// A stage's condition wasn't met; all its members are skipped
+    StageSkipped {
+        execution_id: String,
+        stage_id: String,
+        reason: String,
+        condition: ConditionResult,
+    },
+
+    /// Every member of a stage has finished
+    StageCompleted {
+        execution_id: String,
+        stage_id: String,
+        /// Member statuses collapsed into one
+        status: NodeExecutionStatus,
+        duration_ms: u64,
+        /// The stage's aggregated output, when it aggregates
+        output: Option<String>,
+    },
+
     /// Overall progress update
     ProgressUpdate {
         execution_id: String,
@@ -92,6 +124,73 @@ pub enum WorkflowEvent {
     ExecutionCancelled {
         execution_id: String,
         workflow_id: String,
+        /// Set when cancelled for a reason, e.g. app shutdown
+        reason: Option<String>,
+    },
+
+    /// Execution is projected to miss its deadline
+    DeadlineAtRisk {
+        execution_id: String,
+        deadline_ms: u64,
+        elapsed_ms: u64,
+        projected_ms: u64,
+        critical_path: Vec<String>,
+    },
+
+    /// An assert node checked another node's output
+    AssertionEvaluated {
+        execution_id: String,
+        node_id: String,
+        target: String,
+        passed: bool,
+        message: String,
+        score: Option<f64>,
+    },
+
+    /// A self-correcting node's tests ran after an agent round
+    SelfCorrectionRound {
+        execution_id: String,
+        node_id: String,
+        /// 0 for the first attempt, then 1..=max_rounds
+        round: u32,
+        max_rounds: u32,
+        passed: bool,
+        exit_code: Option<i32>,
+    },
+
+    /// Two nodes of the same level edited the same files
+    ConflictDetected {
+        execution_id: String,
+        level: usize,
+        /// Node that finished first
+        first_node: String,
+        second_node: String,
+        files: Vec<String>,
+        /// Conflict policy applied: "report", "serialize" or "merge"
+        resolution: String,
+    },
+
+    /// An isolated execution's nodes are done and its changes await
+    /// approval; sent just before the execution's final event
+    WorkspaceReady {
+        execution_id: String,
+        workspace_dir: String,
+        changed_files: usize,
+    },
+
+    /// A running node published a section of its output early
+    SectionPublished {
+        execution_id: String,
+        node_id: String,
+        section: String,
+    },
+
+    /// A node's output tripped its guardrails; the node waits for someone
+    /// to approve it anyway
+    GuardrailTriggered {
+        execution_id: String,
+        node_id: String,
+        violations: Vec<GuardrailViolation>,
     },
 }
 
@@ -107,10 +206,20 @@ impl WorkflowEvent {
             WorkflowEvent::NodeSkipped { execution_id, .. } => execution_id,
             WorkflowEvent::LevelStarted { execution_id, .. } => execution_id,
             WorkflowEvent::LevelCompleted { execution_id, .. } => execution_id,
+            WorkflowEvent::StageStarted { execution_id, .. } => execution_id,
+            WorkflowEvent::StageSkipped { execution_id, .. } => execution_id,
+            WorkflowEvent::StageCompleted { execution_id, .. } => execution_id,
             WorkflowEvent::ProgressUpdate { execution_id, .. } => execution_id,
             WorkflowEvent::ExecutionCompleted { execution_id, .. } => execution_id,
             WorkflowEvent::ExecutionFailed { execution_id, .. } => execution_id,
             WorkflowEvent::ExecutionCancelled { execution_id, .. } => execution_id,
+            WorkflowEvent::DeadlineAtRisk { execution_id, .. } => execution_id,
+            WorkflowEvent::AssertionEvaluated { execution_id, .. } => execution_id,
+            WorkflowEvent::SelfCorrectionRound { execution_id, .. } => execution_id,
+            WorkflowEvent::ConflictDetected { execution_id, .. } => execution_id,
+            WorkflowEvent::WorkspaceReady { execution_id, .. } => execution_id,
+            WorkflowEvent::SectionPublished { execution_id, .. } => execution_id,
+            WorkflowEvent::GuardrailTriggered { execution_id, .. } => execution_id,
         }
     }
 
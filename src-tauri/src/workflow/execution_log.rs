@@ -0,0 +1,377 @@
+//! Execution log: what the executor did and why, per execution.
+//!
+//! Events say what happened to a node but not how the executor got there,
+//! which is what you need when a node was skipped or given the wrong
+//! context. Provides:
+//! - Log levels per execution, with per-node overrides, switchable while
+//!   the execution runs
+//! - Verbose (`debug`) entries for condition evaluations, aggregation
+//!   decisions and prompt assembly, and the assembled prompts themselves
+//!   at `trace`
+//! - Reading the log back, filtered by node and level
+//!
+//! Like the event journal the log is in memory and bounded.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use super::events::WorkflowEvent;
+use super::state::NodeExecutionStatus;
+
+pub const MAX_ENTRIES_PER_EXECUTION: usize = 5_000;
+pub const MAX_EXECUTIONS: usize = 200;
+
+/// How much to log, from least to most
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    #[default]
+    Info,
+    /// Verbose: decisions and how they were made
+    Debug,
+    /// Also full prompts
+    Trace,
+}
+
+impl fmt::Display for LogLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LogLevel::Error => "error",
+            LogLevel::Warn => "warn",
+            LogLevel::Info => "info",
+            LogLevel::Debug => "debug",
+            LogLevel::Trace => "trace",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for LogLevel {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "error" => Ok(LogLevel::Error),
+            "warn" | "warning" => Ok(LogLevel::Warn),
+            "info" => Ok(LogLevel::Info),
+            "debug" | "verbose" => Ok(LogLevel::Debug),
+            "trace" => Ok(LogLevel::Trace),
+            other => Err(format!(
+                "Invalid log level '{}': expected error, warn, info, debug (verbose) or trace",
+                other
+            )),
+        }
+    }
+}
+
+/// One line of an execution's log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub timestamp: DateTime<Utc>,
+    pub level: LogLevel,
+    /// Unset for execution-wide entries
+    pub node_id: Option<String>,
+    pub message: String,
+    /// Structured data behind the message, e.g. a condition's evaluation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub details: Option<serde_json::Value>,
+}
+
+/// Log levels of an execution
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogLevels {
+    pub execution: LogLevel,
+    /// Overrides for single nodes
+    pub nodes: HashMap<String, LogLevel>,
+}
+
+impl LogLevels {
+    fn for_node(&self, node_id: Option<&str>) -> LogLevel {
+        node_id
+            .and_then(|id| self.nodes.get(id).copied())
+            .unwrap_or(self.execution)
+    }
+}
+
+struct ExecutionEntries {
+    entries: VecDeque<LogEntry>,
+    started_at: DateTime<Utc>,
+}
+
+/// Logs and log levels of recent executions
+pub struct ExecutionLogStore {
+    levels: DashMap<Uuid, LogLevels>,
+    logs: DashMap<Uuid, ExecutionEntries>,
+}
+
+impl ExecutionLogStore {
+    pub fn new() -> Self {
+        Self {
+            levels: DashMap::new(),
+            logs: DashMap::new(),
+        }
+    }
+
+    /// Set an execution's level, or only one node's when `node_id` is given
+    pub fn set_level(&self, execution_id: Uuid, node_id: Option<&str>, level: LogLevel) {
+        let mut levels = self.levels.entry(execution_id).or_default();
+        match node_id {
+            Some(node_id) => {
+                levels.nodes.insert(node_id.to_string(), level);
+            }
+            None => levels.execution = level,
+        }
+    }
+
+    pub fn levels(&self, execution_id: &Uuid) -> LogLevels {
+        self.levels.get(execution_id).map(|l| l.clone()).unwrap_or_default()
+    }
+
+    /// Whether entries at `level` are kept for this node. Check it before
+    /// building expensive details.
+    pub fn enabled(&self, execution_id: &Uuid, node_id: Option<&str>, level: LogLevel) -> bool {
+        let configured = self
+            .levels
+            .get(execution_id)
+            .map_or(LogLevel::default(), |levels| levels.for_node(node_id));
+        level <= configured
+    }
+
+    /// Add an entry if its level is enabled
+    pub fn log(
+        &self,
+        execution_id: Uuid,
+        node_id: Option<&str>,
+        level: LogLevel,
+        message: impl Into<String>,
+        details: Option<serde_json::Value>,
+    ) {
+        if !self.enabled(&execution_id, node_id, level) {
+            return;
+        }
+
+        let is_new = !self.logs.contains_key(&execution_id);
+        {
+            let mut log = self.logs.entry(execution_id).or_insert_with(|| ExecutionEntries {
+                entries: VecDeque::new(),
+                started_at: Utc::now(),
+            });
+            log.entries.push_back(LogEntry {
+                timestamp: Utc::now(),
+                level,
+                node_id: node_id.map(str::to_string),
+                message: message.into(),
+                details,
+            });
+            if log.entries.len() > MAX_ENTRIES_PER_EXECUTION {
+                log.entries.pop_front();
+            }
+        }
+
+        if is_new {
+            self.evict_oldest();
+        }
+    }
+
+    fn evict_oldest(&self) {
+        while self.logs.len() > MAX_EXECUTIONS {
+            let oldest = self
+                .logs
+                .iter()
+                .min_by_key(|entry| entry.started_at)
+                .map(|entry| *entry.key());
+            match oldest {
+                Some(id) => {
+                    self.logs.remove(&id);
+                    self.levels.remove(&id);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// An execution's entries, oldest first, for one node if `node_id` is
+    /// given and at `max_level` or more severe
+    pub fn entries(&self, execution_id: &Uuid, node_id: Option<&str>, max_level: LogLevel) -> Vec<LogEntry> {
+        self.logs
+            .get(execution_id)
+            .map(|log| {
+                log.entries
+                    .iter()
+                    .filter(|e| e.level <= max_level)
+                    .filter(|e| node_id.map_or(true, |id| e.node_id.as_deref() == Some(id)))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Log the milestones of an execution as they are emitted
+    pub fn log_event(&self, event: &WorkflowEvent) {
+        let execution_id = match Uuid::parse_str(event.execution_id()) {
+            Ok(id) => id,
+            Err(_) => return,
+        };
+
+        let (level, node_id, message) = match event {
+            WorkflowEvent::ExecutionStarted { workflow_name, total_nodes, .. } => (
+                LogLevel::Info,
+                None,
+                format!("Started workflow '{}' with {} nodes", workflow_name, total_nodes),
+            ),
+            WorkflowEvent::NodeStatusChanged { node_id, status: NodeExecutionStatus::Queued, .. } => {
+                (LogLevel::Info, Some(node_id), "Queued for a concurrency slot or lock".to_string())
+            }
+            WorkflowEvent::NodeStatusChanged { node_id, status: NodeExecutionStatus::WaitingForApproval, .. } => {
+                (LogLevel::Info, Some(node_id), "Waiting for approval".to_string())
+            }
+            WorkflowEvent::NodeStarted { node_id, agent_id, .. } => {
+                (LogLevel::Info, Some(node_id), format!("Started agent {}", agent_id))
+            }
+            WorkflowEvent::NodeCompleted { node_id, .. } => (LogLevel::Info, Some(node_id), "Completed".to_string()),
+            WorkflowEvent::NodeFailed { node_id, error, .. } => {
+                (LogLevel::Error, Some(node_id), format!("Failed: {}", error))
+            }
+            WorkflowEvent::NodeSkipped { node_id, reason, .. } => {
+                (LogLevel::Info, Some(node_id), format!("Skipped: {}", reason))
+            }
+            WorkflowEvent::StageStarted { stage_id, node_ids, .. } => (
+                LogLevel::Info,
+                None,
+                format!("Started stage '{}' with {} nodes", stage_id, node_ids.len()),
+            ),
+            WorkflowEvent::StageSkipped { stage_id, reason, .. } => {
+                (LogLevel::Info, None, format!("Skipped stage '{}': {}", stage_id, reason))
+            }
+            WorkflowEvent::StageCompleted { stage_id, status, duration_ms, .. } => {
+                let (level, outcome) = match status {
+                    NodeExecutionStatus::Failed => (LogLevel::Warn, "failed"),
+                    NodeExecutionStatus::Skipped => (LogLevel::Info, "skipped all its nodes"),
+                    _ => (LogLevel::Info, "completed"),
+                };
+                (level, None, format!("Stage '{}' {} in {} ms", stage_id, outcome, duration_ms))
+            }
+            WorkflowEvent::AssertionEvaluated { node_id, target, passed, message, .. } => (
+                if *passed { LogLevel::Info } else { LogLevel::Warn },
+                Some(node_id),
+                format!("Assertion on '{}' {}: {}", target, if *passed { "passed" } else { "failed" }, message),
+            ),
+            WorkflowEvent::ConflictDetected { first_node, second_node, files, resolution, .. } => (
+                LogLevel::Warn,
+                None,
+                format!(
+                    "'{}' and '{}' both edited {} file(s); resolution: {}",
+                    first_node,
+                    second_node,
+                    files.len(),
+                    resolution
+                ),
+            ),
+            WorkflowEvent::SectionPublished { node_id, section, .. } => {
+                (LogLevel::Info, Some(node_id), format!("Published section '{}'", section))
+            }
+            WorkflowEvent::GuardrailTriggered { node_id, violations, .. } => (
+                LogLevel::Warn,
+                Some(node_id),
+                format!("Guardrails held the output on {} violation(s); waiting for approval", violations.len()),
+            ),
+            WorkflowEvent::DeadlineAtRisk { projected_ms, deadline_ms, .. } => (
+                LogLevel::Warn,
+                None,
+                format!("Projected to take {} ms against a {} ms deadline", projected_ms, deadline_ms),
+            ),
+            WorkflowEvent::ExecutionCompleted { duration_ms, .. } => {
+                (LogLevel::Info, None, format!("Completed in {} ms", duration_ms))
+            }
+            WorkflowEvent::ExecutionFailed { error, .. } => (LogLevel::Error, None, format!("Failed: {}", error)),
+            WorkflowEvent::ExecutionCancelled { reason, .. } => (
+                LogLevel::Warn,
+                None,
+                match reason {
+                    Some(reason) => format!("Cancelled: {}", reason),
+                    None => "Cancelled".to_string(),
+                },
+            ),
+            _ => return,
+        };
+
+        self.log(execution_id, node_id.map(String::as_str), level, message, None);
+    }
+
+    /// Drop an execution's log and levels
+    pub fn remove(&self, execution_id: &Uuid) {
+        self.logs.remove(execution_id);
+        self.levels.remove(execution_id);
+    }
+}
+
+impl Default for ExecutionLogStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref EXECUTION_LOG: ExecutionLogStore = ExecutionLogStore::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_levels_can_change_mid_run() {
+        let log = ExecutionLogStore::new();
+        let execution = Uuid::new_v4();
+
+        log.log(execution, Some("plan"), LogLevel::Debug, "condition: always", None);
+        log.log(execution, Some("plan"), LogLevel::Info, "started", None);
+        assert_eq!(log.entries(&execution, None, LogLevel::Trace).len(), 1);
+
+        // Verbose for one node only
+        log.set_level(execution, Some("review"), "verbose".parse().unwrap());
+        log.log(execution, Some("plan"), LogLevel::Debug, "aggregated 2 outputs", None);
+        log.log(execution, Some("review"), LogLevel::Debug, "aggregated 3 outputs", None);
+        log.log(execution, Some("review"), LogLevel::Trace, "full prompt", None);
+
+        let review = log.entries(&execution, Some("review"), LogLevel::Trace);
+        assert_eq!(review.len(), 1);
+        assert_eq!(review[0].message, "aggregated 3 outputs");
+
+        // Quieter for the whole execution; the node override stays
+        log.set_level(execution, None, LogLevel::Error);
+        log.log(execution, Some("plan"), LogLevel::Info, "completed", None);
+        assert!(log.enabled(&execution, Some("review"), LogLevel::Debug));
+        assert_eq!(log.entries(&execution, None, LogLevel::Trace).len(), 2);
+        assert_eq!(log.entries(&execution, None, LogLevel::Info).len(), 1);
+    }
+
+    #[test]
+    fn test_log_events() {
+        let log = ExecutionLogStore::new();
+        let execution = Uuid::new_v4();
+
+        log.log_event(&WorkflowEvent::NodeFailed {
+            execution_id: execution.to_string(),
+            node_id: "build".to_string(),
+            error: "exit code 2".to_string(),
+        });
+        log.log_event(&WorkflowEvent::LevelCompleted {
+            execution_id: execution.to_string(),
+            level: 0,
+        });
+
+        let entries = log.entries(&execution, None, LogLevel::Trace);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].level, LogLevel::Error);
+        assert_eq!(entries[0].node_id.as_deref(), Some("build"));
+        assert!("loud".parse::<LogLevel>().is_err());
+    }
+}
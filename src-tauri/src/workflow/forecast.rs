@@ -0,0 +1,352 @@
+//! Cost and duration forecasts for executions that haven't started.
+//!
+//! An orchestrated run can spend a lot before its first result comes back,
+//! so the estimate is shown before the user confirms it. Provides:
+//! - Per-node token estimates, taken from the same node's past runs, else
+//!   from past runs of its agent role, else from its prompt alone
+//! - Prices per million tokens by model, with overrides
+//! - The run's duration, as the slowest node of each execution level
+//!
+//! Past runs only count when they recorded their token usage and weren't
+//! run in chaos mode; the model of a node is the one it would run on after
+//! node configs and model pins.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::chaos::CHAOS_TAG;
+use super::enhanced_executor::EnhancedNodeConfig;
+use super::graph::WorkflowGraph;
+use super::history::{ExecutionRecord, NodeExecutionRecord};
+use super::state::NodeExecutionStatus;
+use super::tokenizer::count_tokens;
+
+/// Output tokens assumed for a node without history
+const DEFAULT_OUTPUT_TOKENS: u64 = 1_500;
+
+/// Duration assumed for a node without history
+const DEFAULT_NODE_DURATION_MS: u64 = 60_000;
+
+/// Price of a model, in US dollars per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    pub input_per_million: f64,
+    pub output_per_million: f64,
+}
+
+impl ModelPrice {
+    const fn new(input_per_million: f64, output_per_million: f64) -> Self {
+        Self {
+            input_per_million,
+            output_per_million,
+        }
+    }
+
+    pub fn cost(&self, input_tokens: u64, output_tokens: u64) -> f64 {
+        (input_tokens as f64 * self.input_per_million + output_tokens as f64 * self.output_per_million) / 1_000_000.0
+    }
+}
+
+/// List prices by model name prefix; the longest matching prefix wins
+const PRICES: &[(&str, ModelPrice)] = &[
+    ("claude-opus", ModelPrice::new(15.0, 75.0)),
+    ("claude-sonnet", ModelPrice::new(3.0, 15.0)),
+    ("claude-3-5-sonnet", ModelPrice::new(3.0, 15.0)),
+    ("claude-3-7-sonnet", ModelPrice::new(3.0, 15.0)),
+    ("claude-haiku", ModelPrice::new(0.8, 4.0)),
+    ("claude-3-5-haiku", ModelPrice::new(0.8, 4.0)),
+    ("gpt-4o", ModelPrice::new(2.5, 10.0)),
+    ("gpt-4o-mini", ModelPrice::new(0.15, 0.6)),
+    ("gpt-4.1", ModelPrice::new(2.0, 8.0)),
+    ("gpt-4.1-mini", ModelPrice::new(0.4, 1.6)),
+];
+
+/// Price of nodes on the backend's default model or an unknown one
+const DEFAULT_PRICE: ModelPrice = ModelPrice::new(3.0, 15.0);
+
+/// What an estimate takes into account besides the graph
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct EstimateConfig {
+    /// Workflow the graph belongs to, whose past runs are the closest match
+    pub workflow_id: Option<Uuid>,
+    /// The execution's input, the task of nodes without their own
+    pub input_prompt: String,
+    /// Prices by model name prefix, on top of the built-in ones
+    pub prices: HashMap<String, ModelPrice>,
+    /// Runs estimated to cost more need confirming
+    pub confirm_above_usd: Option<f64>,
+}
+
+impl EstimateConfig {
+    pub fn price(&self, model: Option<&str>) -> ModelPrice {
+        let Some(model) = model else {
+            return DEFAULT_PRICE;
+        };
+        let overrides = self.prices.iter().map(|(prefix, price)| (prefix.as_str(), *price));
+        PRICES
+            .iter()
+            .copied()
+            .chain(overrides)
+            .filter(|(prefix, _)| model.starts_with(prefix))
+            // Overrides come last, so they win ties
+            .fold(None, |best: Option<(&str, ModelPrice)>, candidate| match best {
+                Some(best) if best.0.len() > candidate.0.len() => Some(best),
+                _ => Some(candidate),
+            })
+            .map_or(DEFAULT_PRICE, |(_, price)| price)
+    }
+}
+
+/// What a node's estimate is based on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateBasis {
+    /// Past runs of this node in this workflow
+    Node,
+    /// Past runs of nodes with the same agent role
+    Role,
+    /// The node's prompt and default output and duration
+    Prompt,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeEstimate {
+    pub node_id: String,
+    pub agent_role: String,
+    pub model: Option<String>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    pub duration_ms: u64,
+    pub basis: EstimateBasis,
+    /// Past node runs the estimate averages
+    pub samples: usize,
+}
+
+/// Forecast of an execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionEstimate {
+    /// Agent nodes, in execution order
+    pub nodes: Vec<NodeEstimate>,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost_usd: f64,
+    pub duration_ms: u64,
+    /// Whether the cost is above the configured threshold
+    pub requires_confirmation: bool,
+}
+
+/// Token and duration averages over past node runs
+struct Samples {
+    input: u64,
+    output: u64,
+    duration_ms: Option<u64>,
+    count: usize,
+}
+
+impl Samples {
+    fn of<'a>(nodes: impl Iterator<Item = &'a NodeExecutionRecord>) -> Option<Self> {
+        let (mut input, mut output, mut duration, mut timed, mut count) = (0, 0, 0, 0, 0);
+        for node in nodes {
+            let Some(tokens) = node.tokens else {
+                continue;
+            };
+            input += tokens.input;
+            output += tokens.output;
+            if let Some(ms) = node.duration_ms {
+                duration += ms;
+                timed += 1;
+            }
+            count += 1;
+        }
+        (count > 0).then(|| Samples {
+            input: input / count as u64,
+            output: output / count as u64,
+            duration_ms: (timed > 0).then(|| duration / timed),
+            count: count as usize,
+        })
+    }
+}
+
+/// Estimate running `graph` with `node_configs`, whose models are already
+/// resolved, from the completed nodes of `history`
+pub fn estimate(
+    graph: &WorkflowGraph,
+    node_configs: &HashMap<String, EnhancedNodeConfig>,
+    history: &[ExecutionRecord],
+    config: &EstimateConfig,
+) -> Result<ExecutionEstimate, String> {
+    let levels = graph.compute_execution_levels().map_err(|e| e.to_string())?;
+    let completed = || {
+        history
+            .iter()
+            .filter(|record| !record.tags.iter().any(|tag| tag == CHAOS_TAG))
+            .flat_map(|record| record.node_records.iter().map(move |node| (record, node)))
+            .filter(|(_, node)| node.status == NodeExecutionStatus::Completed)
+    };
+
+    let mut nodes = Vec::new();
+    let mut duration_ms = 0;
+    for level in &levels {
+        let mut slowest = 0;
+        for node_id in level {
+            let node = &graph.nodes[node_id];
+            if !node.kind.is_agent() {
+                continue;
+            }
+            let model = node_configs.get(node_id).and_then(|c| c.model.clone());
+
+            let same_node = config.workflow_id.and_then(|workflow_id| {
+                Samples::of(
+                    completed()
+                        .filter(|(record, past)| record.workflow_id == Some(workflow_id) && past.node_id == *node_id)
+                        .map(|(_, past)| past),
+                )
+            });
+            let (basis, samples) = match same_node {
+                Some(samples) => (EstimateBasis::Node, Some(samples)),
+                None => match Samples::of(
+                    completed()
+                        .filter(|(_, past)| past.agent_role.eq_ignore_ascii_case(&node.agent_role))
+                        .map(|(_, past)| past),
+                ) {
+                    Some(samples) => (EstimateBasis::Role, Some(samples)),
+                    None => (EstimateBasis::Prompt, None),
+                },
+            };
+            let (input_tokens, output_tokens, node_duration, count) = match samples {
+                Some(samples) => (
+                    samples.input,
+                    samples.output,
+                    samples.duration_ms.unwrap_or(DEFAULT_NODE_DURATION_MS),
+                    samples.count,
+                ),
+                None => {
+                    let task = node.assigned_task.as_deref().unwrap_or(&config.input_prompt);
+                    let input = [node.system_prompt.as_deref(), Some(task)]
+                        .into_iter()
+                        .flatten()
+                        .map(|text| count_tokens(text, model.as_deref()) as u64)
+                        .sum();
+                    (input, DEFAULT_OUTPUT_TOKENS, DEFAULT_NODE_DURATION_MS, 0)
+                }
+            };
+
+            slowest = slowest.max(node_duration);
+            nodes.push(NodeEstimate {
+                node_id: node_id.clone(),
+                agent_role: node.agent_role.clone(),
+                cost_usd: config.price(model.as_deref()).cost(input_tokens, output_tokens),
+                model,
+                input_tokens,
+                output_tokens,
+                duration_ms: node_duration,
+                basis,
+                samples: count,
+            });
+        }
+        duration_ms += slowest;
+    }
+
+    let cost_usd = nodes.iter().map(|node| node.cost_usd).sum();
+    Ok(ExecutionEstimate {
+        input_tokens: nodes.iter().map(|node| node.input_tokens).sum(),
+        output_tokens: nodes.iter().map(|node| node.output_tokens).sum(),
+        requires_confirmation: config.confirm_above_usd.is_some_and(|limit| cost_usd > limit),
+        cost_usd,
+        duration_ms,
+        nodes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::history::{ExecutionRecordBuilder, NodeTokens};
+    use crate::workflow::state::ExecutionStatus;
+    use chrono::Utc;
+    use serde_json::json;
+
+    fn past_node(node_id: &str, role: &str, input: u64, output: u64, duration_ms: u64) -> NodeExecutionRecord {
+        NodeExecutionRecord {
+            node_id: node_id.to_string(),
+            node_name: node_id.to_string(),
+            agent_role: role.to_string(),
+            agent_id: None,
+            status: NodeExecutionStatus::Completed,
+            started_at: None,
+            completed_at: None,
+            duration_ms: Some(duration_ms),
+            queue_wait_ms: None,
+            retry_count: 0,
+            output_summary: None,
+            error: None,
+            partial_output_summary: None,
+            retry_attempts: Vec::new(),
+            tokens: Some(NodeTokens { input, output, exact: false }),
+            model: None,
+            prompt_version: None,
+        }
+    }
+
+    #[test]
+    fn test_estimate_from_history() {
+        let graph = WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "design", "data": {"label": "Design", "agentRole": "architect", "assignedTask": "Design login"}},
+                {"id": "build", "data": {"label": "Build", "agentRole": "implementer"}},
+                {"id": "docs", "data": {"label": "Docs", "agentRole": "documenter"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "design", "target": "build"},
+                {"id": "e2", "source": "design", "target": "docs"}
+            ]
+        }))
+        .unwrap();
+
+        let workflow_id = Uuid::new_v4();
+        let mut builder = ExecutionRecordBuilder::new(Uuid::new_v4(), Uuid::new_v4(), "shop".to_string(), String::new())
+            .workflow(workflow_id, "Login".to_string());
+        builder.add_node_record(past_node("build", "implementer", 10_000, 2_000, 120_000));
+        let mut other = ExecutionRecordBuilder::new(Uuid::new_v4(), Uuid::new_v4(), "blog".to_string(), String::new());
+        other.add_node_record(past_node("write", "documenter", 4_000, 1_000, 30_000));
+        other.add_node_record(past_node("write-more", "documenter", 6_000, 3_000, 50_000));
+        let history = vec![
+            builder.build(ExecutionStatus::Completed, Utc::now()),
+            other.build(ExecutionStatus::Completed, Utc::now()),
+        ];
+
+        let node_configs = HashMap::from([(
+            "build".to_string(),
+            EnhancedNodeConfig {
+                model: Some("claude-opus-4-20250514".to_string()),
+                ..Default::default()
+            },
+        )]);
+        let config = EstimateConfig {
+            workflow_id: Some(workflow_id),
+            input_prompt: "Add login".to_string(),
+            prices: HashMap::from([("claude-opus-4".to_string(), ModelPrice::new(10.0, 50.0))]),
+            confirm_above_usd: Some(0.1),
+        };
+        let estimate = estimate(&graph, &node_configs, &history, &config).unwrap();
+        let by_id: HashMap<&str, &NodeEstimate> = estimate.nodes.iter().map(|n| (n.node_id.as_str(), n)).collect();
+
+        assert_eq!(estimate.nodes[0].node_id, "design");
+        assert_eq!(by_id["design"].basis, EstimateBasis::Prompt);
+        assert_eq!(by_id["design"].output_tokens, DEFAULT_OUTPUT_TOKENS);
+        assert_eq!(by_id["build"].basis, EstimateBasis::Node);
+        // 10k input and 2k output at the overriding price
+        assert!((by_id["build"].cost_usd - 0.2).abs() < 1e-9);
+        assert_eq!((by_id["docs"].basis, by_id["docs"].samples), (EstimateBasis::Role, 2));
+        assert_eq!((by_id["docs"].input_tokens, by_id["docs"].duration_ms), (5_000, 40_000));
+
+        // Design, then the slower of build and docs
+        assert_eq!(estimate.duration_ms, DEFAULT_NODE_DURATION_MS + 120_000);
+        assert!(estimate.requires_confirmation);
+        assert_eq!(config.price(Some("gpt-4o-mini-2024")), ModelPrice::new(0.15, 0.6));
+        assert_eq!(config.price(None), DEFAULT_PRICE);
+    }
+}
@@ -0,0 +1,317 @@
+//! Coverage and lint gates.
+//!
+//! A gate node runs a coverage tool (tarpaulin, istanbul) or a linter
+//! (clippy, ESLint) in the project directory, reads the numbers from its
+//! output, and holds them against the project's thresholds. Provides:
+//! - The supported tools, their default commands and output parsers
+//! - Per-project thresholds
+//! - The pass/fail verdict with one message per threshold broken
+
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::Duration;
+use uuid::Uuid;
+
+use super::self_correction::shell_command;
+use crate::process::group;
+
+/// Gate tools get ten minutes unless configured otherwise; coverage runs
+/// the whole test suite instrumented
+const DEFAULT_GATE_TIMEOUT_MS: u64 = 600_000;
+/// Output kept in the error when a tool's output can't be read
+const MAX_ERROR_OUTPUT_CHARS: usize = 2_000;
+
+/// A tool a gate node runs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GateTool {
+    /// `cargo tarpaulin` line coverage
+    Tarpaulin,
+    /// nyc/istanbul line coverage from its text or text-summary reporter
+    Istanbul,
+    /// `cargo clippy` diagnostics in JSON
+    Clippy,
+    /// ESLint results from its JSON formatter
+    Eslint,
+}
+
+impl GateTool {
+    /// The command run when the node doesn't give one
+    pub fn default_command(&self) -> &'static str {
+        match self {
+            GateTool::Tarpaulin => "cargo tarpaulin --skip-clean",
+            GateTool::Istanbul => "npx nyc --reporter=text-summary npm test",
+            GateTool::Clippy => "cargo clippy --all-targets --message-format=json",
+            GateTool::Eslint => "npx eslint . --format json",
+        }
+    }
+
+    pub fn is_coverage(&self) -> bool {
+        matches!(self, GateTool::Tarpaulin | GateTool::Istanbul)
+    }
+
+    /// Read the tool's numbers from its output; `None` if the output holds
+    /// none
+    pub fn parse(&self, output: &str) -> Option<GateMetrics> {
+        match self {
+            GateTool::Tarpaulin => parse_tarpaulin(output),
+            GateTool::Istanbul => parse_istanbul(output),
+            GateTool::Clippy => parse_clippy(output),
+            GateTool::Eslint => parse_eslint(output),
+        }
+    }
+}
+
+/// What a gate tool measured
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GateMetrics {
+    /// Line coverage, 0-100 (coverage tools only)
+    pub coverage_percent: Option<f64>,
+    pub errors: u64,
+    pub warnings: u64,
+}
+
+/// A project's gate limits. Unset limits aren't checked, except that lint
+/// errors are allowed only up to `max_lint_errors`, which defaults to none.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct GateThresholds {
+    pub min_coverage: Option<f64>,
+    pub max_lint_errors: Option<u64>,
+    pub max_lint_warnings: Option<u64>,
+}
+
+/// The verdict of one gate run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GateResult {
+    pub tool: GateTool,
+    pub command: String,
+    pub metrics: GateMetrics,
+    pub thresholds: GateThresholds,
+    pub passed: bool,
+    /// One line per threshold broken
+    pub violations: Vec<String>,
+}
+
+impl GateResult {
+    /// Hold `metrics` against `thresholds`
+    pub fn judge(tool: GateTool, command: String, metrics: GateMetrics, thresholds: GateThresholds) -> Self {
+        let mut violations = Vec::new();
+
+        if tool.is_coverage() {
+            if let (Some(coverage), Some(min)) = (metrics.coverage_percent, thresholds.min_coverage) {
+                if coverage < min {
+                    violations.push(format!("Coverage {:.2}% is below the minimum of {:.2}%", coverage, min));
+                }
+            }
+        } else {
+            let max_errors = thresholds.max_lint_errors.unwrap_or(0);
+            if metrics.errors > max_errors {
+                violations.push(format!("{} lint error(s), at most {} allowed", metrics.errors, max_errors));
+            }
+            if let Some(max_warnings) = thresholds.max_lint_warnings {
+                if metrics.warnings > max_warnings {
+                    violations.push(format!("{} lint warning(s), at most {} allowed", metrics.warnings, max_warnings));
+                }
+            }
+        }
+
+        Self {
+            tool,
+            command,
+            metrics,
+            thresholds,
+            passed: violations.is_empty(),
+            violations,
+        }
+    }
+
+    /// One line for the execution report
+    pub fn summary(&self) -> String {
+        let measured = match self.metrics.coverage_percent {
+            Some(coverage) => format!("{:.2}% coverage", coverage),
+            None => format!("{} error(s), {} warning(s)", self.metrics.errors, self.metrics.warnings),
+        };
+        if self.passed {
+            format!("{:?} gate passed: {}", self.tool, measured)
+        } else {
+            format!("{:?} gate failed: {}", self.tool, self.violations.join("; "))
+        }
+    }
+}
+
+/// Run a gate tool in `working_dir` and read its numbers. The tools exit
+/// non-zero when they find problems, so only unreadable output is an error.
+pub async fn run_gate(
+    tool: GateTool,
+    command: &str,
+    working_dir: &Path,
+    timeout_ms: Option<u64>,
+    env: &[(String, String)],
+) -> Result<GateMetrics, String> {
+    let mut cmd = shell_command(command);
+    cmd.current_dir(working_dir).envs(env.iter().cloned());
+
+    let timeout = Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_GATE_TIMEOUT_MS));
+    let output = group::output_within(&mut cmd, timeout)
+        .await
+        .ok_or_else(|| format!("'{}' did not finish within {}ms", command, timeout.as_millis()))?
+        .map_err(|e| format!("Failed to run '{}': {}", command, e))?;
+
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push('\n');
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+
+    tool.parse(&text).ok_or_else(|| {
+        let count = text.chars().count();
+        let tail: String = text.chars().skip(count.saturating_sub(MAX_ERROR_OUTPUT_CHARS)).collect();
+        format!("Could not read {:?} results from the output of '{}':\n{}", tool, command, tail.trim())
+    })
+}
+
+/// Every project's gate thresholds
+#[derive(Default)]
+pub struct GateThresholdStore {
+    thresholds: DashMap<Uuid, GateThresholds>,
+}
+
+impl GateThresholdStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, project_id: Uuid, thresholds: GateThresholds) {
+        self.thresholds.insert(project_id, thresholds);
+    }
+
+    /// A project's thresholds, or the defaults if it has none
+    pub fn get(&self, project_id: &Uuid) -> GateThresholds {
+        self.thresholds.get(project_id).map(|t| t.clone()).unwrap_or_default()
+    }
+}
+
+lazy_static! {
+    pub static ref GATE_THRESHOLDS: GateThresholdStore = GateThresholdStore::new();
+    static ref TARPAULIN_SUMMARY: Regex = Regex::new(r"([\d.]+)% coverage, \d+/\d+ lines covered").unwrap();
+    static ref ISTANBUL_SUMMARY: Regex = Regex::new(r"^Lines\s*:\s*([\d.]+)%").unwrap();
+    static ref ISTANBUL_TABLE: Regex = Regex::new(r"^All files\s*\|([^|]*)\|([^|]*)\|([^|]*)\|([^|]*)\|").unwrap();
+}
+
+/// The last `N% coverage, covered/total lines covered` line
+fn parse_tarpaulin(output: &str) -> Option<GateMetrics> {
+    let caps = TARPAULIN_SUMMARY.captures_iter(output).last()?;
+    Some(GateMetrics {
+        coverage_percent: Some(caps[1].parse().ok()?),
+        ..GateMetrics::default()
+    })
+}
+
+/// The `Lines : N%` line of text-summary, else the Lines column of the
+/// text table's `All files` row
+fn parse_istanbul(output: &str) -> Option<GateMetrics> {
+    let coverage = output.lines().find_map(|line| {
+        let line = line.trim();
+        match ISTANBUL_SUMMARY.captures(line) {
+            Some(caps) => caps[1].parse().ok(),
+            None => ISTANBUL_TABLE.captures(line).and_then(|caps| caps[4].trim().parse().ok()),
+        }
+    })?;
+    Some(GateMetrics {
+        coverage_percent: Some(coverage),
+        ..GateMetrics::default()
+    })
+}
+
+/// Counts the `compiler-message` lines of cargo's JSON output. Messages
+/// without a source span are summaries like "3 warnings emitted".
+fn parse_clippy(output: &str) -> Option<GateMetrics> {
+    let mut metrics = GateMetrics::default();
+    let mut seen_build = false;
+
+    for line in output.lines().filter(|line| line.starts_with('{')) {
+        let Ok(value) = serde_json::from_str::<serde_json::Value>(line) else {
+            continue;
+        };
+        match value["reason"].as_str() {
+            Some("build-finished") => seen_build = true,
+            Some("compiler-message") => {
+                let message = &value["message"];
+                if message["spans"].as_array().map_or(true, |spans| spans.is_empty()) {
+                    continue;
+                }
+                match message["level"].as_str() {
+                    Some("error") => metrics.errors += 1,
+                    Some("warning") => metrics.warnings += 1,
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    seen_build.then_some(metrics)
+}
+
+/// Sums the error and warning counts of every file in ESLint's JSON array
+fn parse_eslint(output: &str) -> Option<GateMetrics> {
+    let start = output.find("[{").or_else(|| output.find("[]"))?;
+    let end = output.rfind(']')?;
+    let files: Vec<serde_json::Value> = serde_json::from_str(output.get(start..=end)?).ok()?;
+
+    let mut metrics = GateMetrics::default();
+    for file in &files {
+        metrics.errors += file["errorCount"].as_u64().unwrap_or(0) + file["fatalErrorCount"].as_u64().unwrap_or(0);
+        metrics.warnings += file["warningCount"].as_u64().unwrap_or(0);
+    }
+    Some(metrics)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_coverage() {
+        let tarpaulin = "|| src/lib.rs: 40/50\n|| \n85.71% coverage, 120/140 lines covered\n";
+        assert_eq!(GateTool::Tarpaulin.parse(tarpaulin).unwrap().coverage_percent, Some(85.71));
+
+        let summary = "=============================== Coverage summary ===============================\n\
+                       Statements   : 80.5% ( 161/200 )\n\
+                       Lines        : 82.25% ( 150/182 )\n";
+        assert_eq!(GateTool::Istanbul.parse(summary).unwrap().coverage_percent, Some(82.25));
+
+        let table = "File      | % Stmts | % Branch | % Funcs | % Lines | Uncovered Line #s\n\
+                     All files |   90.12 |    75.5  |     100 |   91.3  |\n";
+        assert_eq!(GateTool::Istanbul.parse(table).unwrap().coverage_percent, Some(91.3));
+        assert!(GateTool::Tarpaulin.parse("error: no such command: `tarpaulin`").is_none());
+
+        let thresholds = GateThresholds { min_coverage: Some(90.0), ..GateThresholds::default() };
+        let result = GateResult::judge(GateTool::Tarpaulin, "cargo tarpaulin".into(), GateTool::Tarpaulin.parse(tarpaulin).unwrap(), thresholds);
+        assert!(!result.passed);
+        assert_eq!(result.violations, vec!["Coverage 85.71% is below the minimum of 90.00%"]);
+    }
+
+    #[test]
+    fn test_lint() {
+        let clippy = r#"{"reason":"compiler-artifact","package_id":"app"}
+{"reason":"compiler-message","message":{"level":"warning","message":"unused variable","spans":[{"file_name":"src/main.rs"}]}}
+{"reason":"compiler-message","message":{"level":"error","message":"mismatched types","spans":[{"file_name":"src/lib.rs"}]}}
+{"reason":"compiler-message","message":{"level":"warning","message":"1 warning emitted","spans":[]}}
+{"reason":"build-finished","success":false}"#;
+        let metrics = GateTool::Clippy.parse(clippy).unwrap();
+        assert_eq!((metrics.errors, metrics.warnings), (1, 1));
+        assert!(GateTool::Clippy.parse("error: could not find `Cargo.toml`").is_none());
+
+        let eslint = r#"[{"filePath":"/app/a.js","errorCount":2,"fatalErrorCount":0,"warningCount":1},{"filePath":"/app/b.js","errorCount":0,"warningCount":3}]"#;
+        let metrics = GateTool::Eslint.parse(eslint).unwrap();
+        assert_eq!((metrics.errors, metrics.warnings), (2, 4));
+        assert_eq!(GateTool::Eslint.parse("[]\n").unwrap(), GateMetrics::default());
+
+        let lenient = GateThresholds { max_lint_errors: Some(2), max_lint_warnings: Some(3), ..GateThresholds::default() };
+        let result = GateResult::judge(GateTool::Eslint, "eslint".into(), metrics.clone(), lenient);
+        assert_eq!(result.violations, vec!["4 lint warning(s), at most 3 allowed"]);
+        assert!(!GateResult::judge(GateTool::Eslint, "eslint".into(), metrics, GateThresholds::default()).passed);
+    }
+}
@@ -1,20 +1,156 @@
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
+use super::assertions::AssertionCheck;
+use super::gates::GateTool;
+use super::node_library::NODE_LIBRARY;
+use super::patch::PatchMode;
+use super::report::ReportFormat;
+use super::schema::{self, SchemaViolation, GRAPH_SCHEMA_VERSION};
+
 #[derive(Debug, Error)]
 pub enum GraphError {
     #[error("Invalid graph format: {0}")]
     InvalidFormat(String),
 
-    #[error("Cycle detected in workflow graph")]
-    CycleDetected,
+    #[error("Cycle detected in workflow graph: {}", .0.path())]
+    CycleDetected(CycleDiagnosis),
 
     #[error("Node not found: {0}")]
     NodeNotFound(String),
 
     #[error("JSON parsing error: {0}")]
     JsonError(#[from] serde_json::Error),
+
+    #[error("Graph does not match schema: {}", format_violations(.0))]
+    Schema(Vec<SchemaViolation>),
+
+    #[error("Unsupported graph version {0} (newer than {GRAPH_SCHEMA_VERSION})")]
+    UnsupportedVersion(u64),
+}
+
+fn format_violations(violations: &[SchemaViolation]) -> String {
+    violations.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+}
+
+/// Where a graph loops back on itself, and how to fix it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CycleDiagnosis {
+    /// Node IDs around one cycle in edge order; the last leads back to the first
+    pub cycle: Vec<String>,
+    /// Edges whose removal leaves the graph acyclic. The set is minimal:
+    /// keeping any one of them leaves a cycle.
+    pub breaking_edges: Vec<ParsedEdge>,
+}
+
+impl CycleDiagnosis {
+    /// The cycle as `a -> b -> c -> a`
+    pub fn path(&self) -> String {
+        let mut nodes = self.cycle.clone();
+        nodes.extend(self.cycle.first().cloned());
+        nodes.join(" -> ")
+    }
+}
+
+/// What a node does when it executes
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum NodeKind {
+    /// Spawn an agent for the node's role
+    #[default]
+    Agent,
+    /// Run an embedded Rhai script in-process instead of spawning an agent
+    Script { source: String },
+    /// Block until another execution completes, then import its outputs.
+    /// Targets a specific execution, or the latest run of a saved workflow.
+    WaitForExecution {
+        execution_id: Option<String>,
+        workflow_id: Option<String>,
+        /// Node IDs whose outputs to import (empty = all completed nodes)
+        #[serde(default)]
+        outputs: Vec<String>,
+        /// Give up after this long (defaults to the agent wait limit)
+        timeout_ms: Option<u64>,
+    },
+    /// Write a report of the execution so far, optionally emailing it
+    Report {
+        #[serde(default)]
+        format: ReportFormat,
+        /// Relative to the project directory, which it may not leave;
+        /// defaults to `reports/<execution_id>.<ext>` there
+        output_path: Option<String>,
+        /// Recipients; sent through the SMTP server from `SMTP_*` settings
+        #[serde(default)]
+        email_to: Vec<String>,
+        subject: Option<String>,
+    },
+    /// Check another node's output. A failed check is recorded for
+    /// evaluation but only fails the execution when `fail_execution` is set.
+    Assert {
+        assertion: AssertionCheck,
+        /// Node whose output to check (defaults to the only predecessor)
+        target: Option<String>,
+        #[serde(default)]
+        fail_execution: bool,
+    },
+    /// Have a judge agent score another agent node's output. Low scores
+    /// re-run that node with the judge's critique, using its retry budget
+    /// and then its fallback strategy.
+    QualityGate {
+        rubric: String,
+        #[serde(default = "super::assertions::default_min_score")]
+        min_score: f64,
+        /// Judge model (defaults to the agent default)
+        model: Option<String>,
+        /// Agent node to gate (defaults to the only predecessor)
+        target: Option<String>,
+    },
+    /// Apply a unified diff from another node's output to the project,
+    /// reporting which hunks applied
+    ApplyPatch {
+        /// Node whose output holds the diff (defaults to the only predecessor)
+        target: Option<String>,
+        #[serde(default)]
+        mode: PatchMode,
+        /// Write the hunks that apply even when others don't
+        #[serde(default)]
+        allow_partial: bool,
+    },
+    /// Run a coverage tool or linter and hold its numbers against the
+    /// project's gate thresholds. Like an assertion, a failed gate only
+    /// fails the execution when `fail_execution` is set.
+    CheckGate {
+        tool: GateTool,
+        /// Defaults to the tool's standard invocation
+        command: Option<String>,
+        timeout_ms: Option<u64>,
+        #[serde(default)]
+        fail_execution: bool,
+    },
+    /// Diff the lockfiles the execution changed and look up the package
+    /// versions it added in the OSV advisory database, and new packages in
+    /// their registry. Fails when one has advisories or doesn't exist;
+    /// until then nothing in the execution can be approved.
+    SupplyChainCheck {
+        /// Lockfiles to check, relative to the project (defaults to every
+        /// supported lockfile the execution changed)
+        #[serde(default)]
+        lockfiles: Vec<String>,
+        /// Don't look new packages up in their registry
+        #[serde(default)]
+        allow_unknown: bool,
+        /// Advisory IDs judged not to apply
+        #[serde(default)]
+        ignore_advisories: Vec<String>,
+    },
+}
+
+impl NodeKind {
+    /// Whether this node spawns an agent process
+    pub fn is_agent(&self) -> bool {
+        matches!(self, NodeKind::Agent)
+    }
 }
 
 /// Parsed node from React Flow graph
@@ -25,6 +161,11 @@ pub struct ParsedNode {
     pub agent_role: String,
     pub system_prompt: Option<String>,
     pub assigned_task: Option<String>,
+    #[serde(default)]
+    pub kind: NodeKind,
+    /// Node library preset the node was configured from
+    #[serde(default)]
+    pub preset: Option<String>,
 }
 
 /// Parsed edge from React Flow graph
@@ -36,6 +177,18 @@ pub struct ParsedEdge {
     pub data_type: Option<String>,
 }
 
+/// Named group of nodes, run and monitored as one unit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ParsedStage {
+    pub id: String,
+    pub label: String,
+    /// Member node IDs; a node belongs to at most one stage
+    pub nodes: Vec<String>,
+    /// Show the stage as a single node
+    #[serde(default)]
+    pub collapsed: bool,
+}
+
 /// Internal React Flow node structure for deserialization
 #[derive(Debug, Deserialize)]
 struct ReactFlowNode {
@@ -46,12 +199,16 @@ struct ReactFlowNode {
 #[derive(Debug, Deserialize)]
 struct ReactFlowNodeData {
     label: String,
-    #[serde(rename = "agentRole")]
+    #[serde(rename = "agentRole", default)]
     agent_role: String,
     #[serde(rename = "systemPrompt")]
     system_prompt: Option<String>,
     #[serde(rename = "assignedTask")]
     assigned_task: Option<String>,
+    #[serde(default)]
+    kind: NodeKind,
+    #[serde(default)]
+    preset: Option<String>,
 }
 
 /// Internal React Flow edge structure for deserialization
@@ -69,6 +226,16 @@ struct ReactFlowEdgeData {
     data_type: Option<String>,
 }
 
+/// Stage declared at the top level of the graph document
+#[derive(Debug, Deserialize)]
+struct GraphStage {
+    id: String,
+    label: Option<String>,
+    nodes: Vec<String>,
+    #[serde(default)]
+    collapsed: bool,
+}
+
 /// Workflow graph with adjacency lists for traversal
 #[derive(Debug, Clone)]
 pub struct WorkflowGraph {
@@ -78,11 +245,17 @@ pub struct WorkflowGraph {
     pub successors: HashMap<String, Vec<String>>,
     /// Reverse adjacency: node_id -> list of predecessor node_ids
     pub predecessors: HashMap<String, Vec<String>>,
+    /// Named groups of nodes, in declaration order
+    pub stages: Vec<ParsedStage>,
 }
 
 impl WorkflowGraph {
-    /// Parse a React Flow graph JSON into a WorkflowGraph
+    /// Parse a React Flow graph JSON into a WorkflowGraph. Older graph
+    /// versions are migrated first, then checked against the schema.
     pub fn from_json(graph_json: &serde_json::Value) -> Result<Self, GraphError> {
+        let graph_json = &schema::migrate_graph(graph_json.clone())?;
+        schema::validate_graph(graph_json).map_err(GraphError::Schema)?;
+
         // Extract nodes array
         let nodes_json = graph_json
             .get("nodes")
@@ -99,13 +272,40 @@ impl WorkflowGraph {
 
         // Convert to parsed nodes
         let mut nodes = HashMap::new();
-        for rf_node in rf_nodes {
+        for mut rf_node in rf_nodes {
+            // Fields the node leaves unset come from its preset. A missing
+            // preset is left to lint, so the workflow still loads.
+            if let Some(preset) = rf_node.data.preset.as_deref().and_then(|id| NODE_LIBRARY.get(id)) {
+                let data = &mut rf_node.data;
+                if data.agent_role.is_empty() {
+                    data.agent_role = preset.agent_role;
+                }
+                data.system_prompt = data.system_prompt.take().or(preset.system_prompt);
+                data.assigned_task = data.assigned_task.take().or(preset.assigned_task);
+            }
+
+            if rf_node.data.kind.is_agent() && rf_node.data.agent_role.is_empty() && rf_node.data.preset.is_none() {
+                return Err(GraphError::InvalidFormat(format!(
+                    "Node '{}' is missing agentRole",
+                    rf_node.id
+                )));
+            }
+
+            if let NodeKind::WaitForExecution { execution_id: None, workflow_id: None, .. } = rf_node.data.kind {
+                return Err(GraphError::InvalidFormat(format!(
+                    "Node '{}' must set execution_id or workflow_id to wait for",
+                    rf_node.id
+                )));
+            }
+
             let parsed = ParsedNode {
                 id: rf_node.id.clone(),
                 label: rf_node.data.label,
                 agent_role: rf_node.data.agent_role,
                 system_prompt: rf_node.data.system_prompt,
                 assigned_task: rf_node.data.assigned_task,
+                kind: rf_node.data.kind,
+                preset: rf_node.data.preset,
             };
             nodes.insert(rf_node.id, parsed);
         }
@@ -150,79 +350,297 @@ impl WorkflowGraph {
             edges.push(parsed);
         }
 
+        let stages = match graph_json.get("stages") {
+            None | Some(serde_json::Value::Null) => Vec::new(),
+            Some(stages_json) => parse_stages(serde_json::from_value(stages_json.clone())?, &nodes)?,
+        };
+
         Ok(Self {
             nodes,
             edges,
             successors,
             predecessors,
+            stages,
         })
     }
 
     /// Compute execution levels using Kahn's algorithm (topological sort)
     /// Returns Vec<Vec<String>> where each inner vec contains nodes that can run in parallel
+    ///
+    /// A stage's members also wait for all of the stage's
+    /// [dependencies](Self::stage_dependencies), so its condition is decided
+    /// once they have all finished.
     pub fn compute_execution_levels(&self) -> Result<Vec<Vec<String>>, GraphError> {
-        // Calculate in-degree for each node
-        let mut in_degree: HashMap<String, usize> = HashMap::new();
+        let unprocessed = match self.levels_by(|node_id| match self.stage_of(node_id) {
+            Some(stage) => self
+                .stage_dependencies(stage)
+                .into_iter()
+                .filter(|dep| !self.dependencies(node_id).contains(dep))
+                .collect(),
+            None => Vec::new(),
+        }) {
+            Ok(levels) => return Ok(levels),
+            Err(unprocessed) => unprocessed,
+        };
+
+        // Tell a cycle in the graph from stages that wait on each other
+        match self.levels_by(|_| Vec::new()) {
+            Err(unprocessed) => Err(GraphError::CycleDetected(self.diagnose_cycle(&unprocessed))),
+            Ok(_) => {
+                let mut stages: Vec<&str> = unprocessed
+                    .iter()
+                    .filter_map(|id| self.stage_of(id))
+                    .map(|stage| stage.id.as_str())
+                    .collect();
+                stages.sort_unstable();
+                stages.dedup();
+                Err(GraphError::InvalidFormat(format!(
+                    "Stages {} each depend on nodes that wait for the other's members",
+                    stages.iter().map(|id| format!("'{}'", id)).collect::<Vec<_>>().join(", ")
+                )))
+            }
+        }
+    }
+
+    /// Levels where each node comes after its dependencies and whatever
+    /// `extra` adds for it, or the nodes left unplaced by a cycle
+    fn levels_by<F>(&self, extra: F) -> Result<Vec<Vec<String>>, HashSet<String>>
+    where
+        F: Fn(&str) -> Vec<String>,
+    {
+        // Calculate in-degree for each node, borrowing IDs until a node is placed
+        let mut in_degree: HashMap<&str, usize> = HashMap::new();
+        let mut successors: HashMap<&str, Vec<&str>> = self
+            .successors
+            .iter()
+            .map(|(id, succs)| (id.as_str(), succs.iter().map(String::as_str).collect()))
+            .collect();
         for node_id in self.nodes.keys() {
-            in_degree.insert(
-                node_id.clone(),
-                self.predecessors.get(node_id).map_or(0, |p| p.len()),
-            );
+            let extra_deps = extra(node_id);
+            in_degree.insert(node_id.as_str(), self.dependencies(node_id).len() + extra_deps.len());
+            for dep in extra_deps {
+                if let Some((dep, _)) = self.nodes.get_key_value(&dep) {
+                    successors.entry(dep.as_str()).or_default().push(node_id.as_str());
+                }
+            }
         }
 
-        // Initialize queue with nodes that have no dependencies (in-degree 0)
-        let mut queue: VecDeque<String> = in_degree
+        // Nodes that have no dependencies (in-degree 0) form the first level
+        let mut current_level: Vec<&str> = in_degree
             .iter()
             .filter(|(_, &deg)| deg == 0)
-            .map(|(id, _)| id.clone())
+            .map(|(id, _)| *id)
             .collect();
 
         let mut levels: Vec<Vec<String>> = Vec::new();
         let mut processed_count = 0;
 
-        while !queue.is_empty() {
-            // All nodes in the current queue can execute in parallel (same level)
-            let current_level: Vec<String> = queue.drain(..).collect();
+        while !current_level.is_empty() {
+            // All nodes in the current level can execute in parallel
             processed_count += current_level.len();
 
             // Find next level's nodes
-            let mut next_queue: Vec<String> = Vec::new();
+            let mut next_level: Vec<&str> = Vec::new();
 
             for node_id in &current_level {
-                if let Some(successors) = self.successors.get(node_id) {
-                    for succ_id in successors {
-                        if let Some(deg) = in_degree.get_mut(succ_id) {
-                            *deg -= 1;
-                            if *deg == 0 {
-                                next_queue.push(succ_id.clone());
-                            }
+                for succ_id in successors.get(*node_id).into_iter().flatten() {
+                    if let Some(deg) = in_degree.get_mut(succ_id) {
+                        *deg -= 1;
+                        if *deg == 0 {
+                            next_level.push(succ_id);
                         }
                     }
                 }
             }
 
-            levels.push(current_level);
-
-            // Add next level nodes to queue
-            for node_id in next_queue {
-                queue.push_back(node_id);
-            }
+            levels.push(current_level.iter().map(|id| id.to_string()).collect());
+            current_level = next_level;
         }
 
         // Check for cycles: if we haven't processed all nodes, there's a cycle
         if processed_count != self.nodes.len() {
-            return Err(GraphError::CycleDetected);
+            return Err(in_degree
+                .into_iter()
+                .filter(|(_, deg)| *deg > 0)
+                .map(|(id, _)| id.to_string())
+                .collect());
         }
 
         Ok(levels)
     }
 
+    /// Find a cycle among the nodes a topological sort couldn't reach, and a
+    /// minimal set of edges to remove. Removing the back edges of a
+    /// depth-first search breaks every cycle; each is then put back if the
+    /// graph stays acyclic without removing it.
+    fn diagnose_cycle(&self, unprocessed: &HashSet<String>) -> CycleDiagnosis {
+        let edges: Vec<&ParsedEdge> = self
+            .edges
+            .iter()
+            .filter(|e| unprocessed.contains(&e.source) && unprocessed.contains(&e.target))
+            .collect();
+        let mut outgoing: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (idx, edge) in edges.iter().enumerate() {
+            outgoing.entry(edge.source.as_str()).or_default().push(idx);
+        }
+
+        // Start where the cycles are entered from the rest of the graph, so
+        // the edge looping back to the entry is the one reported
+        let mut starts: Vec<&String> = unprocessed.iter().collect();
+        starts.sort_by_key(|id| {
+            let entered = self
+                .dependencies(id)
+                .iter()
+                .any(|pred| !unprocessed.contains(pred));
+            (!entered, id.as_str())
+        });
+
+        let mut on_stack: HashSet<&str> = HashSet::new();
+        let mut visited: HashSet<&str> = HashSet::new();
+        let mut back_edges: Vec<usize> = Vec::new();
+        let mut cycle: Vec<String> = Vec::new();
+
+        for start in starts {
+            if visited.contains(start.as_str()) {
+                continue;
+            }
+            // (node, index of the next outgoing edge to follow)
+            let mut stack: Vec<(&str, usize)> = vec![(start.as_str(), 0)];
+            visited.insert(start.as_str());
+            on_stack.insert(start.as_str());
+
+            while let Some((node, next)) = stack.last_mut() {
+                let node = *node;
+                let Some(&edge_idx) = outgoing.get(node).and_then(|out| out.get(*next)) else {
+                    on_stack.remove(node);
+                    stack.pop();
+                    continue;
+                };
+                *next += 1;
+
+                let target = edges[edge_idx].target.as_str();
+                if on_stack.contains(target) {
+                    if cycle.is_empty() {
+                        let from = stack.iter().position(|(id, _)| *id == target).unwrap_or(0);
+                        cycle = stack[from..].iter().map(|(id, _)| id.to_string()).collect();
+                    }
+                    back_edges.push(edge_idx);
+                } else if visited.insert(target) {
+                    on_stack.insert(target);
+                    stack.push((target, 0));
+                }
+            }
+        }
+
+        let mut removed: HashSet<usize> = back_edges.iter().copied().collect();
+        for edge_idx in back_edges {
+            removed.remove(&edge_idx);
+            let kept = edges
+                .iter()
+                .enumerate()
+                .filter(|(idx, _)| !removed.contains(idx))
+                .map(|(_, edge)| *edge);
+            if !is_acyclic(unprocessed, kept) {
+                removed.insert(edge_idx);
+            }
+        }
+
+        let mut breaking_edges: Vec<usize> = removed.into_iter().collect();
+        breaking_edges.sort_unstable();
+        CycleDiagnosis {
+            cycle,
+            breaking_edges: breaking_edges.into_iter().map(|idx| edges[idx].clone()).collect(),
+        }
+    }
+
+    /// Find the longest path through the graph, weighting each node with `weight`.
+    /// Returns node IDs from root to leaf.
+    pub fn critical_path<F>(&self, weight: F) -> Result<Vec<String>, GraphError>
+    where
+        F: Fn(&ParsedNode) -> u64,
+    {
+        let levels = self.compute_execution_levels()?;
+        Ok(self.critical_path_in_levels(&levels, weight))
+    }
+
+    /// [`critical_path`](Self::critical_path) for levels already computed
+    /// from this graph
+    pub fn critical_path_in_levels<F>(&self, levels: &[Vec<String>], weight: F) -> Vec<String>
+    where
+        F: Fn(&ParsedNode) -> u64,
+    {
+        // Longest weighted distance ending at each node, plus the predecessor on that path
+        let mut best: HashMap<&str, (u64, Option<&str>)> = HashMap::new();
+        for node_id in levels.iter().flatten() {
+            let own = self.nodes.get(node_id).map_or(0, &weight);
+            let (dist, prev) = self
+                .dependencies(node_id)
+                .iter()
+                .filter_map(|pred| best.get(pred.as_str()).map(|(d, _)| (*d, Some(pred.as_str()))))
+                .max_by(|a, b| a.0.cmp(&b.0).then_with(|| b.1.cmp(&a.1)))
+                .unwrap_or((0, None));
+            best.insert(node_id.as_str(), (dist + own, prev));
+        }
+
+        let mut current = best
+            .iter()
+            .max_by(|a, b| a.1 .0.cmp(&b.1 .0).then_with(|| b.0.cmp(a.0)))
+            .map(|(id, _)| *id);
+
+        let mut path = Vec::new();
+        while let Some(id) = current {
+            current = best.get(id).and_then(|(_, prev)| *prev);
+            path.push(id.to_string());
+        }
+        path.reverse();
+
+        path
+    }
+
     /// Get all node IDs that must complete before the given node can start
     pub fn get_dependencies(&self, node_id: &str) -> Vec<String> {
-        self.predecessors
-            .get(node_id)
-            .cloned()
-            .unwrap_or_default()
+        self.dependencies(node_id).to_vec()
+    }
+
+    /// [`get_dependencies`](Self::get_dependencies) without copying
+    pub fn dependencies(&self, node_id: &str) -> &[String] {
+        self.predecessors.get(node_id).map_or(&[], Vec::as_slice)
+    }
+
+    /// Get a stage by ID
+    pub fn get_stage(&self, stage_id: &str) -> Option<&ParsedStage> {
+        self.stages.iter().find(|stage| stage.id == stage_id)
+    }
+
+    /// The stage a node belongs to, if any
+    pub fn stage_of(&self, node_id: &str) -> Option<&ParsedStage> {
+        self.stages.iter().find(|stage| stage.nodes.iter().any(|id| id == node_id))
+    }
+
+    /// Nodes outside a stage that its members depend on. Nodes that
+    /// themselves come after a member are left out, as they can't finish
+    /// before the stage starts.
+    pub fn stage_dependencies(&self, stage: &ParsedStage) -> Vec<String> {
+        // Everything downstream of a member
+        let mut downstream: HashSet<&str> = HashSet::new();
+        let mut queue: Vec<&str> = stage.nodes.iter().map(String::as_str).collect();
+        while let Some(node_id) = queue.pop() {
+            for succ in self.successors.get(node_id).into_iter().flatten() {
+                if downstream.insert(succ) {
+                    queue.push(succ);
+                }
+            }
+        }
+
+        let mut deps: Vec<String> = Vec::new();
+        for node_id in &stage.nodes {
+            for dep in self.dependencies(node_id) {
+                if !stage.nodes.contains(dep) && !downstream.contains(dep.as_str()) && !deps.contains(dep) {
+                    deps.push(dep.clone());
+                }
+            }
+        }
+        deps
     }
 
     /// Get a node by ID
@@ -267,6 +685,69 @@ impl WorkflowGraph {
     }
 }
 
+/// Check declared stages: known, distinct IDs that don't clash with node
+/// IDs (a stage's aggregated output is stored under its ID), and existing
+/// members that belong to one stage only
+fn parse_stages(declared: Vec<GraphStage>, nodes: &HashMap<String, ParsedNode>) -> Result<Vec<ParsedStage>, GraphError> {
+    let mut stage_ids: HashSet<&str> = HashSet::new();
+    let mut owner: HashMap<&str, &str> = HashMap::new();
+
+    for stage in &declared {
+        if nodes.contains_key(&stage.id) || !stage_ids.insert(&stage.id) {
+            return Err(GraphError::InvalidFormat(format!(
+                "Stage ID '{}' is already used by another stage or node",
+                stage.id
+            )));
+        }
+        for node_id in &stage.nodes {
+            if !nodes.contains_key(node_id) {
+                return Err(GraphError::NodeNotFound(node_id.clone()));
+            }
+            if let Some(other) = owner.insert(node_id, &stage.id) {
+                return Err(GraphError::InvalidFormat(format!(
+                    "Node '{}' is in both stage '{}' and stage '{}'",
+                    node_id, other, stage.id
+                )));
+            }
+        }
+    }
+
+    Ok(declared
+        .into_iter()
+        .map(|stage| ParsedStage {
+            label: stage.label.unwrap_or_else(|| stage.id.clone()),
+            id: stage.id,
+            nodes: stage.nodes,
+            collapsed: stage.collapsed,
+        })
+        .collect())
+}
+
+/// Whether `edges` between `nodes` form no cycle
+fn is_acyclic<'a>(nodes: &HashSet<String>, edges: impl Iterator<Item = &'a ParsedEdge>) -> bool {
+    let mut in_degree: HashMap<&str, usize> = nodes.iter().map(|id| (id.as_str(), 0)).collect();
+    let mut successors: HashMap<&str, Vec<&str>> = HashMap::new();
+    for edge in edges {
+        *in_degree.entry(edge.target.as_str()).or_default() += 1;
+        successors.entry(edge.source.as_str()).or_default().push(edge.target.as_str());
+    }
+
+    let mut queue: Vec<&str> = in_degree.iter().filter(|(_, deg)| **deg == 0).map(|(id, _)| *id).collect();
+    let mut processed = 0;
+    while let Some(node) = queue.pop() {
+        processed += 1;
+        for succ in successors.get(node).into_iter().flatten() {
+            if let Some(deg) = in_degree.get_mut(succ) {
+                *deg -= 1;
+                if *deg == 0 {
+                    queue.push(succ);
+                }
+            }
+        }
+    }
+    processed == in_degree.len()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -332,7 +813,48 @@ mod tests {
         let graph = WorkflowGraph::from_json(&json).unwrap();
         let result = graph.compute_execution_levels();
 
-        assert!(matches!(result, Err(GraphError::CycleDetected)));
+        match result {
+            Err(GraphError::CycleDetected(diagnosis)) => {
+                assert_eq!(diagnosis.cycle, vec!["a", "b", "c"]);
+                assert_eq!(diagnosis.path(), "a -> b -> c -> a");
+                let ids: Vec<&str> = diagnosis.breaking_edges.iter().map(|e| e.id.as_str()).collect();
+                assert_eq!(ids, vec!["e3"]);
+            }
+            other => panic!("expected a cycle, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_cycle_diagnosis_minimal_edges() {
+        // start -> b -> c, looping back through c -> b and c -> d -> b
+        let json = json!({
+            "nodes": [
+                {"id": "start", "data": {"label": "Start", "agentRole": "orchestrator"}},
+                {"id": "b", "data": {"label": "B", "agentRole": "implementer"}},
+                {"id": "c", "data": {"label": "C", "agentRole": "implementer"}},
+                {"id": "d", "data": {"label": "D", "agentRole": "implementer"}},
+                {"id": "end", "data": {"label": "End", "agentRole": "tester"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "start", "target": "b"},
+                {"id": "e2", "source": "b", "target": "c"},
+                {"id": "e3", "source": "c", "target": "b"},
+                {"id": "e4", "source": "c", "target": "d"},
+                {"id": "e5", "source": "d", "target": "b"},
+                {"id": "e6", "source": "d", "target": "end"}
+            ]
+        });
+
+        let graph = WorkflowGraph::from_json(&json).unwrap();
+        let Err(GraphError::CycleDetected(diagnosis)) = graph.compute_execution_levels() else {
+            panic!("expected a cycle");
+        };
+
+        assert_eq!(diagnosis.cycle, vec!["b", "c"]);
+        let ids: Vec<&str> = diagnosis.breaking_edges.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, vec!["e3", "e5"]);
+        // The nodes downstream of the cycle aren't part of it
+        assert!(!diagnosis.cycle.contains(&"end".to_string()));
     }
 
     #[test]
@@ -346,4 +868,101 @@ mod tests {
         let leaves = graph.get_leaf_nodes();
         assert_eq!(leaves, vec!["d"]);
     }
+
+    #[test]
+    fn test_parse_script_node() {
+        let json = json!({
+            "nodes": [
+                {"id": "a", "data": {"label": "A", "agentRole": "implementer"}},
+                {"id": "s", "data": {"label": "S", "kind": {"type": "script", "source": "40 + 2"}}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "a", "target": "s"}
+            ]
+        });
+
+        let graph = WorkflowGraph::from_json(&json).unwrap();
+        assert_eq!(graph.get_node("a").unwrap().kind, NodeKind::Agent);
+        assert_eq!(
+            graph.get_node("s").unwrap().kind,
+            NodeKind::Script { source: "40 + 2".to_string() }
+        );
+
+        // Agent nodes still require a role
+        let missing_role = json!({
+            "nodes": [{"id": "a", "data": {"label": "A"}}],
+            "edges": []
+        });
+        assert!(matches!(
+            WorkflowGraph::from_json(&missing_role),
+            Err(GraphError::Schema(_))
+        ));
+    }
+
+    #[test]
+    fn test_parse_wait_for_execution_node() {
+        let json = json!({
+            "nodes": [{"id": "w", "data": {"label": "W", "kind": {
+                "type": "wait_for_execution",
+                "workflow_id": "7f1d6c8e-0000-0000-0000-000000000000",
+                "outputs": ["build"]
+            }}}],
+            "edges": []
+        });
+
+        let graph = WorkflowGraph::from_json(&json).unwrap();
+        assert!(matches!(
+            &graph.get_node("w").unwrap().kind,
+            NodeKind::WaitForExecution { execution_id: None, workflow_id: Some(_), outputs, timeout_ms: None }
+                if outputs == &vec!["build".to_string()]
+        ));
+
+        // Needs something to wait for
+        let no_target = json!({
+            "nodes": [{"id": "w", "data": {"label": "W", "kind": {"type": "wait_for_execution"}}}],
+            "edges": []
+        });
+        assert!(WorkflowGraph::from_json(&no_target).is_err());
+    }
+
+    #[test]
+    fn test_parse_supply_chain_check_node() {
+        let json = json!({
+            "nodes": [{"id": "deps", "data": {"label": "Deps", "kind": {
+                "type": "supply_chain_check",
+                "lockfiles": ["Cargo.lock"],
+                "ignore_advisories": ["RUSTSEC-2020-0071"]
+            }}}],
+            "edges": []
+        });
+
+        let graph = WorkflowGraph::from_json(&json).unwrap();
+        assert!(matches!(
+            &graph.get_node("deps").unwrap().kind,
+            NodeKind::SupplyChainCheck { lockfiles, allow_unknown: false, ignore_advisories }
+                if lockfiles == &vec!["Cargo.lock".to_string()] && ignore_advisories.len() == 1
+        ));
+
+        let bad_lockfiles = json!({
+            "nodes": [{"id": "deps", "data": {"label": "Deps", "kind": {"type": "supply_chain_check", "lockfiles": "Cargo.lock"}}}],
+            "edges": []
+        });
+        assert!(matches!(WorkflowGraph::from_json(&bad_lockfiles), Err(GraphError::Schema(_))));
+    }
+
+    #[test]
+    fn test_critical_path() {
+        let json = create_test_graph();
+        let graph = WorkflowGraph::from_json(&json).unwrap();
+
+        // Uniform weights: ties broken by node id
+        let path = graph.critical_path(|_| 1).unwrap();
+        assert_eq!(path, vec!["a", "b", "d"]);
+
+        // A slow tester moves the critical path through c
+        let path = graph
+            .critical_path(|n| if n.agent_role == "tester" { 10 } else { 1 })
+            .unwrap();
+        assert_eq!(path, vec!["a", "c", "d"]);
+    }
 }
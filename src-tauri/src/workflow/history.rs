@@ -13,11 +13,25 @@ use std::collections::HashMap;
 use std::path::PathBuf;
 use uuid::Uuid;
 
-use super::context::AgentOutput;
-use super::state::{ExecutionStatus, NodeExecutionStatus};
+use super::anomaly::Anomaly;
+use super::context::{AgentOutput, ExecutionContext, NodeTranscript};
+use super::env_vars::EXECUTION_ENVS;
+use super::environment::EnvironmentFingerprint;
+use super::model_pins::ModelSubstitution;
+use super::result_cache::fnv1a;
+use super::graph::WorkflowGraph;
+use super::retry::RetryAttemptError;
+use super::state::{ExecutionStatus, NodeExecutionState, NodeExecutionStatus, WorkflowExecutionState};
+use super::text;
+use super::tokenizer::{Encoding, TokenCount, TOKENIZERS};
+use crate::settings::secrets;
+
+/// Characters of a node's output kept as its summary
+const OUTPUT_SUMMARY_CHARS: usize = 200;
 
 /// A complete record of a workflow execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject), graphql(complex))]
 pub struct ExecutionRecord {
     /// Unique execution ID
     pub id: Uuid,
@@ -48,17 +62,78 @@ pub struct ExecutionRecord {
     /// Number of nodes that were skipped
     pub skipped_nodes: usize,
     /// Detailed node execution records
+    #[cfg_attr(feature = "graphql", graphql(skip))]
     pub node_records: Vec<NodeExecutionRecord>,
     /// Agent outputs collected during execution
+    #[cfg_attr(feature = "graphql", graphql(skip))]
     pub outputs: HashMap<String, Vec<AgentOutput>>,
     /// Execution timeline events
+    #[cfg_attr(feature = "graphql", graphql(skip))]
     pub timeline: Vec<TimelineEvent>,
     /// Execution metrics
+    #[cfg_attr(feature = "graphql", graphql(skip))]
     pub metrics: ExecutionMetrics,
     /// Tags for categorization
     pub tags: Vec<String>,
     /// User notes
     pub notes: Option<String>,
+    /// How the run departed from earlier runs of its workflow
+    #[serde(default)]
+    #[cfg_attr(feature = "graphql", graphql(skip))]
+    pub anomalies: Vec<Anomaly>,
+    /// Versions, models and commit the run started with
+    #[serde(default)]
+    #[cfg_attr(feature = "graphql", graphql(skip))]
+    pub environment: Option<EnvironmentFingerprint>,
+    /// Pinned models that were unavailable, and what ran instead
+    #[serde(default)]
+    #[cfg_attr(feature = "graphql", graphql(skip))]
+    pub model_substitutions: Vec<ModelSubstitution>,
+    /// Reviewers' notes on single nodes, oldest first
+    #[serde(default)]
+    #[cfg_attr(feature = "graphql", graphql(skip))]
+    pub annotations: Vec<NodeAnnotation>,
+    /// Thumbs up or down on node outputs, one per author and node
+    #[serde(default)]
+    #[cfg_attr(feature = "graphql", graphql(skip))]
+    pub ratings: Vec<NodeRating>,
+}
+
+/// How much a node annotation matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationSeverity {
+    Info,
+    Warning,
+    /// The node's output was wrong; left out of datasets on request
+    Wrong,
+}
+
+/// A reviewer's note on one node of a finished execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeAnnotation {
+    pub id: Uuid,
+    pub node_id: String,
+    pub author: String,
+    pub text: String,
+    pub severity: AnnotationSeverity,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Rating {
+    Up,
+    Down,
+}
+
+/// A reviewer's thumbs up or down on one node's output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeRating {
+    pub node_id: String,
+    pub author: String,
+    pub rating: Rating,
+    pub rated_at: DateTime<Utc>,
 }
 
 /// Record of a single node's execution
@@ -72,9 +147,72 @@ pub struct NodeExecutionRecord {
     pub started_at: Option<DateTime<Utc>>,
     pub completed_at: Option<DateTime<Utc>>,
     pub duration_ms: Option<u64>,
+    /// Time spent waiting for a concurrency slot or resource lock
+    #[serde(default)]
+    pub queue_wait_ms: Option<u64>,
     pub retry_count: u32,
     pub output_summary: Option<String>,
     pub error: Option<String>,
+    /// Start of what the agent produced before it failed or timed out
+    #[serde(default)]
+    pub partial_output_summary: Option<String>,
+    /// Each failed attempt: the ones retried, then the final failure
+    #[serde(default)]
+    pub retry_attempts: Vec<RetryAttemptError>,
+    /// Tokens sent and received over all attempts, when they were recorded
+    #[serde(default)]
+    pub tokens: Option<NodeTokens>,
+    /// Model of the last attempt; `None` for the backend's default
+    #[serde(default)]
+    pub model: Option<String>,
+    /// Short hash of the last attempt's system prompt, telling apart runs
+    /// of the same role under different prompts
+    #[serde(default)]
+    pub prompt_version: Option<String>,
+}
+
+/// Tokens one node's agent attempts used
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NodeTokens {
+    /// System prompts and prompts
+    pub input: u64,
+    /// Responses
+    pub output: u64,
+    /// Whether every attempt was counted with its encoding's merge table;
+    /// otherwise the counts are estimates
+    #[serde(default)]
+    pub exact: bool,
+}
+
+impl NodeExecutionRecord {
+    /// Record of a node as its execution left it
+    pub fn from_state(node: &NodeExecutionState, node_name: String, agent_role: String) -> Self {
+        let duration_ms = match (node.started_at, node.completed_at) {
+            (Some(started), Some(completed)) => Some((completed - started).num_milliseconds().max(0) as u64),
+            _ => None,
+        };
+        let summarize = |output: &String| text::truncate(output, OUTPUT_SUMMARY_CHARS, "...");
+
+        Self {
+            node_id: node.node_id.clone(),
+            node_name,
+            agent_role,
+            agent_id: node.agent_id,
+            status: node.status,
+            started_at: node.started_at,
+            completed_at: node.completed_at,
+            duration_ms,
+            queue_wait_ms: node.queue_wait_ms,
+            retry_count: node.retry_count(),
+            output_summary: node.output.as_ref().map(summarize),
+            error: node.error.clone(),
+            partial_output_summary: node.partial_output.as_ref().map(summarize),
+            retry_attempts: node.retry_attempts.clone(),
+            tokens: None,
+            model: None,
+            prompt_version: None,
+        }
+    }
 }
 
 /// Timeline event during execution
@@ -106,6 +244,8 @@ pub enum TimelineEventType {
     ContextUpdated,
     CheckpointCreated,
     ReplanTriggered,
+    /// A reviewer annotated a node after the run
+    Annotation,
     Custom,
 }
 
@@ -114,6 +254,10 @@ pub enum TimelineEventType {
 pub struct ExecutionMetrics {
     /// Total tokens used (if tracked)
     pub total_tokens: Option<u64>,
+    /// Whether `total_tokens` was counted with merge tables rather than
+    /// estimated
+    #[serde(default)]
+    pub total_tokens_exact: bool,
     /// Total API calls made
     pub api_calls: u32,
     /// Average node execution time in ms
@@ -124,6 +268,9 @@ pub struct ExecutionMetrics {
     pub min_node_duration_ms: Option<u64>,
     /// Number of retries across all nodes
     pub total_retries: u32,
+    /// Time nodes spent waiting for concurrency slots and resource locks
+    #[serde(default)]
+    pub total_queue_wait_ms: u64,
     /// Parallel execution efficiency (0-100%)
     pub parallelism_efficiency: Option<f32>,
     /// Memory usage peak (bytes)
@@ -162,7 +309,7 @@ impl ExecutionHistoryStore {
     /// List all records, sorted by start time (newest first)
     pub fn list(&self) -> Vec<ExecutionRecord> {
         let mut records: Vec<_> = self.records.iter().map(|r| r.clone()).collect();
-        records.sort_by(|a, b| b.started_at.cmp(&a.started_at));
+        records.sort_by_key(|r| std::cmp::Reverse(r.started_at));
         records
     }
 
@@ -218,6 +365,82 @@ impl ExecutionHistoryStore {
         }
     }
 
+    /// Annotate a node of a record; the annotation also appears in the
+    /// record's timeline
+    pub fn annotate_node(
+        &self,
+        id: &Uuid,
+        node_id: &str,
+        author: &str,
+        text: &str,
+        severity: AnnotationSeverity,
+    ) -> Result<NodeAnnotation, String> {
+        let (author, text) = (author.trim(), text.trim());
+        if text.is_empty() {
+            return Err("Annotation text is empty".to_string());
+        }
+        let mut record = self.records.get_mut(id).ok_or_else(|| format!("Execution record not found: {}", id))?;
+        if !record.node_records.iter().any(|node| node.node_id == node_id) {
+            return Err(format!("Execution {} has no node '{}'", id, node_id));
+        }
+
+        let annotation = NodeAnnotation {
+            id: Uuid::new_v4(),
+            node_id: node_id.to_string(),
+            author: if author.is_empty() { "anonymous".to_string() } else { author.to_string() },
+            text: text.to_string(),
+            severity,
+            created_at: Utc::now(),
+        };
+        record.timeline.push(TimelineEvent {
+            timestamp: annotation.created_at,
+            event_type: TimelineEventType::Annotation,
+            node_id: Some(annotation.node_id.clone()),
+            message: format!("{}: {}", annotation.author, annotation.text),
+            metadata: Some(serde_json::json!({ "annotation_id": annotation.id, "severity": severity })),
+        });
+        record.annotations.push(annotation.clone());
+        Ok(annotation)
+    }
+
+    /// Remove an annotation and its timeline event; false if there was none
+    pub fn remove_annotation(&self, id: &Uuid, annotation_id: &Uuid) -> bool {
+        let Some(mut record) = self.records.get_mut(id) else {
+            return false;
+        };
+        let before = record.annotations.len();
+        record.annotations.retain(|annotation| annotation.id != *annotation_id);
+        let annotation_id = annotation_id.to_string();
+        record.timeline.retain(|event| {
+            event.metadata.as_ref().and_then(|metadata| metadata.get("annotation_id")).and_then(|id| id.as_str())
+                != Some(annotation_id.as_str())
+        });
+        record.annotations.len() != before
+    }
+
+    /// Rate a node of a record, replacing the author's earlier rating;
+    /// `None` takes it back
+    pub fn rate_node(&self, id: &Uuid, node_id: &str, author: &str, rating: Option<Rating>) -> Result<(), String> {
+        let mut record = self.records.get_mut(id).ok_or_else(|| format!("Execution record not found: {}", id))?;
+        if !record.node_records.iter().any(|node| node.node_id == node_id) {
+            return Err(format!("Execution {} has no node '{}'", id, node_id));
+        }
+        let author = match author.trim() {
+            "" => "anonymous",
+            author => author,
+        };
+        record.ratings.retain(|r| !(r.node_id == node_id && r.author == author));
+        if let Some(rating) = rating {
+            record.ratings.push(NodeRating {
+                node_id: node_id.to_string(),
+                author: author.to_string(),
+                rating,
+                rated_at: Utc::now(),
+            });
+        }
+        Ok(())
+    }
+
     /// Add tags to a record
     pub fn add_tags(&self, id: &Uuid, tags: Vec<String>) -> bool {
         if let Some(mut record) = self.records.get_mut(id) {
@@ -273,7 +496,7 @@ impl ExecutionHistoryStore {
     /// Remove oldest records
     fn cleanup_oldest(&self, count: usize) {
         let mut records = self.list();
-        records.sort_by(|a, b| a.started_at.cmp(&b.started_at)); // Oldest first
+        records.sort_by_key(|a| a.started_at); // Oldest first
 
         for record in records.into_iter().take(count) {
             self.records.remove(&record.id);
@@ -338,6 +561,11 @@ impl ExecutionRecordBuilder {
         }
     }
 
+    pub fn started_at(mut self, started_at: DateTime<Utc>) -> Self {
+        self.started_at = started_at;
+        self
+    }
+
     pub fn workflow(mut self, workflow_id: Uuid, workflow_name: String) -> Self {
         self.workflow_id = Some(workflow_id);
         self.workflow_name = workflow_name;
@@ -382,6 +610,7 @@ impl ExecutionRecordBuilder {
 
         let metrics = ExecutionMetrics {
             total_tokens: None,
+            total_tokens_exact: false,
             api_calls: self.node_records.len() as u32,
             avg_node_duration_ms: if node_durations.is_empty() {
                 None
@@ -391,6 +620,7 @@ impl ExecutionRecordBuilder {
             max_node_duration_ms: node_durations.iter().max().copied(),
             min_node_duration_ms: node_durations.iter().min().copied(),
             total_retries: self.node_records.iter().map(|n| n.retry_count).sum(),
+            total_queue_wait_ms: self.node_records.iter().filter_map(|n| n.queue_wait_ms).sum(),
             parallelism_efficiency: None,
             peak_memory_bytes: None,
         };
@@ -416,8 +646,100 @@ impl ExecutionRecordBuilder {
             metrics,
             tags: self.tags,
             notes: None,
+            anomalies: Vec::new(),
+            environment: None,
+            model_substitutions: Vec::new(),
+            annotations: Vec::new(),
+            ratings: Vec::new(),
+        }
+    }
+}
+
+/// Record of a finished execution: its nodes in level order, with their
+/// retries, and their outputs. Secret values an agent echoed are redacted.
+pub fn record_execution(
+    state: &WorkflowExecutionState,
+    context: &ExecutionContext,
+    graph: &WorkflowGraph,
+    workflow_name: &str,
+    project_name: String,
+) -> ExecutionRecord {
+    let mut builder = ExecutionRecordBuilder::new(
+        state.execution_id,
+        state.project_id,
+        project_name,
+        state.input_prompt.clone(),
+    )
+    .started_at(state.started_at);
+    builder.workflow_name = workflow_name.to_string();
+    if !state.workflow_id.is_nil() {
+        builder = builder.workflow(state.workflow_id, workflow_name.to_string());
+    }
+
+    for node_id in state.execution_levels.iter().flatten() {
+        let Some(node) = state.get_node_state(node_id) else {
+            continue;
+        };
+        let (name, role) = graph
+            .get_node(node_id)
+            .map(|n| (n.label.clone(), n.agent_role.clone()))
+            .unwrap_or_else(|| (node_id.clone(), String::new()));
+        builder.add_node_record(NodeExecutionRecord::from_state(&node, name, role));
+        for output in context.get_node_outputs(node_id) {
+            builder.add_output(node_id.clone(), output);
+        }
+    }
+
+    let completed_at = (*state.completed_at.read()).unwrap_or_else(Utc::now);
+    let mut record = builder.build(state.get_status(), completed_at);
+    let transcripts = context.get_transcripts();
+    for node in &mut record.node_records {
+        let attempts: Vec<&NodeTranscript> = transcripts.iter().filter(|t| t.node_id == node.node_id).collect();
+        if let Some(last) = attempts.last() {
+            node.model = last.model.clone();
+            node.prompt_version = Some(prompt_version(last.system_prompt.as_deref()));
+            node.tokens = Some(sum_tokens(attempts.into_iter().map(transcript_tokens)));
         }
     }
+    if !transcripts.is_empty() {
+        let total = sum_tokens(transcripts.iter().map(transcript_tokens));
+        record.metrics.total_tokens = Some(total.input + total.output);
+        record.metrics.total_tokens_exact = total.exact;
+    }
+    record.environment = state.environment.read().clone();
+    record.model_substitutions = state.model_substitutions.read().clone();
+    EXECUTION_ENVS.redacted(&state.execution_id, &secrets::redacted(&record))
+}
+
+/// Version of a system prompt: the same for the same text, on any machine
+pub fn prompt_version(system_prompt: Option<&str>) -> String {
+    fnv1a(system_prompt.unwrap_or("").as_bytes())[..8].to_string()
+}
+
+/// Tokens of several attempts together, exact only if each count was
+fn sum_tokens(tokens: impl Iterator<Item = NodeTokens>) -> NodeTokens {
+    tokens.fold(NodeTokens { exact: true, ..NodeTokens::default() }, |total, tokens| NodeTokens {
+        input: total.input + tokens.input,
+        output: total.output + tokens.output,
+        exact: total.exact && tokens.exact,
+    })
+}
+
+/// Tokens of one attempt's prompts and answer
+fn transcript_tokens(transcript: &NodeTranscript) -> NodeTokens {
+    let encoding = Encoding::for_model(transcript.model.as_deref());
+    let count = |text: Option<&str>| text.map(|text| TOKENIZERS.count(text, encoding));
+    let counts = [
+        count(transcript.system_prompt.as_deref()),
+        count(Some(&transcript.prompt)),
+        count(transcript.response.as_deref()),
+    ];
+    let tokens = |count: &Option<TokenCount>| count.as_ref().map_or(0, |count| count.tokens as u64);
+    NodeTokens {
+        input: tokens(&counts[0]) + tokens(&counts[1]),
+        output: tokens(&counts[2]),
+        exact: counts.iter().flatten().all(|count| count.exact),
+    }
 }
 
 /// Persistent history storage using JSON files
@@ -510,7 +832,7 @@ impl PersistentHistoryStore {
             .collect();
 
         // Sort by filename (which includes timestamp)
-        files.sort_by(|a, b| b.file_name().cmp(&a.file_name()));
+        files.sort_by_key(|f| std::cmp::Reverse(f.file_name()));
 
         for entry in files.into_iter().take(count) {
             if let Ok(content) = std::fs::read_to_string(entry.path()) {
@@ -546,9 +868,15 @@ mod tests {
             started_at: Some(Utc::now()),
             completed_at: Some(Utc::now()),
             duration_ms: Some(1000),
+            queue_wait_ms: None,
             retry_count: 0,
             output_summary: Some("Done".to_string()),
             error: None,
+            partial_output_summary: None,
+            retry_attempts: Vec::new(),
+            tokens: None,
+            model: None,
+            prompt_version: None,
         });
 
         let record = builder.build(ExecutionStatus::Completed, Utc::now());
@@ -558,6 +886,84 @@ mod tests {
         assert_eq!(record.status, ExecutionStatus::Completed);
     }
 
+    #[test]
+    fn test_record_execution_keeps_retries() {
+        let graph = WorkflowGraph::from_json(&serde_json::json!({
+            "nodes": [
+                {"id": "build", "data": {"label": "Build", "agentRole": "implementer"}},
+                {"id": "test", "data": {"label": "Test", "agentRole": "tester"}}
+            ],
+            "edges": [{"id": "e1", "source": "build", "target": "test"}]
+        }))
+        .unwrap();
+        let state = WorkflowExecutionState::new(
+            Uuid::new_v4(),
+            Uuid::nil(),
+            Uuid::new_v4(),
+            "Ship it".to_string(),
+            graph.compute_execution_levels().unwrap(),
+        );
+        let attempt = |attempt: u32, delay: Option<std::time::Duration>| RetryAttemptError {
+            attempt,
+            error: "rate limit".to_string(),
+            timestamp: Utc::now(),
+            delay_before_next: delay,
+        };
+        state.update_node_state("build", |ns| {
+            ns.retry_attempts.push(attempt(1, Some(std::time::Duration::from_secs(1))));
+            ns.complete(Some("x".repeat(OUTPUT_SUMMARY_CHARS + 50)));
+        });
+        state.update_node_state("test", |ns| {
+            ns.retry_attempts.push(attempt(1, Some(std::time::Duration::from_secs(1))));
+            ns.retry_attempts.push(attempt(2, Some(std::time::Duration::from_secs(2))));
+            ns.retry_attempts.push(attempt(3, None));
+            ns.partial_output = Some("Ran 4 of 9 suites".to_string());
+            ns.fail("rate limit".to_string());
+        });
+        state.set_status(ExecutionStatus::Failed);
+
+        let context = ExecutionContext::new(state.execution_id, state.project_id, "Ship it".to_string());
+        for attempt in 1..=2 {
+            context.record_transcript(NodeTranscript {
+                node_id: "build".to_string(),
+                agent_role: "implementer".to_string(),
+                system_prompt: None,
+                prompt: "Ship it".to_string(),
+                response: Some("Shipped".to_string()),
+                model: Some("claude-sonnet-4-20250514".to_string()),
+                attempt,
+                success: attempt == 2,
+                timestamp: Utc::now(),
+            });
+        }
+        let record = record_execution(&state, &context, &graph, "Enhanced Workflow", "Project".to_string());
+
+        assert_eq!(record.workflow_id, None);
+        assert_eq!(record.workflow_name, "Enhanced Workflow");
+        let ids: Vec<&str> = record.node_records.iter().map(|n| n.node_id.as_str()).collect();
+        assert_eq!(ids, vec!["build", "test"]);
+        assert_eq!(record.node_records[0].node_name, "Build");
+        assert_eq!(record.node_records[0].retry_count, 1);
+        assert_eq!(record.node_records[1].retry_count, 2);
+        assert_eq!(record.node_records[1].retry_attempts.len(), 3);
+        assert_eq!(record.metrics.total_retries, 3);
+        let tokens = record.node_records[0].tokens.unwrap();
+        assert!(tokens.input > 0 && tokens.output > 0);
+        assert_eq!(record.metrics.total_tokens, Some(tokens.input + tokens.output));
+        assert!(!tokens.exact && !record.metrics.total_tokens_exact, "Claude counts are estimates");
+        assert_eq!(record.node_records[0].model.as_deref(), Some("claude-sonnet-4-20250514"));
+        assert!(record.node_records[1].tokens.is_none());
+        assert!(record.node_records[0].output_summary.as_ref().unwrap().ends_with("..."));
+        assert_eq!(record.node_records[1].partial_output_summary.as_deref(), Some("Ran 4 of 9 suites"));
+
+        // Older records without attempts still load
+        let mut json = serde_json::to_value(&record.node_records[0]).unwrap();
+        json.as_object_mut().unwrap().remove("retry_attempts");
+        json.as_object_mut().unwrap().remove("partial_output_summary");
+        let restored: NodeExecutionRecord = serde_json::from_value(json).unwrap();
+        assert!(restored.retry_attempts.is_empty());
+    }
+
     #[test]
     fn test_history_store() {
         let store = ExecutionHistoryStore::new(10);
@@ -583,6 +989,11 @@ mod tests {
             metrics: ExecutionMetrics::default(),
             tags: vec![],
             notes: None,
+            anomalies: Vec::new(),
+            environment: None,
+            model_substitutions: Vec::new(),
+            annotations: Vec::new(),
+            ratings: Vec::new(),
         };
 
         store.add(record.clone());
@@ -594,4 +1005,62 @@ mod tests {
         assert_eq!(stats.total_executions, 1);
         assert_eq!(stats.success_rate, 100.0);
     }
+
+    #[test]
+    fn test_node_annotations() {
+        let store = ExecutionHistoryStore::new(10);
+        let mut builder = ExecutionRecordBuilder::new(Uuid::new_v4(), Uuid::new_v4(), "Shop".to_string(), "Review".to_string());
+        builder.add_node_record(NodeExecutionRecord {
+            node_id: "review".to_string(),
+            node_name: "Review".to_string(),
+            agent_role: "reviewer".to_string(),
+            agent_id: None,
+            status: NodeExecutionStatus::Completed,
+            started_at: None,
+            completed_at: None,
+            duration_ms: None,
+            queue_wait_ms: None,
+            retry_count: 0,
+            output_summary: Some("Looks good".to_string()),
+            error: None,
+            partial_output_summary: None,
+            retry_attempts: Vec::new(),
+            tokens: None,
+            model: None,
+            prompt_version: None,
+        });
+        let record = builder.build(ExecutionStatus::Completed, Utc::now());
+        store.add(record.clone());
+
+        let wrong = store
+            .annotate_node(&record.id, "review", " dana ", "Missed the SQL injection in orders.rs", AnnotationSeverity::Wrong)
+            .unwrap();
+        assert_eq!(wrong.author, "dana");
+        assert!(store.annotate_node(&record.id, "deploy", "dana", "Slow", AnnotationSeverity::Info).is_err());
+        assert!(store.annotate_node(&record.id, "review", "dana", "  ", AnnotationSeverity::Info).is_err());
+        let info = store.annotate_node(&record.id, "review", "", "Rerun with the new prompt", AnnotationSeverity::Info).unwrap();
+        assert_eq!(info.author, "anonymous");
+
+        let annotated = store.get(&record.id).unwrap();
+        assert_eq!(annotated.annotations.len(), 2);
+        let event = annotated.timeline.last().unwrap();
+        assert!(matches!(event.event_type, TimelineEventType::Annotation));
+        assert_eq!(event.message, "anonymous: Rerun with the new prompt");
+
+        assert!(store.remove_annotation(&record.id, &wrong.id));
+        assert!(!store.remove_annotation(&record.id, &wrong.id));
+        let annotated = store.get(&record.id).unwrap();
+        assert_eq!(annotated.annotations.len(), 1);
+        assert_eq!(annotated.timeline.iter().filter(|e| matches!(e.event_type, TimelineEventType::Annotation)).count(), 1);
+
+        // One rating per author, replaced or taken back
+        store.rate_node(&record.id, "review", "dana", Some(Rating::Up)).unwrap();
+        store.rate_node(&record.id, "review", "dana", Some(Rating::Down)).unwrap();
+        store.rate_node(&record.id, "review", "lee", Some(Rating::Up)).unwrap();
+        assert!(store.rate_node(&record.id, "deploy", "lee", Some(Rating::Up)).is_err());
+        let ratings = store.get(&record.id).unwrap().ratings;
+        assert_eq!(ratings.iter().map(|r| r.rating).collect::<Vec<_>>(), [Rating::Down, Rating::Up]);
+        store.rate_node(&record.id, "review", "lee", None).unwrap();
+        assert_eq!(store.get(&record.id).unwrap().ratings.len(), 1);
+    }
 }
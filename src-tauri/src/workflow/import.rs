@@ -0,0 +1,487 @@
+//! Import workflows from other automation formats.
+//!
+//! Supports a subset of:
+//! - GitHub Actions workflow YAML (jobs become agent nodes, `needs` become edges)
+//! - n8n workflow JSON (nodes and `main` connections; triggers are dropped)
+//!
+//! Each step is described in the node's assigned task so the agent can
+//! reproduce it. Anything that can't be mapped is reported as a warning.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use thiserror::Error;
+
+use super::graph::WorkflowGraph;
+use super::schema::GRAPH_SCHEMA_VERSION;
+
+/// Vertical spacing between generated nodes
+const LAYOUT_ROW_HEIGHT: f64 = 150.0;
+/// Horizontal spacing between nodes on the same level
+const LAYOUT_COLUMN_WIDTH: f64 = 250.0;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("Could not parse {format}: {message}")]
+    Parse { format: &'static str, message: String },
+
+    #[error("Unrecognized workflow format")]
+    UnknownFormat,
+
+    #[error("Workflow has no importable jobs or nodes")]
+    Empty,
+
+    #[error("Job '{job}' needs unknown job '{needs}'")]
+    UnknownDependency { job: String, needs: String },
+
+    #[error("Imported graph is invalid: {0}")]
+    InvalidGraph(String),
+}
+
+/// Source format of an imported workflow
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportFormat {
+    GithubActions,
+    N8n,
+}
+
+impl ImportFormat {
+    /// Guess the format: n8n exports are JSON with `nodes` and `connections`,
+    /// GitHub Actions files are YAML with `jobs`
+    pub fn detect(source: &str) -> Option<Self> {
+        if let Ok(value) = serde_json::from_str::<Value>(source) {
+            return (value.get("nodes").is_some() && value.get("connections").is_some()).then_some(ImportFormat::N8n);
+        }
+
+        let value: serde_yaml::Value = serde_yaml::from_str(source).ok()?;
+        value.get("jobs").is_some().then_some(ImportFormat::GithubActions)
+    }
+}
+
+/// A converted workflow, ready to save
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedWorkflow {
+    pub name: Option<String>,
+    pub format: ImportFormat,
+    /// Graph in the current schema version
+    pub graph: Value,
+    pub warnings: Vec<String>,
+}
+
+/// An importable node before layout
+struct ImportNode {
+    id: String,
+    label: String,
+    role: &'static str,
+    task: String,
+    system_prompt: Option<String>,
+}
+
+/// Convert a workflow definition, detecting its format when not given
+pub fn import_workflow(source: &str, format: Option<ImportFormat>) -> Result<ImportedWorkflow, ImportError> {
+    let format = format.or_else(|| ImportFormat::detect(source)).ok_or(ImportError::UnknownFormat)?;
+
+    let mut warnings = Vec::new();
+    let (name, nodes, edges) = match format {
+        ImportFormat::GithubActions => convert_github_actions(source, &mut warnings)?,
+        ImportFormat::N8n => convert_n8n(source, &mut warnings)?,
+    };
+
+    if nodes.is_empty() {
+        return Err(ImportError::Empty);
+    }
+
+    let graph = build_graph(nodes, edges);
+
+    // Catch anything the converters let through, including cycles
+    WorkflowGraph::from_json(&graph)
+        .and_then(|g| g.compute_execution_levels())
+        .map_err(|e| ImportError::InvalidGraph(e.to_string()))?;
+
+    Ok(ImportedWorkflow { name, format, graph, warnings })
+}
+
+// =============================================================================
+// GitHub Actions
+// =============================================================================
+
+#[derive(Debug, Deserialize)]
+struct GhWorkflow {
+    name: Option<String>,
+    #[serde(default)]
+    jobs: serde_yaml::Mapping,
+}
+
+#[derive(Debug, Deserialize)]
+struct GhJob {
+    name: Option<String>,
+    #[serde(default)]
+    needs: GhNeeds,
+    #[serde(rename = "runs-on")]
+    runs_on: Option<serde_yaml::Value>,
+    uses: Option<String>,
+    #[serde(default)]
+    steps: Vec<GhStep>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(untagged)]
+enum GhNeeds {
+    #[default]
+    None,
+    One(String),
+    Many(Vec<String>),
+}
+
+impl GhNeeds {
+    fn into_vec(self) -> Vec<String> {
+        match self {
+            GhNeeds::None => Vec::new(),
+            GhNeeds::One(job) => vec![job],
+            GhNeeds::Many(jobs) => jobs,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GhStep {
+    name: Option<String>,
+    uses: Option<String>,
+    run: Option<String>,
+}
+
+type Converted = (Option<String>, Vec<ImportNode>, Vec<(String, String)>);
+
+fn convert_github_actions(source: &str, warnings: &mut Vec<String>) -> Result<Converted, ImportError> {
+    let parse_error = |e: serde_yaml::Error| ImportError::Parse {
+        format: "GitHub Actions YAML",
+        message: e.to_string(),
+    };
+    let workflow: GhWorkflow = serde_yaml::from_str(source).map_err(parse_error)?;
+
+    let mut nodes = Vec::new();
+    let mut needs = Vec::new();
+
+    for (key, value) in workflow.jobs {
+        let Some(job_id) = key.as_str().map(str::to_string) else {
+            warnings.push(format!("Skipped job with non-string key {:?}", key));
+            continue;
+        };
+        let job: GhJob = serde_yaml::from_value(value).map_err(parse_error)?;
+
+        let label = job.name.clone().unwrap_or_else(|| job_id.clone());
+        let mut task = format!("Carry out the work of the GitHub Actions job '{}'.", label);
+        if let Some(runs_on) = job.runs_on.as_ref().and_then(|r| serde_yaml::to_string(r).ok()) {
+            task.push_str(&format!(" It originally ran on: {}", runs_on.trim()));
+        }
+        if let Some(reusable) = &job.uses {
+            warnings.push(format!("Job '{}' calls reusable workflow '{}'; its steps were not imported", job_id, reusable));
+            task.push_str(&format!("\n\nIt called the reusable workflow `{}`.", reusable));
+        }
+        if !job.steps.is_empty() {
+            task.push_str("\n\nSteps:");
+            for (i, step) in job.steps.iter().enumerate() {
+                let name = step.name.clone().unwrap_or_else(|| format!("Step {}", i + 1));
+                match (&step.run, &step.uses) {
+                    (Some(run), _) => task.push_str(&format!("\n{}. {}: run `{}`", i + 1, name, run.trim())),
+                    (None, Some(uses)) => task.push_str(&format!("\n{}. {}: use action `{}`", i + 1, name, uses)),
+                    (None, None) => task.push_str(&format!("\n{}. {}", i + 1, name)),
+                }
+            }
+        }
+
+        let role = guess_role(&format!("{} {}", job_id, label));
+        needs.extend(job.needs.into_vec().into_iter().map(|dep| (dep, job_id.clone())));
+        nodes.push(ImportNode {
+            id: job_id,
+            label,
+            role,
+            task,
+            system_prompt: None,
+        });
+    }
+
+    let ids: HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+    if let Some((dep, job)) = needs.iter().find(|(dep, _)| !ids.contains(dep.as_str())) {
+        return Err(ImportError::UnknownDependency {
+            job: job.clone(),
+            needs: dep.clone(),
+        });
+    }
+
+    Ok((workflow.name, nodes, needs))
+}
+
+/// Pick an agent role from words in a job's ID and name
+fn guess_role(text: &str) -> &'static str {
+    let text = text.to_lowercase();
+    let has = |words: &[&str]| words.iter().any(|w| text.contains(w));
+
+    if has(&["security", "codeql", "audit", "scan", "vuln"]) {
+        "security"
+    } else if has(&["test", "e2e", "coverage", "qa"]) {
+        "tester"
+    } else if has(&["doc", "readme", "changelog"]) {
+        "documenter"
+    } else if has(&["deploy", "release", "publish", "docker", "infra", "terraform"]) {
+        "devops"
+    } else {
+        "implementer"
+    }
+}
+
+// =============================================================================
+// n8n
+// =============================================================================
+
+#[derive(Debug, Deserialize)]
+struct N8nWorkflow {
+    name: Option<String>,
+    #[serde(default)]
+    nodes: Vec<N8nNode>,
+    /// Keyed by source node name
+    #[serde(default)]
+    connections: HashMap<String, N8nOutputs>,
+}
+
+/// Output type ("main", "ai_tool", ...) -> one target list per output
+/// index; unconnected outputs are null
+type N8nOutputs = HashMap<String, Vec<Option<Vec<N8nTarget>>>>;
+
+#[derive(Debug, Deserialize)]
+struct N8nNode {
+    name: String,
+    #[serde(rename = "type")]
+    node_type: String,
+    #[serde(default)]
+    parameters: Value,
+    #[serde(default)]
+    disabled: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct N8nTarget {
+    node: String,
+}
+
+fn convert_n8n(source: &str, warnings: &mut Vec<String>) -> Result<Converted, ImportError> {
+    let workflow: N8nWorkflow = serde_json::from_str(source).map_err(|e| ImportError::Parse {
+        format: "n8n JSON",
+        message: e.to_string(),
+    })?;
+
+    let mut nodes = Vec::new();
+    for node in &workflow.nodes {
+        let short_type = node.node_type.rsplit('.').next().unwrap_or(&node.node_type);
+
+        if node.disabled {
+            warnings.push(format!("Skipped disabled node '{}'", node.name));
+            continue;
+        }
+        if short_type.to_lowercase().contains("trigger") || short_type == "webhook" {
+            warnings.push(format!("Dropped trigger node '{}' ({})", node.name, short_type));
+            continue;
+        }
+
+        // LangChain agent nodes carry their prompt and system message directly
+        let (task, system_prompt) = if node.node_type.contains("langchain") && short_type == "agent" {
+            (
+                node.parameters.get("text").and_then(Value::as_str).map(str::to_string),
+                node.parameters
+                    .pointer("/options/systemMessage")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            )
+        } else {
+            (None, None)
+        };
+
+        let task = task.unwrap_or_else(|| {
+            let mut task = format!("Carry out the n8n '{}' step '{}'.", short_type, node.name);
+            if node.parameters.as_object().is_some_and(|p| !p.is_empty()) {
+                task.push_str(&format!("\n\nParameters:\n{}", node.parameters));
+            }
+            task
+        });
+
+        nodes.push(ImportNode {
+            id: node.name.clone(),
+            label: node.name.clone(),
+            role: guess_role(&format!("{} {}", short_type, node.name)),
+            task,
+            system_prompt,
+        });
+    }
+
+    let ids: HashSet<&str> = nodes.iter().map(|n| n.id.as_str()).collect();
+    let mut edges = Vec::new();
+    let mut seen = HashSet::new();
+    for (source, outputs) in &workflow.connections {
+        for (output_type, branches) in outputs {
+            if output_type != "main" {
+                warnings.push(format!("Ignored '{}' connections from '{}'", output_type, source));
+                continue;
+            }
+            for target in branches.iter().flatten().flatten() {
+                // Edges touching dropped nodes go away with them
+                if ids.contains(source.as_str()) && ids.contains(target.node.as_str()) && seen.insert((source, &target.node)) {
+                    edges.push((source.clone(), target.node.clone()));
+                }
+            }
+        }
+    }
+
+    Ok((workflow.name, nodes, edges))
+}
+
+// =============================================================================
+// Graph assembly
+// =============================================================================
+
+/// Build a React Flow graph, laying nodes out by dependency depth
+fn build_graph(nodes: Vec<ImportNode>, edges: Vec<(String, String)>) -> Value {
+    let mut depth: HashMap<&str, usize> = HashMap::new();
+    // Longest-path depth; bounded passes so a cycle can't loop forever
+    for _ in 0..nodes.len() {
+        let mut changed = false;
+        for (source, target) in &edges {
+            let next = depth.get(source.as_str()).copied().unwrap_or(0) + 1;
+            if depth.get(target.as_str()).copied().unwrap_or(0) < next {
+                depth.insert(target, next);
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+    }
+
+    let mut columns: HashMap<usize, usize> = HashMap::new();
+    let rf_nodes: Vec<Value> = nodes
+        .iter()
+        .map(|node| {
+            let row = depth.get(node.id.as_str()).copied().unwrap_or(0);
+            let column = columns.entry(row).or_insert(0);
+            let position = json!({
+                "x": *column as f64 * LAYOUT_COLUMN_WIDTH,
+                "y": row as f64 * LAYOUT_ROW_HEIGHT,
+            });
+            *column += 1;
+
+            json!({
+                "id": node.id,
+                "type": "agent",
+                "position": position,
+                "data": {
+                    "label": node.label,
+                    "agentRole": node.role,
+                    "assignedTask": node.task,
+                    "systemPrompt": node.system_prompt,
+                },
+            })
+        })
+        .collect();
+
+    let rf_edges: Vec<Value> = edges
+        .iter()
+        .map(|(source, target)| {
+            json!({
+                "id": format!("e-{}-{}", source, target),
+                "source": source,
+                "target": target,
+            })
+        })
+        .collect();
+
+    json!({
+        "version": GRAPH_SCHEMA_VERSION,
+        "nodes": rf_nodes,
+        "edges": rf_edges,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GITHUB_WORKFLOW: &str = r#"
+name: CI
+on: [push]
+jobs:
+  build:
+    runs-on: ubuntu-latest
+    steps:
+      - uses: actions/checkout@v4
+      - name: Build
+        run: cargo build
+  test:
+    needs: build
+    runs-on: ubuntu-latest
+    steps:
+      - run: cargo test
+  deploy:
+    needs: [build, test]
+    runs-on: ubuntu-latest
+    steps:
+      - run: ./deploy.sh
+"#;
+
+    #[test]
+    fn test_import_github_actions() {
+        assert_eq!(ImportFormat::detect(GITHUB_WORKFLOW), Some(ImportFormat::GithubActions));
+
+        let imported = import_workflow(GITHUB_WORKFLOW, None).unwrap();
+        assert_eq!(imported.name.as_deref(), Some("CI"));
+
+        let graph = WorkflowGraph::from_json(&imported.graph).unwrap();
+        assert_eq!(graph.node_count(), 3);
+        assert_eq!(graph.get_node("test").unwrap().agent_role, "tester");
+        assert_eq!(graph.get_node("deploy").unwrap().agent_role, "devops");
+        assert!(graph.get_node("build").unwrap().assigned_task.as_ref().unwrap().contains("run `cargo build`"));
+
+        let mut deps = graph.get_dependencies("deploy");
+        deps.sort();
+        assert_eq!(deps, vec!["build", "test"]);
+    }
+
+    #[test]
+    fn test_unknown_needs() {
+        let yaml = "jobs:\n  test:\n    needs: build\n    steps: []\n";
+        assert!(matches!(
+            import_workflow(yaml, Some(ImportFormat::GithubActions)),
+            Err(ImportError::UnknownDependency { .. })
+        ));
+    }
+
+    #[test]
+    fn test_import_n8n() {
+        let n8n = json!({
+            "name": "Triage",
+            "nodes": [
+                {"name": "When clicking", "type": "n8n-nodes-base.manualTrigger", "parameters": {}},
+                {"name": "Fetch", "type": "n8n-nodes-base.httpRequest", "parameters": {"url": "https://example.com"}},
+                {"name": "Summarize", "type": "@n8n/n8n-nodes-langchain.agent",
+                 "parameters": {"text": "Summarize the response", "options": {"systemMessage": "Be brief"}}}
+            ],
+            "connections": {
+                "When clicking": {"main": [[{"node": "Fetch", "type": "main", "index": 0}]]},
+                "Fetch": {"main": [[{"node": "Summarize", "type": "main", "index": 0}]]}
+            }
+        })
+        .to_string();
+
+        assert_eq!(ImportFormat::detect(&n8n), Some(ImportFormat::N8n));
+
+        let imported = import_workflow(&n8n, None).unwrap();
+        assert_eq!(imported.warnings.len(), 1);
+
+        let graph = WorkflowGraph::from_json(&imported.graph).unwrap();
+        assert_eq!(graph.node_count(), 2);
+        assert_eq!(graph.get_dependencies("Summarize"), vec!["Fetch"]);
+
+        let summarize = graph.get_node("Summarize").unwrap();
+        assert_eq!(summarize.assigned_task.as_deref(), Some("Summarize the response"));
+        assert_eq!(summarize.system_prompt.as_deref(), Some("Be brief"));
+    }
+}
@@ -0,0 +1,262 @@
+//! Replayable journal of workflow events.
+//!
+//! Events used to be emitted fire-and-forget, so a view opened mid-run (or
+//! a client that reconnects) missed everything before it subscribed.
+//! Provides:
+//! - Per-execution sequence numbers on every event
+//! - Catching up on the events after a given sequence number
+//! - Live subscription that starts exactly where a replay ends, for
+//!   streaming APIs that resume from the last event a client saw
+//!
+//! The journal is in memory and bounded: each execution keeps its latest
+//! `MAX_EVENTS_PER_EXECUTION` events and only the latest
+//! `MAX_EXECUTIONS` executions are kept.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::VecDeque;
+use tauri::{AppHandle, Emitter};
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use super::events::{WorkflowEvent, WORKFLOW_EVENT_NAME};
+use super::env_vars::EXECUTION_ENVS;
+use super::execution_log::EXECUTION_LOG;
+
+pub const MAX_EVENTS_PER_EXECUTION: usize = 10_000;
+pub const MAX_EXECUTIONS: usize = 200;
+
+/// Live events buffered per subscriber before it starts lagging
+const LIVE_CAPACITY: usize = 1024;
+
+/// An event with its place in the execution's journal
+#[derive(Debug, Clone, Serialize)]
+pub struct JournaledEvent {
+    /// Starts at 1 for each execution
+    pub seq: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: WorkflowEvent,
+}
+
+/// Events after a sequence number
+#[derive(Debug, Clone, Serialize)]
+pub struct EventsSince {
+    pub events: Vec<JournaledEvent>,
+    /// Sequence number of the newest event (0 if there are none)
+    pub last_seq: u64,
+    /// Older events were dropped and can't be replayed
+    pub truncated: bool,
+    /// The execution has finished; no more events will follow
+    pub finished: bool,
+}
+
+struct ExecutionJournal {
+    events: VecDeque<JournaledEvent>,
+    last_seq: u64,
+    started_at: DateTime<Utc>,
+    finished: bool,
+}
+
+impl ExecutionJournal {
+    fn new() -> Self {
+        Self {
+            events: VecDeque::new(),
+            last_seq: 0,
+            started_at: Utc::now(),
+            finished: false,
+        }
+    }
+
+    fn since(&self, seq: u64) -> EventsSince {
+        let oldest = self.events.front().map_or(self.last_seq + 1, |e| e.seq);
+        EventsSince {
+            events: self.events.iter().filter(|e| e.seq > seq).cloned().collect(),
+            last_seq: self.last_seq,
+            truncated: seq + 1 < oldest,
+            finished: self.finished,
+        }
+    }
+}
+
+/// Journals of recent executions
+pub struct EventJournal {
+    executions: DashMap<Uuid, ExecutionJournal>,
+    live: broadcast::Sender<JournaledEvent>,
+}
+
+impl EventJournal {
+    pub fn new() -> Self {
+        let (live, _) = broadcast::channel(LIVE_CAPACITY);
+        Self {
+            executions: DashMap::new(),
+            live,
+        }
+    }
+
+    /// Number an event and add it to its execution's journal. Events
+    /// without a valid execution ID aren't journaled.
+    pub fn record(&self, event: WorkflowEvent) -> Option<JournaledEvent> {
+        let execution_id = Uuid::parse_str(event.execution_id()).ok()?;
+        let is_new = !self.executions.contains_key(&execution_id);
+
+        let journaled = {
+            let mut journal = self.executions.entry(execution_id).or_insert_with(ExecutionJournal::new);
+            journal.last_seq += 1;
+            journal.finished |= event.is_terminal();
+            let journaled = JournaledEvent {
+                seq: journal.last_seq,
+                timestamp: Utc::now(),
+                event,
+            };
+            journal.events.push_back(journaled.clone());
+            if journal.events.len() > MAX_EVENTS_PER_EXECUTION {
+                journal.events.pop_front();
+            }
+            // Sent while the journal is held so `subscribe_since` can't miss it
+            let _ = self.live.send(journaled.clone());
+            journaled
+        };
+
+        if is_new {
+            self.evict_oldest();
+        }
+        Some(journaled)
+    }
+
+    fn evict_oldest(&self) {
+        while self.executions.len() > MAX_EXECUTIONS {
+            let oldest = self
+                .executions
+                .iter()
+                .min_by_key(|entry| entry.started_at)
+                .map(|entry| *entry.key());
+            match oldest {
+                Some(id) => {
+                    self.executions.remove(&id);
+                }
+                None => break,
+            }
+        }
+    }
+
+    /// Events of an execution after `seq` (0 for all of them)
+    pub fn since(&self, execution_id: &Uuid, seq: u64) -> Option<EventsSince> {
+        self.executions.get(execution_id).map(|journal| journal.since(seq))
+    }
+
+    /// Events after `seq` plus a receiver for the ones that follow, with
+    /// nothing lost or repeated in between. Live events of other
+    /// executions arrive on the receiver too and must be filtered out.
+    pub fn subscribe_since(
+        &self,
+        execution_id: Uuid,
+        seq: u64,
+    ) -> (EventsSince, broadcast::Receiver<JournaledEvent>) {
+        // The entry is held while subscribing, so no event can slip in between
+        let journal = self.executions.entry(execution_id).or_insert_with(ExecutionJournal::new);
+        (journal.since(seq), self.live.subscribe())
+    }
+
+    /// Drop an execution's journal
+    pub fn remove(&self, execution_id: &Uuid) {
+        self.executions.remove(execution_id);
+    }
+}
+
+impl Default for EventJournal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref EVENT_JOURNAL: EventJournal = EventJournal::new();
+}
+
+/// Journal an event, note it in the execution log and emit it to the
+/// frontend with its sequence number
+pub fn publish(app: &AppHandle, event: WorkflowEvent) {
+    let event = match Uuid::parse_str(event.execution_id()) {
+        Ok(execution_id) => EXECUTION_ENVS.redacted(&execution_id, &event),
+        Err(_) => event,
+    };
+    EXECUTION_LOG.log_event(&event);
+    match EVENT_JOURNAL.record(event.clone()) {
+        Some(journaled) => {
+            let _ = app.emit(WORKFLOW_EVENT_NAME, &journaled);
+        }
+        None => {
+            let _ = app.emit(WORKFLOW_EVENT_NAME, &event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn progress(execution_id: Uuid, progress: u8) -> WorkflowEvent {
+        WorkflowEvent::ProgressUpdate {
+            execution_id: execution_id.to_string(),
+            completed_nodes: 0,
+            total_nodes: 1,
+            progress_percent: progress,
+        }
+    }
+
+    #[test]
+    fn test_replay_and_resume() {
+        let journal = EventJournal::new();
+        let execution = Uuid::new_v4();
+        let other = Uuid::new_v4();
+
+        for p in [10, 20, 30] {
+            journal.record(progress(execution, p)).unwrap();
+        }
+        assert_eq!(journal.record(progress(other, 50)).unwrap().seq, 1);
+
+        let all = journal.since(&execution, 0).unwrap();
+        assert_eq!(all.events.iter().map(|e| e.seq).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(all.last_seq, 3);
+        assert!(!all.truncated && !all.finished);
+
+        let (missed, mut live) = journal.subscribe_since(execution, 2);
+        assert_eq!(missed.events.len(), 1);
+        assert_eq!(missed.events[0].seq, 3);
+
+        journal
+            .record(WorkflowEvent::ExecutionCompleted {
+                execution_id: execution.to_string(),
+                workflow_id: Uuid::nil().to_string(),
+                duration_ms: 5,
+            })
+            .unwrap();
+        let next = live.try_recv().unwrap();
+        assert_eq!(next.seq, 4);
+        assert!(journal.since(&execution, 4).unwrap().finished);
+
+        // Serialized events keep the event's own fields next to the sequence
+        let json = serde_json::to_value(&next).unwrap();
+        assert_eq!(json["type"], "execution_completed");
+        assert_eq!(json["seq"], 4);
+
+        assert!(journal.since(&Uuid::new_v4(), 0).is_none());
+    }
+
+    #[test]
+    fn test_truncation_is_reported() {
+        let journal = EventJournal::new();
+        let execution = Uuid::new_v4();
+        for _ in 0..MAX_EVENTS_PER_EXECUTION + 5 {
+            journal.record(progress(execution, 1));
+        }
+
+        let from_start = journal.since(&execution, 0).unwrap();
+        assert!(from_start.truncated);
+        assert_eq!(from_start.events.len(), MAX_EVENTS_PER_EXECUTION);
+        assert_eq!(from_start.events[0].seq, 6);
+        assert!(!journal.since(&execution, 5).unwrap().truncated);
+    }
+}
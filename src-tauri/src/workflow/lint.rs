@@ -0,0 +1,296 @@
+//! Workflow linting.
+//!
+//! Checks a graph and its node configs for patterns that run but tend to
+//! go wrong, before anything is spawned. Provides:
+//! - The rules and their default severities
+//! - Per-project rule toggles and severity overrides
+//! - Findings naming the rule, the node and what to change
+
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use super::enhanced_executor::EnhancedNodeConfig;
+use super::graph::{GraphError, WorkflowGraph};
+use super::node_library;
+
+/// How much a finding matters
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintSeverity {
+    Info,
+    Warning,
+    Error,
+}
+
+/// A lint rule
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LintRule {
+    /// Every implementer node has a tester somewhere downstream
+    ImplementerWithoutTester,
+    /// Agent nodes deeper than the second level configure their own retries,
+    /// since a failure there throws away the most finished work
+    DeepNodeWithoutRetry,
+    /// Nodes with several predecessors say how to combine their outputs
+    FanInWithoutAggregation,
+    /// Nodes reference presets that are in the node library
+    UnknownPreset,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 4] = [
+        LintRule::ImplementerWithoutTester,
+        LintRule::DeepNodeWithoutRetry,
+        LintRule::FanInWithoutAggregation,
+        LintRule::UnknownPreset,
+    ];
+
+    pub fn default_severity(self) -> LintSeverity {
+        match self {
+            LintRule::ImplementerWithoutTester => LintSeverity::Warning,
+            LintRule::DeepNodeWithoutRetry => LintSeverity::Info,
+            LintRule::FanInWithoutAggregation => LintSeverity::Warning,
+            LintRule::UnknownPreset => LintSeverity::Warning,
+        }
+    }
+}
+
+/// A project's rule toggles and severity overrides
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LintConfig {
+    pub disabled: Vec<LintRule>,
+    pub severities: HashMap<LintRule, LintSeverity>,
+}
+
+impl LintConfig {
+    /// The rule's severity, or `None` if it is turned off
+    pub fn severity(&self, rule: LintRule) -> Option<LintSeverity> {
+        if self.disabled.contains(&rule) {
+            return None;
+        }
+        Some(self.severities.get(&rule).copied().unwrap_or_else(|| rule.default_severity()))
+    }
+}
+
+/// One rule broken by one node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LintFinding {
+    pub rule: LintRule,
+    pub severity: LintSeverity,
+    pub node_id: String,
+    pub message: String,
+}
+
+/// Every enabled rule's findings, most severe first
+pub fn lint_workflow(
+    graph: &WorkflowGraph,
+    node_configs: &HashMap<String, EnhancedNodeConfig>,
+    config: &LintConfig,
+) -> Result<Vec<LintFinding>, GraphError> {
+    let levels = graph.compute_execution_levels()?;
+    let mut findings = Vec::new();
+
+    for rule in LintRule::ALL {
+        let Some(severity) = config.severity(rule) else {
+            continue;
+        };
+        let mut finding = |node_id: &str, message: String| {
+            findings.push(LintFinding {
+                rule,
+                severity,
+                node_id: node_id.to_string(),
+                message,
+            });
+        };
+
+        match rule {
+            LintRule::ImplementerWithoutTester => {
+                for node_id in levels.iter().flatten() {
+                    let node = &graph.nodes[node_id];
+                    if node.agent_role == "implementer"
+                        && !downstream(graph, node_id).any(|id| graph.nodes[id].agent_role == "tester")
+                    {
+                        finding(node_id, format!("Implementer '{}' is not followed by a tester", node.label));
+                    }
+                }
+            }
+            LintRule::DeepNodeWithoutRetry => {
+                for (depth, level) in levels.iter().enumerate().skip(2) {
+                    for node_id in level {
+                        let node = &graph.nodes[node_id];
+                        let has_retry = node_configs.get(node_id).is_some_and(|c| c.retry.is_some());
+                        if node.kind.is_agent() && !has_retry {
+                            finding(
+                                node_id,
+                                format!("'{}' runs at level {} without its own retry config", node.label, depth),
+                            );
+                        }
+                    }
+                }
+            }
+            LintRule::FanInWithoutAggregation => {
+                for node_id in levels.iter().flatten() {
+                    let inputs = graph.dependencies(node_id).len();
+                    let has_aggregation = node_configs.get(node_id).is_some_and(|c| c.aggregation.is_some());
+                    if inputs > 1 && !has_aggregation {
+                        finding(
+                            node_id,
+                            format!(
+                                "'{}' takes input from {} nodes but declares no aggregation strategy",
+                                graph.nodes[node_id].label, inputs
+                            ),
+                        );
+                    }
+                }
+            }
+            LintRule::UnknownPreset => {
+                for (node_id, preset_id) in node_library::unknown_presets(graph) {
+                    let node = &graph.nodes[node_id];
+                    let consequence = if node.agent_role.is_empty() {
+                        "it has no agent role of its own and won't run"
+                    } else {
+                        "it runs without the preset's prompt and config"
+                    };
+                    finding(
+                        node_id,
+                        format!("'{}' uses unknown preset '{}'; {}", node.label, preset_id, consequence),
+                    );
+                }
+            }
+        }
+    }
+
+    findings.sort_by_key(|f| std::cmp::Reverse(f.severity));
+    Ok(findings)
+}
+
+/// Every node reachable from `node_id`, excluding itself
+fn downstream<'a>(graph: &'a WorkflowGraph, node_id: &str) -> impl Iterator<Item = &'a String> {
+    let mut seen: HashSet<&String> = HashSet::new();
+    let mut pending: Vec<&String> = graph.successors.get(node_id).into_iter().flatten().collect();
+    while let Some(id) = pending.pop() {
+        if seen.insert(id) {
+            pending.extend(graph.successors.get(id).into_iter().flatten());
+        }
+    }
+    seen.into_iter()
+}
+
+/// Every project's lint config
+#[derive(Default)]
+pub struct LintConfigStore {
+    configs: DashMap<Uuid, LintConfig>,
+}
+
+impl LintConfigStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&self, project_id: Uuid, config: LintConfig) {
+        self.configs.insert(project_id, config);
+    }
+
+    /// A project's config, or the defaults if it has none
+    pub fn get(&self, project_id: &Uuid) -> LintConfig {
+        self.configs.get(project_id).map(|c| c.clone()).unwrap_or_default()
+    }
+}
+
+lazy_static! {
+    pub static ref LINT_CONFIGS: LintConfigStore = LintConfigStore::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::{NodeAggregationConfig, RetryConfig};
+    use serde_json::json;
+
+    fn graph() -> WorkflowGraph {
+        // design -> (api | ui) -> review, with only ui tested
+        WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "design", "data": {"label": "Design", "agentRole": "architect"}},
+                {"id": "api", "data": {"label": "API", "agentRole": "implementer"}},
+                {"id": "ui", "data": {"label": "UI", "agentRole": "implementer"}},
+                {"id": "ui-tests", "data": {"label": "UI tests", "agentRole": "tester"}},
+                {"id": "review", "data": {"label": "Review", "agentRole": "reviewer"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "design", "target": "api"},
+                {"id": "e2", "source": "design", "target": "ui"},
+                {"id": "e3", "source": "ui", "target": "ui-tests"},
+                {"id": "e4", "source": "api", "target": "review"},
+                {"id": "e5", "source": "ui-tests", "target": "review"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_lint_rules() {
+        let graph = graph();
+        let findings = lint_workflow(&graph, &HashMap::new(), &LintConfig::default()).unwrap();
+        let found: Vec<(LintRule, &str)> = findings.iter().map(|f| (f.rule, f.node_id.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (LintRule::ImplementerWithoutTester, "api"),
+                (LintRule::FanInWithoutAggregation, "review"),
+                (LintRule::DeepNodeWithoutRetry, "ui-tests"),
+                (LintRule::DeepNodeWithoutRetry, "review"),
+            ]
+        );
+
+        let configs = HashMap::from([
+            (
+                "review".to_string(),
+                EnhancedNodeConfig {
+                    retry: Some(RetryConfig::default()),
+                    aggregation: Some(NodeAggregationConfig::default()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "ui-tests".to_string(),
+                EnhancedNodeConfig {
+                    retry: Some(RetryConfig::default()),
+                    ..Default::default()
+                },
+            ),
+        ]);
+        let findings = lint_workflow(&graph, &configs, &LintConfig::default()).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].rule, LintRule::ImplementerWithoutTester);
+    }
+
+    #[test]
+    fn test_lint_config() {
+        let config = LintConfig {
+            disabled: vec![LintRule::DeepNodeWithoutRetry],
+            severities: HashMap::from([(LintRule::FanInWithoutAggregation, LintSeverity::Error)]),
+        };
+        let findings = lint_workflow(&graph(), &HashMap::new(), &config).unwrap();
+        assert_eq!(findings.len(), 2);
+        assert_eq!((findings[0].rule, findings[0].severity), (LintRule::FanInWithoutAggregation, LintSeverity::Error));
+        assert_eq!(findings[1].severity, LintSeverity::Warning);
+    }
+
+    #[test]
+    fn test_unknown_preset() {
+        let graph = WorkflowGraph::from_json(&json!({
+            "nodes": [{"id": "audit", "data": {"label": "Audit", "preset": "deleted-preset"}}],
+            "edges": []
+        }))
+        .unwrap();
+        let findings = lint_workflow(&graph, &HashMap::new(), &LintConfig::default()).unwrap();
+        assert_eq!(findings.len(), 1);
+        assert_eq!((findings[0].rule, findings[0].severity), (LintRule::UnknownPreset, LintSeverity::Warning));
+        assert!(findings[0].message.contains("won't run"));
+    }
+}
@@ -0,0 +1,183 @@
+//! Project locales.
+//!
+//! A project can set a locale such as `de` or `pt-BR` so its agents answer
+//! in that language and built-in templates are listed in it. Provides:
+//! - Validating and normalizing locale tags
+//! - The language instruction added to agents' system prompts
+//! - Localized names and descriptions of built-in templates, falling back
+//!   from `pt-BR` to `pt` to English
+//!
+//! Template translations live in `locales/templates.json`; a template or
+//! language missing there keeps its English text.
+
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::templates::WorkflowTemplate;
+use crate::commands::project::get_project_locale;
+
+#[derive(Deserialize)]
+struct TemplateText {
+    name: String,
+    description: String,
+}
+
+lazy_static! {
+    /// Template name and description by language, then template ID
+    static ref TEMPLATE_TEXT: HashMap<String, HashMap<String, TemplateText>> =
+        serde_json::from_str(include_str!("locales/templates.json")).expect("Invalid template translations");
+}
+
+/// English names of languages by ISO 639-1 code, for the instruction
+const LANGUAGES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("cs", "Czech"),
+    ("da", "Danish"),
+    ("de", "German"),
+    ("el", "Greek"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fi", "Finnish"),
+    ("fr", "French"),
+    ("he", "Hebrew"),
+    ("hi", "Hindi"),
+    ("hu", "Hungarian"),
+    ("id", "Indonesian"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nb", "Norwegian"),
+    ("nl", "Dutch"),
+    ("no", "Norwegian"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ro", "Romanian"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("th", "Thai"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("vi", "Vietnamese"),
+    ("zh", "Chinese"),
+];
+
+/// `locale` as a tag like `pt-BR`: a two or three letter language, then
+/// optional subtags such as a region or script
+pub fn normalize(locale: &str) -> Result<String, String> {
+    let invalid = || format!("Invalid locale '{}', expected a tag like 'de' or 'pt-BR'", locale);
+    let mut subtags = locale.trim().split(['-', '_']);
+    let language = subtags.next().unwrap_or_default();
+    if !(2..=3).contains(&language.len()) || !language.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(invalid());
+    }
+
+    let mut normalized = language.to_ascii_lowercase();
+    for subtag in subtags {
+        if subtag.is_empty() || subtag.len() > 8 || !subtag.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(invalid());
+        }
+        normalized.push('-');
+        normalized.push_str(&match subtag.len() {
+            // Regions are upper case, scripts title case
+            2 => subtag.to_ascii_uppercase(),
+            4 => format!("{}{}", subtag[..1].to_ascii_uppercase(), subtag[1..].to_ascii_lowercase()),
+            _ => subtag.to_ascii_lowercase(),
+        });
+    }
+    Ok(normalized)
+}
+
+fn language(locale: &str) -> &str {
+    locale.split('-').next().unwrap_or(locale)
+}
+
+/// The instruction that keeps agents answering in `locale`'s language;
+/// `None` for English, which agents answer in anyway
+pub fn language_instruction(locale: &str) -> Option<String> {
+    let code = language(locale).to_ascii_lowercase();
+    if code == "en" {
+        return None;
+    }
+    let name = LANGUAGES
+        .iter()
+        .find(|(language, _)| *language == code)
+        .map_or_else(|| format!("the language of locale {}", locale), |(_, name)| name.to_string());
+    Some(format!(
+        "Write all prose (answers, summaries, reports, documentation and code comments) in {} ({}). \
+         Keep code, identifiers, file paths, commands and quoted tool output unchanged.",
+        name, locale
+    ))
+}
+
+/// `system_prompt` with the language instruction of `locale` appended,
+/// unless it already has it
+pub fn with_language_instruction(system_prompt: Option<String>, locale: Option<&str>) -> Option<String> {
+    let Some(instruction) = locale.and_then(language_instruction) else {
+        return system_prompt;
+    };
+    match system_prompt {
+        Some(prompt) if prompt.contains(&instruction) => Some(prompt),
+        Some(prompt) if !prompt.trim().is_empty() => Some(format!("{}\n\n{}", prompt.trim_end(), instruction)),
+        _ => Some(instruction),
+    }
+}
+
+/// `system_prompt` for an agent working on `project_id`, in the project's
+/// language
+pub fn project_system_prompt(project_id: Option<Uuid>, system_prompt: Option<String>) -> Option<String> {
+    let locale = project_id.and_then(|id| get_project_locale(&id));
+    with_language_instruction(system_prompt, locale.as_deref())
+}
+
+/// Translate a built-in template's name and description into `locale`,
+/// or the closest language that has them
+pub fn localize_template(template: &mut WorkflowTemplate, locale: &str) {
+    let mut tag = locale;
+    loop {
+        if let Some(text) = TEMPLATE_TEXT.get(tag).and_then(|templates| templates.get(&template.id)) {
+            template.name = text.name.clone();
+            template.description = text.description.clone();
+            return;
+        }
+        match tag.rsplit_once('-') {
+            Some((parent, _)) => tag = parent,
+            None => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::templates::get_builtin_templates;
+
+    #[test]
+    fn test_locales() {
+        assert_eq!(normalize("pt_br").unwrap(), "pt-BR");
+        assert_eq!(normalize(" zh-hant-tw ").unwrap(), "zh-Hant-TW");
+        assert!(normalize("english").is_err());
+        assert!(normalize("de-").is_err());
+
+        assert_eq!(with_language_instruction(Some("You review code.".to_string()), Some("en-GB")).as_deref(), Some("You review code."));
+        let german = with_language_instruction(Some("You review code.".to_string()), Some("de-AT")).unwrap();
+        assert!(german.starts_with("You review code.\n\nWrite all prose"));
+        assert!(german.contains("in German (de-AT)"));
+        assert_eq!(with_language_instruction(Some(german.clone()), Some("de-AT")), Some(german));
+        assert!(with_language_instruction(None, Some("xx")).unwrap().contains("the language of locale xx"));
+
+        // Every translation names a built-in template
+        let ids: Vec<String> = get_builtin_templates().into_iter().map(|t| t.id).collect();
+        for templates in TEMPLATE_TEXT.values() {
+            assert!(templates.keys().all(|id| ids.contains(id)));
+        }
+
+        let mut template = get_builtin_templates().into_iter().find(|t| t.id == "bug-fix").unwrap();
+        localize_template(&mut template, "pt-BR");
+        assert_eq!(template.name, "Correção de bugs");
+        let mut template = get_builtin_templates().into_iter().find(|t| t.id == "bug-fix").unwrap();
+        localize_template(&mut template, "ko");
+        assert_eq!(template.name, "Bug Fix");
+    }
+}
@@ -0,0 +1,243 @@
+//! Named locks on shared project resources.
+//!
+//! Nodes declare the resources they need (e.g. "package.json", "database")
+//! and the executor holds those locks while the node runs. Provides:
+//! - Shared (read) and exclusive (write) locks, scoped per project
+//! - Deadlock-free acquisition of several locks at once (sorted by name)
+//! - A view of who holds what, for the UI
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::{OwnedRwLockReadGuard, OwnedRwLockWriteGuard, RwLock};
+use uuid::Uuid;
+
+/// How a node holds a lock
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LockMode {
+    /// Other shared holders may run alongside
+    Shared,
+    /// No other holder may run alongside
+    #[default]
+    Exclusive,
+}
+
+/// A lock a node needs while it runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LockRequest {
+    pub name: String,
+    #[serde(default)]
+    pub mode: LockMode,
+}
+
+/// Someone currently holding a lock
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub execution_id: Uuid,
+    pub node_id: String,
+    pub mode: LockMode,
+    pub acquired_at: DateTime<Utc>,
+}
+
+/// A held lock and its holders
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub name: String,
+    pub holders: Vec<LockHolder>,
+}
+
+struct LockEntry {
+    lock: Arc<RwLock<()>>,
+    holders: Mutex<Vec<LockHolder>>,
+}
+
+/// One lock in a set; the guard that is set releases the lock on drop
+struct HeldLock {
+    name: String,
+    _shared: Option<OwnedRwLockReadGuard<()>>,
+    _exclusive: Option<OwnedRwLockWriteGuard<()>>,
+}
+
+/// Locks held by one node; released on drop
+pub struct LockSet {
+    manager: &'static LockManager,
+    project_id: Uuid,
+    execution_id: Uuid,
+    node_id: String,
+    held: Vec<HeldLock>,
+}
+
+impl Drop for LockSet {
+    fn drop(&mut self) {
+        for held in self.held.drain(..) {
+            if let Some(entry) = self.manager.locks.get(&(self.project_id, held.name.clone())) {
+                entry
+                    .holders
+                    .lock()
+                    .retain(|h| !(h.execution_id == self.execution_id && h.node_id == self.node_id));
+            }
+        }
+    }
+}
+
+/// Per-project named locks
+pub struct LockManager {
+    locks: DashMap<(Uuid, String), Arc<LockEntry>>,
+}
+
+impl LockManager {
+    pub fn new() -> Self {
+        Self { locks: DashMap::new() }
+    }
+
+    fn entry(&self, project_id: Uuid, name: &str) -> Arc<LockEntry> {
+        self.locks
+            .entry((project_id, name.to_string()))
+            .or_insert_with(|| {
+                Arc::new(LockEntry {
+                    lock: Arc::new(RwLock::new(())),
+                    holders: Mutex::new(Vec::new()),
+                })
+            })
+            .clone()
+    }
+
+    /// Wait for every requested lock. Locks are taken in name order so two
+    /// nodes needing the same locks can't deadlock; a name requested twice
+    /// is taken once, in the stronger mode.
+    pub async fn acquire(
+        &'static self,
+        project_id: Uuid,
+        execution_id: Uuid,
+        node_id: &str,
+        requests: &[LockRequest],
+    ) -> LockSet {
+        let mut wanted: Vec<LockRequest> = requests.to_vec();
+        wanted.sort_by(|a, b| a.name.cmp(&b.name).then((b.mode == LockMode::Exclusive).cmp(&(a.mode == LockMode::Exclusive))));
+        wanted.dedup_by(|later, earlier| later.name == earlier.name);
+
+        let mut set = LockSet {
+            manager: self,
+            project_id,
+            execution_id,
+            node_id: node_id.to_string(),
+            held: Vec::with_capacity(wanted.len()),
+        };
+
+        for request in wanted {
+            let entry = self.entry(project_id, &request.name);
+            let held = match request.mode {
+                LockMode::Shared => HeldLock {
+                    name: request.name,
+                    _shared: Some(entry.lock.clone().read_owned().await),
+                    _exclusive: None,
+                },
+                LockMode::Exclusive => HeldLock {
+                    name: request.name,
+                    _shared: None,
+                    _exclusive: Some(entry.lock.clone().write_owned().await),
+                },
+            };
+            entry.holders.lock().push(LockHolder {
+                execution_id,
+                node_id: node_id.to_string(),
+                mode: request.mode,
+                acquired_at: Utc::now(),
+            });
+            set.held.push(held);
+        }
+
+        set
+    }
+
+    /// Locks currently held in a project, by name
+    pub fn held(&self, project_id: &Uuid) -> Vec<LockInfo> {
+        let mut held: Vec<LockInfo> = self
+            .locks
+            .iter()
+            .filter(|entry| &entry.key().0 == project_id)
+            .filter_map(|entry| {
+                let holders = entry.value().holders.lock().clone();
+                (!holders.is_empty()).then(|| LockInfo {
+                    name: entry.key().1.clone(),
+                    holders,
+                })
+            })
+            .collect();
+        held.sort_by(|a, b| a.name.cmp(&b.name));
+        held
+    }
+}
+
+impl Default for LockManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Global lock manager, shared by all executions
+lazy_static::lazy_static! {
+    pub static ref LOCK_MANAGER: LockManager = LockManager::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn request(name: &str, mode: LockMode) -> LockRequest {
+        LockRequest { name: name.to_string(), mode }
+    }
+
+    #[tokio::test]
+    async fn test_exclusive_blocks_and_shared_coexist() {
+        lazy_static::lazy_static! {
+            static ref MANAGER: LockManager = LockManager::new();
+        }
+        let project = Uuid::new_v4();
+        let execution = Uuid::new_v4();
+
+        let readers = [request("database", LockMode::Shared)];
+        let first = MANAGER.acquire(project, execution, "a", &readers).await;
+        let second = MANAGER.acquire(project, execution, "b", &readers).await;
+        assert_eq!(MANAGER.held(&project)[0].holders.len(), 2);
+
+        let writer = [request("database", LockMode::Exclusive)];
+        let blocked = tokio::time::timeout(Duration::from_millis(50), MANAGER.acquire(project, execution, "c", &writer)).await;
+        assert!(blocked.is_err());
+
+        drop(first);
+        drop(second);
+        let held = MANAGER.acquire(project, execution, "c", &writer).await;
+        assert_eq!(MANAGER.held(&project)[0].holders[0].node_id, "c");
+
+        // Other projects have their own locks
+        let other = tokio::time::timeout(Duration::from_millis(50), MANAGER.acquire(Uuid::new_v4(), execution, "d", &writer)).await;
+        assert!(other.is_ok());
+
+        drop(held);
+        assert!(MANAGER.held(&project).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_names_take_stronger_mode() {
+        lazy_static::lazy_static! {
+            static ref MANAGER: LockManager = LockManager::new();
+        }
+        let project = Uuid::new_v4();
+
+        let requests = [
+            request("package.json", LockMode::Shared),
+            request("database", LockMode::Shared),
+            request("package.json", LockMode::Exclusive),
+        ];
+        let _set = MANAGER.acquire(project, Uuid::new_v4(), "a", &requests).await;
+
+        let held = MANAGER.held(&project);
+        assert_eq!(held.iter().map(|l| l.name.as_str()).collect::<Vec<_>>(), vec!["database", "package.json"]);
+        assert_eq!(held[1].holders[0].mode, LockMode::Exclusive);
+    }
+}
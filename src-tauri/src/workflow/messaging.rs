@@ -3,13 +3,17 @@
 //! Enables agents to:
 //! - Send direct messages to other agents
 //! - Broadcast messages to all agents
+//! - Publish to topics that agents subscribe to by role or node
 //! - Request information from other agents
 //! - Coordinate work in real-time
+//!
+//! Each bus is bounded by a message cap and an optional TTL, and can be
+//! persisted into the execution's checkpoints.
 
 use chrono::{DateTime, Utc};
 use dashmap::DashMap;
 use serde::{Deserialize, Serialize};
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use uuid::Uuid;
@@ -43,6 +47,9 @@ pub struct AgentMessage {
     pub read: bool,
     /// Reply to message ID (for threaded conversations)
     pub reply_to: Option<Uuid>,
+    /// Topic for published messages, e.g. "security-findings"
+    #[serde(default)]
+    pub topic: Option<String>,
 }
 
 /// Types of inter-agent messages
@@ -93,19 +100,104 @@ pub enum MessageContent {
     Blocked { reason: String, blocked_by: Option<String> },
 }
 
+impl MessageContent {
+    /// Render the content for an agent's prompt
+    pub fn to_context_string(&self) -> String {
+        match self {
+            MessageContent::Text(s) => s.clone(),
+            MessageContent::Json(v) => serde_json::to_string_pretty(v).unwrap_or_default(),
+            MessageContent::Code { language, content } => format!("```{}\n{}\n```", language, content),
+            MessageContent::FileReference { path, description } => match description {
+                Some(d) => format!("File: {} ({})", path, d),
+                None => format!("File: {}", path),
+            },
+            MessageContent::Progress { current, total, message } => format!("[{}/{}] {}", current, total, message),
+            MessageContent::ActionRequest { action, parameters } => format!("Request: {} {}", action, parameters),
+            MessageContent::ActionResponse { success, result, error } => match (success, result, error) {
+                (true, Some(r), _) => format!("Succeeded: {}", r),
+                (true, None, _) => "Succeeded".to_string(),
+                (false, _, Some(e)) => format!("Failed: {}", e),
+                (false, _, None) => "Failed".to_string(),
+            },
+            MessageContent::DependencyReady { node_id, .. } => format!("Dependency {} is ready", node_id),
+            MessageContent::Blocked { reason, blocked_by } => match blocked_by {
+                Some(b) => format!("Blocked by {}: {}", b, reason),
+                None => format!("Blocked: {}", reason),
+            },
+        }
+    }
+}
+
 /// Message priority levels
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum MessagePriority {
     Low = 0,
+    #[default]
     Normal = 1,
     High = 2,
     Urgent = 3,
 }
 
-impl Default for MessagePriority {
+/// Who receives a topic's messages
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TopicSubscriber {
+    /// Every agent with this role
+    Role(String),
+    /// The agent running this node
+    Node(String),
+}
+
+/// Narrows unread messages; unset fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MessageFilter {
+    #[serde(default)]
+    pub topic: Option<String>,
+    #[serde(default)]
+    pub message_type: Option<MessageType>,
+    #[serde(default)]
+    pub from_node_id: Option<String>,
+    #[serde(default)]
+    pub min_priority: Option<MessagePriority>,
+}
+
+impl MessageFilter {
+    pub fn matches(&self, message: &AgentMessage) -> bool {
+        self.topic.as_ref().map_or(true, |t| message.topic.as_ref() == Some(t))
+            && self.message_type.as_ref().map_or(true, |t| &message.message_type == t)
+            && self.from_node_id.as_ref().map_or(true, |n| &message.from_node_id == n)
+            && self.min_priority.map_or(true, |p| message.priority >= p)
+    }
+}
+
+const DEFAULT_MAX_MESSAGES: usize = 10_000;
+
+fn default_max_messages() -> usize {
+    DEFAULT_MAX_MESSAGES
+}
+
+/// Limits and persistence for one execution's message bus
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MessageBusConfig {
+    /// Messages kept; the oldest are dropped beyond this
+    #[serde(default = "default_max_messages")]
+    pub max_messages: usize,
+    /// Drop messages older than this
+    #[serde(default)]
+    pub ttl_ms: Option<u64>,
+    /// Save the bus's messages with each checkpoint of the execution
+    #[serde(default)]
+    pub persist: bool,
+}
+
+impl Default for MessageBusConfig {
     fn default() -> Self {
-        Self::Normal
+        Self {
+            max_messages: DEFAULT_MAX_MESSAGES,
+            ttl_ms: None,
+            persist: false,
+        }
     }
 }
 
@@ -119,22 +211,39 @@ pub struct MessageBus {
     inboxes: DashMap<Uuid, VecDeque<Uuid>>,
     /// Broadcast channel for real-time notifications
     broadcast_tx: broadcast::Sender<AgentMessage>,
-    /// Maximum messages to keep
-    max_messages: usize,
+    /// Message cap, TTL and persistence
+    config: MessageBusConfig,
+    /// Subscribers per topic
+    subscriptions: DashMap<String, HashSet<TopicSubscriber>>,
+    /// Node and role of registered agents, for topic routing
+    members: DashMap<Uuid, (String, String)>,
+    /// Messages already put into each node's prompt
+    delivered: DashMap<String, HashSet<Uuid>>,
 }
 
 impl MessageBus {
     pub fn new(execution_id: Uuid) -> Self {
+        Self::with_config(execution_id, MessageBusConfig::default())
+    }
+
+    pub fn with_config(execution_id: Uuid, config: MessageBusConfig) -> Self {
         let (broadcast_tx, _) = broadcast::channel(1000);
         Self {
             execution_id,
             messages: DashMap::new(),
             inboxes: DashMap::new(),
             broadcast_tx,
-            max_messages: 10000,
+            config,
+            subscriptions: DashMap::new(),
+            members: DashMap::new(),
+            delivered: DashMap::new(),
         }
     }
 
+    pub fn config(&self) -> &MessageBusConfig {
+        &self.config
+    }
+
     /// Send a message
     pub fn send(&self, mut message: AgentMessage) -> Uuid {
         message.id = Uuid::new_v4();
@@ -151,8 +260,16 @@ impl MessageBus {
         if let Some(to_agent_id) = message.to_agent_id {
             self.inboxes
                 .entry(to_agent_id)
-                .or_insert_with(VecDeque::new)
+                .or_default()
                 .push_back(message_id);
+        } else if let Some(topic) = &message.topic {
+            // Topic - add to subscribed agents' inboxes
+            for member in self.members.iter() {
+                let (node_id, role) = member.value();
+                if self.is_subscribed(topic, node_id, role) {
+                    self.inboxes.entry(*member.key()).or_default().push_back(message_id);
+                }
+            }
         } else {
             // Broadcast - add to all inboxes
             for mut inbox in self.inboxes.iter_mut() {
@@ -163,10 +280,7 @@ impl MessageBus {
         // Notify subscribers
         let _ = self.broadcast_tx.send(message);
 
-        // Cleanup if needed
-        if self.messages.len() > self.max_messages {
-            self.cleanup_old_messages();
-        }
+        self.prune();
 
         message_id
     }
@@ -195,10 +309,12 @@ impl MessageBus {
             priority: MessagePriority::Normal,
             read: false,
             reply_to: None,
+            topic: None,
         })
     }
 
     /// Send a request message and return the message ID for tracking responses
+    #[allow(clippy::too_many_arguments)]
     pub fn send_request(
         &self,
         from_agent_id: Uuid,
@@ -226,10 +342,12 @@ impl MessageBus {
             priority: MessagePriority::High,
             read: false,
             reply_to: None,
+            topic: None,
         })
     }
 
     /// Send a response to a request
+    #[allow(clippy::too_many_arguments)]
     pub fn send_response(
         &self,
         from_agent_id: Uuid,
@@ -256,6 +374,7 @@ impl MessageBus {
             priority: MessagePriority::High,
             read: false,
             reply_to: Some(reply_to),
+            topic: None,
         })
     }
 
@@ -281,11 +400,78 @@ impl MessageBus {
             priority: MessagePriority::Normal,
             read: false,
             reply_to: None,
+            topic: None,
+        })
+    }
+
+    /// Publish to a topic; subscribers see it in their inbox and next prompt
+    pub fn publish(
+        &self,
+        from_agent_id: Uuid,
+        from_node_id: &str,
+        from_role: &str,
+        topic: &str,
+        message_type: MessageType,
+        content: MessageContent,
+    ) -> Uuid {
+        self.send(AgentMessage {
+            id: Uuid::nil(),
+            execution_id: self.execution_id,
+            from_agent_id,
+            from_node_id: from_node_id.to_string(),
+            from_role: from_role.to_string(),
+            to_agent_id: None,
+            to_node_id: None,
+            message_type,
+            content,
+            timestamp: Utc::now(),
+            priority: MessagePriority::Normal,
+            read: false,
+            reply_to: None,
+            topic: Some(topic.to_string()),
+        })
+    }
+
+    /// Subscribe a role or node to a topic
+    pub fn subscribe_topic(&self, topic: &str, subscriber: TopicSubscriber) {
+        self.subscriptions.entry(topic.to_string()).or_default().insert(subscriber);
+    }
+
+    /// Whether a node, or its role, subscribes to a topic
+    pub fn is_subscribed(&self, topic: &str, node_id: &str, role: &str) -> bool {
+        self.subscriptions.get(topic).is_some_and(|subscribers| {
+            subscribers.iter().any(|s| match s {
+                TopicSubscriber::Role(r) => r == role,
+                TopicSubscriber::Node(n) => n == node_id,
+            })
         })
     }
 
+    /// Messages a node has not seen yet: sent to it directly, broadcast, or
+    /// published on a topic it or its role subscribes to. Each message is
+    /// returned once per node, for its next prompt.
+    pub fn deliver_to_node(&self, node_id: &str, role: &str) -> Vec<AgentMessage> {
+        let mut delivered = self.delivered.entry(node_id.to_string()).or_default();
+
+        let pending: Vec<AgentMessage> = self
+            .get_all_messages()
+            .into_iter()
+            .filter(|m| m.from_node_id != node_id && !delivered.contains(&m.id))
+            .filter(|m| match (&m.to_node_id, m.to_agent_id, &m.topic) {
+                (Some(to), _, _) => to == node_id,
+                (None, Some(_), _) => false,
+                (None, None, Some(topic)) => self.is_subscribed(topic, node_id, role),
+                (None, None, None) => true,
+            })
+            .collect();
+
+        delivered.extend(pending.iter().map(|m| m.id));
+        pending
+    }
+
     /// Get messages for an agent
     pub fn get_inbox(&self, agent_id: &Uuid) -> Vec<AgentMessage> {
+        self.prune();
         self.inboxes
             .get(agent_id)
             .map(|inbox| {
@@ -299,9 +485,14 @@ impl MessageBus {
 
     /// Get unread messages for an agent
     pub fn get_unread(&self, agent_id: &Uuid) -> Vec<AgentMessage> {
+        self.get_unread_filtered(agent_id, &MessageFilter::default())
+    }
+
+    /// Get unread messages for an agent that match a filter
+    pub fn get_unread_filtered(&self, agent_id: &Uuid, filter: &MessageFilter) -> Vec<AgentMessage> {
         self.get_inbox(agent_id)
             .into_iter()
-            .filter(|m| !m.read)
+            .filter(|m| !m.read && filter.matches(m))
             .collect()
     }
 
@@ -322,8 +513,9 @@ impl MessageBus {
 
     /// Get all messages in the execution
     pub fn get_all_messages(&self) -> Vec<AgentMessage> {
+        self.prune();
         let mut messages: Vec<_> = self.messages.iter().map(|m| m.clone()).collect();
-        messages.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        messages.sort_by_key(|a| a.timestamp);
         messages
     }
 
@@ -350,7 +542,7 @@ impl MessageBus {
             }
         }
 
-        thread.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+        thread.sort_by_key(|a| a.timestamp);
         thread
     }
 
@@ -361,7 +553,14 @@ impl MessageBus {
 
     /// Register an agent's inbox
     pub fn register_agent(&self, agent_id: Uuid) {
-        self.inboxes.entry(agent_id).or_insert_with(VecDeque::new);
+        self.inboxes.entry(agent_id).or_default();
+    }
+
+    /// Register an agent's inbox along with the node and role it runs as,
+    /// so it receives topic messages for them
+    pub fn register_member(&self, agent_id: Uuid, node_id: &str, role: &str) {
+        self.register_agent(agent_id);
+        self.members.insert(agent_id, (node_id.to_string(), role.to_string()));
     }
 
     /// Get conversation between two agents
@@ -375,16 +574,46 @@ impl MessageBus {
             .collect()
     }
 
-    /// Clean up old messages
-    fn cleanup_old_messages(&self) {
-        let mut messages: Vec<_> = self.messages.iter().map(|m| (m.id, m.timestamp)).collect();
-        messages.sort_by(|a, b| a.1.cmp(&b.1));
+    /// Drop expired messages, then the oldest beyond the cap; returns how many were dropped
+    pub fn prune(&self) -> usize {
+        let mut dropped: Vec<Uuid> = Vec::new();
 
-        // Remove oldest 20%
-        let to_remove = self.max_messages / 5;
-        for (id, _) in messages.into_iter().take(to_remove) {
-            self.messages.remove(&id);
+        if let Some(ttl_ms) = self.config.ttl_ms {
+            let cutoff = Utc::now() - chrono::Duration::milliseconds(ttl_ms as i64);
+            dropped.extend(self.messages.iter().filter(|m| m.timestamp < cutoff).map(|m| m.id));
+            for id in &dropped {
+                self.messages.remove(id);
+            }
         }
+
+        if self.messages.len() > self.config.max_messages {
+            let mut messages: Vec<_> = self.messages.iter().map(|m| (m.id, m.timestamp)).collect();
+            messages.sort_by_key(|a| a.1);
+
+            let excess = messages.len() - self.config.max_messages;
+            for (id, _) in messages.into_iter().take(excess) {
+                self.messages.remove(&id);
+                dropped.push(id);
+            }
+        }
+
+        if !dropped.is_empty() {
+            for mut inbox in self.inboxes.iter_mut() {
+                inbox.retain(|id| self.messages.contains_key(id));
+            }
+        }
+
+        dropped.len()
+    }
+
+    /// Drop every message, keeping registered inboxes; returns how many were dropped
+    pub fn clear(&self) -> usize {
+        let count = self.messages.len();
+        self.messages.clear();
+        for mut inbox in self.inboxes.iter_mut() {
+            inbox.clear();
+        }
+        count
     }
 }
 
@@ -402,7 +631,12 @@ impl MessageBusStore {
 
     /// Create a message bus for an execution
     pub fn create(&self, execution_id: Uuid) -> Arc<MessageBus> {
-        let bus = Arc::new(MessageBus::new(execution_id));
+        self.create_with_config(execution_id, MessageBusConfig::default())
+    }
+
+    /// Create a message bus with its own limits
+    pub fn create_with_config(&self, execution_id: Uuid, config: MessageBusConfig) -> Arc<MessageBus> {
+        let bus = Arc::new(MessageBus::with_config(execution_id, config));
         self.buses.insert(execution_id, bus.clone());
         bus
     }
@@ -416,6 +650,11 @@ impl MessageBusStore {
     pub fn remove(&self, execution_id: &Uuid) -> Option<Arc<MessageBus>> {
         self.buses.remove(execution_id).map(|(_, b)| b)
     }
+
+    /// Remove an execution's bus and drop its messages; returns how many were dropped
+    pub fn purge(&self, execution_id: &Uuid) -> usize {
+        self.remove(execution_id).map_or(0, |bus| bus.clear())
+    }
 }
 
 impl Default for MessageBusStore {
@@ -424,6 +663,31 @@ impl Default for MessageBusStore {
     }
 }
 
+/// Prompt section listing messages for an agent
+pub fn format_for_prompt(messages: &[AgentMessage]) -> String {
+    if messages.is_empty() {
+        return String::new();
+    }
+
+    let mut section = String::from("=== Messages from Other Agents ===\n\n");
+    for message in messages {
+        let topic = message.topic.as_ref().map(|t| format!(" on {}", t)).unwrap_or_default();
+        section.push_str(&format!(
+            "--- From {} ({}){} ---\n{}\n\n",
+            message.from_node_id,
+            message.from_role,
+            topic,
+            message.content.to_context_string()
+        ));
+    }
+    section
+}
+
+// Global message bus store, shared by the executor and commands
+lazy_static::lazy_static! {
+    pub static ref MESSAGE_BUS_STORE: MessageBusStore = MessageBusStore::new();
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -488,7 +752,7 @@ mod tests {
             serde_json::json!({"component": "auth"}),
         );
 
-        let response_id = bus.send_response(
+        bus.send_response(
             agent2,
             "node-2",
             "implementer",
@@ -503,4 +767,88 @@ mod tests {
         let thread = bus.get_thread(&request_id);
         assert_eq!(thread.len(), 2);
     }
+
+    #[test]
+    fn test_message_cap_drops_oldest() {
+        let config = MessageBusConfig {
+            max_messages: 2,
+            ..Default::default()
+        };
+        let bus = MessageBus::with_config(Uuid::new_v4(), config);
+        let agent1 = Uuid::new_v4();
+        let agent2 = Uuid::new_v4();
+        bus.register_agent(agent2);
+
+        let first = bus.send_text(agent1, "node-1", "architect", Some(agent2), Some("node-2"), "one");
+        bus.send_text(agent1, "node-1", "architect", Some(agent2), Some("node-2"), "two");
+        bus.send_text(agent1, "node-1", "architect", Some(agent2), Some("node-2"), "three");
+
+        assert_eq!(bus.get_all_messages().len(), 2);
+        assert!(bus.get_message(&first).is_none());
+        assert_eq!(bus.get_inbox(&agent2).len(), 2);
+    }
+
+    #[test]
+    fn test_ttl_expiry_and_purge() {
+        let config = MessageBusConfig {
+            ttl_ms: Some(60_000),
+            ..Default::default()
+        };
+        let store = MessageBusStore::new();
+        let execution_id = Uuid::new_v4();
+        let bus = store.create_with_config(execution_id, config);
+        let agent = Uuid::new_v4();
+
+        let stale = bus.broadcast_status(agent, "node-1", "architect", "started");
+        bus.messages.get_mut(&stale).unwrap().timestamp = Utc::now() - chrono::Duration::minutes(5);
+        bus.broadcast_status(agent, "node-1", "architect", "still going");
+
+        assert_eq!(bus.get_all_messages().len(), 1);
+        assert!(bus.get_message(&stale).is_none());
+
+        assert_eq!(store.purge(&execution_id), 1);
+        assert!(store.get(&execution_id).is_none());
+        assert_eq!(store.purge(&execution_id), 0);
+    }
+
+    #[test]
+    fn test_topics_route_by_role_and_node() {
+        let bus = MessageBus::new(Uuid::new_v4());
+        let auditor = Uuid::new_v4();
+        let implementer = Uuid::new_v4();
+        let tester = Uuid::new_v4();
+
+        bus.register_member(implementer, "impl", "implementer");
+        bus.register_member(tester, "qa", "tester");
+        bus.subscribe_topic("security-findings", TopicSubscriber::Role("implementer".to_string()));
+        bus.subscribe_topic("security-findings", TopicSubscriber::Node("docs".to_string()));
+
+        bus.publish(
+            auditor,
+            "audit",
+            "security",
+            "security-findings",
+            MessageType::Data,
+            MessageContent::Text("Token is logged in plain text".to_string()),
+        );
+        bus.broadcast_status(auditor, "audit", "security", "Audit finished");
+
+        assert_eq!(bus.get_inbox(&implementer).len(), 2);
+        assert_eq!(bus.get_inbox(&tester).len(), 1);
+
+        let filter = MessageFilter {
+            topic: Some("security-findings".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(bus.get_unread_filtered(&implementer, &filter).len(), 1);
+        assert!(bus.get_unread_filtered(&tester, &filter).is_empty());
+
+        // Nodes get each message once, in their next prompt
+        let docs = bus.deliver_to_node("docs", "writer");
+        assert_eq!(docs.len(), 2);
+        assert!(format_for_prompt(&docs).contains("--- From audit (security) on security-findings ---"));
+        assert!(bus.deliver_to_node("docs", "writer").is_empty());
+        assert_eq!(bus.deliver_to_node("qa", "tester").len(), 1);
+        assert!(bus.deliver_to_node("audit", "security").is_empty());
+    }
 }
@@ -1,38 +1,129 @@
+pub mod activity;
 pub mod adaptive;
 pub mod aggregation;
+pub mod anomaly;
+pub mod approvals;
+pub mod assertions;
+pub mod audit;
+pub mod auto_cancel;
+pub mod batch;
+pub mod chaos;
 pub mod checkpoint;
+pub mod concurrency;
 pub mod conditions;
+pub mod conflicts;
 pub mod context;
+pub mod dataset;
+pub mod develop;
 pub mod enhanced_executor;
+pub mod env_vars;
+pub mod environment;
+pub mod evaluation;
 pub mod events;
+pub mod execution_log;
 pub mod executor;
+pub mod forecast;
+pub mod gates;
 pub mod graph;
+pub mod guardrails;
 pub mod history;
+pub mod import;
+pub mod journal;
+pub mod lint;
+pub mod locale;
+pub mod locks;
 pub mod messaging;
+pub mod model_pins;
+pub mod node_library;
 pub mod orchestrator;
+pub mod output_format;
+pub mod patch;
+pub mod plugins;
+pub mod project_health;
+pub mod prompt_performance;
+pub mod prompt_preview;
+pub mod quick_search;
+pub mod rate_limit;
+pub mod report;
 pub mod resources;
+pub mod result_cache;
 pub mod retry;
+pub mod rollback;
+pub mod run_presets;
+pub mod schema;
+pub mod script;
+pub mod self_correction;
+pub mod share;
+pub mod simulation;
+pub mod sla;
+pub mod stages;
 pub mod state;
+pub mod streaming;
+pub mod supply_chain;
 pub mod templates;
+pub mod test_results;
+pub mod text;
+pub mod tokenizer;
 
 // Core exports
 pub use events::WorkflowEvent;
 pub use executor::WorkflowExecutor;
-pub use graph::{GraphError, ParsedEdge, ParsedNode, WorkflowGraph};
+pub use graph::{CycleDiagnosis, GraphError, NodeKind, ParsedEdge, ParsedNode, ParsedStage, WorkflowGraph};
 pub use orchestrator::{OrchestratorPlan, PlannedTask};
+pub use schema::{SchemaViolation, GRAPH_SCHEMA_VERSION};
 pub use state::{ExecutionStatus, ExecutionStore, NodeExecutionStatus, WorkflowExecutionState};
 
 // Enhanced orchestration exports
+pub use activity::{Activity, ActivityEntry, ActivityStore, FileChange, ACTIVITY_STORE};
 pub use adaptive::{AdaptivePlanningConfig, PlanModification, ReplanRequest, ReplanResult, ReplanTrigger};
-pub use aggregation::{AggregatedOutput, AggregationStrategy, NodeAggregationConfig};
+pub use aggregation::{AggregatedOutput, AggregationPreview, AggregationStrategy, NodeAggregationConfig, OutputTransform, SampleOutput};
+pub use anomaly::{Anomaly, AnomalyAlert, AnomalyKind, AnomalyThresholds};
+pub use approvals::{ApprovalDecision, ApprovalRequest, ApprovalStore, APPROVAL_STORE};
+pub use assertions::{AssertionCheck, AssertionOutcome, CheckResult};
+pub use audit::{AuditAction, AuditEntry, AuditLog, AUDIT_LOG};
+pub use auto_cancel::{AutoCancelOverride, AutoCancelPolicy, AUTO_CANCEL_POLICIES};
+pub use chaos::ChaosConfig;
 pub use checkpoint::{CheckpointManager, CheckpointSummary, ExecutionCheckpoint, ResumeOptions};
-pub use conditions::{ConditionResult, EdgeType, ExecutionCondition};
-pub use context::{AgentOutput, ContextStore, ExecutionContext, OutputData};
-pub use enhanced_executor::{EnhancedExecutionConfig, EnhancedNodeConfig, EnhancedWorkflowExecutor};
-pub use retry::{FallbackStrategy, RetryConfig, RetryDecision, RetryResult, RetryState};
+pub use concurrency::{ExecutionConcurrency, ExecutionGroups, GroupInfo, GroupPolicy, EXECUTION_GROUPS};
+pub use conditions::{ConditionResult, ConsultedValue, EdgeType, ExecutionCondition, NodeDecision};
+pub use conflicts::{ConflictPolicy, FileConflict};
+pub use context::{AgentOutput, ContextStore, ExecutionContext, NodeTranscript, OutputData};
+pub use enhanced_executor::{DeadlineConfig, EnhancedExecutionConfig, EnhancedNodeConfig, EnhancedWorkflowExecutor, WorkflowIdentity};
+pub use env_vars::{EnvVar, ExecutionEnv, ExecutionEnvStore, EXECUTION_ENVS};
+pub use environment::EnvironmentFingerprint;
+pub use model_pins::{ModelPins, ModelSubstitution, SubstitutionPolicy};
+pub use node_library::{NodePreset, PresetNodeConfig, NODE_LIBRARY};
+pub use result_cache::{NodeCacheConfig, RESULT_CACHE};
+pub use rollback::{RollbackAction, RollbackFile, RollbackPreview};
+pub use retry::{FallbackStrategy, RetryConfig, RetryDecision, RetryPromptMode, RetryResult, RetryState};
+pub use script::{ScriptInput, ScriptLimits, ScriptResult};
+pub use self_correction::{SelfCorrectionConfig, TestRun};
+pub use share::{ShareBundle, ShareFormat};
+pub use simulation::{ConditionSimulation, Scenario, SimulatedNode};
+pub use sla::{RuleStatus, SlaAlert, SlaRule, SlaStatus, SlaStore, SLA_STORE};
+pub use stages::{StageConfig, StageGate, StageProgress};
+pub use streaming::{PublishedSection, Publications, PUBLISHED_TAG};
+pub use supply_chain::{AddedDependency, Dependency, Ecosystem, SupplyChainReport, SupplyChainReports, SUPPLY_CHAIN_REPORTS};
 
 // Additional feature exports
-pub use history::{ExecutionHistoryStore, ExecutionRecord, HistoryStatistics, TimelineEvent, TimelineEventType};
-pub use messaging::{AgentMessage, MessageBus, MessageBusStore, MessageContent, MessagePriority, MessageType};
-pub use resources::{QueuedTask, ResourceConfig, ResourceError, ResourceManager, ResourceStatsSnapshot, TaskPriority};
-pub use templates::{TemplateCategory, TemplateVariable, VariableType, WorkflowTemplate, get_builtin_templates, get_template, get_templates_by_category, search_templates};
+pub use batch::{BatchBackend, BatchHandle, BatchItem, BatchItemRecord, BatchRecord, BatchReport, BatchStore};
+pub use dataset::{DatasetError, DatasetExport, DatasetFilter, ExecutionTranscripts, Redactor};
+pub use gates::{GateMetrics, GateResult, GateThresholdStore, GateThresholds, GateTool, GATE_THRESHOLDS};
+pub use forecast::{EstimateBasis, EstimateConfig, ExecutionEstimate, ModelPrice, NodeEstimate};
+pub use execution_log::{ExecutionLogStore, LogEntry, LogLevel, LogLevels, EXECUTION_LOG};
+pub use evaluation::{AssertionStats, CaseResult, EvalCase, EvaluationReport, EvaluationStore, EvaluationSuite};
+pub use import::{ImportError, ImportFormat, ImportedWorkflow};
+pub use journal::{EventJournal, EventsSince, JournaledEvent, EVENT_JOURNAL};
+pub use lint::{LintConfig, LintFinding, LintRule, LintSeverity, LINT_CONFIGS};
+pub use locks::{LockHolder, LockInfo, LockManager, LockMode, LockRequest, LOCK_MANAGER};
+pub use history::{AnnotationSeverity, ExecutionHistoryStore, ExecutionRecord, HistoryStatistics, NodeAnnotation, NodeRating, Rating, TimelineEvent, TimelineEventType};
+pub use output_format::OutputFormat;
+pub use patch::{FilePatch, FileReport, Hunk, HunkOutcome, PatchMode, PatchReport};
+pub use messaging::{AgentMessage, MessageBus, MessageBusConfig, MessageBusStore, MessageContent, MessageFilter, MessagePriority, MessageType, TopicSubscriber, MESSAGE_BUS_STORE};
+pub use plugins::{PluginError, PluginInfo, PluginKind, PluginRegistry, PLUGIN_REGISTRY};
+pub use report::{resolve_output_path, ExecutionReport, ReportFormat};
+pub use rate_limit::{credential_label, CredentialRateLimit, RateLimit, RateLimitKey, RateLimitStatus};
+pub use resources::{FairShare, QueuePolicy, QueuedTask, QueuedTaskInfo, ResourceConfig, ResourceError, ResourceManager, ResourceStatsSnapshot, TaskPriority};
+pub use test_results::{TestFormat, TestResults};
+pub use tokenizer::{count_tokens, Encoding, TokenCount, TOKENIZERS};
+pub use templates::{SubstitutionReport, TemplateCategory, TemplateNodeConfig, TemplateVariable, VariableType, WorkflowTemplate, get_builtin_templates, get_template, get_templates_by_category, search_templates};
@@ -0,0 +1,192 @@
+//! Model versions pinned per agent role.
+//!
+//! A workflow can pin the model each role's agents run on, so its results
+//! don't shift when the CLI's default model changes. Pins are applied when
+//! an execution starts; a pinned model the backend doesn't offer fails the
+//! start or is substituted, as the workflow's policy says. Without a list
+//! of the models the backend offers pins can't be checked, and an
+//! execution that needs them doesn't start. Provides:
+//! - Pins and the substitution policy
+//! - Applying pins to the node configs of a graph
+//! - The substitutions made, for the execution record
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::enhanced_executor::EnhancedNodeConfig;
+use super::graph::WorkflowGraph;
+
+/// What to do when a pinned model isn't available
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SubstitutionPolicy {
+    /// Don't start the execution
+    #[default]
+    Fail,
+    /// Use the first available alternative listed for the pinned model,
+    /// failing if there is none
+    Substitute { alternatives: HashMap<String, Vec<String>> },
+    /// Let agents run on the backend's default model
+    UseDefault,
+}
+
+/// A workflow's model pins
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ModelPins {
+    /// Model by agent role, e.g. `{"implementer": "claude-sonnet-4-20250514"}`
+    pub roles: HashMap<String, String>,
+    pub on_unavailable: SubstitutionPolicy,
+}
+
+/// A pinned model that was replaced for an execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModelSubstitution {
+    pub role: String,
+    pub pinned: String,
+    /// `None` when agents fell back to the backend's default
+    pub used: Option<String>,
+    pub node_ids: Vec<String>,
+}
+
+impl ModelPins {
+    fn pinned_model(&self, role: &str) -> Option<(&String, &String)> {
+        self.roles.iter().find(|(pinned_role, _)| pinned_role.eq_ignore_ascii_case(role))
+    }
+
+    /// Set the model of every agent node whose role is pinned, unless its
+    /// node config already names one. `available` lists the models the
+    /// backend offers; when it is empty nothing is known to be available,
+    /// and any pin that applies is an error.
+    pub fn apply(
+        &self,
+        graph: &WorkflowGraph,
+        node_configs: &mut HashMap<String, EnhancedNodeConfig>,
+        available: &[String],
+    ) -> Result<Vec<ModelSubstitution>, String> {
+        let is_available = |model: &str| available.iter().any(|m| m == model);
+        let mut node_ids: Vec<&String> = graph.nodes.keys().collect();
+        node_ids.sort();
+
+        let mut substitutions: Vec<ModelSubstitution> = Vec::new();
+        for node_id in node_ids {
+            let node = &graph.nodes[node_id];
+            let Some((role, pinned)) = self.pinned_model(&node.agent_role) else {
+                continue;
+            };
+            if !node.kind.is_agent() || node_configs.get(node_id).is_some_and(|c| c.model.is_some()) {
+                continue;
+            }
+            if available.is_empty() {
+                return Err(format!(
+                    "Model '{}' is pinned for role '{}', but the models the backend offers are unknown; \
+                     list them in the backend settings' available models",
+                    pinned, role
+                ));
+            }
+
+            let used = if is_available(pinned) {
+                Some(pinned.clone())
+            } else {
+                let used = match &self.on_unavailable {
+                    SubstitutionPolicy::Fail => {
+                        return Err(format!("Model '{}' pinned for role '{}' is not available", pinned, role));
+                    }
+                    SubstitutionPolicy::Substitute { alternatives } => {
+                        let alternative = alternatives
+                            .get(pinned)
+                            .and_then(|models| models.iter().find(|model| is_available(model)))
+                            .ok_or_else(|| {
+                                format!(
+                                    "Model '{}' pinned for role '{}' is not available, nor is any alternative to it",
+                                    pinned, role
+                                )
+                            })?;
+                        Some(alternative.clone())
+                    }
+                    SubstitutionPolicy::UseDefault => None,
+                };
+                match substitutions.iter_mut().find(|s| &s.role == role) {
+                    Some(substitution) => substitution.node_ids.push(node_id.clone()),
+                    None => substitutions.push(ModelSubstitution {
+                        role: role.clone(),
+                        pinned: pinned.clone(),
+                        used: used.clone(),
+                        node_ids: vec![node_id.clone()],
+                    }),
+                }
+                used
+            };
+            node_configs.entry(node_id.clone()).or_default().model = used;
+        }
+
+        Ok(substitutions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn graph() -> WorkflowGraph {
+        WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "design", "data": {"label": "Design", "agentRole": "architect"}},
+                {"id": "build", "data": {"label": "Build", "agentRole": "implementer"}},
+                {"id": "fix", "data": {"label": "Fix", "agentRole": "implementer"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "design", "target": "build"},
+                {"id": "e2", "source": "build", "target": "fix"}
+            ]
+        }))
+        .unwrap()
+    }
+
+    fn pins(on_unavailable: SubstitutionPolicy) -> ModelPins {
+        ModelPins {
+            roles: HashMap::from([
+                ("architect".to_string(), "opus-4".to_string()),
+                ("implementer".to_string(), "sonnet-3".to_string()),
+            ]),
+            on_unavailable,
+        }
+    }
+
+    #[test]
+    fn test_apply_pins() {
+        let available = vec!["opus-4".to_string(), "sonnet-4".to_string()];
+        let mut node_configs = HashMap::from([(
+            "fix".to_string(),
+            EnhancedNodeConfig {
+                model: Some("haiku".to_string()),
+                ..Default::default()
+            },
+        )]);
+
+        let err = pins(SubstitutionPolicy::Fail).apply(&graph(), &mut node_configs.clone(), &available).unwrap_err();
+        assert!(err.contains("'sonnet-3' pinned for role 'implementer'"));
+        // An unknown model list checks nothing, whatever the policy
+        let err = pins(SubstitutionPolicy::UseDefault).apply(&graph(), &mut node_configs.clone(), &[]).unwrap_err();
+        assert!(err.contains("models the backend offers are unknown"));
+
+        let policy = SubstitutionPolicy::Substitute {
+            alternatives: HashMap::from([("sonnet-3".to_string(), vec!["sonnet-3.5".to_string(), "sonnet-4".to_string()])]),
+        };
+        let substitutions = pins(policy).apply(&graph(), &mut node_configs, &available).unwrap();
+        assert_eq!(node_configs["design"].model.as_deref(), Some("opus-4"));
+        assert_eq!(node_configs["build"].model.as_deref(), Some("sonnet-4"));
+        // A node's own model wins over its role's pin
+        assert_eq!(node_configs["fix"].model.as_deref(), Some("haiku"));
+        assert_eq!(
+            substitutions,
+            vec![ModelSubstitution {
+                role: "implementer".to_string(),
+                pinned: "sonnet-3".to_string(),
+                used: Some("sonnet-4".to_string()),
+                node_ids: vec!["build".to_string()],
+            }]
+        );
+    }
+}
@@ -0,0 +1,468 @@
+//! Library of reusable, preconfigured nodes.
+//!
+//! Where a template is a whole workflow, a preset is one node worth
+//! reusing across workflows, like a "Rust security reviewer": its role,
+//! prompt, retries and expected output bundled under an ID. Provides:
+//! - Built-in presets, plus presets saved by users, kept in a JSON file
+//!   so workflows using them still load after a restart
+//! - Resolving a graph node that references a preset (`"preset": "<id>"` in
+//!   its data); fields the node sets itself win over the preset's
+//! - Filling the node configs of such nodes from the preset
+//! - Nodes whose preset doesn't exist (deleted, or from another machine);
+//!   they still load, lint warns about them, and they run only if they
+//!   set their own agent role
+//!
+//! Nodes keep the reference, not a copy, so editing a saved preset changes
+//! every workflow that uses it from its next run.
+
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::enhanced_executor::EnhancedNodeConfig;
+use super::graph::WorkflowGraph;
+use super::output_format::{self, OutputFormat};
+use super::retry::RetryConfig;
+
+/// Node config a preset brings; each field applies only where the node's
+/// own config leaves it unset
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PresetNodeConfig {
+    pub retry: Option<RetryConfig>,
+    pub expected_output_format: Option<OutputFormat>,
+    pub output_tags: Vec<String>,
+    /// JSON Schema the answer must match
+    pub output_schema: Option<serde_json::Value>,
+    pub model: Option<String>,
+}
+
+/// A reusable node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodePreset {
+    pub id: String,
+    pub name: String,
+    pub description: String,
+    pub agent_role: String,
+    pub system_prompt: Option<String>,
+    /// Task for nodes that don't set their own
+    #[serde(default)]
+    pub assigned_task: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub config: PresetNodeConfig,
+    /// Shipped with the app; can't be replaced or deleted
+    #[serde(default)]
+    pub built_in: bool,
+}
+
+impl NodePreset {
+    /// `config` with the fields it leaves unset taken from this preset
+    pub fn fill(&self, config: &mut EnhancedNodeConfig) {
+        if config.retry.is_none() {
+            config.retry = self.config.retry.clone();
+        }
+        if config.expected_output_format.is_none() {
+            config.expected_output_format = self.config.expected_output_format;
+        }
+        if config.output_tags.is_empty() {
+            config.output_tags = self.config.output_tags.clone();
+        }
+        if config.output_schema.is_none() {
+            config.output_schema = self.config.output_schema.clone();
+        }
+        if config.model.is_none() {
+            config.model = self.config.model.clone();
+        }
+    }
+}
+
+fn retries(max_attempts: u32) -> Option<RetryConfig> {
+    Some(RetryConfig {
+        max_attempts,
+        ..Default::default()
+    })
+}
+
+/// What the security reviewer reports: its findings, possibly none
+fn security_findings_schema() -> serde_json::Value {
+    serde_json::json!({
+        "type": "object",
+        "required": ["findings"],
+        "properties": {
+            "findings": {
+                "type": "array",
+                "items": {
+                    "type": "object",
+                    "required": ["file", "severity", "issue", "fix"],
+                    "properties": {
+                        "file": {"type": "string"},
+                        "line": {"type": "integer", "minimum": 1},
+                        "severity": {"enum": ["low", "medium", "high", "critical"]},
+                        "issue": {"type": "string"},
+                        "fix": {"type": "string"}
+                    }
+                }
+            }
+        }
+    })
+}
+
+/// Presets that ship with the app
+pub fn builtin_presets() -> Vec<NodePreset> {
+    vec![
+        NodePreset {
+            id: "rust-security-reviewer".to_string(),
+            name: "Rust security reviewer".to_string(),
+            description: "Reviews Rust changes for memory safety, unsafe blocks, panics on untrusted input and vulnerable dependencies.".to_string(),
+            agent_role: "security".to_string(),
+            system_prompt: Some(
+                "You are a security reviewer for Rust code. Audit every `unsafe` block and FFI boundary, \
+                 look for panics (unwrap, indexing, overflow) reachable from untrusted input, unchecked \
+                 deserialization, path traversal and command injection, and run `cargo audit` when it is \
+                 installed. Report each finding with its file, line, severity and a suggested fix."
+                    .to_string(),
+            ),
+            assigned_task: Some("Review the changes for security issues".to_string()),
+            tags: vec!["rust".to_string(), "security".to_string(), "review".to_string()],
+            config: PresetNodeConfig {
+                retry: retries(2),
+                expected_output_format: Some(OutputFormat::Json),
+                output_tags: vec!["security-review".to_string()],
+                output_schema: Some(security_findings_schema()),
+                model: None,
+            },
+            built_in: true,
+        },
+        NodePreset {
+            id: "postgres-migration-writer".to_string(),
+            name: "Postgres migration writer".to_string(),
+            description: "Writes reversible PostgreSQL schema migrations that are safe to run on a live database.".to_string(),
+            agent_role: "implementer".to_string(),
+            system_prompt: Some(
+                "You write PostgreSQL migrations. Put each change in a new migration file following the \
+                 project's naming scheme, with a matching down migration. Keep them safe on a live \
+                 database: create indexes concurrently, add columns as nullable or with a constant \
+                 default, and backfill in batches. Never edit a migration that has already been applied."
+                    .to_string(),
+            ),
+            assigned_task: None,
+            tags: vec!["postgres".to_string(), "database".to_string(), "migration".to_string()],
+            config: PresetNodeConfig {
+                retry: retries(2),
+                expected_output_format: Some(OutputFormat::Patch),
+                output_tags: vec!["migration".to_string()],
+                output_schema: None,
+                model: None,
+            },
+            built_in: true,
+        },
+        NodePreset {
+            id: "typescript-test-writer".to_string(),
+            name: "TypeScript test writer".to_string(),
+            description: "Adds unit tests for changed TypeScript code using the project's test runner.".to_string(),
+            agent_role: "tester".to_string(),
+            system_prompt: Some(
+                "You write unit tests for TypeScript code with the test runner the project already uses. \
+                 Cover the changed behavior, edge cases and error paths, mock only at module boundaries, \
+                 and run the tests before you finish."
+                    .to_string(),
+            ),
+            assigned_task: Some("Write tests for the changes".to_string()),
+            tags: vec!["typescript".to_string(), "testing".to_string()],
+            config: PresetNodeConfig {
+                retry: retries(3),
+                expected_output_format: None,
+                output_tags: vec!["tests".to_string()],
+                output_schema: None,
+                model: None,
+            },
+            built_in: true,
+        },
+        NodePreset {
+            id: "changelog-writer".to_string(),
+            name: "Changelog writer".to_string(),
+            description: "Summarizes the changes as user-facing changelog entries.".to_string(),
+            agent_role: "documenter".to_string(),
+            system_prompt: Some(
+                "You write changelog entries for users, not developers. Group them under Added, Changed, \
+                 Fixed and Removed, one line each, and leave out internal refactoring."
+                    .to_string(),
+            ),
+            assigned_task: Some("Write the changelog entries for these changes".to_string()),
+            tags: vec!["documentation".to_string(), "release".to_string()],
+            config: PresetNodeConfig {
+                expected_output_format: Some(OutputFormat::Markdown),
+                output_tags: vec!["changelog".to_string()],
+                ..Default::default()
+            },
+            built_in: true,
+        },
+    ]
+}
+
+/// Built-in and saved presets by ID
+pub struct NodeLibrary {
+    presets: DashMap<String, NodePreset>,
+    /// File the user presets are saved to; `None` keeps them in memory
+    path: Option<PathBuf>,
+}
+
+impl Default for NodeLibrary {
+    fn default() -> Self {
+        let presets = DashMap::new();
+        for preset in builtin_presets() {
+            presets.insert(preset.id.clone(), preset);
+        }
+        Self { presets, path: None }
+    }
+}
+
+impl NodeLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The built-in presets plus the user presets saved at `path`
+    pub fn load(path: PathBuf) -> Self {
+        let library = Self {
+            path: Some(path.clone()),
+            ..Self::default()
+        };
+        let saved: Vec<NodePreset> = match std::fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                log::warn!("Ignoring invalid node preset file {:?}: {}", path, e);
+                Vec::new()
+            }),
+            Err(_) => Vec::new(),
+        };
+        for mut preset in saved {
+            if library.presets.get(&preset.id).is_some_and(|existing| existing.built_in) {
+                log::warn!("Ignoring saved preset '{}', which is now built in", preset.id);
+                continue;
+            }
+            preset.built_in = false;
+            library.presets.insert(preset.id.clone(), preset);
+        }
+        library
+    }
+
+    pub fn default_path() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+            .join("node_presets.json")
+    }
+
+    /// Write the user presets to the library's file, if it has one
+    fn persist(&self) -> Result<(), String> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut saved: Vec<NodePreset> = self
+            .presets
+            .iter()
+            .filter(|entry| !entry.built_in)
+            .map(|entry| entry.value().clone())
+            .collect();
+        saved.sort_by(|a, b| a.id.cmp(&b.id));
+        let content = serde_json::to_string_pretty(&saved).map_err(|e| e.to_string())?;
+        path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(path, content))
+            .map_err(|e| format!("Failed to save node presets to {:?}: {}", path, e))
+    }
+
+    pub fn get(&self, id: &str) -> Option<NodePreset> {
+        self.presets.get(id).map(|entry| entry.value().clone())
+    }
+
+    /// Every preset, built-in ones first, then by name
+    pub fn list(&self) -> Vec<NodePreset> {
+        let mut presets: Vec<NodePreset> = self.presets.iter().map(|entry| entry.value().clone()).collect();
+        presets.sort_by(|a, b| b.built_in.cmp(&a.built_in).then_with(|| a.name.cmp(&b.name)));
+        presets
+    }
+
+    /// Presets whose name, description or tags contain `query`
+    pub fn search(&self, query: &str) -> Vec<NodePreset> {
+        let query = query.to_lowercase();
+        self.list()
+            .into_iter()
+            .filter(|preset| {
+                preset.name.to_lowercase().contains(&query)
+                    || preset.description.to_lowercase().contains(&query)
+                    || preset.tags.iter().any(|tag| tag.to_lowercase().contains(&query))
+            })
+            .collect()
+    }
+
+    /// Add or replace a user preset
+    pub fn save(&self, mut preset: NodePreset) -> Result<NodePreset, String> {
+        if preset.id.trim().is_empty() || preset.agent_role.trim().is_empty() {
+            return Err("A preset needs an ID and an agent role".to_string());
+        }
+        if self.presets.get(&preset.id).is_some_and(|existing| existing.built_in) {
+            return Err(format!("Preset '{}' is built in; save it under another ID", preset.id));
+        }
+        if let Some(schema) = &preset.config.output_schema {
+            output_format::validate_schema(schema)?;
+        }
+        preset.built_in = false;
+        let previous = self.presets.insert(preset.id.clone(), preset.clone());
+        if let Err(e) = self.persist() {
+            match previous {
+                Some(previous) => self.presets.insert(preset.id.clone(), previous),
+                None => self.presets.remove(&preset.id).map(|(_, preset)| preset),
+            };
+            return Err(e);
+        }
+        Ok(preset)
+    }
+
+    /// Delete a user preset; false if there was none
+    pub fn delete(&self, id: &str) -> Result<bool, String> {
+        if self.presets.get(id).is_some_and(|existing| existing.built_in) {
+            return Err(format!("Preset '{}' is built in and can't be deleted", id));
+        }
+        let Some((_, removed)) = self.presets.remove(id) else {
+            return Ok(false);
+        };
+        if let Err(e) = self.persist() {
+            self.presets.insert(removed.id.clone(), removed);
+            return Err(e);
+        }
+        Ok(true)
+    }
+}
+
+lazy_static! {
+    pub static ref NODE_LIBRARY: NodeLibrary = NodeLibrary::load(NodeLibrary::default_path());
+}
+
+/// Nodes of `graph` whose preset isn't in the library, with the preset IDs
+pub fn unknown_presets(graph: &WorkflowGraph) -> Vec<(&str, &str)> {
+    let mut unknown: Vec<(&str, &str)> = graph
+        .nodes
+        .values()
+        .filter_map(|node| Some((node.id.as_str(), node.preset.as_deref()?)))
+        .filter(|(_, preset_id)| NODE_LIBRARY.get(preset_id).is_none())
+        .collect();
+    unknown.sort();
+    unknown
+}
+
+/// Fail if a node relies on a missing preset for its agent role
+pub fn check_runnable(graph: &WorkflowGraph) -> Result<(), String> {
+    match unknown_presets(graph)
+        .into_iter()
+        .find(|(node_id, _)| graph.nodes[*node_id].agent_role.is_empty())
+    {
+        Some((node_id, preset_id)) => Err(format!(
+            "Node '{}' uses unknown preset '{}' and sets no agent role of its own",
+            node_id, preset_id
+        )),
+        None => Ok(()),
+    }
+}
+
+/// Fill the node configs of `graph`'s preset nodes from their presets. Call
+/// after the execution's own configs are parsed, so they win.
+pub fn apply_presets(graph: &WorkflowGraph, node_configs: &mut HashMap<String, EnhancedNodeConfig>) {
+    for node in graph.nodes.values() {
+        if let Some(preset) = node.preset.as_deref().and_then(|id| NODE_LIBRARY.get(id)) {
+            preset.fill(node_configs.entry(node.id.clone()).or_default());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_preset_nodes() {
+        let graph = WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "audit", "data": {"label": "Audit", "preset": "rust-security-reviewer"}},
+                {"id": "migrate", "data": {
+                    "label": "Migrate",
+                    "preset": "postgres-migration-writer",
+                    "assignedTask": "Add an orders table"
+                }}
+            ],
+            "edges": []
+        }))
+        .unwrap();
+        let audit = graph.get_node("audit").unwrap();
+        assert_eq!(audit.agent_role, "security");
+        assert_eq!(audit.assigned_task.as_deref(), Some("Review the changes for security issues"));
+        assert_eq!(graph.get_node("migrate").unwrap().assigned_task.as_deref(), Some("Add an orders table"));
+
+        // The execution's config wins where it sets a field
+        let mut node_configs = HashMap::from([(
+            "migrate".to_string(),
+            EnhancedNodeConfig {
+                output_tags: vec!["schema".to_string()],
+                ..Default::default()
+            },
+        )]);
+        apply_presets(&graph, &mut node_configs);
+        assert_eq!(node_configs["audit"].retry.as_ref().unwrap().max_attempts, 2);
+        assert_eq!(node_configs["migrate"].output_tags, vec!["schema"]);
+        assert_eq!(node_configs["migrate"].expected_output_format, Some(OutputFormat::Patch));
+
+        assert!(node_configs["audit"].output_schema.is_some());
+        assert_eq!(node_configs["audit"].output_format(), Some(OutputFormat::Json));
+
+        // A missing preset doesn't keep the workflow from loading
+        let unknown = WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "a", "data": {"label": "A", "preset": "missing"}},
+                {"id": "b", "data": {"label": "B", "preset": "missing", "agentRole": "reviewer"}}
+            ],
+            "edges": []
+        }))
+        .unwrap();
+        assert_eq!(unknown_presets(&unknown), vec![("a", "missing"), ("b", "missing")]);
+        assert!(check_runnable(&unknown).unwrap_err().contains("'a'"));
+        assert!(check_runnable(&graph).is_ok());
+
+        let library = NodeLibrary::new();
+        let mut preset = library.get("changelog-writer").unwrap();
+        assert!(library.save(preset.clone()).is_err());
+        preset.id = "release-notes".to_string();
+        assert!(!library.save(preset).unwrap().built_in);
+        assert_eq!(library.search("release").len(), 2);
+        assert!(library.delete("changelog-writer").is_err());
+        assert!(library.delete("release-notes").unwrap());
+    }
+
+    #[test]
+    fn test_saved_presets_survive_reload() {
+        let dir = std::env::temp_dir().join(format!("nexus-presets-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("node_presets.json");
+
+        let library = NodeLibrary::load(path.clone());
+        let mut preset = library.get("changelog-writer").unwrap();
+        preset.id = "release-notes".to_string();
+        preset.config.output_schema = Some(json!({"type": "array"}));
+        library.save(preset.clone()).unwrap();
+        preset.id = "bad-schema".to_string();
+        preset.config.output_schema = Some(json!({"type": 5}));
+        assert!(library.save(preset).is_err());
+
+        let reloaded = NodeLibrary::load(path.clone());
+        assert!(!reloaded.get("release-notes").unwrap().built_in);
+        assert!(reloaded.get("bad-schema").is_none());
+        assert!(reloaded.get("rust-security-reviewer").unwrap().built_in);
+        assert!(reloaded.delete("release-notes").unwrap());
+        assert!(NodeLibrary::load(path).get("release-notes").is_none());
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -5,12 +5,13 @@ use tauri::{AppHandle, Emitter, Manager};
 use uuid::Uuid;
 
 use crate::commands::project::get_project_working_directory;
-use crate::process::manager::{AgentConfig, AgentManager};
+use crate::process::manager::{AgentConfig, AgentManager, AgentOwner};
 use crate::process::AGENT_REGISTRY;
 use crate::state::AppState;
 
-use super::events::{WorkflowEvent, WORKFLOW_EVENT_NAME};
-use super::graph::{ParsedEdge, ParsedNode, WorkflowGraph};
+use super::events::WorkflowEvent;
+use super::journal;
+use super::graph::{NodeKind, ParsedEdge, ParsedNode, WorkflowGraph};
 
 /// A task in the orchestrator's plan
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -146,6 +147,8 @@ pub fn plan_to_graph(plan: &OrchestratorPlan) -> WorkflowGraph {
             agent_role: task.agent_role.clone(),
             system_prompt: task.system_prompt.clone(),
             assigned_task: Some(task.description.clone()),
+            kind: NodeKind::Agent,
+            preset: None,
         };
         nodes.insert(task.id.clone(), node);
         successors.insert(task.id.clone(), Vec::new());
@@ -181,6 +184,7 @@ pub fn plan_to_graph(plan: &OrchestratorPlan) -> WorkflowGraph {
         edges,
         successors,
         predecessors,
+        stages: Vec::new(),
     }
 }
 
@@ -209,6 +213,12 @@ pub async fn run_orchestrator_planning(
         project_id: Some(project_id),
         system_prompt: Some(ORCHESTRATOR_PLAN_PROMPT.to_string()),
         assigned_task: Some(input_prompt.to_string()),
+        model: None,
+        env: Vec::new(),
+        owner: Uuid::parse_str(execution_id).ok().map(|execution_id| AgentOwner {
+            execution_id,
+            node_id: None,
+        }),
     };
 
     // Spawn the orchestrator agent
@@ -220,8 +230,8 @@ pub async fn run_orchestrator_planning(
     app_state.agents.insert(agent_id, agent_info);
 
     // Emit event that orchestrator started with real agent_id
-    let _ = app.emit(
-        WORKFLOW_EVENT_NAME,
+    journal::publish(
+        app,
         WorkflowEvent::NodeStarted {
             execution_id: execution_id.to_string(),
             node_id: "orchestrator".to_string(),
@@ -259,7 +269,7 @@ async fn wait_for_agent_output(_app: &AppHandle, agent_id: Uuid) -> Result<Strin
             if completion.success {
                 // Get the collected output from the registry
                 let output = AGENT_REGISTRY.get_output(&agent_id)
-                    .unwrap_or_else(|| completion.output);
+                    .unwrap_or(completion.output);
 
                 if output.is_empty() {
                     return Err("Orchestrator agent completed but produced no output".to_string());
@@ -0,0 +1,186 @@
+//! Output formats a node can require of its agent.
+//!
+//! Provides:
+//! - Format instructions appended to the agent's prompt
+//! - Parsing the output into structured data, failing the attempt when it
+//!   doesn't match so the retry policy can ask again
+//! - Checking JSON output against a node's output schema
+
+use serde::{Deserialize, Serialize};
+
+use super::context::OutputData;
+use super::patch::{self, FilePatch};
+
+/// What a node's agent is asked to answer with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputFormat {
+    /// Markdown text, passed on as is
+    Markdown,
+    /// A single JSON value, passed on as structured data
+    Json,
+    /// A unified diff
+    Patch,
+}
+
+impl OutputFormat {
+    /// What the prompt tells the agent about its answer
+    pub fn instructions(&self) -> &'static str {
+        match self {
+            OutputFormat::Markdown => "Format your final answer as Markdown.",
+            OutputFormat::Json => {
+                "Answer with a single JSON value in a ```json code block and nothing else. \
+                 It must parse as strict JSON: no comments or trailing commas."
+            }
+            OutputFormat::Patch => {
+                "Answer with your changes as a unified diff (as `git diff` prints it) in a ```diff \
+                 code block. Use paths relative to the project root with a/ and b/ prefixes, and \
+                 make every hunk's line counts match its @@ header."
+            }
+        }
+    }
+
+    /// Turn an agent's output into data of this format
+    pub fn parse(&self, output: &str) -> Result<OutputData, String> {
+        if output.trim().is_empty() {
+            return Err("Output is empty".to_string());
+        }
+
+        match self {
+            OutputFormat::Markdown => Ok(OutputData::Text(output.to_string())),
+            OutputFormat::Json => parse_json(output).map(OutputData::Json),
+            OutputFormat::Patch => extract_patch(output).map(|(diff, _)| OutputData::Code {
+                language: "diff".to_string(),
+                content: diff.to_string(),
+            }),
+        }
+    }
+}
+
+/// What the prompt tells the agent about the schema its JSON must match
+pub fn schema_instructions(schema: &serde_json::Value) -> String {
+    format!("The JSON must match this JSON Schema:\n```json\n{}\n```", schema)
+}
+
+/// Check that `schema` is a JSON Schema outputs can be checked against
+pub fn validate_schema(schema: &serde_json::Value) -> Result<(), String> {
+    jsonschema::validator_for(schema)
+        .map(|_| ())
+        .map_err(|e| format!("Invalid output schema: {}", e))
+}
+
+/// Check parsed output against a node's output schema
+pub fn check_schema(data: &OutputData, schema: &serde_json::Value) -> Result<(), String> {
+    let OutputData::Json(value) = data else {
+        return Err("Output is not JSON, but the node requires an output schema".to_string());
+    };
+    let validator = jsonschema::validator_for(schema).map_err(|e| format!("Invalid output schema: {}", e))?;
+    let errors: Vec<String> = validator
+        .iter_errors(value)
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            format!("{}: {}", if path.is_empty() { "/" } else { &path }, e)
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Output doesn't match the output schema: {}", errors.join("; ")))
+    }
+}
+
+/// The unified diff in an output, from its diff code block if it has one,
+/// and its files
+pub fn extract_patch(output: &str) -> Result<(&str, Vec<FilePatch>), String> {
+    let diff = fenced_block(output, &["diff", "patch"]).unwrap_or(output);
+    let files = patch::parse_unified_diff(diff).map_err(|e| format!("Output is not a valid unified diff: {}", e))?;
+    Ok((diff, files))
+}
+
+/// The whole output as JSON, else its JSON code block, else the text from
+/// the first opening bracket to the last closing one
+fn parse_json(output: &str) -> Result<serde_json::Value, String> {
+    let trimmed = output.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Ok(value);
+    }
+
+    let candidate = match fenced_block(trimmed, &["json"]) {
+        Some(block) => block,
+        None => {
+            let start = trimmed.find(['{', '[']).ok_or("Output contains no JSON object or array")?;
+            let end = trimmed.rfind(['}', ']']).filter(|end| *end > start).ok_or("Output's JSON is unterminated")?;
+            &trimmed[start..=end]
+        }
+    };
+    serde_json::from_str(candidate).map_err(|e| format!("Output is not valid JSON: {}", e))
+}
+
+/// The body of the first code block tagged with one of `languages` or
+/// untagged
+fn fenced_block<'a>(text: &'a str, languages: &[&str]) -> Option<&'a str> {
+    let mut offset = 0;
+    let mut body_start = None;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        match (body_start, trimmed.strip_prefix("```")) {
+            (None, Some(tag)) if tag.is_empty() || languages.contains(&tag) => body_start = Some(offset + line.len()),
+            (Some(start), Some("")) => return Some(&text[start..offset]),
+            _ => {}
+        }
+        offset += line.len();
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_json() {
+        let fenced = "Here you go:\n```json\n{\"risk\": \"low\"}\n```\nLet me know.";
+        assert!(matches!(OutputFormat::Json.parse(fenced), Ok(OutputData::Json(v)) if v["risk"] == "low"));
+
+        let inline = "The result is [1, 2, 3].";
+        assert!(matches!(OutputFormat::Json.parse(inline), Ok(OutputData::Json(v)) if v[2] == 3));
+
+        let broken = OutputFormat::Json.parse("```json\n{\"risk\": low}\n```").unwrap_err();
+        assert!(broken.starts_with("Output is not valid JSON"));
+        assert!(OutputFormat::Json.parse("No structured answer").is_err());
+    }
+
+    #[test]
+    fn test_check_schema() {
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["risk"],
+            "properties": {"risk": {"enum": ["low", "high"]}}
+        });
+        assert!(validate_schema(&schema).is_ok());
+        assert!(validate_schema(&serde_json::json!({"type": 5})).is_err());
+
+        let ok = OutputFormat::Json.parse("{\"risk\": \"low\"}").unwrap();
+        assert!(check_schema(&ok, &schema).is_ok());
+        let wrong = OutputFormat::Json.parse("{\"risk\": \"medium\"}").unwrap();
+        assert!(check_schema(&wrong, &schema).unwrap_err().contains("/risk"));
+        assert!(check_schema(&OutputData::Text("low".to_string()), &schema).is_err());
+    }
+
+    #[test]
+    fn test_parse_patch() {
+        let output = "I changed the greeting.\n```diff\n--- a/hello.txt\n+++ b/hello.txt\n@@ -1 +1 @@\n-hi\n+hello\n```\n";
+        match OutputFormat::Patch.parse(output) {
+            Ok(OutputData::Code { language, content }) => {
+                assert_eq!(language, "diff");
+                assert!(content.starts_with("--- a/hello.txt"));
+                assert!(!content.contains("```"));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(OutputFormat::Patch.parse("Just change hi to hello").is_err());
+        assert!(OutputFormat::Markdown.parse("  \n").is_err());
+    }
+}
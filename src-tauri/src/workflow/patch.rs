@@ -0,0 +1,482 @@
+//! Unified diffs produced by agents.
+//!
+//! Provides:
+//! - Parsing a unified diff (`diff -u` or `git diff` output) into files
+//!   and hunks, checking each hunk's line counts against its header
+//! - Checking which hunks apply to a project, tolerating hunks that moved
+//!   as `patch` does, and writing the result with the file's line endings
+//! - Refusing paths that leave the project, lexically or through symlinks
+
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// What a patch node does with a patch that applies
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchMode {
+    /// Write it to the project
+    #[default]
+    Apply,
+    /// Write it once someone approves it
+    Stage,
+    /// Only report whether it applies
+    Check,
+}
+
+/// One contiguous change within a file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hunk {
+    pub old_start: usize,
+    pub old_lines: usize,
+    pub new_start: usize,
+    pub new_lines: usize,
+    /// Body lines with their ' ', '-' or '+' prefix
+    pub lines: Vec<String>,
+}
+
+/// The changes to one file. A created file has no old path, a deleted
+/// one no new path.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FilePatch {
+    pub old_path: Option<String>,
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    /// The path the patch is reported under
+    pub fn path(&self) -> &str {
+        self.new_path.as_deref().or(self.old_path.as_deref()).unwrap_or_default()
+    }
+}
+
+/// Parse every file of a unified diff. Text outside the file sections,
+/// such as `diff --git` and `index` lines, is ignored.
+pub fn parse_unified_diff(text: &str) -> Result<Vec<FilePatch>, String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut files: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+        if let (Some(old), Some(new)) = (line.strip_prefix("--- "), lines.get(i + 1).and_then(|l| l.strip_prefix("+++ "))) {
+            files.push(FilePatch {
+                old_path: diff_path(old, "a/"),
+                new_path: diff_path(new, "b/"),
+                hunks: Vec::new(),
+            });
+            i += 2;
+            continue;
+        }
+
+        if line.starts_with("@@") {
+            let Some(file) = files.last_mut() else {
+                return Err(format!("Hunk on line {} comes before any file header", i + 1));
+            };
+            let (old_start, old_lines, new_start, new_lines) =
+                parse_hunk_header(line).ok_or_else(|| format!("Malformed hunk header on line {}: {}", i + 1, line))?;
+            let mut hunk = Hunk {
+                old_start,
+                old_lines,
+                new_start,
+                new_lines,
+                lines: Vec::new(),
+            };
+
+            let (mut old_seen, mut new_seen) = (0, 0);
+            i += 1;
+            while (old_seen < old_lines || new_seen < new_lines) && i < lines.len() {
+                let body = lines[i];
+                match body.chars().next() {
+                    Some('-') => old_seen += 1,
+                    Some('+') => new_seen += 1,
+                    // Some tools drop the space of blank context lines
+                    Some(' ') | None => {
+                        old_seen += 1;
+                        new_seen += 1;
+                    }
+                    Some('\\') => {}
+                    _ => break,
+                }
+                hunk.lines.push(if body.is_empty() { " ".to_string() } else { body.to_string() });
+                i += 1;
+            }
+            // "\ No newline at end of file" after the last line
+            while lines.get(i).is_some_and(|l| l.starts_with('\\')) {
+                hunk.lines.push(lines[i].to_string());
+                i += 1;
+            }
+
+            if old_seen != old_lines || new_seen != new_lines {
+                return Err(format!(
+                    "Hunk {} of {} is truncated: header says -{} +{} lines, body has -{} +{}",
+                    file.hunks.len() + 1,
+                    file.path(),
+                    old_lines,
+                    new_lines,
+                    old_seen,
+                    new_seen
+                ));
+            }
+            file.hunks.push(hunk);
+            continue;
+        }
+
+        i += 1;
+    }
+
+    if files.is_empty() {
+        return Err("No file headers (--- and +++ lines) found".to_string());
+    }
+    if let Some(empty) = files.iter().find(|f| f.hunks.is_empty()) {
+        return Err(format!("{} has no hunks", empty.path()));
+    }
+    Ok(files)
+}
+
+/// How one hunk fared against the project
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HunkOutcome {
+    pub old_start: usize,
+    pub applied: bool,
+    /// Lines the hunk moved from where its header put it
+    pub offset: isize,
+    pub reason: Option<String>,
+}
+
+/// How one file fared
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileReport {
+    pub path: String,
+    pub hunks: Vec<HunkOutcome>,
+}
+
+/// Which hunks of a patch apply, and whether the patch was written
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchReport {
+    pub written: bool,
+    pub applied_hunks: usize,
+    pub rejected_hunks: usize,
+    pub files: Vec<FileReport>,
+}
+
+impl PatchReport {
+    /// The rejected hunks, one per line, for an error message
+    pub fn rejections(&self) -> String {
+        self.files
+            .iter()
+            .flat_map(|file| {
+                file.hunks.iter().enumerate().filter(|(_, h)| !h.applied).map(move |(i, hunk)| {
+                    format!("{} hunk {}: {}", file.path, i + 1, hunk.reason.as_deref().unwrap_or("rejected"))
+                })
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// A file's content once its applicable hunks are in; `None` deletes it
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    pub path: PathBuf,
+    pub content: Option<String>,
+}
+
+/// Work out what applying `files` to the project in `dir` would do,
+/// without touching it
+pub fn plan(dir: &Path, files: &[FilePatch]) -> (PatchReport, Vec<PlannedFile>) {
+    let mut report = PatchReport {
+        written: false,
+        applied_hunks: 0,
+        rejected_hunks: 0,
+        files: Vec::new(),
+    };
+    let mut planned = Vec::new();
+
+    for file in files {
+        let hunks = match read_original(dir, file) {
+            Ok((path, original)) => {
+                let (content, hunks) = apply_hunks(&original, &file.hunks);
+                if hunks.iter().any(|h| h.applied) {
+                    let deleted = file.new_path.is_none() && hunks.iter().all(|h| h.applied) && content.is_empty();
+                    planned.push(PlannedFile {
+                        path,
+                        content: if deleted { None } else { Some(content) },
+                    });
+                }
+                hunks
+            }
+            Err(reason) => file
+                .hunks
+                .iter()
+                .map(|hunk| HunkOutcome {
+                    old_start: hunk.old_start,
+                    applied: false,
+                    offset: 0,
+                    reason: Some(reason.clone()),
+                })
+                .collect(),
+        };
+
+        let applied = hunks.iter().filter(|h| h.applied).count();
+        report.applied_hunks += applied;
+        report.rejected_hunks += hunks.len() - applied;
+        report.files.push(FileReport {
+            path: file.path().to_string(),
+            hunks,
+        });
+    }
+
+    (report, planned)
+}
+
+/// Write planned files under `dir`
+pub fn write(dir: &Path, planned: &[PlannedFile]) -> Result<(), String> {
+    for file in planned {
+        let path = dir.join(&file.path);
+        let written = match &file.content {
+            Some(content) => path
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|_| std::fs::write(&path, content)),
+            None => std::fs::remove_file(&path),
+        };
+        written.map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// The project-relative path a file patch changes and its current content
+fn read_original(dir: &Path, file: &FilePatch) -> Result<(PathBuf, String), String> {
+    let path = PathBuf::from(file.path());
+    if path.as_os_str().is_empty() || path.components().any(|c| !matches!(c, Component::Normal(_))) {
+        return Err(format!("Path '{}' is not inside the project", file.path()));
+    }
+
+    let full = dir.join(&path);
+    if !resolves_inside(dir, &full) {
+        return Err(format!("Path '{}' is not inside the project", file.path()));
+    }
+    match (&file.old_path, full.exists()) {
+        (None, true) => Err("File to create already exists".to_string()),
+        (None, false) => Ok((path, String::new())),
+        (Some(_), false) => Err("File does not exist".to_string()),
+        (Some(_), true) => std::fs::read_to_string(&full)
+            .map(|original| (path, original))
+            .map_err(|e| format!("Failed to read file: {}", e)),
+    }
+}
+
+/// Whether `full` stays inside `dir` once symlinks are resolved; a path
+/// that doesn't exist yet is judged by its nearest existing ancestor
+pub(crate) fn resolves_inside(dir: &Path, full: &Path) -> bool {
+    let Ok(root) = dir.canonicalize() else {
+        return false;
+    };
+    full.ancestors()
+        .find_map(|ancestor| ancestor.canonicalize().ok())
+        .is_some_and(|resolved| resolved.starts_with(&root))
+}
+
+/// Apply hunks in order to `original`, each at the position nearest its
+/// header where its old lines match; hunks that match nowhere are left out.
+/// Untouched lines keep their endings and new ones take the file's first.
+fn apply_hunks(original: &str, hunks: &[Hunk]) -> (String, Vec<HunkOutcome>) {
+    let eol = match original.find('\n') {
+        Some(end) if original[..end].ends_with('\r') => "\r\n",
+        _ => "\n",
+    };
+    let mut lines: Vec<String> = original.split_inclusive('\n').map(str::to_string).collect();
+    let mut outcomes = Vec::new();
+    // Line count change from applied hunks, and where the next may start
+    let mut shift: isize = 0;
+    let mut floor = 0;
+
+    for hunk in hunks {
+        let old: Vec<&str> = hunk.lines.iter().filter(|l| !l.starts_with(['+', '\\'])).map(|l| &l[1..]).collect();
+
+        // A pure insertion's start is the line it goes after
+        let header_index = if hunk.old_lines == 0 { hunk.old_start } else { hunk.old_start.saturating_sub(1) };
+        let expected = (header_index as isize + shift).clamp(0, lines.len() as isize) as usize;
+        let matches_at = |at: usize| {
+            at >= floor
+                && at + old.len() <= lines.len()
+                && lines[at..at + old.len()].iter().zip(&old).all(|(a, b)| a.trim_end() == b.trim_end())
+        };
+        let position = (0..=lines.len())
+            .flat_map(|distance| [expected.checked_sub(distance), Some(expected + distance)])
+            .flatten()
+            .find(|at| matches_at(*at));
+
+        match position {
+            Some(at) => {
+                // Context lines stay as the file has them
+                let mut replaced = lines[at..at + old.len()].iter();
+                let new: Vec<String> = hunk
+                    .lines
+                    .iter()
+                    .filter_map(|l| match l.chars().next() {
+                        Some(' ') => replaced.next().cloned(),
+                        Some('-') => replaced.next().and(None),
+                        Some('+') => Some(format!("{}{}", l[1..].trim_end_matches('\r'), eol)),
+                        _ => None,
+                    })
+                    .collect();
+                lines.splice(at..at + old.len(), new.iter().cloned());
+                shift += new.len() as isize - old.len() as isize;
+                floor = at + new.len();
+                outcomes.push(HunkOutcome {
+                    old_start: hunk.old_start,
+                    applied: true,
+                    offset: at as isize - expected as isize,
+                    reason: None,
+                });
+            }
+            None => outcomes.push(HunkOutcome {
+                old_start: hunk.old_start,
+                applied: false,
+                offset: 0,
+                reason: Some("Lines to change not found".to_string()),
+            }),
+        }
+    }
+
+    // Lines now followed by others need an ending; the last has one only
+    // if the file did
+    let keep_final_eol = original.is_empty() || original.ends_with('\n');
+    let count = lines.len();
+    for (i, line) in lines.iter_mut().enumerate() {
+        if i + 1 < count || keep_final_eol {
+            if !line.ends_with('\n') {
+                line.push_str(eol);
+            }
+        } else {
+            let text = line.trim_end_matches(['\r', '\n']).len();
+            line.truncate(text);
+        }
+    }
+    (lines.concat(), outcomes)
+}
+
+/// The path in a `---`/`+++` line, without its `a/`/`b/` prefix or
+/// trailing timestamp
+fn diff_path(header: &str, prefix: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or_default().trim();
+    if path == "/dev/null" {
+        return None;
+    }
+    Some(path.strip_prefix(prefix).unwrap_or(path).to_string())
+}
+
+/// `@@ -start[,count] +start[,count] @@`; a missing count is 1
+fn parse_hunk_header(line: &str) -> Option<(usize, usize, usize, usize)> {
+    let ranges = line.strip_prefix("@@ ")?.split(" @@").next()?;
+    let (old, new) = ranges.split_once(' ')?;
+    let range = |r: &str| -> Option<(usize, usize)> {
+        match r.split_once(',') {
+            Some((start, count)) => Some((start.parse().ok()?, count.parse().ok()?)),
+            None => Some((r.parse().ok()?, 1)),
+        }
+    };
+    let (old_start, old_lines) = range(old.strip_prefix('-')?)?;
+    let (new_start, new_lines) = range(new.strip_prefix('+')?)?;
+    Some((old_start, old_lines, new_start, new_lines))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_unified_diff() {
+        let diff = "diff --git a/src/lib.rs b/src/lib.rs\n\
+                    index 3b18e51..a1b2c3d 100644\n\
+                    --- a/src/lib.rs\n\
+                    +++ b/src/lib.rs\n\
+                    @@ -1,3 +1,4 @@\n \
+                    use std::io;\n\
+                    +use std::fs;\n\
+                    \n \
+                    fn main() {}\n\
+                    --- /dev/null\n\
+                    +++ b/README.md\n\
+                    @@ -0,0 +1 @@\n\
+                    +# Nexus\n";
+
+        let files = parse_unified_diff(diff).unwrap();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[0].path(), "src/lib.rs");
+        assert_eq!(files[0].hunks[0].lines.len(), 4);
+        assert_eq!(files[1].old_path, None);
+        assert_eq!((files[1].hunks[0].new_start, files[1].hunks[0].new_lines), (1, 1));
+    }
+
+    #[test]
+    fn test_plan_and_write() {
+        let dir = std::env::temp_dir().join(format!("nexus-patch-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // Two lines were added above the spot the agent saw
+        std::fs::write(dir.join("notes.txt"), "new\nnew\none\ntwo\nthree\n").unwrap();
+
+        let diff = "--- a/notes.txt\n+++ b/notes.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n three\n\
+                    @@ -9,1 +9,1 @@\n-missing\n+gone\n\
+                    --- /dev/null\n+++ b/docs/new.md\n@@ -0,0 +1 @@\n+# New\n\
+                    --- a/../outside.txt\n+++ b/../outside.txt\n@@ -1 +1 @@\n-a\n+b\n";
+        let (report, planned) = plan(&dir, &parse_unified_diff(diff).unwrap());
+
+        assert_eq!((report.applied_hunks, report.rejected_hunks), (2, 2));
+        assert_eq!(report.files[0].hunks[0].offset, 2);
+        assert!(report.rejections().contains("notes.txt hunk 2: Lines to change not found"));
+        assert!(report.rejections().contains("not inside the project"));
+
+        write(&dir, &planned).unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("notes.txt")).unwrap(), "new\nnew\none\n2\nthree\n");
+        assert_eq!(std::fs::read_to_string(dir.join("docs/new.md")).unwrap(), "# New\n");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_keeps_line_endings() {
+        let diff = "--- a/x.txt\n+++ b/x.txt\n@@ -1,3 +1,4 @@\n one\n-two\n+2\n+2.5\n three\n";
+        let hunks = &parse_unified_diff(diff).unwrap()[0].hunks;
+
+        let (content, outcomes) = apply_hunks("one\r\ntwo\r\nthree\r\n", hunks);
+        assert!(outcomes[0].applied);
+        assert_eq!(content, "one\r\n2\r\n2.5\r\nthree\r\n");
+
+        // A mixed file keeps each untouched line's ending
+        let (content, _) = apply_hunks("one\ntwo\nthree\r\n", hunks);
+        assert_eq!(content, "one\n2\n2.5\nthree\r\n");
+
+        // No newline at the end stays that way
+        let (content, _) = apply_hunks("one\r\ntwo\r\nthree", hunks);
+        assert_eq!(content, "one\r\n2\r\n2.5\r\nthree");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejects_symlinks_out_of_the_project() {
+        let root = std::env::temp_dir().join(format!("nexus-patch-{}", uuid::Uuid::new_v4()));
+        let (dir, outside) = (root.join("project"), root.join("outside"));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::create_dir_all(&outside).unwrap();
+        std::fs::write(outside.join("secret.txt"), "a\n").unwrap();
+        std::os::unix::fs::symlink(&outside, dir.join("link")).unwrap();
+
+        let diff = "--- a/link/secret.txt\n+++ b/link/secret.txt\n@@ -1 +1 @@\n-a\n+b\n\
+                    --- /dev/null\n+++ b/link/new.txt\n@@ -0,0 +1 @@\n+b\n";
+        let (report, planned) = plan(&dir, &parse_unified_diff(diff).unwrap());
+        assert_eq!((report.applied_hunks, report.rejected_hunks), (0, 2));
+        assert!(planned.is_empty());
+        assert!(report.rejections().contains("not inside the project"));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn test_rejects_broken_diffs() {
+        let truncated = "--- a/x.txt\n+++ b/x.txt\n@@ -1,3 +1,3 @@\n one\n-two\n+2\n";
+        assert!(parse_unified_diff(truncated).unwrap_err().contains("truncated"));
+        assert!(parse_unified_diff("Here is the fix: change line 3").is_err());
+        assert!(parse_unified_diff("--- a/x.txt\n+++ b/x.txt\n@@ nonsense @@\n").is_err());
+    }
+}
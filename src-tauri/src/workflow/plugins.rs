@@ -0,0 +1,466 @@
+//! WASM plugin host for user-supplied workflow extensions.
+//!
+//! Plugins are WebAssembly modules dropped into the plugins directory and
+//! registered by name (the file stem). Each module provides one of:
+//! - `evaluate_condition` - a custom `ExecutionCondition` evaluator
+//! - `transform` - a custom `OutputTransform`
+//! - `aggregate` - a custom `AggregationStrategy`
+//!
+//! Plugin ABI: the module exports `memory` and `alloc(len: i32) -> i32`, and
+//! its entry point takes `(ptr: i32, len: i32)` pointing at UTF-8 JSON input.
+//! `evaluate_condition` returns an `i32` (non-zero means execute); `transform`
+//! and `aggregate` return an `i64` packed as `(ptr << 32) | len` pointing at
+//! their UTF-8 output. Modules get no host imports and run with a fuel budget.
+//!
+//! The WASM runtime is only compiled in with the `plugins` feature.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Fuel budget for a single plugin call, guards against runaway loops
+#[cfg(feature = "plugins")]
+const PLUGIN_FUEL: u64 = 50_000_000;
+
+/// Maximum output a plugin may hand back (16 MiB)
+#[cfg(feature = "plugins")]
+const MAX_PLUGIN_OUTPUT_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, Error)]
+pub enum PluginError {
+    #[error("Plugin not found: {0}")]
+    NotFound(String),
+
+    #[error("Plugin '{name}' is a {actual} plugin, expected {expected}")]
+    WrongKind {
+        name: String,
+        expected: PluginKind,
+        actual: PluginKind,
+    },
+
+    #[error("Plugin support is not enabled in this build")]
+    Unsupported,
+
+    #[error("Failed to load plugin: {0}")]
+    Load(String),
+
+    #[error("Plugin execution failed: {0}")]
+    Runtime(String),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("JSON error: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// What a plugin extends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PluginKind {
+    Condition,
+    Transform,
+    Aggregator,
+}
+
+impl PluginKind {
+    /// Name of the export implementing this kind
+    pub fn entry_point(&self) -> &'static str {
+        match self {
+            PluginKind::Condition => "evaluate_condition",
+            PluginKind::Transform => "transform",
+            PluginKind::Aggregator => "aggregate",
+        }
+    }
+
+    #[cfg(feature = "plugins")]
+    fn from_exports<'a>(mut exports: impl Iterator<Item = &'a str>) -> Option<Self> {
+        exports.find_map(|name| match name {
+            "evaluate_condition" => Some(PluginKind::Condition),
+            "transform" => Some(PluginKind::Transform),
+            "aggregate" => Some(PluginKind::Aggregator),
+            _ => None,
+        })
+    }
+}
+
+impl std::fmt::Display for PluginKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginKind::Condition => write!(f, "condition"),
+            PluginKind::Transform => write!(f, "transform"),
+            PluginKind::Aggregator => write!(f, "aggregator"),
+        }
+    }
+}
+
+/// Metadata about a registered plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginInfo {
+    pub name: String,
+    pub kind: PluginKind,
+    pub path: String,
+    pub loaded_at: DateTime<Utc>,
+}
+
+struct LoadedPlugin {
+    info: PluginInfo,
+    #[cfg(feature = "plugins")]
+    module: wasmtime::Module,
+}
+
+/// Raw result of a plugin call
+#[cfg_attr(not(feature = "plugins"), allow(dead_code))]
+enum PluginOutput {
+    Flag(bool),
+    Bytes(Vec<u8>),
+}
+
+/// Registry of loaded plugins, keyed by name
+pub struct PluginRegistry {
+    plugins: DashMap<String, Arc<LoadedPlugin>>,
+    #[cfg(feature = "plugins")]
+    engine: wasmtime::Engine,
+}
+
+impl PluginRegistry {
+    pub fn new() -> Self {
+        Self {
+            plugins: DashMap::new(),
+            #[cfg(feature = "plugins")]
+            engine: {
+                let mut config = wasmtime::Config::new();
+                config.consume_fuel(true);
+                wasmtime::Engine::new(&config).expect("Failed to create WASM engine")
+            },
+        }
+    }
+
+    pub fn default_plugin_dir() -> PathBuf {
+        dirs::data_local_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("nexus")
+            .join("plugins")
+    }
+
+    /// Whether this build can load WASM plugins
+    pub fn is_supported() -> bool {
+        cfg!(feature = "plugins")
+    }
+
+    /// Load a single plugin file, replacing any plugin with the same name
+    #[cfg(feature = "plugins")]
+    pub fn load_file(&self, path: &Path) -> Result<PluginInfo, PluginError> {
+        let name = path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| PluginError::Load(format!("Invalid plugin path: {}", path.display())))?
+            .to_string();
+
+        let bytes = std::fs::read(path)?;
+        let module = wasmtime::Module::new(&self.engine, &bytes)
+            .map_err(|e| PluginError::Load(format!("{}: {}", name, e)))?;
+
+        let kind = PluginKind::from_exports(module.exports().map(|e| e.name())).ok_or_else(|| {
+            PluginError::Load(format!(
+                "{} exports none of evaluate_condition, transform, aggregate",
+                name
+            ))
+        })?;
+
+        for required in ["memory", "alloc"] {
+            if module.get_export(required).is_none() {
+                return Err(PluginError::Load(format!("{} does not export `{}`", name, required)));
+            }
+        }
+
+        let info = PluginInfo {
+            name: name.clone(),
+            kind,
+            path: path.to_string_lossy().to_string(),
+            loaded_at: Utc::now(),
+        };
+
+        log::info!("Loaded {} plugin '{}' from {}", kind, name, path.display());
+        self.plugins
+            .insert(name, Arc::new(LoadedPlugin { info: info.clone(), module }));
+
+        Ok(info)
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    pub fn load_file(&self, _path: &Path) -> Result<PluginInfo, PluginError> {
+        Err(PluginError::Unsupported)
+    }
+
+    /// Load every `.wasm`/`.wat` file in a directory.
+    /// Returns the plugins that loaded and the errors for those that didn't.
+    pub fn load_dir(&self, dir: &Path) -> (Vec<PluginInfo>, Vec<String>) {
+        let mut loaded = Vec::new();
+        let mut errors = Vec::new();
+
+        let entries = match std::fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return (loaded, errors),
+            Err(e) => {
+                errors.push(format!("{}: {}", dir.display(), e));
+                return (loaded, errors);
+            }
+        };
+
+        for entry in entries.filter_map(|e| e.ok()) {
+            let path = entry.path();
+            let is_plugin = path
+                .extension()
+                .map(|ext| ext == "wasm" || ext == "wat")
+                .unwrap_or(false);
+            if !is_plugin {
+                continue;
+            }
+
+            match self.load_file(&path) {
+                Ok(info) => loaded.push(info),
+                Err(e) => {
+                    log::warn!("Skipping plugin {}: {}", path.display(), e);
+                    errors.push(format!("{}: {}", path.display(), e));
+                }
+            }
+        }
+
+        (loaded, errors)
+    }
+
+    /// List registered plugins sorted by name
+    pub fn list(&self) -> Vec<PluginInfo> {
+        let mut plugins: Vec<_> = self.plugins.iter().map(|p| p.info.clone()).collect();
+        plugins.sort_by(|a, b| a.name.cmp(&b.name));
+        plugins
+    }
+
+    pub fn get_info(&self, name: &str) -> Option<PluginInfo> {
+        self.plugins.get(name).map(|p| p.info.clone())
+    }
+
+    /// Remove a plugin from the registry
+    pub fn unload(&self, name: &str) -> bool {
+        self.plugins.remove(name).is_some()
+    }
+
+    /// Run a condition plugin; `true` means the node should execute
+    pub fn call_condition(&self, name: &str, input: &serde_json::Value) -> Result<bool, PluginError> {
+        match self.invoke(name, PluginKind::Condition, input)? {
+            PluginOutput::Flag(flag) => Ok(flag),
+            PluginOutput::Bytes(_) => Err(PluginError::Runtime("unexpected output".to_string())),
+        }
+    }
+
+    /// Run a transform plugin, returning its raw output text
+    pub fn call_transform(&self, name: &str, input: &serde_json::Value) -> Result<String, PluginError> {
+        self.invoke_text(name, PluginKind::Transform, input)
+    }
+
+    /// Run an aggregator plugin, returning its raw output text
+    pub fn call_aggregate(&self, name: &str, input: &serde_json::Value) -> Result<String, PluginError> {
+        self.invoke_text(name, PluginKind::Aggregator, input)
+    }
+
+    fn invoke_text(
+        &self,
+        name: &str,
+        kind: PluginKind,
+        input: &serde_json::Value,
+    ) -> Result<String, PluginError> {
+        match self.invoke(name, kind, input)? {
+            PluginOutput::Bytes(bytes) => String::from_utf8(bytes)
+                .map_err(|_| PluginError::Runtime(format!("{} returned invalid UTF-8", name))),
+            PluginOutput::Flag(_) => Err(PluginError::Runtime("unexpected output".to_string())),
+        }
+    }
+
+    fn invoke(
+        &self,
+        name: &str,
+        kind: PluginKind,
+        input: &serde_json::Value,
+    ) -> Result<PluginOutput, PluginError> {
+        let plugin = self
+            .plugins
+            .get(name)
+            .map(|p| p.clone())
+            .ok_or_else(|| PluginError::NotFound(name.to_string()))?;
+
+        if plugin.info.kind != kind {
+            return Err(PluginError::WrongKind {
+                name: name.to_string(),
+                expected: kind,
+                actual: plugin.info.kind,
+            });
+        }
+
+        let input = serde_json::to_vec(input)?;
+        self.run(&plugin, &input)
+    }
+
+    #[cfg(feature = "plugins")]
+    fn run(&self, plugin: &LoadedPlugin, input: &[u8]) -> Result<PluginOutput, PluginError> {
+        use wasmtime::{Instance, Store};
+
+        let runtime_err = |e: wasmtime::Error| PluginError::Runtime(format!("{}: {}", plugin.info.name, e));
+
+        let mut store = Store::new(&self.engine, ());
+        store.set_fuel(PLUGIN_FUEL).map_err(runtime_err)?;
+
+        let instance = Instance::new(&mut store, &plugin.module, &[]).map_err(runtime_err)?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| PluginError::Runtime(format!("{}: missing memory export", plugin.info.name)))?;
+
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&mut store, "alloc")
+            .map_err(runtime_err)?;
+        let input_ptr = alloc.call(&mut store, input.len() as i32).map_err(runtime_err)?;
+        memory
+            .write(&mut store, input_ptr as u32 as usize, input)
+            .map_err(|e| PluginError::Runtime(format!("{}: {}", plugin.info.name, e)))?;
+
+        let entry_point = plugin.info.kind.entry_point();
+        match plugin.info.kind {
+            PluginKind::Condition => {
+                let func = instance
+                    .get_typed_func::<(i32, i32), i32>(&mut store, entry_point)
+                    .map_err(runtime_err)?;
+                let result = func
+                    .call(&mut store, (input_ptr, input.len() as i32))
+                    .map_err(runtime_err)?;
+                Ok(PluginOutput::Flag(result != 0))
+            }
+            PluginKind::Transform | PluginKind::Aggregator => {
+                let func = instance
+                    .get_typed_func::<(i32, i32), i64>(&mut store, entry_point)
+                    .map_err(runtime_err)?;
+                let packed = func
+                    .call(&mut store, (input_ptr, input.len() as i32))
+                    .map_err(runtime_err)? as u64;
+
+                let out_ptr = (packed >> 32) as usize;
+                let out_len = (packed & 0xffff_ffff) as usize;
+                if out_len > MAX_PLUGIN_OUTPUT_BYTES {
+                    return Err(PluginError::Runtime(format!(
+                        "{} returned {} bytes (limit {})",
+                        plugin.info.name, out_len, MAX_PLUGIN_OUTPUT_BYTES
+                    )));
+                }
+
+                let mut buffer = vec![0u8; out_len];
+                memory
+                    .read(&store, out_ptr, &mut buffer)
+                    .map_err(|e| PluginError::Runtime(format!("{}: {}", plugin.info.name, e)))?;
+                Ok(PluginOutput::Bytes(buffer))
+            }
+        }
+    }
+
+    #[cfg(not(feature = "plugins"))]
+    fn run(&self, _plugin: &LoadedPlugin, _input: &[u8]) -> Result<PluginOutput, PluginError> {
+        Err(PluginError::Unsupported)
+    }
+}
+
+impl Default for PluginRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Global plugin registry instance
+lazy_static::lazy_static! {
+    pub static ref PLUGIN_REGISTRY: PluginRegistry = PluginRegistry::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_plugin() {
+        let registry = PluginRegistry::new();
+        let result = registry.call_condition("missing", &serde_json::json!({}));
+        assert!(matches!(result, Err(PluginError::NotFound(_))));
+    }
+
+    #[test]
+    fn test_load_dir_missing_is_empty() {
+        let registry = PluginRegistry::new();
+        let (loaded, errors) = registry.load_dir(Path::new("/nonexistent/nexus/plugins"));
+        assert!(loaded.is_empty());
+        assert!(errors.is_empty());
+    }
+
+    #[cfg(feature = "plugins")]
+    fn write_plugin(name: &str, wat: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("nexus-plugins-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(format!("{}.wat", name));
+        std::fs::write(&path, wat).unwrap();
+        path
+    }
+
+    #[cfg(feature = "plugins")]
+    #[test]
+    fn test_condition_plugin() {
+        // Executes when the input JSON is longer than 10 bytes
+        let path = write_plugin(
+            "long_input",
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) i32.const 0)
+                (func (export "evaluate_condition") (param i32 i32) (result i32)
+                    local.get 1
+                    i32.const 10
+                    i32.gt_s))"#,
+        );
+
+        let registry = PluginRegistry::new();
+        let info = registry.load_file(&path).unwrap();
+        assert_eq!(info.name, "long_input");
+        assert_eq!(info.kind, PluginKind::Condition);
+
+        assert!(registry
+            .call_condition("long_input", &serde_json::json!({"params": "something long"}))
+            .unwrap());
+        assert!(!registry.call_condition("long_input", &serde_json::json!(1)).unwrap());
+
+        let wrong = registry.call_transform("long_input", &serde_json::json!({}));
+        assert!(matches!(wrong, Err(PluginError::WrongKind { .. })));
+    }
+
+    #[cfg(feature = "plugins")]
+    #[test]
+    fn test_transform_plugin_echoes_input() {
+        // Returns the input buffer unchanged: (ptr << 32) | len
+        let path = write_plugin(
+            "echo",
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "alloc") (param i32) (result i32) i32.const 16)
+                (func (export "transform") (param i32 i32) (result i64)
+                    local.get 0
+                    i64.extend_i32_u
+                    i64.const 32
+                    i64.shl
+                    local.get 1
+                    i64.extend_i32_u
+                    i64.or))"#,
+        );
+
+        let registry = PluginRegistry::new();
+        registry.load_file(&path).unwrap();
+
+        let input = serde_json::json!({"data": "hello"});
+        let output = registry.call_transform("echo", &input).unwrap();
+        assert_eq!(output, input.to_string());
+    }
+}
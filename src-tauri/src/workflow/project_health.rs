@@ -0,0 +1,289 @@
+//! Per-project health summary.
+//!
+//! Everything a project dashboard card shows in one call. Provides:
+//! - The latest executions and the success rate of recent ones
+//! - Workflows whose latest run failed, with how many runs in a row did
+//! - Executions stuck waiting to start, and approvals nobody has given
+//! - Disk usage of the project directory
+//! - One overall status from all of these
+//!
+//! Runs in chaos mode fail on purpose and are left out.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::fs;
+use std::io;
+use std::path::Path;
+use uuid::Uuid;
+
+use super::approvals::ApprovalRequest;
+use super::chaos::CHAOS_TAG;
+use super::history::ExecutionRecord;
+use super::state::ExecutionStatus;
+
+/// Executions listed as the latest
+const LATEST_EXECUTIONS: usize = 5;
+/// Executions the success rate is taken over
+const SUCCESS_RATE_WINDOW: usize = 20;
+/// How long an execution may wait to start before it counts as stale
+const STALE_AFTER_MINUTES: i64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HealthStatus {
+    Healthy,
+    /// Something waits on the user or is stuck
+    Warning,
+    /// A workflow's latest run failed
+    Failing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionSummary {
+    pub execution_id: Uuid,
+    pub workflow_id: Option<Uuid>,
+    pub workflow_name: String,
+    pub status: ExecutionStatus,
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FailingWorkflow {
+    pub workflow_id: Option<Uuid>,
+    pub workflow_name: String,
+    /// Failed runs since the last successful one
+    pub consecutive_failures: usize,
+    pub last_failed_at: DateTime<Utc>,
+    /// First node error of the latest run
+    pub last_error: Option<String>,
+}
+
+/// An execution of the project not finished yet
+#[derive(Debug, Clone)]
+pub struct ActiveExecution {
+    pub execution_id: Uuid,
+    pub workflow_name: String,
+    pub status: ExecutionStatus,
+    pub started_at: DateTime<Utc>,
+}
+
+/// An execution waiting to start for too long, e.g. behind its
+/// concurrency group
+#[derive(Debug, Clone, Serialize)]
+pub struct StaleRun {
+    pub execution_id: Uuid,
+    pub workflow_name: String,
+    pub waiting_since: DateTime<Utc>,
+    pub waited_ms: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct DiskUsage {
+    pub bytes: u64,
+    pub files: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectHealth {
+    pub project_id: Uuid,
+    pub status: HealthStatus,
+    /// Newest first
+    pub latest_executions: Vec<ExecutionSummary>,
+    /// Share of recent finished executions that completed, 0 to 1
+    pub success_rate: Option<f64>,
+    pub failing_workflows: Vec<FailingWorkflow>,
+    pub running: usize,
+    pub stale_runs: Vec<StaleRun>,
+    pub open_approvals: Vec<ApprovalRequest>,
+    /// None when the directory couldn't be read
+    pub disk_usage: Option<DiskUsage>,
+}
+
+/// Summarize a project's health from its execution history, its
+/// unfinished executions and the approvals they wait for
+pub fn summarize(
+    project_id: Uuid,
+    records: &[ExecutionRecord],
+    active: &[ActiveExecution],
+    open_approvals: Vec<ApprovalRequest>,
+    disk_usage: Option<DiskUsage>,
+    now: DateTime<Utc>,
+) -> ProjectHealth {
+    let mut records: Vec<&ExecutionRecord> = records
+        .iter()
+        .filter(|record| record.project_id == project_id && !record.tags.iter().any(|tag| tag == CHAOS_TAG))
+        .collect();
+    records.sort_by_key(|record| std::cmp::Reverse(record.started_at));
+
+    let latest_executions = records
+        .iter()
+        .take(LATEST_EXECUTIONS)
+        .map(|record| ExecutionSummary {
+            execution_id: record.id,
+            workflow_id: record.workflow_id,
+            workflow_name: record.workflow_name.clone(),
+            status: record.status,
+            started_at: record.started_at,
+            duration_ms: record.duration_ms,
+        })
+        .collect();
+
+    let recent: Vec<_> = records
+        .iter()
+        .filter(|record| matches!(record.status, ExecutionStatus::Completed | ExecutionStatus::Failed))
+        .take(SUCCESS_RATE_WINDOW)
+        .collect();
+    let success_rate = (!recent.is_empty()).then(|| {
+        recent.iter().filter(|record| record.status == ExecutionStatus::Completed).count() as f64 / recent.len() as f64
+    });
+
+    let failing_workflows = failing_workflows(&records);
+
+    let stale_after = Duration::minutes(STALE_AFTER_MINUTES);
+    let stale_runs = active
+        .iter()
+        .filter(|execution| execution.status == ExecutionStatus::Pending && now - execution.started_at >= stale_after)
+        .map(|execution| StaleRun {
+            execution_id: execution.execution_id,
+            workflow_name: execution.workflow_name.clone(),
+            waiting_since: execution.started_at,
+            waited_ms: (now - execution.started_at).num_milliseconds().max(0) as u64,
+        })
+        .collect::<Vec<_>>();
+    let running = active.iter().filter(|execution| execution.status == ExecutionStatus::Running).count();
+
+    let status = if !failing_workflows.is_empty() {
+        HealthStatus::Failing
+    } else if !stale_runs.is_empty() || !open_approvals.is_empty() {
+        HealthStatus::Warning
+    } else {
+        HealthStatus::Healthy
+    };
+
+    ProjectHealth {
+        project_id,
+        status,
+        latest_executions,
+        success_rate,
+        failing_workflows,
+        running,
+        stale_runs,
+        open_approvals,
+        disk_usage,
+    }
+}
+
+/// Workflows whose latest finished run failed, from records newest first
+fn failing_workflows(records: &[&ExecutionRecord]) -> Vec<FailingWorkflow> {
+    let mut workflows: Vec<FailingWorkflow> = Vec::new();
+    // Workflows whose streak of failures has ended, or never started
+    let mut settled: Vec<(Option<Uuid>, &str)> = Vec::new();
+
+    for record in records {
+        let key = (record.workflow_id, record.workflow_name.as_str());
+        let same = |id: Option<Uuid>, name: &str| match key.0 {
+            Some(_) => id == key.0,
+            None => id.is_none() && name == key.1,
+        };
+        if settled.iter().any(|(id, name)| same(*id, name)) {
+            continue;
+        }
+        match record.status {
+            ExecutionStatus::Failed => {
+                match workflows.iter_mut().find(|w| same(w.workflow_id, &w.workflow_name)) {
+                    Some(workflow) => workflow.consecutive_failures += 1,
+                    None => workflows.push(FailingWorkflow {
+                        workflow_id: record.workflow_id,
+                        workflow_name: record.workflow_name.clone(),
+                        consecutive_failures: 1,
+                        last_failed_at: record.started_at,
+                        last_error: record.node_records.iter().find_map(|node| node.error.clone()),
+                    }),
+                }
+            }
+            ExecutionStatus::Completed => settled.push(key),
+            // Cancelled or unfinished runs neither fail nor clear a workflow
+            _ => {}
+        }
+    }
+    workflows
+}
+
+/// Size and number of the files under `dir`, not following symlinks
+pub fn disk_usage(dir: &Path) -> io::Result<DiskUsage> {
+    let mut usage = DiskUsage { bytes: 0, files: 0 };
+    let mut pending = vec![dir.to_path_buf()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(&dir)?.flatten() {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            if metadata.is_dir() {
+                pending.push(entry.path());
+            } else if metadata.is_file() {
+                usage.bytes += metadata.len();
+                usage.files += 1;
+            }
+        }
+    }
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::history::ExecutionRecordBuilder;
+
+    fn record(project_id: Uuid, workflow_id: Uuid, status: ExecutionStatus, minutes_ago: i64, now: DateTime<Utc>) -> ExecutionRecord {
+        let started_at = now - Duration::minutes(minutes_ago);
+        ExecutionRecordBuilder::new(Uuid::new_v4(), project_id, "shop".to_string(), "task".to_string())
+            .started_at(started_at)
+            .workflow(workflow_id, format!("workflow {}", workflow_id.as_u128() % 10))
+            .build(status, started_at + Duration::minutes(1))
+    }
+
+    #[test]
+    fn test_project_health() {
+        let now = Utc::now();
+        let project_id = Uuid::new_v4();
+        let (deploy, lint) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut chaos = record(project_id, lint, ExecutionStatus::Failed, 1, now);
+        chaos.tags.push(CHAOS_TAG.to_string());
+        let records = vec![
+            record(project_id, deploy, ExecutionStatus::Failed, 10, now),
+            record(project_id, deploy, ExecutionStatus::Failed, 20, now),
+            record(project_id, deploy, ExecutionStatus::Completed, 30, now),
+            record(project_id, deploy, ExecutionStatus::Failed, 40, now),
+            record(project_id, lint, ExecutionStatus::Completed, 15, now),
+            record(Uuid::new_v4(), lint, ExecutionStatus::Failed, 5, now),
+            chaos,
+        ];
+        let active = vec![
+            ActiveExecution {
+                execution_id: Uuid::new_v4(),
+                workflow_name: "nightly".to_string(),
+                status: ExecutionStatus::Pending,
+                started_at: now - Duration::minutes(45),
+            },
+            ActiveExecution {
+                execution_id: Uuid::new_v4(),
+                workflow_name: "nightly".to_string(),
+                status: ExecutionStatus::Running,
+                started_at: now - Duration::minutes(45),
+            },
+        ];
+
+        let health = summarize(project_id, &records, &active, Vec::new(), None, now);
+        assert_eq!(health.status, HealthStatus::Failing);
+        assert_eq!(health.latest_executions.len(), 5);
+        assert_eq!(health.latest_executions[0].workflow_id, Some(deploy));
+        assert_eq!(health.success_rate, Some(0.4));
+        assert_eq!(health.failing_workflows.len(), 1);
+        assert_eq!(health.failing_workflows[0].consecutive_failures, 2);
+        assert_eq!((health.running, health.stale_runs.len()), (1, 1));
+
+        let healthy = summarize(project_id, &records[2..5], &[], Vec::new(), None, now);
+        assert_eq!(healthy.status, HealthStatus::Healthy);
+    }
+}
@@ -0,0 +1,279 @@
+//! How each version of a role's system prompt performed.
+//!
+//! Prompt tweaks are easy to make and hard to judge from single runs.
+//! Provides:
+//! - Runs of a role grouped by prompt version (a hash of the system prompt)
+//! - Per version: failures, thumbs up and down, and outputs annotated as
+//!   wrong
+//! - The versions that fail or are rated badly more than the role's other
+//!   versions, with a sentence saying how much more
+//!
+//! Runs in chaos mode fail on purpose and are left out.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use uuid::Uuid;
+
+use super::chaos::CHAOS_TAG;
+use super::history::{AnnotationSeverity, ExecutionRecord, Rating};
+use super::state::NodeExecutionStatus;
+
+/// Runs a version, and the versions it is compared to, need before it can
+/// be called out
+const MIN_RUNS: usize = 3;
+/// How far a version's rate must be above the other versions' to be
+/// called out
+const SUSPECT_MARGIN: f64 = 0.2;
+/// Executions listed per version as examples of bad runs
+const MAX_EXAMPLES: usize = 5;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptVersionStats {
+    pub prompt_version: String,
+    pub runs: usize,
+    pub failures: usize,
+    /// Runs someone rated or annotated
+    pub reviewed: usize,
+    pub thumbs_up: usize,
+    pub thumbs_down: usize,
+    /// Runs annotated as wrong
+    pub marked_wrong: usize,
+    pub failure_rate: f64,
+    /// Share of reviewed runs rated down more than up, or marked wrong
+    pub bad_feedback_rate: Option<f64>,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+    /// Latest executions where this version failed or was rated badly
+    pub bad_executions: Vec<Uuid>,
+    /// Fails or is rated badly more than the other versions
+    pub suspect: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptPerformance {
+    pub role: String,
+    /// Most recently used first
+    pub versions: Vec<PromptVersionStats>,
+    /// What stands out, one sentence each
+    pub findings: Vec<String>,
+}
+
+/// One run of a node of the role
+struct Run {
+    execution_id: Uuid,
+    at: DateTime<Utc>,
+    failed: bool,
+    up: usize,
+    down: usize,
+    wrong: bool,
+}
+
+impl Run {
+    fn reviewed(&self) -> bool {
+        self.up + self.down > 0 || self.wrong
+    }
+
+    fn bad_feedback(&self) -> bool {
+        self.wrong || self.down > self.up
+    }
+}
+
+/// Compare the prompt versions `role`'s nodes ran with across `records`
+pub fn analyze(role: &str, records: &[ExecutionRecord]) -> PromptPerformance {
+    let mut runs: Vec<(String, Run)> = Vec::new();
+    for record in records.iter().filter(|record| !record.tags.iter().any(|tag| tag == CHAOS_TAG)) {
+        for node in &record.node_records {
+            let finished = matches!(node.status, NodeExecutionStatus::Completed | NodeExecutionStatus::Failed);
+            let Some(version) = node.prompt_version.as_ref().filter(|_| finished && node.agent_role == role) else {
+                continue;
+            };
+            let ratings = record.ratings.iter().filter(|rating| rating.node_id == node.node_id);
+            let (up, down) = ratings.fold((0, 0), |(up, down), rating| match rating.rating {
+                Rating::Up => (up + 1, down),
+                Rating::Down => (up, down + 1),
+            });
+            let run = Run {
+                execution_id: record.id,
+                at: node.completed_at.or(node.started_at).unwrap_or(record.started_at),
+                failed: node.status == NodeExecutionStatus::Failed,
+                up,
+                down,
+                wrong: record
+                    .annotations
+                    .iter()
+                    .any(|annotation| annotation.node_id == node.node_id && annotation.severity == AnnotationSeverity::Wrong),
+            };
+            runs.push((version.clone(), run));
+        }
+    }
+    runs.sort_by_key(|(_, run)| std::cmp::Reverse(run.at));
+
+    let mut versions: Vec<PromptVersionStats> = Vec::new();
+    for (version, run) in &runs {
+        let stats = match versions.iter_mut().position(|stats| stats.prompt_version == *version) {
+            Some(index) => &mut versions[index],
+            None => {
+                versions.push(PromptVersionStats {
+                    prompt_version: version.clone(),
+                    runs: 0,
+                    failures: 0,
+                    reviewed: 0,
+                    thumbs_up: 0,
+                    thumbs_down: 0,
+                    marked_wrong: 0,
+                    failure_rate: 0.0,
+                    bad_feedback_rate: None,
+                    first_seen: run.at,
+                    last_seen: run.at,
+                    bad_executions: Vec::new(),
+                    suspect: false,
+                });
+                versions.last_mut().unwrap()
+            }
+        };
+        stats.runs += 1;
+        stats.failures += run.failed as usize;
+        stats.reviewed += run.reviewed() as usize;
+        stats.thumbs_up += run.up;
+        stats.thumbs_down += run.down;
+        stats.marked_wrong += run.wrong as usize;
+        stats.first_seen = run.at;
+        if (run.failed || run.bad_feedback()) && stats.bad_executions.len() < MAX_EXAMPLES && !stats.bad_executions.contains(&run.execution_id) {
+            stats.bad_executions.push(run.execution_id);
+        }
+    }
+
+    // Runs rated badly and runs reviewed, of a version or of all others
+    let bad_feedback = |version: &str, same: bool| {
+        runs.iter()
+            .filter(|(v, run)| (v == version) == same && run.reviewed())
+            .fold((0, 0), |(bad, reviewed), (_, run)| (bad + run.bad_feedback() as usize, reviewed + 1))
+    };
+    let mut findings = Vec::new();
+    for stats in &mut versions {
+        stats.failure_rate = stats.failures as f64 / stats.runs as f64;
+        let (bad, reviewed) = bad_feedback(&stats.prompt_version, true);
+        stats.bad_feedback_rate = (reviewed > 0).then(|| bad as f64 / reviewed as f64);
+
+        let others: Vec<&Run> = runs.iter().filter(|(v, _)| *v != stats.prompt_version).map(|(_, run)| run).collect();
+        if stats.runs >= MIN_RUNS && others.len() >= MIN_RUNS {
+            let other_rate = others.iter().filter(|run| run.failed).count() as f64 / others.len() as f64;
+            if stats.failure_rate - other_rate >= SUSPECT_MARGIN {
+                stats.suspect = true;
+                findings.push(format!(
+                    "Prompt version {} failed {} of {} runs ({:.0}%), other versions {:.0}%",
+                    stats.prompt_version,
+                    stats.failures,
+                    stats.runs,
+                    stats.failure_rate * 100.0,
+                    other_rate * 100.0
+                ));
+            }
+        }
+        let (other_bad, other_reviewed) = bad_feedback(&stats.prompt_version, false);
+        if let Some(rate) = stats.bad_feedback_rate.filter(|_| reviewed >= MIN_RUNS && other_reviewed >= MIN_RUNS) {
+            let other_rate = other_bad as f64 / other_reviewed as f64;
+            if rate - other_rate >= SUSPECT_MARGIN {
+                stats.suspect = true;
+                findings.push(format!(
+                    "Prompt version {} was rated badly on {} of {} reviewed runs ({:.0}%), other versions {:.0}%",
+                    stats.prompt_version,
+                    bad,
+                    reviewed,
+                    rate * 100.0,
+                    other_rate * 100.0
+                ));
+            }
+        }
+    }
+    if versions.len() == 1 && versions[0].runs >= MIN_RUNS {
+        findings.push(format!("Every run used prompt version {}; there is nothing to compare it to", versions[0].prompt_version));
+    }
+
+    PromptPerformance {
+        role: role.to_string(),
+        versions,
+        findings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::history::{ExecutionRecordBuilder, NodeAnnotation, NodeExecutionRecord, NodeRating};
+    use crate::workflow::state::ExecutionStatus;
+
+    fn record(version: &str, failed: bool, rating: Option<Rating>, wrong: bool) -> ExecutionRecord {
+        let mut builder = ExecutionRecordBuilder::new(Uuid::new_v4(), Uuid::new_v4(), "Shop".to_string(), "Review".to_string());
+        let status = if failed { NodeExecutionStatus::Failed } else { NodeExecutionStatus::Completed };
+        builder.add_node_record(NodeExecutionRecord {
+            node_id: "review".to_string(),
+            node_name: "Review".to_string(),
+            agent_role: "reviewer".to_string(),
+            agent_id: None,
+            status,
+            started_at: None,
+            completed_at: Some(Utc::now()),
+            duration_ms: None,
+            queue_wait_ms: None,
+            retry_count: 0,
+            output_summary: None,
+            error: None,
+            partial_output_summary: None,
+            retry_attempts: Vec::new(),
+            tokens: None,
+            model: None,
+            prompt_version: Some(version.to_string()),
+        });
+        let mut record = builder.build(ExecutionStatus::Completed, Utc::now());
+        if let Some(rating) = rating {
+            record.ratings.push(NodeRating {
+                node_id: "review".to_string(),
+                author: "dana".to_string(),
+                rating,
+                rated_at: Utc::now(),
+            });
+        }
+        if wrong {
+            record.annotations.push(NodeAnnotation {
+                id: Uuid::new_v4(),
+                node_id: "review".to_string(),
+                author: "dana".to_string(),
+                text: "Missed the SQL injection".to_string(),
+                severity: AnnotationSeverity::Wrong,
+                created_at: Utc::now(),
+            });
+        }
+        record
+    }
+
+    #[test]
+    fn test_prompt_performance() {
+        let records = vec![
+            record("aaaa0001", false, Some(Rating::Up), false),
+            record("aaaa0001", false, Some(Rating::Up), false),
+            record("aaaa0001", true, None, false),
+            record("aaaa0001", false, Some(Rating::Up), false),
+            record("bbbb0002", false, Some(Rating::Down), false),
+            record("bbbb0002", false, None, true),
+            record("bbbb0002", false, Some(Rating::Down), false),
+            record("bbbb0002", false, Some(Rating::Up), false),
+            record("bbbb0002", true, None, false),
+        ];
+
+        let report = analyze("reviewer", &records);
+        assert_eq!(report.versions.len(), 2);
+        let version = |name: &str| report.versions.iter().find(|v| v.prompt_version == name).unwrap();
+        let (old, new) = (version("aaaa0001"), version("bbbb0002"));
+        assert_eq!((old.runs, old.failures, old.thumbs_up), (4, 1, 3));
+        assert_eq!(old.bad_feedback_rate, Some(0.0));
+        assert!(!old.suspect);
+        assert_eq!((new.reviewed, new.marked_wrong, new.bad_feedback_rate), (4, 1, Some(0.75)));
+        assert!(new.suspect);
+        assert_eq!(new.bad_executions.len(), 4);
+        assert_eq!(report.findings.len(), 1);
+        assert!(report.findings[0].starts_with("Prompt version bbbb0002 was rated badly on 3 of 4"));
+
+        assert!(analyze("tester", &records).versions.is_empty());
+    }
+}
@@ -0,0 +1,232 @@
+//! The prompt a node's agent would be given, without running it.
+//!
+//! Data-flow mistakes (a predecessor filtered out, a variable never set,
+//! context in the wrong order) otherwise only show up in an agent's answer.
+//! Provides:
+//! - The system prompt and the task with predecessor context, assembled the
+//!   way the enhanced executor does it
+//! - Which predecessors contributed, and which had no output to give
+//! - Token counts for both, for the node's model
+//!
+//! Inbox messages and impact-scope sections are added only at run time,
+//! since delivering them consumes them; the preview lists them when the
+//! node would get them.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::context::{AgentOutput, ExecutionContext, OutputData};
+use super::enhanced_executor::{EnhancedExecutionConfig, EnhancedNodeConfig};
+use super::graph::WorkflowGraph;
+use super::locale;
+use super::tokenizer::{Encoding, TOKENIZERS};
+
+/// What the node's prompt is built from
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PromptInputs {
+    /// The execution's input, the task of nodes without their own
+    pub input_prompt: String,
+    /// Outputs by node; predecessors missing here get a placeholder
+    #[serde(default)]
+    pub outputs: HashMap<String, Vec<AgentOutput>>,
+    #[serde(default)]
+    pub variables: HashMap<String, serde_json::Value>,
+    /// The project's locale, whose language instruction joins the system prompt
+    #[serde(default)]
+    pub locale: Option<String>,
+}
+
+/// Tokens of each part of the prompt
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptTokens {
+    pub system_prompt: usize,
+    pub prompt: usize,
+    pub total: usize,
+    pub encoding: Encoding,
+    /// Whether the counts are exact, or estimates for lack of the
+    /// encoding's merge table
+    pub exact: bool,
+}
+
+/// The prompt a node would be given
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PromptPreview {
+    pub node_id: String,
+    pub agent_role: String,
+    pub model: Option<String>,
+    pub system_prompt: Option<String>,
+    pub prompt: String,
+    /// Predecessors, in graph order
+    pub predecessors: Vec<String>,
+    /// Predecessors with no output, shown as placeholders in the prompt
+    pub placeholders: Vec<String>,
+    /// Sections added at run time that the preview leaves out
+    pub not_included: Vec<String>,
+    pub tokens: PromptTokens,
+}
+
+/// Assemble the prompt of `node_id` from `inputs`
+pub fn preview(
+    graph: &WorkflowGraph,
+    node_id: &str,
+    config: &EnhancedExecutionConfig,
+    node_configs: &HashMap<String, EnhancedNodeConfig>,
+    inputs: &PromptInputs,
+) -> Result<PromptPreview, String> {
+    let node = graph
+        .get_node(node_id)
+        .ok_or_else(|| format!("Node '{}' not found in the graph", node_id))?;
+    if !node.kind.is_agent() {
+        return Err(format!("Node '{}' doesn't run an agent", node_id));
+    }
+    let node_config = node_configs.get(node_id).cloned().unwrap_or_default();
+
+    let context = ExecutionContext::new(Uuid::nil(), Uuid::nil(), inputs.input_prompt.clone());
+    for (key, value) in &inputs.variables {
+        context.set_variable(key, value.clone());
+    }
+    let predecessors = graph.dependencies(node_id);
+    let mut placeholders = Vec::new();
+    for predecessor in predecessors {
+        let outputs = inputs.outputs.get(predecessor).cloned().unwrap_or_default();
+        if outputs.is_empty() {
+            placeholders.push(predecessor.clone());
+            // With the output tags the predecessor would add, so placeholders pass tag filters
+            let role = graph.get_node(predecessor).map(|p| p.agent_role.clone()).unwrap_or_default();
+            context.store_output(AgentOutput {
+                agent_id: Uuid::nil(),
+                node_id: predecessor.clone(),
+                agent_role: role,
+                data: OutputData::Text(format!("<output of {}>", predecessor)),
+                timestamp: context.started_at,
+                tags: node_configs.get(predecessor).map(|c| c.output_tags.clone()).unwrap_or_default(),
+            });
+        }
+        for output in outputs {
+            context.store_output(output);
+        }
+    }
+
+    // As in spawn_enhanced_node_execution
+    let base_task = node.assigned_task.clone().unwrap_or_else(|| inputs.input_prompt.clone());
+    let mut prompt = if config.enable_data_flow && !predecessors.is_empty() {
+        match &node_config.aggregation {
+            Some(aggregation) => {
+                let predecessor_context = aggregation
+                    .apply(context.get_predecessor_outputs(predecessors))
+                    .map(|aggregated| aggregated.to_prompt_context())
+                    .unwrap_or_default();
+                context.build_prompt_with_context(&base_task, &predecessor_context, config.include_original_prompt)
+            }
+            None => context.build_agent_prompt(&base_task, predecessors, config.include_original_prompt),
+        }
+    } else {
+        base_task
+    };
+    if let Some(instructions) = node_config.output_instructions() {
+        prompt = format!("{}\n\n{}", prompt, instructions);
+    }
+
+    let mut not_included = Vec::new();
+    let role_topics = config.role_topics.get(&node.agent_role).is_some_and(|topics| !topics.is_empty());
+    if role_topics || !node_config.subscribe_topics.is_empty() {
+        not_included.push("messages".to_string());
+    }
+    if node_config.impact_scope.is_some() {
+        not_included.push("impact_scope".to_string());
+    }
+
+    let system_prompt = node_config.system_prompt_override.clone().or_else(|| node.system_prompt.clone());
+    let system_prompt = locale::with_language_instruction(system_prompt, inputs.locale.as_deref());
+    let encoding = Encoding::for_model(node_config.model.as_deref());
+    let system_tokens = system_prompt.as_deref().map_or(0, |text| TOKENIZERS.count(text, encoding).tokens);
+    let prompt_tokens = TOKENIZERS.count(&prompt, encoding);
+    Ok(PromptPreview {
+        node_id: node_id.to_string(),
+        agent_role: node.agent_role.clone(),
+        model: node_config.model.clone(),
+        system_prompt,
+        prompt,
+        predecessors: predecessors.to_vec(),
+        placeholders,
+        not_included,
+        tokens: PromptTokens {
+            system_prompt: system_tokens,
+            prompt: prompt_tokens.tokens,
+            total: system_tokens + prompt_tokens.tokens,
+            encoding,
+            exact: prompt_tokens.exact,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::aggregation::{AggregationStrategy, NodeAggregationConfig};
+    use serde_json::json;
+
+    #[test]
+    fn test_preview_with_placeholders() {
+        let graph = WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "design", "data": {"label": "Design", "agentRole": "architect"}},
+                {"id": "audit", "data": {"label": "Audit", "agentRole": "security"}},
+                {"id": "build", "data": {
+                    "label": "Build",
+                    "agentRole": "implementer",
+                    "systemPrompt": "You write Rust.",
+                    "assignedTask": "Implement the design"
+                }}
+            ],
+            "edges": [
+                {"id": "e1", "source": "design", "target": "build"},
+                {"id": "e2", "source": "audit", "target": "build"}
+            ]
+        }))
+        .unwrap();
+        let inputs = PromptInputs {
+            input_prompt: "Add login".to_string(),
+            outputs: HashMap::from([(
+                "design".to_string(),
+                vec![AgentOutput {
+                    agent_id: Uuid::nil(),
+                    node_id: "design".to_string(),
+                    agent_role: "architect".to_string(),
+                    data: OutputData::Text("Use sessions".to_string()),
+                    timestamp: chrono::Utc::now(),
+                    tags: Vec::new(),
+                }],
+            )]),
+            variables: HashMap::from([("branch".to_string(), json!("feature/login"))]),
+            locale: None,
+        };
+        let node_configs = HashMap::from([(
+            "build".to_string(),
+            EnhancedNodeConfig {
+                aggregation: Some(NodeAggregationConfig {
+                    strategy: AggregationStrategy::Concatenate {
+                        separator: "\n".to_string(),
+                        include_source: true,
+                    },
+                    ..Default::default()
+                }),
+                ..Default::default()
+            },
+        )]);
+
+        let config = EnhancedExecutionConfig::default();
+        let preview = preview(&graph, "build", &config, &node_configs, &inputs).unwrap();
+        assert_eq!(preview.system_prompt.as_deref(), Some("You write Rust."));
+        assert_eq!(preview.placeholders, vec!["audit".to_string()]);
+        assert!(preview.prompt.starts_with("=== Original User Request ===\nAdd login"));
+        assert!(preview.prompt.contains("[From design (architect)]\nUse sessions"));
+        assert!(preview.prompt.contains("[From audit (security)]\n<output of audit>"));
+        assert!(preview.prompt.contains("branch: \"feature/login\""));
+        assert!(preview.prompt.ends_with("=== Your Task ===\nImplement the design"));
+        assert_eq!(preview.tokens.total, preview.tokens.system_prompt + preview.tokens.prompt);
+
+        assert!(super::preview(&graph, "missing", &config, &node_configs, &inputs).is_err());
+    }
+}
@@ -0,0 +1,231 @@
+//! Fuzzy search across everything that can be run or opened.
+//!
+//! Backs the command palette and "run by name" actions. Provides:
+//! - Fuzzy matching of a query against names, so `rvw pr` finds
+//!   "Review pull request", with the matched characters for highlighting
+//! - Matching of descriptions, tags and prompts, counted for less
+//! - Ranking of workflows, templates, projects and executions together,
+//!   with favorites and often used items ahead of equal matches
+//! - Telling a match sure enough to act on without asking from a loose one
+
+use serde::Serialize;
+
+/// Text longer than this, such as prompts, is only searched for the
+/// query's words, not fuzzily: nearly any query is a subsequence of it
+const MAX_FUZZY_LEN: usize = 64;
+/// Keywords count for this much of a title match
+const KEYWORD_WEIGHT: f64 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchKind {
+    Workflow,
+    Template,
+    Project,
+    Execution,
+}
+
+/// Something the query is matched against
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub kind: MatchKind,
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    /// Descriptions, tags, prompts
+    pub keywords: Vec<String>,
+    /// Added to the score, for favorites and often used items
+    pub boost: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuickMatch {
+    pub kind: MatchKind,
+    pub id: String,
+    pub title: String,
+    pub subtitle: Option<String>,
+    pub score: f64,
+    /// Character positions of the title the query matched
+    pub highlights: Vec<usize>,
+}
+
+fn word_start(chars: &[char], index: usize) -> bool {
+    index == 0 || !chars[index - 1].is_alphanumeric() || (chars[index].is_uppercase() && chars[index - 1].is_lowercase())
+}
+
+/// How well `query` matches `text`, and the characters of `text` it
+/// matched; none unless every character of `query` appears in order
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(f64, Vec<usize>)> {
+    let query: Vec<char> = query.to_lowercase().chars().collect();
+    let chars: Vec<char> = text.chars().collect();
+    let lower: Vec<char> = text.to_lowercase().chars().collect();
+    if query.is_empty() || lower.len() != chars.len() {
+        return None;
+    }
+
+    // A contiguous match beats any scattered one
+    if let Some(start) = (0..chars.len()).find(|&i| lower[i..].starts_with(&query)) {
+        let mut score = query.len() as f64 * 2.0 + 3.0;
+        if word_start(&chars, start) {
+            score += if start == 0 { 4.0 } else { 2.0 };
+        }
+        return Some((score, (start..start + query.len()).collect()));
+    }
+    if chars.len() > MAX_FUZZY_LEN {
+        return None;
+    }
+
+    // Jumping ahead to word starts can pass characters needed later; then
+    // take each character where it first appears
+    subsequence(&query, &chars, &lower, true).or_else(|| subsequence(&query, &chars, &lower, false))
+}
+
+fn subsequence(query: &[char], chars: &[char], lower: &[char], prefer_word_starts: bool) -> Option<(f64, Vec<usize>)> {
+    let mut positions: Vec<usize> = Vec::with_capacity(query.len());
+    let mut score = 0.0;
+    let mut next = 0;
+    for c in query {
+        let continues = !positions.is_empty() && lower.get(next) == Some(c);
+        let found = (prefer_word_starts && !continues)
+            .then(|| (next..chars.len()).find(|&i| lower[i] == *c && word_start(chars, i)))
+            .flatten()
+            .or_else(|| (next..chars.len()).find(|&i| lower[i] == *c))?;
+        score += 1.0;
+        if word_start(chars, found) {
+            score += 2.0;
+        }
+        match positions.last() {
+            Some(&last) if found == last + 1 => score += 1.5,
+            Some(&last) => score -= 0.1 * (found - last - 1) as f64,
+            None => score -= 0.05 * found as f64,
+        }
+        positions.push(found);
+        next = found + 1;
+    }
+    Some((score, positions))
+}
+
+/// Whether every word of `query` matches `title` as a run of characters
+/// or as the starts of its words ("rpr" for "Review pull request"), not
+/// just scattered letters or keywords; only such matches are acted on
+/// without asking
+pub fn is_confident(query: &str, title: &str) -> bool {
+    let chars: Vec<char> = title.chars().collect();
+    let mut words = query.split_whitespace().peekable();
+    words.peek().is_some()
+        && words.all(|word| {
+            fuzzy_match(word, title).is_some_and(|(_, positions)| {
+                let contiguous = positions.windows(2).all(|pair| pair[1] == pair[0] + 1);
+                contiguous || positions.iter().all(|&i| word_start(&chars, i))
+            })
+        })
+}
+
+/// Score of one word of the query against a candidate; title highlights
+/// are added to `highlights`
+fn match_word(word: &str, candidate: &Candidate, highlights: &mut Vec<usize>) -> Option<f64> {
+    let title = fuzzy_match(word, &candidate.title);
+    let keyword = candidate
+        .keywords
+        .iter()
+        .filter_map(|keyword| fuzzy_match(word, keyword))
+        .map(|(score, _)| score * KEYWORD_WEIGHT)
+        .fold(None, |best: Option<f64>, score| Some(best.map_or(score, |best| best.max(score))));
+    match (title, keyword) {
+        (Some((score, positions)), keyword) if keyword.map_or(true, |keyword| score >= keyword) => {
+            highlights.extend(positions);
+            Some(score)
+        }
+        (_, keyword) => keyword,
+    }
+}
+
+/// The candidates matching every word of `query`, best first. An empty
+/// query lists them by boost alone.
+pub fn rank(query: &str, candidates: Vec<Candidate>, limit: usize) -> Vec<QuickMatch> {
+    let words: Vec<&str> = query.split_whitespace().collect();
+    let mut matches: Vec<QuickMatch> = candidates
+        .into_iter()
+        .filter_map(|candidate| {
+            let mut highlights = Vec::new();
+            let mut score = candidate.boost;
+            for word in &words {
+                score += match_word(word, &candidate, &mut highlights)?;
+            }
+            highlights.sort_unstable();
+            highlights.dedup();
+            Some(QuickMatch {
+                kind: candidate.kind,
+                id: candidate.id,
+                title: candidate.title,
+                subtitle: candidate.subtitle,
+                score,
+                highlights,
+            })
+        })
+        .filter(|found| !words.is_empty() || found.score > 0.0)
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.title.cmp(&b.title)));
+    matches.truncate(limit);
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(kind: MatchKind, title: &str, keywords: &[&str], boost: f64) -> Candidate {
+        Candidate {
+            kind,
+            id: title.to_lowercase(),
+            title: title.to_string(),
+            subtitle: None,
+            keywords: keywords.iter().map(|keyword| keyword.to_string()).collect(),
+            boost,
+        }
+    }
+
+    #[test]
+    fn test_fuzzy_ranking() {
+        assert_eq!(fuzzy_match("rpr", "Review pull request").map(|m| m.1), Some(vec![0, 7, 12]));
+        assert_eq!(fuzzy_match("pull", "Review pull request").map(|m| m.1), Some(vec![7, 8, 9, 10]));
+        assert!(fuzzy_match("rqp", "Review pull request").is_none());
+        assert!(fuzzy_match("sec", "securityAudit").unwrap().0 > fuzzy_match("sec", "fix specs").unwrap().0);
+
+        let candidates = vec![
+            candidate(MatchKind::Workflow, "Review pull request", &["code review"], 0.0),
+            candidate(MatchKind::Template, "Release notes", &["changelog"], 0.0),
+            candidate(MatchKind::Project, "Shop", &["Rust", "TypeScript"], 0.0),
+            candidate(
+                MatchKind::Execution,
+                "Nightly build",
+                &["Rebuild the release artifacts and upload them to the staging bucket"],
+                0.0,
+            ),
+        ];
+        let titles = |matches: Vec<QuickMatch>| matches.into_iter().map(|m| m.title).collect::<Vec<_>>();
+        assert_eq!(titles(rank("rvw pr", candidates.clone(), 10)), ["Review pull request"]);
+        assert_eq!(titles(rank("release", candidates.clone(), 10)), ["Release notes", "Nightly build"]);
+        assert_eq!(titles(rank("changelog", candidates.clone(), 10)), ["Release notes"]);
+        assert_eq!(titles(rank("rust shop", candidates.clone(), 10)), ["Shop"]);
+        // Long text is searched for words only
+        assert!(rank("rbsb", candidates.clone(), 10).is_empty());
+
+        // Equal matches go to the often used one; no query lists by use
+        let mut boosted = candidates.clone();
+        boosted.push(candidate(MatchKind::Workflow, "Release notes", &[], 2.0));
+        assert_eq!(rank("release", boosted.clone(), 10)[0].kind, MatchKind::Workflow);
+        assert_eq!(titles(rank("", boosted, 10)), ["Release notes"]);
+    }
+
+    #[test]
+    fn test_confident_matches() {
+        assert!(is_confident("rpr", "Review pull request"));
+        assert!(is_confident("review pull", "Review pull request"));
+        assert!(is_confident("deploy", "Staging deploy"));
+        // Scattered letters and keyword-only matches aren't enough
+        assert!(!is_confident("rvw", "Review pull request"));
+        assert!(!is_confident("changelog", "Release notes"));
+        assert!(!is_confident("", "Release notes"));
+    }
+}
@@ -0,0 +1,239 @@
+//! Token buckets limiting how fast agents are started.
+//!
+//! Provides:
+//! - Buckets refilled continuously at their per-minute rate, so a request
+//!   never waits longer than one token takes to come back
+//! - A burst size: how many tokens a bucket holds, and so how many requests
+//!   can go out at once after a quiet period
+//! - Buckets per backend and credential, so one saturated provider or API
+//!   key doesn't throttle another
+//! - Status of a bucket: tokens left, when the next one arrives, and how
+//!   many requests it let through or refused
+
+use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// A rate limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RateLimit {
+    /// Requests per minute, refilled evenly over the minute
+    pub per_minute: u32,
+    /// Tokens the bucket holds; the per-minute rate when unset
+    #[serde(default)]
+    pub burst: Option<u32>,
+}
+
+impl RateLimit {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.per_minute == 0 {
+            return Err("must allow at least 1 request per minute".to_string());
+        }
+        if self.burst == Some(0) {
+            return Err("burst must be at least 1, or unset".to_string());
+        }
+        Ok(())
+    }
+
+    pub fn burst(&self) -> u32 {
+        self.burst.unwrap_or(self.per_minute)
+    }
+
+    /// Time one token takes to come back
+    fn token_interval(&self) -> Duration {
+        Duration::from_secs_f64(60.0 / self.per_minute as f64)
+    }
+}
+
+/// The rate limit of one API key of a backend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CredentialRateLimit {
+    pub backend: String,
+    /// The key's label, see [`credential_label`]
+    pub credential: String,
+    #[serde(flatten)]
+    pub limit: RateLimit,
+}
+
+/// What a bucket limits: a backend, or one credential of it
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct RateLimitKey {
+    pub backend: String,
+    pub credential: Option<String>,
+}
+
+impl RateLimitKey {
+    pub fn backend(backend: &str) -> Self {
+        Self {
+            backend: backend.to_string(),
+            credential: None,
+        }
+    }
+
+    pub fn credential(mut self, credential: &str) -> Self {
+        self.credential = Some(credential.to_string());
+        self
+    }
+}
+
+/// Label of a raw API key that tells keys apart without revealing them:
+/// its last four characters. Named secrets go by their name instead.
+pub fn credential_label(key: &str) -> String {
+    let tail: String = key.chars().rev().take(4).collect::<Vec<_>>().into_iter().rev().collect();
+    format!("...{}", tail)
+}
+
+/// Tokens left in a bucket, as shown to users
+#[derive(Debug, Clone, Serialize)]
+pub struct RateLimitStatus {
+    /// Backend the bucket limits; none for the global bucket
+    pub backend: Option<String>,
+    /// Credential the bucket limits; none for a whole backend
+    pub credential: Option<String>,
+    pub per_minute: u32,
+    pub burst: u32,
+    pub tokens_remaining: u32,
+    /// When the next token arrives; none while the bucket is full
+    pub next_refill_at: Option<DateTime<Utc>>,
+    /// Requests let through
+    pub allowed: u64,
+    /// Requests refused for lack of tokens
+    pub throttled: u64,
+}
+
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+/// A token bucket; without a limit every request passes
+pub struct RateLimiter {
+    limit: Option<RateLimit>,
+    bucket: Mutex<Bucket>,
+    allowed: AtomicU64,
+    throttled: AtomicU64,
+}
+
+impl RateLimiter {
+    pub fn new(limit: Option<RateLimit>) -> Self {
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: limit.map_or(0.0, |limit| limit.burst() as f64),
+                refilled_at: Instant::now(),
+            }),
+            limit,
+            allowed: AtomicU64::new(0),
+            throttled: AtomicU64::new(0),
+        }
+    }
+
+    pub fn limit(&self) -> Option<RateLimit> {
+        self.limit
+    }
+
+    /// Take a token, if there is one
+    pub fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        let Some(limit) = self.limit else {
+            return true;
+        };
+        let mut bucket = self.bucket.lock();
+        refill(&mut bucket, &limit, now);
+        if bucket.tokens < 1.0 {
+            self.throttled.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        self.allowed.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Put back a token taken for a request that didn't go out
+    pub fn refund(&self) {
+        if let Some(limit) = self.limit {
+            let mut bucket = self.bucket.lock();
+            bucket.tokens = (bucket.tokens + 1.0).min(limit.burst() as f64);
+            self.allowed.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Tokens left; none without a limit
+    pub fn status(&self, key: Option<&RateLimitKey>) -> Option<RateLimitStatus> {
+        self.status_at(key, Instant::now())
+    }
+
+    fn status_at(&self, key: Option<&RateLimitKey>, now: Instant) -> Option<RateLimitStatus> {
+        let limit = self.limit?;
+        let mut bucket = self.bucket.lock();
+        refill(&mut bucket, &limit, now);
+        let burst = limit.burst();
+        let next_refill_at = (bucket.tokens < burst as f64).then(|| {
+            let wait = limit.token_interval().mul_f64(1.0 - bucket.tokens.fract());
+            Utc::now() + chrono::Duration::from_std(wait).unwrap_or_else(|_| chrono::Duration::zero())
+        });
+        Some(RateLimitStatus {
+            backend: key.map(|key| key.backend.clone()),
+            credential: key.and_then(|key| key.credential.clone()),
+            per_minute: limit.per_minute,
+            burst,
+            tokens_remaining: bucket.tokens as u32,
+            next_refill_at,
+            allowed: self.allowed.load(Ordering::Relaxed),
+            throttled: self.throttled.load(Ordering::Relaxed),
+        })
+    }
+}
+
+/// Add the tokens earned since the last refill, up to the burst size
+fn refill(bucket: &mut Bucket, limit: &RateLimit, now: Instant) {
+    let elapsed = now.saturating_duration_since(bucket.refilled_at);
+    let earned = elapsed.as_secs_f64() * limit.per_minute as f64 / 60.0;
+    bucket.tokens = (bucket.tokens + earned).min(limit.burst() as f64);
+    bucket.refilled_at = now;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_smooth_refill_and_burst() {
+        let limiter = RateLimiter::new(Some(RateLimit {
+            per_minute: 60,
+            burst: Some(3),
+        }));
+        let start = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.try_acquire_at(start));
+        }
+        assert!(!limiter.try_acquire_at(start));
+
+        // One token a second, not six at once after six seconds
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(900)));
+        assert!(limiter.try_acquire_at(start + Duration::from_millis(1_000)));
+        assert!(!limiter.try_acquire_at(start + Duration::from_millis(1_500)));
+
+        // Never more than the burst, however long it was quiet
+        let status = limiter.status_at(None, start + Duration::from_secs(600)).unwrap();
+        assert_eq!(status.tokens_remaining, 3);
+        assert!(status.next_refill_at.is_none());
+
+        assert!(limiter.try_acquire_at(start + Duration::from_secs(600)));
+        let key = RateLimitKey::backend("anthropic");
+        let status = limiter.status_at(Some(&key), start + Duration::from_secs(600)).unwrap();
+        assert_eq!((status.tokens_remaining, status.backend.as_deref()), (2, Some("anthropic")));
+        assert!(status.next_refill_at.is_some());
+        assert_eq!((status.allowed, status.throttled), (5, 3));
+        assert_eq!(credential_label("sk-ant-api03-secret-wxyz"), "...wxyz");
+
+        let unlimited = RateLimiter::new(None);
+        assert!((0..1_000).all(|_| unlimited.try_acquire()));
+        assert!(unlimited.status(None).is_none());
+        assert!(RateLimit { per_minute: 10, burst: Some(0) }.validate().is_err());
+    }
+}
@@ -0,0 +1,609 @@
+//! Execution report generation.
+//!
+//! Summarizes an execution for humans:
+//! - The plan (execution levels)
+//! - Per-node status, timing and output
+//! - Metrics (duration, success counts)
+//! - Diffs found in agent outputs
+//! - Assertion and gate verdicts
+//!
+//! Reports render to Markdown, HTML or PDF.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+use uuid::Uuid;
+
+use crate::integrations::{Attachment, EmailMessage};
+
+use super::assertions::AssertionOutcome;
+use super::context::{ExecutionContext, OutputData};
+use super::patch::resolves_inside;
+use super::state::{ExecutionStatus, NodeExecutionStatus, WorkflowExecutionState};
+use super::text;
+
+/// Longest node output included in a report, in characters
+const MAX_OUTPUT_CHARS: usize = 4_000;
+
+/// Where to write an export given as `path` under the project directory
+/// `working_dir`. Absolute paths and paths leaving the directory, through
+/// `..` or a symlink, are refused.
+pub fn resolve_output_path(working_dir: &Path, path: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(path);
+    let lexically_inside = !path.is_empty()
+        && relative.components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir));
+    let full = working_dir.join(relative);
+    if !lexically_inside || !resolves_inside(working_dir, &full) {
+        return Err(format!("Output path '{}' must be relative and inside the project directory", path));
+    }
+    Ok(full)
+}
+
+/// Output format for a report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Html,
+    Pdf,
+}
+
+impl ReportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "md",
+            ReportFormat::Html => "html",
+            ReportFormat::Pdf => "pdf",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ReportFormat::Markdown => "text/markdown",
+            ReportFormat::Html => "text/html",
+            ReportFormat::Pdf => "application/pdf",
+        }
+    }
+}
+
+/// One node's section of a report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NodeReport {
+    pub node_id: String,
+    pub status: NodeExecutionStatus,
+    pub duration_ms: Option<u64>,
+    pub output: Option<String>,
+    pub error: Option<String>,
+}
+
+/// A diff found in a node's output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportDiff {
+    pub node_id: String,
+    pub content: String,
+}
+
+/// Summary numbers for a report
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportMetrics {
+    pub total_nodes: usize,
+    pub completed_nodes: usize,
+    pub failed_nodes: usize,
+    pub skipped_nodes: usize,
+    pub duration_ms: Option<u64>,
+    pub avg_node_duration_ms: Option<u64>,
+}
+
+/// Everything a rendered report shows
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionReport {
+    pub execution_id: Uuid,
+    pub workflow_id: Uuid,
+    pub project_id: Uuid,
+    pub status: ExecutionStatus,
+    pub input_prompt: String,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub levels: Vec<Vec<String>>,
+    pub nodes: Vec<NodeReport>,
+    pub metrics: ReportMetrics,
+    pub diffs: Vec<ReportDiff>,
+    /// Assertion, quality-gate and check-gate verdicts, in the order recorded
+    #[serde(default)]
+    pub checks: Vec<AssertionOutcome>,
+}
+
+impl ExecutionReport {
+    /// Build a report from an execution's state and, when available, its context
+    pub fn build(state: &WorkflowExecutionState, context: Option<&ExecutionContext>) -> Self {
+        let mut nodes = Vec::new();
+        let mut diffs = Vec::new();
+
+        for node_id in state.execution_levels.iter().flatten() {
+            let Some(node_state) = state.get_node_state(node_id) else {
+                continue;
+            };
+
+            let latest = context.and_then(|ctx| ctx.get_latest_output(node_id));
+            if let Some(output) = &latest {
+                diffs.extend(extract_diffs(&output.data).into_iter().map(|content| ReportDiff {
+                    node_id: node_id.clone(),
+                    content,
+                }));
+            }
+
+            let duration_ms = match (node_state.started_at, node_state.completed_at) {
+                (Some(start), Some(end)) => Some((end - start).num_milliseconds().max(0) as u64),
+                _ => None,
+            };
+
+            nodes.push(NodeReport {
+                node_id: node_id.clone(),
+                status: node_state.status,
+                duration_ms,
+                output: latest
+                    .map(|o| o.data.to_context_string())
+                    .or(node_state.output)
+                    .map(|o| truncate_chars(&o, MAX_OUTPUT_CHARS)),
+                error: node_state.error,
+            });
+        }
+
+        let count = |status: NodeExecutionStatus| nodes.iter().filter(|n| n.status == status).count();
+        let durations: Vec<u64> = nodes.iter().filter_map(|n| n.duration_ms).collect();
+        let completed_at = *state.completed_at.read();
+
+        let metrics = ReportMetrics {
+            total_nodes: nodes.len(),
+            completed_nodes: count(NodeExecutionStatus::Completed),
+            failed_nodes: count(NodeExecutionStatus::Failed),
+            skipped_nodes: count(NodeExecutionStatus::Skipped),
+            duration_ms: completed_at.map(|end| (end - state.started_at).num_milliseconds().max(0) as u64),
+            avg_node_duration_ms: if durations.is_empty() {
+                None
+            } else {
+                Some(durations.iter().sum::<u64>() / durations.len() as u64)
+            },
+        };
+
+        Self {
+            execution_id: state.execution_id,
+            workflow_id: state.workflow_id,
+            project_id: state.project_id,
+            status: state.get_status(),
+            input_prompt: state.input_prompt.clone(),
+            started_at: state.started_at,
+            completed_at,
+            levels: state.execution_levels.clone(),
+            nodes,
+            metrics,
+            diffs,
+            checks: context.map(|ctx| ctx.get_assertions()).unwrap_or_default(),
+        }
+    }
+
+    /// Render in the given format
+    pub fn render(&self, format: ReportFormat) -> Vec<u8> {
+        match format {
+            ReportFormat::Markdown => self.to_markdown().into_bytes(),
+            ReportFormat::Html => self.to_html().into_bytes(),
+            ReportFormat::Pdf => text_to_pdf(&self.to_markdown()),
+        }
+    }
+
+    /// Default location for a report inside a project directory
+    pub fn default_path(&self, working_dir: &Path, format: ReportFormat) -> PathBuf {
+        working_dir
+            .join("reports")
+            .join(format!("{}.{}", self.execution_id, format.extension()))
+    }
+
+    /// Render and write to `path`, creating parent directories
+    pub fn write_to(&self, path: &Path, format: ReportFormat) -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.render(format))
+    }
+
+    /// Build an email with a short summary body and the report attached
+    pub fn email_message(&self, to: Vec<String>, subject: Option<String>, format: ReportFormat) -> EmailMessage {
+        let m = &self.metrics;
+        let body = format!(
+            "Execution {} status: {:?}\n\n\
+             Nodes: {} completed, {} failed, {} skipped of {}\nDuration: {}\n\n\
+             The full report is attached.\n",
+            self.execution_id,
+            self.status,
+            m.completed_nodes,
+            m.failed_nodes,
+            m.skipped_nodes,
+            m.total_nodes,
+            format_ms(m.duration_ms)
+        );
+
+        EmailMessage {
+            to,
+            subject: subject.unwrap_or_else(|| format!("Nexus execution report {}", self.execution_id)),
+            body,
+            attachment: Some(Attachment {
+                filename: format!("report-{}.{}", self.execution_id, format.extension()),
+                mime_type: format.mime_type().to_string(),
+                data: self.render(format),
+            }),
+        }
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut md = format!("# Execution Report: {}\n\n", self.execution_id);
+        md.push_str(&format!("- **Status:** {:?}\n", self.status));
+        md.push_str(&format!("- **Started:** {}\n", self.started_at.to_rfc3339()));
+        if let Some(completed_at) = self.completed_at {
+            md.push_str(&format!("- **Completed:** {}\n", completed_at.to_rfc3339()));
+        }
+        md.push_str(&format!("\n## Input\n\n{}\n", self.input_prompt));
+
+        md.push_str("\n## Plan\n\n");
+        for (i, level) in self.levels.iter().enumerate() {
+            md.push_str(&format!("{}. {}\n", i + 1, level.join(", ")));
+        }
+
+        let m = &self.metrics;
+        md.push_str("\n## Metrics\n\n| Metric | Value |\n|---|---|\n");
+        md.push_str(&format!("| Nodes | {} |\n", m.total_nodes));
+        md.push_str(&format!("| Completed | {} |\n", m.completed_nodes));
+        md.push_str(&format!("| Failed | {} |\n", m.failed_nodes));
+        md.push_str(&format!("| Skipped | {} |\n", m.skipped_nodes));
+        md.push_str(&format!("| Duration | {} |\n", format_ms(m.duration_ms)));
+        md.push_str(&format!("| Avg node duration | {} |\n", format_ms(m.avg_node_duration_ms)));
+
+        if !self.checks.is_empty() {
+            md.push_str("\n## Checks\n\n| Node | Target | Result | Message |\n|---|---|---|---|\n");
+            for check in &self.checks {
+                md.push_str(&format!(
+                    "| {} | {} | {} | {} |\n",
+                    check.node_id,
+                    check.target,
+                    if check.passed { "Passed" } else { "Failed" },
+                    check.message.replace('|', "\\|").replace('\n', " ")
+                ));
+            }
+        }
+
+        md.push_str("\n## Nodes\n");
+        for node in &self.nodes {
+            md.push_str(&format!(
+                "\n### {} ({:?}, {})\n\n",
+                node.node_id,
+                node.status,
+                format_ms(node.duration_ms)
+            ));
+            if let Some(error) = &node.error {
+                md.push_str(&format!("**Error:** {}\n\n", error));
+            }
+            if let Some(output) = &node.output {
+                md.push_str(&format!("```\n{}\n```\n", output));
+            }
+        }
+
+        if !self.diffs.is_empty() {
+            md.push_str("\n## Diffs\n");
+            for diff in &self.diffs {
+                md.push_str(&format!("\n### {}\n\n```diff\n{}\n```\n", diff.node_id, diff.content));
+            }
+        }
+
+        md
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>Execution {id}</title>\
+             <style>body{{font-family:sans-serif;max-width:960px;margin:2em auto}}\
+             pre{{background:#f4f4f4;padding:1em;overflow:auto}}\
+             table{{border-collapse:collapse}}td,th{{border:1px solid #ccc;padding:4px 8px}}\
+             .failed{{color:#b00}}.completed{{color:#080}}</style></head><body>\n\
+             <h1>Execution Report: {id}</h1>\n",
+            id = self.execution_id
+        );
+
+        html.push_str(&format!(
+            "<p><b>Status:</b> {:?}<br><b>Started:</b> {}</p>\n",
+            self.status,
+            self.started_at.to_rfc3339()
+        ));
+        html.push_str(&format!("<h2>Input</h2>\n<pre>{}</pre>\n", escape_html(&self.input_prompt)));
+
+        html.push_str("<h2>Plan</h2>\n<ol>\n");
+        for level in &self.levels {
+            html.push_str(&format!("<li>{}</li>\n", escape_html(&level.join(", "))));
+        }
+        html.push_str("</ol>\n");
+
+        let m = &self.metrics;
+        html.push_str("<h2>Metrics</h2>\n<table>\n");
+        for (name, value) in [
+            ("Nodes", m.total_nodes.to_string()),
+            ("Completed", m.completed_nodes.to_string()),
+            ("Failed", m.failed_nodes.to_string()),
+            ("Skipped", m.skipped_nodes.to_string()),
+            ("Duration", format_ms(m.duration_ms)),
+            ("Avg node duration", format_ms(m.avg_node_duration_ms)),
+        ] {
+            html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", name, value));
+        }
+        html.push_str("</table>\n");
+
+        if !self.checks.is_empty() {
+            html.push_str("<h2>Checks</h2>\n<table>\n<tr><th>Node</th><th>Target</th><th>Result</th><th>Message</th></tr>\n");
+            for check in &self.checks {
+                let (class, result) = if check.passed { ("completed", "Passed") } else { ("failed", "Failed") };
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td></tr>\n",
+                    escape_html(&check.node_id),
+                    escape_html(&check.target),
+                    class,
+                    result,
+                    escape_html(&check.message)
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("<h2>Nodes</h2>\n");
+
+        for node in &self.nodes {
+            let status = format!("{:?}", node.status).to_lowercase();
+            html.push_str(&format!(
+                "<h3>{} <span class=\"{}\">({}, {})</span></h3>\n",
+                escape_html(&node.node_id),
+                status,
+                status,
+                format_ms(node.duration_ms)
+            ));
+            if let Some(error) = &node.error {
+                html.push_str(&format!("<p class=\"failed\"><b>Error:</b> {}</p>\n", escape_html(error)));
+            }
+            if let Some(output) = &node.output {
+                html.push_str(&format!("<pre>{}</pre>\n", escape_html(output)));
+            }
+        }
+
+        if !self.diffs.is_empty() {
+            html.push_str("<h2>Diffs</h2>\n");
+            for diff in &self.diffs {
+                html.push_str(&format!(
+                    "<h3>{}</h3>\n<pre>{}</pre>\n",
+                    escape_html(&diff.node_id),
+                    escape_html(&diff.content)
+                ));
+            }
+        }
+
+        html.push_str("</body></html>\n");
+        html
+    }
+}
+
+/// Pull unified diffs out of an output: `Code` blocks tagged diff/patch and
+/// fenced ```diff blocks inside text
+fn extract_diffs(data: &OutputData) -> Vec<String> {
+    match data {
+        OutputData::Code { language, content } if matches!(language.as_str(), "diff" | "patch") => {
+            vec![content.clone()]
+        }
+        OutputData::Text(text) => {
+            let mut diffs = Vec::new();
+            let mut current: Option<Vec<&str>> = None;
+
+            for line in text.lines() {
+                match current.as_mut() {
+                    None if matches!(line.trim(), "```diff" | "```patch") => current = Some(Vec::new()),
+                    None => {}
+                    Some(lines) if line.trim() == "```" => {
+                        diffs.push(lines.join("\n"));
+                        current = None;
+                    }
+                    Some(lines) => lines.push(line),
+                }
+            }
+            diffs
+        }
+        _ => Vec::new(),
+    }
+}
+
+pub(crate) fn format_ms(ms: Option<u64>) -> String {
+    match ms {
+        Some(ms) if ms >= 60_000 => format!("{}m {}s", ms / 60_000, (ms % 60_000) / 1000),
+        Some(ms) => format!("{:.1}s", ms as f64 / 1000.0),
+        None => "-".to_string(),
+    }
+}
+
+fn truncate_chars(content: &str, max: usize) -> String {
+    text::truncate(content, max, "\n... (truncated)")
+}
+
+pub(crate) fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Lay out plain text as a minimal PDF using the built-in Courier font.
+/// Characters outside Latin-1 are replaced with '?'.
+fn text_to_pdf(text: &str) -> Vec<u8> {
+    const LINES_PER_PAGE: usize = 60;
+    const MAX_LINE_CHARS: usize = 95;
+
+    // Wrap long lines so nothing runs off the page
+    let mut lines: Vec<String> = Vec::new();
+    for line in text.lines() {
+        let chars: Vec<char> = line.chars().collect();
+        if chars.is_empty() {
+            lines.push(String::new());
+        }
+        for chunk in chars.chunks(MAX_LINE_CHARS) {
+            lines.push(chunk.iter().collect());
+        }
+    }
+
+    let pages: Vec<&[String]> = lines.chunks(LINES_PER_PAGE).collect();
+    let page_count = pages.len().max(1);
+
+    // Object layout: 1 catalog, 2 pages, 3 font, then (page, content) pairs
+    let mut objects: Vec<Vec<u8>> = Vec::new();
+    let kids: Vec<String> = (0..page_count).map(|i| format!("{} 0 R", 4 + i * 2)).collect();
+    objects.push(b"<< /Type /Catalog /Pages 2 0 R >>".to_vec());
+    objects.push(format!("<< /Type /Pages /Kids [{}] /Count {} >>", kids.join(" "), page_count).into_bytes());
+    objects.push(b"<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>".to_vec());
+
+    for i in 0..page_count {
+        let mut stream = b"BT /F1 9 Tf 11 TL 40 800 Td\n".to_vec();
+        for line in pages.get(i).copied().unwrap_or_default() {
+            stream.push(b'(');
+            for c in line.chars() {
+                match c {
+                    '(' | ')' | '\\' => stream.extend_from_slice(&[b'\\', c as u8]),
+                    c if (c as u32) < 0x20 => stream.push(b' '),
+                    c if (c as u32) <= 0xFF => stream.push(c as u8),
+                    _ => stream.push(b'?'),
+                }
+            }
+            stream.extend_from_slice(b") Tj T*\n");
+        }
+        stream.extend_from_slice(b"ET");
+
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 595 842] \
+                 /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+                5 + i * 2
+            )
+            .into_bytes(),
+        );
+        let mut content = format!("<< /Length {} >>\nstream\n", stream.len()).into_bytes();
+        content.extend_from_slice(&stream);
+        content.extend_from_slice(b"\nendstream");
+        objects.push(content);
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::new();
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref_offset = pdf.len();
+    pdf.extend_from_slice(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend_from_slice(format!("{:010} 00000 n \n", offset).as_bytes());
+    }
+    pdf.extend_from_slice(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+            objects.len() + 1,
+            xref_offset
+        )
+        .as_bytes(),
+    );
+
+    pdf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::context::AgentOutput;
+
+    #[test]
+    fn test_resolve_output_path() {
+        let dir = std::env::temp_dir().join(format!("nexus-report-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        assert_eq!(resolve_output_path(&dir, "reports/run.md"), Ok(dir.join("reports/run.md")));
+        assert_eq!(resolve_output_path(&dir, "./run.md"), Ok(dir.join("./run.md")));
+        for escape in ["", "/tmp/run.md", "../run.md", "reports/../../run.md"] {
+            assert!(resolve_output_path(&dir, escape).is_err(), "{:?} should be refused", escape);
+        }
+        #[cfg(unix)]
+        {
+            std::os::unix::fs::symlink(std::env::temp_dir(), dir.join("tmp")).unwrap();
+            assert!(resolve_output_path(&dir, "tmp/run.md").is_err());
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    fn sample_execution() -> (WorkflowExecutionState, ExecutionContext) {
+        let execution_id = Uuid::new_v4();
+        let state = WorkflowExecutionState::new(
+            execution_id,
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "Fix the <login> bug".to_string(),
+            vec![vec!["impl".to_string()], vec!["review".to_string()]],
+        );
+        state.update_node_state("impl", |ns| ns.complete(Some("done".to_string())));
+        state.update_node_state("review", |ns| ns.fail("Rejected".to_string()));
+        state.set_status(ExecutionStatus::Failed);
+
+        let context = ExecutionContext::new(execution_id, state.project_id, state.input_prompt.clone());
+        context.store_output(AgentOutput {
+            agent_id: Uuid::nil(),
+            node_id: "impl".to_string(),
+            agent_role: "implementer".to_string(),
+            data: OutputData::Text("Patched:\n```diff\n-old\n+new\n```\n".to_string()),
+            timestamp: Utc::now(),
+            tags: vec![],
+        });
+        context.record_assertion(AssertionOutcome {
+            node_id: "lint".to_string(),
+            target: "clippy".to_string(),
+            passed: false,
+            message: "Clippy gate failed: 2 lint error(s), at most 0 allowed".to_string(),
+            score: None,
+        });
+
+        (state, context)
+    }
+
+    #[test]
+    fn test_report_contents() {
+        let (state, context) = sample_execution();
+        let report = ExecutionReport::build(&state, Some(&context));
+
+        assert_eq!(report.metrics.total_nodes, 2);
+        assert_eq!(report.metrics.completed_nodes, 1);
+        assert_eq!(report.metrics.failed_nodes, 1);
+        assert_eq!(report.diffs.len(), 1);
+        assert_eq!(report.diffs[0].content, "-old\n+new");
+
+        let md = report.to_markdown();
+        assert!(md.contains("## Plan\n\n1. impl\n2. review"));
+        assert!(md.contains("**Error:** Rejected"));
+        assert!(md.contains("| lint | clippy | Failed | Clippy gate failed"));
+
+        let html = report.to_html();
+        assert!(html.contains("Fix the &lt;login&gt; bug"));
+    }
+
+    #[test]
+    fn test_pdf_structure() {
+        let (state, context) = sample_execution();
+        let pdf = ExecutionReport::build(&state, Some(&context)).render(ReportFormat::Pdf);
+        let text = String::from_utf8_lossy(&pdf);
+
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+
+        // The xref offset must point at the xref table
+        let startxref: usize = text.rsplit("startxref\n").next().unwrap().lines().next().unwrap().parse().unwrap();
+        assert!(text[startxref..].starts_with("xref"));
+    }
+}
@@ -1,9 +1,13 @@
 //! Resource management for controlling agent execution.
 //!
 //! Provides:
-//! - Concurrent agent limits
-//! - Task queuing with priorities
-//! - Rate limiting
+//! - Concurrent agent limits, shared fairly between executions
+//! - Task queuing with priorities or shortest-job-first; tasks waiting
+//!   for a slot are listed in the queue, where they can be cancelled or
+//!   moved up
+//! - Per-role duration estimates from completed runs
+//! - Rate limiting per backend and credential, so one saturated provider
+//!   doesn't hold back another
 //! - Resource pools
 
 use chrono::{DateTime, Utc};
@@ -12,11 +16,14 @@ use serde::{Deserialize, Serialize};
 use std::collections::{BinaryHeap, HashMap};
 use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 use std::sync::Arc;
-use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::sync::{oneshot, Semaphore};
 use uuid::Uuid;
 
+use super::history::ExecutionRecord;
+use super::rate_limit::{CredentialRateLimit, RateLimit, RateLimitKey, RateLimitStatus, RateLimiter};
+
 /// Configuration for resource management
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ResourceConfig {
     /// Maximum concurrent agents
     pub max_concurrent_agents: u32,
@@ -24,12 +31,29 @@ pub struct ResourceConfig {
     pub max_agents_per_role: HashMap<String, u32>,
     /// Queue size limit
     pub max_queue_size: usize,
-    /// Rate limit: max requests per minute
+    /// Rate limit: max requests per minute of each backend credential
+    /// without a limit of its own, and of agents of no known backend
     pub rate_limit_per_minute: Option<u32>,
+    /// Requests that can go out at once; the per-minute rate when unset
+    #[serde(default)]
+    pub rate_limit_burst: Option<u32>,
+    /// Rate limits of single backends, e.g. "anthropic", in place of the
+    /// default one. Each of a backend's credentials gets a bucket of its own.
+    #[serde(default)]
+    pub backend_rate_limits: HashMap<String, RateLimit>,
+    /// Rate limits of single credentials, in place of their backend's
+    #[serde(default)]
+    pub credential_rate_limits: Vec<CredentialRateLimit>,
     /// Timeout for acquiring resources (ms)
     pub acquire_timeout_ms: u64,
     /// Enable priority queuing
     pub enable_priority_queue: bool,
+    /// Ordering of tasks within the same priority
+    #[serde(default)]
+    pub queue_policy: QueuePolicy,
+    /// Which waiting execution gets a freed agent slot
+    #[serde(default)]
+    pub fair_share: FairShare,
 }
 
 impl Default for ResourceConfig {
@@ -39,13 +63,41 @@ impl Default for ResourceConfig {
             max_agents_per_role: HashMap::new(),
             max_queue_size: 100,
             rate_limit_per_minute: Some(60),
+            rate_limit_burst: None,
+            backend_rate_limits: HashMap::new(),
+            credential_rate_limits: Vec::new(),
             acquire_timeout_ms: 30000,
             enable_priority_queue: true,
+            queue_policy: QueuePolicy::Priority,
+            fair_share: FairShare::RoundRobin,
         }
     }
 }
 
 impl ResourceConfig {
+    /// The default rate limit, if any
+    pub fn rate_limit(&self) -> Option<RateLimit> {
+        self.rate_limit_per_minute.map(|per_minute| RateLimit {
+            per_minute,
+            burst: self.rate_limit_burst,
+        })
+    }
+
+    /// The rate limit of a backend or credential's bucket: its own, its
+    /// backend's or the default one
+    pub fn rate_limit_for(&self, key: &RateLimitKey) -> Option<RateLimit> {
+        key.credential
+            .as_deref()
+            .and_then(|credential| {
+                self.credential_rate_limits
+                    .iter()
+                    .find(|limit| limit.backend == key.backend && limit.credential == credential)
+            })
+            .map(|limit| limit.limit)
+            .or_else(|| self.backend_rate_limits.get(&key.backend).copied())
+            .or_else(|| self.rate_limit())
+    }
+
     /// Configuration for high-throughput scenarios
     pub fn high_throughput() -> Self {
         Self {
@@ -53,8 +105,13 @@ impl ResourceConfig {
             max_agents_per_role: HashMap::new(),
             max_queue_size: 500,
             rate_limit_per_minute: Some(120),
+            rate_limit_burst: None,
+            backend_rate_limits: HashMap::new(),
+            credential_rate_limits: Vec::new(),
             acquire_timeout_ms: 60000,
             enable_priority_queue: true,
+            queue_policy: QueuePolicy::ShortestJobFirst,
+            fair_share: FairShare::RoundRobin,
         }
     }
 
@@ -65,29 +122,276 @@ impl ResourceConfig {
             max_agents_per_role: HashMap::new(),
             max_queue_size: 50,
             rate_limit_per_minute: Some(30),
+            rate_limit_burst: None,
+            backend_rate_limits: HashMap::new(),
+            credential_rate_limits: Vec::new(),
             acquire_timeout_ms: 60000,
             enable_priority_queue: true,
+            queue_policy: QueuePolicy::Priority,
+            fair_share: FairShare::RoundRobin,
         }
     }
 }
 
 /// Priority levels for queued tasks
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, Default)]
 pub enum TaskPriority {
     Low = 0,
+    #[default]
     Normal = 1,
     High = 2,
     Critical = 3,
 }
 
-impl Default for TaskPriority {
-    fn default() -> Self {
-        Self::Normal
+impl TaskPriority {
+    /// Share of agent slots relative to other priorities
+    pub fn weight(self) -> u32 {
+        1 << self as u32
+    }
+}
+
+/// How agent slots are shared between executions waiting for one
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FairShare {
+    /// Higher priority first, then by the queue policy, so one big fan-out
+    /// can take every slot
+    Fifo,
+    /// In turn: to the waiting execution holding the fewest slots; of
+    /// those, to the higher priority, then by the queue policy
+    #[default]
+    RoundRobin,
+    /// Like round-robin, with the slots held divided by the priority's weight
+    Weighted,
+}
+
+/// A task waiting for an agent slot
+struct Waiter {
+    seq: u64,
+    execution_id: Uuid,
+    priority: TaskPriority,
+    estimated_duration_ms: Option<u64>,
+    grant: oneshot::Sender<()>,
+}
+
+#[derive(Default)]
+struct SlotState {
+    free: usize,
+    /// Slots held by execution
+    held: HashMap<Uuid, u32>,
+    waiters: Vec<Waiter>,
+    next_seq: u64,
+    /// Ordering of waiters the fair-share policy leaves tied
+    queue_policy: QueuePolicy,
+}
+
+impl SlotState {
+    fn take(&mut self, execution_id: Uuid) {
+        *self.held.entry(execution_id).or_insert(0) += 1;
+    }
+
+    fn give_back(&mut self, execution_id: &Uuid) {
+        if let Some(count) = self.held.get_mut(execution_id) {
+            *count = count.saturating_sub(1);
+            if *count == 0 {
+                self.held.remove(execution_id);
+            }
+        }
+    }
+
+    /// Index of the waiter next in line under `policy`
+    fn next_waiter(&self, policy: FairShare) -> Option<usize> {
+        self.waiters
+            .iter()
+            .enumerate()
+            .min_by(|(_, a), (_, b)| self.compare(&self.held, policy, a, b))
+            .map(|(index, _)| index)
+    }
+
+    /// Waiters in the order they will get a slot under `policy`, if none
+    /// is released meanwhile
+    fn line(&self, policy: FairShare) -> Vec<u64> {
+        let mut held = self.held.clone();
+        let mut waiting: Vec<&Waiter> = self.waiters.iter().collect();
+        let mut line = Vec::with_capacity(waiting.len());
+        while let Some(index) = (0..waiting.len()).min_by(|&a, &b| self.compare(&held, policy, waiting[a], waiting[b])) {
+            let waiter = waiting.swap_remove(index);
+            *held.entry(waiter.execution_id).or_insert(0) += 1;
+            line.push(waiter.seq);
+        }
+        line
+    }
+
+    /// Which of two waiters goes first under `policy`, given the slots
+    /// each execution holds; `Less` means `a`
+    fn compare(&self, held: &HashMap<Uuid, u32>, policy: FairShare, a: &Waiter, b: &Waiter) -> std::cmp::Ordering {
+        let held = |waiter: &Waiter| held.get(&waiter.execution_id).copied().unwrap_or(0) as u64;
+        let by_priority = b.priority.cmp(&a.priority);
+        match policy {
+            FairShare::Fifo => by_priority,
+            FairShare::RoundRobin => held(a).cmp(&held(b)).then(by_priority),
+            // held(a) / weight(a) < held(b) / weight(b), without dividing
+            FairShare::Weighted => (held(a) * b.priority.weight() as u64).cmp(&(held(b) * a.priority.weight() as u64)),
+        }
+        .then_with(|| match self.queue_policy {
+            QueuePolicy::Priority => std::cmp::Ordering::Equal,
+            // Tasks without an estimate go last
+            QueuePolicy::ShortestJobFirst => {
+                let estimate = |waiter: &Waiter| (waiter.estimated_duration_ms.is_none(), waiter.estimated_duration_ms);
+                estimate(a).cmp(&estimate(b))
+            }
+        })
+        .then(a.seq.cmp(&b.seq))
+    }
+}
+
+/// Agent slots, handed to waiting tasks by the fair-share policy
+struct Slots {
+    policy: FairShare,
+    state: Mutex<SlotState>,
+}
+
+impl Slots {
+    fn new(capacity: usize, policy: FairShare, queue_policy: QueuePolicy) -> Self {
+        Self {
+            policy,
+            state: Mutex::new(SlotState {
+                free: capacity,
+                queue_policy,
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// A slot for `execution_id` now, or a place in line
+    fn take_or_wait(
+        &self,
+        execution_id: Uuid,
+        priority: TaskPriority,
+        estimated_duration_ms: Option<u64>,
+    ) -> Option<(u64, oneshot::Receiver<()>)> {
+        let mut state = self.state.lock();
+        if state.free > 0 && state.waiters.is_empty() {
+            state.free -= 1;
+            state.take(execution_id);
+            return None;
+        }
+        let (grant, granted) = oneshot::channel();
+        let seq = state.next_seq;
+        state.next_seq += 1;
+        state.waiters.push(Waiter {
+            seq,
+            execution_id,
+            priority,
+            estimated_duration_ms,
+            grant,
+        });
+        Some((seq, granted))
+    }
+
+    /// Leave the line; false if the slot was granted meanwhile
+    fn withdraw(&self, seq: u64) -> bool {
+        let mut state = self.state.lock();
+        match state.waiters.iter().position(|waiter| waiter.seq == seq) {
+            Some(index) => {
+                state.waiters.remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Move a waiter in line; false if it isn't waiting
+    fn set_priority(&self, seq: u64, priority: TaskPriority) -> bool {
+        let mut state = self.state.lock();
+        match state.waiters.iter_mut().find(|waiter| waiter.seq == seq) {
+            Some(waiter) => {
+                waiter.priority = priority;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Hand the slot `execution_id` held to the next waiter, if any
+    fn release(&self, execution_id: &Uuid) {
+        let mut state = self.state.lock();
+        state.give_back(execution_id);
+        while let Some(index) = state.next_waiter(self.policy) {
+            let waiter = state.waiters.remove(index);
+            state.take(waiter.execution_id);
+            if waiter.grant.send(()).is_ok() {
+                return;
+            }
+            // Stopped waiting without withdrawing
+            state.give_back(&waiter.execution_id);
+        }
+        state.free += 1;
+    }
+
+    /// Seqs of the waiters, in the order they will get a slot
+    fn line(&self) -> Vec<u64> {
+        self.state.lock().line(self.policy)
+    }
+
+    fn set_queue_policy(&self, queue_policy: QueuePolicy) {
+        self.state.lock().queue_policy = queue_policy;
+    }
+
+    fn free(&self) -> usize {
+        self.state.lock().free
+    }
+
+    fn held(&self) -> HashMap<Uuid, u32> {
+        self.state.lock().held.clone()
+    }
+}
+
+/// An agent slot, returned when dropped
+struct Slot {
+    slots: Arc<Slots>,
+    execution_id: Uuid,
+}
+
+impl Drop for Slot {
+    fn drop(&mut self) {
+        self.slots.release(&self.execution_id);
+    }
+}
+
+/// How queued tasks of equal priority are ordered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueuePolicy {
+    /// Oldest task first
+    #[default]
+    Priority,
+    /// Shortest estimated duration first; tasks without an estimate go last
+    ShortestJobFirst,
+}
+
+impl QueuePolicy {
+    /// Compare two tasks; `Greater` means `a` should be dequeued first
+    pub fn compare(&self, a: &QueuedTask, b: &QueuedTask) -> std::cmp::Ordering {
+        let by_age = || b.queued_at.cmp(&a.queued_at);
+
+        a.priority.cmp(&b.priority).then_with(|| match self {
+            QueuePolicy::Priority => by_age(),
+            QueuePolicy::ShortestJobFirst => {
+                match (a.estimated_duration_ms, b.estimated_duration_ms) {
+                    (Some(x), Some(y)) => y.cmp(&x),
+                    (Some(_), None) => std::cmp::Ordering::Greater,
+                    (None, Some(_)) => std::cmp::Ordering::Less,
+                    (None, None) => std::cmp::Ordering::Equal,
+                }
+                .then_with(by_age)
+            }
+        })
     }
 }
 
 /// A queued task waiting for resources
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QueuedTask {
     pub id: Uuid,
     pub execution_id: Uuid,
@@ -115,10 +419,48 @@ impl PartialOrd for QueuedTask {
 impl Ord for QueuedTask {
     fn cmp(&self, other: &Self) -> std::cmp::Ordering {
         // Higher priority first, then older tasks first
-        match self.priority.cmp(&other.priority) {
-            std::cmp::Ordering::Equal => other.queued_at.cmp(&self.queued_at),
-            ord => ord.reverse(), // Reverse for max-heap behavior
-        }
+        QueuePolicy::Priority.compare(self, other)
+    }
+}
+
+/// A queued task as listed for users
+#[derive(Debug, Clone, Serialize)]
+pub struct QueuedTaskInfo {
+    #[serde(flatten)]
+    pub task: QueuedTask,
+    /// Place in line, 0 being served next. Tasks waiting for an agent slot
+    /// come first, in the order they will get one.
+    pub position: usize,
+    /// How long the task has been waiting
+    pub wait_ms: u64,
+}
+
+/// Heap entry ordering a task by the active queue policy
+#[derive(Debug, Clone)]
+struct QueueEntry {
+    task: QueuedTask,
+    policy: QueuePolicy,
+    /// Place in line for an agent slot, of a task waiting in `acquire`
+    waiter: Option<u64>,
+}
+
+impl PartialEq for QueueEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.task == other.task
+    }
+}
+
+impl Eq for QueueEntry {}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.policy.compare(&self.task, &other.task)
     }
 }
 
@@ -126,50 +468,117 @@ impl Ord for QueuedTask {
 #[allow(dead_code)]
 pub struct ResourceManager {
     config: ResourceConfig,
-    /// Semaphore for overall concurrency limit
-    concurrency_semaphore: Arc<Semaphore>,
+    /// Agent slots, for the overall concurrency limit
+    slots: Arc<Slots>,
     /// Per-role semaphores (reserved for future role-based limiting)
     role_semaphores: Mutex<HashMap<String, Arc<Semaphore>>>,
     /// Priority queue for waiting tasks
-    task_queue: Mutex<BinaryHeap<QueuedTask>>,
+    task_queue: Mutex<BinaryHeap<QueueEntry>>,
+    /// Per-role (total_ms, count) of completed runs, used for estimates
+    role_durations: Mutex<HashMap<String, (u64, u64)>>,
     /// Current active agents count
     active_agents: AtomicU32,
     /// Per-role active counts
     active_per_role: Mutex<HashMap<String, u32>>,
-    /// Rate limiter state
+    /// Rate limiter of agents of no known backend
     rate_limiter: RateLimiter,
+    /// Rate limiters of backends and credentials, added as they are first
+    /// used
+    backend_limiters: Mutex<HashMap<RateLimitKey, Arc<RateLimiter>>>,
     /// Statistics
     stats: ResourceStats,
 }
 
 impl ResourceManager {
     pub fn new(config: ResourceConfig) -> Self {
-        let semaphore = Arc::new(Semaphore::new(config.max_concurrent_agents as usize));
-
         Self {
-            concurrency_semaphore: semaphore,
+            slots: Arc::new(Slots::new(config.max_concurrent_agents as usize, config.fair_share, config.queue_policy)),
             role_semaphores: Mutex::new(HashMap::new()),
             task_queue: Mutex::new(BinaryHeap::new()),
+            role_durations: Mutex::new(HashMap::new()),
             active_agents: AtomicU32::new(0),
             active_per_role: Mutex::new(HashMap::new()),
-            rate_limiter: RateLimiter::new(config.rate_limit_per_minute),
+            rate_limiter: RateLimiter::new(config.rate_limit()),
+            backend_limiters: backend_limiters(&config),
             stats: ResourceStats::new(),
             config,
         }
     }
 
-    /// Try to acquire resources for an agent
+    /// Try to acquire resources for an agent. While slots are short, a
+    /// freed one goes to a waiting execution by the fair-share policy.
     pub async fn acquire(
         &self,
-        _execution_id: Uuid,
-        _node_id: &str,
+        execution_id: Uuid,
+        node_id: &str,
         agent_role: &str,
-        _priority: TaskPriority,
+        priority: TaskPriority,
     ) -> Result<ResourcePermit, ResourceError> {
-        // Check rate limit
-        if !self.rate_limiter.try_acquire() {
-            return Err(ResourceError::RateLimited);
-        }
+        self.acquire_inner(None, execution_id, node_id, agent_role, priority, || false).await
+    }
+
+    /// Like [`acquire`](Self::acquire), for an agent calling the backend
+    /// or credential of `key`. It counts against that bucket's rate limit
+    /// only, not the one of agents of no known backend.
+    pub async fn acquire_for(
+        &self,
+        key: &RateLimitKey,
+        execution_id: Uuid,
+        node_id: &str,
+        agent_role: &str,
+        priority: TaskPriority,
+    ) -> Result<ResourcePermit, ResourceError> {
+        self.acquire_inner(Some(key), execution_id, node_id, agent_role, priority, || false).await
+    }
+
+    /// Like [`acquire_for`](Self::acquire_for), keeping the task's place in
+    /// line past the acquire timeout for as long as `keep_waiting` says so.
+    /// The timeout only sets how often it is asked; the task keeps its id,
+    /// priority and age in the queue.
+    pub async fn acquire_for_while(
+        &self,
+        key: &RateLimitKey,
+        execution_id: Uuid,
+        node_id: &str,
+        agent_role: &str,
+        priority: TaskPriority,
+        keep_waiting: impl Fn() -> bool,
+    ) -> Result<ResourcePermit, ResourceError> {
+        self.acquire_inner(Some(key), execution_id, node_id, agent_role, priority, keep_waiting).await
+    }
+
+    async fn acquire_inner(
+        &self,
+        key: Option<&RateLimitKey>,
+        execution_id: Uuid,
+        node_id: &str,
+        agent_role: &str,
+        priority: TaskPriority,
+        keep_waiting: impl Fn() -> bool,
+    ) -> Result<ResourcePermit, ResourceError> {
+        // Check the rate limit of the backend or credential, or the default
+        // one. The token goes back unless a slot is granted.
+        let tokens = match key {
+            Some(key) => {
+                let limiter = self.backend_limiter(key);
+                if !limiter.as_ref().map_or(true, |limiter| limiter.try_acquire()) {
+                    return Err(ResourceError::RateLimited);
+                }
+                RateTokens {
+                    backend: limiter,
+                    overall: None,
+                }
+            }
+            None => {
+                if !self.rate_limiter.try_acquire() {
+                    return Err(ResourceError::RateLimited);
+                }
+                RateTokens {
+                    backend: None,
+                    overall: Some(&self.rate_limiter),
+                }
+            }
+        };
 
         // Check queue size
         {
@@ -183,16 +592,41 @@ impl ResourceManager {
         // Try to get a permit with timeout
         let timeout = std::time::Duration::from_millis(self.config.acquire_timeout_ms);
 
-        let permit = match tokio::time::timeout(
-            timeout,
-            self.concurrency_semaphore.clone().acquire_owned(),
-        ).await {
-            Ok(Ok(permit)) => permit,
-            Ok(Err(_)) => return Err(ResourceError::SemaphoreClosed),
-            Err(_) => {
-                self.stats.timeouts.fetch_add(1, Ordering::Relaxed);
-                return Err(ResourceError::Timeout);
+        let estimated_duration_ms = self.estimated_duration(agent_role);
+        if let Some((seq, mut granted)) = self.slots.take_or_wait(execution_id, priority, estimated_duration_ms) {
+            let task = QueuedTask {
+                id: Uuid::new_v4(),
+                execution_id,
+                node_id: node_id.to_string(),
+                agent_role: agent_role.to_string(),
+                priority,
+                queued_at: Utc::now(),
+                estimated_duration_ms,
+            };
+            let _queued = self.queue_waiter(task, seq);
+            loop {
+                match tokio::time::timeout(timeout, &mut granted).await {
+                    Ok(Ok(())) => {
+                        self.stats.dequeued.fetch_add(1, Ordering::Relaxed);
+                        break;
+                    }
+                    // Withdrawn by `cancel_queued` or `clear_queue`
+                    Ok(Err(_)) => return Err(ResourceError::Cancelled),
+                    Err(_) if keep_waiting() => {}
+                    Err(_) => {
+                        if !self.slots.withdraw(seq) {
+                            // Granted as the wait ran out
+                            self.slots.release(&execution_id);
+                        }
+                        self.stats.timeouts.fetch_add(1, Ordering::Relaxed);
+                        return Err(ResourceError::Timeout);
+                    }
+                }
             }
+        }
+        let permit = Slot {
+            slots: self.slots.clone(),
+            execution_id,
         };
 
         // Check role-specific limit
@@ -209,6 +643,8 @@ impl ResourceManager {
             *active.entry(agent_role.to_string()).or_insert(0) += 1;
         }
 
+        tokens.keep();
+
         // Update stats
         self.active_agents.fetch_add(1, Ordering::Relaxed);
         self.stats.acquired.fetch_add(1, Ordering::Relaxed);
@@ -224,7 +660,9 @@ impl ResourceManager {
     /// Release resources when agent completes
     pub fn release(&self, permit: ResourcePermit) {
         let duration = Utc::now() - permit.acquired_at;
-        self.stats.add_duration(duration.num_milliseconds() as u64);
+        let duration_ms = duration.num_milliseconds().max(0) as u64;
+        self.stats.add_duration(duration_ms);
+        self.record_duration(&permit.agent_role, duration_ms);
 
         // Update per-role count
         {
@@ -240,15 +678,24 @@ impl ResourceManager {
         // Permit is dropped automatically, releasing the semaphore
     }
 
-    /// Queue a task for later execution
-    pub fn queue_task(&self, task: QueuedTask) -> Result<usize, ResourceError> {
+    /// Queue a task for later execution.
+    /// Tasks without an estimated duration get one from the role's history.
+    pub fn queue_task(&self, mut task: QueuedTask) -> Result<usize, ResourceError> {
+        if task.estimated_duration_ms.is_none() {
+            task.estimated_duration_ms = self.estimated_duration(&task.agent_role);
+        }
+
         let mut queue = self.task_queue.lock();
 
         if queue.len() >= self.config.max_queue_size {
             return Err(ResourceError::QueueFull);
         }
 
-        queue.push(task);
+        queue.push(QueueEntry {
+            task,
+            policy: self.config.queue_policy,
+            waiter: None,
+        });
         self.stats.queued.fetch_add(1, Ordering::Relaxed);
 
         Ok(queue.len())
@@ -257,13 +704,126 @@ impl ResourceManager {
     /// Get the next task from the queue
     pub fn dequeue_task(&self) -> Option<QueuedTask> {
         let mut queue = self.task_queue.lock();
-        let task = queue.pop();
+        let task = queue.pop().map(|entry| entry.task);
         if task.is_some() {
             self.stats.dequeued.fetch_add(1, Ordering::Relaxed);
         }
         task
     }
 
+    /// Queued tasks in the order they will be served: those waiting for an
+    /// agent slot as the fair-share policy grants them, then the rest as
+    /// they will be dequeued
+    pub fn list_queued(&self) -> Vec<QueuedTaskInfo> {
+        let mut entries = self.task_queue.lock().clone().into_vec();
+        let line = self.slots.line();
+        let place = |entry: &QueueEntry| entry.waiter.and_then(|seq| line.iter().position(|&s| s == seq));
+        entries.sort_by(|a, b| match (place(a), place(b)) {
+            (Some(x), Some(y)) => x.cmp(&y),
+            (Some(_), None) => std::cmp::Ordering::Less,
+            (None, Some(_)) => std::cmp::Ordering::Greater,
+            (None, None) => b.cmp(a),
+        });
+        let now = Utc::now();
+        entries
+            .into_iter()
+            .enumerate()
+            .map(|(position, entry)| QueuedTaskInfo {
+                wait_ms: (now - entry.task.queued_at).num_milliseconds().max(0) as u64,
+                task: entry.task,
+                position,
+            })
+            .collect()
+    }
+
+    /// Take a task out of the queue; None if it isn't queued. A task
+    /// waiting for an agent slot stops waiting, its `acquire` failing with
+    /// [`ResourceError::Cancelled`].
+    pub fn cancel_queued(&self, task_id: &Uuid) -> Option<QueuedTask> {
+        let entry = self.remove_queued(task_id)?;
+        match entry.waiter {
+            // Granted a slot as it was cancelled
+            Some(seq) if !self.slots.withdraw(seq) => None,
+            _ => Some(entry.task),
+        }
+    }
+
+    /// Change the priority of a queued task, moving it in line; None if it
+    /// isn't queued
+    pub fn reprioritize_queued(&self, task_id: &Uuid, priority: TaskPriority) -> Option<QueuedTask> {
+        let mut queue = self.task_queue.lock();
+        let mut entries = std::mem::take(&mut *queue).into_vec();
+        let task = entries.iter_mut().find(|entry| entry.task.id == *task_id).map(|entry| {
+            entry.task.priority = priority;
+            if let Some(seq) = entry.waiter {
+                self.slots.set_priority(seq, priority);
+            }
+            entry.task.clone()
+        });
+        *queue = entries.into();
+        task
+    }
+
+    /// Drop every queued task, returning how many there were. Tasks
+    /// waiting for an agent slot stop waiting.
+    pub fn clear_queue(&self) -> usize {
+        let entries = std::mem::take(&mut *self.task_queue.lock());
+        for seq in entries.iter().filter_map(|entry| entry.waiter) {
+            self.slots.withdraw(seq);
+        }
+        entries.len()
+    }
+
+    fn remove_queued(&self, task_id: &Uuid) -> Option<QueueEntry> {
+        let mut queue = self.task_queue.lock();
+        let mut entries = std::mem::take(&mut *queue).into_vec();
+        let removed = entries
+            .iter()
+            .position(|entry| entry.task.id == *task_id)
+            .map(|index| entries.swap_remove(index));
+        *queue = entries.into();
+        removed
+    }
+
+    /// List a task waiting for an agent slot in the queue, until the
+    /// returned guard drops
+    fn queue_waiter(&self, task: QueuedTask, seq: u64) -> QueuedWait<'_> {
+        let id = task.id;
+        self.task_queue.lock().push(QueueEntry {
+            task,
+            policy: self.config.queue_policy,
+            waiter: Some(seq),
+        });
+        self.stats.queued.fetch_add(1, Ordering::Relaxed);
+        QueuedWait { manager: self, id }
+    }
+
+    /// Record how long a run of the given role took
+    pub fn record_duration(&self, agent_role: &str, duration_ms: u64) {
+        let mut durations = self.role_durations.lock();
+        let entry = durations.entry(agent_role.to_string()).or_insert((0, 0));
+        entry.0 += duration_ms;
+        entry.1 += 1;
+    }
+
+    /// Average duration of completed runs for a role
+    pub fn estimated_duration(&self, agent_role: &str) -> Option<u64> {
+        self.role_durations
+            .lock()
+            .get(agent_role)
+            .filter(|(_, count)| *count > 0)
+            .map(|(total, count)| total / count)
+    }
+
+    /// Seed duration estimates from past execution records
+    pub fn seed_from_history(&self, records: &[ExecutionRecord]) {
+        for node in records.iter().flat_map(|r| &r.node_records) {
+            if let Some(duration_ms) = node.duration_ms {
+                self.record_duration(&node.agent_role, duration_ms);
+            }
+        }
+    }
+
     /// Get current queue length
     pub fn queue_length(&self) -> usize {
         self.task_queue.lock().len()
@@ -276,38 +836,139 @@ impl ResourceManager {
 
     /// Get statistics
     pub fn get_stats(&self) -> ResourceStatsSnapshot {
-        self.stats.snapshot(self.active_count(), self.queue_length())
+        let mut snapshot = self.stats.snapshot(self.active_count(), self.queue_length());
+        snapshot.active_per_execution = self.slots.held();
+        snapshot
     }
 
     /// Update configuration
     pub fn update_config(&mut self, config: ResourceConfig) {
-        // Update semaphore if concurrency limit changed
-        if config.max_concurrent_agents != self.config.max_concurrent_agents {
-            self.concurrency_semaphore = Arc::new(Semaphore::new(config.max_concurrent_agents as usize));
+        // Replace the slots if their number or sharing changed
+        if config.max_concurrent_agents != self.config.max_concurrent_agents || config.fair_share != self.config.fair_share {
+            self.slots = Arc::new(Slots::new(config.max_concurrent_agents as usize, config.fair_share, config.queue_policy));
         }
 
-        // Update rate limiter
-        self.rate_limiter = RateLimiter::new(config.rate_limit_per_minute);
+        // Re-order queued tasks if the queue policy changed
+        if config.queue_policy != self.config.queue_policy {
+            self.slots.set_queue_policy(config.queue_policy);
+            let queue = self.task_queue.get_mut();
+            let tasks = std::mem::take(queue).into_vec();
+            queue.extend(tasks.into_iter().map(|entry| QueueEntry {
+                policy: config.queue_policy,
+                ..entry
+            }));
+        }
+
+        // Update rate limiters
+        self.rate_limiter = RateLimiter::new(config.rate_limit());
+        self.backend_limiters = backend_limiters(&config);
 
         self.config = config;
     }
 
+    /// Get the current configuration
+    pub fn config(&self) -> &ResourceConfig {
+        &self.config
+    }
+
     /// Check if resources are available (non-blocking)
     pub fn is_available(&self) -> bool {
-        self.concurrency_semaphore.available_permits() > 0
+        self.slots.free() > 0
     }
 
     /// Get available permits count
     pub fn available_permits(&self) -> usize {
-        self.concurrency_semaphore.available_permits()
+        self.slots.free()
+    }
+
+    /// Tokens left in each rate-limited bucket, the one of agents of no
+    /// known backend first, then by backend and credential
+    pub fn rate_limit_status(&self) -> Vec<RateLimitStatus> {
+        let limiters = self.backend_limiters.lock();
+        let mut keys: Vec<_> = limiters.keys().collect();
+        keys.sort();
+        self.rate_limiter
+            .status(None)
+            .into_iter()
+            .chain(keys.into_iter().filter_map(|key| limiters[key].status(Some(key))))
+            .collect()
+    }
+
+    /// The bucket of `key`; none when no limit applies to it
+    fn backend_limiter(&self, key: &RateLimitKey) -> Option<Arc<RateLimiter>> {
+        let mut limiters = self.backend_limiters.lock();
+        if let Some(limiter) = limiters.get(key) {
+            return Some(limiter.clone());
+        }
+        let limiter = Arc::new(RateLimiter::new(Some(self.config.rate_limit_for(key)?)));
+        limiters.insert(key.clone(), limiter.clone());
+        Some(limiter)
+    }
+}
+
+/// Buckets of the configured backends and credentials, so they are listed
+/// before first use
+fn backend_limiters(config: &ResourceConfig) -> Mutex<HashMap<RateLimitKey, Arc<RateLimiter>>> {
+    let backends = config
+        .backend_rate_limits
+        .iter()
+        .map(|(backend, limit)| (RateLimitKey::backend(backend), *limit));
+    let credentials = config
+        .credential_rate_limits
+        .iter()
+        .map(|limit| (RateLimitKey::backend(&limit.backend).credential(&limit.credential), limit.limit));
+    Mutex::new(
+        backends
+            .chain(credentials)
+            .map(|(key, limit)| (key, Arc::new(RateLimiter::new(Some(limit)))))
+            .collect(),
+    )
+}
+
+/// A rate limit token taken for an agent, refunded when dropped unless it
+/// got its slot: an agent that times out or is cancelled in line never
+/// calls the backend
+struct RateTokens<'a> {
+    backend: Option<Arc<RateLimiter>>,
+    overall: Option<&'a RateLimiter>,
+}
+
+impl RateTokens<'_> {
+    fn keep(mut self) {
+        self.backend = None;
+        self.overall = None;
+    }
+}
+
+impl Drop for RateTokens<'_> {
+    fn drop(&mut self) {
+        if let Some(limiter) = &self.backend {
+            limiter.refund();
+        }
+        if let Some(limiter) = self.overall {
+            limiter.refund();
+        }
+    }
+}
+
+/// Takes a waiting task out of the queue once it stops waiting, however
+/// that happens
+struct QueuedWait<'a> {
+    manager: &'a ResourceManager,
+    id: Uuid,
+}
+
+impl Drop for QueuedWait<'_> {
+    fn drop(&mut self) {
+        self.manager.remove_queued(&self.id);
     }
 }
 
 /// A permit representing acquired resources
-/// The permit is held to maintain the semaphore lock until dropped
+/// The permit holds an agent slot until dropped
 #[allow(dead_code)]
 pub struct ResourcePermit {
-    permit: OwnedSemaphorePermit,
+    permit: Slot,
     agent_role: String,
     acquired_at: DateTime<Utc>,
 }
@@ -325,6 +986,8 @@ pub enum ResourceError {
     RoleLimitExceeded { role: String, limit: u32 },
     /// Semaphore was closed
     SemaphoreClosed,
+    /// Taken out of the queue while waiting
+    Cancelled,
 }
 
 impl std::fmt::Display for ResourceError {
@@ -337,73 +1000,13 @@ impl std::fmt::Display for ResourceError {
                 write!(f, "Role {} limit of {} exceeded", role, limit)
             }
             ResourceError::SemaphoreClosed => write!(f, "Resource manager was closed"),
+            ResourceError::Cancelled => write!(f, "Taken out of the queue"),
         }
     }
 }
 
 impl std::error::Error for ResourceError {}
 
-/// Simple rate limiter using token bucket algorithm
-struct RateLimiter {
-    tokens: AtomicU32,
-    max_tokens: u32,
-    last_refill: Mutex<DateTime<Utc>>,
-    enabled: bool,
-}
-
-impl RateLimiter {
-    fn new(rate_per_minute: Option<u32>) -> Self {
-        let max_tokens = rate_per_minute.unwrap_or(u32::MAX);
-        Self {
-            tokens: AtomicU32::new(max_tokens),
-            max_tokens,
-            last_refill: Mutex::new(Utc::now()),
-            enabled: rate_per_minute.is_some(),
-        }
-    }
-
-    fn try_acquire(&self) -> bool {
-        if !self.enabled {
-            return true;
-        }
-
-        // Refill tokens based on elapsed time
-        self.refill();
-
-        // Try to take a token
-        loop {
-            let current = self.tokens.load(Ordering::Relaxed);
-            if current == 0 {
-                return false;
-            }
-            if self.tokens.compare_exchange(
-                current,
-                current - 1,
-                Ordering::Relaxed,
-                Ordering::Relaxed,
-            ).is_ok() {
-                return true;
-            }
-        }
-    }
-
-    fn refill(&self) {
-        let mut last_refill = self.last_refill.lock();
-        let now = Utc::now();
-        let elapsed = now - *last_refill;
-        let elapsed_minutes = elapsed.num_seconds() as f64 / 60.0;
-
-        if elapsed_minutes >= 0.1 {
-            // Refill every 6 seconds minimum
-            let tokens_to_add = (self.max_tokens as f64 * elapsed_minutes).min(self.max_tokens as f64) as u32;
-            let current = self.tokens.load(Ordering::Relaxed);
-            let new_tokens = (current + tokens_to_add).min(self.max_tokens);
-            self.tokens.store(new_tokens, Ordering::Relaxed);
-            *last_refill = now;
-        }
-    }
-}
-
 /// Statistics tracking for resource usage
 struct ResourceStats {
     acquired: AtomicU64,
@@ -456,11 +1059,7 @@ impl ResourceStats {
 
     fn snapshot(&self, current_active: u32, queue_length: usize) -> ResourceStatsSnapshot {
         let duration_count = self.duration_count.load(Ordering::Relaxed);
-        let avg_duration = if duration_count > 0 {
-            Some(self.total_duration_ms.load(Ordering::Relaxed) / duration_count)
-        } else {
-            None
-        };
+        let avg_duration = self.total_duration_ms.load(Ordering::Relaxed).checked_div(duration_count);
 
         ResourceStatsSnapshot {
             current_active,
@@ -473,6 +1072,7 @@ impl ResourceStats {
             total_rejected: self.rejected.load(Ordering::Relaxed),
             peak_active: self.peak_active.load(Ordering::Relaxed),
             avg_duration_ms: avg_duration,
+            active_per_execution: HashMap::new(),
         }
     }
 }
@@ -490,6 +1090,9 @@ pub struct ResourceStatsSnapshot {
     pub total_rejected: u64,
     pub peak_active: u32,
     pub avg_duration_ms: Option<u64>,
+    /// Agent slots each execution holds
+    #[serde(default)]
+    pub active_per_execution: HashMap<Uuid, u32>,
 }
 
 #[cfg(test)]
@@ -516,6 +1119,64 @@ mod tests {
         assert_eq!(manager.active_count(), 1);
     }
 
+    #[tokio::test]
+    async fn test_backend_rate_limits() {
+        let manager = ResourceManager::new(ResourceConfig {
+            rate_limit_per_minute: Some(100),
+            backend_rate_limits: HashMap::from([("anthropic".to_string(), RateLimit { per_minute: 2, burst: None })]),
+            ..Default::default()
+        });
+        let manager = &manager;
+        let acquire = |key: RateLimitKey| async move {
+            manager.acquire_for(&key, Uuid::new_v4(), "n", "tester", TaskPriority::Normal).await
+        };
+        let anthropic = RateLimitKey::backend("anthropic");
+
+        let _first = acquire(anthropic.clone()).await.unwrap();
+        let _second = acquire(anthropic.clone()).await.unwrap();
+        assert!(matches!(acquire(anthropic.clone()).await, Err(ResourceError::RateLimited)));
+        // Other backends get buckets of the default limit of their own, and
+        // keyed agents leave the one of agents of no known backend alone
+        let _other = acquire(RateLimitKey::backend("ollama")).await.unwrap();
+        let _unkeyed = manager.acquire(Uuid::new_v4(), "n", "tester", TaskPriority::Normal).await.unwrap();
+
+        let status = manager.rate_limit_status();
+        let remaining: Vec<_> = status.iter().map(|s| (s.backend.as_deref(), s.tokens_remaining)).collect();
+        assert_eq!(remaining, [(None, 99), (Some("anthropic"), 0), (Some("ollama"), 99)]);
+    }
+
+    #[tokio::test]
+    async fn test_credential_rate_limits() {
+        let manager = ResourceManager::new(ResourceConfig {
+            rate_limit_per_minute: None,
+            backend_rate_limits: HashMap::from([("anthropic".to_string(), RateLimit { per_minute: 1, burst: None })]),
+            credential_rate_limits: vec![CredentialRateLimit {
+                backend: "anthropic".to_string(),
+                credential: "team".to_string(),
+                limit: RateLimit { per_minute: 3, burst: None },
+            }],
+            ..Default::default()
+        });
+        let manager = &manager;
+        let acquire = |credential: &'static str| async move {
+            let key = RateLimitKey::backend("anthropic").credential(credential);
+            manager.acquire_for(&key, Uuid::new_v4(), "n", "tester", TaskPriority::Normal).await
+        };
+
+        // A saturated key leaves the others alone
+        assert!(acquire("...a1b2").await.is_ok());
+        assert!(acquire("...a1b2").await.is_err());
+        assert!(acquire("...c3d4").await.is_ok());
+        for _ in 0..3 {
+            assert!(acquire("team").await.is_ok());
+        }
+        assert!(acquire("team").await.is_err());
+
+        let status = manager.rate_limit_status();
+        let credentials: Vec<_> = status.iter().map(|s| (s.credential.as_deref(), s.allowed, s.throttled)).collect();
+        assert_eq!(credentials, [(None, 0, 0), (Some("...a1b2"), 1, 1), (Some("...c3d4"), 1, 0), (Some("team"), 3, 1)]);
+    }
+
     #[test]
     fn test_priority_queue() {
         let manager = ResourceManager::new(ResourceConfig::default());
@@ -563,4 +1224,211 @@ mod tests {
         let task = manager.dequeue_task().unwrap();
         assert_eq!(task.node_id, "low");
     }
+
+    fn queued(node_id: &str, role: &str, estimated_duration_ms: Option<u64>) -> QueuedTask {
+        QueuedTask {
+            id: Uuid::new_v4(),
+            execution_id: Uuid::new_v4(),
+            node_id: node_id.to_string(),
+            agent_role: role.to_string(),
+            priority: TaskPriority::Normal,
+            queued_at: Utc::now(),
+            estimated_duration_ms,
+        }
+    }
+
+    #[test]
+    fn test_shortest_job_first() {
+        let manager = ResourceManager::new(ResourceConfig {
+            queue_policy: QueuePolicy::ShortestJobFirst,
+            ..Default::default()
+        });
+
+        manager.queue_task(queued("unknown", "documenter", None)).unwrap();
+        manager.queue_task(queued("slow", "implementer", Some(60_000))).unwrap();
+        manager.queue_task(queued("fast", "reviewer", Some(5_000))).unwrap();
+
+        assert_eq!(manager.dequeue_task().unwrap().node_id, "fast");
+        assert_eq!(manager.dequeue_task().unwrap().node_id, "slow");
+        assert_eq!(manager.dequeue_task().unwrap().node_id, "unknown");
+    }
+
+    #[test]
+    fn test_manage_queue() {
+        let manager = ResourceManager::new(ResourceConfig::default());
+        let (first, second, third) = (queued("a", "tester", None), queued("b", "tester", None), queued("c", "tester", None));
+        for task in [&first, &second, &third] {
+            manager.queue_task(task.clone()).unwrap();
+        }
+        let order = |manager: &ResourceManager| -> Vec<String> {
+            manager.list_queued().into_iter().map(|info| info.task.node_id).collect()
+        };
+        assert_eq!(order(&manager), ["a", "b", "c"]);
+        assert_eq!(manager.list_queued()[2].position, 2);
+
+        assert_eq!(manager.reprioritize_queued(&third.id, TaskPriority::High).unwrap().priority, TaskPriority::High);
+        assert_eq!(order(&manager), ["c", "a", "b"]);
+        assert_eq!(manager.cancel_queued(&first.id).unwrap().node_id, "a");
+        assert!(manager.cancel_queued(&first.id).is_none());
+        assert!(manager.reprioritize_queued(&first.id, TaskPriority::Low).is_none());
+        assert_eq!(order(&manager), ["c", "b"]);
+        assert_eq!(manager.dequeue_task().unwrap().node_id, "c");
+    }
+
+    #[tokio::test]
+    async fn test_fair_share() {
+        let manager = Arc::new(ResourceManager::new(ResourceConfig {
+            max_concurrent_agents: 2,
+            rate_limit_per_minute: None,
+            ..Default::default()
+        }));
+        let (fan_out, small) = (Uuid::new_v4(), Uuid::new_v4());
+        let first = manager.acquire(fan_out, "a1", "implementer", TaskPriority::Normal).await.unwrap();
+        let _second = manager.acquire(fan_out, "a2", "implementer", TaskPriority::Normal).await.unwrap();
+
+        let wait = |execution_id: Uuid, node_id: &'static str| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.acquire(execution_id, node_id, "tester", TaskPriority::Normal).await })
+        };
+        let fan_out_third = wait(fan_out, "a3");
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        let small_first = wait(small, "b1");
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        // The execution holding fewer slots goes first, though it came later
+        let queued: Vec<_> = manager.list_queued().into_iter().map(|info| info.task.node_id).collect();
+        assert_eq!(queued, ["b1", "a3"]);
+        manager.release(first);
+        let permit = small_first.await.unwrap().unwrap();
+        assert!(!fan_out_third.is_finished());
+        assert_eq!(manager.get_stats().active_per_execution[&small], 1);
+        drop(permit);
+        assert!(fan_out_third.await.unwrap().is_ok());
+
+        let mut state = SlotState::default();
+        state.held.insert(fan_out, 3);
+        state.held.insert(small, 1);
+        for (seq, (execution_id, priority)) in [(fan_out, TaskPriority::Critical), (small, TaskPriority::Low)].into_iter().enumerate() {
+            state.waiters.push(Waiter {
+                seq: seq as u64,
+                execution_id,
+                priority,
+                estimated_duration_ms: None,
+                grant: oneshot::channel().0,
+            });
+        }
+        // 3 of weight 8 is a smaller share than 1 of weight 1
+        assert_eq!(state.next_waiter(FairShare::Weighted), Some(0));
+        assert_eq!(state.next_waiter(FairShare::RoundRobin), Some(1));
+    }
+
+    #[tokio::test]
+    async fn test_waiting_tasks_are_queued() {
+        let manager = Arc::new(ResourceManager::new(ResourceConfig {
+            max_concurrent_agents: 1,
+            rate_limit_per_minute: None,
+            ..Default::default()
+        }));
+        let held = manager.acquire(Uuid::new_v4(), "a", "implementer", TaskPriority::Normal).await.unwrap();
+        let wait = |node_id: &'static str| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.acquire(Uuid::new_v4(), node_id, "tester", TaskPriority::Normal).await })
+        };
+        let (first, second, third) = (wait("b"), wait("c"), wait("d"));
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        let queued = manager.list_queued();
+        let node_ids: Vec<_> = queued.iter().map(|info| info.task.node_id.as_str()).collect();
+        assert_eq!(node_ids, ["b", "c", "d"]);
+
+        // Moving a task up in the queue moves it up in line for the slot
+        manager.reprioritize_queued(&queued[2].task.id, TaskPriority::High).unwrap();
+        assert_eq!(manager.cancel_queued(&queued[0].task.id).unwrap().node_id, "b");
+        assert!(matches!(first.await.unwrap(), Err(ResourceError::Cancelled)));
+
+        manager.release(held);
+        let permit = third.await.unwrap().unwrap();
+        assert!(!second.is_finished());
+        assert_eq!(manager.queue_length(), 1);
+        drop(permit);
+        assert!(second.await.unwrap().is_ok());
+        assert_eq!(manager.queue_length(), 0);
+
+        let stats = manager.get_stats();
+        assert_eq!((stats.total_queued, stats.total_dequeued), (3, 2));
+    }
+
+    #[tokio::test]
+    async fn test_shortest_job_first_grants_slots() {
+        let manager = Arc::new(ResourceManager::new(ResourceConfig {
+            max_concurrent_agents: 1,
+            rate_limit_per_minute: None,
+            queue_policy: QueuePolicy::ShortestJobFirst,
+            ..Default::default()
+        }));
+        manager.record_duration("implementer", 60_000);
+        manager.record_duration("reviewer", 5_000);
+        let held = manager.acquire(Uuid::new_v4(), "a", "tester", TaskPriority::Normal).await.unwrap();
+        let wait = |node_id: &'static str, role: &'static str| {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.acquire(Uuid::new_v4(), node_id, role, TaskPriority::Normal).await })
+        };
+        let long = wait("long", "implementer");
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        let short = wait("short", "reviewer");
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+
+        // The short job goes first, though it came later
+        manager.release(held);
+        let permit = short.await.unwrap().unwrap();
+        assert!(!long.is_finished());
+        drop(permit);
+        assert!(long.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_waiting_past_the_timeout_keeps_the_place_in_line() {
+        let manager = Arc::new(ResourceManager::new(ResourceConfig {
+            max_concurrent_agents: 1,
+            rate_limit_per_minute: None,
+            acquire_timeout_ms: 10,
+            ..Default::default()
+        }));
+        let held = manager.acquire(Uuid::new_v4(), "a", "implementer", TaskPriority::Normal).await.unwrap();
+        let waiting = {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                let key = RateLimitKey::backend("anthropic");
+                manager
+                    .acquire_for_while(&key, Uuid::new_v4(), "b", "tester", TaskPriority::Normal, || true)
+                    .await
+            })
+        };
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        let task = manager.list_queued().remove(0).task;
+        manager.reprioritize_queued(&task.id, TaskPriority::High).unwrap();
+
+        tokio::time::sleep(std::time::Duration::from_millis(40)).await;
+        let queued = manager.list_queued();
+        assert_eq!(queued.len(), 1);
+        assert_eq!((queued[0].task.id, queued[0].task.priority), (task.id, TaskPriority::High));
+        assert_eq!(queued[0].task.queued_at, task.queued_at);
+
+        manager.release(held);
+        assert!(waiting.await.unwrap().is_ok());
+        assert_eq!(manager.get_stats().total_timeouts, 0);
+    }
+
+    #[test]
+    fn test_estimates_from_history() {
+        let manager = ResourceManager::new(ResourceConfig::default());
+        manager.record_duration("tester", 1_000);
+        manager.record_duration("tester", 3_000);
+
+        assert_eq!(manager.estimated_duration("tester"), Some(2_000));
+        assert_eq!(manager.estimated_duration("implementer"), None);
+
+        manager.queue_task(queued("t", "tester", None)).unwrap();
+        let task = manager.dequeue_task().unwrap();
+        assert_eq!(task.estimated_duration_ms, Some(2_000));
+    }
 }
@@ -0,0 +1,200 @@
+//! Cached results of agent nodes.
+//!
+//! A node that opts in declares the project files it reads. Its output is
+//! kept along with a hash of each of those files, and a later run with the
+//! same prompt reuses it instead of spawning an agent, as long as none of
+//! the files changed. Provides:
+//! - Keys covering everything the agent is given
+//! - Hashing the files a node reads, from glob patterns
+//! - Lookups that drop entries whose files changed since they were stored
+//!
+//! Files are hashed after the node finishes, so a node that edits what it
+//! reads is cached with the files as it left them.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Component, Path};
+use uuid::Uuid;
+
+/// Tag on outputs reused from the cache
+pub const CACHED_TAG: &str = "cached";
+
+/// A node's opt-in to result caching
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NodeCacheConfig {
+    /// Glob patterns, relative to the project, of the files the node reads,
+    /// e.g. `src/**/*.rs`. Nodes that declare none aren't cached.
+    pub reads: Vec<String>,
+}
+
+/// A stored result
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheEntry {
+    pub project_id: Uuid,
+    pub node_id: String,
+    /// Hash by path, relative to the project
+    pub files: BTreeMap<String, String>,
+    pub output: String,
+    pub cached_at: DateTime<Utc>,
+}
+
+/// The key for a node run with this prompt
+pub fn cache_key(
+    project_id: Uuid,
+    node_id: &str,
+    agent_role: &str,
+    system_prompt: Option<&str>,
+    task: Option<&str>,
+    model: Option<&str>,
+) -> String {
+    let parts = [
+        project_id.to_string().as_str(),
+        node_id,
+        agent_role,
+        system_prompt.unwrap_or(""),
+        task.unwrap_or(""),
+        model.unwrap_or(""),
+    ]
+    .join("\u{0}");
+    format!("{}:{}", node_id, fnv1a(parts.as_bytes()))
+}
+
+/// Hash every file under `dir` matching `patterns`
+pub fn hash_files(dir: &Path, patterns: &[String]) -> Result<BTreeMap<String, String>, String> {
+    let mut files = BTreeMap::new();
+    for pattern in patterns {
+        let relative = Path::new(pattern);
+        if relative.is_absolute() || relative.components().any(|c| c == Component::ParentDir) {
+            return Err(format!("Read pattern '{}' must stay inside the project", pattern));
+        }
+        let full = format!("{}/{}", glob::Pattern::escape(&dir.to_string_lossy()), pattern);
+        let paths = glob::glob(&full).map_err(|e| format!("Invalid read pattern '{}': {}", pattern, e))?;
+        for path in paths.flatten().filter(|path| path.is_file()) {
+            let content = std::fs::read(&path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let key = path.strip_prefix(dir).unwrap_or(&path).to_string_lossy().replace('\\', "/");
+            files.insert(key, fnv1a(&content));
+        }
+    }
+    Ok(files)
+}
+
+pub(crate) fn fnv1a(bytes: &[u8]) -> String {
+    let hash = bytes
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| (hash ^ *byte as u64).wrapping_mul(0x0100_0000_01b3));
+    format!("{:016x}", hash)
+}
+
+/// Every cached node result
+#[derive(Default)]
+pub struct ResultCache {
+    entries: DashMap<String, CacheEntry>,
+}
+
+impl ResultCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The output stored under `key`, if the files it read under `dir` are
+    /// unchanged. An entry whose files changed is dropped.
+    pub fn lookup(&self, key: &str, config: &NodeCacheConfig, dir: &Path) -> Option<String> {
+        let entry = self.entries.get(key)?.clone();
+        match hash_files(dir, &config.reads) {
+            Ok(files) if files == entry.files => Some(entry.output),
+            _ => {
+                log::debug!("Cached result of node {} is stale", entry.node_id);
+                self.entries.remove(key);
+                None
+            }
+        }
+    }
+
+    /// Keep `output` under `key`, with the current hashes of the files read
+    pub fn store(
+        &self,
+        key: String,
+        project_id: Uuid,
+        node_id: &str,
+        config: &NodeCacheConfig,
+        dir: &Path,
+        output: String,
+    ) -> Result<(), String> {
+        let files = hash_files(dir, &config.reads)?;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                project_id,
+                node_id: node_id.to_string(),
+                files,
+                output,
+                cached_at: Utc::now(),
+            },
+        );
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Drop a project's entries, or every entry; returns how many
+    pub fn clear(&self, project_id: Option<Uuid>) -> usize {
+        let before = self.entries.len();
+        match project_id {
+            Some(project_id) => self.entries.retain(|_, entry| entry.project_id != project_id),
+            None => self.entries.clear(),
+        }
+        before - self.entries.len()
+    }
+}
+
+lazy_static! {
+    pub static ref RESULT_CACHE: ResultCache = ResultCache::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_invalidated_by_file_changes() {
+        let dir = std::env::temp_dir().join(format!("nexus-result-cache-{}", Uuid::new_v4()));
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::write(dir.join("src/lib.rs"), "fn a() {}").unwrap();
+        fs::write(dir.join("README.md"), "docs").unwrap();
+
+        let cache = ResultCache::new();
+        let project_id = Uuid::new_v4();
+        let config = NodeCacheConfig {
+            reads: vec!["src/**/*.rs".to_string()],
+        };
+        let key = cache_key(project_id, "review", "reviewer", None, Some("Review the code"), None);
+        assert_ne!(key, cache_key(project_id, "review", "reviewer", None, Some("Review the tests"), None));
+
+        cache.store(key.clone(), project_id, "review", &config, &dir, "LGTM".to_string()).unwrap();
+        assert_eq!(cache.lookup(&key, &config, &dir).as_deref(), Some("LGTM"));
+
+        // Files outside the patterns don't matter
+        fs::write(dir.join("README.md"), "more docs").unwrap();
+        assert!(cache.lookup(&key, &config, &dir).is_some());
+
+        fs::write(dir.join("src/main.rs"), "fn main() {}").unwrap();
+        assert!(cache.lookup(&key, &config, &dir).is_none());
+        assert!(cache.is_empty());
+
+        let escaping = NodeCacheConfig {
+            reads: vec!["../*".to_string()],
+        };
+        assert!(hash_files(&dir, &escaping.reads).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
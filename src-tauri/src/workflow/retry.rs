@@ -4,6 +4,7 @@
 //! - Exponential backoff with jitter
 //! - Maximum retry attempts
 //! - Custom retry conditions
+//! - Retried attempts told what went wrong last time
 //! - Fallback strategies
 
 use chrono::{DateTime, Utc};
@@ -32,6 +33,65 @@ pub struct RetryConfig {
     pub retry_patterns: Vec<String>,
     /// Error patterns that should NOT trigger retry (take precedence)
     pub no_retry_patterns: Vec<String>,
+    /// What a retried attempt is asked
+    #[serde(default)]
+    pub prompt_mode: RetryPromptMode,
+}
+
+/// Default template for `RetryPromptMode::WithFeedback`
+pub const DEFAULT_FEEDBACK_TEMPLATE: &str = "## Previous attempt\n\n\
+Attempt {attempt} at this task failed:\n\n{error}\n\n\
+The end of its output:\n\n{output}\n\n\
+Don't repeat what failed; take a different approach.";
+
+/// Characters of a failed attempt's output kept for the next prompt
+const FEEDBACK_OUTPUT_CHARS: usize = 2000;
+
+/// What a retried attempt is asked
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RetryPromptMode {
+    /// Re-send the prompt that failed
+    #[default]
+    Same,
+    /// Append the failed attempt's error and the end of its output to the
+    /// task. `template` may use `{attempt}`, `{error}` and `{output}`.
+    WithFeedback {
+        #[serde(default = "default_feedback_template")]
+        template: String,
+    },
+}
+
+fn default_feedback_template() -> String {
+    DEFAULT_FEEDBACK_TEMPLATE.to_string()
+}
+
+impl RetryPromptMode {
+    /// The task for the attempt after `attempt` failed with `error`. Each
+    /// retry starts from the original task, so feedback doesn't pile up.
+    pub fn next_task(&self, task: &str, attempt: u32, error: &str, output: Option<&str>) -> String {
+        let Self::WithFeedback { template } = self else {
+            return task.to_string();
+        };
+
+        let output = output.map(str::trim).filter(|o| !o.is_empty()).map_or_else(
+            || "(no output)".to_string(),
+            |output| match output.char_indices().rev().nth(FEEDBACK_OUTPUT_CHARS - 1) {
+                Some((cut, _)) if cut > 0 => format!("...{}", &output[cut..]),
+                _ => output.to_string(),
+            },
+        );
+        let feedback = template
+            .replace("{attempt}", &attempt.to_string())
+            .replace("{error}", error)
+            .replace("{output}", &output);
+
+        if task.is_empty() {
+            feedback
+        } else {
+            format!("{}\n\n{}", task, feedback)
+        }
+    }
 }
 
 impl Default for RetryConfig {
@@ -59,6 +119,7 @@ impl Default for RetryConfig {
                 "permission denied".to_string(),
                 "not found".to_string(),
             ],
+            prompt_mode: RetryPromptMode::Same,
         }
     }
 }
@@ -183,6 +244,26 @@ impl RetryState {
             };
         }
 
+        self.schedule_retry(error)
+    }
+
+    /// Check if we should retry after an output was rejected (e.g. by a
+    /// quality gate). Rejections skip the error patterns; only the attempt
+    /// budget applies.
+    pub fn should_retry_rejection(&mut self, reason: &str) -> RetryDecision {
+        self.current_attempt += 1;
+
+        if self.current_attempt > self.config.max_attempts {
+            return RetryDecision::Exhausted {
+                total_attempts: self.current_attempt,
+            };
+        }
+
+        self.schedule_retry(reason)
+    }
+
+    /// Record the error and compute the backoff before the next attempt
+    fn schedule_retry(&mut self, error: &str) -> RetryDecision {
         // Calculate delay with exponential backoff
         let delay = self.calculate_delay();
 
@@ -346,7 +427,7 @@ where
 }
 
 /// Fallback strategy when all retries fail
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub enum FallbackStrategy {
     /// Skip this node and continue with workflow
     Skip,
@@ -357,19 +438,41 @@ pub enum FallbackStrategy {
     /// Notify user and pause for manual intervention
     PauseForIntervention,
     /// Fail the entire workflow
+    #[default]
     FailWorkflow,
 }
 
-impl Default for FallbackStrategy {
-    fn default() -> Self {
-        Self::FailWorkflow
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_retry_prompt_with_feedback() {
+        assert_eq!(RetryPromptMode::Same.next_task("Fix the build", 1, "Agent execution failed", None), "Fix the build");
+
+        let mode = RetryPromptMode::WithFeedback {
+            template: "Attempt {attempt} failed ({error}). Output: {output}".to_string(),
+        };
+        assert_eq!(
+            mode.next_task("Fix the build", 2, "tests failed", Some("3 failures\n")),
+            "Fix the build\n\nAttempt 2 failed (tests failed). Output: 3 failures"
+        );
+        assert!(mode.next_task("Fix the build", 1, "killed", None).ends_with("Output: (no output)"));
+
+        // Only the end of a long output is kept
+        let long = format!("{}{}", "a".repeat(FEEDBACK_OUTPUT_CHARS), "tail");
+        let task = mode.next_task("", 1, "failed", Some(&long));
+        let kept = task.split_once("Output: ...").unwrap().1;
+        assert_eq!(kept.chars().count(), FEEDBACK_OUTPUT_CHARS);
+        assert!(kept.ends_with("tail"));
+
+        // The template defaults when left out
+        let mode: RetryPromptMode = serde_json::from_str(r#"{"type": "with_feedback"}"#).unwrap();
+        assert_eq!(mode, RetryPromptMode::WithFeedback { template: DEFAULT_FEEDBACK_TEMPLATE.to_string() });
+        let config: RetryConfig = serde_json::from_value(serde_json::to_value(RetryConfig::default()).unwrap()).unwrap();
+        assert_eq!(config.prompt_mode, RetryPromptMode::Same);
+    }
+
     #[test]
     fn test_should_retry_timeout() {
         let config = RetryConfig::default();
@@ -417,6 +520,26 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn test_rejection_ignores_patterns() {
+        let config = RetryConfig {
+            max_attempts: 1,
+            ..Default::default()
+        };
+        let mut state = RetryState::new(config);
+
+        // Would be NoRetry as an error, but rejections only count attempts
+        assert!(matches!(
+            state.should_retry_rejection("Quality gate scored 3 (minimum 7)"),
+            RetryDecision::Retry { attempt: 1, .. }
+        ));
+        assert!(matches!(
+            state.should_retry_rejection("Quality gate scored 5 (minimum 7)"),
+            RetryDecision::Exhausted { .. }
+        ));
+        assert_eq!(state.get_errors().len(), 1);
+    }
+
     #[test]
     fn test_exponential_backoff() {
         let config = RetryConfig {
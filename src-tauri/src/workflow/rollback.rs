@@ -0,0 +1,248 @@
+//! Undo an execution's file changes.
+//!
+//! When an execution starts in a git repository, the whole working tree
+//! (including uncommitted and untracked files) is written to a git tree
+//! object kept alive by `refs/nexus/executions/<id>`, without touching the
+//! index, branch or stash. Provides:
+//! - Recording that base tree
+//! - A preview of what undoing would change, limited to files the
+//!   execution's activity feed says it touched
+//! - Restoring those files to their base content (and deleting files the
+//!   execution created)
+//!
+//! Ignored files are not in the base tree and can't be restored.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+use tokio::process::Command;
+use uuid::Uuid;
+
+/// What undoing does to a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RollbackAction {
+    /// Put back the content from before the execution
+    Restore,
+    /// Remove a file the execution created
+    Delete,
+}
+
+/// A file that undoing would change
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RollbackFile {
+    pub path: String,
+    pub action: RollbackAction,
+    /// Nodes whose activity touched the file; empty when there is no
+    /// activity feed, e.g. after a restart
+    pub nodes: Vec<String>,
+}
+
+/// What undoing an execution would change
+#[derive(Debug, Clone, Serialize)]
+pub struct RollbackPreview {
+    pub execution_id: Uuid,
+    /// Tree holding the working tree from before the execution
+    pub base_tree: String,
+    pub files: Vec<RollbackFile>,
+    /// Unified diff from the current files to the restored ones
+    pub patch: String,
+}
+
+fn base_ref(execution_id: &Uuid) -> String {
+    format!("refs/nexus/executions/{}", execution_id)
+}
+
+pub(crate) async fn git(dir: &Path, args: &[&str], index: Option<&Path>) -> Result<String, String> {
+    let mut command = Command::new("git");
+    command.args(args).current_dir(dir);
+    if let Some(index) = index {
+        command.env("GIT_INDEX_FILE", index);
+    }
+
+    let output = command
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run git: {}", e))?;
+    if !output.status.success() {
+        return Err(format!(
+            "git {} failed: {}",
+            args.first().unwrap_or(&""),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Write the working tree, as it is on disk, to a tree object using a
+/// throwaway index
+async fn write_worktree_tree(dir: &Path) -> Result<String, String> {
+    let index = std::env::temp_dir().join(format!("nexus-index-{}", Uuid::new_v4()));
+    let result = async {
+        git(dir, &["add", "--all", "--", "."], Some(&index)).await?;
+        git(dir, &["write-tree"], Some(&index)).await
+    }
+    .await;
+    let _ = std::fs::remove_file(&index);
+    Ok(result?.trim().to_string())
+}
+
+/// Record the working tree of `dir` before an execution changes it
+pub async fn record_base(dir: &Path, execution_id: &Uuid) -> Result<String, String> {
+    let tree = write_worktree_tree(dir).await?;
+    git(dir, &["update-ref", &base_ref(execution_id), &tree], None).await?;
+    Ok(tree)
+}
+
+/// The base tree recorded for the execution, the working tree as a tree
+/// now, and the files that differ between them with git's status letter
+pub async fn changes_since_base(dir: &Path, execution_id: &Uuid) -> Result<(String, String, Vec<(String, String)>), String> {
+    let base_tree = git(dir, &["rev-parse", "--verify", "--quiet", &base_ref(execution_id)], None)
+        .await
+        .map_err(|_| format!("No rollback point recorded for execution {}", execution_id))?
+        .trim()
+        .to_string();
+    let current = write_worktree_tree(dir).await?;
+
+    let name_status = git(dir, &["diff", "--name-status", "--no-renames", "-z", &base_tree, &current], None).await?;
+    let mut fields = name_status.split('\0').filter(|field| !field.is_empty());
+    let mut changes = Vec::new();
+    while let (Some(status), Some(path)) = (fields.next(), fields.next()) {
+        changes.push((status.to_string(), path.to_string()));
+    }
+    Ok((base_tree, current, changes))
+}
+
+/// A file's content in a base tree; `None` if it didn't exist yet
+pub async fn base_content(dir: &Path, base_tree: &str, path: &str) -> Result<Option<String>, String> {
+    let spec = format!("{}:{}", base_tree, path);
+    if git(dir, &["cat-file", "-e", &spec], None).await.is_err() {
+        return Ok(None);
+    }
+    git(dir, &["show", &spec], None).await.map(Some)
+}
+
+/// What undoing the execution would change. `touched` maps the files the
+/// execution's activity feed lists to the nodes that touched them; without
+/// it every file that differs from the base is included.
+pub async fn preview(
+    dir: &Path,
+    execution_id: &Uuid,
+    touched: Option<&BTreeMap<String, Vec<String>>>,
+) -> Result<RollbackPreview, String> {
+    let (base_tree, current, changes) = changes_since_base(dir, execution_id).await?;
+    let mut files = Vec::new();
+    for (status, path) in &changes {
+        let path = path.as_str();
+        let nodes = match touched {
+            Some(touched) => match touched.get(path) {
+                Some(nodes) => nodes.clone(),
+                None => continue,
+            },
+            None => Vec::new(),
+        };
+        files.push(RollbackFile {
+            path: path.to_string(),
+            // Added since the base means the execution created it
+            action: if status == "A" { RollbackAction::Delete } else { RollbackAction::Restore },
+            nodes,
+        });
+    }
+
+    let patch = if files.is_empty() {
+        String::new()
+    } else {
+        let mut args = vec!["diff", "--no-renames", current.as_str(), base_tree.as_str(), "--"];
+        args.extend(files.iter().map(|file| file.path.as_str()));
+        git(dir, &args, None).await?
+    };
+
+    Ok(RollbackPreview {
+        execution_id: *execution_id,
+        base_tree,
+        files,
+        patch,
+    })
+}
+
+/// Undo the changes listed in a preview
+pub async fn apply(dir: &Path, preview: &RollbackPreview) -> Result<(), String> {
+    let restore: Vec<&str> = preview
+        .files
+        .iter()
+        .filter(|file| file.action == RollbackAction::Restore)
+        .map(|file| file.path.as_str())
+        .collect();
+    if !restore.is_empty() {
+        // Only the working tree: the index and staged changes are left alone
+        let source = format!("--source={}", preview.base_tree);
+        let mut args = vec!["restore", source.as_str(), "--worktree", "--"];
+        args.extend(restore);
+        git(dir, &args, None).await?;
+    }
+
+    for file in preview.files.iter().filter(|file| file.action == RollbackAction::Delete) {
+        match std::fs::remove_file(dir.join(&file.path)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => {
+                return Err(format!("Failed to delete {}: {}", file.path, e));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_preview_and_apply() {
+        let dir = std::env::temp_dir().join(format!("nexus-rollback-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        git(&dir, &["init", "-q"], None).await.unwrap();
+        std::fs::write(dir.join("kept.txt"), "original").unwrap();
+        std::fs::write(dir.join("removed.txt"), "keep me").unwrap();
+        std::fs::write(dir.join("user.txt"), "user").unwrap();
+
+        let execution_id = Uuid::new_v4();
+        assert!(preview(&dir, &execution_id, None).await.unwrap_err().contains("No rollback point"));
+        record_base(&dir, &execution_id).await.unwrap();
+
+        // The execution edits, deletes and creates files; the user edits one too
+        std::fs::write(dir.join("kept.txt"), "agent edit").unwrap();
+        std::fs::remove_file(dir.join("removed.txt")).unwrap();
+        std::fs::write(dir.join("created.txt"), "new").unwrap();
+        std::fs::write(dir.join("user.txt"), "user edit").unwrap();
+
+        let touched: BTreeMap<String, Vec<String>> = ["kept.txt", "removed.txt", "created.txt"]
+            .into_iter()
+            .map(|path| (path.to_string(), vec!["implement".to_string()]))
+            .collect();
+        let plan = preview(&dir, &execution_id, Some(&touched)).await.unwrap();
+        let actions: Vec<(&str, RollbackAction)> = plan.files.iter().map(|f| (f.path.as_str(), f.action)).collect();
+        assert_eq!(
+            actions,
+            vec![
+                ("created.txt", RollbackAction::Delete),
+                ("kept.txt", RollbackAction::Restore),
+                ("removed.txt", RollbackAction::Restore),
+            ]
+        );
+        assert!(plan.patch.contains("-agent edit"));
+        assert!(plan.patch.contains("+original"));
+
+        apply(&dir, &plan).await.unwrap();
+        assert_eq!(std::fs::read_to_string(dir.join("kept.txt")).unwrap(), "original");
+        assert_eq!(std::fs::read_to_string(dir.join("removed.txt")).unwrap(), "keep me");
+        assert!(!dir.join("created.txt").exists());
+        assert_eq!(std::fs::read_to_string(dir.join("user.txt")).unwrap(), "user edit");
+
+        // Without an activity feed everything that differs is listed
+        let rest = preview(&dir, &execution_id, None).await.unwrap();
+        assert_eq!(rest.files.len(), 1);
+        assert_eq!(rest.files[0].path, "user.txt");
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
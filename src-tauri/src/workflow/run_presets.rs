@@ -0,0 +1,186 @@
+//! Saved arguments for running a workflow.
+//!
+//! Recurring runs, like a weekly dependency audit of one repository, use
+//! the same project, prompt and settings every time. Provides:
+//! - Named presets per saved workflow: project, input prompt, variables
+//!   and node config overrides
+//! - The prompt with the preset's variables filled into its `{{name}}`
+//!   placeholders
+//! - When each preset last ran, and which execution that was
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use uuid::Uuid;
+
+use super::batch::render_prompt;
+
+/// A preset as saved by the user; without an ID it is a new one
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunPresetInput {
+    #[serde(default)]
+    pub id: Option<Uuid>,
+    pub workflow_id: Uuid,
+    pub name: String,
+    pub project_id: Uuid,
+    pub input_prompt: String,
+    #[serde(default)]
+    pub variables: HashMap<String, serde_json::Value>,
+    /// Node configs by node ID, as `execute_enhanced_workflow` takes them
+    #[serde(default)]
+    pub node_configs: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunPreset {
+    pub id: Uuid,
+    pub workflow_id: Uuid,
+    pub name: String,
+    pub project_id: Uuid,
+    /// May contain `{{name}}` placeholders for the variables
+    pub input_prompt: String,
+    /// Seeded into the execution context, and filled into the prompt
+    pub variables: HashMap<String, serde_json::Value>,
+    pub node_configs: HashMap<String, serde_json::Value>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub last_execution_id: Option<Uuid>,
+}
+
+impl RunPreset {
+    /// The input prompt with the variables filled in
+    pub fn prompt(&self) -> String {
+        render_prompt(&self.input_prompt, &self.variables)
+    }
+}
+
+/// Every workflow's run presets
+#[derive(Default)]
+pub struct RunPresetStore {
+    presets: DashMap<Uuid, RunPreset>,
+}
+
+impl RunPresetStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a preset, or replace the one with the input's ID. Names are
+    /// unique per workflow.
+    pub fn save(&self, input: RunPresetInput) -> Result<RunPreset, String> {
+        let name = input.name.trim().to_string();
+        if name.is_empty() {
+            return Err("A preset needs a name".to_string());
+        }
+        let taken = self.presets.iter().any(|preset| {
+            preset.workflow_id == input.workflow_id && preset.name.eq_ignore_ascii_case(&name) && Some(preset.id) != input.id
+        });
+        if taken {
+            return Err(format!("The workflow already has a preset named '{}'", name));
+        }
+
+        let now = Utc::now();
+        let existing = match input.id {
+            Some(id) => Some(self.get(&id).ok_or_else(|| format!("Preset not found: {}", id))?),
+            None => None,
+        };
+        let preset = RunPreset {
+            id: input.id.unwrap_or_else(Uuid::new_v4),
+            workflow_id: input.workflow_id,
+            name,
+            project_id: input.project_id,
+            input_prompt: input.input_prompt,
+            variables: input.variables,
+            node_configs: input.node_configs,
+            created_at: existing.as_ref().map_or(now, |existing| existing.created_at),
+            updated_at: now,
+            last_run_at: existing.as_ref().and_then(|existing| existing.last_run_at),
+            last_execution_id: existing.and_then(|existing| existing.last_execution_id),
+        };
+        self.presets.insert(preset.id, preset.clone());
+        Ok(preset)
+    }
+
+    pub fn get(&self, id: &Uuid) -> Option<RunPreset> {
+        self.presets.get(id).map(|preset| preset.clone())
+    }
+
+    /// Presets of one workflow or of all, by name
+    pub fn list(&self, workflow_id: Option<&Uuid>) -> Vec<RunPreset> {
+        let mut presets: Vec<RunPreset> = self
+            .presets
+            .iter()
+            .filter(|preset| workflow_id.map_or(true, |id| preset.workflow_id == *id))
+            .map(|preset| preset.clone())
+            .collect();
+        presets.sort_by_key(|preset| preset.name.to_lowercase());
+        presets
+    }
+
+    /// Delete a preset; false if there was none
+    pub fn delete(&self, id: &Uuid) -> bool {
+        self.presets.remove(id).is_some()
+    }
+
+    /// Record that a preset started `execution_id`
+    pub fn record_run(&self, id: &Uuid, execution_id: Uuid) {
+        if let Some(mut preset) = self.presets.get_mut(id) {
+            preset.last_run_at = Some(Utc::now());
+            preset.last_execution_id = Some(execution_id);
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref RUN_PRESETS: RunPresetStore = RunPresetStore::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_run_presets() {
+        let store = RunPresetStore::new();
+        let (workflow_id, project_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let input = |name: &str| RunPresetInput {
+            id: None,
+            workflow_id,
+            name: name.to_string(),
+            project_id,
+            input_prompt: "Audit the dependencies of {{repo}} for {{severity}} issues".to_string(),
+            variables: HashMap::from([("repo".to_string(), json!("shop-api")), ("severity".to_string(), json!("high"))]),
+            node_configs: HashMap::new(),
+        };
+
+        let weekly = store.save(input("Weekly audit")).unwrap();
+        assert_eq!(weekly.prompt(), "Audit the dependencies of shop-api for high issues");
+        assert!(store.save(input("weekly AUDIT")).is_err());
+        assert!(store.save(input(" ")).is_err());
+        store.save(input("Before release")).unwrap();
+        let names: Vec<String> = store.list(Some(&workflow_id)).into_iter().map(|preset| preset.name).collect();
+        assert_eq!(names, ["Before release", "Weekly audit"]);
+        assert!(store.list(Some(&Uuid::new_v4())).is_empty());
+
+        let execution_id = Uuid::new_v4();
+        store.record_run(&weekly.id, execution_id);
+        // Editing keeps when it was created and last ran
+        let edited = store
+            .save(RunPresetInput {
+                id: Some(weekly.id),
+                input_prompt: "Audit {{repo}}".to_string(),
+                ..input("Weekly audit")
+            })
+            .unwrap();
+        assert_eq!((edited.created_at, edited.last_execution_id), (weekly.created_at, Some(execution_id)));
+        assert_eq!(edited.prompt(), "Audit shop-api");
+        assert!(store.save(RunPresetInput { id: Some(Uuid::new_v4()), ..input("Other") }).is_err());
+
+        assert!(store.delete(&weekly.id));
+        assert!(!store.delete(&weekly.id));
+    }
+}
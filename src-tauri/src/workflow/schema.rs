@@ -0,0 +1,195 @@
+//! Versioned JSON Schema for the workflow graph format.
+//!
+//! Provides:
+//! - The published schema (`schemas/workflow-graph.schema.json`)
+//! - Validation with JSON Pointer paths to each violation
+//! - Step-by-step migration of older graph documents to the current version
+//!
+//! Version history:
+//! - v1: unversioned. Nodes may be flat, with snake_case fields at the top
+//!   level (the shape emitted in `workflow-graph-updated` events).
+//! - v2: `version` field; node fields live under camelCase `data`.
+
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::graph::GraphError;
+
+/// Migrations in order; `MIGRATIONS[n]` upgrades version `n + 1` to `n + 2`
+const MIGRATIONS: &[fn(&mut Map<String, Value>)] = &[migrate_v1_to_v2];
+
+/// Current graph format version
+pub const GRAPH_SCHEMA_VERSION: u64 = MIGRATIONS.len() as u64 + 1;
+
+static GRAPH_SCHEMA: Lazy<Value> = Lazy::new(|| {
+    serde_json::from_str(include_str!("../../schemas/workflow-graph.schema.json"))
+        .expect("bundled graph schema is valid JSON")
+});
+
+static GRAPH_VALIDATOR: Lazy<jsonschema::Validator> = Lazy::new(|| {
+    jsonschema::validator_for(&GRAPH_SCHEMA).expect("bundled graph schema is a valid JSON Schema")
+});
+
+/// One place where a graph document breaks the schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// JSON Pointer to the offending value ("" for the document root)
+    pub path: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for SchemaViolation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{}: {}", path, self.message)
+    }
+}
+
+/// The graph JSON Schema for the current version
+pub fn graph_schema() -> &'static Value {
+    &GRAPH_SCHEMA
+}
+
+/// Check a current-version graph against the schema
+pub fn validate_graph(graph: &Value) -> Result<(), Vec<SchemaViolation>> {
+    let violations: Vec<SchemaViolation> = GRAPH_VALIDATOR
+        .iter_errors(graph)
+        .map(|e| SchemaViolation {
+            path: e.instance_path.to_string(),
+            message: e.to_string(),
+        })
+        .collect();
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
+
+/// Format version of a graph document; unversioned documents are v1
+pub fn graph_version(graph: &Value) -> Result<u64, GraphError> {
+    match graph.get("version") {
+        None | Some(Value::Null) => Ok(1),
+        Some(version) => version
+            .as_u64()
+            .filter(|v| *v >= 1)
+            .ok_or_else(|| GraphError::InvalidFormat(format!("Invalid graph version: {}", version))),
+    }
+}
+
+/// Upgrade a graph document to the current version. Current documents
+/// are returned unchanged.
+pub fn migrate_graph(mut graph: Value) -> Result<Value, GraphError> {
+    let version = graph_version(&graph)?;
+    if version > GRAPH_SCHEMA_VERSION {
+        return Err(GraphError::UnsupportedVersion(version));
+    }
+
+    let doc = graph
+        .as_object_mut()
+        .ok_or_else(|| GraphError::InvalidFormat("Graph must be a JSON object".to_string()))?;
+
+    for migrate in &MIGRATIONS[(version - 1) as usize..] {
+        migrate(doc);
+    }
+    doc.insert("version".to_string(), json!(GRAPH_SCHEMA_VERSION));
+
+    Ok(graph)
+}
+
+/// v1 -> v2: nest flat node fields under `data`, camelCase them, move edge
+/// `data_type` into `data.dataType`, and default missing `edges`
+fn migrate_v1_to_v2(doc: &mut Map<String, Value>) {
+    const NODE_FIELDS: &[(&str, &str)] = &[
+        ("agent_role", "agentRole"),
+        ("system_prompt", "systemPrompt"),
+        ("assigned_task", "assignedTask"),
+    ];
+
+    doc.entry("edges").or_insert_with(|| json!([]));
+
+    if let Some(Value::Array(nodes)) = doc.get_mut("nodes") {
+        for node in nodes.iter_mut().filter_map(Value::as_object_mut) {
+            if !node.contains_key("data") {
+                let mut data = Map::new();
+                for key in ["label", "kind", "agent_role", "system_prompt", "assigned_task"] {
+                    if let Some(value) = node.remove(key) {
+                        data.insert(key.to_string(), value);
+                    }
+                }
+                node.insert("data".to_string(), Value::Object(data));
+            }
+
+            if let Some(Value::Object(data)) = node.get_mut("data") {
+                for (old, new) in NODE_FIELDS {
+                    if let Some(value) = data.remove(*old) {
+                        data.entry(*new).or_insert(value);
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(Value::Array(edges)) = doc.get_mut("edges") {
+        for edge in edges.iter_mut().filter_map(Value::as_object_mut) {
+            if let Some(data_type) = edge.remove("data_type") {
+                if let Value::Object(data) = edge.entry("data").or_insert_with(|| json!({})) {
+                    data.entry("dataType").or_insert(data_type);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_flat_v1_graph() {
+        let v1 = json!({
+            "nodes": [
+                {"id": "a", "label": "A", "agent_role": "architect", "system_prompt": null},
+                {"id": "b", "data": {"label": "B", "agentRole": "implementer"}}
+            ],
+            "edges": [{"id": "e1", "source": "a", "target": "b", "data_type": "text"}]
+        });
+
+        let migrated = migrate_graph(v1).unwrap();
+        assert_eq!(migrated["version"], json!(GRAPH_SCHEMA_VERSION));
+        assert_eq!(migrated["nodes"][0]["data"]["agentRole"], json!("architect"));
+        assert_eq!(migrated["nodes"][0]["data"]["label"], json!("A"));
+        assert_eq!(migrated["edges"][0]["data"]["dataType"], json!("text"));
+        assert_eq!(validate_graph(&migrated), Ok(()));
+
+        // Current documents pass through untouched
+        assert_eq!(migrate_graph(migrated.clone()).unwrap(), migrated);
+    }
+
+    #[test]
+    fn test_rejects_newer_version() {
+        let future = json!({"version": GRAPH_SCHEMA_VERSION + 1, "nodes": [], "edges": []});
+        assert!(matches!(migrate_graph(future), Err(GraphError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn test_violation_paths() {
+        let graph = json!({
+            "version": GRAPH_SCHEMA_VERSION,
+            "nodes": [
+                {"id": "a", "data": {"label": "A", "agentRole": "architect"}},
+                {"id": "b", "data": {"label": "B"}},
+                {"id": "s", "data": {"label": "S", "kind": {"type": "script"}}}
+            ],
+            "edges": [{"id": "e1", "source": "a"}]
+        });
+
+        let paths: Vec<String> = validate_graph(&graph).unwrap_err().into_iter().map(|v| v.path).collect();
+        assert!(paths.contains(&"/nodes/1/data".to_string()));
+        assert!(paths.contains(&"/nodes/2/data/kind".to_string()));
+        assert!(paths.contains(&"/edges/0".to_string()));
+        assert!(!paths.iter().any(|p| p.starts_with("/nodes/0")));
+    }
+}
@@ -0,0 +1,173 @@
+//! Embedded Rhai scripting for lightweight script nodes.
+//!
+//! Script nodes run glue logic in-process instead of spawning an agent.
+//! A script sees:
+//! - `input` - the original execution prompt
+//! - `inputs` - map of predecessor node id to its latest output
+//! - `vars` - the execution context variables (changes are written back)
+//!
+//! The value of the last expression becomes the node's output: strings
+//! become `Text`, everything else is converted to `Json`.
+
+use rhai::{Dynamic, Engine, Scope};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use super::context::OutputData;
+
+/// Limits applied to every script run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScriptLimits {
+    /// Maximum number of operations before the script is aborted
+    pub max_operations: u64,
+    /// Maximum length of any string value
+    pub max_string_size: usize,
+    /// Maximum length of any array
+    pub max_array_size: usize,
+    /// Maximum number of entries in any object map
+    pub max_map_size: usize,
+}
+
+impl Default for ScriptLimits {
+    fn default() -> Self {
+        Self {
+            max_operations: 1_000_000,
+            max_string_size: 1024 * 1024,
+            max_array_size: 10_000,
+            max_map_size: 10_000,
+        }
+    }
+}
+
+/// Everything a script can read
+#[derive(Debug, Clone, Default)]
+pub struct ScriptInput {
+    pub prompt: String,
+    pub inputs: HashMap<String, serde_json::Value>,
+    pub variables: HashMap<String, serde_json::Value>,
+}
+
+/// Result of a script run
+#[derive(Debug, Clone)]
+pub struct ScriptResult {
+    pub output: OutputData,
+    /// Variables that were added or changed by the script
+    pub changed_variables: HashMap<String, serde_json::Value>,
+}
+
+fn build_engine(limits: &ScriptLimits) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(limits.max_operations);
+    engine.set_max_string_size(limits.max_string_size);
+    engine.set_max_array_size(limits.max_array_size);
+    engine.set_max_map_size(limits.max_map_size);
+    engine
+}
+
+fn to_dynamic<T: Serialize>(value: &T) -> Result<Dynamic, String> {
+    rhai::serde::to_dynamic(value).map_err(|e| format!("Failed to convert script input: {}", e))
+}
+
+/// Check a script for syntax errors without running it
+pub fn compile_check(source: &str) -> Result<(), String> {
+    build_engine(&ScriptLimits::default())
+        .compile(source)
+        .map(|_| ())
+        .map_err(|e| format!("Script syntax error: {}", e))
+}
+
+/// Run a script against the given inputs
+pub fn run_script(
+    source: &str,
+    input: &ScriptInput,
+    limits: &ScriptLimits,
+) -> Result<ScriptResult, String> {
+    let engine = build_engine(limits);
+
+    let mut scope = Scope::new();
+    scope.push("input", input.prompt.clone());
+    scope.push("inputs", to_dynamic(&input.inputs)?);
+    scope.push("vars", to_dynamic(&input.variables)?);
+
+    let result: Dynamic = engine
+        .eval_with_scope(&mut scope, source)
+        .map_err(|e| format!("Script failed: {}", e))?;
+
+    let value: serde_json::Value = rhai::serde::from_dynamic(&result)
+        .map_err(|e| format!("Script returned an unsupported value: {}", e))?;
+
+    let output = match value {
+        serde_json::Value::String(text) => OutputData::Text(text),
+        serde_json::Value::Null => OutputData::Text(String::new()),
+        other => OutputData::Json(other),
+    };
+
+    let mut changed_variables = HashMap::new();
+    if let Some(vars) = scope.get_value::<Dynamic>("vars") {
+        if let Ok(serde_json::Value::Object(map)) = rhai::serde::from_dynamic(&vars) {
+            for (key, value) in map {
+                if input.variables.get(&key) != Some(&value) {
+                    changed_variables.insert(key, value);
+                }
+            }
+        }
+    }
+
+    Ok(ScriptResult {
+        output,
+        changed_variables,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_input() -> ScriptInput {
+        let mut input = ScriptInput {
+            prompt: "Build a login page".to_string(),
+            ..Default::default()
+        };
+        input.inputs.insert("design".to_string(), serde_json::json!({"files": ["a.rs", "b.rs"]}));
+        input.variables.insert("count".to_string(), serde_json::json!(1));
+        input
+    }
+
+    #[test]
+    fn test_script_reads_inputs() {
+        let result = run_script(
+            r#"inputs.design.files.len() + vars.count"#,
+            &test_input(),
+            &ScriptLimits::default(),
+        )
+        .unwrap();
+
+        assert!(matches!(result.output, OutputData::Json(ref v) if v == &serde_json::json!(3)));
+        assert!(result.changed_variables.is_empty());
+    }
+
+    #[test]
+    fn test_script_writes_variables() {
+        let result = run_script(
+            r#"vars.count += 1; vars.status = "ok"; `Task: ${input}`"#,
+            &test_input(),
+            &ScriptLimits::default(),
+        )
+        .unwrap();
+
+        assert!(matches!(result.output, OutputData::Text(ref t) if t == "Task: Build a login page"));
+        assert_eq!(result.changed_variables.get("count"), Some(&serde_json::json!(2)));
+        assert_eq!(result.changed_variables.get("status"), Some(&serde_json::json!("ok")));
+    }
+
+    #[test]
+    fn test_script_operation_limit() {
+        let limits = ScriptLimits {
+            max_operations: 1_000,
+            ..Default::default()
+        };
+        let result = run_script("loop {}", &test_input(), &limits);
+        assert!(result.is_err());
+        assert!(compile_check("let x = ;").is_err());
+    }
+}
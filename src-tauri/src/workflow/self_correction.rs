@@ -0,0 +1,170 @@
+//! Self-correction loop for agent nodes.
+//!
+//! After an agent node finishes, the engine runs the node's test command in
+//! the project directory and, on failure, hands the test output back to the
+//! same agent for another round. Provides:
+//! - Per-node loop settings (test command, round limit, timeout)
+//! - Running the test command with captured, truncated output
+//! - The correction prompt for each round
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::time::{Duration, Instant};
+use tokio::process::Command;
+
+use super::text;
+use crate::process::group;
+
+const DEFAULT_MAX_ROUNDS: u32 = 3;
+/// Test commands get five minutes unless configured otherwise
+const DEFAULT_TEST_TIMEOUT_MS: u64 = 300_000;
+/// Test output kept for the agent, taken from the end where failures are reported
+const MAX_FEEDBACK_CHARS: usize = 8_000;
+
+fn default_max_rounds() -> u32 {
+    DEFAULT_MAX_ROUNDS
+}
+
+/// Test-and-fix loop settings for one agent node
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SelfCorrectionConfig {
+    /// Shell command that runs the test suite, e.g. `cargo test`
+    pub test_command: String,
+    /// Correction rounds allowed after the first attempt
+    #[serde(default = "default_max_rounds")]
+    pub max_rounds: u32,
+    #[serde(default)]
+    pub timeout_ms: Option<u64>,
+}
+
+/// Result of one run of the test command
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TestRun {
+    pub passed: bool,
+    pub exit_code: Option<i32>,
+    /// Combined stdout and stderr, keeping the last `MAX_FEEDBACK_CHARS`
+    pub output: String,
+    pub duration_ms: u64,
+    pub timed_out: bool,
+}
+
+/// Run the test command through the platform shell, with `env` set
+pub async fn run_tests(config: &SelfCorrectionConfig, working_dir: &Path, env: &[(String, String)]) -> Result<TestRun, String> {
+    let mut cmd = shell_command(&config.test_command);
+    cmd.current_dir(working_dir).envs(env.iter().cloned());
+
+    let timeout = Duration::from_millis(config.timeout_ms.unwrap_or(DEFAULT_TEST_TIMEOUT_MS));
+    let start = Instant::now();
+
+    // The test command's servers and watchers go with it
+    match group::output_within(&mut cmd, timeout).await {
+        Some(output) => {
+            let output = output.map_err(|e| format!("Failed to run '{}': {}", config.test_command, e))?;
+            let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+            text.push_str(&String::from_utf8_lossy(&output.stderr));
+
+            Ok(TestRun {
+                passed: output.status.success(),
+                exit_code: output.status.code(),
+                output: tail(&text, MAX_FEEDBACK_CHARS),
+                duration_ms: start.elapsed().as_millis() as u64,
+                timed_out: false,
+            })
+        }
+        None => Ok(TestRun {
+            passed: false,
+            exit_code: None,
+            output: format!("Test command did not finish within {}ms", timeout.as_millis()),
+            duration_ms: start.elapsed().as_millis() as u64,
+            timed_out: true,
+        }),
+    }
+}
+
+/// `command` run by the platform shell
+pub(crate) fn shell_command(command: &str) -> Command {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.arg("/C");
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.arg("-c");
+        cmd
+    };
+    cmd.arg(command);
+    cmd
+}
+
+/// Task for a correction round: the original task plus the failing output
+pub fn correction_prompt(task: &str, command: &str, run: &TestRun, round: u32, max_rounds: u32) -> String {
+    let status = match (run.timed_out, run.exit_code) {
+        (true, _) => "timed out".to_string(),
+        (false, Some(code)) => format!("exited with code {}", code),
+        (false, None) => "was terminated".to_string(),
+    };
+
+    format!(
+        "{}\n\n## Test failures (correction round {} of {})\n\n\
+         After your changes, `{}` {}. Output:\n\n```\n{}\n```\n\n\
+         Fix the code so the tests pass. Do not weaken or delete tests to make them pass.",
+        task,
+        round,
+        max_rounds,
+        command,
+        status,
+        run.output.trim_end()
+    )
+}
+
+/// Last `max` characters of `text`, marked when cut
+fn tail(output: &str, max: usize) -> String {
+    let count = text::grapheme_count(output);
+    if count <= max {
+        return output.to_string();
+    }
+
+    format!("[... {} characters omitted ...]\n{}", count - max, text::suffix(output, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(command: &str) -> SelfCorrectionConfig {
+        SelfCorrectionConfig {
+            test_command: command.to_string(),
+            max_rounds: 2,
+            timeout_ms: Some(5_000),
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_run_tests_reports_failure() {
+        let dir = std::env::temp_dir();
+
+        let passing = run_tests(&config("echo ok"), &dir, &[]).await.unwrap();
+        assert!(passing.passed);
+        assert_eq!(passing.output.trim(), "ok");
+
+        let failing = run_tests(&config("echo 'assertion failed' >&2; exit 3"), &dir, &[]).await.unwrap();
+        assert!(!failing.passed);
+        assert_eq!(failing.exit_code, Some(3));
+        assert!(failing.output.contains("assertion failed"));
+
+        let prompt = correction_prompt("Add a parser", "make test", &failing, 1, 2);
+        assert!(prompt.starts_with("Add a parser"));
+        assert!(prompt.contains("correction round 1 of 2"));
+        assert!(prompt.contains("`make test` exited with code 3"));
+    }
+
+    #[test]
+    fn test_tail_keeps_end() {
+        assert_eq!(tail("short", 10), "short");
+
+        let cut = tail("ééééabcd", 4);
+        assert!(cut.ends_with("\nabcd"));
+        assert!(cut.starts_with("[... 4 characters omitted ...]"));
+    }
+}
@@ -0,0 +1,288 @@
+//! Shareable bundles of finished executions.
+//!
+//! A bundle holds everything needed to look at a run without NEXUS: the
+//! workflow graph, the per-node records, the timeline and every output.
+//! Provides:
+//! - Building a bundle from a history record, with configured secrets and
+//!   credentials or personal data redacted
+//! - A JSON rendering, for tools
+//! - A single HTML file that shows the run read-only, with the bundle
+//!   embedded as JSON, for people
+//!
+//! The HTML needs no scripts or network access, so it opens the same from
+//! a mail attachment, a chat upload or a file share.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::settings::secrets;
+
+use super::dataset::Redactor;
+use super::history::ExecutionRecord;
+use super::report::{escape_html, format_ms};
+
+/// Version of the bundle layout, bumped when fields change meaning
+pub const SHARE_FORMAT_VERSION: u32 = 1;
+
+/// Rendering of a bundle
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShareFormat {
+    #[default]
+    Html,
+    Json,
+}
+
+impl ShareFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ShareFormat::Html => "html",
+            ShareFormat::Json => "json",
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ShareFormat::Html => "text/html",
+            ShareFormat::Json => "application/json",
+        }
+    }
+}
+
+/// A redacted, self-contained copy of an execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShareBundle {
+    pub format_version: u32,
+    pub exported_at: DateTime<Utc>,
+    pub execution: ExecutionRecord,
+    /// The workflow's graph as the editor saved it, if the workflow still exists
+    pub graph: Option<Value>,
+    /// Credentials and personal data replaced, besides configured secrets
+    pub redactions: usize,
+}
+
+impl ShareBundle {
+    /// Bundle `record` and its workflow's `graph`, redacting both
+    pub fn build(record: &ExecutionRecord, graph: Option<&Value>, redactor: &Redactor) -> Self {
+        let mut redactions = 0;
+        let mut execution = serde_json::to_value(secrets::redacted(record)).unwrap_or_default();
+        redactions += redact_json(&mut execution, redactor);
+        let execution = serde_json::from_value(execution).unwrap_or_else(|_| secrets::redacted(record));
+        let graph = graph.map(|graph| {
+            let mut graph = secrets::redacted(graph);
+            redactions += redact_json(&mut graph, redactor);
+            graph
+        });
+
+        Self {
+            format_version: SHARE_FORMAT_VERSION,
+            exported_at: Utc::now(),
+            execution,
+            graph,
+            redactions,
+        }
+    }
+
+    /// `share-<execution_id>.<ext>`
+    pub fn file_name(&self, format: ShareFormat) -> String {
+        format!("share-{}.{}", self.execution.id, format.extension())
+    }
+
+    pub fn render(&self, format: ShareFormat) -> String {
+        match format {
+            ShareFormat::Html => self.to_html(),
+            ShareFormat::Json => self.to_json(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn to_html(&self) -> String {
+        let record = &self.execution;
+        let title = if record.workflow_name.is_empty() {
+            format!("Execution {}", record.id)
+        } else {
+            record.workflow_name.clone()
+        };
+
+        let mut html = format!(
+            "<!DOCTYPE html>\n<html><head><meta charset=\"utf-8\"><title>{title}</title>\
+             <style>body{{font-family:sans-serif;max-width:960px;margin:2em auto}}\
+             pre{{background:#f4f4f4;padding:1em;overflow:auto;white-space:pre-wrap}}\
+             table{{border-collapse:collapse}}td,th{{border:1px solid #ccc;padding:4px 8px;text-align:left}}\
+             summary{{cursor:pointer;font-weight:bold;margin:.5em 0}}\
+             .failed{{color:#b00}}.completed{{color:#080}}.note{{color:#666}}</style></head><body>\n\
+             <h1>{title}</h1>\n",
+            title = escape_html(&title)
+        );
+
+        html.push_str(&format!(
+            "<p><b>Project:</b> {}<br><b>Status:</b> {}<br><b>Started:</b> {}<br><b>Duration:</b> {}<br>\
+             <b>Nodes:</b> {} completed, {} failed, {} skipped of {}</p>\n",
+            escape_html(&record.project_name),
+            label(&record.status),
+            record.started_at.to_rfc3339(),
+            format_ms(record.duration_ms),
+            record.completed_nodes,
+            record.failed_nodes,
+            record.skipped_nodes,
+            record.total_nodes
+        ));
+        html.push_str(&format!(
+            "<p class=\"note\">Shared read-only from NEXUS on {}. Secrets, credentials and email addresses \
+             are redacted.</p>\n",
+            self.exported_at.to_rfc3339()
+        ));
+        html.push_str(&format!("<h2>Input</h2>\n<pre>{}</pre>\n", escape_html(&record.input_prompt)));
+
+        let dependencies = self.dependencies();
+        html.push_str(
+            "<h2>Graph</h2>\n<table>\n<tr><th>Node</th><th>Role</th><th>Status</th><th>Duration</th><th>After</th></tr>\n",
+        );
+        for node in &record.node_records {
+            let status = label(&node.status);
+            html.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td class=\"{}\">{}</td><td>{}</td><td>{}</td></tr>\n",
+                escape_html(&node.node_name),
+                escape_html(&node.agent_role),
+                status,
+                status,
+                format_ms(node.duration_ms),
+                escape_html(&dependencies.get(node.node_id.as_str()).map(|d| d.join(", ")).unwrap_or_default())
+            ));
+        }
+        html.push_str("</table>\n");
+
+        if !record.timeline.is_empty() {
+            html.push_str("<h2>Timeline</h2>\n<table>\n<tr><th>Time</th><th>Node</th><th>Event</th><th>Message</th></tr>\n");
+            for event in &record.timeline {
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    event.timestamp.format("%H:%M:%S%.3f"),
+                    escape_html(event.node_id.as_deref().unwrap_or("")),
+                    label(&event.event_type),
+                    escape_html(&event.message)
+                ));
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("<h2>Outputs</h2>\n");
+        for node in &record.node_records {
+            let outputs = record.outputs.get(&node.node_id).map(Vec::as_slice).unwrap_or_default();
+            if outputs.is_empty() && node.error.is_none() {
+                continue;
+            }
+            html.push_str(&format!(
+                "<details open><summary>{} ({})</summary>\n",
+                escape_html(&node.node_name),
+                escape_html(&node.agent_role)
+            ));
+            if let Some(error) = &node.error {
+                html.push_str(&format!("<p class=\"failed\"><b>Error:</b> {}</p>\n", escape_html(error)));
+            }
+            for output in outputs {
+                html.push_str(&format!("<pre>{}</pre>\n", escape_html(&output.data.to_context_string())));
+            }
+            html.push_str("</details>\n");
+        }
+
+        // `<` escaped so no output can close the script element
+        let data = serde_json::to_string(self).unwrap_or_default().replace('<', "\\u003c");
+        html.push_str(&format!(
+            "<script type=\"application/json\" id=\"nexus-share\">{}</script>\n</body></html>\n",
+            data
+        ));
+        html
+    }
+
+    /// Sources of the graph's edges, by target
+    fn dependencies(&self) -> HashMap<&str, Vec<&str>> {
+        let mut dependencies: HashMap<&str, Vec<&str>> = HashMap::new();
+        let edges = self.graph.as_ref().and_then(|graph| graph.get("edges")).and_then(Value::as_array);
+        for edge in edges.into_iter().flatten() {
+            if let (Some(source), Some(target)) = (edge["source"].as_str(), edge["target"].as_str()) {
+                dependencies.entry(target).or_default().push(source);
+            }
+        }
+        dependencies
+    }
+}
+
+/// Redact every string in `json`; returns the number of replacements
+fn redact_json(json: &mut Value, redactor: &Redactor) -> usize {
+    match json {
+        Value::String(text) => {
+            let (redacted, count) = redactor.redact(text);
+            if count > 0 {
+                *text = redacted;
+            }
+            count
+        }
+        Value::Array(items) => items.iter_mut().map(|item| redact_json(item, redactor)).sum(),
+        Value::Object(map) => map.values_mut().map(|item| redact_json(item, redactor)).sum(),
+        _ => 0,
+    }
+}
+
+/// How a unit enum variant serializes, e.g. `waiting_for_approval`
+fn label<T: Serialize>(value: &T) -> String {
+    serde_json::to_value(value)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::context::{AgentOutput, OutputData};
+    use crate::workflow::history::ExecutionRecordBuilder;
+    use crate::workflow::state::ExecutionStatus;
+    use serde_json::json;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_redacted_bundle() {
+        let mut builder = ExecutionRecordBuilder::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "shop".to_string(),
+            "Deploy, mail ops@example.com when done".to_string(),
+        );
+        builder.add_output(
+            "deploy".to_string(),
+            AgentOutput {
+                agent_id: Uuid::nil(),
+                node_id: "deploy".to_string(),
+                agent_role: "devops".to_string(),
+                data: OutputData::Text("Used api_key=abcd1234 </script><b>done</b>".to_string()),
+                timestamp: Utc::now(),
+                tags: Vec::new(),
+            },
+        );
+        let record = builder.build(ExecutionStatus::Completed, Utc::now());
+        let graph = json!({"nodes": [], "edges": [{"id": "e1", "source": "build", "target": "deploy"}]});
+
+        let bundle = ShareBundle::build(&record, Some(&graph), &Redactor::new(&[]).unwrap());
+        assert_eq!(bundle.redactions, 2);
+        assert_eq!(bundle.execution.input_prompt, "Deploy, mail [REDACTED:email] when done");
+        assert_eq!(bundle.dependencies()["deploy"], vec!["build"]);
+
+        let html = bundle.to_html();
+        assert!(!html.contains("abcd1234") && !html.contains("ops@example.com"));
+        // One closing tag: the embedded data's own
+        assert_eq!(html.matches("</script>").count(), 1);
+        let start = html.find("id=\"nexus-share\">").unwrap() + "id=\"nexus-share\">".len();
+        let end = html.rfind("</script>").unwrap();
+        let embedded: ShareBundle = serde_json::from_str(&html[start..end]).unwrap();
+        assert_eq!(embedded.execution.id, record.id);
+
+        let json: ShareBundle = serde_json::from_str(&bundle.to_json()).unwrap();
+        assert_eq!(json.format_version, SHARE_FORMAT_VERSION);
+    }
+}
@@ -0,0 +1,251 @@
+//! What-if evaluation of a workflow's conditions.
+//!
+//! Branching mistakes otherwise only show up after agents have run.
+//! Provides:
+//! - Deciding every node and stage level by level the way the enhanced
+//!   executor does, against hypothetical node outcomes, variables and outputs
+//! - Each node's decision with its condition trace
+//!
+//! Nothing is spawned; a node that would run is assumed to finish with the
+//! status the scenario gives it, or to complete.
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+use super::conditions::{ConditionResult, ExecutionCondition};
+use super::context::{AgentOutput, ExecutionContext, OutputData};
+use super::graph::{GraphError, WorkflowGraph};
+use super::stages::{StageConfig, StageGate, StageProgress};
+use super::state::NodeExecutionStatus;
+
+/// Hypothetical state to evaluate conditions against
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    /// How nodes finish if they run; unlisted nodes complete
+    #[serde(default)]
+    pub node_statuses: HashMap<String, NodeExecutionStatus>,
+    #[serde(default)]
+    pub variables: HashMap<String, serde_json::Value>,
+    /// Text output of nodes that run, for output conditions
+    #[serde(default)]
+    pub outputs: HashMap<String, String>,
+}
+
+/// What would happen to one node
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SimulatedNode {
+    pub node_id: String,
+    pub level: usize,
+    /// Stage the node belongs to
+    pub stage: Option<String>,
+    pub would_run: bool,
+    pub reason: String,
+    /// Predecessors whose failure would skip the node
+    pub failed_dependencies: Vec<String>,
+    /// How the node's condition evaluated, or its stage's when the stage
+    /// was skipped; unset when a failed dependency skipped it
+    pub condition: Option<ConditionResult>,
+    /// Status later nodes see: the scenario's outcome if it runs
+    pub status: NodeExecutionStatus,
+}
+
+/// What would happen to every node, in execution order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConditionSimulation {
+    pub nodes: Vec<SimulatedNode>,
+    pub would_run: Vec<String>,
+    pub would_skip: Vec<String>,
+}
+
+/// Decide every node of `graph` under `scenario`. Nodes and stages without
+/// a condition always run unless a dependency failed.
+pub fn simulate(
+    graph: &WorkflowGraph,
+    conditions: &HashMap<String, ExecutionCondition>,
+    stages: &HashMap<String, StageConfig>,
+    scenario: &Scenario,
+) -> Result<ConditionSimulation, GraphError> {
+    let levels = graph.compute_execution_levels()?;
+    let context = ExecutionContext::new(Uuid::nil(), Uuid::nil(), String::new());
+    for (name, value) in &scenario.variables {
+        context.set_variable(name, value.clone());
+    }
+
+    let mut statuses: HashMap<String, NodeExecutionStatus> = HashMap::new();
+    let mut failed: HashSet<String> = HashSet::new();
+    let mut stage_progress = StageProgress::default();
+    let mut nodes = Vec::new();
+
+    for (level, node_ids) in levels.iter().enumerate() {
+        // Conditions only see nodes of earlier levels, as in the executor
+        let mut decided = Vec::new();
+
+        for node_id in node_ids {
+            let deps = graph.dependencies(node_id);
+            let mut failed_dependencies: Vec<String> = deps.iter().filter(|dep| failed.contains(*dep)).cloned().collect();
+
+            // A skipped stage skips its members before anything else is checked
+            let gate = stage_progress.gate(graph, node_id, stages, &context, &statuses);
+
+            let (would_run, reason, condition) = if let StageGate::Closed { stage_id, result, .. } = gate {
+                failed_dependencies.clear();
+                (false, StageGate::skip_reason(&stage_id, &result), Some(result))
+            } else if !failed_dependencies.is_empty() {
+                (false, "Dependency failed".to_string(), None)
+            } else {
+                let condition = conditions.get(node_id).unwrap_or(&ExecutionCondition::Always);
+                let result = condition.evaluate(&context, &statuses, deps);
+                (result.should_execute, result.reason.clone(), Some(result))
+            };
+
+            let status = if would_run {
+                scenario
+                    .node_statuses
+                    .get(node_id)
+                    .copied()
+                    .unwrap_or(NodeExecutionStatus::Completed)
+            } else {
+                NodeExecutionStatus::Skipped
+            };
+
+            nodes.push(SimulatedNode {
+                node_id: node_id.clone(),
+                level,
+                stage: graph.stage_of(node_id).map(|stage| stage.id.clone()),
+                would_run,
+                reason,
+                failed_dependencies,
+                condition,
+                status,
+            });
+            decided.push((node_id.clone(), status));
+        }
+
+        for (node_id, status) in decided {
+            if status == NodeExecutionStatus::Failed {
+                failed.insert(node_id.clone());
+            }
+            if status != NodeExecutionStatus::Skipped {
+                if let Some(text) = scenario.outputs.get(&node_id) {
+                    let role = graph.get_node(&node_id).map(|n| n.agent_role.clone()).unwrap_or_default();
+                    context.store_output(AgentOutput {
+                        agent_id: Uuid::nil(),
+                        node_id: node_id.clone(),
+                        agent_role: role,
+                        data: OutputData::Text(text.clone()),
+                        timestamp: Utc::now(),
+                        tags: Vec::new(),
+                    });
+                }
+            }
+            statuses.insert(node_id, status);
+        }
+    }
+
+    let (run, skip): (Vec<&SimulatedNode>, Vec<&SimulatedNode>) = nodes.iter().partition(|n| n.would_run);
+    Ok(ConditionSimulation {
+        would_run: run.iter().map(|n| n.node_id.clone()).collect(),
+        would_skip: skip.iter().map(|n| n.node_id.clone()).collect(),
+        nodes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_simulate_branches() {
+        // build -> (deploy | rollback) -> notify, plus docs gated on a variable
+        let graph = WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "build", "data": {"label": "Build", "agentRole": "implementer"}},
+                {"id": "deploy", "data": {"label": "Deploy", "agentRole": "implementer"}},
+                {"id": "rollback", "data": {"label": "Rollback", "agentRole": "implementer"}},
+                {"id": "notify", "data": {"label": "Notify", "agentRole": "documenter"}},
+                {"id": "docs", "data": {"label": "Docs", "agentRole": "documenter"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "build", "target": "deploy"},
+                {"id": "e2", "source": "build", "target": "rollback"},
+                {"id": "e3", "source": "deploy", "target": "notify"},
+                {"id": "e4", "source": "build", "target": "docs"}
+            ]
+        }))
+        .unwrap();
+
+        let conditions = HashMap::from([
+            ("deploy".to_string(), ExecutionCondition::OnSuccess { predecessor_id: "build".to_string() }),
+            ("rollback".to_string(), ExecutionCondition::OnFailure { predecessor_id: "build".to_string() }),
+            ("docs".to_string(), ExecutionCondition::VariableTruthy { variable: "write_docs".to_string() }),
+        ]);
+
+        let happy = simulate(&graph, &conditions, &HashMap::new(), &Scenario::default()).unwrap();
+        assert_eq!(happy.would_skip, vec!["rollback".to_string(), "docs".to_string()]);
+        assert!(happy.would_run.contains(&"notify".to_string()));
+
+        let deploy_fails = Scenario {
+            node_statuses: HashMap::from([("deploy".to_string(), NodeExecutionStatus::Failed)]),
+            variables: HashMap::from([("write_docs".to_string(), json!(true))]),
+            outputs: HashMap::new(),
+        };
+        let result = simulate(&graph, &conditions, &HashMap::new(), &deploy_fails).unwrap();
+        assert_eq!(result.would_run, vec!["build".to_string(), "deploy".to_string(), "docs".to_string()]);
+        let notify = result.nodes.iter().find(|n| n.node_id == "notify").unwrap();
+        assert_eq!(notify.failed_dependencies, vec!["deploy".to_string()]);
+
+        // A failed dependency skips before any condition is checked, even
+        // an on_failure one
+        let build_fails = Scenario {
+            node_statuses: HashMap::from([("build".to_string(), NodeExecutionStatus::Failed)]),
+            ..Scenario::default()
+        };
+        let result = simulate(&graph, &conditions, &HashMap::new(), &build_fails).unwrap();
+        // notify only depends on the skipped deploy, which doesn't stop it
+        assert_eq!(result.would_run, vec!["build".to_string(), "notify".to_string()]);
+        let rollback = result.nodes.iter().find(|n| n.node_id == "rollback").unwrap();
+        assert_eq!(rollback.failed_dependencies, vec!["build".to_string()]);
+        assert!(rollback.condition.is_none());
+        assert_eq!(rollback.status, NodeExecutionStatus::Skipped);
+    }
+
+    #[test]
+    fn test_simulate_skipped_stage() {
+        let graph = WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "build", "data": {"label": "Build", "agentRole": "implementer"}},
+                {"id": "unit", "data": {"label": "Unit tests", "agentRole": "tester"}},
+                {"id": "e2e", "data": {"label": "E2E tests", "agentRole": "tester"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "build", "target": "unit"},
+                {"id": "e2", "source": "build", "target": "e2e"}
+            ],
+            "stages": [{"id": "tests", "nodes": ["unit", "e2e"]}]
+        }))
+        .unwrap();
+        let stages = HashMap::from([(
+            "tests".to_string(),
+            StageConfig {
+                condition: ExecutionCondition::VariableTruthy { variable: "run_tests".to_string() },
+                aggregation: None,
+            },
+        )]);
+
+        let result = simulate(&graph, &HashMap::new(), &stages, &Scenario::default()).unwrap();
+        assert_eq!(result.would_run, vec!["build".to_string()]);
+        let unit = result.nodes.iter().find(|n| n.node_id == "unit").unwrap();
+        assert_eq!(unit.stage.as_deref(), Some("tests"));
+        assert!(unit.reason.starts_with("Stage 'tests' skipped"));
+
+        let scenario = Scenario {
+            variables: HashMap::from([("run_tests".to_string(), json!(true))]),
+            ..Scenario::default()
+        };
+        let result = simulate(&graph, &HashMap::new(), &stages, &scenario).unwrap();
+        assert!(result.would_skip.is_empty());
+    }
+}
@@ -0,0 +1,275 @@
+//! Service-level rules on saved workflows.
+//!
+//! Rules are checked against the execution history. Provides:
+//! - Maximum run duration, maximum failure rate over recent runs and
+//!   must-run-daily rules
+//! - The status of every rule of a workflow
+//! - Alerts when a rule starts failing, raised once until it passes again,
+//!   and a background monitor that delivers them
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+use uuid::Uuid;
+
+use crate::integrations::{send_email, EmailMessage, SmtpConfig};
+use crate::settings::SETTINGS;
+
+use super::history::{ExecutionHistoryStore, ExecutionRecord};
+use super::state::ExecutionStatus;
+
+/// Event the UI receives for each alert
+pub const SLA_ALERT_EVENT: &str = "sla-alert";
+
+/// How often the monitor checks every workflow's rules
+const CHECK_INTERVAL_SECS: u64 = 60;
+
+/// A rule a workflow's runs must keep
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SlaRule {
+    /// The latest finished run took at most this long
+    MaxDuration { max_ms: u64 },
+    /// At most this share (0.0-1.0) of the last `runs` runs failed
+    MaxFailureRate { max_rate: f64, runs: usize },
+    /// A run started within the last 24 hours
+    MustRunDaily,
+}
+
+impl SlaRule {
+    /// Check the rule against a workflow's runs, newest first. `since` is
+    /// when the rules were set, so a new daily rule isn't failing at once.
+    pub fn evaluate(&self, runs: &[ExecutionRecord], since: DateTime<Utc>, now: DateTime<Utc>) -> RuleStatus {
+        let (passing, message) = match self {
+            SlaRule::MaxDuration { max_ms } => match runs.iter().find_map(|run| run.duration_ms) {
+                Some(duration_ms) if duration_ms > *max_ms => {
+                    (false, format!("Last run took {} ms, over the {} ms limit", duration_ms, max_ms))
+                }
+                Some(duration_ms) => (true, format!("Last run took {} ms", duration_ms)),
+                None => (true, "No finished runs yet".to_string()),
+            },
+            SlaRule::MaxFailureRate { max_rate, runs: window } => {
+                let recent: Vec<&ExecutionRecord> = runs.iter().take(*window).collect();
+                if recent.is_empty() {
+                    (true, "No runs yet".to_string())
+                } else {
+                    let failed = recent.iter().filter(|run| run.status == ExecutionStatus::Failed).count();
+                    let rate = failed as f64 / recent.len() as f64;
+                    let message = format!("{} of the last {} run(s) failed", failed, recent.len());
+                    (rate <= *max_rate, message)
+                }
+            }
+            SlaRule::MustRunDaily => {
+                let last_run = runs.iter().map(|run| run.started_at).max();
+                let overdue = now - last_run.unwrap_or(since).max(since) > Duration::hours(24);
+                let message = match last_run {
+                    Some(started_at) => format!("Last run started {}", started_at.to_rfc3339()),
+                    None => "Never run".to_string(),
+                };
+                (!overdue, message)
+            }
+        };
+
+        RuleStatus {
+            rule: self.clone(),
+            passing,
+            message,
+        }
+    }
+}
+
+/// How one rule stands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleStatus {
+    pub rule: SlaRule,
+    pub passing: bool,
+    pub message: String,
+}
+
+/// How every rule of a workflow stands
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlaStatus {
+    pub workflow_id: Uuid,
+    pub passing: bool,
+    pub rules: Vec<RuleStatus>,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// A rule that started failing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlaAlert {
+    pub workflow_id: Uuid,
+    pub rule: SlaRule,
+    pub message: String,
+    pub raised_at: DateTime<Utc>,
+}
+
+struct WorkflowRules {
+    rules: Vec<SlaRule>,
+    set_at: DateTime<Utc>,
+}
+
+/// The rules of every workflow, and which of them are failing
+#[derive(Default)]
+pub struct SlaStore {
+    rules: DashMap<Uuid, WorkflowRules>,
+    /// (workflow, rule index) pairs already alerted on
+    failing: DashSet<(Uuid, usize)>,
+}
+
+impl SlaStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace a workflow's rules; an empty list removes them
+    pub fn set_rules(&self, workflow_id: Uuid, rules: Vec<SlaRule>) {
+        self.failing.retain(|(id, _)| *id != workflow_id);
+        if rules.is_empty() {
+            self.rules.remove(&workflow_id);
+        } else {
+            self.rules.insert(workflow_id, WorkflowRules { rules, set_at: Utc::now() });
+        }
+    }
+
+    pub fn get_rules(&self, workflow_id: &Uuid) -> Vec<SlaRule> {
+        self.rules.get(workflow_id).map(|entry| entry.rules.clone()).unwrap_or_default()
+    }
+
+    /// Check a workflow's rules against its runs in `history`
+    pub fn status(&self, workflow_id: Uuid, history: &ExecutionHistoryStore, now: DateTime<Utc>) -> SlaStatus {
+        let rules: Vec<RuleStatus> = match self.rules.get(&workflow_id) {
+            Some(entry) => {
+                let runs = history.list_for_workflow(&workflow_id);
+                entry.rules.iter().map(|rule| rule.evaluate(&runs, entry.set_at, now)).collect()
+            }
+            None => Vec::new(),
+        };
+
+        SlaStatus {
+            workflow_id,
+            passing: rules.iter().all(|rule| rule.passing),
+            rules,
+            checked_at: now,
+        }
+    }
+
+    /// Check every workflow, returning alerts for rules that started
+    /// failing since the last check
+    pub fn check_all(&self, history: &ExecutionHistoryStore, now: DateTime<Utc>) -> Vec<SlaAlert> {
+        let workflow_ids: Vec<Uuid> = self.rules.iter().map(|entry| *entry.key()).collect();
+        let mut alerts = Vec::new();
+
+        for workflow_id in workflow_ids {
+            for (index, status) in self.status(workflow_id, history, now).rules.into_iter().enumerate() {
+                if status.passing {
+                    self.failing.remove(&(workflow_id, index));
+                } else if self.failing.insert((workflow_id, index)) {
+                    alerts.push(SlaAlert {
+                        workflow_id,
+                        rule: status.rule,
+                        message: status.message,
+                        raised_at: now,
+                    });
+                }
+            }
+        }
+
+        alerts
+    }
+}
+
+lazy_static::lazy_static! {
+    pub static ref SLA_STORE: SlaStore = SlaStore::new();
+}
+
+/// Check rules every minute, delivering alerts to the UI and, when
+/// recipients are configured, by email
+pub fn spawn_monitor(app: AppHandle, history: &'static ExecutionHistoryStore) {
+    tauri::async_runtime::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(CHECK_INTERVAL_SECS));
+        loop {
+            interval.tick().await;
+            for alert in SLA_STORE.check_all(history, Utc::now()) {
+                deliver(&app, &alert).await;
+            }
+        }
+    });
+}
+
+async fn deliver(app: &AppHandle, alert: &SlaAlert) {
+    log::warn!("SLA rule failing for workflow {}: {}", alert.workflow_id, alert.message);
+    let notifications = SETTINGS.get().notifications;
+
+    if notifications.desktop {
+        let _ = app.emit(SLA_ALERT_EVENT, alert);
+    }
+
+    if !notifications.email_recipients.is_empty() {
+        let message = EmailMessage {
+            to: notifications.email_recipients,
+            subject: format!("Nexus SLA alert for workflow {}", alert.workflow_id),
+            body: format!("Rule: {:?}\n\n{}\n", alert.rule, alert.message),
+            attachment: None,
+        };
+        let sent = match SmtpConfig::from_env() {
+            Ok(smtp) => send_email(&smtp, &message).await.map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        if let Err(e) = sent {
+            log::warn!("Failed to email SLA alert: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::history::ExecutionRecordBuilder;
+
+    fn run(workflow_id: Uuid, status: ExecutionStatus, started_at: DateTime<Utc>, duration_ms: i64) -> ExecutionRecord {
+        ExecutionRecordBuilder::new(Uuid::new_v4(), Uuid::new_v4(), "Project".to_string(), "Run".to_string())
+            .workflow(workflow_id, "Nightly".to_string())
+            .started_at(started_at)
+            .build(status, started_at + Duration::milliseconds(duration_ms))
+    }
+
+    #[test]
+    fn test_rules_and_alerts() {
+        let workflow_id = Uuid::new_v4();
+        let now = Utc::now();
+        let history = ExecutionHistoryStore::new(100);
+        history.add(run(workflow_id, ExecutionStatus::Failed, now - Duration::hours(30), 1_000));
+        history.add(run(workflow_id, ExecutionStatus::Completed, now - Duration::hours(26), 5_000));
+
+        let store = SlaStore::new();
+        store.set_rules(
+            workflow_id,
+            vec![
+                SlaRule::MaxDuration { max_ms: 2_000 },
+                SlaRule::MaxFailureRate { max_rate: 0.5, runs: 10 },
+                SlaRule::MustRunDaily,
+            ],
+        );
+
+        let status = store.status(workflow_id, &history, now);
+        let passing: Vec<bool> = status.rules.iter().map(|rule| rule.passing).collect();
+        // Rules set just now give the daily rule a day's grace
+        assert_eq!(passing, vec![false, true, true]);
+        assert!(!status.passing);
+
+        let later = now + Duration::hours(25);
+        let alerts = store.check_all(&history, later);
+        assert_eq!(alerts.len(), 2);
+        assert_eq!(alerts[1].rule, SlaRule::MustRunDaily);
+        // Still failing: no repeat
+        assert!(store.check_all(&history, later).is_empty());
+
+        history.add(run(workflow_id, ExecutionStatus::Failed, later, 500));
+        let alerts = store.check_all(&history, later);
+        assert_eq!(alerts.len(), 1);
+        assert!(matches!(alerts[0].rule, SlaRule::MaxFailureRate { .. }));
+        assert_eq!(alerts[0].message, "2 of the last 3 run(s) failed");
+    }
+}
@@ -0,0 +1,345 @@
+//! Stages: named groups of nodes that run and are monitored as one unit.
+//!
+//! A graph may declare stages in a top-level `stages` list. Provides:
+//! - A stage condition, evaluated once against the stage's predecessors
+//!   outside it; members are scheduled after all of those, so they have
+//!   finished by then. When it isn't met every member is skipped
+//! - The status a stage shows when collapsed into one node
+//! - Aggregation of the members' outputs into one output stored under the
+//!   stage ID, where conditions and the execution context can find it
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Instant;
+use uuid::Uuid;
+
+use super::aggregation::NodeAggregationConfig;
+use super::conditions::{ConditionResult, ExecutionCondition};
+use super::context::{AgentOutput, ExecutionContext};
+use super::graph::{ParsedStage, WorkflowGraph};
+use super::state::NodeExecutionStatus;
+
+/// Agent role recorded on a stage's aggregated output
+pub const STAGE_OUTPUT_ROLE: &str = "stage";
+
+/// How a stage runs
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StageConfig {
+    /// Must hold for any member to run
+    #[serde(default)]
+    pub condition: ExecutionCondition,
+    /// Combine the members' outputs when the stage finishes
+    pub aggregation: Option<NodeAggregationConfig>,
+}
+
+/// The status of a stage shown as a single node: failed if any member
+/// failed, skipped if all were, completed once all are done, running once
+/// any has started
+pub fn collapse_status<I>(statuses: I) -> NodeExecutionStatus
+where
+    I: IntoIterator<Item = NodeExecutionStatus>,
+{
+    let statuses: Vec<NodeExecutionStatus> = statuses.into_iter().collect();
+    let all = |wanted: &[NodeExecutionStatus]| statuses.iter().all(|s| wanted.contains(s));
+
+    if statuses.contains(&NodeExecutionStatus::Failed) {
+        NodeExecutionStatus::Failed
+    } else if statuses.is_empty() || statuses.iter().all(|s| s.is_waiting()) {
+        NodeExecutionStatus::Pending
+    } else if all(&[NodeExecutionStatus::Skipped]) {
+        NodeExecutionStatus::Skipped
+    } else if all(&[NodeExecutionStatus::Completed, NodeExecutionStatus::Skipped]) {
+        NodeExecutionStatus::Completed
+    } else {
+        NodeExecutionStatus::Running
+    }
+}
+
+/// What a node's stage means for it
+#[derive(Debug, Clone)]
+pub enum StageGate {
+    /// Not in a stage, or in one that is already running
+    Open,
+    /// The first member reached of a stage whose condition was met
+    Entered { stage_id: String, result: ConditionResult },
+    /// The stage's condition wasn't met; `first` is set for the member
+    /// that reached it
+    Closed { stage_id: String, result: ConditionResult, first: bool },
+}
+
+impl StageGate {
+    /// Reason recorded on a member skipped by its stage
+    pub fn skip_reason(stage_id: &str, result: &ConditionResult) -> String {
+        format!("Stage '{}' skipped: {}", stage_id, result.reason)
+    }
+}
+
+/// A stage whose members have all finished
+#[derive(Debug, Clone)]
+pub struct FinishedStage {
+    pub stage_id: String,
+    pub status: NodeExecutionStatus,
+    pub duration_ms: u64,
+}
+
+/// Which stages of an execution have been entered, skipped or finished
+#[derive(Debug, Default)]
+pub struct StageProgress {
+    started: HashMap<String, Instant>,
+    closed: HashMap<String, ConditionResult>,
+    finished: HashSet<String>,
+}
+
+impl StageProgress {
+    /// Decide `node_id`'s stage the first time one of its members is
+    /// reached, which execution levels put after every dependency of the
+    /// stage; later members get the same answer
+    pub fn gate(
+        &mut self,
+        graph: &WorkflowGraph,
+        node_id: &str,
+        configs: &HashMap<String, StageConfig>,
+        context: &ExecutionContext,
+        statuses: &HashMap<String, NodeExecutionStatus>,
+    ) -> StageGate {
+        let Some(stage) = graph.stage_of(node_id) else {
+            return StageGate::Open;
+        };
+        if let Some(result) = self.closed.get(&stage.id) {
+            return StageGate::Closed { stage_id: stage.id.clone(), result: result.clone(), first: false };
+        }
+        if self.started.contains_key(&stage.id) {
+            return StageGate::Open;
+        }
+
+        let condition = configs.get(&stage.id).map(|c| &c.condition).unwrap_or(&ExecutionCondition::Always);
+        let result = condition.evaluate(context, statuses, &graph.stage_dependencies(stage));
+        if result.should_execute {
+            self.started.insert(stage.id.clone(), Instant::now());
+            StageGate::Entered { stage_id: stage.id.clone(), result }
+        } else {
+            self.closed.insert(stage.id.clone(), result.clone());
+            StageGate::Closed { stage_id: stage.id.clone(), result, first: true }
+        }
+    }
+
+    /// Entered stages whose members have all reached a final status since
+    /// the last call
+    pub fn finish(&mut self, graph: &WorkflowGraph, statuses: &HashMap<String, NodeExecutionStatus>) -> Vec<FinishedStage> {
+        let mut finished = Vec::new();
+        for stage in &graph.stages {
+            let Some(started) = self.started.get(&stage.id) else {
+                continue;
+            };
+            if self.finished.contains(&stage.id) {
+                continue;
+            }
+            let members: Vec<NodeExecutionStatus> = stage
+                .nodes
+                .iter()
+                .map(|id| statuses.get(id).copied().unwrap_or(NodeExecutionStatus::Pending))
+                .collect();
+            if members.iter().any(|s| !s.is_finished()) {
+                continue;
+            }
+
+            self.finished.insert(stage.id.clone());
+            finished.push(FinishedStage {
+                stage_id: stage.id.clone(),
+                status: collapse_status(members),
+                duration_ms: started.elapsed().as_millis() as u64,
+            });
+        }
+        finished
+    }
+}
+
+/// Aggregate a finished stage's member outputs into one output under the
+/// stage ID. `None` when no member output matched.
+pub fn aggregate_stage(
+    context: &ExecutionContext,
+    stage: &ParsedStage,
+    aggregation: &NodeAggregationConfig,
+) -> Option<AgentOutput> {
+    let aggregated = aggregation.apply(context.get_predecessor_outputs(&stage.nodes))?;
+    Some(AgentOutput {
+        agent_id: Uuid::nil(),
+        node_id: stage.id.clone(),
+        agent_role: STAGE_OUTPUT_ROLE.to_string(),
+        data: aggregated.data,
+        timestamp: Utc::now(),
+        tags: Vec::new(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::workflow::aggregation::AggregationStrategy;
+    use crate::workflow::context::OutputData;
+    use crate::workflow::graph::GraphError;
+    use serde_json::json;
+
+    fn staged_graph() -> WorkflowGraph {
+        // plan -> [build: api, ui] -> [verify: test] -> ship
+        WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "plan", "data": {"label": "Plan", "agentRole": "architect"}},
+                {"id": "api", "data": {"label": "API", "agentRole": "implementer"}},
+                {"id": "ui", "data": {"label": "UI", "agentRole": "implementer"}},
+                {"id": "test", "data": {"label": "Test", "agentRole": "tester"}},
+                {"id": "ship", "data": {"label": "Ship", "agentRole": "devops"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "plan", "target": "api"},
+                {"id": "e2", "source": "plan", "target": "ui"},
+                {"id": "e3", "source": "api", "target": "test"},
+                {"id": "e4", "source": "ui", "target": "test"},
+                {"id": "e5", "source": "test", "target": "ship"}
+            ],
+            "stages": [
+                {"id": "build", "label": "Build", "nodes": ["api", "ui"]},
+                {"id": "verify", "nodes": ["test"], "collapsed": true}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_collapse_status() {
+        use NodeExecutionStatus::*;
+        assert_eq!(collapse_status([Pending, Pending]), Pending);
+        assert_eq!(collapse_status([Queued, WaitingForApproval, Pending]), Pending);
+        assert_eq!(collapse_status([Completed, Queued]), Running);
+        assert_eq!(collapse_status([Completed, Pending]), Running);
+        assert_eq!(collapse_status([Completed, Skipped]), Completed);
+        assert_eq!(collapse_status([Skipped, Skipped]), Skipped);
+        assert_eq!(collapse_status([Completed, Failed, Running]), Failed);
+    }
+
+    #[test]
+    fn test_stage_gates_and_finishes() {
+        let graph = staged_graph();
+        assert_eq!(graph.stage_dependencies(graph.get_stage("build").unwrap()), vec!["plan"]);
+        assert_eq!(graph.get_stage("verify").unwrap().label, "verify");
+
+        let context = ExecutionContext::new(Uuid::nil(), Uuid::nil(), String::new());
+        let configs = HashMap::from([(
+            "verify".to_string(),
+            StageConfig {
+                condition: ExecutionCondition::VariableTruthy { variable: "run_tests".to_string() },
+                aggregation: None,
+            },
+        )]);
+        let mut progress = StageProgress::default();
+        let mut statuses = HashMap::from([("plan".to_string(), NodeExecutionStatus::Completed)]);
+
+        assert!(matches!(progress.gate(&graph, "plan", &configs, &context, &statuses), StageGate::Open));
+        assert!(matches!(progress.gate(&graph, "api", &configs, &context, &statuses), StageGate::Entered { .. }));
+        assert!(matches!(progress.gate(&graph, "ui", &configs, &context, &statuses), StageGate::Open));
+
+        statuses.insert("api".to_string(), NodeExecutionStatus::Completed);
+        assert!(progress.finish(&graph, &statuses).is_empty());
+        statuses.insert("ui".to_string(), NodeExecutionStatus::Completed);
+        let finished = progress.finish(&graph, &statuses);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].stage_id, "build");
+        assert_eq!(finished[0].status, NodeExecutionStatus::Completed);
+        assert!(progress.finish(&graph, &statuses).is_empty());
+
+        match progress.gate(&graph, "test", &configs, &context, &statuses) {
+            StageGate::Closed { stage_id, result, first } => {
+                assert_eq!(stage_id, "verify");
+                assert!(first);
+                assert_eq!(StageGate::skip_reason(&stage_id, &result), format!("Stage 'verify' skipped: {}", result.reason));
+            }
+            other => panic!("expected the stage to be skipped, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_stage_waits_for_all_its_dependencies() {
+        // plan -> api; lint -> ui, with api and ui in one stage; review
+        // comes after api and feeds ui
+        let graph = WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "plan", "data": {"label": "Plan", "agentRole": "architect"}},
+                {"id": "lint", "data": {"label": "Lint", "agentRole": "tester"}},
+                {"id": "review", "data": {"label": "Review", "agentRole": "reviewer"}},
+                {"id": "api", "data": {"label": "API", "agentRole": "implementer"}},
+                {"id": "ui", "data": {"label": "UI", "agentRole": "implementer"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "plan", "target": "lint"},
+                {"id": "e2", "source": "plan", "target": "api"},
+                {"id": "e3", "source": "lint", "target": "ui"},
+                {"id": "e4", "source": "api", "target": "review"},
+                {"id": "e5", "source": "review", "target": "ui"}
+            ],
+            "stages": [{"id": "build", "nodes": ["api", "ui"]}]
+        }))
+        .unwrap();
+        assert_eq!(graph.stage_dependencies(graph.get_stage("build").unwrap()), vec!["plan", "lint"]);
+
+        let levels = graph.compute_execution_levels().unwrap();
+        let level_of = |id: &str| levels.iter().position(|level| level.iter().any(|n| n == id)).unwrap();
+        assert!(level_of("api") > level_of("lint"));
+        assert!(level_of("ui") > level_of("review"));
+
+        // Two stages that each feed the other's members can't both wait
+        let crossed = WorkflowGraph::from_json(&json!({
+            "nodes": [
+                {"id": "a1", "data": {"label": "A1", "agentRole": "implementer"}},
+                {"id": "a2", "data": {"label": "A2", "agentRole": "implementer"}},
+                {"id": "b1", "data": {"label": "B1", "agentRole": "implementer"}},
+                {"id": "b2", "data": {"label": "B2", "agentRole": "implementer"}}
+            ],
+            "edges": [
+                {"id": "e1", "source": "a1", "target": "b2"},
+                {"id": "e2", "source": "b1", "target": "a2"}
+            ],
+            "stages": [{"id": "a", "nodes": ["a1", "a2"]}, {"id": "b", "nodes": ["b1", "b2"]}]
+        }))
+        .unwrap();
+        assert!(matches!(crossed.compute_execution_levels(), Err(GraphError::InvalidFormat(_))));
+    }
+
+    #[test]
+    fn test_aggregate_stage_and_invalid_stages() {
+        let graph = staged_graph();
+        let context = ExecutionContext::new(Uuid::nil(), Uuid::nil(), String::new());
+        for (node_id, text) in [("api", "endpoints"), ("ui", "screens")] {
+            context.store_output(AgentOutput {
+                agent_id: Uuid::nil(),
+                node_id: node_id.to_string(),
+                agent_role: "implementer".to_string(),
+                data: OutputData::Text(text.to_string()),
+                timestamp: Utc::now(),
+                tags: Vec::new(),
+            });
+        }
+
+        let aggregation = NodeAggregationConfig {
+            strategy: AggregationStrategy::Concatenate { separator: " + ".to_string(), include_source: false },
+            ..NodeAggregationConfig::default()
+        };
+        let output = aggregate_stage(&context, graph.get_stage("build").unwrap(), &aggregation).unwrap();
+        assert_eq!(output.node_id, "build");
+        let text = output.data.to_context_string();
+        assert!(text.contains("endpoints") && text.contains("screens"));
+
+        let overlapping = json!({
+            "nodes": [{"id": "a", "data": {"label": "A", "agentRole": "implementer"}}],
+            "edges": [],
+            "stages": [{"id": "one", "nodes": ["a"]}, {"id": "two", "nodes": ["a"]}]
+        });
+        assert!(WorkflowGraph::from_json(&overlapping).is_err());
+        let clashing = json!({
+            "nodes": [{"id": "a", "data": {"label": "A", "agentRole": "implementer"}}],
+            "edges": [],
+            "stages": [{"id": "a", "nodes": ["a"]}]
+        });
+        assert!(WorkflowGraph::from_json(&clashing).is_err());
+    }
+}
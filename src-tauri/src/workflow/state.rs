@@ -5,12 +5,27 @@ use std::sync::Arc;
 use tokio::sync::broadcast;
 use uuid::Uuid;
 
+use super::activity::ACTIVITY_STORE;
+use super::env_vars::EXECUTION_ENVS;
+use super::environment::EnvironmentFingerprint;
+use super::model_pins::ModelSubstitution;
+use super::execution_log::EXECUTION_LOG;
+use super::journal::EVENT_JOURNAL;
+use super::messaging::MESSAGE_BUS_STORE;
+use super::retry::RetryAttemptError;
+
 /// Status of a single node during execution
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
-#[serde(rename_all = "lowercase")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
+#[serde(rename_all = "snake_case")]
 pub enum NodeExecutionStatus {
     /// Waiting to be executed
+    #[default]
     Pending,
+    /// Waiting for a concurrency slot or resource lock
+    Queued,
+    /// Waiting for someone to approve or reject the node
+    WaitingForApproval,
     /// Currently running
     Running,
     /// Successfully completed
@@ -21,14 +36,21 @@ pub enum NodeExecutionStatus {
     Skipped,
 }
 
-impl Default for NodeExecutionStatus {
-    fn default() -> Self {
-        Self::Pending
+impl NodeExecutionStatus {
+    /// Completed, failed or skipped
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Skipped)
+    }
+
+    /// Not started yet, including nodes waiting for a slot or approval
+    pub fn is_waiting(self) -> bool {
+        matches!(self, Self::Pending | Self::Queued | Self::WaitingForApproval)
     }
 }
 
 /// Execution state for a single workflow node
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::SimpleObject))]
 pub struct NodeExecutionState {
     pub node_id: String,
     pub status: NodeExecutionStatus,
@@ -44,6 +66,19 @@ pub struct NodeExecutionState {
     pub output: Option<String>,
     /// Error message if failed
     pub error: Option<String>,
+    /// What the agent had produced when it failed or timed out
+    #[serde(default)]
+    pub partial_output: Option<String>,
+    /// When the node started waiting for a slot or lock
+    #[serde(default)]
+    pub queued_at: Option<DateTime<Utc>>,
+    /// How long it waited for one
+    #[serde(default)]
+    pub queue_wait_ms: Option<u64>,
+    /// Each failed attempt: the ones retried, then the final failure
+    #[serde(default)]
+    #[cfg_attr(feature = "graphql", graphql(skip))]
+    pub retry_attempts: Vec<RetryAttemptError>,
 }
 
 impl NodeExecutionState {
@@ -57,6 +92,27 @@ impl NodeExecutionState {
             completed_at: None,
             output: None,
             error: None,
+            partial_output: None,
+            queued_at: None,
+            queue_wait_ms: None,
+            retry_attempts: Vec::new(),
+        }
+    }
+
+    /// Wait for a concurrency slot or resource lock
+    pub fn queue(&mut self) {
+        self.status = NodeExecutionStatus::Queued;
+        self.queued_at = Some(Utc::now());
+    }
+
+    pub fn await_approval(&mut self) {
+        self.status = NodeExecutionStatus::WaitingForApproval;
+    }
+
+    /// Got its slots and locks; record how long that took
+    pub fn dequeue(&mut self) {
+        if let Some(queued_at) = self.queued_at {
+            self.queue_wait_ms = Some((Utc::now() - queued_at).num_milliseconds().max(0) as u64);
         }
     }
 
@@ -67,6 +123,13 @@ impl NodeExecutionState {
         self.progress = 0;
     }
 
+    /// Start a node that runs in-process rather than through an agent
+    pub fn start_inline(&mut self) {
+        self.status = NodeExecutionStatus::Running;
+        self.started_at = Some(Utc::now());
+        self.progress = 0;
+    }
+
     pub fn complete(&mut self, output: Option<String>) {
         self.status = NodeExecutionStatus::Completed;
         self.completed_at = Some(Utc::now());
@@ -86,25 +149,27 @@ impl NodeExecutionState {
         self.error = Some(reason);
     }
 
+    /// Attempts that were retried
+    pub fn retry_count(&self) -> u32 {
+        self.retry_attempts.iter().filter(|a| a.delay_before_next.is_some()).count() as u32
+    }
+
     pub fn update_progress(&mut self, progress: u8) {
         self.progress = progress.min(100);
     }
 
     pub fn is_terminal(&self) -> bool {
-        matches!(
-            self.status,
-            NodeExecutionStatus::Completed
-                | NodeExecutionStatus::Failed
-                | NodeExecutionStatus::Skipped
-        )
+        self.status.is_finished()
     }
 }
 
 /// Overall status of a workflow execution
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[cfg_attr(feature = "graphql", derive(async_graphql::Enum))]
 #[serde(rename_all = "lowercase")]
 pub enum ExecutionStatus {
     /// Waiting to start
+    #[default]
     Pending,
     /// Currently executing
     Running,
@@ -116,12 +181,6 @@ pub enum ExecutionStatus {
     Cancelled,
 }
 
-impl Default for ExecutionStatus {
-    fn default() -> Self {
-        Self::Pending
-    }
-}
-
 /// Complete state for a workflow execution
 pub struct WorkflowExecutionState {
     pub execution_id: Uuid,
@@ -140,6 +199,12 @@ pub struct WorkflowExecutionState {
     pub completed_at: parking_lot::RwLock<Option<DateTime<Utc>>>,
     /// Channel to signal cancellation
     pub cancel_tx: broadcast::Sender<()>,
+    /// Why the execution was cancelled, when it was cancelled for a reason
+    pub cancel_reason: parking_lot::RwLock<Option<String>>,
+    /// Captured once the execution starts running
+    pub environment: parking_lot::RwLock<Option<EnvironmentFingerprint>>,
+    /// Pinned models replaced because they were unavailable
+    pub model_substitutions: parking_lot::RwLock<Vec<ModelSubstitution>>,
 }
 
 impl WorkflowExecutionState {
@@ -171,6 +236,9 @@ impl WorkflowExecutionState {
             started_at: Utc::now(),
             completed_at: parking_lot::RwLock::new(None),
             cancel_tx,
+            cancel_reason: parking_lot::RwLock::new(None),
+            environment: parking_lot::RwLock::new(None),
+            model_substitutions: parking_lot::RwLock::new(Vec::new()),
         }
     }
 
@@ -178,8 +246,15 @@ impl WorkflowExecutionState {
         *self.status.read()
     }
 
+    /// Set the status. Cancelled is final: nodes that fail because they
+    /// were stopped don't turn a cancelled execution into a failed one.
     pub fn set_status(&self, status: ExecutionStatus) {
-        *self.status.write() = status;
+        let mut current = self.status.write();
+        if *current == ExecutionStatus::Cancelled {
+            return;
+        }
+        *current = status;
+        drop(current);
         if matches!(
             status,
             ExecutionStatus::Completed | ExecutionStatus::Failed | ExecutionStatus::Cancelled
@@ -210,6 +285,16 @@ impl WorkflowExecutionState {
         self.set_status(ExecutionStatus::Cancelled);
     }
 
+    /// Cancel and record why
+    pub fn cancel_with_reason(&self, reason: &str) {
+        *self.cancel_reason.write() = Some(reason.to_string());
+        self.cancel();
+    }
+
+    pub fn get_cancel_reason(&self) -> Option<String> {
+        self.cancel_reason.read().clone()
+    }
+
     pub fn total_nodes(&self) -> usize {
         self.node_states.len()
     }
@@ -245,22 +330,45 @@ impl WorkflowExecutionState {
     }
 }
 
+/// Finished executions an `ExecutionStore` keeps before evicting the oldest
+pub const MAX_FINISHED_EXECUTIONS: usize = 100;
+
+/// Drop what an execution left in the global stores: its log, event
+/// journal, message bus, activity feed and environment
+pub fn release_execution(execution_id: &Uuid) {
+    EXECUTION_LOG.remove(execution_id);
+    EXECUTION_ENVS.remove(execution_id);
+    EVENT_JOURNAL.remove(execution_id);
+    MESSAGE_BUS_STORE.purge(execution_id);
+    ACTIVITY_STORE.remove_execution(execution_id);
+}
+
 /// Global store for all workflow executions
 pub struct ExecutionStore {
     executions: DashMap<Uuid, Arc<WorkflowExecutionState>>,
+    max_finished: usize,
 }
 
 impl ExecutionStore {
     pub fn new() -> Self {
+        Self::with_max_finished(MAX_FINISHED_EXECUTIONS)
+    }
+
+    /// Store that keeps at most `max_finished` finished executions;
+    /// pending and running ones are never evicted
+    pub fn with_max_finished(max_finished: usize) -> Self {
         Self {
             executions: DashMap::new(),
+            max_finished,
         }
     }
 
+    /// Add an execution, evicting the oldest finished ones over the limit
     pub fn insert(&self, state: WorkflowExecutionState) -> Arc<WorkflowExecutionState> {
         let execution_id = state.execution_id;
         let arc_state = Arc::new(state);
         self.executions.insert(execution_id, arc_state.clone());
+        self.evict_finished();
         arc_state
     }
 
@@ -272,6 +380,66 @@ impl ExecutionStore {
         self.executions.remove(execution_id).map(|(_, v)| v)
     }
 
+    /// Drop executions that finished at or before `cutoff`, along with what
+    /// they left in the global stores. Returns the IDs dropped.
+    pub fn purge_finished(&self, cutoff: DateTime<Utc>) -> Vec<Uuid> {
+        let purged: Vec<Uuid> = self
+            .finished()
+            .into_iter()
+            .filter(|(_, completed_at)| *completed_at <= cutoff)
+            .map(|(id, _)| id)
+            .collect();
+        self.release(&purged);
+        purged
+    }
+
+    /// Drop the oldest finished executions beyond the store's limit
+    fn evict_finished(&self) {
+        let mut finished = self.finished();
+        if finished.len() <= self.max_finished {
+            return;
+        }
+        finished.sort_by_key(|(_, completed_at)| *completed_at);
+        let evicted: Vec<Uuid> = finished[..finished.len() - self.max_finished].iter().map(|(id, _)| *id).collect();
+        self.release(&evicted);
+    }
+
+    /// Finished executions with when they finished
+    fn finished(&self) -> Vec<(Uuid, DateTime<Utc>)> {
+        self.executions
+            .iter()
+            .filter(|entry| {
+                matches!(
+                    entry.value().get_status(),
+                    ExecutionStatus::Completed | ExecutionStatus::Failed | ExecutionStatus::Cancelled
+                )
+            })
+            .filter_map(|entry| Some((*entry.key(), (*entry.value().completed_at.read())?)))
+            .collect()
+    }
+
+    fn release(&self, execution_ids: &[Uuid]) {
+        for execution_id in execution_ids {
+            self.executions.remove(execution_id);
+            release_execution(execution_id);
+        }
+    }
+
+    /// Most recently started execution of a workflow
+    /// The most recently started execution of a workflow, other than
+    /// `excluding`
+    pub fn latest_for_workflow(&self, workflow_id: &Uuid, excluding: Option<&Uuid>) -> Option<Arc<WorkflowExecutionState>> {
+        self.executions
+            .iter()
+            .filter(|entry| entry.value().workflow_id == *workflow_id && Some(entry.key()) != excluding)
+            .max_by_key(|entry| entry.value().started_at)
+            .map(|entry| entry.value().clone())
+    }
+
+    pub fn list(&self) -> Vec<Arc<WorkflowExecutionState>> {
+        self.executions.iter().map(|entry| entry.value().clone()).collect()
+    }
+
     pub fn list_active(&self) -> Vec<Uuid> {
         self.executions
             .iter()
@@ -311,6 +479,7 @@ pub struct ExecutionSummary {
     pub progress: u8,
     pub started_at: String,
     pub completed_at: Option<String>,
+    pub cancel_reason: Option<String>,
 }
 
 impl From<&WorkflowExecutionState> for ExecutionSummary {
@@ -326,6 +495,85 @@ impl From<&WorkflowExecutionState> for ExecutionSummary {
             progress: state.get_overall_progress(),
             started_at: state.started_at.to_rfc3339(),
             completed_at: state.completed_at.read().map(|dt| dt.to_rfc3339()),
+            cancel_reason: state.get_cancel_reason(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancelled_is_final() {
+        let state = WorkflowExecutionState::new(
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            Uuid::new_v4(),
+            "task".to_string(),
+            vec![vec!["a".to_string()]],
+        );
+        state.set_status(ExecutionStatus::Running);
+        state.cancel_with_reason("App shutdown");
+
+        // Nodes stopped by the cancellation fail afterwards
+        state.set_status(ExecutionStatus::Failed);
+        assert_eq!(state.get_status(), ExecutionStatus::Cancelled);
+        assert_eq!(ExecutionSummary::from(&state).cancel_reason.as_deref(), Some("App shutdown"));
+    }
+
+    #[test]
+    fn test_finished_executions_are_bounded_and_purged() {
+        let store = ExecutionStore::with_max_finished(2);
+        let execution = |status: ExecutionStatus| {
+            let state = store.insert(WorkflowExecutionState::new(
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+                Uuid::new_v4(),
+                "task".to_string(),
+                vec![vec!["a".to_string()]],
+            ));
+            if status != ExecutionStatus::Pending {
+                state.set_status(status);
+            }
+            state.execution_id
+        };
+
+        let running = execution(ExecutionStatus::Running);
+        let oldest = execution(ExecutionStatus::Completed);
+        let failed = execution(ExecutionStatus::Failed);
+        let cancelled = execution(ExecutionStatus::Cancelled);
+        // The limit is enforced when the next execution arrives
+        let pending = execution(ExecutionStatus::Pending);
+
+        assert!(store.get(&oldest).is_none());
+        for id in [running, failed, cancelled, pending] {
+            assert!(store.get(&id).is_some());
         }
+
+        let mut purged = store.purge_finished(Utc::now());
+        purged.sort();
+        let mut expected = vec![failed, cancelled];
+        expected.sort();
+        assert_eq!(purged, expected);
+        assert_eq!(store.list().len(), 2);
+        assert!(store.purge_finished(Utc::now() - chrono::Duration::hours(1)).is_empty());
+    }
+
+    #[test]
+    fn test_latest_for_workflow_skips_excluded() {
+        let store = ExecutionStore::new();
+        let workflow_id = Uuid::new_v4();
+        let run = |started_at| {
+            let mut state = WorkflowExecutionState::new(Uuid::new_v4(), workflow_id, Uuid::new_v4(), "task".to_string(), vec![]);
+            state.started_at = started_at;
+            store.insert(state).execution_id
+        };
+        let previous = run(Utc::now() - chrono::Duration::minutes(5));
+        let current = run(Utc::now());
+
+        assert_eq!(store.latest_for_workflow(&workflow_id, None).map(|s| s.execution_id), Some(current));
+        assert_eq!(store.latest_for_workflow(&workflow_id, Some(&current)).map(|s| s.execution_id), Some(previous));
+        assert!(store.latest_for_workflow(&Uuid::new_v4(), None).is_none());
     }
 }
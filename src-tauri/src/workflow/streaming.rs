@@ -0,0 +1,172 @@
+//! Early handoff of a running agent's output to downstream nodes.
+//!
+//! An agent publishes part of its output before it finishes by putting it
+//! between directive lines:
+//!
+//! ```text
+//! @publish api-design
+//! ...
+//! @end
+//! ```
+//!
+//! Provides:
+//! - Finding finished sections in output that is still growing
+//! - A per-execution board of published sections that wakes the nodes
+//!   configured to start on them
+
+use dashmap::{DashMap, DashSet};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Notify;
+
+/// Tag on the context outputs that hold published sections
+pub const PUBLISHED_TAG: &str = "published";
+
+const PUBLISH_DIRECTIVE: &str = "@publish ";
+const END_DIRECTIVE: &str = "@end";
+
+/// A section a node publishes while it runs
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PublishedSection {
+    pub node_id: String,
+    pub section: String,
+}
+
+/// The finished sections in `output` as (name, content), in order. A
+/// section still missing its `@end` line is left for a later look.
+pub fn parse_sections(output: &str) -> Vec<(String, String)> {
+    let mut sections = Vec::new();
+    let mut open: Option<(String, Vec<&str>)> = None;
+
+    for line in output.lines() {
+        let trimmed = line.trim();
+        // A nested directive is ordinary text
+        if let (Some(name), None) = (trimmed.strip_prefix(PUBLISH_DIRECTIVE), &open) {
+            open = Some((name.trim().to_string(), Vec::new()));
+            continue;
+        }
+        match open.take() {
+            Some((name, lines)) if trimmed == END_DIRECTIVE => sections.push((name, lines.join("\n"))),
+            Some((name, mut lines)) => {
+                lines.push(line);
+                open = Some((name, lines));
+            }
+            None => {}
+        }
+    }
+
+    sections
+}
+
+/// Sections published so far in one execution
+#[derive(Default)]
+pub struct Publications {
+    /// Nodes some other node starts on, whose output is worth scanning
+    watched: DashSet<String>,
+    sections: DashMap<PublishedSection, String>,
+    /// Nodes that finished, so will publish nothing more
+    closed: DashSet<String>,
+    changed: Notify,
+}
+
+impl Publications {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Note that a node starts on sections of `node_id`
+    pub fn watch(&self, node_id: &str) {
+        self.watched.insert(node_id.to_string());
+    }
+
+    pub fn is_watched(&self, node_id: &str) -> bool {
+        self.watched.contains(node_id)
+    }
+
+    /// Store a section; false if the node published it already
+    pub fn publish(&self, node_id: &str, section: &str, content: String) -> bool {
+        let key = PublishedSection {
+            node_id: node_id.to_string(),
+            section: section.to_string(),
+        };
+        if self.sections.contains_key(&key) {
+            return false;
+        }
+        self.sections.insert(key, content);
+        self.changed.notify_waiters();
+        true
+    }
+
+    /// Mark a node finished, releasing whoever waits on a section it never
+    /// published
+    pub fn close(&self, node_id: &str) {
+        self.closed.insert(node_id.to_string());
+        self.changed.notify_waiters();
+    }
+
+    pub fn get(&self, section: &PublishedSection) -> Option<String> {
+        self.sections.get(section).map(|content| content.clone())
+    }
+
+    /// Wait for a section; `None` if its node finished without it
+    pub async fn wait(&self, section: &PublishedSection) -> Option<String> {
+        loop {
+            // Registered before looking, so a publish in between still wakes us
+            let changed = self.changed.notified();
+            tokio::pin!(changed);
+            changed.as_mut().enable();
+
+            if let Some(content) = self.get(section) {
+                return Some(content);
+            }
+            if self.closed.contains(&section.node_id) {
+                return None;
+            }
+            changed.await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parse_sections() {
+        let output = "Planning...\n@publish api\nGET /users\nPOST /users\n@end\nMore thinking\n@publish schema\nusers(id)";
+        let sections = parse_sections(output);
+        // The schema section isn't finished yet
+        assert_eq!(sections, vec![("api".to_string(), "GET /users\nPOST /users".to_string())]);
+
+        let finished = format!("{}\n@end", output);
+        assert_eq!(parse_sections(&finished)[1], ("schema".to_string(), "users(id)".to_string()));
+        assert!(parse_sections("@publish\nno name\n@end").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_wait_for_section() {
+        let board = Arc::new(Publications::new());
+        let api = PublishedSection {
+            node_id: "design".to_string(),
+            section: "api".to_string(),
+        };
+
+        let waiting = tokio::spawn({
+            let board = board.clone();
+            let api = api.clone();
+            async move { board.wait(&api).await }
+        });
+        tokio::task::yield_now().await;
+
+        assert!(board.publish("design", "api", "GET /users".to_string()));
+        assert!(!board.publish("design", "api", "changed".to_string()));
+        assert_eq!(waiting.await.unwrap(), Some("GET /users".to_string()));
+
+        let schema = PublishedSection {
+            node_id: "design".to_string(),
+            section: "schema".to_string(),
+        };
+        board.close("design");
+        assert_eq!(board.wait(&schema).await, None);
+    }
+}
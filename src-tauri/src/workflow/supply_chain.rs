@@ -0,0 +1,618 @@
+//! Supply-chain checks on the dependencies an execution adds.
+//!
+//! Agents add and upgrade dependencies freely, including versions with
+//! published vulnerabilities and, when a name is misremembered, packages
+//! that don't exist or that someone registered to catch the mistake.
+//! Provides:
+//! - Parsing of Cargo.lock, package-lock.json, yarn.lock, poetry.lock and
+//!   go.sum
+//! - The packages a lockfile change added or moved to another version,
+//!   compared with the lockfile from before the execution
+//! - Lookup of those versions in the OSV advisory database, and of new
+//!   package names in their registry
+//! - The reports per execution. While one flags packages, nodes of the
+//!   execution can't be approved, and its isolated workspace is only
+//!   merged when the packages are accepted explicitly.
+
+use chrono::{DateTime, Utc};
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::path::Path;
+use std::time::Duration;
+use uuid::Uuid;
+
+use crate::project::ISOLATED_WORKSPACES;
+
+use super::activity::FileChange;
+use super::rollback;
+
+const OSV_QUERYBATCH_URL: &str = "https://api.osv.dev/v1/querybatch";
+/// Queries OSV takes per batch request
+const OSV_BATCH_SIZE: usize = 1000;
+const REQUEST_TIMEOUT_SECS: u64 = 30;
+
+/// A package registry whose lockfiles can be checked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Ecosystem {
+    CratesIo,
+    Npm,
+    #[serde(rename = "pypi")]
+    PyPI,
+    Go,
+}
+
+impl Ecosystem {
+    /// The ecosystem's name in OSV queries
+    pub fn osv_name(&self) -> &'static str {
+        match self {
+            Ecosystem::CratesIo => "crates.io",
+            Ecosystem::Npm => "npm",
+            Ecosystem::PyPI => "PyPI",
+            Ecosystem::Go => "Go",
+        }
+    }
+
+    /// The lockfiles of this ecosystem are known by name
+    fn of_lockfile(path: &str) -> Option<Self> {
+        match Path::new(path).file_name()?.to_str()? {
+            "Cargo.lock" => Some(Ecosystem::CratesIo),
+            "package-lock.json" | "npm-shrinkwrap.json" | "yarn.lock" => Some(Ecosystem::Npm),
+            "poetry.lock" => Some(Ecosystem::PyPI),
+            "go.sum" => Some(Ecosystem::Go),
+            _ => None,
+        }
+    }
+
+    /// Registry URL for one version of a package; answers 404 when the
+    /// registry doesn't have it
+    fn registry_url(&self, name: &str, version: &str) -> String {
+        match self {
+            Ecosystem::CratesIo => format!("https://crates.io/api/v1/crates/{}/{}", name, version),
+            Ecosystem::Npm => format!("https://registry.npmjs.org/{}/{}", name.replace('/', "%2F"), version),
+            Ecosystem::PyPI => format!("https://pypi.org/pypi/{}/{}/json", name, version),
+            // The module proxy escapes capitals as `!` and the lowercase letter
+            Ecosystem::Go => {
+                let escaped: String = name
+                    .chars()
+                    .flat_map(|c| match c.is_ascii_uppercase() {
+                        true => vec!['!', c.to_ascii_lowercase()],
+                        false => vec![c],
+                    })
+                    .collect();
+                format!("https://proxy.golang.org/{}/@v/v{}.info", escaped, version)
+            }
+        }
+    }
+}
+
+/// One version of a package, as a lockfile pins it
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Dependency {
+    pub ecosystem: Ecosystem,
+    pub name: String,
+    pub version: String,
+}
+
+impl std::fmt::Display for Dependency {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {} ({})", self.name, self.version, self.ecosystem.osv_name())
+    }
+}
+
+/// A package version a lockfile gained during the execution
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AddedDependency {
+    /// Lockfile path relative to the project
+    pub lockfile: String,
+    #[serde(flatten)]
+    pub dependency: Dependency,
+    /// The version pinned before, for an upgrade or downgrade
+    pub previous_version: Option<String>,
+    /// IDs of the OSV advisories affecting this version
+    pub advisories: Vec<String>,
+    /// The registry doesn't have the package. Only new packages are looked
+    /// up, and none when the check allows unknown packages.
+    pub unknown: bool,
+}
+
+impl AddedDependency {
+    pub fn flagged(&self) -> bool {
+        !self.advisories.is_empty() || self.unknown
+    }
+
+    fn describe(&self) -> String {
+        match (self.advisories.is_empty(), self.unknown) {
+            (_, true) => format!("{} is not in its registry", self.dependency),
+            (false, _) => format!("{} has advisories {}", self.dependency, self.advisories.join(", ")),
+            (true, false) => self.dependency.to_string(),
+        }
+    }
+}
+
+/// What a supply-chain check node found
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SupplyChainReport {
+    pub execution_id: Uuid,
+    pub node_id: String,
+    /// Lockfiles the execution changed, relative to the project
+    pub lockfiles: Vec<String>,
+    pub added: Vec<AddedDependency>,
+    pub checked_at: DateTime<Utc>,
+}
+
+impl SupplyChainReport {
+    pub fn flagged(&self) -> impl Iterator<Item = &AddedDependency> {
+        self.added.iter().filter(|added| added.flagged())
+    }
+
+    pub fn summary(&self) -> String {
+        let flagged: Vec<String> = self.flagged().map(AddedDependency::describe).collect();
+        match (self.added.len(), flagged.is_empty()) {
+            (0, _) => format!("No dependencies added in {} changed lockfile(s)", self.lockfiles.len()),
+            (added, true) => format!("{} dependency version(s) added, none flagged", added),
+            (added, false) => format!("{} dependency version(s) added; flagged: {}", added, flagged.join("; ")),
+        }
+    }
+}
+
+/// Every package version `content` pins. Local, git and workspace packages
+/// are left out: there is nothing to look up for them.
+pub fn parse_lockfile(path: &str, content: &str) -> Result<BTreeSet<Dependency>, String> {
+    let ecosystem = Ecosystem::of_lockfile(path).ok_or_else(|| format!("Not a supported lockfile: {}", path))?;
+    let invalid = |e: String| format!("Invalid {}: {}", path, e);
+    let pins: Vec<(String, String)> = match Path::new(path).file_name().and_then(|name| name.to_str()) {
+        Some("Cargo.lock") => parse_toml_packages(content, |package| {
+            package
+                .get("source")
+                .and_then(|source| source.as_str())
+                .is_some_and(|source| source.starts_with("registry+") || source.starts_with("sparse+"))
+        })
+        .map_err(invalid)?,
+        Some("poetry.lock") => parse_toml_packages(content, |package| {
+            package
+                .get("source")
+                .and_then(|source| source.get("type"))
+                .and_then(|kind| kind.as_str())
+                .map_or(true, |kind| kind == "legacy")
+        })
+        .map_err(invalid)?,
+        Some("yarn.lock") => parse_yarn_lock(content),
+        Some("go.sum") => parse_go_sum(content),
+        _ => parse_package_lock(content).map_err(invalid)?,
+    };
+    Ok(pins
+        .into_iter()
+        .map(|(name, version)| Dependency { ecosystem, name, version })
+        .collect())
+}
+
+/// The `[[package]]` entries of a Cargo.lock or poetry.lock that `keep`
+/// accepts
+fn parse_toml_packages(content: &str, keep: impl Fn(&toml::Table) -> bool) -> Result<Vec<(String, String)>, String> {
+    let table = content.parse::<toml::Table>().map_err(|e| e.to_string())?;
+    let packages = table.get("package").and_then(|packages| packages.as_array()).cloned().unwrap_or_default();
+    Ok(packages
+        .iter()
+        .filter_map(|package| package.as_table())
+        .filter(|package| keep(package))
+        .filter_map(|package| {
+            let name = package.get("name")?.as_str()?;
+            let version = package.get("version")?.as_str()?;
+            Some((name.to_string(), version.to_string()))
+        })
+        .collect())
+}
+
+/// package-lock.json: the `packages` map of lockfile v2 and v3, or the
+/// nested `dependencies` of v1
+fn parse_package_lock(content: &str) -> Result<Vec<(String, String)>, String> {
+    let lock: serde_json::Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+    // Versions of local, git and tarball packages are paths and URLs
+    let registry_version = |version: &str| !version.contains(':') && !version.contains('/');
+    let mut pins = Vec::new();
+
+    if let Some(packages) = lock.get("packages").and_then(|packages| packages.as_object()) {
+        for (key, package) in packages {
+            let Some((_, name)) = key.rsplit_once("node_modules/") else {
+                continue;
+            };
+            if package.get("link").and_then(|link| link.as_bool()) == Some(true) {
+                continue;
+            }
+            if let Some(version) = package.get("version").and_then(|version| version.as_str()).filter(|v| registry_version(v)) {
+                pins.push((name.to_string(), version.to_string()));
+            }
+        }
+        return Ok(pins);
+    }
+
+    let mut pending: Vec<&serde_json::Value> = lock.get("dependencies").into_iter().collect();
+    while let Some(dependencies) = pending.pop() {
+        for (name, dependency) in dependencies.as_object().into_iter().flatten() {
+            if let Some(version) = dependency.get("version").and_then(|version| version.as_str()).filter(|v| registry_version(v)) {
+                pins.push((name.clone(), version.to_string()));
+            }
+            pending.extend(dependency.get("dependencies"));
+        }
+    }
+    Ok(pins)
+}
+
+/// yarn.lock, both the classic format and Berry's YAML: an unindented line
+/// of specifiers per package, then its indented `version`
+fn parse_yarn_lock(content: &str) -> Vec<(String, String)> {
+    let mut pins = Vec::new();
+    let mut name: Option<String> = None;
+    for line in content.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        if !line.starts_with(char::is_whitespace) {
+            let first = line.trim_end_matches(':').split(',').next().unwrap_or("").trim().trim_matches('"');
+            name = first.rfind('@').filter(|&at| at > 0).and_then(|at| {
+                let range = &first[at + 1..];
+                let local = ["workspace:", "link:", "portal:", "file:"].iter().any(|protocol| range.starts_with(protocol));
+                (!local).then(|| first[..at].to_string())
+            });
+            continue;
+        }
+        let Some(version) = line.trim().strip_prefix("version") else {
+            continue;
+        };
+        if let Some(name) = name.take() {
+            let version = version.trim_start_matches(':').trim().trim_matches('"');
+            pins.push((name, version.to_string()));
+        }
+    }
+    pins
+}
+
+/// go.sum: a module version per line, leaving out the `/go.mod` lines of
+/// modules only needed for their requirements
+fn parse_go_sum(content: &str) -> Vec<(String, String)> {
+    content
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let (module, version) = (fields.next()?, fields.next()?);
+            if version.ends_with("/go.mod") {
+                return None;
+            }
+            Some((module.to_string(), version.trim_start_matches('v').to_string()))
+        })
+        .collect()
+}
+
+/// The package versions `after` pins that `before` didn't. Both are the
+/// lockfile's content, `None` when it didn't or doesn't exist.
+pub fn added_dependencies(lockfile: &str, before: Option<&str>, after: Option<&str>) -> Result<Vec<AddedDependency>, String> {
+    let before = before.map(|content| parse_lockfile(lockfile, content)).transpose()?.unwrap_or_default();
+    let after = after.map(|content| parse_lockfile(lockfile, content)).transpose()?.unwrap_or_default();
+    Ok(after
+        .difference(&before)
+        .map(|dependency| AddedDependency {
+            lockfile: lockfile.to_string(),
+            previous_version: before
+                .iter()
+                .rev()
+                .find(|old| old.name == dependency.name && !after.contains(*old))
+                .map(|old| old.version.clone()),
+            dependency: dependency.clone(),
+            advisories: Vec::new(),
+            unknown: false,
+        })
+        .collect())
+}
+
+/// A lockfile the execution changed, with its content before and after
+struct LockfileChange {
+    path: String,
+    before: Option<String>,
+    after: Option<String>,
+}
+
+/// The supported lockfiles the execution changed, limited to `only` when
+/// given. An isolated execution is compared with its project; any other
+/// with the working tree recorded when it started.
+async fn changed_lockfiles(dir: &Path, execution_id: &Uuid, only: &[String]) -> Result<Vec<LockfileChange>, String> {
+    let wanted = |path: &str| Ecosystem::of_lockfile(path).is_some() && (only.is_empty() || only.iter().any(|p| p == path));
+    let read = |path: &Path| std::fs::read_to_string(path).ok();
+
+    if let Ok(workspace) = ISOLATED_WORKSPACES.get(execution_id) {
+        let changes = ISOLATED_WORKSPACES.changes(execution_id).map_err(|e| e.to_string())?;
+        return Ok(changes
+            .into_iter()
+            .filter(|change| wanted(&change.path))
+            .map(|change| LockfileChange {
+                before: read(&workspace.project_dir.join(&change.path)),
+                after: match change.change {
+                    FileChange::Deleted => None,
+                    _ => read(&workspace.workspace_dir.join(&change.path)),
+                },
+                path: change.path,
+            })
+            .collect());
+    }
+
+    let (base_tree, _, changes) = rollback::changes_since_base(dir, execution_id).await?;
+    let mut lockfiles = Vec::new();
+    for (_, path) in changes.into_iter().filter(|(_, path)| wanted(path)) {
+        lockfiles.push(LockfileChange {
+            before: rollback::base_content(dir, &base_tree, &path).await?,
+            after: read(&dir.join(&path)),
+            path,
+        });
+    }
+    Ok(lockfiles)
+}
+
+fn http_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(Duration::from_secs(REQUEST_TIMEOUT_SECS))
+        // crates.io turns away requests without one
+        .user_agent(concat!("nexus/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| e.to_string())
+}
+
+/// The advisory IDs OSV has for each dependency, in order
+async fn query_osv(client: &reqwest::Client, dependencies: &[&Dependency]) -> Result<Vec<Vec<String>>, String> {
+    let mut advisories = Vec::with_capacity(dependencies.len());
+    for batch in dependencies.chunks(OSV_BATCH_SIZE) {
+        let queries: Vec<serde_json::Value> = batch
+            .iter()
+            .map(|dependency| {
+                serde_json::json!({
+                    "package": { "name": dependency.name, "ecosystem": dependency.ecosystem.osv_name() },
+                    "version": dependency.version,
+                })
+            })
+            .collect();
+        let response = client
+            .post(OSV_QUERYBATCH_URL)
+            .json(&serde_json::json!({ "queries": queries }))
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| format!("OSV query failed: {}", e))?;
+        let body: serde_json::Value = response.json().await.map_err(|e| format!("Invalid OSV response: {}", e))?;
+        let results = body.get("results").and_then(|results| results.as_array()).cloned().unwrap_or_default();
+        if results.len() != batch.len() {
+            return Err(format!("OSV answered {} of {} queries", results.len(), batch.len()));
+        }
+        advisories.extend(results.iter().map(|result| {
+            let vulns = result.get("vulns").and_then(|vulns| vulns.as_array());
+            vulns
+                .into_iter()
+                .flatten()
+                .filter_map(|vuln| vuln.get("id")?.as_str().map(str::to_string))
+                .collect()
+        }));
+    }
+    Ok(advisories)
+}
+
+/// Whether the package's registry has this version of it
+async fn in_registry(client: &reqwest::Client, dependency: &Dependency) -> Result<bool, String> {
+    let url = dependency.ecosystem.registry_url(&dependency.name, &dependency.version);
+    let response = client
+        .get(&url)
+        .send()
+        .await
+        .map_err(|e| format!("Failed to look up {}: {}", dependency, e))?;
+    match response.status() {
+        status if status.is_success() => Ok(true),
+        reqwest::StatusCode::NOT_FOUND => Ok(false),
+        status => Err(format!("Failed to look up {}: registry answered {}", dependency, status)),
+    }
+}
+
+/// Check the dependencies the execution added in `dir`. Advisories in
+/// `ignore_advisories` don't count; with `allow_unknown` new packages
+/// aren't looked up in their registry.
+pub async fn check(
+    dir: &Path,
+    execution_id: Uuid,
+    node_id: &str,
+    lockfiles: &[String],
+    allow_unknown: bool,
+    ignore_advisories: &[String],
+) -> Result<SupplyChainReport, String> {
+    let changes = changed_lockfiles(dir, &execution_id, lockfiles).await?;
+    let mut added = Vec::new();
+    for change in &changes {
+        added.extend(added_dependencies(&change.path, change.before.as_deref(), change.after.as_deref())?);
+    }
+
+    if !added.is_empty() {
+        let client = http_client()?;
+        let dependencies: Vec<&Dependency> = added.iter().map(|added| &added.dependency).collect();
+        let advisories = query_osv(&client, &dependencies).await?;
+        for (dependency, ids) in added.iter_mut().zip(advisories) {
+            dependency.advisories = ids.into_iter().filter(|id| !ignore_advisories.contains(id)).collect();
+        }
+        // Upgrades of packages the project already used are known to exist
+        if !allow_unknown {
+            for dependency in added.iter_mut().filter(|added| added.previous_version.is_none()) {
+                dependency.unknown = !in_registry(&client, &dependency.dependency).await?;
+            }
+        }
+    }
+
+    Ok(SupplyChainReport {
+        execution_id,
+        node_id: node_id.to_string(),
+        lockfiles: changes.into_iter().map(|change| change.path).collect(),
+        added,
+        checked_at: Utc::now(),
+    })
+}
+
+/// Supply-chain reports by execution
+#[derive(Default)]
+pub struct SupplyChainReports {
+    reports: DashMap<Uuid, Vec<SupplyChainReport>>,
+}
+
+impl SupplyChainReports {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Keep a node's report, replacing the one from an earlier attempt
+    pub fn record(&self, report: SupplyChainReport) {
+        let mut reports = self.reports.entry(report.execution_id).or_default();
+        reports.retain(|existing| existing.node_id != report.node_id);
+        reports.push(report);
+    }
+
+    pub fn get(&self, execution_id: &Uuid) -> Vec<SupplyChainReport> {
+        self.reports.get(execution_id).map(|reports| reports.clone()).unwrap_or_default()
+    }
+
+    /// The flagged dependencies of an execution, across its checks
+    pub fn flagged(&self, execution_id: &Uuid) -> Vec<AddedDependency> {
+        self.get(execution_id)
+            .iter()
+            .flat_map(|report| report.flagged().cloned())
+            .collect()
+    }
+
+    /// Why the execution's changes can't be approved, if they can't
+    pub fn approval_blocker(&self, execution_id: &Uuid) -> Option<String> {
+        let flagged = self.flagged(execution_id);
+        if flagged.is_empty() {
+            return None;
+        }
+        let described: Vec<String> = flagged.iter().map(AddedDependency::describe).collect();
+        Some(format!("The supply-chain check flagged dependencies: {}", described.join("; ")))
+    }
+}
+
+lazy_static! {
+    pub static ref SUPPLY_CHAIN_REPORTS: SupplyChainReports = SupplyChainReports::new();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CARGO_BEFORE: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+
+[[package]]
+name = "serde"
+version = "1.0.180"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+
+    const CARGO_AFTER: &str = r#"
+version = 3
+
+[[package]]
+name = "app"
+version = "0.1.0"
+
+[[package]]
+name = "serde"
+version = "1.0.190"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+
+[[package]]
+name = "serde_jsonn"
+version = "1.0.0"
+source = "sparse+https://index.crates.io/"
+
+[[package]]
+name = "forked"
+version = "0.2.0"
+source = "git+https://github.com/someone/forked#abc123"
+"#;
+
+    #[test]
+    fn test_added_dependencies() {
+        let added = added_dependencies("Cargo.lock", Some(CARGO_BEFORE), Some(CARGO_AFTER)).unwrap();
+        let found: Vec<(&str, &str, Option<&str>)> = added
+            .iter()
+            .map(|a| (a.dependency.name.as_str(), a.dependency.version.as_str(), a.previous_version.as_deref()))
+            .collect();
+        assert_eq!(found, [("serde", "1.0.190", Some("1.0.180")), ("serde_jsonn", "1.0.0", None)]);
+        assert!(added_dependencies("Cargo.lock", Some(CARGO_AFTER), Some(CARGO_AFTER)).unwrap().is_empty());
+        assert!(added_dependencies("Cargo.toml", None, Some("")).is_err());
+
+        let package_lock = r#"{
+            "lockfileVersion": 3,
+            "packages": {
+                "": { "name": "shop", "version": "1.0.0" },
+                "node_modules/lodash": { "version": "4.17.15" },
+                "node_modules/@types/node": { "version": "20.1.0" },
+                "node_modules/a/node_modules/lodash": { "version": "3.10.1" },
+                "node_modules/local": { "resolved": "../local", "link": true },
+                "node_modules/tarball": { "version": "file:vendor/tarball.tgz" }
+            }
+        }"#;
+        let names: Vec<String> = parse_lockfile("web/package-lock.json", package_lock)
+            .unwrap()
+            .into_iter()
+            .map(|d| format!("{}@{}", d.name, d.version))
+            .collect();
+        assert_eq!(names, ["@types/node@20.1.0", "lodash@3.10.1", "lodash@4.17.15"]);
+
+        let yarn_lock = "# yarn lockfile v1\n\n\
+            \"@babel/code-frame@^7.0.0\", \"@babel/code-frame@^7.10.4\":\n  version \"7.12.13\"\n  resolved \"https://registry.yarnpkg.com/x\"\n\n\
+            left-pad@npm:^1.3.0:\n  version: 1.3.0\n\n\
+            shop@workspace:.:\n  version: 0.0.0-use.local\n";
+        let pins: Vec<(String, String)> = parse_lockfile("yarn.lock", yarn_lock)
+            .unwrap()
+            .into_iter()
+            .map(|d| (d.name, d.version))
+            .collect();
+        assert_eq!(pins, [("@babel/code-frame".to_string(), "7.12.13".to_string()), ("left-pad".to_string(), "1.3.0".to_string())]);
+
+        let go_sum = "github.com/gin-gonic/gin v1.9.1 h1:abc=\n\
+            github.com/gin-gonic/gin v1.9.1/go.mod h1:def=\n\
+            golang.org/x/net v0.17.0/go.mod h1:ghi=\n";
+        let pins: Vec<Dependency> = parse_lockfile("go.sum", go_sum).unwrap().into_iter().collect();
+        assert_eq!(pins.len(), 1);
+        assert_eq!((pins[0].name.as_str(), pins[0].version.as_str()), ("github.com/gin-gonic/gin", "1.9.1"));
+        assert_eq!(
+            Ecosystem::Go.registry_url("github.com/BurntSushi/toml", "1.3.2"),
+            "https://proxy.golang.org/github.com/!burnt!sushi/toml/@v/v1.3.2.info"
+        );
+    }
+
+    #[test]
+    fn test_approval_blocker() {
+        let store = SupplyChainReports::new();
+        let execution_id = Uuid::new_v4();
+        let mut added = added_dependencies("Cargo.lock", Some(CARGO_BEFORE), Some(CARGO_AFTER)).unwrap();
+        let report = |added: Vec<AddedDependency>| SupplyChainReport {
+            execution_id,
+            node_id: "deps".to_string(),
+            lockfiles: vec!["Cargo.lock".to_string()],
+            added,
+            checked_at: Utc::now(),
+        };
+
+        store.record(report(added.clone()));
+        assert_eq!(store.approval_blocker(&execution_id), None);
+        assert!(store.get(&execution_id)[0].summary().starts_with("2 dependency version(s) added, none flagged"));
+
+        added[0].advisories.push("RUSTSEC-2099-0001".to_string());
+        added[1].unknown = true;
+        // A re-run of the node replaces its report
+        store.record(report(added));
+        assert_eq!(store.get(&execution_id).len(), 1);
+        let blocker = store.approval_blocker(&execution_id).unwrap();
+        assert!(blocker.contains("serde 1.0.190 (crates.io) has advisories RUSTSEC-2099-0001"));
+        assert!(blocker.contains("serde_jsonn 1.0.0 (crates.io) is not in its registry"));
+        assert_eq!(store.approval_blocker(&Uuid::new_v4()), None);
+    }
+}
@@ -1,11 +1,19 @@
 //! Pre-built workflow templates for common development tasks.
 //!
 //! Templates provide ready-to-use workflow patterns that users can
-//! customize for their specific needs.
+//! customize for their specific needs, along with node configuration
+//! defaults the enhanced executor starts from.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 
 use serde::{Deserialize, Serialize};
 
+use super::aggregation::{AggregationStrategy, NodeAggregationConfig};
+use super::conditions::ExecutionCondition;
+use super::enhanced_executor::EnhancedNodeConfig;
 use super::orchestrator::PlannedTask;
+use super::retry::RetryConfig;
+use crate::settings::secrets;
 
 /// A workflow template definition
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +26,70 @@ pub struct WorkflowTemplate {
     pub tasks: Vec<PlannedTask>,
     pub variables: Vec<TemplateVariable>,
     pub estimated_duration_minutes: Option<u32>,
+    /// Node configuration defaults by task ID
+    #[serde(default)]
+    pub node_configs: HashMap<String, TemplateNodeConfig>,
+}
+
+/// Defaults a template ships for one of its tasks' nodes; a node config
+/// given with the execution overrides them field by field
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TemplateNodeConfig {
+    pub condition: Option<ExecutionCondition>,
+    pub retry: Option<RetryConfig>,
+    pub aggregation: Option<NodeAggregationConfig>,
+    pub output_tags: Vec<String>,
+}
+
+impl TemplateNodeConfig {
+    /// The node config these defaults start an execution with
+    pub fn to_node_config(&self) -> EnhancedNodeConfig {
+        EnhancedNodeConfig {
+            condition: self.condition.clone().unwrap_or_default(),
+            retry: self.retry.clone(),
+            aggregation: self.aggregation.clone(),
+            output_tags: self.output_tags.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// How a template's `{{variable}}` placeholders matched the variables
+/// given to instantiate it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SubstitutionReport {
+    /// Placeholders no variable was given for, with the tasks they are left in
+    pub unresolved: BTreeMap<String, Vec<String>>,
+    /// Variables given that no task uses
+    pub unused_variables: Vec<String>,
+    /// Tasks left with at least one placeholder, in template order
+    pub affected_tasks: Vec<String>,
+}
+
+impl SubstitutionReport {
+    pub fn is_clean(&self) -> bool {
+        self.unresolved.is_empty()
+    }
+}
+
+/// Names of the `{{name}}` placeholders in `text`. Secret references
+/// (`{{secret:name}}`) aren't placeholders; they're resolved at spawn.
+fn placeholders(text: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        rest = &rest[start + 2..];
+        let Some(end) = rest.find("}}") else {
+            break;
+        };
+        let name = &rest[..end];
+        if !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || matches!(c, '_' | '-' | '.')) {
+            names.push(name);
+            rest = &rest[end + 2..];
+        }
+    }
+    names
 }
 
 /// Categories for organizing templates
@@ -53,27 +125,88 @@ pub enum VariableType {
     Boolean,
     FilePath,
     Choice { options: Vec<String> },
+    /// The name of a secret from the settings. Tasks get a reference that
+    /// is resolved when the agent is spawned, never the value itself.
+    Secret,
+    /// Free text that may span lines
+    MultiLineText,
 }
 
 impl WorkflowTemplate {
     /// Create a template instance with custom variable values
-    pub fn instantiate(&self, variables: &std::collections::HashMap<String, String>) -> Vec<PlannedTask> {
+    pub fn instantiate(&self, variables: &HashMap<String, String>) -> Vec<PlannedTask> {
+        let values: Vec<(String, String)> = variables
+            .iter()
+            .map(|(key, value)| {
+                let value = match self.variables.iter().find(|v| &v.name == key).map(|v| &v.variable_type) {
+                    Some(VariableType::Secret) => secrets::reference(value),
+                    Some(VariableType::MultiLineText) => value.replace("\r\n", "\n"),
+                    _ => value.clone(),
+                };
+                (format!("{{{{{}}}}}", key), value)
+            })
+            .collect();
+
         self.tasks
             .iter()
             .map(|task| {
                 let mut task = task.clone();
                 // Replace {{variable}} placeholders in description
-                for (key, value) in variables {
-                    let placeholder = format!("{{{{{}}}}}", key);
-                    task.description = task.description.replace(&placeholder, value);
+                for (placeholder, value) in &values {
+                    task.description = task.description.replace(placeholder, value);
                     if let Some(ref mut prompt) = task.system_prompt {
-                        *prompt = prompt.replace(&placeholder, value);
+                        *prompt = prompt.replace(placeholder, value);
                     }
                 }
                 task
             })
             .collect()
     }
+
+    /// Which placeholders in the tasks `variables` leave unresolved, and
+    /// which of `variables` no task uses
+    pub fn audit_substitution(&self, variables: &HashMap<String, String>) -> SubstitutionReport {
+        let mut report = SubstitutionReport::default();
+        let mut used = BTreeSet::new();
+
+        for task in &self.tasks {
+            let texts = std::iter::once(task.description.as_str()).chain(task.system_prompt.as_deref());
+            for name in texts.flat_map(placeholders) {
+                if variables.contains_key(name) {
+                    used.insert(name);
+                    continue;
+                }
+                let tasks = report.unresolved.entry(name.to_string()).or_default();
+                if !tasks.contains(&task.id) {
+                    tasks.push(task.id.clone());
+                }
+                if !report.affected_tasks.contains(&task.id) {
+                    report.affected_tasks.push(task.id.clone());
+                }
+            }
+        }
+
+        let mut unused: Vec<String> = variables.keys().filter(|name| !used.contains(name.as_str())).cloned().collect();
+        unused.sort();
+        report.unused_variables = unused;
+        report
+    }
+
+    /// Node configs for the template's tasks, keyed by task ID
+    pub fn node_defaults(&self) -> HashMap<String, EnhancedNodeConfig> {
+        self.node_configs
+            .iter()
+            .map(|(task_id, config)| (task_id.clone(), config.to_node_config()))
+            .collect()
+    }
+}
+
+/// Retries for an agent task whose failure is most often a flaky run
+fn retry_twice() -> RetryConfig {
+    RetryConfig {
+        max_attempts: 2,
+        ..Default::default()
+    }
 }
 
 /// Get all built-in workflow templates
@@ -87,6 +220,38 @@ pub fn get_builtin_templates() -> Vec<WorkflowTemplate> {
             category: TemplateCategory::Development,
             tags: vec!["feature".to_string(), "full-stack".to_string(), "agile".to_string()],
             estimated_duration_minutes: Some(60),
+            node_configs: HashMap::from([
+                (
+                    "implement".to_string(),
+                    TemplateNodeConfig {
+                        retry: Some(retry_twice()),
+                        output_tags: vec!["code".to_string()],
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "test".to_string(),
+                    TemplateNodeConfig {
+                        retry: Some(retry_twice()),
+                        output_tags: vec!["tests".to_string()],
+                        ..Default::default()
+                    },
+                ),
+                (
+                    "document".to_string(),
+                    TemplateNodeConfig {
+                        condition: Some(ExecutionCondition::AllPredecessorsSucceeded),
+                        aggregation: Some(NodeAggregationConfig {
+                            strategy: AggregationStrategy::Concatenate {
+                                separator: "\n\n---\n\n".to_string(),
+                                include_source: true,
+                            },
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    },
+                ),
+            ]),
             variables: vec![
                 TemplateVariable {
                     name: "feature_name".to_string(),
@@ -155,6 +320,7 @@ pub fn get_builtin_templates() -> Vec<WorkflowTemplate> {
             category: TemplateCategory::Development,
             tags: vec!["bug".to_string(), "fix".to_string(), "debugging".to_string()],
             estimated_duration_minutes: Some(30),
+            node_configs: HashMap::new(),
             variables: vec![
                 TemplateVariable {
                     name: "bug_description".to_string(),
@@ -207,6 +373,7 @@ pub fn get_builtin_templates() -> Vec<WorkflowTemplate> {
             category: TemplateCategory::CodeReview,
             tags: vec!["review".to_string(), "quality".to_string(), "security".to_string()],
             estimated_duration_minutes: Some(20),
+            node_configs: HashMap::new(),
             variables: vec![
                 TemplateVariable {
                     name: "files_to_review".to_string(),
@@ -267,6 +434,7 @@ pub fn get_builtin_templates() -> Vec<WorkflowTemplate> {
             category: TemplateCategory::Development,
             tags: vec!["api".to_string(), "rest".to_string(), "backend".to_string()],
             estimated_duration_minutes: Some(45),
+            node_configs: HashMap::new(),
             variables: vec![
                 TemplateVariable {
                     name: "api_name".to_string(),
@@ -336,6 +504,7 @@ pub fn get_builtin_templates() -> Vec<WorkflowTemplate> {
             category: TemplateCategory::Refactoring,
             tags: vec!["refactor".to_string(), "cleanup".to_string(), "improvement".to_string()],
             estimated_duration_minutes: Some(40),
+            node_configs: HashMap::new(),
             variables: vec![
                 TemplateVariable {
                     name: "target_code".to_string(),
@@ -396,6 +565,7 @@ pub fn get_builtin_templates() -> Vec<WorkflowTemplate> {
             category: TemplateCategory::DevOps,
             tags: vec!["cicd".to_string(), "devops".to_string(), "automation".to_string()],
             estimated_duration_minutes: Some(50),
+            node_configs: HashMap::new(),
             variables: vec![
                 TemplateVariable {
                     name: "project_type".to_string(),
@@ -468,6 +638,7 @@ pub fn get_builtin_templates() -> Vec<WorkflowTemplate> {
             category: TemplateCategory::Documentation,
             tags: vec!["docs".to_string(), "documentation".to_string(), "readme".to_string()],
             estimated_duration_minutes: Some(35),
+            node_configs: HashMap::new(),
             variables: vec![
                 TemplateVariable {
                     name: "project_name".to_string(),
@@ -521,6 +692,7 @@ pub fn get_builtin_templates() -> Vec<WorkflowTemplate> {
             category: TemplateCategory::Security,
             tags: vec!["security".to_string(), "audit".to_string(), "vulnerability".to_string()],
             estimated_duration_minutes: Some(45),
+            node_configs: HashMap::new(),
             variables: vec![
                 TemplateVariable {
                     name: "audit_scope".to_string(),
@@ -616,7 +788,7 @@ mod tests {
     #[test]
     fn test_template_instantiation() {
         let template = get_template("feature-development").unwrap();
-        let mut vars = std::collections::HashMap::new();
+        let mut vars = HashMap::new();
         vars.insert("feature_name".to_string(), "User Auth".to_string());
         vars.insert("feature_description".to_string(), "OAuth2 login".to_string());
 
@@ -625,6 +797,74 @@ mod tests {
         assert!(tasks[0].description.contains("OAuth2 login"));
     }
 
+    #[test]
+    fn test_secret_and_multi_line_variables() {
+        let mut template = get_template("feature-development").unwrap();
+        template.tasks[0].description = "Use {{token}} for:\n{{notes}}".to_string();
+        template.variables = vec![
+            TemplateVariable {
+                name: "token".to_string(),
+                description: "Deploy token".to_string(),
+                default_value: None,
+                required: true,
+                variable_type: VariableType::Secret,
+            },
+            TemplateVariable {
+                name: "notes".to_string(),
+                description: "Notes".to_string(),
+                default_value: None,
+                required: false,
+                variable_type: VariableType::MultiLineText,
+            },
+        ];
+        let vars = HashMap::from([
+            ("token".to_string(), "deploy_token".to_string()),
+            ("notes".to_string(), "one\r\ntwo".to_string()),
+        ]);
+
+        let tasks = template.instantiate(&vars);
+        assert_eq!(tasks[0].description, "Use {{secret:deploy_token}} for:\none\ntwo");
+    }
+
+    #[test]
+    fn test_substitution_audit() {
+        let mut template = get_template("feature-development").unwrap();
+        template.tasks[0].description = "Build {{feature_name}} ({{feature_description}}) on {{branch}}, see {{secret:docs}} and {{ not a placeholder }}".to_string();
+        template.tasks[1].system_prompt = Some("Target {{branch}} and {{platform}}".to_string());
+        let vars = HashMap::from([
+            ("feature_name".to_string(), "Search".to_string()),
+            ("feature_description".to_string(), "Full-text search".to_string()),
+            ("colour".to_string(), "blue".to_string()),
+        ]);
+
+        let report = template.audit_substitution(&vars);
+        let first = template.tasks[0].id.clone();
+        let second = template.tasks[1].id.clone();
+        assert_eq!(report.unresolved["branch"], vec![first.clone(), second.clone()]);
+        assert_eq!(report.unresolved["platform"], vec![second.clone()]);
+        assert_eq!(report.unresolved.len(), 2);
+        assert_eq!(report.affected_tasks, vec![first, second]);
+        assert_eq!(report.unused_variables, vec!["colour".to_string()]);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_node_defaults() {
+        let template = get_template("feature-development").unwrap();
+        let defaults = template.node_defaults();
+        assert_eq!(defaults["implement"].retry.as_ref().unwrap().max_attempts, 2);
+        assert_eq!(defaults["test"].output_tags, vec!["tests"]);
+        assert!(matches!(defaults["document"].condition, ExecutionCondition::AllPredecessorsSucceeded));
+        assert!(defaults["document"].aggregation.is_some());
+
+        // Every default names one of the template's tasks
+        for template in get_builtin_templates() {
+            for task_id in template.node_configs.keys() {
+                assert!(template.tasks.iter().any(|t| &t.id == task_id), "{}: {}", template.id, task_id);
+            }
+        }
+    }
+
     #[test]
     fn test_search_templates() {
         let results = search_templates("security");
@@ -0,0 +1,273 @@
+//! Test runner output turned into structured results.
+//!
+//! Provides:
+//! - Parsers for `cargo test`, pytest, Jest and JUnit XML output, and
+//!   detection of which one produced a given output
+//! - Pass/fail/skip counts and the names of the failing tests
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Tag on the structured results stored beside a node's output
+pub const TEST_RESULTS_TAG: &str = "test_results";
+
+/// Which runner's output to expect
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestFormat {
+    /// Recognize the runner from the output
+    #[default]
+    Auto,
+    Cargo,
+    Pytest,
+    Jest,
+    Junit,
+}
+
+/// The outcome of a test run
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TestResults {
+    pub format: TestFormat,
+    pub passed: u64,
+    pub failed: u64,
+    pub skipped: u64,
+    pub total: u64,
+    /// As the runner names them, e.g. `tests/test_api.py::test_login`
+    pub failed_tests: Vec<String>,
+}
+
+lazy_static! {
+    static ref CARGO_TEST: Regex = Regex::new(r"^test (\S+) \.\.\. (ok|FAILED|ignored)").unwrap();
+    static ref CARGO_SUMMARY: Regex =
+        Regex::new(r"test result: \w+\. (\d+) passed; (\d+) failed; (\d+) ignored").unwrap();
+    static ref PYTEST_SUMMARY: Regex = Regex::new(r"^=+ (.*\b(?:passed|failed|error|errors)\b.*) in [\d.]+s.* =+$").unwrap();
+    static ref PYTEST_FAILED: Regex = Regex::new(r"^(?:FAILED|ERROR) (\S+::\S+)|^(\S+::\S+) (?:FAILED|ERROR)").unwrap();
+    static ref JEST_SUMMARY: Regex = Regex::new(r"^Tests:\s+(.*\d+ total)").unwrap();
+    static ref JEST_FAILED: Regex = Regex::new(r"^\s*● (.+)$").unwrap();
+    static ref COUNT: Regex = Regex::new(r"(\d+) (\w+)").unwrap();
+    static ref XML_ATTR: Regex = Regex::new(r#"(\w+)="([^"]*)""#).unwrap();
+}
+
+/// Parse a runner's output; `None` if it isn't output of that runner
+pub fn parse(format: TestFormat, output: &str) -> Option<TestResults> {
+    let format = match format {
+        TestFormat::Auto => detect(output)?,
+        format => format,
+    };
+    let mut results = match format {
+        TestFormat::Cargo => parse_cargo(output),
+        TestFormat::Pytest => parse_pytest(output),
+        TestFormat::Jest => parse_jest(output),
+        TestFormat::Junit => parse_junit(output),
+        TestFormat::Auto => None,
+    }?;
+    results.format = format;
+    Some(results)
+}
+
+/// The runner that produced `output`, by its summary lines
+pub fn detect(output: &str) -> Option<TestFormat> {
+    if output.contains("<testsuite") || output.contains("<testcase") {
+        Some(TestFormat::Junit)
+    } else if CARGO_SUMMARY.is_match(output) {
+        Some(TestFormat::Cargo)
+    } else if output.lines().any(|line| JEST_SUMMARY.is_match(line)) {
+        Some(TestFormat::Jest)
+    } else if output.lines().any(|line| PYTEST_SUMMARY.is_match(line.trim())) {
+        Some(TestFormat::Pytest)
+    } else {
+        None
+    }
+}
+
+/// Sums the `test result:` line of every test binary
+fn parse_cargo(output: &str) -> Option<TestResults> {
+    let mut results = TestResults::default();
+    let mut summaries = 0;
+
+    for line in output.lines() {
+        if let Some(caps) = CARGO_TEST.captures(line) {
+            if &caps[2] == "FAILED" {
+                results.failed_tests.push(caps[1].to_string());
+            }
+        } else if let Some(caps) = CARGO_SUMMARY.captures(line) {
+            summaries += 1;
+            results.passed += caps[1].parse::<u64>().unwrap_or(0);
+            results.failed += caps[2].parse::<u64>().unwrap_or(0);
+            results.skipped += caps[3].parse::<u64>().unwrap_or(0);
+        }
+    }
+
+    if summaries == 0 {
+        return None;
+    }
+    results.total = results.passed + results.failed + results.skipped;
+    Some(results)
+}
+
+/// The final `=== 1 failed, 3 passed in 0.12s ===` line; errors count as
+/// failures
+fn parse_pytest(output: &str) -> Option<TestResults> {
+    let summary = output
+        .lines()
+        .rev()
+        .find_map(|line| PYTEST_SUMMARY.captures(line.trim()).map(|caps| caps[1].to_string()))?;
+
+    let mut results = TestResults::default();
+    for caps in COUNT.captures_iter(&summary) {
+        let count = caps[1].parse::<u64>().unwrap_or(0);
+        match &caps[2] {
+            "passed" | "xpassed" => results.passed += count,
+            "failed" | "error" | "errors" => results.failed += count,
+            "skipped" | "xfailed" | "deselected" => results.skipped += count,
+            _ => {}
+        }
+    }
+    results.total = results.passed + results.failed + results.skipped;
+
+    for line in output.lines() {
+        if let Some(caps) = PYTEST_FAILED.captures(line.trim()) {
+            let name = caps.get(1).or_else(|| caps.get(2)).map(|m| m.as_str().to_string());
+            if let Some(name) = name.filter(|name| !results.failed_tests.contains(name)) {
+                results.failed_tests.push(name);
+            }
+        }
+    }
+    Some(results)
+}
+
+/// The `Tests: 1 failed, 2 passed, 3 total` line, with failing tests
+/// from their `●` headings
+fn parse_jest(output: &str) -> Option<TestResults> {
+    let summary = output
+        .lines()
+        .find_map(|line| JEST_SUMMARY.captures(line).map(|caps| caps[1].to_string()))?;
+
+    let mut results = TestResults::default();
+    for caps in COUNT.captures_iter(&summary) {
+        let count = caps[1].parse::<u64>().unwrap_or(0);
+        match &caps[2] {
+            "passed" => results.passed = count,
+            "failed" => results.failed = count,
+            "skipped" | "todo" => results.skipped += count,
+            "total" => results.total = count,
+            _ => {}
+        }
+    }
+
+    for line in output.lines() {
+        if let Some(caps) = JEST_FAILED.captures(line) {
+            let name = caps[1].trim().to_string();
+            // Suites that failed to run are headed "Test suite failed to run"
+            if !name.starts_with("Test suite failed") && !results.failed_tests.contains(&name) {
+                results.failed_tests.push(name);
+            }
+        }
+    }
+    Some(results)
+}
+
+/// Counted from the `<testcase>` elements, since suites don't all report
+/// skips. A `<failure>` or `<error>` inside a case fails it.
+fn parse_junit(output: &str) -> Option<TestResults> {
+    let mut results = TestResults::default();
+    let mut rest = output;
+
+    while let Some(start) = rest.find("<testcase") {
+        rest = &rest[start..];
+        let tag_end = rest.find('>')?;
+        let tag = &rest[..tag_end];
+        let body = if tag.ends_with('/') {
+            ""
+        } else {
+            let close = rest.find("</testcase>").unwrap_or(rest.len());
+            &rest[tag_end..close]
+        };
+
+        let attrs: Vec<(String, String)> = XML_ATTR
+            .captures_iter(tag)
+            .map(|caps| (caps[1].to_string(), caps[2].to_string()))
+            .collect();
+        let attr = |key: &str| attrs.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_str());
+        let name = match (attr("classname"), attr("name")) {
+            (Some(class), Some(name)) => format!("{}.{}", class, name),
+            (None, Some(name)) => name.to_string(),
+            _ => "unnamed".to_string(),
+        };
+
+        results.total += 1;
+        if body.contains("<failure") || body.contains("<error") {
+            results.failed += 1;
+            results.failed_tests.push(name);
+        } else if body.contains("<skipped") {
+            results.skipped += 1;
+        } else {
+            results.passed += 1;
+        }
+        rest = &rest[tag_end..];
+    }
+
+    (results.total > 0).then_some(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cargo() {
+        let output = "running 3 tests\n\
+                      test api::login ... ok\n\
+                      test api::logout ... FAILED\n\
+                      test db::slow ... ignored\n\
+                      test result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured; 0 filtered out\n\
+                      running 2 tests\n\
+                      test result: ok. 2 passed; 0 failed; 0 ignored; 0 measured; 0 filtered out\n";
+        let results = parse(TestFormat::Auto, output).unwrap();
+        assert_eq!(results.format, TestFormat::Cargo);
+        assert_eq!((results.passed, results.failed, results.skipped, results.total), (3, 1, 1, 5));
+        assert_eq!(results.failed_tests, vec!["api::logout"]);
+    }
+
+    #[test]
+    fn test_pytest() {
+        let output = "tests/test_api.py::test_login PASSED\n\
+                      tests/test_api.py::test_logout FAILED\n\
+                      =========== short test summary info ===========\n\
+                      FAILED tests/test_api.py::test_logout - AssertionError: 401\n\
+                      ERROR tests/test_db.py::test_connect - ConnectionError\n\
+                      ===== 1 failed, 4 passed, 2 skipped, 1 error in 0.52s =====\n";
+        let results = parse(TestFormat::Auto, output).unwrap();
+        assert_eq!(results.format, TestFormat::Pytest);
+        assert_eq!((results.passed, results.failed, results.skipped, results.total), (4, 2, 2, 8));
+        assert_eq!(results.failed_tests, vec!["tests/test_api.py::test_logout", "tests/test_db.py::test_connect"]);
+    }
+
+    #[test]
+    fn test_jest() {
+        let output = "FAIL src/cart.test.js\n  ● Cart › applies discounts\n\n    expect(received).toBe(expected)\n\n\
+                      Test Suites: 1 failed, 3 passed, 4 total\n\
+                      Tests:       1 failed, 1 skipped, 10 passed, 12 total\n";
+        let results = parse(TestFormat::Auto, output).unwrap();
+        assert_eq!(results.format, TestFormat::Jest);
+        assert_eq!((results.passed, results.failed, results.skipped, results.total), (10, 1, 1, 12));
+        assert_eq!(results.failed_tests, vec!["Cart › applies discounts"]);
+    }
+
+    #[test]
+    fn test_junit() {
+        let output = r#"<?xml version="1.0"?>
+            <testsuite name="api" tests="3">
+              <testcase classname="api.AuthTest" name="login" time="0.1"/>
+              <testcase classname="api.AuthTest" name="logout"><failure message="401">trace</failure></testcase>
+              <testcase classname="api.AuthTest" name="sso"><skipped/></testcase>
+            </testsuite>"#;
+        let results = parse(TestFormat::Auto, output).unwrap();
+        assert_eq!((results.passed, results.failed, results.skipped, results.total), (1, 1, 1, 3));
+        assert_eq!(results.failed_tests, vec!["api.AuthTest.logout"]);
+
+        assert!(parse(TestFormat::Auto, "All good!").is_none());
+        assert!(parse(TestFormat::Cargo, output).is_none());
+    }
+}
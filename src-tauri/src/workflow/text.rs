@@ -0,0 +1,63 @@
+//! Shortening text for summaries, previews and prompts.
+//!
+//! Lengths are counted in graphemes, the characters a reader sees, so a
+//! cut never lands inside a multi-byte character, an emoji sequence or a
+//! combining mark, and CJK text gets as many characters as Latin text.
+//! Provides:
+//! - The first or last `max` graphemes of some text
+//! - Truncation with a marker appended when anything was cut
+
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Graphemes in `text`
+pub fn grapheme_count(text: &str) -> usize {
+    text.graphemes(true).count()
+}
+
+/// The first `max` graphemes of `text`
+pub fn prefix(text: &str, max: usize) -> &str {
+    match text.grapheme_indices(true).nth(max) {
+        Some((cut, _)) => &text[..cut],
+        None => text,
+    }
+}
+
+/// The last `max` graphemes of `text`
+pub fn suffix(text: &str, max: usize) -> &str {
+    if max == 0 {
+        return "";
+    }
+    match text.grapheme_indices(true).nth_back(max - 1) {
+        Some((cut, _)) => &text[cut..],
+        None => text,
+    }
+}
+
+/// At most `max` graphemes of `text`, followed by `marker` when it was cut
+pub fn truncate(text: &str, max: usize, marker: &str) -> String {
+    let kept = prefix(text, max);
+    if kept.len() == text.len() {
+        text.to_string()
+    } else {
+        format!("{}{}", kept, marker)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multibyte_boundaries() {
+        assert_eq!(truncate("hello world", 5, "..."), "hello...");
+        assert_eq!(truncate("hello", 5, "..."), "hello");
+        assert_eq!(truncate("数据流调试工具", 4, "…"), "数据流调…");
+        // An accent written as a combining mark stays with its letter
+        assert_eq!(prefix("cafe\u{301} noir", 4), "cafe\u{301}");
+        assert_eq!(prefix("👩‍💻👍", 1), "👩‍💻");
+        assert_eq!(suffix("日本語のテキスト", 3), "キスト");
+        assert_eq!(suffix("abc", 10), "abc");
+        assert_eq!(suffix("abc", 0), "");
+        assert_eq!(grapheme_count("👩‍💻 ok"), 4);
+    }
+}
@@ -0,0 +1,306 @@
+//! Token counts for prompts and answers.
+//!
+//! Text is split the way tiktoken's pre-tokenizer splits it, then each
+//! piece is byte-pair encoded with the merge table of the model's encoding.
+//! Provides:
+//! - The encoding each model uses
+//! - Loading tiktoken's `.tiktoken` merge tables from the tokenizers
+//!   directory, once per encoding
+//! - Exact counts where the table is installed, and estimates from the
+//!   split pieces where it isn't
+//!
+//! No tables ship with the app: copy e.g. `cl100k_base.tiktoken` into
+//! [`Tokenizers::default_dir`], or point `NEXUS_TOKENIZERS_DIR` elsewhere.
+//! Claude's vocabulary isn't published, so counts for Claude models, the
+//! default, are always estimates; every count carries
+//! [`TokenCount::exact`] so callers can label it as one.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use dashmap::DashMap;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A family of models sharing one vocabulary
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Encoding {
+    /// GPT-4 and GPT-3.5
+    Cl100kBase,
+    /// GPT-4o, GPT-4.1 and the o-series
+    O200kBase,
+    /// Claude models, which the agents run on by default
+    Claude,
+}
+
+impl Encoding {
+    /// The encoding of `model`; unknown and unset models are taken to be
+    /// Claude models
+    pub fn for_model(model: Option<&str>) -> Self {
+        let model = model.unwrap_or_default().to_lowercase();
+        let model = model.rsplit('/').next().unwrap_or_default();
+        if ["gpt-4o", "gpt-4.1", "gpt-5", "o1", "o3", "o4"].iter().any(|prefix| model.starts_with(prefix)) {
+            Encoding::O200kBase
+        } else if ["gpt-4", "gpt-3.5", "text-embedding"].iter().any(|prefix| model.starts_with(prefix)) {
+            Encoding::Cl100kBase
+        } else {
+            Encoding::Claude
+        }
+    }
+
+    /// File name of the merge table, without extension
+    pub fn name(&self) -> &'static str {
+        match self {
+            Encoding::Cl100kBase => "cl100k_base",
+            Encoding::O200kBase => "o200k_base",
+            Encoding::Claude => "claude",
+        }
+    }
+
+    /// Letters a token covers on average, for estimates. Larger vocabularies
+    /// keep more words whole.
+    fn letters_per_token(&self) -> usize {
+        match self {
+            Encoding::Cl100kBase => 5,
+            Encoding::O200kBase => 6,
+            Encoding::Claude => 4,
+        }
+    }
+}
+
+/// A byte-pair merge table: each token's bytes and its rank, lower ranks
+/// merging first
+pub struct BpeTable {
+    ranks: HashMap<Vec<u8>, u32>,
+}
+
+impl BpeTable {
+    /// Parse tiktoken's format, one base64 token and its rank per line
+    pub fn parse(contents: &str) -> Result<Self, String> {
+        let mut ranks = HashMap::new();
+        for (number, line) in contents.lines().enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+            let invalid = || format!("Invalid merge table line {}", number + 1);
+            let (token, rank) = line.trim().split_once(' ').ok_or_else(invalid)?;
+            let token = BASE64.decode(token).map_err(|_| invalid())?;
+            let rank = rank.parse::<u32>().map_err(|_| invalid())?;
+            ranks.insert(token, rank);
+        }
+        Ok(Self { ranks })
+    }
+
+    /// Tokens `piece` encodes to
+    fn count(&self, piece: &[u8]) -> usize {
+        if piece.len() < 2 || self.ranks.contains_key(piece) {
+            return piece.len().min(1);
+        }
+        // Token boundaries, starting at single bytes; merge the adjacent
+        // pair of lowest rank until no pair is a token
+        let mut bounds: Vec<usize> = (0..=piece.len()).collect();
+        while let Some((_, i)) = (0..bounds.len() - 2)
+            .filter_map(|i| self.ranks.get(&piece[bounds[i]..bounds[i + 2]]).map(|rank| (*rank, i)))
+            .min()
+        {
+            bounds.remove(i + 1);
+        }
+        bounds.len() - 1
+    }
+}
+
+/// Merge tables by encoding, loaded on first use
+#[derive(Default)]
+pub struct Tokenizers {
+    tables: DashMap<Encoding, Option<Arc<BpeTable>>>,
+}
+
+impl Tokenizers {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `NEXUS_TOKENIZERS_DIR`, or `tokenizers` in the app's data directory
+    pub fn default_dir() -> PathBuf {
+        std::env::var_os("NEXUS_TOKENIZERS_DIR").map(PathBuf::from).unwrap_or_else(|| {
+            dirs::data_local_dir()
+                .unwrap_or_else(std::env::temp_dir)
+                .join("nexus")
+                .join("tokenizers")
+        })
+    }
+
+    /// The table of `encoding`, if one is installed
+    pub fn table(&self, encoding: Encoding) -> Option<Arc<BpeTable>> {
+        self.tables
+            .entry(encoding)
+            .or_insert_with(|| {
+                let path = Self::default_dir().join(format!("{}.tiktoken", encoding.name()));
+                let contents = std::fs::read_to_string(&path).ok()?;
+                match BpeTable::parse(&contents) {
+                    Ok(table) => Some(Arc::new(table)),
+                    Err(e) => {
+                        log::warn!("Ignoring merge table {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
+
+    /// Tokens in `text` for `encoding`
+    pub fn count(&self, text: &str, encoding: Encoding) -> TokenCount {
+        let table = self.table(encoding);
+        let tokens = pre_tokenize(text)
+            .into_iter()
+            .map(|piece| match &table {
+                Some(table) => table.count(piece.as_bytes()),
+                None => estimate(piece, encoding),
+            })
+            .sum();
+        TokenCount {
+            tokens,
+            encoding,
+            exact: table.is_some(),
+        }
+    }
+}
+
+lazy_static! {
+    pub static ref TOKENIZERS: Tokenizers = Tokenizers::new();
+}
+
+/// Tokens in some text
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenCount {
+    pub tokens: usize,
+    pub encoding: Encoding,
+    /// Whether it was counted with the encoding's merge table rather than
+    /// estimated
+    pub exact: bool,
+}
+
+/// Tokens `text` takes up for `model`, exact or estimated; use
+/// [`Tokenizers::count`] where the difference is shown
+pub fn count_tokens(text: &str, model: Option<&str>) -> usize {
+    TOKENIZERS.count(text, Encoding::for_model(model)).tokens
+}
+
+/// Tokens one piece is likely to take without a merge table: letters by
+/// the encoding's average, digits in threes, and a token for each other
+/// character, as punctuation and non-Latin text are split finely
+fn estimate(piece: &str, encoding: Encoding) -> usize {
+    let letters = piece.chars().filter(char::is_ascii_alphabetic).count();
+    let digits = piece.chars().filter(char::is_ascii_digit).count();
+    let others = piece.chars().filter(|c| !c.is_ascii_alphanumeric() && !c.is_whitespace()).count();
+    let tokens = letters.div_ceil(encoding.letters_per_token()) + digits.div_ceil(3) + others;
+    // A leading space or lone newline rides along with the piece
+    tokens.max(1)
+}
+
+/// Split text as tiktoken's cl100k pattern does: contractions, words with
+/// one leading non-letter, numbers of up to three digits, punctuation runs,
+/// and whitespace, keeping the space before a word with the word
+fn pre_tokenize(text: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let offset = |i: usize| chars.get(i).map_or(text.len(), |(offset, _)| *offset);
+    let is_newline = |c: char| c == '\r' || c == '\n';
+    let is_punctuation = |c: char| !c.is_whitespace() && !c.is_alphanumeric();
+
+    let mut pieces = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i].1;
+        let next = chars.get(i + 1).map(|(_, c)| *c);
+
+        let contraction_len = if c == '\'' { contraction(&chars[i + 1..]) } else { None };
+        if let Some(len) = contraction_len {
+            i += 1 + len;
+        } else if c.is_alphabetic() || (!c.is_alphanumeric() && !is_newline(c) && next.is_some_and(char::is_alphabetic)) {
+            i += 1;
+            while i < chars.len() && chars[i].1.is_alphabetic() {
+                i += 1;
+            }
+        } else if c.is_numeric() {
+            while i < chars.len() && i - start < 3 && chars[i].1.is_numeric() {
+                i += 1;
+            }
+        } else if is_punctuation(c) || (c == ' ' && next.is_some_and(is_punctuation)) {
+            i += 1;
+            while i < chars.len() && is_punctuation(chars[i].1) {
+                i += 1;
+            }
+            while i < chars.len() && is_newline(chars[i].1) {
+                i += 1;
+            }
+        } else {
+            let mut end = i;
+            while end < chars.len() && chars[end].1.is_whitespace() {
+                end += 1;
+            }
+            i = match (i..end).rev().find(|&k| is_newline(chars[k].1)) {
+                // Up to the last newline
+                Some(newline) => newline + 1,
+                // The last space goes with the word after it
+                None if end < chars.len() && end - i > 1 => end - 1,
+                None => end,
+            };
+        }
+        pieces.push(&text[offset(start)..offset(i)]);
+    }
+    pieces
+}
+
+/// Length of the contraction `'s`, `'t`, `'re`, `'ve`, `'m`, `'ll` or `'d`
+/// that `rest` starts with, after the apostrophe
+fn contraction(rest: &[(usize, char)]) -> Option<usize> {
+    let first = rest.first()?.1.to_ascii_lowercase();
+    let second = rest.get(1).map(|(_, c)| c.to_ascii_lowercase());
+    match (first, second) {
+        ('r', Some('e')) | ('v', Some('e')) | ('l', Some('l')) => Some(2),
+        ('s' | 't' | 'm' | 'd', _) => Some(1),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pre_tokenize() {
+        assert_eq!(
+            pre_tokenize("Hello world, it's 12345!\n\n  fn  main()"),
+            vec!["Hello", " world", ",", " it", "'s", " ", "123", "45", "!\n\n", " ", " fn", " ", " main", "()"]
+        );
+    }
+
+    #[test]
+    fn test_bpe_count() {
+        let line = |token: &str, rank: u32| format!("{} {}", BASE64.encode(token), rank);
+        let mut lines: Vec<String> = (b'a'..=b'z').map(|b| line(&(b as char).to_string(), b as u32)).collect();
+        lines.push(line(" ", 1));
+        lines.extend([line("he", 200), line("ll", 201), line("hell", 202), line("hello", 203), line(" w", 204)]);
+        let table = BpeTable::parse(&lines.join("\n")).unwrap();
+
+        assert_eq!(table.count(b"hello"), 1);
+        assert_eq!(table.count(b"hellz"), 2);
+        assert_eq!(table.count(b" world"), 5);
+        assert!(BpeTable::parse("not-base64! x").is_err());
+    }
+
+    #[test]
+    fn test_estimates() {
+        assert_eq!(Encoding::for_model(Some("gpt-4o-mini")), Encoding::O200kBase);
+        assert_eq!(Encoding::for_model(Some("openai/gpt-4-turbo")), Encoding::Cl100kBase);
+        assert_eq!(Encoding::for_model(None), Encoding::Claude);
+
+        let tokenizers = Tokenizers::new();
+        tokenizers.tables.insert(Encoding::Claude, None);
+        let count = tokenizers.count("Implement the login form.", Encoding::Claude);
+        assert!(!count.exact);
+        // "Implement" 3, " the" 1, " login" 2, " form" 1, "." 1
+        assert_eq!(count.tokens, 8);
+    }
+}
@@ -12,12 +12,13 @@ import {
 } from 'lucide-react';
 import { StatusIndicator, ProgressBar } from '../common';
 import type { AgentRole } from '../../types';
+import type { NodeExecutionStatus } from '../../services/tauri';
 
 interface AgentNodeData {
   label: string;
   agentRole: AgentRole;
   agentId?: string;
-  status?: 'pending' | 'running' | 'completed' | 'failed';
+  status?: NodeExecutionStatus;
   progress?: number;
 }
 
@@ -49,10 +50,17 @@ function AgentNodeComponent({ data, selected }: NodeProps) {
 
   const statusToIndicator = {
     pending: 'idle',
+    // Waiting for a concurrency slot or a resource lock
+    queued: 'starting',
+    waiting_for_approval: 'paused',
     running: 'running',
     completed: 'completed',
     failed: 'failed',
-  } as const;
+    skipped: 'idle',
+  } as const satisfies Record<NodeExecutionStatus, string>;
+
+  const waitingLabel =
+    status === 'queued' ? 'Queued' : status === 'waiting_for_approval' ? 'Awaiting approval' : null;
 
   return (
     <div
@@ -93,6 +101,9 @@ function AgentNodeComponent({ data, selected }: NodeProps) {
           <span className="text-xs text-text-secondary capitalize">
             {nodeData.agentRole}
           </span>
+          {waitingLabel && (
+            <span className="block text-xs text-neon-orange">{waitingLabel}</span>
+          )}
         </div>
       </div>
 
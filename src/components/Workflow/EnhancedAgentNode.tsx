@@ -21,6 +21,7 @@ import {
   FileCode,
 } from 'lucide-react';
 import type { AgentRole } from '../../types';
+import type { NodeTokens } from '../../services/tauri';
 
 export interface EnhancedAgentNodeData {
   label: string;
@@ -31,7 +32,7 @@ export interface EnhancedAgentNodeData {
   // Enhanced data
   currentTask?: string;
   elapsedTime?: number; // seconds
-  tokenUsage?: { input: number; output: number };
+  tokenUsage?: NodeTokens;
   filesModified?: number;
   terminalOutput?: string[]; // last few lines
   errorMessage?: string;
@@ -219,9 +220,13 @@ function EnhancedAgentNodeComponent({ data, selected }: NodeProps) {
                   </div>
                 )}
                 {nodeData.tokenUsage && (
-                  <div className="flex items-center gap-1 text-[var(--text-tertiary)]">
+                  <div
+                    className="flex items-center gap-1 text-[var(--text-tertiary)]"
+                    title={nodeData.tokenUsage.exact ? 'Tokens used' : 'Estimated tokens used'}
+                  >
                     <Zap size={10} />
                     <span>
+                      {!nodeData.tokenUsage.exact && '~'}
                       {formatTokens(nodeData.tokenUsage.input)}↑{' '}
                       {formatTokens(nodeData.tokenUsage.output)}↓
                     </span>
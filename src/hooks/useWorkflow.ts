@@ -101,6 +101,52 @@ export function useWorkflowExecution() {
           case 'level_completed':
             console.log(`Level ${event.level} completed`);
             break;
+
+          case 'stage_started':
+            console.log(`Starting stage ${event.label} with nodes:`, event.node_ids);
+            break;
+
+          case 'stage_skipped':
+            console.log(`Stage ${event.stage_id} skipped: ${event.reason}`);
+            break;
+
+          case 'stage_completed':
+            console.log(`Stage ${event.stage_id} ${event.status} in ${event.duration_ms}ms`);
+            break;
+
+          case 'deadline_at_risk':
+            console.warn(
+              `Deadline at risk: projected ${event.projected_ms}ms vs deadline ${event.deadline_ms}ms`
+            );
+            break;
+
+          case 'assertion_evaluated':
+            if (!event.passed) {
+              console.warn(`Assertion ${event.node_id} failed on ${event.target}: ${event.message}`);
+            }
+            break;
+
+          case 'self_correction_round':
+            if (!event.passed) {
+              console.warn(
+                `Tests failed for ${event.node_id} (round ${event.round} of ${event.max_rounds}, exit ${event.exit_code})`
+              );
+            }
+            break;
+
+          case 'conflict_detected':
+            console.warn(
+              `Nodes ${event.first_node} and ${event.second_node} both edited ${event.files.join(', ')} (${event.resolution})`
+            );
+            break;
+
+          case 'workspace_ready':
+            console.info(`${event.changed_files} changed file(s) awaiting review in ${event.workspace_dir}`);
+            break;
+
+          case 'guardrail_triggered':
+            console.warn(`Guardrails held the output of ${event.node_id}; approve it to continue`, event.violations);
+            break;
         }
       });
     };
@@ -41,6 +41,7 @@ import type { Workflow } from '../types';
 import { useWorkflowCanvas, useWorkflowExecution } from '../hooks';
 import type { AgentRole, WorkflowNode } from '../types';
 import type { EnhancedAgentNodeData } from '../components/Workflow/EnhancedAgentNode';
+import type { NodeExecutionStatus } from '../services/tauri';
 import type { AgentMessage } from '../components/Workflow/MessagePanel';
 import { applyLayout, type LayoutPreset } from '../utils/layoutWorkflow';
 
@@ -140,14 +141,16 @@ export function EnhancedWorkflowEditor() {
 
         // Map status to timeline event types
         const statusToEventType = {
-          pending: 'blocked' as const,
-          running: 'running' as const,
-          completed: 'complete' as const,
-          failed: 'fail' as const,
-          skipped: 'blocked' as const,
-        };
-
-        const eventType = statusToEventType[nodeStatus.status as keyof typeof statusToEventType] || 'blocked';
+          pending: 'blocked',
+          queued: 'blocked',
+          waiting_for_approval: 'pause',
+          running: 'running',
+          completed: 'complete',
+          failed: 'fail',
+          skipped: 'blocked',
+        } as const satisfies Record<NodeExecutionStatus, string>;
+
+        const eventType = statusToEventType[nodeStatus.status] ?? 'blocked';
 
         events.push({
           type: eventType,
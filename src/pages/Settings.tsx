@@ -56,7 +56,7 @@ export function Settings() {
       try {
         const [dbStatus, agents, projects, workflows] = await Promise.all([
           tauri.getDatabaseStatus().catch(() => ({ connected: false })),
-          tauri.listAgents().catch(() => []),
+          tauri.listAgents().then((list) => list.agents).catch(() => []),
           tauri.listProjects().catch(() => []),
           tauri.listWorkflows().catch(() => []),
         ]);
@@ -9,6 +9,9 @@ import type {
   Workflow,
   CreateWorkflowRequest,
   ExecuteWorkflowRequest,
+  EnvVar,
+  ExecutionConcurrency,
+  ModelPins,
   AgentOutput,
   SystemStatus,
   DatabaseStatus,
@@ -23,8 +26,32 @@ export async function killAgent(agentId: string): Promise<void> {
   return invoke('kill_agent', { agentId });
 }
 
-export async function listAgents(): Promise<Agent[]> {
-  return invoke('list_agents');
+export type AgentSortField = 'name' | 'role' | 'status' | 'started_at' | 'runtime' | 'last_output';
+
+export interface AgentFilter {
+  statuses?: string[];
+  roles?: string[];
+  project_id?: string;
+  execution_id?: string;
+  min_age_secs?: number;
+  max_age_secs?: number;
+}
+
+export interface AgentListQuery extends AgentFilter {
+  sort_by?: AgentSortField;
+  descending?: boolean;
+  offset?: number;
+  limit?: number;
+}
+
+export interface AgentList {
+  agents: Agent[];
+  total: number;
+}
+
+// Filtered, sorted and paged agents; all agents without a query
+export async function listAgents(query?: AgentListQuery): Promise<AgentList> {
+  return invoke('list_agents', { query });
 }
 
 export async function getAgent(agentId: string): Promise<Agent> {
@@ -39,6 +66,11 @@ export async function restartAgent(agentId: string): Promise<Agent> {
   return invoke('restart_agent', { agentId });
 }
 
+// Resume an agent interrupted by an app restart in its Claude Code session
+export async function reattachAgent(agentId: string): Promise<Agent> {
+  return invoke('reattach_agent', { agentId });
+}
+
 export async function getAgentOutput(agentId: string): Promise<string> {
   return invoke('get_agent_output', { agentId });
 }
@@ -55,6 +87,33 @@ export async function resumeAgent(agentId: string): Promise<void> {
   return invoke('resume_agent', { agentId });
 }
 
+export interface BulkAgentFailure {
+  agent_id: string;
+  error: string;
+}
+
+export interface RestartAgentsResult {
+  restarted: { previous_id: string; agent: Agent }[];
+  failed: BulkAgentFailure[];
+}
+
+// Kill every unfinished standalone agent matching the filter (an empty
+// filter needs `all`); returns the killed IDs
+export async function killAgents(filter: AgentFilter, all = false): Promise<string[]> {
+  return invoke('kill_agents', { filter, all });
+}
+
+// Restart every matching standalone agent with its original config; an
+// empty filter needs `all`
+export async function restartAgents(filter: AgentFilter, all = false): Promise<RestartAgentsResult> {
+  return invoke('restart_agents', { filter, all });
+}
+
+// Forget agents that finished at least `olderThanSecs` ago; returns the removed IDs
+export async function cleanupFinishedAgents(olderThanSecs: number): Promise<string[]> {
+  return invoke('cleanup_finished_agents', { olderThanSecs });
+}
+
 // Project Commands
 export async function createProject(request: CreateProjectRequest): Promise<Project> {
   return invoke('create_project', { request });
@@ -80,6 +139,132 @@ export async function getProjectsBaseDirectory(): Promise<string> {
   return invoke('get_projects_base_directory');
 }
 
+// A git repository found by scanForProjects
+export interface ProjectCandidate {
+  path: string;
+  name: string;
+  languages: string[];
+  imported: boolean;
+}
+
+export interface ProjectImport {
+  path: string;
+  name?: string;
+  description?: string;
+}
+
+export interface ImportResult {
+  imported: Project[];
+  skipped: { path: string; reason: string }[];
+}
+
+// Find git repositories under a directory
+export async function scanForProjects(rootPath: string, maxDepth?: number): Promise<ProjectCandidate[]> {
+  return invoke('scan_for_projects', { rootPath, maxDepth });
+}
+
+// Import repositories as projects
+export async function importProjects(projects: ProjectImport[]): Promise<ImportResult> {
+  return invoke('import_projects', { projects });
+}
+
+// Check a path could be the projects base directory
+export async function validateProjectsBaseDirectory(path: string): Promise<string> {
+  return invoke('validate_projects_base_directory', { path });
+}
+
+// 'move' moves the projects in the old base directory along; 'repoint'
+// only updates their paths, for files the user moved already
+export type RelocateMode = 'move' | 'repoint';
+
+export interface ProjectRelocation {
+  project_id: string;
+  from: string;
+  to: string;
+}
+
+export interface BaseDirectoryChange {
+  baseDirectory: string;
+  relocated: ProjectRelocation[];
+  warnings: string[];
+}
+
+// Change the projects base directory, relocating existing projects if asked
+export async function setProjectsBaseDirectory(
+  path: string,
+  relocate?: RelocateMode
+): Promise<BaseDirectoryChange> {
+  return invoke('set_projects_base_directory', { path, relocate });
+}
+
+// Each source file's imports of other project files
+export interface DependencyGraph {
+  imports: Record<string, string[]>;
+}
+
+// A changed file (distance 0) or a file depending on one
+export interface ImpactedFile {
+  path: string;
+  distance: number;
+  via: string | null;
+}
+
+export async function analyzeProjectDependencies(projectId: string): Promise<DependencyGraph> {
+  return invoke('analyze_project_dependencies', { projectId });
+}
+
+// Without a diff, the project's uncommitted changes are used
+export async function getImpactedFiles(projectId: string, diff?: string, maxDepth?: number): Promise<ImpactedFile[]> {
+  return invoke('get_impacted_files', { projectId, diff, maxDepth });
+}
+
+// A package of a Cargo, npm/pnpm/yarn or Go workspace
+export interface Package {
+  name: string;
+  path: string;
+  kind: 'cargo' | 'node' | 'go';
+  dependencies: string[];
+}
+
+// Every filter set must match; affected_only uses the uncommitted changes
+export interface PackageSelection {
+  names?: string[];
+  depends_on?: string | null;
+  affected_only?: boolean;
+}
+
+export async function detectProjectPackages(projectId: string): Promise<Package[]> {
+  return invoke('detect_project_packages', { projectId });
+}
+
+// Where a project's agents may connect. Restricted projects' agents go
+// through a local proxy; the model API and loopback stay reachable, and
+// refused connections are recorded in the audit log.
+export interface NetworkPolicy {
+  mode: 'open' | 'deny_all' | 'allowlist';
+  // 'example.com' allows it and its subdomains, '*.example.com' only the subdomains
+  allowed_domains?: string[];
+}
+
+export async function setNetworkPolicy(projectId: string, policy: NetworkPolicy): Promise<NetworkPolicy> {
+  return invoke('set_network_policy', { projectId, policy });
+}
+
+export async function getNetworkPolicy(projectId: string): Promise<NetworkPolicy> {
+  return invoke('get_network_policy', { projectId });
+}
+
+// Runs the workflow once per selected package; returns the batch ID
+export async function fanOutPackages(request: {
+  workflow_id: string;
+  project_id: string;
+  input_prompt: string;
+  selection?: PackageSelection;
+  max_concurrent?: number;
+}): Promise<string> {
+  return invoke('fan_out_packages', { request });
+}
+
 // Workflow Commands
 export async function createWorkflow(request: CreateWorkflowRequest): Promise<Workflow> {
   return invoke('create_workflow', { request });
@@ -97,6 +282,44 @@ export async function executeWorkflow(request: ExecuteWorkflowRequest): Promise<
   return invoke('execute_workflow', { request });
 }
 
+// Saved arguments for running a workflow; omit `id` to create one
+export interface RunPresetInput {
+  id?: string;
+  workflow_id: string;
+  name: string;
+  project_id: string;
+  // May contain {{name}} placeholders for the variables
+  input_prompt: string;
+  variables?: Record<string, unknown>;
+  // By node ID, as executeEnhancedWorkflow takes them
+  node_configs?: Record<string, unknown>;
+}
+
+export interface RunPreset extends Required<Omit<RunPresetInput, 'id'>> {
+  id: string;
+  created_at: string;
+  updated_at: string;
+  last_run_at: string | null;
+  last_execution_id: string | null;
+}
+
+export async function saveRunPreset(preset: RunPresetInput): Promise<RunPreset> {
+  return invoke('save_run_preset', { preset });
+}
+
+export async function listRunPresets(workflowId?: string): Promise<RunPreset[]> {
+  return invoke('list_run_presets', { workflowId });
+}
+
+export async function deleteRunPreset(presetId: string): Promise<boolean> {
+  return invoke('delete_run_preset', { presetId });
+}
+
+// Returns the execution ID
+export async function runPreset(presetId: string): Promise<string> {
+  return invoke('run_preset', { presetId });
+}
+
 export async function cancelWorkflowExecution(executionId: string): Promise<boolean> {
   return invoke('cancel_workflow_execution', { executionId });
 }
@@ -116,15 +339,110 @@ export interface WorkflowExecutionStatus {
   progress: number;
   started_at: string;
   completed_at?: string;
+  cancel_reason?: string;
 }
 
 export async function getWorkflowExecutionStatus(executionId: string): Promise<WorkflowExecutionStatus> {
   return invoke('get_workflow_execution_status', { executionId });
 }
 
+// Forget executions that finished at least `olderThanSecs` ago, with their logs and messages; returns the removed IDs
+export async function purgeCompletedExecutions(olderThanSecs: number): Promise<string[]> {
+  return invoke('purge_completed_executions', { olderThanSecs });
+}
+
+// A node held in 'waiting_for_approval' until someone decides on it
+export interface ApprovalRequest {
+  execution_id: string;
+  node_id: string;
+  // What is being approved, e.g. the files a staged patch changes
+  summary?: string;
+  requested_at: string;
+}
+
+export async function listPendingApprovals(executionId?: string): Promise<ApprovalRequest[]> {
+  return invoke('list_pending_approvals', { executionId });
+}
+
+// Refused while a supply-chain check of the execution flags dependencies
+export async function approveNode(executionId: string, nodeId: string): Promise<void> {
+  return invoke('approve_node', { executionId, nodeId });
+}
+
+// A package version a lockfile gained during an execution
+export interface AddedDependency {
+  lockfile: string;
+  ecosystem: 'crates_io' | 'npm' | 'pypi' | 'go';
+  name: string;
+  version: string;
+  previous_version?: string;
+  // OSV advisory IDs
+  advisories: string[];
+  // Not in its registry
+  unknown: boolean;
+}
+
+export interface SupplyChainReport {
+  execution_id: string;
+  node_id: string;
+  lockfiles: string[];
+  added: AddedDependency[];
+  checked_at: string;
+}
+
+export async function getSupplyChainReports(executionId: string): Promise<SupplyChainReport[]> {
+  return invoke('get_supply_chain_reports', { executionId });
+}
+
+// Fails the node with the reason
+export async function rejectNode(executionId: string, nodeId: string, reason?: string): Promise<void> {
+  return invoke('reject_node', { executionId, nodeId, reason });
+}
+
+export interface ExecutionGroup {
+  name: string;
+  running?: string;
+  // In the order they will run
+  queued: string[];
+}
+
+export async function listExecutionGroups(): Promise<ExecutionGroup[]> {
+  return invoke('list_execution_groups');
+}
+
+export interface SchemaViolation {
+  /** JSON Pointer into the graph */
+  path: string;
+  message: string;
+}
+
+export interface GraphEdge {
+  id: string;
+  source: string;
+  target: string;
+  data_type: string | null;
+}
+
+// Named group of nodes in a workflow graph
+export interface GraphStage {
+  id: string;
+  label?: string;
+  nodes: string[];
+  // Show the stage as a single node
+  collapsed?: boolean;
+}
+
+export interface CycleDiagnosis {
+  /** Node IDs around the cycle; the last leads back to the first */
+  cycle: string[];
+  /** Minimal set of edges whose removal breaks every cycle */
+  breaking_edges: GraphEdge[];
+}
+
 export interface WorkflowValidationResult {
   is_valid: boolean;
   has_cycle: boolean;
+  cycle: CycleDiagnosis | null;
   disconnected_nodes: string[];
   root_nodes: string[];
   leaf_nodes: string[];
@@ -132,12 +450,17 @@ export interface WorkflowValidationResult {
   total_edges: number;
   execution_levels: number | null;
   error: string | null;
+  schema_errors: SchemaViolation[];
 }
 
-export async function validateWorkflow(graph: { nodes: unknown[]; edges: unknown[] }): Promise<WorkflowValidationResult> {
+export async function validateWorkflow(graph: { nodes: unknown[]; edges: unknown[]; stages?: GraphStage[] }): Promise<WorkflowValidationResult> {
   return invoke('validate_workflow', { graph });
 }
 
+export async function getWorkflowGraphSchema(): Promise<Record<string, unknown>> {
+  return invoke('get_workflow_graph_schema');
+}
+
 // System Commands
 export async function getSystemStatus(): Promise<SystemStatus> {
   return invoke('get_system_status');
@@ -147,6 +470,172 @@ export async function getDatabaseStatus(): Promise<DatabaseStatus> {
   return invoke('get_database_status');
 }
 
+// Replay queued database writes now; resolves to how many are still queued
+export async function replayDatabaseWrites(): Promise<number> {
+  return invoke('replay_database_writes');
+}
+
+// The migrated counts are projects and workflows created offline and
+// copied into the database when it first connected
+export interface DatabaseConnectivity {
+  connected: boolean;
+  error: string | null;
+  migrated_projects: number;
+  migrated_workflows: number;
+  changed_at: string;
+}
+
+// Fired when the database connects, disconnects or comes back
+export function onDatabaseConnectivity(callback: (connectivity: DatabaseConnectivity) => void): Promise<UnlistenFn> {
+  return listen<DatabaseConnectivity>('database-connectivity', (event) => callback(event.payload));
+}
+
+// Announced by another NEXUS instance sharing the database
+export type InstanceEvent =
+  | { type: 'workflow_saved'; workflow_id: string }
+  | { type: 'execution_started'; execution_id: string; workflow_id: string; workflow_name: string; project_id: string }
+  | { type: 'execution_finished'; execution_id: string }
+  | { type: 'cancel_execution'; execution_id: string; reason: string };
+
+export interface InstanceNotification {
+  instance_id: string;
+  event: InstanceEvent;
+}
+
+// Saved workflows are already in listWorkflows() by the time this fires
+export function onInstanceEvent(callback: (notification: InstanceNotification) => void): Promise<UnlistenFn> {
+  return listen<InstanceNotification>('instance-event', (event) => callback(event.payload));
+}
+
+// Result of one environment check
+export interface DiagnosticCheck {
+  id: string;
+  name: string;
+  status: 'pass' | 'warn' | 'fail';
+  message: string;
+  hint?: string;
+}
+
+export interface DiagnosticsReport {
+  checks: DiagnosticCheck[];
+  status: 'pass' | 'warn' | 'fail';
+  ran_at: string;
+}
+
+// Check CLIs, database, data directories and the API port
+export async function runDiagnostics(): Promise<DiagnosticsReport> {
+  return invoke('run_diagnostics');
+}
+
+// An execution that quitting would interrupt
+export interface ActiveExecution {
+  execution_id: string;
+  workflow_id: string;
+  progress: number;
+}
+
+export interface ShutdownReport {
+  cancelled_executions: string[];
+  checkpoints_saved: number;
+  agents_stopped: number;
+  agents_detached: number;
+}
+
+// Executions still running
+export async function getActiveExecutions(): Promise<ActiveExecution[]> {
+  return invoke('get_active_executions');
+}
+
+// Checkpoint and cancel running executions, stop agents, then quit
+export async function shutdownApp(reason?: string): Promise<ShutdownReport> {
+  return invoke('shutdown_app', { reason });
+}
+
+// Set by an emergency stop; spawning stays off until resumed with the code
+export interface SpawnLock {
+  reason: string;
+  confirmation_code: string;
+  locked_at: string;
+}
+
+export interface EmergencyStopReport {
+  lock: SpawnLock;
+  agents_stopped: number;
+  cancelled_executions: string[];
+  cancelled_batches: number;
+  cleared_queued_tasks: number;
+  checkpoints_saved: number;
+}
+
+// Kill every agent, cancel executions and queued work, and disable spawning
+export async function emergencyStop(reason?: string): Promise<EmergencyStopReport> {
+  return invoke('emergency_stop', { reason });
+}
+
+// Current spawn lock, or null while spawning is allowed
+export async function getSpawnLock(): Promise<SpawnLock | null> {
+  return invoke('get_spawn_lock');
+}
+
+// Re-enable spawning with the confirmation code from the stop report
+export async function resumeSpawning(confirmation: string): Promise<void> {
+  return invoke('resume_spawning', { confirmation });
+}
+
+export function onEmergencyStop(callback: (report: EmergencyStopReport) => void): Promise<UnlistenFn> {
+  return listen<EmergencyStopReport>('emergency-stop', (event) => callback(event.payload));
+}
+
+// The local HTTP API server; port differs from preferred_port when that was taken
+export interface ApiServerInfo {
+  running: boolean;
+  preferred_port: number;
+  port: number | null;
+  url: string | null;
+  error: string | null;
+  started_at: string | null;
+}
+
+export async function getApiServerInfo(): Promise<ApiServerInfo> {
+  return invoke('get_api_server_info');
+}
+
+// Restart with the current settings; resolves once a port is bound or binding failed
+export async function restartApiServer(): Promise<ApiServerInfo> {
+  return invoke('restart_api_server');
+}
+
+// Fired when the API server starts, stops or fails
+export function onApiServerStatus(callback: (info: ApiServerInfo) => void): Promise<UnlistenFn> {
+  return listen<ApiServerInfo>('api-server-status', (event) => callback(event.payload));
+}
+
+// Fired when no port from the preferred one on could be bound
+export function onApiServerBindFailed(callback: (info: ApiServerInfo) => void): Promise<UnlistenFn> {
+  return listen<ApiServerInfo>('api-server-bind-failed', (event) => callback(event.payload));
+}
+
+// A nexus:// link handled by the app
+export interface DeepLinkResult {
+  url: string;
+  link?:
+    | { action: 'execute'; workflow_id: string; project_id: string; prompt: string }
+    | { action: 'run_preset'; preset_id: string }
+    | { action: 'open'; path: string };
+  execution_id?: string;
+  error?: string;
+}
+
+// Fired for every nexus:// link, including ones passed to a second launch
+export function onDeepLink(callback: (result: DeepLinkResult) => void): Promise<UnlistenFn> {
+  return listen<DeepLinkResult>('deep-link', (event) => callback(event.payload));
+}
+
+// Fired when closing would interrupt executions; confirm with shutdownApp()
+export function onShutdownRequested(callback: (active: ActiveExecution[]) => void): Promise<UnlistenFn> {
+  return listen<ActiveExecution[]>('shutdown-requested', (event) => callback(event.payload));
+}
+
 // Event Listeners
 export function onAgentOutput(callback: (output: AgentOutput) => void): Promise<UnlistenFn> {
   return listen<AgentOutput>('agent-output', (event) => callback(event.payload));
@@ -177,7 +666,7 @@ export function onWorkflowExecutionStarted(
 }
 
 // Workflow Event Types
-export type NodeExecutionStatus = 'pending' | 'running' | 'completed' | 'failed' | 'skipped';
+export type NodeExecutionStatus = 'pending' | 'queued' | 'waiting_for_approval' | 'running' | 'completed' | 'failed' | 'skipped';
 
 export interface WorkflowEventExecutionStarted {
   type: 'execution_started';
@@ -223,6 +712,21 @@ export interface WorkflowEventNodeSkipped {
   execution_id: string;
   node_id: string;
   reason: string;
+  // Set when the node's condition skipped it
+  condition?: ConditionResult;
+}
+
+// A value a condition looked at while being evaluated
+export type ConsultedValue =
+  | { kind: 'variable'; name: string; value?: unknown }
+  | { kind: 'node_status'; node_id: string; status?: string }
+  | { kind: 'output'; node_id: string; preview?: string; tags: string[] };
+
+export interface ConditionResult {
+  should_execute: boolean;
+  reason: string;
+  evaluated_conditions: string[];
+  consulted: ConsultedValue[];
 }
 
 export interface WorkflowEventLevelStarted {
@@ -238,6 +742,33 @@ export interface WorkflowEventLevelCompleted {
   level: number;
 }
 
+export interface WorkflowEventStageStarted {
+  type: 'stage_started';
+  execution_id: string;
+  stage_id: string;
+  label: string;
+  node_ids: string[];
+}
+
+export interface WorkflowEventStageSkipped {
+  type: 'stage_skipped';
+  execution_id: string;
+  stage_id: string;
+  reason: string;
+  condition: ConditionResult;
+}
+
+export interface WorkflowEventStageCompleted {
+  type: 'stage_completed';
+  execution_id: string;
+  stage_id: string;
+  // Member statuses collapsed into one
+  status: NodeExecutionStatus;
+  duration_ms: number;
+  // Aggregated output, when the stage aggregates
+  output: string | null;
+}
+
 export interface WorkflowEventProgressUpdate {
   type: 'progress_update';
   execution_id: string;
@@ -265,6 +796,67 @@ export interface WorkflowEventExecutionCancelled {
   type: 'execution_cancelled';
   execution_id: string;
   workflow_id: string;
+  reason?: string;
+}
+
+export interface WorkflowEventDeadlineAtRisk {
+  type: 'deadline_at_risk';
+  execution_id: string;
+  deadline_ms: number;
+  elapsed_ms: number;
+  projected_ms: number;
+  critical_path: string[];
+}
+
+export interface WorkflowEventAssertionEvaluated {
+  type: 'assertion_evaluated';
+  execution_id: string;
+  node_id: string;
+  target: string;
+  passed: boolean;
+  message: string;
+  score: number | null;
+}
+
+export interface WorkflowEventSelfCorrectionRound {
+  type: 'self_correction_round';
+  execution_id: string;
+  node_id: string;
+  round: number;
+  max_rounds: number;
+  passed: boolean;
+  exit_code: number | null;
+}
+
+export interface WorkflowEventConflictDetected {
+  type: 'conflict_detected';
+  execution_id: string;
+  level: number;
+  first_node: string;
+  second_node: string;
+  files: string[];
+  resolution: 'report' | 'serialize' | 'merge';
+}
+
+export interface WorkflowEventWorkspaceReady {
+  type: 'workspace_ready';
+  execution_id: string;
+  workspace_dir: string;
+  changed_files: number;
+}
+
+export interface WorkflowEventSectionPublished {
+  type: 'section_published';
+  execution_id: string;
+  node_id: string;
+  section: string;
+}
+
+export interface WorkflowEventGuardrailTriggered {
+  type: 'guardrail_triggered';
+  execution_id: string;
+  node_id: string;
+  violations: GuardrailViolation[];
 }
 
 export type WorkflowEvent =
@@ -276,10 +868,20 @@ export type WorkflowEvent =
   | WorkflowEventNodeSkipped
   | WorkflowEventLevelStarted
   | WorkflowEventLevelCompleted
+  | WorkflowEventStageStarted
+  | WorkflowEventStageSkipped
+  | WorkflowEventStageCompleted
   | WorkflowEventProgressUpdate
   | WorkflowEventExecutionCompleted
   | WorkflowEventExecutionFailed
-  | WorkflowEventExecutionCancelled;
+  | WorkflowEventExecutionCancelled
+  | WorkflowEventDeadlineAtRisk
+  | WorkflowEventAssertionEvaluated
+  | WorkflowEventSelfCorrectionRound
+  | WorkflowEventConflictDetected
+  | WorkflowEventWorkspaceReady
+  | WorkflowEventSectionPublished
+  | WorkflowEventGuardrailTriggered;
 
 export function onWorkflowEvent(callback: (event: WorkflowEvent) => void): Promise<UnlistenFn> {
   return listen<WorkflowEvent>('workflow-event', (event) => callback(event.payload));
@@ -325,8 +927,16 @@ export interface RetryConfig {
   initial_delay_ms?: number;
   max_delay_ms?: number;
   backoff_multiplier?: number;
+  /** How a retry's prompt differs from the failed attempt's */
+  prompt_mode?: RetryPromptMode;
 }
 
+// Retries repeat the prompt, or append the failure to it. Templates may use
+// {attempt}, {error} and {output}.
+export type RetryPromptMode =
+  | { type: 'same' }
+  | { type: 'with_feedback'; template?: string };
+
 // Condition types for conditional execution
 export type ConditionType =
   | 'always'
@@ -336,7 +946,8 @@ export type ConditionType =
   | 'all_predecessors_succeeded'
   | 'any_predecessor_succeeded'
   | 'variable_equals'
-  | 'variable_truthy';
+  | 'variable_truthy'
+  | 'output_tagged';
 
 // Node configuration for enhanced execution
 export interface EnhancedNodeConfig {
@@ -345,17 +956,89 @@ export interface EnhancedNodeConfig {
   retry_config?: RetryConfig;
   system_prompt_override?: string;
   output_tags?: string[];
+  require_approval?: boolean;
+  // Start once these predecessors publish the sections ("@publish <name>" ... "@end")
+  start_on_published?: { node_id: string; section: string }[];
+  // Asked of the agent and parsed; an answer that doesn't parse is retried
+  expected_output_format?: 'markdown' | 'json' | 'patch';
+  // JSON Schema the answer must match; implies 'json' when no format is set
+  output_schema?: Record<string, unknown>;
+  // Parse the output as test results into <node_id>.passed_tests, <node_id>.failed_tests, ... variables
+  // and an output tagged 'test_results' beside the node's own
+  test_results?: 'auto' | 'cargo' | 'pytest' | 'jest' | 'junit';
+  // List the files a change impacts in the prompt; the change comes from
+  // diff_node's output, else the project's uncommitted changes
+  impact_scope?: { diff_node?: string; max_depth?: number; max_files?: number };
+  // Reuse the result of an earlier run with the same prompt while the files
+  // matching these globs (relative to the project) are unchanged
+  cache?: { reads: string[] };
+  // Replaces the workflow's guardrails for this node
+  guardrails?: GuardrailConfig;
+}
+
+// Agent output tripping a guardrail waits for approval (approveNode) before
+// it is used; rejecting it fails the node
+export interface GuardrailConfig {
+  // Regexes the output must not match
+  deny_patterns?: string[];
+  // Email addresses, US social security numbers, card numbers
+  detect_pii?: boolean;
+  // rm -rf, mkfs, curl | sh and the like
+  detect_dangerous_commands?: boolean;
+}
+
+export interface GuardrailViolation {
+  kind: 'deny_pattern' | 'pii' | 'dangerous_command';
+  // The deny-pattern, kind of personal data or command
+  rule: string;
+  line: number;
+  // Not given for personal data
+  excerpt: string | null;
+}
+
+// Condition and output aggregation for a stage (a named group of nodes)
+export interface StageConfig {
+  condition_type?: ConditionType;
+  condition_params?: Record<string, unknown>;
+  aggregation?: Record<string, unknown>;
 }
 
 // Enhanced execution request
 export interface EnhancedExecutionRequest {
-  graph: { nodes: unknown[]; edges: unknown[] };
+  graph: { nodes: unknown[]; edges: unknown[]; stages?: GraphStage[] };
   project_id: string;
   input_prompt: string;
   retry_config?: RetryConfig;
   enable_data_flow?: boolean;
   include_original_prompt?: boolean;
   node_configs?: Record<string, EnhancedNodeConfig>;
+  // Run agents in a copy of the project; review with getWorkspaceChanges
+  isolated_workspace?: boolean;
+  stage_configs?: Record<string, StageConfig>;
+  concurrency?: ExecutionConcurrency;
+  model_pins?: ModelPins;
+  // Apply the template's node config defaults to nodes named after its tasks
+  template_id?: string;
+  // Inject failures, delays and truncated answers to test failure handling
+  chaos?: ChaosConfig;
+  auto_cancel_override?: AutoCancelOverride;
+  guardrails?: GuardrailConfig;
+  // Saved workflow whose environment defaults apply
+  workflow_id?: string;
+  // Over the workflow's defaults and the project's .env
+  env?: Record<string, EnvVar>;
+}
+
+// Rates are shares of agent attempts, 0 to 1
+export interface ChaosConfig {
+  failure_rate?: number;
+  delay_rate?: number;
+  max_delay_ms?: number;
+  truncate_rate?: number;
+  // Defaults to one derived from the execution ID; set it to replay a run's faults
+  seed?: number;
+  // Only these nodes; every agent node when empty
+  nodes?: string[];
 }
 
 // Execute workflow with enhanced features
@@ -383,23 +1066,302 @@ export async function getExecutionContext(executionId: string): Promise<Executio
   return invoke('get_execution_context', { executionId });
 }
 
-// Checkpoint summary
-export interface CheckpointSummary {
-  id: string;
-  execution_id: string;
-  workflow_id: string;
-  status: string;
-  progress: number;
-  total_nodes: number;
-  completed_nodes: number;
-  failed_nodes: number;
-  pending_nodes: number;
-  checkpoint_at: string;
-  current_level: number;
-  total_levels: number;
+// An item of a node's activity feed; agent_id is unset for files found by diffing
+export type ActivityEntry = {
+  timestamp: string;
+  agent_id?: string;
+} & (
+  | { kind: 'command'; command: string; description?: string }
+  | { kind: 'file_write'; path: string; tool: string }
+  | { kind: 'file_change'; path: string; change: 'created' | 'modified' | 'deleted' }
+);
+
+// Get the commands a node's agents ran and the files they changed
+export async function getNodeActivity(executionId: string, nodeId: string): Promise<ActivityEntry[]> {
+  return invoke('get_node_activity', { executionId, nodeId });
 }
 
-// List all checkpoints
+// Workflow events as emitted: numbered per execution, starting at 1
+export type JournaledWorkflowEvent = WorkflowEvent & {
+  seq: number;
+  timestamp: string;
+};
+
+export interface EventsSince {
+  events: JournaledWorkflowEvent[];
+  last_seq: number;
+  truncated: boolean;
+  finished: boolean;
+}
+
+// Events after `seq` (all of them without it), to catch up before following live events
+export async function getEventsSince(executionId: string, seq?: number): Promise<EventsSince> {
+  return invoke('get_events_since', { executionId, seq });
+}
+
+// Why a node ran or was skipped
+export interface NodeDecision {
+  node_id: string;
+  should_execute: boolean;
+  reason: string;
+  failed_dependencies: string[];
+  condition: unknown | null;
+  result: ConditionResult | null;
+  decided_at: string;
+}
+
+// Explain the failed dependencies or condition evaluation behind a node running or being skipped
+export async function explainNodeDecision(executionId: string, nodeId: string): Promise<NodeDecision> {
+  return invoke('explain_node_decision', { executionId, nodeId });
+}
+
+export interface SimulatedNode {
+  node_id: string;
+  level: number;
+  stage: string | null;
+  would_run: boolean;
+  reason: string;
+  failed_dependencies: string[];
+  condition: ConditionResult | null;
+  status: NodeExecutionStatus;
+}
+
+export interface ConditionSimulation {
+  nodes: SimulatedNode[];
+  would_run: string[];
+  would_skip: string[];
+}
+
+// Evaluate every node's condition against hypothetical node outcomes and variables, without running anything
+export async function simulateConditions(
+  graph: unknown,
+  nodeStatuses?: Record<string, NodeExecutionStatus>,
+  variables?: Record<string, unknown>,
+  nodeConfigs?: Record<string, EnhancedNodeConfig>,
+  outputs?: Record<string, string>,
+  stageConfigs?: Record<string, StageConfig>
+): Promise<ConditionSimulation> {
+  return invoke('simulate_conditions', { graph, nodeStatuses, variables, nodeConfigs, stageConfigs, outputs });
+}
+
+export interface PromptPreviewRequest {
+  node_id: string;
+  execution_id?: string;
+  graph?: unknown;
+  project_id?: string;
+  input_prompt?: string;
+  sample_variables?: Record<string, unknown>;
+  sample_outputs?: Record<string, string>;
+  node_configs?: Record<string, EnhancedNodeConfig>;
+  template_id?: string;
+  enable_data_flow?: boolean;
+  include_original_prompt?: boolean;
+}
+
+export interface PromptPreview {
+  node_id: string;
+  agent_role: string;
+  model: string | null;
+  system_prompt: string | null;
+  prompt: string;
+  predecessors: string[];
+  placeholders: string[];
+  not_included: string[];
+  tokens: { system_prompt: number; prompt: number; total: number; encoding: TokenEncoding; exact: boolean };
+}
+
+export type TokenEncoding = 'cl100k_base' | 'o200k_base' | 'claude';
+
+export interface TokenCount {
+  tokens: number;
+  encoding: TokenEncoding;
+  // False when estimated because the encoding's merge table isn't installed,
+  // which is always the case for Claude models; show such counts as estimates
+  exact: boolean;
+}
+
+// Tokens a node's attempts used, as recorded in its execution history
+export interface NodeTokens {
+  input: number;
+  output: number;
+  exact: boolean;
+}
+
+// Count tokens with the tokenizer of a model (the CLI's default when omitted)
+export async function countTokens(text: string, model?: string): Promise<TokenCount> {
+  return invoke('count_tokens', { text, model });
+}
+
+// The exact system prompt and task a node's agent would receive, from an execution's outputs or sample data
+export async function previewNodePrompt(request: PromptPreviewRequest): Promise<PromptPreview> {
+  return invoke('preview_node_prompt', { request });
+}
+
+export interface ModelPrice {
+  input_per_million: number;
+  output_per_million: number;
+}
+
+export interface EstimateRequest {
+  node_configs?: Record<string, EnhancedNodeConfig>;
+  template_id?: string;
+  // The saved workflow's pins are used when omitted
+  model_pins?: ModelPins;
+  workflow_id?: string;
+  input_prompt?: string;
+  // US dollars per million tokens, by model name prefix
+  prices?: Record<string, ModelPrice>;
+  confirm_above_usd?: number;
+}
+
+export type EstimateBasis = 'node' | 'role' | 'prompt';
+
+export interface NodeEstimate {
+  node_id: string;
+  agent_role: string;
+  model: string | null;
+  input_tokens: number;
+  output_tokens: number;
+  cost_usd: number;
+  duration_ms: number;
+  basis: EstimateBasis;
+  samples: number;
+}
+
+export interface ExecutionEstimate {
+  nodes: NodeEstimate[];
+  input_tokens: number;
+  output_tokens: number;
+  cost_usd: number;
+  duration_ms: number;
+  requires_confirmation: boolean;
+}
+
+// Forecast a run's cost and duration from past token usage, to show before confirming it
+export async function estimateExecution(graph: unknown, config?: EstimateRequest): Promise<ExecutionEstimate> {
+  return invoke('estimate_execution', { graph, config });
+}
+
+// Execution log levels, least to most verbose
+export type LogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+export interface LogLevels {
+  execution: LogLevel;
+  nodes: Record<string, LogLevel>;
+}
+
+export interface ExecutionLogEntry {
+  timestamp: string;
+  level: LogLevel;
+  node_id?: string;
+  message: string;
+  details?: unknown;
+}
+
+// Change an execution's log level while it runs, or one node's with nodeId; 'debug' is verbose mode
+export async function setExecutionLogLevel(executionId: string, level: LogLevel | 'verbose', nodeId?: string): Promise<LogLevels> {
+  return invoke('set_execution_log_level', { executionId, level, nodeId });
+}
+
+// Get an execution's log, optionally for one node and down to a level
+export async function getExecutionLog(executionId: string, nodeId?: string, level?: LogLevel): Promise<ExecutionLogEntry[]> {
+  return invoke('get_execution_log', { executionId, nodeId, level });
+}
+
+// A file undoing an execution would change; nodes is empty without an activity feed
+export interface RollbackFile {
+  path: string;
+  action: 'restore' | 'delete';
+  nodes: string[];
+}
+
+export interface RollbackPreview {
+  execution_id: string;
+  base_tree: string;
+  files: RollbackFile[];
+  patch: string;
+}
+
+// Show what undoing an execution's file changes would do
+export async function previewRollback(executionId: string): Promise<RollbackPreview> {
+  return invoke('preview_rollback', { executionId });
+}
+
+// Restore the files an execution changed to how they were when it started
+export async function rollbackExecutionChanges(executionId: string): Promise<RollbackPreview> {
+  return invoke('rollback_execution_changes', { executionId });
+}
+
+// Scratch copy of a project used by an isolated execution
+export interface WorkspaceInfo {
+  execution_id: string;
+  project_dir: string;
+  workspace_dir: string;
+  created_at: string;
+}
+
+// conflict: the project's copy changed too; approving overwrites it
+export interface WorkspaceChange {
+  path: string;
+  change: 'created' | 'modified' | 'deleted';
+  conflict: boolean;
+}
+
+export interface WorkspaceReview {
+  workspace: WorkspaceInfo;
+  changes: WorkspaceChange[];
+}
+
+export interface MergeReport {
+  applied: string[];
+  skipped: string[];
+  remaining: number;
+}
+
+// Isolated workspaces still waiting for review
+export async function listIsolatedWorkspaces(): Promise<WorkspaceInfo[]> {
+  return invoke('list_isolated_workspaces');
+}
+
+// Changes an isolated execution made
+export async function getWorkspaceChanges(executionId: string): Promise<WorkspaceReview> {
+  return invoke('get_workspace_changes', { executionId });
+}
+
+// Merge approved changes into the project (all, or only paths)
+// Dependencies flagged by a supply-chain check block the merge unless accepted
+export async function approveWorkspaceChanges(
+  executionId: string,
+  paths?: string[],
+  overwriteConflicts?: boolean,
+  acceptFlaggedDependencies?: boolean
+): Promise<MergeReport> {
+  return invoke('approve_workspace_changes', { executionId, paths, overwriteConflicts, acceptFlaggedDependencies });
+}
+
+// Delete an isolated workspace without merging
+export async function discardWorkspace(executionId: string): Promise<void> {
+  return invoke('discard_workspace', { executionId });
+}
+
+// Checkpoint summary
+export interface CheckpointSummary {
+  id: string;
+  execution_id: string;
+  workflow_id: string;
+  status: string;
+  cancel_reason?: string;
+  progress: number;
+  total_nodes: number;
+  completed_nodes: number;
+  failed_nodes: number;
+  pending_nodes: number;
+  checkpoint_at: string;
+  current_level: number;
+  total_levels: number;
+}
+
+// List all checkpoints
 export async function listCheckpoints(): Promise<CheckpointSummary[]> {
   return invoke('list_checkpoints');
 }
@@ -414,6 +1376,16 @@ export async function cleanupCheckpoints(keepPerExecution?: number): Promise<num
   return invoke('cleanup_checkpoints', { keepPerExecution });
 }
 
+// Export a checkpoint to a file or directory; returns the written path
+export async function exportCheckpoint(checkpointId: string, path: string): Promise<string> {
+  return invoke('export_checkpoint', { checkpointId, path });
+}
+
+// Import an exported checkpoint under fresh IDs
+export async function importCheckpoint(path: string, projectId?: string): Promise<CheckpointSummary> {
+  return invoke('import_checkpoint', { path, projectId });
+}
+
 // Agent role information
 export interface AgentRoleInfo {
   id: string;
@@ -439,6 +1411,33 @@ export async function getAggregationStrategies(): Promise<AggregationStrategyInf
   return invoke('get_aggregation_strategies');
 }
 
+// A predecessor output written by hand; string content is text, anything else JSON
+export interface SampleOutput {
+  node_id: string;
+  agent_role?: string;
+  content: unknown;
+  tags?: string[];
+}
+
+export interface AggregationPreview {
+  selected: string[];
+  output: {
+    data: unknown;
+    sources: { node_id: string; agent_role: string; timestamp: string }[];
+    strategy_used: string;
+  } | null;
+  prompt_context: string | null;
+}
+
+// Run an aggregation strategy on sample outputs without running agents
+export async function previewAggregation(
+  strategy: Record<string, unknown> & { type: string },
+  sampleOutputs: SampleOutput[],
+  transform?: Record<string, unknown> & { type: string }
+): Promise<AggregationPreview> {
+  return invoke('preview_aggregation', { strategy, sampleOutputs, transform });
+}
+
 // Condition type information
 export interface ConditionTypeInfo {
   id: string;
@@ -462,6 +1461,7 @@ export interface TemplateVariable {
   description: string;
   default_value: string | null;
   required: boolean;
+  // 'string' | 'number' | 'boolean' | 'file_path' | 'choice' | 'secret' | 'multi_line_text'
   variable_type: string;
   options: string[] | null;
 }
@@ -485,6 +1485,13 @@ export interface PlannedTask {
   agent_role: string;
   description: string;
   depends_on: string[];
+  // Node config defaults shipped by the template
+  node_config?: {
+    condition?: Record<string, unknown>;
+    retry?: Record<string, unknown>;
+    aggregation?: Record<string, unknown>;
+    output_tags: string[];
+  };
 }
 
 // Template category info
@@ -494,32 +1501,46 @@ export interface TemplateCategoryInfo {
   description: string;
 }
 
-// List all workflow templates
-export async function listWorkflowTemplates(): Promise<WorkflowTemplateInfo[]> {
-  return invoke('list_workflow_templates');
+// List all workflow templates, in the project's locale when given
+export async function listWorkflowTemplates(projectId?: string): Promise<WorkflowTemplateInfo[]> {
+  return invoke('list_workflow_templates', { projectId });
 }
 
 // Get a specific workflow template
-export async function getWorkflowTemplate(templateId: string): Promise<WorkflowTemplateInfo> {
-  return invoke('get_workflow_template', { templateId });
+export async function getWorkflowTemplate(templateId: string, projectId?: string): Promise<WorkflowTemplateInfo> {
+  return invoke('get_workflow_template', { templateId, projectId });
 }
 
 // Search workflow templates
-export async function searchWorkflowTemplates(query: string): Promise<WorkflowTemplateInfo[]> {
-  return invoke('search_workflow_templates', { query });
+export async function searchWorkflowTemplates(query: string, projectId?: string): Promise<WorkflowTemplateInfo[]> {
+  return invoke('search_workflow_templates', { query, projectId });
 }
 
 // Get templates by category
-export async function getTemplatesByCategory(category: string): Promise<WorkflowTemplateInfo[]> {
-  return invoke('get_templates_by_category', { category });
+export async function getTemplatesByCategory(category: string, projectId?: string): Promise<WorkflowTemplateInfo[]> {
+  return invoke('get_templates_by_category', { category, projectId });
 }
 
 // Instantiate a template with variables
+export interface SubstitutionReport {
+  // Placeholder name -> IDs of the tasks it was left in
+  unresolved: Record<string, string[]>;
+  unused_variables: string[];
+  affected_tasks: string[];
+}
+
+export interface TemplateInstance {
+  tasks: PlannedTask[];
+  substitutions: SubstitutionReport;
+}
+
+// With failOnUnresolved, placeholders no variable was given for reject the instantiation
 export async function instantiateTemplate(
   templateId: string,
-  variables: Record<string, string>
-): Promise<PlannedTask[]> {
-  return invoke('instantiate_template', { templateId, variables });
+  variables: Record<string, string>,
+  failOnUnresolved?: boolean
+): Promise<TemplateInstance> {
+  return invoke('instantiate_template', { templateId, variables, failOnUnresolved });
 }
 
 // Get available template categories
@@ -527,6 +1548,48 @@ export async function getTemplateCategories(): Promise<TemplateCategoryInfo[]> {
   return invoke('get_template_categories');
 }
 
+// =============================================================================
+// Node Library Commands
+// =============================================================================
+
+// A reusable node; graph nodes reference it with `preset: id` in their data
+// and override any field by setting it themselves
+export interface NodePreset {
+  id: string;
+  name: string;
+  description: string;
+  agent_role: string;
+  system_prompt: string | null;
+  assigned_task?: string | null;
+  tags?: string[];
+  // Applied where the execution's node config leaves a field unset
+  config?: {
+    retry?: RetryConfig | null;
+    expected_output_format?: 'markdown' | 'json' | 'patch' | null;
+    output_schema?: Record<string, unknown> | null;
+    output_tags?: string[];
+    model?: string | null;
+  };
+  built_in?: boolean;
+}
+
+export async function listNodePresets(query?: string): Promise<NodePreset[]> {
+  return invoke('list_node_presets', { query });
+}
+
+export async function getNodePreset(presetId: string): Promise<NodePreset> {
+  return invoke('get_node_preset', { presetId });
+}
+
+// Built-in presets can't be replaced; save a copy under another ID
+export async function saveNodePreset(preset: NodePreset): Promise<NodePreset> {
+  return invoke('save_node_preset', { preset });
+}
+
+export async function deleteNodePreset(presetId: string): Promise<boolean> {
+  return invoke('delete_node_preset', { presetId });
+}
+
 // =============================================================================
 // Execution History Commands
 // =============================================================================
@@ -545,6 +1608,113 @@ export interface ExecutionRecordSummary {
   completed_nodes: number;
   failed_nodes: number;
   tags: string[];
+  anomalies: Anomaly[];
+  model_substitutions: ModelSubstitution[];
+}
+
+// A pinned model that was unavailable; `used` is null when agents ran on
+// the default model
+export interface ModelSubstitution {
+  role: string;
+  pinned: string;
+  used: string | null;
+  node_ids: string[];
+}
+
+// A way a run departed from earlier runs of its workflow; `baseline` is
+// their median, or for node failures the node's earlier success rate
+export interface Anomaly {
+  kind: 'duration' | 'tokens' | 'node_failure';
+  node_id: string | null;
+  observed: number;
+  baseline: number;
+  message: string;
+}
+
+export interface AnomalyAlert {
+  execution_id: string;
+  workflow_id: string | null;
+  workflow_name: string;
+  anomalies: Anomaly[];
+}
+
+// Fired when a finished execution is flagged as anomalous
+export function onExecutionAnomaly(callback: (alert: AnomalyAlert) => void): Promise<UnlistenFn> {
+  return listen<AnomalyAlert>('execution-anomaly', (event) => callback(event.payload));
+}
+
+// A reviewer's note on one node of a finished execution; 'wrong' marks
+// its output as wrong, and keeps it out of exported datasets
+export type AnnotationSeverity = 'info' | 'warning' | 'wrong';
+
+export interface NodeAnnotation {
+  id: string;
+  node_id: string;
+  author: string;
+  text: string;
+  severity: AnnotationSeverity;
+  created_at: string;
+}
+
+// Without an author, the OS user name is used
+export async function annotateExecutionNode(
+  executionId: string,
+  nodeId: string,
+  text: string,
+  severity: AnnotationSeverity,
+  author?: string
+): Promise<NodeAnnotation> {
+  return invoke('annotate_execution_node', { executionId, nodeId, text, severity, author });
+}
+
+export async function listExecutionAnnotations(executionId: string, nodeId?: string): Promise<NodeAnnotation[]> {
+  return invoke('list_execution_annotations', { executionId, nodeId });
+}
+
+export async function removeExecutionAnnotation(executionId: string, annotationId: string): Promise<boolean> {
+  return invoke('remove_execution_annotation', { executionId, annotationId });
+}
+
+export type Rating = 'up' | 'down';
+
+// Replaces the author's earlier rating of the node; null takes it back
+export async function rateExecutionNode(
+  executionId: string,
+  nodeId: string,
+  rating: Rating | null,
+  author?: string
+): Promise<void> {
+  return invoke('rate_execution_node', { executionId, nodeId, rating, author });
+}
+
+// Runs of a role under one system prompt; the version is a hash of the prompt
+export interface PromptVersionStats {
+  prompt_version: string;
+  runs: number;
+  failures: number;
+  // Runs someone rated or annotated
+  reviewed: number;
+  thumbs_up: number;
+  thumbs_down: number;
+  marked_wrong: number;
+  failure_rate: number;
+  bad_feedback_rate: number | null;
+  first_seen: string;
+  last_seen: string;
+  bad_executions: string[];
+  // Fails or is rated badly more than the role's other versions
+  suspect: boolean;
+}
+
+export interface PromptPerformance {
+  role: string;
+  // Most recently used first
+  versions: PromptVersionStats[];
+  findings: string[];
+}
+
+export async function analyzePromptPerformance(role: string): Promise<PromptPerformance> {
+  return invoke('analyze_prompt_performance', { role });
 }
 
 // History statistics
@@ -578,6 +1748,327 @@ export async function searchExecutionHistory(query: string): Promise<ExecutionRe
   return invoke('search_execution_history', { query });
 }
 
+// What an execution started with, for attributing and reproducing results
+export interface EnvironmentFingerprint {
+  captured_at: string | null;
+  nexus_version: string;
+  platform: string;
+  backends: Record<string, string>;
+  // By node ID, plus 'default' and 'deadline_fast_model'
+  models: Record<string, string>;
+  git_commit: string | null;
+  git_dirty: boolean;
+  role_registry_hash: string | null;
+}
+
+export async function getExecutionEnvironment(executionId: string): Promise<EnvironmentFingerprint> {
+  return invoke('get_execution_environment', { executionId });
+}
+
+export type ShareFormat = 'html' | 'json';
+
+export interface ExecutionShareResponse {
+  execution_id: string;
+  format: ShareFormat;
+  mime_type: string;
+  file_name: string;
+  redactions: number;
+  // Set when written to outputPath, otherwise the bundle is in content
+  path: string | null;
+  content: string | null;
+}
+
+// A redacted bundle of a finished run (graph, timeline, outputs) for people without NEXUS
+export async function exportExecutionShare(
+  executionId: string,
+  format?: ShareFormat,
+  outputPath?: string,
+  redactPatterns?: string[]
+): Promise<ExecutionShareResponse> {
+  return invoke('export_execution_share', { executionId, format, outputPath, redactPatterns });
+}
+
+// Without a project, clears every project's cached node results
+export async function clearNodeResultCache(projectId?: string): Promise<number> {
+  return invoke('clear_node_result_cache', { projectId });
+}
+
+// Passing null removes the workflow's pins
+export async function setWorkflowModelPins(workflowId: string, pins: ModelPins | null): Promise<Workflow> {
+  return invoke('set_workflow_model_pins', { workflowId, pins });
+}
+
+// Redacted values sent back unchanged keep their stored value
+export async function setWorkflowEnv(workflowId: string, env: Record<string, EnvVar>): Promise<Workflow> {
+  return invoke('set_workflow_env', { workflowId, env });
+}
+
+// A rule a saved workflow's runs must keep; max_rate is a share (0-1) of the last `runs` runs
+export type SlaRule =
+  | { type: 'max_duration'; max_ms: number }
+  | { type: 'max_failure_rate'; max_rate: number; runs: number }
+  | { type: 'must_run_daily' };
+
+export interface SlaRuleStatus {
+  rule: SlaRule;
+  passing: boolean;
+  message: string;
+}
+
+export interface SlaStatus {
+  workflow_id: string;
+  passing: boolean;
+  rules: SlaRuleStatus[];
+  checked_at: string;
+}
+
+export interface SlaAlert {
+  workflow_id: string;
+  rule: SlaRule;
+  message: string;
+  raised_at: string;
+}
+
+// An empty list removes the workflow's rules
+export async function setSlaRules(workflowId: string, rules: SlaRule[]): Promise<SlaStatus> {
+  return invoke('set_sla_rules', { workflowId, rules });
+}
+
+export async function getSlaStatus(workflowId: string): Promise<SlaStatus> {
+  return invoke('get_sla_status', { workflowId });
+}
+
+// Fired once when a rule starts failing, and again only after it has passed
+export function onSlaAlert(callback: (alert: SlaAlert) => void): Promise<UnlistenFn> {
+  return listen<SlaAlert>('sla-alert', (event) => callback(event.payload));
+}
+
+export type HealthStatus = 'healthy' | 'warning' | 'failing';
+
+export interface ProjectExecutionSummary {
+  execution_id: string;
+  workflow_id: string | null;
+  workflow_name: string;
+  status: 'pending' | 'running' | 'completed' | 'failed' | 'cancelled';
+  started_at: string;
+  duration_ms: number | null;
+}
+
+export interface FailingWorkflow {
+  workflow_id: string | null;
+  workflow_name: string;
+  consecutive_failures: number;
+  last_failed_at: string;
+  last_error: string | null;
+}
+
+// An execution waiting to start for more than 30 minutes
+export interface StaleRun {
+  execution_id: string;
+  workflow_name: string;
+  waiting_since: string;
+  waited_ms: number;
+}
+
+export interface ProjectHealth {
+  project_id: string;
+  status: HealthStatus;
+  // Newest first
+  latest_executions: ProjectExecutionSummary[];
+  // Completed share of recent finished executions, 0 to 1
+  success_rate: number | null;
+  failing_workflows: FailingWorkflow[];
+  running: number;
+  stale_runs: StaleRun[];
+  open_approvals: ApprovalRequest[];
+  // Null when the project directory couldn't be read
+  disk_usage: { bytes: number; files: number } | null;
+}
+
+export async function getProjectHealth(projectId: string): Promise<ProjectHealth> {
+  return invoke('get_project_health', { projectId });
+}
+
+export interface QuickMatch {
+  kind: 'workflow' | 'template' | 'project' | 'execution';
+  id: string;
+  title: string;
+  subtitle: string | null;
+  score: number;
+  // Character positions of the title the query matched
+  highlights: number[];
+}
+
+// Without a query, favorites and often used items
+export async function quickSearch(query: string, limit?: number): Promise<QuickMatch[]> {
+  return invoke('quick_search', { query, limit });
+}
+
+export type QuickRunResult =
+  | {
+      status: 'started';
+      execution_id: string;
+      workflow_id: string;
+      workflow_name: string;
+      project_id: string;
+    }
+  // Nothing ran: the query matched only loosely, or two workflows tied
+  | { status: 'ambiguous'; candidates: QuickMatch[] };
+
+// Runs the best matching saved workflow, in the most recently used project
+// unless one is given, when the match is sure and untied; otherwise returns
+// the closest workflows to choose from
+export async function quickRun(query: string, inputPrompt: string, projectId?: string): Promise<QuickRunResult> {
+  return invoke('quick_run', { query, projectId, inputPrompt });
+}
+
+// Limits for check gate nodes; unset limits aren't checked, except that
+// lint errors default to none allowed
+export interface GateThresholds {
+  min_coverage?: number | null;
+  max_lint_errors?: number | null;
+  max_lint_warnings?: number | null;
+}
+
+export async function setGateThresholds(projectId: string, thresholds: GateThresholds): Promise<GateThresholds> {
+  return invoke('set_gate_thresholds', { projectId, thresholds });
+}
+
+export async function getGateThresholds(projectId: string): Promise<GateThresholds> {
+  return invoke('get_gate_thresholds', { projectId });
+}
+
+// Safety net under every execution of a project; unset limits don't apply
+export interface AutoCancelPolicy {
+  max_execution_ms?: number | null;
+  // Over all of a node's attempts, whatever its retry config
+  max_node_ms?: number | null;
+}
+
+// Other limits for one execution, refused unless the user confirmed them
+export interface AutoCancelOverride {
+  policy: AutoCancelPolicy;
+  confirmed: boolean;
+  reason?: string;
+}
+
+export async function setAutoCancelPolicy(projectId: string, policy: AutoCancelPolicy): Promise<AutoCancelPolicy> {
+  return invoke('set_auto_cancel_policy', { projectId, policy });
+}
+
+export async function getAutoCancelPolicy(projectId: string): Promise<AutoCancelPolicy> {
+  return invoke('get_auto_cancel_policy', { projectId });
+}
+
+export type AuditAction =
+  | 'auto_cancel_policy_changed'
+  | 'auto_cancel_overridden'
+  | 'execution_auto_cancelled'
+  | 'node_auto_cancelled'
+  | 'network_policy_changed'
+  | 'egress_blocked'
+  | 'flagged_dependencies_accepted';
+
+export interface AuditEntry {
+  id: string;
+  timestamp: string;
+  action: AuditAction;
+  project_id: string | null;
+  execution_id: string | null;
+  node_id: string | null;
+  message: string;
+  details: unknown | null;
+}
+
+// Newest first
+export async function getAuditLog(projectId?: string, limit?: number): Promise<AuditEntry[]> {
+  return invoke('get_audit_log', { projectId, limit });
+}
+
+export type LintRule =
+  | 'implementer_without_tester'
+  | 'deep_node_without_retry'
+  | 'fan_in_without_aggregation'
+  | 'unknown_preset';
+export type LintSeverity = 'info' | 'warning' | 'error';
+
+// Rules in `disabled` are skipped; others use their default severity
+// unless overridden
+export interface LintConfig {
+  disabled?: LintRule[];
+  severities?: Partial<Record<LintRule, LintSeverity>>;
+}
+
+export interface LintFinding {
+  rule: LintRule;
+  severity: LintSeverity;
+  node_id: string;
+  message: string;
+}
+
+// Most severe findings first; uses the project's lint config if given
+export async function lintWorkflow(
+  graph: EnhancedExecutionRequest['graph'],
+  nodeConfigs?: Record<string, EnhancedNodeConfig>,
+  projectId?: string,
+  templateId?: string
+): Promise<LintFinding[]> {
+  return invoke('lint_workflow', { projectId, graph, nodeConfigs, templateId });
+}
+
+export async function setLintConfig(projectId: string, config: LintConfig): Promise<LintConfig> {
+  return invoke('set_lint_config', { projectId, config });
+}
+
+export async function getLintConfig(projectId: string): Promise<LintConfig> {
+  return invoke('get_lint_config', { projectId });
+}
+
+// Hypothetical outcomes for a dry run; unlisted nodes complete
+export interface Scenario {
+  node_statuses?: Record<string, NodeExecutionStatus>;
+  variables?: Record<string, unknown>;
+  outputs?: Record<string, string>;
+}
+
+export interface WorkflowDiagnostics {
+  session_id: string;
+  workflow_id: string;
+  // 0 for the workflow as it was when watching started
+  revision: number;
+  checked_at: string;
+  validation: WorkflowValidationResult;
+  lint: LintFinding[];
+  // null when the graph doesn't parse or has a cycle
+  dry_run: ConditionSimulation | null;
+}
+
+// Re-check a saved workflow on every change; later revisions arrive through onWorkflowDiagnostics
+export async function developWorkflow(
+  workflowId: string,
+  options: {
+    projectId?: string;
+    nodeConfigs?: Record<string, EnhancedNodeConfig>;
+    stageConfigs?: Record<string, StageConfig>;
+    templateId?: string;
+    scenario?: Scenario;
+  } = {}
+): Promise<WorkflowDiagnostics> {
+  return invoke('develop_workflow', { workflowId, ...options });
+}
+
+export async function stopDevelopingWorkflow(workflowId: string): Promise<boolean> {
+  return invoke('stop_developing_workflow', { workflowId });
+}
+
+export function onWorkflowDiagnostics(callback: (diagnostics: WorkflowDiagnostics) => void): Promise<UnlistenFn> {
+  return listen<WorkflowDiagnostics>('workflow-diagnostics', (event) => callback(event.payload));
+}
+
+export async function updateWorkflowGraph(workflowId: string, graph: unknown): Promise<Workflow> {
+  return invoke('update_workflow_graph', { workflowId, graph });
+}
+
 // =============================================================================
 // Resource Management Commands
 // =============================================================================
@@ -594,15 +2085,36 @@ export interface ResourceStats {
   total_rejected: number;
   peak_active: number;
   avg_duration_ms: number | null;
+  active_per_execution: Record<string, number>;
 }
 
+// Requests per minute, and how many can go out at once
+export interface RateLimit {
+  per_minute: number;
+  burst?: number;
+}
+
+// Rate limit of one API key, by its label (a secret's name, or "..." and
+// the key's last four characters)
+export interface CredentialRateLimit extends RateLimit {
+  backend: string;
+  credential: string;
+}
+
+// How agent slots are shared between competing executions
+export type FairShare = 'fifo' | 'round_robin' | 'weighted';
+
 // Resource configuration
 export interface ResourceConfigInfo {
   max_concurrent_agents: number;
   max_queue_size: number;
   rate_limit_per_minute: number | null;
+  rate_limit_burst: number | null;
+  backend_rate_limits: Record<string, RateLimit>;
+  credential_rate_limits: CredentialRateLimit[];
   acquire_timeout_ms: number;
   enable_priority_queue: boolean;
+  fair_share: FairShare;
 }
 
 // Resource availability
@@ -628,6 +2140,57 @@ export async function checkResourceAvailability(): Promise<ResourceAvailability>
   return invoke('check_resource_availability');
 }
 
+// Tokens left in a rate limit; backend is null for the overall one,
+// credential for a whole backend
+export interface RateLimitStatus {
+  backend: string | null;
+  credential: string | null;
+  per_minute: number;
+  burst: number;
+  tokens_remaining: number;
+  next_refill_at: string | null;
+  allowed: number;
+  throttled: number;
+}
+
+// Get the overall, per-backend and per-credential rate limit status
+export async function getRateLimitStatus(): Promise<RateLimitStatus[]> {
+  return invoke('get_rate_limit_status');
+}
+
+export type TaskPriority = 'Low' | 'Normal' | 'High' | 'Critical';
+
+// A task waiting for an agent slot
+export interface QueuedTask {
+  id: string;
+  execution_id: string;
+  node_id: string;
+  agent_role: string;
+  priority: TaskPriority;
+  queued_at: string;
+  estimated_duration_ms: number | null;
+}
+
+export interface QueuedTaskInfo extends QueuedTask {
+  position: number;
+  wait_ms: number;
+}
+
+// List queued tasks, next to run first
+export async function listQueuedTasks(): Promise<QueuedTaskInfo[]> {
+  return invoke('list_queued_tasks');
+}
+
+// Take a task out of the queue
+export async function cancelQueuedTask(taskId: string): Promise<QueuedTask> {
+  return invoke('cancel_queued_task', { taskId });
+}
+
+// Change the priority of a queued task
+export async function reprioritizeQueuedTask(taskId: string, priority: TaskPriority): Promise<QueuedTask> {
+  return invoke('reprioritize_queued_task', { taskId, priority });
+}
+
 // =============================================================================
 // Messaging Commands
 // =============================================================================
@@ -645,6 +2208,15 @@ export interface AgentMessageInfo {
   timestamp: string;
   priority: string;
   read: boolean;
+  topic: string | null;
+}
+
+// Narrows unread messages; unset fields match everything
+export interface MessageFilter {
+  topic?: string;
+  message_type?: string;
+  from_node_id?: string;
+  min_priority?: 'low' | 'normal' | 'high' | 'urgent';
 }
 
 // Get all messages for an execution
@@ -655,9 +2227,235 @@ export async function getExecutionMessages(executionId: string): Promise<AgentMe
 // Get unread messages for an agent
 export async function getUnreadAgentMessages(
   executionId: string,
-  agentId: string
+  agentId: string,
+  filter?: MessageFilter
 ): Promise<AgentMessageInfo[]> {
-  return invoke('get_unread_agent_messages', { executionId, agentId });
+  return invoke('get_unread_agent_messages', { executionId, agentId, filter });
+}
+
+// Send a message to all agents of an execution, or to a topic's subscribers
+export async function broadcastExecutionMessage(
+  executionId: string,
+  text: string,
+  topic?: string
+): Promise<string> {
+  return invoke('broadcast_execution_message', { executionId, text, topic });
+}
+
+// Result of purging an execution's messages
+export interface PurgeMessagesResult {
+  messages_removed: number;
+  checkpoints_updated: number;
+}
+
+// Drop an execution's messages from memory and from its checkpoints
+export async function purgeExecutionMessages(executionId: string): Promise<PurgeMessagesResult> {
+  return invoke('purge_execution_messages', { executionId });
+}
+
+// =============================================================================
+// Registry Sync Commands
+// =============================================================================
+
+export type SyncItemKind = 'template' | 'role' | 'prompt';
+
+// Shared git repository or HTTP registry
+export type RegistryBackend =
+  | { type: 'git'; url: string; branch?: string; path?: string }
+  | { type: 'http'; url: string; token?: string };
+
+export interface SyncConfig {
+  backend: RegistryBackend;
+  resolution?: 'manual' | 'prefer_local' | 'prefer_remote' | 'newest';
+  author?: string;
+}
+
+// A custom template, role definition or prompt version
+export interface SyncItem {
+  kind: SyncItemKind;
+  id: string;
+  content: unknown;
+  updated_at: string;
+  updated_by?: string;
+}
+
+// An item changed both locally and in the registry
+export interface SyncConflict {
+  key: string;
+  local: SyncItem;
+  remote: SyncItem;
+  detected_at: string;
+}
+
+export interface SyncReport {
+  pushed: string[];
+  pulled: string[];
+  conflicts: SyncConflict[];
+  synced_at: string;
+}
+
+export interface SyncStatus {
+  configured: boolean;
+  backend?: string;
+  last_sync_at?: string;
+  last_error?: string;
+  local_items: number;
+  pending_push: number;
+  conflicts: SyncConflict[];
+}
+
+// Set the shared registry
+export async function configureSync(config: SyncConfig): Promise<void> {
+  return invoke('configure_sync', { config });
+}
+
+// Get the sync configuration
+export async function getSyncConfig(): Promise<SyncConfig | null> {
+  return invoke('get_sync_config');
+}
+
+// Add or update an item in the local library
+export async function saveLibraryItem(kind: SyncItemKind, id: string, content: unknown): Promise<SyncItem> {
+  return invoke('save_library_item', { kind, id, content });
+}
+
+// List items in the local library
+export async function listLibraryItems(kind?: SyncItemKind): Promise<SyncItem[]> {
+  return invoke('list_library_items', { kind });
+}
+
+// Push and pull library changes with the registry
+export async function syncLibrary(): Promise<SyncReport> {
+  return invoke('sync_library');
+}
+
+// Get sync status and open conflicts
+export async function syncStatus(): Promise<SyncStatus> {
+  return invoke('sync_status');
+}
+
+// Resolve a conflict by keeping the local or the registry copy
+export async function resolveSyncConflict(key: string, keepLocal: boolean): Promise<void> {
+  return invoke('resolve_sync_conflict', { key, keepLocal });
+}
+
+// =============================================================================
+// Settings Commands
+// =============================================================================
+
+// Application settings; stored secrets come back as '********'
+export interface Settings {
+  api: { enabled: boolean; port: number };
+  resources: {
+    max_concurrent_agents: number;
+    max_agents_per_role: Record<string, number>;
+    max_queue_size: number;
+    rate_limit_per_minute?: number;
+    rate_limit_burst?: number;
+    backend_rate_limits: Record<string, RateLimit>;
+    credential_rate_limits: CredentialRateLimit[];
+    acquire_timeout_ms: number;
+    enable_priority_queue: boolean;
+    queue_policy: 'priority' | 'shortest_job_first';
+    fair_share: FairShare;
+  };
+  backends: {
+    anthropic_api_key?: string;
+    mcp_server_url?: string;
+    github_token?: string;
+    jira_email?: string;
+    jira_api_token?: string;
+    // Named secrets for template variables of type 'secret'
+    secrets?: Record<string, string>;
+    // Models the backend offers; pinned models are checked against them,
+    // and workflows that pin models don't start while it is empty
+    available_models?: string[];
+  };
+  retention: {
+    history_max_records: number;
+    checkpoints_per_execution: number;
+    message_ttl_hours?: number;
+  };
+  notifications: {
+    desktop: boolean;
+    on_completion: boolean;
+    on_failure: boolean;
+    email_recipients: string[];
+  };
+  projects: {
+    base_directory?: string;
+  };
+}
+
+type DeepPartial<T> = { [K in keyof T]?: T[K] extends object ? DeepPartial<T[K]> | null : T[K] | null };
+
+// Get the current settings
+export async function getSettings(): Promise<Settings> {
+  return invoke('get_settings');
+}
+
+// Update some settings; null clears a value. Emits 'settings-changed', and
+// restarts the API server when its settings change.
+export async function updateSettings(patch: DeepPartial<Settings>): Promise<Settings> {
+  return invoke('update_settings', { patch });
+}
+
+export type ItemKind = 'workflow' | 'template' | 'project';
+
+export interface RecentItem {
+  kind: ItemKind;
+  id: string;
+  last_used_at: string;
+  use_count: number;
+}
+
+export interface Favorite {
+  kind: ItemKind;
+  id: string;
+  pinned_at: string;
+}
+
+export interface SuggestedItem {
+  kind: ItemKind;
+  id: string;
+  favorite: boolean;
+  use_count: number;
+  last_used_at: string | null;
+}
+
+// Most recent first
+export async function listRecentItems(kind?: ItemKind, limit?: number): Promise<RecentItem[]> {
+  return invoke('list_recent_items', { kind, limit });
+}
+
+// Running a workflow or instantiating a template records it already
+export async function recordRecentItem(kind: ItemKind, id: string): Promise<void> {
+  return invoke('record_recent_item', { kind, id });
+}
+
+export async function clearRecentItems(kind?: ItemKind): Promise<void> {
+  return invoke('clear_recent_items', { kind });
+}
+
+export async function listFavorites(kind?: ItemKind): Promise<Favorite[]> {
+  return invoke('list_favorites', { kind });
+}
+
+export async function pinFavorite(kind: ItemKind, id: string): Promise<boolean> {
+  return invoke('pin_favorite', { kind, id });
+}
+
+export async function unpinFavorite(kind: ItemKind, id: string): Promise<boolean> {
+  return invoke('unpin_favorite', { kind, id });
+}
+
+export async function reorderFavorites(kind: ItemKind, ids: string[]): Promise<Favorite[]> {
+  return invoke('reorder_favorites', { kind, ids });
+}
+
+// Favorites first, then frequently and recently used items
+export async function getSuggestedItems(kind: ItemKind, limit?: number): Promise<SuggestedItem[]> {
+  return invoke('get_suggested_items', { kind, limit });
 }
 
 // =============================================================================
@@ -39,7 +39,7 @@ export const useAgentStore = create<AgentState>((set) => ({
   fetchAgents: async () => {
     set({ isLoading: true, error: null });
     try {
-      const agents = await tauri.listAgents();
+      const { agents } = await tauri.listAgents();
       const agentMap = new Map(agents.map((a) => [a.id, a]));
       set({ agents: agentMap, isLoading: false });
     } catch (error) {
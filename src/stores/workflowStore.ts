@@ -65,7 +65,7 @@ interface WorkflowState {
   setEdges: (edges: WorkflowEdge[]) => void;
   addNode: (node: WorkflowNode) => void;
   removeNode: (nodeId: string) => void;
-  updateNodeStatus: (nodeId: string, status: 'pending' | 'running' | 'completed' | 'failed') => void;
+  updateNodeStatus: (nodeId: string, status: NodeExecutionStatus) => void;
   addEdge: (edge: WorkflowEdge) => void;
   removeEdge: (edgeId: string) => void;
   clearCanvas: () => void;
@@ -381,7 +381,7 @@ export const useWorkflowStore = create<WorkflowState>((set, get) => ({
               ...n,
               data: {
                 ...n.data,
-                status: status as 'pending' | 'running' | 'completed' | 'failed',
+                status,
                 progress,
                 agentId,
               },
@@ -499,6 +499,7 @@ export const useWorkflowStore = create<WorkflowState>((set, get) => ({
       const errorResult: WorkflowValidationResult = {
         is_valid: false,
         has_cycle: false,
+        cycle: null,
         disconnected_nodes: [],
         root_nodes: [],
         leaf_nodes: [],
@@ -506,6 +507,7 @@ export const useWorkflowStore = create<WorkflowState>((set, get) => ({
         total_edges: edges.length,
         execution_levels: null,
         error: String(error),
+        schema_errors: [],
       };
       set({ validationResult: errorResult });
       return errorResult;
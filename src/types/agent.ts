@@ -10,6 +10,12 @@ export interface Agent {
   progress: number;
   pid?: number;
   createdAt?: string;
+  started_at?: string;
+  finished_at?: string;
+  runtime_secs?: number;
+  last_output_at?: string;
+  execution_id?: string;
+  usage?: { cpu_percent: number; memory_kb: number };
 }
 
 export type AgentRole =
@@ -13,4 +13,6 @@ export interface DatabaseStatus {
   connected: boolean;
   poolSize?: number;
   idleConnections?: number;
+  // Writes queued while the connection was down, replayed once it is back
+  pending_writes: number;
 }
@@ -6,6 +6,10 @@ export interface Project {
   description?: string;
   status: ProjectStatus;
   workingDirectory: string;
+  /** Locale such as `de` or `pt-BR` that agents answer in and templates are listed in */
+  locale?: string;
+  /** Main languages, most used first; set for imported repositories */
+  languages: string[];
   createdAt: string;
   updatedAt: string;
 }
@@ -17,12 +21,15 @@ export interface CreateProjectRequest {
   working_directory?: string;
   /** Whether to initialize the project structure (README, .gitignore, etc.). Defaults to true. */
   init_structure?: boolean;
+  locale?: string;
 }
 
 export interface UpdateProjectRequest {
   name?: string;
   description?: string;
   status?: ProjectStatus;
+  /** An empty string clears the locale */
+  locale?: string;
 }
 
 export const PROJECT_STATUS_COLORS: Record<ProjectStatus, string> = {
@@ -1,14 +1,17 @@
 import type { Node, Edge } from '@xyflow/react';
+import type { NodeExecutionStatus } from '../services/tauri';
 
 export interface WorkflowNode extends Node {
   data: {
     label: string;
     agentRole: string;
     agentId?: string;
-    status?: 'pending' | 'running' | 'completed' | 'failed';
+    status?: NodeExecutionStatus;
     progress?: number;
     systemPrompt?: string;
     assignedTask?: string;
+    /** Node library preset supplying the role, prompts and task left unset here */
+    preset?: string;
   };
 }
 
@@ -25,6 +28,29 @@ export interface WorkflowGraph {
   edges: WorkflowEdge[];
 }
 
+// Executions in the same group run one at a time, across workflows
+export interface ExecutionConcurrency {
+  group: string;
+  policy?: 'queue' | 'cancel_in_progress' | 'reject';
+}
+
+// Model per agent role; an unavailable model fails the run, is replaced by
+// its first available alternative, or leaves agents on the default model
+export interface ModelPins {
+  roles: Record<string, string>;
+  on_unavailable?:
+    | { type: 'fail' }
+    | { type: 'substitute'; alternatives: Record<string, string[]> }
+    | { type: 'use_default' };
+}
+
+// Values may hold ${NAME} and {{secret:NAME}} references; secret ones are
+// redacted from logs and history, and come back from the app redacted
+export interface EnvVar {
+  value: string;
+  secret?: boolean;
+}
+
 export interface Workflow {
   id: string;
   name: string;
@@ -32,6 +58,10 @@ export interface Workflow {
   graph: WorkflowGraph;
   isTemplate: boolean;
   createdAt: string;
+  concurrency?: ExecutionConcurrency;
+  model_pins?: ModelPins | null;
+  // Environment defaults of its runs
+  env: Record<string, EnvVar>;
 }
 
 export interface CreateWorkflowRequest {
@@ -39,6 +69,9 @@ export interface CreateWorkflowRequest {
   description?: string;
   graph: WorkflowGraph;
   is_template?: boolean;
+  concurrency?: ExecutionConcurrency;
+  model_pins?: ModelPins | null;
+  env?: Record<string, EnvVar>;
 }
 
 export interface ExecuteWorkflowRequest {